@@ -1,22 +1,28 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use email::{
     account::config::{passwd::PasswordConfig, AccountConfig},
     backend::{
-        context::BackendContextBuilder, feature::BackendFeature, macros::BackendContext,
-        mapper::SomeBackendContextBuilderMapper, Backend, BackendBuilder,
+        context::BackendContextBuilder, dynamic::DynBackend, feature::BackendFeature,
+        macros::BackendContext, mapper::SomeBackendContextBuilderMapper, Backend, BackendBuilder,
     },
+    envelope::list::ListEnvelopes,
     folder::{list::ListFolders, Folder, FolderKind},
     imap::{
         config::{ImapAuthConfig, ImapConfig},
         ImapContext, ImapContextBuilder,
     },
-    smtp::{SmtpContextBuilder, SmtpContextSync},
+    message::send::SendMessage,
+    smtp::{
+        config::{SmtpAuthConfig, SmtpConfig},
+        SmtpContextBuilder, SmtpContextSync,
+    },
     tls::Encryption,
     AnyResult,
 };
 use email_testing_server::with_email_testing_server;
+use mail_builder::MessageBuilder;
 use secret::Secret;
 
 #[test_log::test(tokio::test(flavor = "multi_thread"))]
@@ -98,7 +104,21 @@ async fn build(self) -> AnyResult<Self::Context> {
             )),
             smtp: None,
         };
-        let backend_builder = BackendBuilder::new(account_config.clone(), ctx_builder);
+        let backend_builder = BackendBuilder::new(account_config.clone(), ctx_builder.clone());
+        let backend: Backend<DynamicContext> = backend_builder.build().await.unwrap();
+        let folders = backend.list_folders().await.unwrap();
+
+        assert!(folders.contains(&Folder {
+            kind: Some(FolderKind::Inbox),
+            name: "INBOX".into(),
+            desc: "".into()
+        }));
+
+        // 6. wire all features of a subcontext at once
+
+        let backend_builder = BackendBuilder::new(account_config.clone(), ctx_builder)
+            .without_features()
+            .with_features_from(|ctx_builder| &ctx_builder.imap);
         let backend: Backend<DynamicContext> = backend_builder.build().await.unwrap();
         let folders = backend.list_folders().await.unwrap();
 
@@ -110,3 +130,56 @@ async fn build(self) -> AnyResult<Self::Context> {
     })
     .await
 }
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_dynamic_backend_composition() {
+    with_email_testing_server(|ports| async move {
+        let account_config = Arc::new(AccountConfig::default());
+
+        let imap_config = Arc::new(ImapConfig {
+            host: "localhost".into(),
+            port: ports.imap,
+            encryption: Some(Encryption::None),
+            login: "bob".into(),
+            auth: ImapAuthConfig::Password(PasswordConfig(Secret::new_raw("password"))),
+            ..Default::default()
+        });
+
+        let smtp_config = Arc::new(SmtpConfig {
+            host: "localhost".into(),
+            port: ports.smtp,
+            encryption: Some(Encryption::None),
+            login: "alice".into(),
+            auth: SmtpAuthConfig::Password(PasswordConfig(Secret::new_raw("password"))),
+        });
+
+        // subcontexts are chosen at runtime, without defining a
+        // custom context
+
+        let backend: DynBackend = BackendBuilder::new_dyn(account_config.clone())
+            .with_context_dyn(ImapContextBuilder::new(account_config.clone(), imap_config))
+            .with_context_dyn(SmtpContextBuilder::new(account_config.clone(), smtp_config))
+            .build()
+            .await
+            .unwrap();
+
+        let raw_msg = MessageBuilder::new()
+            .from("alice@localhost")
+            .to("bob@localhost")
+            .subject("Dynamic message!")
+            .text_body("Dynamic message!")
+            .write_to_vec()
+            .unwrap();
+        backend.send_message(&raw_msg).await.unwrap();
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        let envelopes = backend
+            .list_envelopes("INBOX", Default::default())
+            .await
+            .unwrap();
+        assert_eq!(1, envelopes.len());
+        assert_eq!("Dynamic message!", envelopes.first().unwrap().subject);
+    })
+    .await
+}
@@ -5,7 +5,7 @@
     account::config::AccountConfig,
     backend::BackendBuilder,
     envelope::{list::ListEnvelopes, Id},
-    flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags, Flag},
+    flag::{add::AddFlags, list::ListFlags, remove::RemoveFlags, set::SetFlags, Flag},
     folder::{
         add::AddFolder, config::FolderConfig, delete::DeleteFolder, expunge::ExpungeFolder,
         list::ListFolders, Folder, FolderKind, Folders,
@@ -39,6 +39,9 @@ async fn test_maildir_features() {
     let mdir_config = Arc::new(MaildirConfig {
         root_dir: tmp_dir.clone(),
         maildirpp: false,
+        layout: None,
+        uid_db: false,
+        envelopes: Default::default(),
     });
 
     let mdir_ctx = MaildirContextBuilder::new(account_config.clone(), mdir_config.clone());
@@ -224,6 +227,18 @@ async fn test_maildir_features() {
     assert!(!envelope.flags.contains(&Flag::Flagged));
     assert!(!envelope.flags.contains(&Flag::Answered));
 
+    // check that a keyword added to the message is listed
+    let keyword = Flag::custom("$Forwarded");
+    mdir.add_flag("INBOX", &Id::single(&envelope.id), keyword.clone())
+        .await
+        .unwrap();
+    let flags = mdir.list_flags("INBOX").await.unwrap();
+    assert!(flags.contains(&Flag::Seen));
+    assert!(flags.contains(&keyword));
+    mdir.remove_flag("INBOX", &Id::single(&envelope.id), keyword)
+        .await
+        .unwrap();
+
     // check that the message can be copied
     mdir.copy_messages("INBOX", "subdir", &Id::single(&envelope.id))
         .await
@@ -108,6 +108,9 @@ async fn test_search_emails_query() {
     let mdir_config = Arc::new(MaildirConfig {
         root_dir: tmp.join("maildir"),
         maildirpp: false,
+        layout: None,
+        uid_db: false,
+        envelopes: Default::default(),
     });
 
     let mdir_ctx = MaildirContextBuilder::new(account_config.clone(), mdir_config.clone());
@@ -267,6 +270,7 @@ async fn test_query(
                 page_size: 0,
                 page: 0,
                 query: Some(query),
+                preview: None,
             },
         )
         .await
@@ -39,6 +39,9 @@ async fn test_sync() {
     let left_config = Arc::new(MaildirConfig {
         root_dir: tmp.join("left"),
         maildirpp: true,
+        layout: None,
+        uid_db: false,
+        envelopes: Default::default(),
     });
 
     let left_account_config = Arc::new(AccountConfig {
@@ -64,6 +67,9 @@ async fn test_sync() {
     let right_config = Arc::new(MaildirConfig {
         root_dir: tmp.join("right"),
         maildirpp: false,
+        layout: None,
+        uid_db: false,
+        envelopes: Default::default(),
     });
 
     let right_account_config = Arc::new(AccountConfig {
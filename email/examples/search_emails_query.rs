@@ -64,6 +64,7 @@ pub async fn main() {
                     page: 1,
                     page_size: 10,
                     query: Some(query),
+                    preview: None,
                 },
             )
             .await
@@ -8,9 +8,13 @@
 pub mod passwd;
 #[cfg(feature = "pgp")]
 pub mod pgp;
+#[cfg(feature = "keyring")]
+mod rename;
+#[cfg(feature = "keyring")]
+pub mod rotate;
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     env,
     ffi::OsStr,
     fs, io,
@@ -26,17 +30,20 @@
 #[cfg(feature = "notify")]
 use notify_rust::Notification;
 use process::Command;
+use secret::Secret;
 use shellexpand_utils::{shellexpand_path, shellexpand_str, try_shellexpand_path};
 use tracing::debug;
 
 #[cfg(feature = "pgp")]
 use self::pgp::PgpConfig;
+use super::runtime::RuntimeDir;
 #[cfg(feature = "sync")]
 use super::sync::config::SyncConfig;
 #[doc(inline)]
 pub use super::{Error, Result};
 use crate::{
     date::from_mail_parser_to_chrono_datetime,
+    doctor::{DoctorReport, Problem},
     email::config::EmailTextPlainFormat,
     envelope::{config::EnvelopeConfig, Envelope},
     flag::config::FlagConfig,
@@ -103,6 +110,14 @@ pub struct AccountConfig {
     /// (usually `/tmp`).
     pub downloads_dir: Option<PathBuf>,
 
+    /// The runtime directory.
+    ///
+    /// It holds short-lived files like drafts or lock files. Each account uses its own sub-directory, only
+    /// accessible by its owner. Defaults to
+    /// `$XDG_RUNTIME_DIR/pimalaya/email`, or to a user-specific
+    /// directory inside the system temporary directory.
+    pub runtime_dir: Option<PathBuf>,
+
     /// The folder configuration.
     pub folder: Option<FolderConfig>,
 
@@ -163,6 +178,27 @@ pub fn get_downloads_dir(&self) -> PathBuf {
             .unwrap_or_else(env::temp_dir)
     }
 
+    /// Get the global runtime directory, shared by all accounts.
+    ///
+    /// The directory is created if it does not exist yet.
+    pub fn get_global_runtime_dir(&self) -> Result<RuntimeDir> {
+        let dir = self.runtime_dir.as_ref().map(shellexpand_path);
+        RuntimeDir::global(dir.as_deref())
+    }
+
+    /// Get the runtime directory of the account.
+    ///
+    /// The directory is created if it does not exist yet.
+    pub fn get_runtime_dir(&self) -> Result<RuntimeDir> {
+        let dir = self.runtime_dir.as_ref().map(shellexpand_path);
+        RuntimeDir::account(dir.as_deref(), &self.name)
+    }
+
+    /// Get the local draft file path of the account.
+    pub fn get_local_draft_path(&self) -> Result<PathBuf> {
+        Ok(self.get_runtime_dir()?.join("draft.eml"))
+    }
+
     /// Build the downloadable version of the given path.
     ///
     /// The aim of this helper is to build a safe download path for a
@@ -195,6 +231,85 @@ pub fn is_sync_enabled(&self) -> bool {
             .unwrap_or_default()
     }
 
+    /// Renames the account, then re-keys the state derived from its
+    /// name.
+    ///
+    /// The keyring entries of the given secrets derived from the
+    /// account name are moved to entries derived from the new name,
+    /// and the secrets are updated accordingly (see
+    /// `ImapAuthConfig::account_secrets_mut` and
+    /// `SmtpAuthConfig::account_secrets_mut`). When no custom
+    /// synchronization directory is defined, the default one (derived
+    /// from the account name) is moved as well.
+    ///
+    /// The renaming fails without any change if one of the new
+    /// keyring entries or the new directory already exists. If
+    /// moving the directory fails, the new keyring entries are
+    /// removed and the old ones are kept. Synchronization caches and
+    /// lock files are derived from backend configurations rather than
+    /// account names, so they are not affected.
+    pub async fn rename<'a>(
+        &mut self,
+        new_name: impl ToString,
+        #[cfg_attr(not(feature = "keyring"), allow(unused_variables))] secrets: impl IntoIterator<
+            Item = (&'static str, &'a mut Secret),
+        >,
+    ) -> Result<()> {
+        let new_name = new_name.to_string();
+
+        #[cfg(feature = "sync")]
+        let sync_dirs = self.find_sync_dirs_to_rename(&new_name)?;
+
+        #[cfg(feature = "keyring")]
+        let keyring = rename::KeyringRename::copy(&self.name, &new_name, secrets).await?;
+
+        #[cfg(feature = "sync")]
+        if let Some((old_dir, new_dir)) = sync_dirs {
+            debug!(?old_dir, ?new_dir, "moving sync directory");
+
+            if let Err(err) = fs::rename(&old_dir, &new_dir) {
+                #[cfg(feature = "keyring")]
+                keyring.rollback().await;
+                return Err(Error::RenameSyncDirError(err, old_dir, new_dir));
+            }
+        }
+
+        #[cfg(feature = "keyring")]
+        keyring.commit().await;
+
+        self.name = new_name;
+
+        Ok(())
+    }
+
+    /// Returns the default synchronization directory of the account
+    /// and the one derived from the given new name, if the former
+    /// needs to be moved.
+    #[cfg(feature = "sync")]
+    fn find_sync_dirs_to_rename(&self, new_name: &str) -> Result<Option<(PathBuf, PathBuf)>> {
+        if self.sync.as_ref().and_then(|c| c.dir.as_ref()).is_some() {
+            return Ok(None);
+        }
+
+        let Some(dir) = data_dir() else {
+            return Ok(None);
+        };
+
+        let dir = dir.join("pimalaya").join("email").join("sync");
+        let old_dir = dir.join(&self.name);
+        let new_dir = dir.join(new_name);
+
+        if !old_dir.is_dir() || old_dir == new_dir {
+            return Ok(None);
+        }
+
+        if new_dir.exists() {
+            return Err(Error::RenameSyncDirAlreadyExistsError(new_dir));
+        }
+
+        Ok(Some((old_dir, new_dir)))
+    }
+
     /// Return `true` if the synchronization directory already exists.
     #[cfg(feature = "sync")]
     pub fn does_sync_dir_exist(&self) -> bool {
@@ -212,6 +327,108 @@ pub fn does_sync_dir_exist(&self) -> bool {
         }
     }
 
+    /// Validate the account configuration.
+    ///
+    /// Checks the account identity, the existence of configured
+    /// directories, the sanity of folder aliases and the
+    /// availability of PGP keys. Backend configurations are checked
+    /// separately, using their own `check_up` function.
+    ///
+    /// Problems are collected rather than returned as errors, see
+    /// [`DoctorReport`].
+    pub async fn validate(&self) -> DoctorReport {
+        let mut report = DoctorReport::new();
+
+        if self.name.trim().is_empty() {
+            report.push(Problem::error("account", "account name is empty"));
+        }
+
+        if !self.email.contains('@') {
+            let msg = format!("invalid email address {:?}", self.email);
+            report.push(Problem::error("account.email", msg));
+        }
+
+        if let Some(dir) = self.downloads_dir.as_ref() {
+            let dir = shellexpand_path(dir);
+            if !dir.is_dir() {
+                let msg = format!("downloads directory {} does not exist", dir.display());
+                report.push(Problem::warning("account.downloads-dir", msg));
+            }
+        }
+
+        report.merge(self.validate_folder_aliases());
+
+        #[cfg(feature = "sync")]
+        if self.is_sync_enabled() && !self.does_sync_dir_exist() {
+            let msg = "synchronization directory does not exist yet, it will be created";
+            report.push(Problem::info("sync.dir", msg));
+        }
+
+        #[cfg(feature = "pgp")]
+        if let Some(pgp) = self.pgp.as_ref() {
+            report.merge(pgp.check_up(&self.email).await);
+        }
+
+        report
+    }
+
+    /// Validate folder aliases.
+    ///
+    /// Aliases should not be empty, folder kinds should not be
+    /// aliased twice (like `draft` and `drafts`) and different
+    /// folders should not share the same alias, otherwise the folder
+    /// kind of an alias is ambiguous.
+    fn validate_folder_aliases(&self) -> DoctorReport {
+        let mut report = DoctorReport::new();
+
+        let Some(aliases) = self.get_folder_aliases() else {
+            return report;
+        };
+
+        let mut kinds: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+        let mut folders: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+
+        for (name, alias) in aliases {
+            let alias = shellexpand_str(alias);
+
+            if alias.trim().is_empty() {
+                let msg = format!("alias of folder {name} is empty");
+                report.push(Problem::error("folder.aliases", msg));
+                continue;
+            }
+
+            let kind = FolderKind::from(name);
+            if !kind.is_user_defined() {
+                kinds
+                    .entry(kind.to_string())
+                    .or_default()
+                    .push(name.as_str());
+            }
+
+            folders.entry(alias).or_default().push(name.as_str());
+        }
+
+        for (kind, mut names) in kinds {
+            if names.len() > 1 {
+                names.sort();
+                let names = names.join(", ");
+                let msg = format!("folder kind {kind} is aliased multiple times ({names})");
+                report.push(Problem::warning("folder.aliases", msg));
+            }
+        }
+
+        for (alias, mut names) in folders {
+            if names.len() > 1 {
+                names.sort();
+                let names = names.join(", ");
+                let msg = format!("folders {names} share the same alias {alias}");
+                report.push(Problem::warning("folder.aliases", msg));
+            }
+        }
+
+        report
+    }
+
     /// Execute the envelope received hook.
     #[cfg(feature = "watch")]
     pub async fn exec_received_envelope_hook(&self, envelope: &Envelope) {
@@ -246,6 +463,7 @@ pub async fn exec_envelope_hook(&self, hook: &WatchHook, envelope: &Envelope) {
         let sender_name = envelope.from.name.as_deref().unwrap_or("unknown");
         let recipient = envelope.to.name.as_deref().unwrap_or(&envelope.to.addr);
         let recipient_name = envelope.to.name.as_deref().unwrap_or("unknown");
+        let priority = envelope.priority.to_string();
 
         if let Some(cmd) = hook.cmd.as_ref() {
             let res = cmd
@@ -258,6 +476,7 @@ pub async fn exec_envelope_hook(&self, hook: &WatchHook, envelope: &Envelope) {
                 .replace("{recipient}", recipient)
                 .replace("{recipient.name}", recipient_name)
                 .replace("{recipient.address}", &envelope.to.addr)
+                .replace("{priority}", &priority)
                 .run()
                 .await;
 
@@ -277,6 +496,7 @@ pub async fn exec_envelope_hook(&self, hook: &WatchHook, envelope: &Envelope) {
                 .replace("{recipient}", recipient)
                 .replace("{recipient.name}", recipient_name)
                 .replace("{recipient.address}", &envelope.to.addr)
+                .replace("{priority}", &priority)
         };
 
         #[cfg(all(feature = "notify", target_os = "linux"))]
@@ -493,6 +713,17 @@ pub fn should_save_copy_sent_message(&self) -> bool {
             .unwrap_or_default()
     }
 
+    /// Return `true` if outgoing encrypted messages should also be
+    /// encrypted to the sender's own key.
+    #[cfg(feature = "pgp")]
+    pub fn should_pgp_encrypt_to_self(&self) -> bool {
+        self.message
+            .as_ref()
+            .and_then(|c| c.send.as_ref())
+            .and_then(|c| c.pgp_encrypt_to_self)
+            .unwrap_or_default()
+    }
+
     /// Generate a template interpreter with prefilled options from
     /// the current user account configuration.
     pub fn generate_tpl_interpreter(&self) -> MimeInterpreterBuilder {
@@ -657,6 +888,33 @@ fn from(config: &'a AccountConfig) -> Self {
     }
 }
 
+/// Returns the keyring-based secret held by the given secret, if
+/// any.
+///
+/// Keyring-based secrets wrapped by cached secrets are returned as
+/// well.
+#[cfg(feature = "keyring")]
+pub(crate) fn find_keyring_secret(secret: &Secret) -> Option<&Secret> {
+    match secret {
+        Secret::Keyring(_) => Some(secret),
+        Secret::Cached(cached) => find_keyring_secret(&cached.secret),
+        _ => None,
+    }
+}
+
+/// Mutable version of [`find_keyring_secret`].
+#[cfg(feature = "keyring")]
+pub(crate) fn find_keyring_secret_mut(secret: &mut Secret) -> Option<&mut Secret> {
+    if matches!(secret, Secret::Keyring(_)) {
+        return Some(secret);
+    }
+
+    match secret {
+        Secret::Cached(cached) => find_keyring_secret_mut(&mut cached.secret),
+        _ => None,
+    }
+}
+
 /// Rename duplicated file by adding a auto-incremented counter
 /// suffix.
 ///
@@ -729,4 +987,55 @@ fn rename_file_if_duplicate() {
             Ok(path) if path == PathBuf::from("downloads/file.ext_5.ext2")
         ));
     }
+
+    #[test]
+    fn validate_folder_aliases() {
+        use crate::{
+            doctor::{Problem, Severity},
+            folder::config::FolderConfig,
+        };
+
+        let config = |aliases: &[(&str, &str)]| super::AccountConfig {
+            folder: Some(FolderConfig {
+                aliases: Some(
+                    aliases
+                        .iter()
+                        .map(|(name, alias)| (name.to_string(), alias.to_string()))
+                        .collect(),
+                ),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        // when aliases are sane
+        let report = config(&[("inbox", "INBOX"), ("sent", "Sent")]).validate_folder_aliases();
+        assert!(report.is_empty());
+
+        // when an alias is empty
+        let report = config(&[("trash", " ")]).validate_folder_aliases();
+        assert_eq!(report.severity(), Some(Severity::Error));
+
+        // when a folder kind is aliased twice
+        let report =
+            config(&[("draft", "Drafts"), ("drafts", "Brouillons")]).validate_folder_aliases();
+        assert_eq!(
+            report.problems(),
+            [Problem::warning(
+                "folder.aliases",
+                "folder kind Drafts is aliased multiple times (draft, drafts)"
+            )]
+        );
+
+        // when folders share the same alias
+        let report =
+            config(&[("sent", "Archives"), ("trash", "Archives")]).validate_folder_aliases();
+        assert_eq!(
+            report.problems(),
+            [Problem::warning(
+                "folder.aliases",
+                "folders sent, trash share the same alias Archives"
+            )]
+        );
+    }
 }
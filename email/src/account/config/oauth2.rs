@@ -3,12 +3,21 @@
 //! This module contains everything related to OAuth 2.0
 //! configuration.
 
-use std::{fmt, io, net::TcpListener, vec};
-
-use oauth::v2_0::{AuthorizationCodeGrant, Client, RefreshAccessToken};
+use std::{
+    fmt, io,
+    net::TcpListener,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+    vec,
+};
+
+use futures::lock::Mutex;
+use oauth::v2_0::{AuthorizationCodeGrant, Client, Provider, RefreshAccessToken, Tokens};
 use secret::Secret;
-use tracing::debug;
+use tracing::{debug, warn};
 
+#[cfg(feature = "keyring")]
+use super::find_keyring_secret;
 #[doc(inline)]
 pub use super::{Error, Result};
 
@@ -24,6 +33,12 @@ pub struct OAuth2Config {
     /// for authentication.
     pub method: OAuth2Method,
 
+    /// Built-in provider preset.
+    ///
+    /// When defined, the authorization URL, the token URL and the
+    /// scopes default to the ones of the provider.
+    pub provider: Option<Provider>,
+
     /// Client identifier issued to the client during the registration process described by
     /// [Section 2.2](https://datatracker.ietf.org/doc/html/rfc6749#section-2.2).
     pub client_id: String,
@@ -33,13 +48,28 @@ pub struct OAuth2Config {
     pub client_secret: Option<Secret>,
 
     /// URL of the authorization server's authorization endpoint.
+    ///
+    /// Can be omitted when a provider is defined.
+    #[cfg_attr(
+        feature = "derive",
+        serde(default, skip_serializing_if = "String::is_empty")
+    )]
     pub auth_url: String,
 
     /// URL of the authorization server's token endpoint.
+    ///
+    /// Can be omitted when a provider is defined.
+    #[cfg_attr(
+        feature = "derive",
+        serde(default, skip_serializing_if = "String::is_empty")
+    )]
     pub token_url: String,
 
     /// Access token returned by the token endpoint and used to access
     /// protected resources.
+    ///
+    /// When stored in a keyring, the expiry of the access token is
+    /// stored next to it, in an entry suffixed with `-expires-at`.
     #[cfg_attr(
         feature = "derive",
         serde(default, skip_serializing_if = "Secret::is_empty")
@@ -64,13 +94,77 @@ pub struct OAuth2Config {
     pub redirect_port: Option<u16>,
 
     /// Access token scope(s), as defined by the authorization server.
+    ///
+    /// An empty list of scopes falls back to the default scopes of
+    /// the provider, if defined.
     #[cfg_attr(feature = "derive", serde(flatten))]
     pub scopes: OAuth2Scopes,
+
+    /// Number of seconds before the expiry of the access token from
+    /// which it is proactively refreshed.
+    ///
+    /// This margin absorbs clock skews and network latencies, so
+    /// that the access token does not expire in the middle of an
+    /// authentication.
+    ///
+    /// Defaults to 60 seconds.
+    pub refresh_margin: Option<u64>,
+
+    /// The hook called whenever new tokens are issued.
+    ///
+    /// Tokens stored in a keyring are always saved back to it. The
+    /// hook is useful to persist tokens stored elsewhere, for
+    /// example rotated refresh tokens.
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub tokens_hook: Option<OAuth2TokensHook>,
+
+    /// The in-memory state of the tokens.
+    ///
+    /// Clones of the configuration share the same state, so that
+    /// concurrent contexts (IMAP, SMTP…) share the same access token
+    /// and do not race when refreshing it.
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub tokens_state: OAuth2TokensState,
 }
 
 impl OAuth2Config {
     pub const LOCALHOST: &'static str = "localhost";
 
+    pub fn default_refresh_margin() -> u64 {
+        60
+    }
+
+    /// Returns the URL of the authorization endpoint, falling back
+    /// to the one of the provider.
+    pub fn auth_url(&self) -> &str {
+        match &self.provider {
+            Some(provider) if self.auth_url.is_empty() => provider.auth_url(),
+            _ => &self.auth_url,
+        }
+    }
+
+    /// Returns the URL of the token endpoint, falling back to the
+    /// one of the provider.
+    pub fn token_url(&self) -> &str {
+        match &self.provider {
+            Some(provider) if self.token_url.is_empty() => provider.token_url(),
+            _ => &self.token_url,
+        }
+    }
+
+    /// Returns the access token scopes, falling back to the default
+    /// scopes of the provider.
+    pub fn scopes(&self) -> Vec<String> {
+        let scopes: Vec<String> = self.scopes.clone().into_iter().collect();
+
+        match &self.provider {
+            Some(provider) if scopes.is_empty() => {
+                provider.scopes().iter().map(ToString::to_string).collect()
+            }
+            _ => scopes,
+        }
+    }
+
     /// Return the first available port on [`LOCALHOST`].
     pub fn get_first_available_port() -> Result<u16> {
         (49_152..65_535)
@@ -95,6 +189,9 @@ pub async fn reset(&self) -> Result<()> {
             .delete_if_keyring()
             .await
             .map_err(Error::DeleteRefreshTokenOauthError)?;
+        self.write_expires_at(None).await;
+
+        *self.tokens_state.0.lock().await = Default::default();
 
         Ok(())
     }
@@ -146,8 +243,8 @@ pub async fn configure(
         let client = Client::new(
             self.client_id.clone(),
             client_secret,
-            self.auth_url.clone(),
-            self.token_url.clone(),
+            self.auth_url(),
+            self.token_url(),
             redirect_scheme,
             redirect_host,
             redirect_port,
@@ -160,7 +257,7 @@ pub async fn configure(
             auth_code_grant = auth_code_grant.with_pkce();
         }
 
-        for scope in self.scopes.clone() {
+        for scope in self.scopes() {
             auth_code_grant = auth_code_grant.with_scope(scope);
         }
 
@@ -170,29 +267,85 @@ pub async fn configure(
         println!();
         println!("{}", redirect_url);
 
-        let (access_token, refresh_token) = auth_code_grant
-            .wait_for_redirection(&client, csrf_token)
+        let tokens = auth_code_grant
+            .wait_for_tokens(&client, csrf_token)
             .await
             .map_err(Error::WaitForOauthRedirectionError)?;
 
-        self.access_token
-            .set_if_keyring(access_token)
-            .await
-            .map_err(Error::SetAccessTokenOauthError)?;
-
-        if let Some(refresh_token) = &refresh_token {
-            self.refresh_token
-                .set_if_keyring(refresh_token)
-                .await
-                .map_err(Error::SetRefreshTokenOauthError)?;
-        }
+        let mut state = self.tokens_state.0.lock().await;
+        self.save_tokens(&mut state, tokens).await?;
 
         Ok(())
     }
 
     /// Runs the refresh access token OAuth 2.0 flow by exchanging a
     /// refresh token with a new pair of access/refresh token.
+    ///
+    /// Concurrent calls are serialized: when the tokens have been
+    /// refreshed by another call in the meantime, the new access
+    /// token is returned without refreshing it again.
     pub async fn refresh_access_token(&self) -> Result<String> {
+        let requested_at = Instant::now();
+        let mut state = self.tokens_state.0.lock().await;
+
+        if let (Some(access_token), Some(refreshed_at)) = (&state.access_token, state.refreshed_at)
+        {
+            if refreshed_at >= requested_at {
+                debug!("oauth2 access token already refreshed, skipping refresh");
+                return Ok(access_token.clone());
+            }
+        }
+
+        self.refresh_tokens(&mut state).await
+    }
+
+    /// Returns a valid access token.
+    ///
+    /// The access token is taken from memory when possible,
+    /// otherwise from its secret, along with its persisted expiry.
+    /// When it is about to expire (see
+    /// [`OAuth2Config::refresh_margin`]), it is proactively
+    /// refreshed.
+    pub async fn access_token(&self) -> Result<String> {
+        let mut state = self.tokens_state.0.lock().await;
+
+        let access_token = match state.access_token.clone() {
+            Some(access_token) => access_token,
+            None => {
+                let access_token = self
+                    .access_token
+                    .get()
+                    .await
+                    .map_err(Error::GetAccessTokenOauthError)?;
+                state.access_token = Some(access_token.clone());
+                state.expires_at = self.read_expires_at().await.map(to_instant);
+                access_token
+            }
+        };
+
+        let margin = self
+            .refresh_margin
+            .unwrap_or_else(Self::default_refresh_margin);
+        let margin = Duration::from_secs(margin);
+
+        match state.expires_at {
+            Some(expires_at) if expires_at <= Instant::now() + margin => {
+                debug!("oauth2 access token about to expire, refreshing it");
+                match self.refresh_tokens(&mut state).await {
+                    Ok(access_token) => Ok(access_token),
+                    Err(err) => {
+                        warn!("cannot refresh oauth2 access token: {err}");
+                        debug!("{err:?}");
+                        Ok(access_token)
+                    }
+                }
+            }
+            _ => Ok(access_token),
+        }
+    }
+
+    /// Builds the OAuth 2.0 client of the configuration.
+    async fn client(&self) -> Result<Client> {
         let redirect_scheme = match self.redirect_scheme.as_ref() {
             Some(scheme) => scheme.clone(),
             None => "http".into(),
@@ -222,47 +375,221 @@ pub async fn refresh_access_token(&self) -> Result<String> {
         let client = Client::new(
             self.client_id.clone(),
             client_secret,
-            self.auth_url.clone(),
-            self.token_url.clone(),
+            self.auth_url(),
+            self.token_url(),
             redirect_scheme,
             redirect_host,
             redirect_port,
         )
         .map_err(Error::BuildOauthClientError)?;
 
-        let refresh_token = self
-            .refresh_token
-            .get()
-            .await
-            .map_err(Error::GetRefreshTokenOauthError)?;
+        Ok(client)
+    }
+
+    /// Exchanges the refresh token for new tokens, then saves them.
+    async fn refresh_tokens(&self, state: &mut OAuth2TokensStateInner) -> Result<String> {
+        let client = self.client().await?;
+
+        let refresh_token = match state.refresh_token.clone() {
+            Some(refresh_token) => refresh_token,
+            None => self
+                .refresh_token
+                .get()
+                .await
+                .map_err(Error::GetRefreshTokenOauthError)?,
+        };
 
-        let (access_token, refresh_token) = RefreshAccessToken::new()
-            .refresh_access_token(&client, refresh_token)
+        let tokens = RefreshAccessToken::new()
+            .refresh_tokens(&client, refresh_token)
             .await
             .map_err(Error::RefreshAccessTokenOauthError)?;
+        let access_token = tokens.access_token.clone();
+
+        self.save_tokens(state, tokens).await?;
+        state.refreshed_at = Some(Instant::now());
+
+        Ok(access_token)
+    }
 
+    /// Saves the given tokens in memory and into the keyring (if
+    /// applicable), then calls the tokens hook.
+    async fn save_tokens(&self, state: &mut OAuth2TokensStateInner, tokens: Tokens) -> Result<()> {
         self.access_token
-            .set_if_keyring(&access_token)
+            .set_if_keyring(&tokens.access_token)
             .await
             .map_err(Error::SetAccessTokenOauthError)?;
 
-        if let Some(refresh_token) = &refresh_token {
+        if let Some(refresh_token) = &tokens.refresh_token {
             self.refresh_token
                 .set_if_keyring(refresh_token)
                 .await
                 .map_err(Error::SetRefreshTokenOauthError)?;
         }
 
-        Ok(access_token)
+        if let Some(hook) = &self.tokens_hook {
+            if let Err(err) = hook.call(&tokens) {
+                warn!("cannot persist oauth2 tokens: {err}");
+                debug!("{err:?}");
+            }
+        }
+
+        self.write_expires_at(tokens.expires_in.map(|ttl| SystemTime::now() + ttl))
+            .await;
+
+        state.access_token = Some(tokens.access_token);
+        state.expires_at = tokens.expires_in.map(|ttl| Instant::now() + ttl);
+
+        if let Some(refresh_token) = tokens.refresh_token {
+            state.refresh_token = Some(refresh_token);
+        }
+
+        Ok(())
     }
 
-    /// Returns the access token if existing, otherwise returns an
-    /// error.
-    pub async fn access_token(&self) -> Result<String> {
-        self.access_token
-            .get()
-            .await
-            .map_err(Error::GetAccessTokenOauthError)
+    /// Returns the keyring-based secret holding the expiry of the
+    /// access token, if the access token is stored in a keyring.
+    #[cfg(feature = "keyring")]
+    fn expires_at_secret(&self) -> Option<Secret> {
+        let Some(Secret::Keyring(entry)) = find_keyring_secret(&self.access_token) else {
+            return None;
+        };
+
+        let key = format!("{}-expires-at", entry.key);
+
+        match Secret::try_new_keyring_entry(key) {
+            Ok(secret) => Some(secret),
+            Err(err) => {
+                warn!("cannot build oauth2 access token expiry entry, ignoring it: {err}");
+                debug!("{err:?}");
+                None
+            }
+        }
+    }
+
+    /// Reads the persisted expiry of the access token, if any.
+    #[cfg(feature = "keyring")]
+    async fn read_expires_at(&self) -> Option<SystemTime> {
+        let secret = self.expires_at_secret()?;
+
+        let secs = match secret.find().await {
+            Ok(secs) => secs?,
+            Err(err) => {
+                warn!("cannot read oauth2 access token expiry, ignoring it: {err}");
+                debug!("{err:?}");
+                return None;
+            }
+        };
+
+        match secs.trim().parse() {
+            Ok(secs) => Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
+            Err(err) => {
+                warn!("cannot parse oauth2 access token expiry, ignoring it: {err}");
+                debug!("{err:?}");
+                None
+            }
+        }
+    }
+
+    #[cfg(not(feature = "keyring"))]
+    async fn read_expires_at(&self) -> Option<SystemTime> {
+        None
+    }
+
+    /// Persists the given expiry of the access token, or deletes the
+    /// persisted one when the access token does not expire.
+    #[cfg(feature = "keyring")]
+    async fn write_expires_at(&self, expires_at: Option<SystemTime>) {
+        let Some(secret) = self.expires_at_secret() else {
+            return;
+        };
+
+        let res = match expires_at {
+            Some(expires_at) => {
+                let secs = expires_at
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                secret.set_if_keyring(secs.to_string()).await.map(|_| ())
+            }
+            None => secret.delete_if_keyring().await,
+        };
+
+        if let Err(err) = res {
+            warn!("cannot persist oauth2 access token expiry, ignoring it: {err}");
+            debug!("{err:?}");
+        }
+    }
+
+    #[cfg(not(feature = "keyring"))]
+    async fn write_expires_at(&self, _expires_at: Option<SystemTime>) {}
+}
+
+/// Converts the given system time into an instant.
+///
+/// Times in the past are converted into the current instant.
+fn to_instant(time: SystemTime) -> Instant {
+    let ttl = time.duration_since(SystemTime::now()).unwrap_or_default();
+    Instant::now() + ttl
+}
+
+/// The hook called whenever new OAuth 2.0 tokens are issued.
+#[derive(Clone)]
+pub struct OAuth2TokensHook(Arc<dyn Fn(&Tokens) -> io::Result<()> + Send + Sync>);
+
+impl OAuth2TokensHook {
+    /// Creates a new hook from the given callback.
+    pub fn new(hook: impl Fn(&Tokens) -> io::Result<()> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+
+    /// Calls the hook with the given tokens.
+    pub fn call(&self, tokens: &Tokens) -> io::Result<()> {
+        (self.0)(tokens)
+    }
+}
+
+impl PartialEq for OAuth2TokensHook {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for OAuth2TokensHook {}
+
+impl fmt::Debug for OAuth2TokensHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OAuth2TokensHook()")
+    }
+}
+
+#[derive(Debug, Default)]
+struct OAuth2TokensStateInner {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    expires_at: Option<Instant>,
+    refreshed_at: Option<Instant>,
+}
+
+/// The in-memory state of OAuth 2.0 tokens.
+///
+/// The state is shared between clones, and locked during refreshes
+/// so that only one refresh happens at a time.
+#[derive(Clone, Default)]
+pub struct OAuth2TokensState(Arc<Mutex<OAuth2TokensStateInner>>);
+
+// The state is a runtime state rather than a configuration, so it
+// does not take part in the equality of configurations.
+impl PartialEq for OAuth2TokensState {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for OAuth2TokensState {}
+
+impl fmt::Debug for OAuth2TokensState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OAuth2TokensState()")
     }
 }
 
@@ -320,3 +647,128 @@ fn into_iter(self) -> Self::IntoIter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use oauth::v2_0::Provider;
+
+    use super::{OAuth2Config, OAuth2Scopes};
+
+    #[test]
+    fn provider_fallbacks() {
+        let mut config = OAuth2Config {
+            provider: Some(Provider::Gmail),
+            ..Default::default()
+        };
+
+        assert_eq!(config.auth_url(), Provider::Gmail.auth_url());
+        assert_eq!(config.token_url(), Provider::Gmail.token_url());
+        assert_eq!(config.scopes(), vec!["https://mail.google.com/"]);
+
+        config.token_url = String::from("https://localhost/token");
+        config.scopes = OAuth2Scopes::Scope(String::from("custom"));
+
+        assert_eq!(config.auth_url(), Provider::Gmail.auth_url());
+        assert_eq!(config.token_url(), "https://localhost/token");
+        assert_eq!(config.scopes(), vec!["custom"]);
+    }
+
+    #[tokio::test]
+    async fn access_token_from_state() {
+        let config = OAuth2Config::default();
+
+        {
+            let mut state = config.tokens_state.0.lock().await;
+            state.access_token = Some(String::from("token"));
+            state.expires_at = Some(Instant::now() + Duration::from_secs(3600));
+        }
+
+        // clones share the same state
+        let token = config.clone().access_token().await.unwrap();
+        assert_eq!(token, "token");
+
+        // expiring tokens that cannot be refreshed are still returned
+        config.tokens_state.0.lock().await.expires_at = Some(Instant::now());
+        let token = config.access_token().await.unwrap();
+        assert_eq!(token, "token");
+    }
+
+    #[tokio::test]
+    async fn refresh_access_token_single_flight() {
+        let config = OAuth2Config::default();
+
+        // tokens refreshed while waiting for the lock are reused
+        {
+            let mut state = config.tokens_state.0.lock().await;
+            state.access_token = Some(String::from("refreshed"));
+            state.refreshed_at = Some(Instant::now() + Duration::from_secs(1));
+        }
+
+        let token = config.refresh_access_token().await.unwrap();
+        assert_eq!(token, "refreshed");
+
+        // otherwise tokens are refreshed, which fails here since the
+        // configuration is empty
+        config.tokens_state.0.lock().await.refreshed_at = None;
+        assert!(config.refresh_access_token().await.is_err());
+    }
+
+    #[cfg(feature = "keyring")]
+    #[tokio::test]
+    async fn expires_at_persisted() {
+        use oauth::v2_0::Tokens;
+        use secret::Secret;
+
+        let config = OAuth2Config {
+            access_token: Secret::try_new_keyring_entry(String::from(
+                "memory:oauth2-expiry-access-token",
+            ))
+            .unwrap(),
+            ..Default::default()
+        };
+
+        let tokens = Tokens {
+            access_token: String::from("token"),
+            refresh_token: None,
+            expires_in: Some(Duration::from_secs(3600)),
+        };
+
+        let mut state = config.tokens_state.0.lock().await;
+        config
+            .save_tokens(&mut state, tokens.clone())
+            .await
+            .unwrap();
+        drop(state);
+
+        // a new configuration, as after a restart, reads the expiry
+        // back from the keyring
+        let restarted = OAuth2Config {
+            access_token: config.access_token.clone(),
+            ..Default::default()
+        };
+
+        let token = restarted.access_token().await.unwrap();
+        assert_eq!(token, "token");
+        let expires_at = restarted.tokens_state.0.lock().await.expires_at.unwrap();
+        assert!(expires_at > Instant::now() + Duration::from_secs(3500));
+
+        // tokens without lifetime remove the persisted expiry
+        let mut state = config.tokens_state.0.lock().await;
+        let tokens = Tokens {
+            expires_in: None,
+            ..tokens
+        };
+        config.save_tokens(&mut state, tokens).await.unwrap();
+        drop(state);
+
+        let restarted = OAuth2Config {
+            access_token: config.access_token.clone(),
+            ..Default::default()
+        };
+
+        restarted.access_token().await.unwrap();
+        assert_eq!(restarted.tokens_state.0.lock().await.expires_at, None);
+    }
+}
@@ -1,4 +1,4 @@
-use mml::pgp::{Pgp, PgpCommands};
+use mml::pgp::{KeySelection, Pgp, PgpCommands};
 use process::Command;
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -10,10 +10,14 @@
 pub struct PgpCommandsConfig {
     pub encrypt_cmd: Option<Command>,
     pub encrypt_recipient_fmt: Option<String>,
+    pub encrypt_hidden_recipient_fmt: Option<String>,
     pub encrypt_recipients_sep: Option<String>,
+    pub list_keys_cmd: Option<Command>,
     pub decrypt_cmd: Option<Command>,
     pub sign_cmd: Option<Command>,
     pub verify_cmd: Option<Command>,
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub key_selection: KeySelection,
 }
 
 impl From<PgpCommandsConfig> for Pgp {
@@ -21,10 +25,13 @@ fn from(config: PgpCommandsConfig) -> Self {
         Pgp::Commands(PgpCommands {
             encrypt_cmd: config.encrypt_cmd,
             encrypt_recipient_fmt: config.encrypt_recipient_fmt,
+            encrypt_hidden_recipient_fmt: config.encrypt_hidden_recipient_fmt,
             encrypt_recipients_sep: config.encrypt_recipients_sep,
+            list_keys_cmd: config.list_keys_cmd,
             decrypt_cmd: config.decrypt_cmd,
             sign_cmd: config.sign_cmd,
             verify_cmd: config.verify_cmd,
+            key_selection: config.key_selection,
         })
     }
 }
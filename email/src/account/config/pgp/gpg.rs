@@ -1,4 +1,6 @@
-use mml::pgp::{Pgp, PgpGpg};
+use mml::pgp::{KeySelection, Pgp, PgpGpg};
+
+use crate::doctor::{DoctorReport, Problem};
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(
@@ -6,11 +8,36 @@
     derive(serde::Serialize, serde::Deserialize),
     serde(rename_all = "kebab-case")
 )]
-pub struct PgpGpgConfig;
+pub struct PgpGpgConfig {
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub key_selection: KeySelection,
+}
+
+impl PgpGpgConfig {
+    /// Checks that a GPG context can be created.
+    pub fn check_up(&self) -> DoctorReport {
+        let mut report = DoctorReport::new();
+
+        let gpg = PgpGpg {
+            home_dir: None,
+            key_selection: self.key_selection.clone(),
+        };
+
+        if let Err(err) = gpg.get_context() {
+            let msg = format!("cannot create GPG context: {err}");
+            report.push(Problem::error("pgp", msg));
+        }
+
+        report
+    }
+}
 
 impl From<PgpGpgConfig> for Pgp {
-    fn from(_config: PgpGpgConfig) -> Self {
+    fn from(config: PgpGpgConfig) -> Self {
         // TODO: retrieve Gpg home_dir from configurations.
-        Pgp::Gpg(PgpGpg { home_dir: None })
+        Pgp::Gpg(PgpGpg {
+            home_dir: None,
+            key_selection: config.key_selection,
+        })
     }
 }
@@ -25,6 +25,7 @@
 pub use self::native::PgpNativeConfig;
 #[doc(inline)]
 pub use super::{Error, Result};
+use crate::doctor::DoctorReport;
 
 /// The PGP configuration.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -75,6 +76,21 @@ pub async fn reset(&self) -> Result<()> {
         }
     }
 
+    /// Checks the availability of PGP keys for the given email
+    /// address.
+    #[allow(unused)]
+    pub async fn check_up(&self, email: &str) -> DoctorReport {
+        match self {
+            Self::None => DoctorReport::new(),
+            #[cfg(feature = "pgp-commands")]
+            Self::Commands(..) => DoctorReport::new(),
+            #[cfg(feature = "pgp-gpg")]
+            Self::Gpg(config) => config.check_up(),
+            #[cfg(feature = "pgp-native")]
+            Self::Native(config) => config.check_up(email).await,
+        }
+    }
+
     #[allow(unused)]
     pub async fn configure(
         &self,
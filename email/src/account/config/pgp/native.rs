@@ -1,7 +1,8 @@
 use std::io;
 
 use keyring::KeyringEntry;
-use mml::pgp::{NativePgpPublicKeysResolver, NativePgpSecretKey, Pgp, PgpNative};
+use mml::pgp::{KeySelection, NativePgpPublicKeysResolver, NativePgpSecretKey, Pgp, PgpNative};
+use pgp::{http::RefreshedKey, native::SignedPublicKey, ImportedKey};
 use secret::Secret;
 use shellexpand_utils::shellexpand_path;
 use tokio::fs;
@@ -9,6 +10,7 @@
 
 #[doc(inline)]
 pub use super::{Error, Result};
+use crate::doctor::{DoctorReport, Problem};
 
 /// The native PGP configuration.
 ///
@@ -22,9 +24,13 @@
 )]
 pub struct PgpNativeConfig {
     pub secret_key: NativePgpSecretKey,
+    #[cfg_attr(feature = "derive", serde(default))]
     pub secret_key_passphrase: Secret,
     pub wkd: bool,
     pub key_servers: Vec<String>,
+    pub passphrase_cache_ttl: Option<u64>,
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub key_selection: KeySelection,
 }
 
 impl PgpNativeConfig {
@@ -114,6 +120,79 @@ pub async fn configure(
 
         Ok(())
     }
+
+    /// Checks the availability of the secret key and of the public
+    /// key of the given email address.
+    pub async fn check_up(&self, email: &str) -> DoctorReport {
+        let mut report = DoctorReport::new();
+
+        if let Err(err) = self.secret_key.get(email).await {
+            let msg = format!("cannot get PGP secret key: {err}");
+            report.push(Problem::error("pgp.secret-key", msg));
+        }
+
+        if let Err(err) = self.public_key().await {
+            let msg = format!("cannot get PGP public key: {err}");
+            report.push(Problem::warning("pgp.public-key", msg).with_kind(err.kind()));
+        }
+
+        report
+    }
+
+    /// Reads the public key stored alongside the secret key by
+    /// [`PgpNativeConfig::configure`].
+    pub async fn public_key(&self) -> Result<SignedPublicKey> {
+        match &self.secret_key {
+            NativePgpSecretKey::None => Err(Error::GetPgpPublicKeyNoneError),
+            NativePgpSecretKey::Raw(skey) => Ok(pgp::to_signed_public_key(skey)),
+            NativePgpSecretKey::Path(skey_path) => {
+                let pkey_path = shellexpand_path(skey_path).with_extension("pub");
+                pgp::read_pkey_from_path(pkey_path.clone())
+                    .await
+                    .map_err(|err| Error::ReadPgpPublicKeyFileError(err, pkey_path))
+            }
+            NativePgpSecretKey::Keyring(skey_entry) => {
+                let pkey_entry = KeyringEntry::try_new(skey_entry.key.clone() + "-pub")
+                    .map_err(Error::GetPublicKeyFromKeyringError)?;
+                let pkey = pkey_entry
+                    .get_secret()
+                    .await
+                    .map_err(Error::GetPublicKeyFromKeyringError)?;
+                let keys = pgp::import(pkey)
+                    .await
+                    .map_err(Error::ImportPgpPublicKeyError)?;
+
+                keys.into_iter()
+                    .find_map(|key| match key {
+                        ImportedKey::Public(pkey) => Some(pkey),
+                        ImportedKey::Secret(_) => None,
+                    })
+                    .ok_or(Error::GetPgpPublicKeyNoneError)
+            }
+        }
+    }
+
+    /// Publishes the public key to the configured key servers.
+    ///
+    /// Key servers are tried in order until one of them accepts the
+    /// public key. Returns the key server the public key has been
+    /// published to.
+    pub async fn publish(&self) -> Result<String> {
+        let pkey = self.public_key().await?;
+        let key_server = pgp::http::publish(pkey, self.key_servers.clone())
+            .await
+            .map_err(Error::PublishPgpPublicKeyError)?;
+        Ok(key_server)
+    }
+
+    /// Re-fetches the given known public keys from the configured key
+    /// servers, in order to record their revocations and expirations.
+    pub async fn refresh(
+        &self,
+        pkeys: Vec<SignedPublicKey>,
+    ) -> Vec<(String, pgp::Result<RefreshedKey>)> {
+        pgp::http::refresh_keys(pkeys, self.key_servers.clone()).await
+    }
 }
 
 impl Default for PgpNativeConfig {
@@ -123,6 +202,8 @@ fn default() -> Self {
             secret_key_passphrase: Default::default(),
             wkd: Self::default_wkd(),
             key_servers: Self::default_key_servers(),
+            passphrase_cache_ttl: None,
+            key_selection: Default::default(),
         }
     }
 }
@@ -145,6 +226,9 @@ fn from(config: PgpNativeConfig) -> Self {
             secret_key: config.secret_key,
             secret_key_passphrase: config.secret_key_passphrase,
             public_keys_resolvers,
+            key_selection: config.key_selection,
+            passphrase_cache_ttl: config.passphrase_cache_ttl,
+            ..Default::default()
         })
     }
 }
@@ -0,0 +1,277 @@
+//! Module dedicated to account renaming.
+//!
+//! This module contains the [`KeyringRename`] helper, used by
+//! [`AccountConfig::rename`] to move the keyring entries derived from
+//! the account name. Entries are first copied, then old entries are
+//! deleted once every other renaming step succeeded, so that the
+//! renaming can be rolled back.
+//!
+//! [`AccountConfig::rename`]: super::AccountConfig::rename
+
+use secret::Secret;
+use tracing::{debug, trace, warn};
+
+use super::{find_keyring_secret_mut, Error, Result};
+
+/// A keyring-based secret being renamed.
+struct RenamedSecret<'a> {
+    /// The secret, still using the old keyring entry.
+    secret: &'a mut Secret,
+
+    /// The secret using the new keyring entry.
+    renamed: Secret,
+
+    /// Whether a value has been written into the new keyring entry.
+    written: bool,
+}
+
+/// The keyring renaming helper.
+#[derive(Default)]
+pub(crate) struct KeyringRename<'a> {
+    secrets: Vec<RenamedSecret<'a>>,
+}
+
+impl<'a> KeyringRename<'a> {
+    /// Copies the keyring entries of the given secrets derived from
+    /// the old account name into entries derived from the new one.
+    ///
+    /// Secrets are given along with the suffix of their entry key.
+    /// Secrets that are not stored in the keyring, or that are
+    /// stored in custom keyring entries, are left untouched. Old
+    /// entries are kept until [`KeyringRename::commit`]. On failure,
+    /// the new entries already written are deleted.
+    pub async fn copy(
+        old_name: &str,
+        new_name: &str,
+        secrets: impl IntoIterator<Item = (&'static str, &'a mut Secret)>,
+    ) -> Result<Self> {
+        let mut rename = Self::default();
+
+        for (suffix, secret) in secrets {
+            let from = format!("{old_name}-{suffix}");
+            let to = format!("{new_name}-{suffix}");
+
+            if let Err(err) = rename.copy_secret(secret, &from, &to).await {
+                rename.rollback().await;
+                return Err(err);
+            }
+        }
+
+        Ok(rename)
+    }
+
+    async fn copy_secret(&mut self, secret: &'a mut Secret, from: &str, to: &str) -> Result<()> {
+        let Some(secret) = find_keyring_secret_mut(secret) else {
+            return Ok(());
+        };
+
+        let Secret::Keyring(entry) = &*secret else {
+            return Ok(());
+        };
+
+        let Some(key) = renamed_key(&entry.key, from, to) else {
+            return Ok(());
+        };
+
+        let old_key = entry.key.clone();
+        let map_err = |err| Error::RenameKeyringSecretError(err, old_key.clone(), key.clone());
+
+        let renamed = Secret::try_new_keyring_entry(key.clone()).map_err(map_err)?;
+
+        if renamed.find().await.map_err(map_err)?.is_some() {
+            return Err(Error::RenameKeyringSecretAlreadyExistsError(key));
+        }
+
+        let value = secret.find().await.map_err(map_err)?;
+        let written = value.is_some();
+
+        debug!(%old_key, %key, "copying keyring entry");
+
+        self.secrets.push(RenamedSecret {
+            secret,
+            renamed: renamed.clone(),
+            written,
+        });
+
+        if let Some(value) = value {
+            renamed.set_if_keyring(value).await.map_err(map_err)?;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the new keyring entries, keeping the old ones.
+    pub async fn rollback(self) {
+        for RenamedSecret { renamed, .. } in self.secrets.into_iter().filter(|s| s.written) {
+            if let Err(err) = renamed.delete_if_keyring().await {
+                warn!("cannot delete renamed keyring entry, ignoring it: {err}");
+                trace!("{err:?}");
+            }
+        }
+    }
+
+    /// Deletes the old keyring entries, then makes secrets use the
+    /// new ones.
+    ///
+    /// Old entries that cannot be deleted are left behind.
+    pub async fn commit(self) {
+        for RenamedSecret {
+            secret,
+            renamed,
+            written,
+        } in self.secrets
+        {
+            if written {
+                if let Err(err) = secret.delete_if_keyring().await {
+                    warn!("cannot delete old keyring entry, ignoring it: {err}");
+                    trace!("{err:?}");
+                }
+            }
+
+            *secret = renamed;
+        }
+    }
+}
+
+/// Returns the key of the renamed entry, if the given key matches
+/// the given old key.
+///
+/// The backend prefix of the key, if any, is preserved (see
+/// `KeyringBackend::parse_key`).
+fn renamed_key(key: &str, from: &str, to: &str) -> Option<String> {
+    let prefix = key.strip_suffix(from)?;
+
+    if prefix.is_empty() || prefix.ends_with(':') {
+        Some(format!("{prefix}{to}"))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use secret::{CachedSecret, Secret};
+
+    use super::renamed_key;
+    use crate::account::{config::AccountConfig, Error};
+
+    fn keyring(key: &str) -> Secret {
+        Secret::try_new_keyring_entry(key.to_owned()).unwrap()
+    }
+
+    #[test]
+    fn renamed_keys() {
+        let (from, to) = ("old-imap-passwd", "new-imap-passwd");
+
+        assert_eq!(renamed_key(from, from, to).unwrap(), to);
+        assert_eq!(
+            renamed_key("memory:old-imap-passwd", from, to).unwrap(),
+            "memory:new-imap-passwd"
+        );
+        assert_eq!(renamed_key("custom-entry", from, to), None);
+        assert_eq!(renamed_key("bold-imap-passwd", from, to), None);
+    }
+
+    #[tokio::test]
+    async fn rename_moves_keyring_entries() {
+        let mut config = AccountConfig {
+            name: String::from("rename-ok"),
+            ..Default::default()
+        };
+
+        let mut passwd = keyring("memory:rename-ok-imap-passwd");
+        passwd.set_if_keyring("imap").await.unwrap();
+
+        let mut token = Secret::Cached(CachedSecret::new(keyring(
+            "memory:rename-ok-smtp-oauth2-access-token",
+        )));
+        token.set("smtp").await.unwrap();
+
+        let mut custom = keyring("memory:rename-ok-custom");
+        custom.set_if_keyring("custom").await.unwrap();
+
+        let secrets = [
+            ("imap-passwd", &mut passwd),
+            ("smtp-oauth2-access-token", &mut token),
+            ("smtp-passwd", &mut custom),
+        ];
+
+        config.rename("renamed-ok", secrets).await.unwrap();
+        assert_eq!(config.name, "renamed-ok");
+
+        assert_eq!(passwd, keyring("memory:renamed-ok-imap-passwd"));
+        assert_eq!(passwd.find().await.unwrap().unwrap(), "imap");
+        let old = keyring("memory:rename-ok-imap-passwd");
+        assert_eq!(old.find().await.unwrap(), None);
+
+        let Secret::Cached(cached) = &token else {
+            panic!("cached secret should stay cached");
+        };
+        let expected = keyring("memory:renamed-ok-smtp-oauth2-access-token");
+        assert_eq!(*cached.secret, expected);
+        assert_eq!(expected.find().await.unwrap().unwrap(), "smtp");
+
+        assert_eq!(custom, keyring("memory:rename-ok-custom"));
+        assert_eq!(custom.find().await.unwrap().unwrap(), "custom");
+    }
+
+    #[tokio::test]
+    async fn rename_rolls_back_on_failure() {
+        let mut config = AccountConfig {
+            name: String::from("rename-ko"),
+            ..Default::default()
+        };
+
+        let mut passwd = keyring("memory:rename-ko-imap-passwd");
+        passwd.set_if_keyring("imap").await.unwrap();
+
+        // the environment backend is read-only, so the new entry
+        // cannot be written
+        env::set_var("RENAME_KO_SMTP_PASSWD", "smtp");
+        let mut smtp_passwd = keyring("env:rename-ko-smtp-passwd");
+
+        let secrets = [
+            ("imap-passwd", &mut passwd),
+            ("smtp-passwd", &mut smtp_passwd),
+        ];
+
+        let err = config.rename("renamed-ko", secrets).await.unwrap_err();
+        assert!(matches!(err, Error::RenameKeyringSecretError(..)));
+        assert_eq!(config.name, "rename-ko");
+
+        assert_eq!(passwd, keyring("memory:rename-ko-imap-passwd"));
+        assert_eq!(passwd.find().await.unwrap().unwrap(), "imap");
+        let new = keyring("memory:renamed-ko-imap-passwd");
+        assert_eq!(new.find().await.unwrap(), None);
+
+        assert_eq!(smtp_passwd, keyring("env:rename-ko-smtp-passwd"));
+    }
+
+    #[tokio::test]
+    async fn rename_refuses_existing_entries() {
+        let mut config = AccountConfig {
+            name: String::from("rename-conflict"),
+            ..Default::default()
+        };
+
+        let mut passwd = keyring("memory:rename-conflict-imap-passwd");
+        passwd.set_if_keyring("old").await.unwrap();
+
+        let existing = keyring("memory:renamed-conflict-imap-passwd");
+        existing.set_if_keyring("existing").await.unwrap();
+
+        let err = config
+            .rename("renamed-conflict", [("imap-passwd", &mut passwd)])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::RenameKeyringSecretAlreadyExistsError(_)
+        ));
+        assert_eq!(config.name, "rename-conflict");
+        assert_eq!(existing.find().await.unwrap().unwrap(), "existing");
+        assert_eq!(passwd.find().await.unwrap().unwrap(), "old");
+    }
+}
@@ -0,0 +1,452 @@
+//! Module dedicated to password rotation.
+//!
+//! This module contains the [`PasswordRotation`] helper, used by
+//! "change password" flows to replace the password stored in the
+//! keyring. The new password is verified by reconnecting to the
+//! IMAP and SMTP servers, and the previous one is restored if the
+//! verification fails.
+
+use std::{fmt, sync::Arc};
+
+use secret::Secret;
+use tracing::debug;
+
+use super::find_keyring_secret;
+#[doc(inline)]
+pub use super::{Error, Result};
+use crate::doctor::DoctorReport;
+#[cfg(feature = "imap")]
+use crate::imap::config::{ImapAuthConfig, ImapConfig};
+#[cfg(feature = "smtp")]
+use crate::smtp::config::{SmtpAuthConfig, SmtpConfig};
+
+/// The password rotation event.
+///
+/// Represents the progress of a password rotation. The scope of the
+/// event is the backend the password belongs to, for example `imap`.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum PasswordRotationEvent {
+    /// The new password has been stored in the keyring entry of the
+    /// given scope.
+    StoredPassword(String),
+    /// The connection of the given scope is being verified using the
+    /// new password.
+    VerifyingConnection(String),
+    /// The connection of the given scope has been verified.
+    VerifiedConnection(String),
+    /// The verification failed, previous passwords are being
+    /// restored.
+    RollingBack,
+    /// The previous password of the given scope has been restored.
+    RestoredPassword(String),
+}
+
+impl fmt::Display for PasswordRotationEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StoredPassword(scope) => write!(f, "Stored new {scope} password"),
+            Self::VerifyingConnection(scope) => write!(f, "Verifying {scope} connection"),
+            Self::VerifiedConnection(scope) => write!(f, "Verified {scope} connection"),
+            Self::RollingBack => write!(f, "Rolling back to previous passwords"),
+            Self::RestoredPassword(scope) => write!(f, "Restored previous {scope} password"),
+        }
+    }
+}
+
+/// The password rotation event handler.
+pub type PasswordRotationEventHandler = dyn Fn(PasswordRotationEvent) + Send + Sync;
+
+/// The password rotation helper.
+///
+/// Replaces the password of the given backend configurations in the
+/// keyring, then verifies it by reconnecting to the servers. Either
+/// every password is rotated, or none of them.
+///
+/// Only passwords stored in the keyring can be rotated, including
+/// cached ones.
+pub struct PasswordRotation {
+    /// The new password.
+    passwd: String,
+
+    /// The IMAP configuration, if its password needs to be rotated.
+    #[cfg(feature = "imap")]
+    imap_config: Option<Arc<ImapConfig>>,
+
+    /// The SMTP configuration, if its password needs to be rotated.
+    #[cfg(feature = "smtp")]
+    smtp_config: Option<Arc<SmtpConfig>>,
+
+    /// The handler receiving progress events.
+    handler: Option<Arc<PasswordRotationEventHandler>>,
+}
+
+impl PasswordRotation {
+    /// Creates a new password rotation using the given new password.
+    pub fn new(passwd: impl ToString) -> Self {
+        Self {
+            passwd: passwd.to_string(),
+            #[cfg(feature = "imap")]
+            imap_config: None,
+            #[cfg(feature = "smtp")]
+            smtp_config: None,
+            handler: None,
+        }
+    }
+
+    /// Rotates the password of the given IMAP configuration, using
+    /// the builder pattern.
+    #[cfg(feature = "imap")]
+    pub fn with_imap_config(mut self, config: Arc<ImapConfig>) -> Self {
+        self.imap_config = Some(config);
+        self
+    }
+
+    /// Rotates the password of the given SMTP configuration, using
+    /// the builder pattern.
+    #[cfg(feature = "smtp")]
+    pub fn with_smtp_config(mut self, config: Arc<SmtpConfig>) -> Self {
+        self.smtp_config = Some(config);
+        self
+    }
+
+    /// Sends progress events to the given handler, using the builder
+    /// pattern.
+    pub fn with_handler(
+        mut self,
+        handler: impl Fn(PasswordRotationEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.handler = Some(Arc::new(handler));
+        self
+    }
+
+    fn emit(&self, event: PasswordRotationEvent) {
+        debug!("{event}");
+
+        if let Some(handler) = self.handler.as_ref() {
+            handler(event)
+        }
+    }
+
+    /// Returns the passwords of the configurations, by scope.
+    #[allow(unused_mut)]
+    fn passwds(&self) -> Result<Vec<(&'static str, &Secret)>> {
+        let mut passwds = Vec::new();
+
+        #[cfg(feature = "imap")]
+        if let Some(config) = self.imap_config.as_ref() {
+            match &config.auth {
+                ImapAuthConfig::Password(passwd) => passwds.push(("imap", &passwd.0)),
+                #[cfg(feature = "oauth2")]
+                ImapAuthConfig::OAuth2(_) => {
+                    return Err(Error::RotatePasswordNotPasswordAuthError("imap"))
+                }
+            }
+        }
+
+        #[cfg(feature = "smtp")]
+        if let Some(config) = self.smtp_config.as_ref() {
+            match &config.auth {
+                SmtpAuthConfig::Password(passwd) => passwds.push(("smtp", &passwd.0)),
+                #[cfg(feature = "oauth2")]
+                SmtpAuthConfig::OAuth2(_) => {
+                    return Err(Error::RotatePasswordNotPasswordAuthError("smtp"))
+                }
+            }
+        }
+
+        if passwds.is_empty() {
+            return Err(Error::RotatePasswordNothingToRotateError);
+        }
+
+        Ok(passwds)
+    }
+
+    /// Collects the keyring secrets to rotate, by scope.
+    ///
+    /// Keyring secrets wrapped by cached secrets are collected as
+    /// well. Fails without touching the keyring if one of the
+    /// configurations does not use a keyring-based password.
+    fn secrets(&self) -> Result<Vec<(&'static str, Secret)>> {
+        self.passwds()?
+            .into_iter()
+            .map(|(scope, passwd)| match find_keyring_secret(passwd) {
+                Some(secret) => Ok((scope, secret.clone())),
+                None => Err(Error::RotatePasswordNotInKeyringError(scope)),
+            })
+            .collect()
+    }
+
+    /// Invalidates the cached passwords of the configurations, so
+    /// that the next connections read them from the keyring again.
+    fn invalidate_caches(&self) {
+        for (_, passwd) in self.passwds().unwrap_or_default() {
+            invalidate_cache(passwd);
+        }
+    }
+
+    /// Verifies the connection of the given scope using the current
+    /// content of the keyring.
+    async fn verify(&self, scope: &str) -> DoctorReport {
+        match scope {
+            #[cfg(feature = "imap")]
+            "imap" => match self.imap_config.as_ref() {
+                Some(config) => config.check_up().await,
+                None => DoctorReport::new(),
+            },
+            #[cfg(feature = "smtp")]
+            "smtp" => match self.smtp_config.as_ref() {
+                Some(config) => config.check_up().await,
+                None => DoctorReport::new(),
+            },
+            _ => DoctorReport::new(),
+        }
+    }
+
+    /// Rotates the password.
+    ///
+    /// The new password is stored in every keyring entry, then each
+    /// connection is verified. If storing or verifying fails, the
+    /// previous passwords are restored and the error is returned.
+    pub async fn rotate(self) -> Result<()> {
+        let secrets = self.secrets()?;
+
+        // IMAP and SMTP may share the same keyring entry, in which
+        // case only the first previous value is relevant
+        let mut prev_passwds: Vec<(&'static str, Secret, Option<String>)> = Vec::new();
+
+        for (scope, secret) in &secrets {
+            if prev_passwds.iter().any(|(_, s, _)| s == secret) {
+                continue;
+            }
+
+            let prev_passwd = match secret.find().await {
+                Ok(passwd) => passwd,
+                Err(err) => {
+                    self.rollback(&prev_passwds).await?;
+                    return Err(Error::GetFromKeyringError(err));
+                }
+            };
+
+            prev_passwds.push((*scope, secret.clone(), prev_passwd));
+
+            if let Err(err) = secret.set_if_keyring(&self.passwd).await {
+                self.rollback(&prev_passwds).await?;
+                return Err(Error::SetIntoKeyringError(err));
+            }
+
+            self.emit(PasswordRotationEvent::StoredPassword(scope.to_string()));
+        }
+
+        self.invalidate_caches();
+
+        for (scope, _) in &secrets {
+            self.emit(PasswordRotationEvent::VerifyingConnection(
+                scope.to_string(),
+            ));
+
+            let report = self.verify(scope).await;
+
+            if report.has_errors() {
+                self.rollback(&prev_passwds).await?;
+
+                let reason = report
+                    .problems()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                return Err(Error::RotatePasswordVerifyError(*scope, reason));
+            }
+
+            self.emit(PasswordRotationEvent::VerifiedConnection(scope.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Restores the given previous passwords.
+    ///
+    /// Keyring entries that did not exist before the rotation are
+    /// deleted.
+    async fn rollback(
+        &self,
+        prev_passwds: &[(&'static str, Secret, Option<String>)],
+    ) -> Result<()> {
+        self.emit(PasswordRotationEvent::RollingBack);
+
+        // cached passwords may already hold the new password
+        self.invalidate_caches();
+
+        for (scope, secret, prev_passwd) in prev_passwds {
+            let res = match prev_passwd {
+                Some(passwd) => secret.set_if_keyring(passwd).await.map(|_| ()),
+                None => secret.delete_if_keyring().await,
+            };
+
+            res.map_err(|err| Error::RotatePasswordRollbackError(err, *scope))?;
+
+            self.emit(PasswordRotationEvent::RestoredPassword(scope.to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Invalidates the cached values held by the given secret, if any.
+fn invalidate_cache(secret: &Secret) {
+    if let Secret::Cached(cached) = secret {
+        cached.invalidate();
+        invalidate_cache(&cached.secret);
+    }
+}
+
+impl fmt::Debug for PasswordRotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("PasswordRotation");
+        #[cfg(feature = "imap")]
+        debug.field("imap_config", &self.imap_config);
+        #[cfg(feature = "smtp")]
+        debug.field("smtp_config", &self.smtp_config);
+        debug.finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "imap"))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use email_testing_server::start_email_testing_server;
+    use secret::{CachedSecret, Secret};
+
+    use super::{Error, PasswordRotation, PasswordRotationEvent};
+    use crate::{
+        account::config::passwd::PasswordConfig,
+        imap::config::{ImapAuthConfig, ImapConfig},
+        tls::Encryption,
+    };
+
+    fn keyring(key: &str) -> Secret {
+        Secret::try_new_keyring_entry(key.to_owned()).unwrap()
+    }
+
+    fn imap_config(passwd: Secret) -> ImapConfig {
+        ImapConfig {
+            auth: ImapAuthConfig::Password(PasswordConfig(passwd)),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn rotate_requires_keyring() {
+        let err = PasswordRotation::new("new").rotate().await.unwrap_err();
+        assert!(matches!(err, Error::RotatePasswordNothingToRotateError));
+
+        let config = imap_config(Secret::new_raw("old"));
+
+        let err = PasswordRotation::new("new")
+            .with_imap_config(Arc::new(config))
+            .rotate()
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::RotatePasswordNotInKeyringError("imap")
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn rotate_cached_keyring_password() {
+        let (ports, shutdown) = start_email_testing_server().await;
+
+        let entry = keyring("memory:rotate-ok-imap-passwd");
+        entry.set_if_keyring("old").await.unwrap();
+
+        // the old password is cached before the rotation
+        let passwd = Secret::Cached(CachedSecret::new(entry.clone()));
+        assert_eq!(passwd.get().await.unwrap(), "old");
+
+        let config = Arc::new(ImapConfig {
+            host: "localhost".into(),
+            port: ports.imap,
+            encryption: Some(Encryption::None),
+            login: "alice".into(),
+            ..imap_config(passwd.clone())
+        });
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let res = PasswordRotation::new("password")
+            .with_imap_config(config)
+            .with_handler({
+                let events = events.clone();
+                move |event| events.lock().unwrap().push(event)
+            })
+            .rotate()
+            .await;
+
+        shutdown();
+        res.unwrap();
+
+        assert_eq!(entry.get().await.unwrap(), "password");
+        assert_eq!(passwd.get().await.unwrap(), "password");
+
+        let scope = String::from("imap");
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                PasswordRotationEvent::StoredPassword(scope.clone()),
+                PasswordRotationEvent::VerifyingConnection(scope.clone()),
+                PasswordRotationEvent::VerifiedConnection(scope),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rotate_rolls_back_on_verify_failure() {
+        let entry = keyring("memory:rotate-verify-ko-imap-passwd");
+        entry.set_if_keyring("old").await.unwrap();
+
+        let passwd = Secret::Cached(CachedSecret::new(entry.clone()));
+
+        // the configuration has no host, so the verification fails
+        let err = PasswordRotation::new("new")
+            .with_imap_config(Arc::new(imap_config(passwd.clone())))
+            .rotate()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::RotatePasswordVerifyError("imap", _)));
+
+        assert_eq!(entry.get().await.unwrap(), "old");
+        assert_eq!(passwd.get().await.unwrap(), "old");
+    }
+
+    #[cfg(feature = "smtp")]
+    #[tokio::test]
+    async fn rotate_rolls_back_on_store_failure() {
+        use crate::smtp::config::{SmtpAuthConfig, SmtpConfig};
+
+        let entry = keyring("memory:rotate-store-ko-imap-passwd");
+        entry.set_if_keyring("old").await.unwrap();
+
+        // the environment backend is read-only, so the new password
+        // cannot be stored
+        std::env::set_var("ROTATE_STORE_KO_SMTP_PASSWD", "old");
+        let smtp_config = SmtpConfig {
+            auth: SmtpAuthConfig::Password(PasswordConfig(keyring(
+                "env:rotate-store-ko-smtp-passwd",
+            ))),
+            ..Default::default()
+        };
+
+        let err = PasswordRotation::new("new")
+            .with_imap_config(Arc::new(imap_config(entry.clone())))
+            .with_smtp_config(Arc::new(smtp_config))
+            .rotate()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::SetIntoKeyringError(_)));
+
+        assert_eq!(entry.get().await.unwrap(), "old");
+    }
+}
@@ -4,6 +4,8 @@
 use http::ureq::http::{StatusCode, Uri};
 use thiserror::Error;
 
+use crate::ErrorKind;
+
 /// The global `Result` alias of the module.
 pub type Result<T> = result::Result<T, Error>;
 
@@ -19,6 +21,25 @@ pub enum Error {
     #[cfg(feature = "sync")]
     #[error("cannot get invalid or missing synchronization directory {1}")]
     GetSyncDirInvalidError(#[source] shellexpand_utils::Error, PathBuf),
+    #[cfg(feature = "sync")]
+    #[error("cannot rename synchronization directory: {0} already exists")]
+    RenameSyncDirAlreadyExistsError(PathBuf),
+    #[cfg(feature = "sync")]
+    #[error("cannot rename synchronization directory {1} to {2}")]
+    RenameSyncDirError(#[source] io::Error, PathBuf, PathBuf),
+    #[cfg(feature = "keyring")]
+    #[error("cannot rename keyring entry {1} to {2}")]
+    RenameKeyringSecretError(#[source] secret::Error, String, String),
+    #[cfg(feature = "keyring")]
+    #[error("cannot rename keyring entry: {0} already exists")]
+    RenameKeyringSecretAlreadyExistsError(String),
+
+    #[error("cannot create runtime directory {1}")]
+    CreateRuntimeDirError(#[source] io::Error, PathBuf),
+    #[error("cannot use runtime directory {0}: not a directory owned by the current user")]
+    UnsafeRuntimeDirError(PathBuf),
+    #[error("cannot create runtime file {1}")]
+    CreateRuntimeFileError(#[source] io::Error, PathBuf),
 
     #[error("cannot parse download file name from {0}")]
     ParseDownloadFileNameError(PathBuf),
@@ -70,6 +91,16 @@ pub enum Error {
     SetIntoKeyringError(#[source] secret::Error),
     #[error("cannot delete password from global keyring")]
     DeletePasswordFromKeyringError(#[source] secret::Error),
+    #[error("cannot rotate password: no backend configuration given")]
+    RotatePasswordNothingToRotateError,
+    #[error("cannot rotate {0} password: authentication is not password-based")]
+    RotatePasswordNotPasswordAuthError(&'static str),
+    #[error("cannot rotate {0} password: password is not stored in the keyring")]
+    RotatePasswordNotInKeyringError(&'static str),
+    #[error("cannot verify new {0} password, previous password restored: {1}")]
+    RotatePasswordVerifyError(&'static str, String),
+    #[error("cannot restore previous {1} password")]
+    RotatePasswordRollbackError(#[source] secret::Error, &'static str),
     #[cfg(feature = "pgp-native")]
     #[error("cannot delete pgp key from keyring")]
     DeletePgpKeyFromKeyringError(#[source] keyring::Error),
@@ -99,6 +130,18 @@ pub enum Error {
     #[error("cannot set public key to keyring")]
     SetPublicKeyToKeyringError(#[source] keyring::Error),
     #[cfg(feature = "pgp-native")]
+    #[error("cannot get pgp public key: secret key not configured")]
+    GetPgpPublicKeyNoneError,
+    #[cfg(feature = "pgp-native")]
+    #[error("cannot read pgp public key file at {1}")]
+    ReadPgpPublicKeyFileError(#[source] pgp::Error, PathBuf),
+    #[cfg(feature = "pgp-native")]
+    #[error("cannot import pgp public key")]
+    ImportPgpPublicKeyError(#[source] pgp::Error),
+    #[cfg(feature = "pgp-native")]
+    #[error("cannot publish pgp public key")]
+    PublishPgpPublicKeyError(#[source] pgp::Error),
+    #[cfg(feature = "pgp-native")]
     #[error("cannot get secret key password")]
     GetPgpSecretKeyPasswdError(#[source] io::Error),
     #[cfg(feature = "pgp-native")]
@@ -135,3 +178,64 @@ pub enum Error {
     #[error("cannot parse email {0}: {1}")]
     ParsingEmailAddress(String, #[source] email_address::Error),
 }
+
+impl Error {
+    /// Returns the kind of the error.
+    ///
+    /// See [`ErrorKind`] for the available kinds.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::GetAccountConfigNotFoundError(_) => ErrorKind::NotFound,
+            #[cfg(feature = "sync")]
+            Self::RenameSyncDirAlreadyExistsError(_) => ErrorKind::Conflict,
+            #[cfg(feature = "keyring")]
+            Self::RenameKeyringSecretError(..) => ErrorKind::Auth,
+            #[cfg(feature = "keyring")]
+            Self::RenameKeyringSecretAlreadyExistsError(_) => ErrorKind::Conflict,
+            #[cfg(feature = "sync")]
+            Self::GetXdgDataDirSyncError | Self::GetSyncDirInvalidError(..) => ErrorKind::Config,
+            #[cfg(feature = "oauth2")]
+            Self::InitOauthClientError(_)
+            | Self::BuildOauthClientError(_)
+            | Self::WaitForOauthRedirectionError(_)
+            | Self::RefreshAccessTokenOauthError(_) => ErrorKind::Auth,
+            Self::GetAccessTokenOauthError(_)
+            | Self::SetAccessTokenOauthError(_)
+            | Self::DeleteAccessTokenOauthError(_)
+            | Self::GetRefreshTokenOauthError(_)
+            | Self::SetRefreshTokenOauthError(_)
+            | Self::DeleteRefreshTokenOauthError(_)
+            | Self::GetClientSecretFromUserOauthError(_)
+            | Self::GetClientSecretFromKeyringOauthError(_)
+            | Self::SetClientSecretIntoKeyringOauthError(_)
+            | Self::DeleteClientSecretOauthError(_)
+            | Self::GetFromUserError(_)
+            | Self::GetFromKeyringError(_)
+            | Self::SetIntoKeyringError(_)
+            | Self::DeletePasswordFromKeyringError(_)
+            | Self::RotatePasswordVerifyError(..)
+            | Self::RotatePasswordRollbackError(..) => ErrorKind::Auth,
+            Self::RotatePasswordNothingToRotateError
+            | Self::RotatePasswordNotPasswordAuthError(_)
+            | Self::RotatePasswordNotInKeyringError(_) => ErrorKind::Config,
+            Self::GetMxRecordNotFoundError(_)
+            | Self::GetMailconfTxtRecordNotFoundError(_)
+            | Self::GetSrvRecordNotFoundError(_) => ErrorKind::NotFound,
+            #[cfg(feature = "autoconfig")]
+            Self::TXTLookUpFailure(_)
+            | Self::MXLookUpFailure(_)
+            | Self::SRVLookUpFailure(_)
+            | Self::GetConnectionAutoConfigError(..)
+            | Self::ToBytesAutoConfigError(..) => ErrorKind::Network,
+            #[cfg(feature = "autoconfig")]
+            Self::GetAutoConfigError(..) | Self::SerdeXmlFailedForAutoConfig(..) => {
+                ErrorKind::Protocol
+            }
+            #[cfg(feature = "autoconfig")]
+            Self::ParsingEmailAddress(..) => ErrorKind::Config,
+            #[cfg(feature = "pgp-native")]
+            Self::GetPgpPublicKeyNoneError => ErrorKind::Config,
+            _ => ErrorKind::Other,
+        }
+    }
+}
@@ -7,6 +7,7 @@
 
 pub mod config;
 mod error;
+pub mod runtime;
 #[cfg(feature = "sync")]
 pub mod sync;
 
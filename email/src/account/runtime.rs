@@ -0,0 +1,353 @@
+//! Module dedicated to runtime directories.
+//!
+//! Runtime directories hold short-lived files like drafts, sendmail
+//! buffers or lock files. The global runtime directory defaults to
+//! `$XDG_RUNTIME_DIR/pimalaya/email`, and falls back to a
+//! user-specific directory inside the system temporary directory so
+//! that users sharing the same host do not collide. Each account
+//! gets its own runtime directory inside the global one.
+//!
+//! Runtime directories created by this module are only accessible by
+//! their owner.
+
+use std::{
+    env,
+    fs::{self, DirBuilder, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+};
+
+use tracing::debug;
+
+use super::{Error, Result};
+
+/// The runtime directory.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RuntimeDir {
+    path: PathBuf,
+}
+
+impl RuntimeDir {
+    /// Creates a new runtime directory at the given path.
+    ///
+    /// The directory and its missing parents are created if needed,
+    /// with owner-only permissions. Existing directories keep their
+    /// permissions.
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        create_private_dir(&path)?;
+        Ok(Self { path })
+    }
+
+    /// Returns the global runtime directory.
+    ///
+    /// The given custom directory takes precedence over the default
+    /// one.
+    pub fn global(dir: Option<&Path>) -> Result<Self> {
+        let dir = match dir {
+            Some(dir) => Self::new(dir)?,
+            None => default_global_dir()?,
+        };
+
+        debug!(path = ?dir.path, "using global runtime directory");
+        Ok(dir)
+    }
+
+    /// Returns the runtime directory of the given account, inside the
+    /// global one.
+    pub fn account(dir: Option<&Path>, account_name: &str) -> Result<Self> {
+        let path = Self::global(dir)?
+            .join("accounts")
+            .join(sanitize(account_name));
+        debug!(?path, "using runtime directory of account {account_name}");
+        Self::new(path)
+    }
+
+    /// Returns the path of the runtime directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the path of the given entry, inside the runtime
+    /// directory.
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.path.join(path)
+    }
+
+    /// Creates a new runtime sub-directory with the given name.
+    pub fn subdir(&self, name: impl AsRef<Path>) -> Result<Self> {
+        Self::new(self.join(name))
+    }
+
+    /// Creates a new temporary file inside the runtime directory.
+    ///
+    /// The file name is made of the given prefix, a random part and
+    /// the given suffix, so that concurrent processes never share the
+    /// same file. The file is removed when dropped.
+    pub fn create_temp_file(&self, prefix: &str, suffix: &str) -> Result<RuntimeFile> {
+        let name = format!("{prefix}{}{suffix}", uuid::Uuid::new_v4());
+        let path = self.join(name);
+
+        let mut opts = OpenOptions::new();
+        opts.read(true).write(true).create_new(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            opts.mode(0o600);
+        }
+
+        let file = opts
+            .open(&path)
+            .map_err(|err| Error::CreateRuntimeFileError(err, path.clone()))?;
+
+        Ok(RuntimeFile { path, file })
+    }
+}
+
+impl AsRef<Path> for RuntimeDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// The temporary runtime file.
+///
+/// The file is removed from the filesystem when dropped, unless it
+/// is kept using [`RuntimeFile::keep`].
+#[derive(Debug)]
+pub struct RuntimeFile {
+    path: PathBuf,
+    file: File,
+}
+
+impl RuntimeFile {
+    /// Returns the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the underlying file handle.
+    pub fn as_file(&self) -> &File {
+        &self.file
+    }
+
+    /// Returns the underlying mutable file handle.
+    pub fn as_file_mut(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// Keeps the file on the filesystem and returns its path.
+    pub fn keep(mut self) -> PathBuf {
+        // an empty path prevents the drop from removing the file
+        std::mem::take(&mut self.path)
+    }
+}
+
+impl Drop for RuntimeFile {
+    fn drop(&mut self) {
+        if self.path.as_os_str().is_empty() {
+            return;
+        }
+
+        if let Err(_err) = fs::remove_file(&self.path) {
+            debug!(path = ?self.path, "cannot remove runtime file: {_err}");
+            debug!("{_err:?}");
+        }
+    }
+}
+
+/// Returns the default global runtime directory.
+///
+/// The system temporary directory being shared between users, the
+/// user-specific directory created inside it is refused if it is not
+/// a private directory owned by the current user.
+fn default_global_dir() -> Result<RuntimeDir> {
+    let xdg_runtime_dir = env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute());
+
+    if let Some(dir) = xdg_runtime_dir {
+        return RuntimeDir::new(dir.join("pimalaya").join("email"));
+    }
+
+    let user = env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .map(|user| sanitize(&user))
+        .unwrap_or_else(|_| String::from("default"));
+
+    let dir = env::temp_dir().join(format!("pimalaya-{user}"));
+    create_user_temp_dir(&dir)?;
+    RuntimeDir::new(dir.join("email"))
+}
+
+/// Creates the given directory and its missing parents with
+/// owner-only permissions.
+fn create_private_dir(path: &Path) -> Result<()> {
+    let mut builder = DirBuilder::new();
+    builder.recursive(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+
+    builder
+        .create(path)
+        .map_err(|err| Error::CreateRuntimeDirError(err, path.to_owned()))
+}
+
+/// Creates the given user-specific directory inside the shared
+/// system temporary directory, then checks that it can be trusted.
+///
+/// The directory is checked using its own metadata, without
+/// following symlinks: it must be a directory owned by the current
+/// user, only accessible by them.
+fn create_user_temp_dir(path: &Path) -> Result<()> {
+    let mut builder = DirBuilder::new();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+
+    match builder.create(path) {
+        Err(err) if err.kind() != io::ErrorKind::AlreadyExists => {
+            return Err(Error::CreateRuntimeDirError(err, path.to_owned()));
+        }
+        _ => (),
+    }
+
+    let metadata = fs::symlink_metadata(path)
+        .map_err(|err| Error::CreateRuntimeDirError(err, path.to_owned()))?;
+
+    if !metadata.is_dir() {
+        return Err(Error::UnsafeRuntimeDirError(path.to_owned()));
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        // SAFETY: geteuid is always successful
+        let uid = unsafe { libc::geteuid() };
+
+        if metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+            return Err(Error::UnsafeRuntimeDirError(path.to_owned()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Turns the given account or user name into a valid file name.
+fn sanitize(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            c if c.is_alphanumeric() => c,
+            '.' | '-' | '_' | '@' | '+' => c,
+            _ => '_',
+        })
+        .collect();
+
+    match name.trim_start_matches('.') {
+        "" => String::from("_"),
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write};
+
+    use tempfile::tempdir;
+
+    use super::RuntimeDir;
+
+    #[test]
+    fn temp_file_removed_on_drop() {
+        let root = tempdir().unwrap();
+        let dir = root.path().join("runtime");
+        let runtime_dir = RuntimeDir::new(&dir).unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+
+        let mut file = runtime_dir.create_temp_file("draft-", ".eml").unwrap();
+        file.as_file_mut().write_all(b"draft").unwrap();
+        let path = file.path().to_owned();
+        assert!(path.starts_with(&dir));
+        assert_eq!(fs::read(&path).unwrap(), b"draft");
+
+        drop(file);
+        assert!(!path.exists());
+
+        let file = runtime_dir.create_temp_file("kept-", "").unwrap();
+        let path = file.keep();
+        assert!(path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn existing_dir_permissions_kept() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempdir().unwrap();
+        let dir = root.path().join("custom");
+        fs::create_dir(&dir).unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let runtime_dir = RuntimeDir::account(Some(&dir), "account").unwrap();
+
+        let mode = fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+        let mode = fs::metadata(runtime_dir.path())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn user_temp_dir_checked() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        use super::create_user_temp_dir;
+        use crate::account::Error;
+
+        let root = tempdir().unwrap();
+
+        let dir = root.path().join("new");
+        create_user_temp_dir(&dir).unwrap();
+        let mode = fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        create_user_temp_dir(&dir).unwrap();
+
+        let link = root.path().join("link");
+        symlink(&dir, &link).unwrap();
+        let err = create_user_temp_dir(&link).unwrap_err();
+        assert!(matches!(err, Error::UnsafeRuntimeDirError(_)));
+
+        let file = root.path().join("file");
+        fs::write(&file, "").unwrap();
+        let err = create_user_temp_dir(&file).unwrap_err();
+        assert!(matches!(err, Error::UnsafeRuntimeDirError(_)));
+
+        let shared = root.path().join("shared");
+        fs::create_dir(&shared).unwrap();
+        fs::set_permissions(&shared, fs::Permissions::from_mode(0o777)).unwrap();
+        let err = create_user_temp_dir(&shared).unwrap_err();
+        assert!(matches!(err, Error::UnsafeRuntimeDirError(_)));
+        let mode = fs::metadata(&shared).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o777);
+    }
+}
@@ -40,6 +40,7 @@
     config::{AutoConfig, EmailProvider},
     dns::DnsClient,
 };
+use crate::ErrorKind;
 
 /// The global `Result` alias of the module.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -71,6 +72,28 @@ pub enum Error {
     ParsingEmailAddress(String, #[source] email_address::Error),
 }
 
+impl Error {
+    /// Returns the kind of the error.
+    ///
+    /// See [`ErrorKind`] for the available kinds.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::CreateHttpConnectorError(_) => ErrorKind::Other,
+            Self::GetMxRecordNotFoundError(_)
+            | Self::GetMailconfTxtRecordNotFoundError(_)
+            | Self::GetSrvRecordNotFoundError(_) => ErrorKind::NotFound,
+            Self::LookUpTxtError(_)
+            | Self::LookUpMxError(_)
+            | Self::LookUpSrvError(_)
+            | Self::SendGetRequestError(..) => ErrorKind::Network,
+            Self::GetAutoConfigError(..) | Self::SerdeXmlFailedForAutoConfig(..) => {
+                ErrorKind::Protocol
+            }
+            Self::ParsingEmailAddress(..) => ErrorKind::Config,
+        }
+    }
+}
+
 /// Discover configuration associated to a given email address using
 /// ISP locations then DNS, as described in the Mozilla [wiki].
 ///
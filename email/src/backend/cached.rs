@@ -0,0 +1,416 @@
+//! # Cached backend
+//!
+//! Module dedicated to offline-first backends. A [`CachedBackend`]
+//! decorates a remote backend with the local Maildir cache used by
+//! the account synchronization (see
+//! [`AccountSyncBuilder`](crate::account::sync::AccountSyncBuilder)).
+//!
+//! As long as the remote backend is reachable, features are executed
+//! by the remote backend. As soon as a feature fails because of the
+//! network (see [`ErrorKind::Network`]), the cached backend switches
+//! to offline mode: read features are served from the local cache,
+//! and write features are applied to the local cache. Those changes
+//! are replayed to the remote backend by the next synchronization.
+//!
+//! Envelope identifiers are specific to the backend that returned
+//! them: identifiers returned while offline are local cache
+//! identifiers, and should not be used once back online.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use async_trait::async_trait;
+use tracing::debug;
+
+use super::{context::BackendContextBuilder, Backend, BackendBuilder, Error};
+use crate::{
+    account::config::{AccountConfig, HasAccountConfig},
+    envelope::{
+        get::GetEnvelope,
+        list::{ListEnvelopes, ListEnvelopesOptions},
+        Envelope, Envelopes, Id, SingleId,
+    },
+    flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags, Flags},
+    folder::{list::ListFolders, Folders},
+    maildir::MaildirContextSync,
+    message::{
+        add::AddMessage, copy::CopyMessages, delete::DeleteMessages, get::GetMessages,
+        peek::PeekMessages, r#move::MoveMessages, Messages,
+    },
+    sync::hash::SyncHash,
+    AnyBoxedError, AnyResult, ErrorKind,
+};
+
+/// Execute the given feature using the remote backend, or using the
+/// local cache when offline.
+macro_rules! cached {
+    ($self:ident.$feat:ident($($arg:expr),*)) => {{
+        if let Some(remote) = $self.online_remote() {
+            match remote.$feat($($arg),*).await {
+                Err(err) if is_unreachable(&err) => $self.go_offline(stringify!($feat), &err),
+                res => return res,
+            }
+        }
+
+        $self.local.$feat($($arg),*).await
+    }};
+}
+
+/// Same as `cached!`, except that changes applied to the local cache
+/// are marked as pending synchronization.
+macro_rules! cached_mut {
+    ($self:ident.$feat:ident($($arg:expr),*)) => {{
+        let res = cached!($self.$feat($($arg),*));
+
+        if res.is_ok() && $self.is_offline() {
+            $self.pending_changes.store(true, Ordering::Relaxed);
+        }
+
+        res
+    }};
+}
+
+/// The offline-first cached backend.
+///
+/// See the [module-level documentation](self).
+pub struct CachedBackend<R: BackendContextBuilder> {
+    /// The builder of the remote backend, used to reconnect.
+    remote_builder: BackendBuilder<R>,
+    /// The remote backend, if it could be built.
+    remote: Option<Backend<R::Context>>,
+    /// The backend of the local Maildir cache.
+    local: Backend<MaildirContextSync>,
+    /// Whether the remote backend is considered unreachable.
+    offline: AtomicBool,
+    /// Whether changes have been applied to the local cache while
+    /// offline.
+    pending_changes: AtomicBool,
+}
+
+impl<R> CachedBackend<R>
+where
+    R: BackendContextBuilder + SyncHash + 'static,
+{
+    /// Creates a new cached backend from the given remote backend
+    /// builder.
+    ///
+    /// The local cache is the Maildir synchronization directory of
+    /// the account. If the remote backend cannot be built because of
+    /// the network, the cached backend starts in offline mode.
+    pub async fn new(remote_builder: BackendBuilder<R>) -> AnyResult<Self> {
+        let account_config = remote_builder.account_config.clone();
+
+        let mut local_ctx_builder = remote_builder
+            .ctx_builder
+            .try_to_sync_cache_builder(&account_config)
+            .map_err(Error::BuildCacheBackendError)?;
+        local_ctx_builder.configure().await?;
+
+        let local = BackendBuilder::new(account_config, local_ctx_builder)
+            .build()
+            .await?;
+
+        let remote = match remote_builder.clone().build().await {
+            Ok(remote) => Some(remote),
+            Err(err) if is_unreachable(&err) => {
+                debug!(?err, "remote backend unreachable, starting offline");
+                None
+            }
+            Err(err) => return Err(err),
+        };
+
+        Ok(Self {
+            remote_builder,
+            offline: AtomicBool::new(remote.is_none()),
+            remote,
+            local,
+            pending_changes: AtomicBool::new(false),
+        })
+    }
+
+    /// Try to go back online.
+    ///
+    /// The remote backend is rebuilt if it is dead, or if it could
+    /// not be built in the first place. Returns `true` if the remote
+    /// backend is reachable.
+    pub async fn reconnect(&mut self) -> AnyResult<bool> {
+        let res = match self.remote.as_mut() {
+            Some(remote) => remote.reconnect().await.map(|_| ()),
+            None => match self.remote_builder.clone().build().await {
+                Ok(remote) => {
+                    self.remote = Some(remote);
+                    Ok(())
+                }
+                Err(err) => Err(err),
+            },
+        };
+
+        match res {
+            Ok(()) => {
+                debug!("remote backend reachable, going back online");
+                self.offline.store(false, Ordering::Relaxed);
+                Ok(true)
+            }
+            Err(err) if is_unreachable(&err) => {
+                debug!(?err, "remote backend still unreachable");
+                Ok(false)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl<R: BackendContextBuilder> CachedBackend<R> {
+    /// Returns `true` if the remote backend is considered
+    /// unreachable.
+    pub fn is_offline(&self) -> bool {
+        self.offline.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if changes have been applied to the local cache
+    /// while offline.
+    ///
+    /// Those changes are replayed to the remote backend by the next
+    /// synchronization, see
+    /// [`AccountSyncBuilder`](crate::account::sync::AccountSyncBuilder).
+    pub fn has_pending_changes(&self) -> bool {
+        self.pending_changes.load(Ordering::Relaxed)
+    }
+
+    /// Marks pending changes as synchronized.
+    ///
+    /// Should be called after a successful synchronization.
+    pub fn clear_pending_changes(&self) {
+        self.pending_changes.store(false, Ordering::Relaxed)
+    }
+
+    /// Returns the backend of the local Maildir cache.
+    pub fn local(&self) -> &Backend<MaildirContextSync> {
+        &self.local
+    }
+
+    /// Returns the remote backend, if it could be built.
+    pub fn remote(&self) -> Option<&Backend<R::Context>> {
+        self.remote.as_ref()
+    }
+
+    fn online_remote(&self) -> Option<&Backend<R::Context>> {
+        if self.is_offline() {
+            None
+        } else {
+            self.remote.as_ref()
+        }
+    }
+
+    fn go_offline(&self, feat: &str, err: &AnyBoxedError) {
+        debug!(
+            feat,
+            ?err,
+            "remote backend unreachable, switching to local cache"
+        );
+        self.offline.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Returns `true` if the given error means that the remote backend
+/// cannot be reached.
+fn is_unreachable(err: &AnyBoxedError) -> bool {
+    err.kind() == ErrorKind::Network
+}
+
+impl<R: BackendContextBuilder> HasAccountConfig for CachedBackend<R> {
+    fn account_config(&self) -> &AccountConfig {
+        &self.local.account_config
+    }
+}
+
+#[async_trait]
+impl<R: BackendContextBuilder> ListFolders for CachedBackend<R> {
+    async fn list_folders(&self) -> AnyResult<Folders> {
+        cached!(self.list_folders())
+    }
+}
+
+#[async_trait]
+impl<R: BackendContextBuilder> GetEnvelope for CachedBackend<R> {
+    async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope> {
+        cached!(self.get_envelope(folder, id))
+    }
+}
+
+#[async_trait]
+impl<R: BackendContextBuilder> ListEnvelopes for CachedBackend<R> {
+    async fn list_envelopes(
+        &self,
+        folder: &str,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<Envelopes> {
+        cached!(self.list_envelopes(folder, opts.clone()))
+    }
+}
+
+#[async_trait]
+impl<R: BackendContextBuilder> AddFlags for CachedBackend<R> {
+    async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        cached_mut!(self.add_flags(folder, id, flags))
+    }
+}
+
+#[async_trait]
+impl<R: BackendContextBuilder> SetFlags for CachedBackend<R> {
+    async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        cached_mut!(self.set_flags(folder, id, flags))
+    }
+}
+
+#[async_trait]
+impl<R: BackendContextBuilder> RemoveFlags for CachedBackend<R> {
+    async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        cached_mut!(self.remove_flags(folder, id, flags))
+    }
+}
+
+#[async_trait]
+impl<R: BackendContextBuilder> AddMessage for CachedBackend<R> {
+    async fn add_message_with_flags(
+        &self,
+        folder: &str,
+        msg: &[u8],
+        flags: &Flags,
+    ) -> AnyResult<SingleId> {
+        cached_mut!(self.add_message_with_flags(folder, msg, flags))
+    }
+}
+
+#[async_trait]
+impl<R: BackendContextBuilder> PeekMessages for CachedBackend<R> {
+    async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        cached!(self.peek_messages(folder, id))
+    }
+}
+
+#[async_trait]
+impl<R: BackendContextBuilder> GetMessages for CachedBackend<R> {
+    async fn get_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        cached!(self.get_messages(folder, id))
+    }
+}
+
+#[async_trait]
+impl<R: BackendContextBuilder> CopyMessages for CachedBackend<R> {
+    async fn copy_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
+        cached_mut!(self.copy_messages(from_folder, to_folder, id))
+    }
+}
+
+#[async_trait]
+impl<R: BackendContextBuilder> MoveMessages for CachedBackend<R> {
+    async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
+        cached_mut!(self.move_messages(from_folder, to_folder, id))
+    }
+}
+
+#[async_trait]
+impl<R: BackendContextBuilder> DeleteMessages for CachedBackend<R> {
+    async fn delete_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
+        cached_mut!(self.delete_messages(folder, id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{any::Any, hash::DefaultHasher, sync::Arc};
+
+    use async_trait::async_trait;
+    use thiserror::Error;
+
+    use super::CachedBackend;
+    use crate::{
+        account::{config::AccountConfig, sync::config::SyncConfig},
+        backend::{
+            context::{BackendContext, BackendContextBuilder},
+            BackendBuilder,
+        },
+        envelope::list::{ListEnvelopes, ListEnvelopesOptions},
+        folder::add::AddFolder,
+        message::add::AddMessage,
+        sync::hash::SyncHash,
+        AnyBoxedError, AnyError, AnyResult, ErrorKind,
+    };
+
+    #[derive(Debug, Error)]
+    #[error("cannot connect to remote backend")]
+    struct UnreachableError;
+
+    impl AnyError for UnreachableError {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Network
+        }
+    }
+
+    impl From<UnreachableError> for AnyBoxedError {
+        fn from(err: UnreachableError) -> Self {
+            Box::new(err)
+        }
+    }
+
+    struct UnreachableContext;
+
+    impl BackendContext for UnreachableContext {}
+
+    #[derive(Clone)]
+    struct UnreachableContextBuilder;
+
+    impl SyncHash for UnreachableContextBuilder {
+        fn sync_hash(&self, _: &mut DefaultHasher) {}
+    }
+
+    #[async_trait]
+    impl BackendContextBuilder for UnreachableContextBuilder {
+        type Context = UnreachableContext;
+
+        async fn build(self) -> AnyResult<Self::Context> {
+            Err(UnreachableError.into())
+        }
+    }
+
+    #[tokio::test]
+    async fn serve_from_cache_when_offline() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let account_config = Arc::new(AccountConfig {
+            sync: Some(SyncConfig {
+                dir: Some(dir.path().to_owned()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+
+        let remote_builder = BackendBuilder::new(account_config, UnreachableContextBuilder);
+        let mut backend = CachedBackend::new(remote_builder).await.unwrap();
+
+        assert!(backend.is_offline());
+        assert!(!backend.has_pending_changes());
+
+        backend.local().add_folder("INBOX").await.unwrap();
+        backend
+            .add_message("INBOX", b"Subject: offline\r\n\r\nHello!\r\n")
+            .await
+            .unwrap();
+
+        assert!(backend.has_pending_changes());
+
+        let envelopes = backend
+            .list_envelopes("INBOX", ListEnvelopesOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(envelopes.len(), 1);
+        assert_eq!(envelopes[0].subject, "offline");
+
+        assert!(!backend.reconnect().await.unwrap());
+        assert!(backend.is_offline());
+    }
+}
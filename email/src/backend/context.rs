@@ -15,7 +15,7 @@
 use crate::envelope::watch::WatchEnvelopes;
 use crate::{
     envelope::{get::GetEnvelope, list::ListEnvelopes},
-    flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags},
+    flag::{add::AddFlags, list::ListFlags, remove::RemoveFlags, set::SetFlags},
     folder::{
         add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders,
         purge::PurgeFolder,
@@ -91,6 +91,7 @@ async fn configure(&mut self) -> AnyResult<()> {
     feature!(AddFlags);
     feature!(SetFlags);
     feature!(RemoveFlags);
+    feature!(ListFlags);
     feature!(AddMessage);
     feature!(SendMessage);
     feature!(PeekMessages);
@@ -156,6 +157,7 @@ fn try_to_sync_cache_builder(
             signature: account_config.signature.clone(),
             signature_delim: account_config.signature_delim.clone(),
             downloads_dir: account_config.downloads_dir.clone(),
+            runtime_dir: account_config.runtime_dir.clone(),
             folder: account_config.folder.clone(),
             envelope: account_config.envelope.clone(),
             flag: account_config.flag.clone(),
@@ -169,6 +171,9 @@ fn try_to_sync_cache_builder(
         let config = Arc::new(MaildirConfig {
             root_dir,
             maildirpp: false,
+            layout: None,
+            uid_db: false,
+            envelopes: Default::default(),
         });
 
         let ctx = MaildirContextBuilder::new(account_config.clone(), config);
@@ -0,0 +1,337 @@
+//! # Dynamic backend
+//!
+//! This module contains the [`DynBackendContextBuilder`], a backend
+//! context builder composed of type-erased subcontext builders. It
+//! allows applications to choose the backends to compose at runtime
+//! (for example a Maildir or IMAP store with a SMTP or Sendmail
+//! sender), without defining a context per combination.
+//!
+//! See [`BackendBuilder::with_context_dyn`].
+
+use std::{any::Any, sync::Arc};
+
+use async_trait::async_trait;
+use futures::future::try_join_all;
+use paste::paste;
+use tracing::debug;
+
+use super::{
+    context::{BackendContext, BackendContextBuilder},
+    feature::{BackendFeature, CheckUp},
+    BackendBuilder,
+};
+#[cfg(feature = "thread")]
+use crate::envelope::thread::ThreadEnvelopes;
+#[cfg(feature = "watch")]
+use crate::envelope::watch::WatchEnvelopes;
+use crate::{
+    account::config::AccountConfig,
+    envelope::{get::GetEnvelope, list::ListEnvelopes},
+    flag::{add::AddFlags, list::ListFlags, remove::RemoveFlags, set::SetFlags},
+    folder::{
+        add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders,
+        purge::PurgeFolder,
+    },
+    message::{
+        add::AddMessage, copy::CopyMessages, delete::DeleteMessages, get::GetMessages,
+        peek::PeekMessages, r#move::MoveMessages, remove::RemoveMessages, send::SendMessage,
+    },
+    AnyResult,
+};
+
+/// The dynamic backend.
+pub type DynBackend = super::Backend<DynBackendContext>;
+
+/// The dynamic backend context.
+///
+/// Holds the type-erased subcontexts, in the order their builders
+/// have been added to the [`DynBackendContextBuilder`].
+pub struct DynBackendContext {
+    ctxs: Vec<Box<dyn Any + Send + Sync>>,
+}
+
+impl DynBackendContext {
+    /// Get the first subcontext matching the given type.
+    pub fn get<C: BackendContext + 'static>(&self) -> Option<&C> {
+        self.ctxs.iter().find_map(|ctx| ctx.downcast_ref())
+    }
+
+    fn get_at<C: BackendContext + 'static>(&self, index: usize) -> Option<&C> {
+        self.ctxs.get(index)?.downcast_ref()
+    }
+}
+
+impl BackendContext for DynBackendContext {}
+
+/// Object-safe version of [`BackendContextBuilder`].
+#[async_trait]
+trait AnyBackendContextBuilder: Send + Sync {
+    fn dyn_clone(&self) -> Box<dyn AnyBackendContextBuilder>;
+
+    fn dyn_check_configuration(&self) -> AnyResult<()>;
+
+    async fn dyn_configure(&mut self) -> AnyResult<()>;
+
+    async fn dyn_build(self: Box<Self>) -> AnyResult<Box<dyn Any + Send + Sync>>;
+}
+
+#[async_trait]
+impl<CB> AnyBackendContextBuilder for CB
+where
+    CB: BackendContextBuilder + 'static,
+    CB::Context: 'static,
+{
+    fn dyn_clone(&self) -> Box<dyn AnyBackendContextBuilder> {
+        Box::new(self.clone())
+    }
+
+    fn dyn_check_configuration(&self) -> AnyResult<()> {
+        self.check_configuration()
+    }
+
+    async fn dyn_configure(&mut self) -> AnyResult<()> {
+        self.configure().await
+    }
+
+    async fn dyn_build(self: Box<Self>) -> AnyResult<Box<dyn Any + Send + Sync>> {
+        Ok(Box::new((*self).build().await?))
+    }
+}
+
+/// Macro for mapping a feature of a subcontext builder to the
+/// dynamic context builder.
+macro_rules! map_feature {
+    ($self:ident, $cb:ident, $index:ident, $feat:ty) => {
+        paste! {
+            if $self.[<$feat:snake>].is_none() {
+                if let Some(f) = $cb.[<$feat:snake>]() {
+                    debug!(index = $index, "using dynamic subcontext for {}", stringify!($feat));
+                    $self.[<$feat:snake>] = Some(Arc::new(move |ctx: &DynBackendContext| {
+                        f(ctx.get_at::<CB::Context>($index)?)
+                    }));
+                }
+            }
+        }
+    };
+}
+
+/// Macro for defining [`BackendContextBuilder`] features of the
+/// dynamic context builder.
+macro_rules! feature {
+    ($feat:ty) => {
+        paste! {
+            fn [<$feat:snake>](&self) -> Option<BackendFeature<Self::Context, dyn $feat>> {
+                self.[<$feat:snake>].clone()
+            }
+        }
+    };
+}
+
+/// The dynamic backend context builder.
+///
+/// Composes type-erased subcontext builders. Each feature is taken
+/// from the first subcontext builder supporting it, in the order
+/// subcontext builders have been added.
+#[derive(Default)]
+pub struct DynBackendContextBuilder {
+    builders: Vec<Box<dyn AnyBackendContextBuilder>>,
+
+    check_up: Option<BackendFeature<DynBackendContext, dyn CheckUp>>,
+
+    add_folder: Option<BackendFeature<DynBackendContext, dyn AddFolder>>,
+    list_folders: Option<BackendFeature<DynBackendContext, dyn ListFolders>>,
+    expunge_folder: Option<BackendFeature<DynBackendContext, dyn ExpungeFolder>>,
+    purge_folder: Option<BackendFeature<DynBackendContext, dyn PurgeFolder>>,
+    delete_folder: Option<BackendFeature<DynBackendContext, dyn DeleteFolder>>,
+
+    get_envelope: Option<BackendFeature<DynBackendContext, dyn GetEnvelope>>,
+    list_envelopes: Option<BackendFeature<DynBackendContext, dyn ListEnvelopes>>,
+    #[cfg(feature = "thread")]
+    thread_envelopes: Option<BackendFeature<DynBackendContext, dyn ThreadEnvelopes>>,
+    #[cfg(feature = "watch")]
+    watch_envelopes: Option<BackendFeature<DynBackendContext, dyn WatchEnvelopes>>,
+
+    add_flags: Option<BackendFeature<DynBackendContext, dyn AddFlags>>,
+    set_flags: Option<BackendFeature<DynBackendContext, dyn SetFlags>>,
+    remove_flags: Option<BackendFeature<DynBackendContext, dyn RemoveFlags>>,
+    list_flags: Option<BackendFeature<DynBackendContext, dyn ListFlags>>,
+
+    add_message: Option<BackendFeature<DynBackendContext, dyn AddMessage>>,
+    send_message: Option<BackendFeature<DynBackendContext, dyn SendMessage>>,
+    peek_messages: Option<BackendFeature<DynBackendContext, dyn PeekMessages>>,
+    get_messages: Option<BackendFeature<DynBackendContext, dyn GetMessages>>,
+    copy_messages: Option<BackendFeature<DynBackendContext, dyn CopyMessages>>,
+    move_messages: Option<BackendFeature<DynBackendContext, dyn MoveMessages>>,
+    delete_messages: Option<BackendFeature<DynBackendContext, dyn DeleteMessages>>,
+    remove_messages: Option<BackendFeature<DynBackendContext, dyn RemoveMessages>>,
+}
+
+impl DynBackendContextBuilder {
+    /// Create a new, empty dynamic context builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the given subcontext builder.
+    ///
+    /// Features supported by the subcontext builder and not already
+    /// supported by a previously added one are wired.
+    pub fn add_context<CB>(&mut self, cb: CB)
+    where
+        CB: BackendContextBuilder + 'static,
+        CB::Context: 'static,
+    {
+        let index = self.builders.len();
+
+        map_feature!(self, cb, index, CheckUp);
+        map_feature!(self, cb, index, AddFolder);
+        map_feature!(self, cb, index, ListFolders);
+        map_feature!(self, cb, index, ExpungeFolder);
+        map_feature!(self, cb, index, PurgeFolder);
+        map_feature!(self, cb, index, DeleteFolder);
+        map_feature!(self, cb, index, GetEnvelope);
+        map_feature!(self, cb, index, ListEnvelopes);
+        #[cfg(feature = "thread")]
+        map_feature!(self, cb, index, ThreadEnvelopes);
+        #[cfg(feature = "watch")]
+        map_feature!(self, cb, index, WatchEnvelopes);
+        map_feature!(self, cb, index, AddFlags);
+        map_feature!(self, cb, index, SetFlags);
+        map_feature!(self, cb, index, RemoveFlags);
+        map_feature!(self, cb, index, ListFlags);
+        map_feature!(self, cb, index, AddMessage);
+        map_feature!(self, cb, index, SendMessage);
+        map_feature!(self, cb, index, PeekMessages);
+        map_feature!(self, cb, index, GetMessages);
+        map_feature!(self, cb, index, CopyMessages);
+        map_feature!(self, cb, index, MoveMessages);
+        map_feature!(self, cb, index, DeleteMessages);
+        map_feature!(self, cb, index, RemoveMessages);
+
+        self.builders.push(Box::new(cb));
+    }
+
+    /// Add the given subcontext builder, using the builder pattern.
+    pub fn with_context<CB>(mut self, cb: CB) -> Self
+    where
+        CB: BackendContextBuilder + 'static,
+        CB::Context: 'static,
+    {
+        self.add_context(cb);
+        self
+    }
+}
+
+impl Clone for DynBackendContextBuilder {
+    fn clone(&self) -> Self {
+        Self {
+            builders: self.builders.iter().map(|cb| cb.dyn_clone()).collect(),
+
+            check_up: self.check_up.clone(),
+
+            add_folder: self.add_folder.clone(),
+            list_folders: self.list_folders.clone(),
+            expunge_folder: self.expunge_folder.clone(),
+            purge_folder: self.purge_folder.clone(),
+            delete_folder: self.delete_folder.clone(),
+
+            get_envelope: self.get_envelope.clone(),
+            list_envelopes: self.list_envelopes.clone(),
+            #[cfg(feature = "thread")]
+            thread_envelopes: self.thread_envelopes.clone(),
+            #[cfg(feature = "watch")]
+            watch_envelopes: self.watch_envelopes.clone(),
+
+            add_flags: self.add_flags.clone(),
+            set_flags: self.set_flags.clone(),
+            remove_flags: self.remove_flags.clone(),
+            list_flags: self.list_flags.clone(),
+
+            add_message: self.add_message.clone(),
+            send_message: self.send_message.clone(),
+            peek_messages: self.peek_messages.clone(),
+            get_messages: self.get_messages.clone(),
+            copy_messages: self.copy_messages.clone(),
+            move_messages: self.move_messages.clone(),
+            delete_messages: self.delete_messages.clone(),
+            remove_messages: self.remove_messages.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl BackendContextBuilder for DynBackendContextBuilder {
+    type Context = DynBackendContext;
+
+    fn check_configuration(&self) -> AnyResult<()> {
+        for cb in &self.builders {
+            cb.dyn_check_configuration()?;
+        }
+
+        Ok(())
+    }
+
+    async fn configure(&mut self) -> AnyResult<()> {
+        for cb in &mut self.builders {
+            cb.dyn_configure().await?;
+        }
+
+        Ok(())
+    }
+
+    feature!(CheckUp);
+    feature!(AddFolder);
+    feature!(ListFolders);
+    feature!(ExpungeFolder);
+    feature!(PurgeFolder);
+    feature!(DeleteFolder);
+    feature!(GetEnvelope);
+    feature!(ListEnvelopes);
+    #[cfg(feature = "thread")]
+    feature!(ThreadEnvelopes);
+    #[cfg(feature = "watch")]
+    feature!(WatchEnvelopes);
+    feature!(AddFlags);
+    feature!(SetFlags);
+    feature!(RemoveFlags);
+    feature!(ListFlags);
+    feature!(AddMessage);
+    feature!(SendMessage);
+    feature!(PeekMessages);
+    feature!(GetMessages);
+    feature!(CopyMessages);
+    feature!(MoveMessages);
+    feature!(DeleteMessages);
+    feature!(RemoveMessages);
+
+    async fn build(self) -> AnyResult<Self::Context> {
+        debug!("building {} dynamic subcontexts", self.builders.len());
+        let ctxs = try_join_all(self.builders.into_iter().map(|cb| cb.dyn_build())).await?;
+        Ok(DynBackendContext { ctxs })
+    }
+}
+
+impl BackendBuilder<DynBackendContextBuilder> {
+    /// Create a new dynamic backend builder, without any subcontext.
+    ///
+    /// Subcontexts are added at runtime with
+    /// [`BackendBuilder::with_context_dyn`].
+    pub fn new_dyn(account_config: Arc<AccountConfig>) -> Self {
+        Self::new(account_config, DynBackendContextBuilder::new())
+    }
+
+    /// Add the given subcontext builder to the dynamic context
+    /// builder, using the builder pattern.
+    ///
+    /// Each feature is taken from the first subcontext builder
+    /// supporting it, so the store backend should usually be added
+    /// before the sender backend.
+    pub fn with_context_dyn<CB>(mut self, cb: CB) -> Self
+    where
+        CB: BackendContextBuilder + 'static,
+        CB::Context: 'static,
+    {
+        self.ctx_builder.add_context(cb);
+        self
+    }
+}
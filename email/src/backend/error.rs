@@ -2,7 +2,7 @@
 
 use thiserror::Error;
 
-use crate::{AnyBoxedError, AnyError};
+use crate::{AnyBoxedError, AnyError, ErrorKind};
 
 /// The global `Result` alias of the module.
 pub type Result<T> = result::Result<T, Error>;
@@ -34,6 +34,8 @@ pub enum Error {
     SetFlagsNotAvailableError,
     #[error("cannot remove flag(s): feature not available, or backend configuration for this functionality is not set")]
     RemoveFlagsNotAvailableError,
+    #[error("cannot list flags: feature not available, or backend configuration for this functionality is not set")]
+    ListFlagsNotAvailableError,
     #[error("cannot add message: feature not available, or backend configuration for this functionality is not set")]
     AddMessageNotAvailableError,
     #[error("cannot add message with flags: feature not available, or backend configuration for this functionality is not set")]
@@ -52,12 +54,53 @@ pub enum Error {
     DeleteMessagesNotAvailableError,
     #[error("cannot remove messages: feature not available, or backend configuration for this functionality is not set")]
     RemoveMessagesNotAvailableError,
+    #[error("cannot reconnect backend: context builder not available")]
+    ReconnectNotAvailableError,
+    #[cfg(feature = "sync")]
+    #[error("cannot build local cache backend")]
+    BuildCacheBackendError(#[source] crate::account::Error),
+
+    #[error("cannot add account {0} to group: name cannot be empty or contain {sep:?}", sep = super::group::GROUP_ID_SEPARATOR)]
+    AddGroupAccountInvalidNameError(String),
+    #[error("cannot add account {0} to group: account already exists")]
+    AddGroupAccountAlreadyExistsError(String),
+    #[error("cannot find account matching group envelope id {0}")]
+    FindGroupAccountError(String),
+    #[error("cannot list envelopes of account {1}")]
+    ListGroupEnvelopesError(#[source] AnyBoxedError, String),
+    #[cfg(feature = "watch")]
+    #[error("cannot watch envelopes of account {1}")]
+    WatchGroupEnvelopesError(#[source] AnyBoxedError, String),
+}
+
+impl Error {
+    /// Returns the kind of the error.
+    ///
+    /// See [`ErrorKind`] for the available kinds.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::FindGroupAccountError(_) => ErrorKind::NotFound,
+            Self::AddGroupAccountAlreadyExistsError(_) => ErrorKind::Conflict,
+            Self::ListGroupEnvelopesError(err, _) => err.kind(),
+            #[cfg(feature = "sync")]
+            Self::BuildCacheBackendError(err) => err.kind(),
+            #[cfg(feature = "watch")]
+            Self::WatchGroupEnvelopesError(err, _) => err.kind(),
+            // NOTE: other variants are about features not available
+            // for the current backend configuration
+            _ => ErrorKind::Config,
+        }
+    }
 }
 
 impl AnyError for Error {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn kind(&self) -> ErrorKind {
+        Error::kind(self)
+    }
 }
 
 impl From<Error> for AnyBoxedError {
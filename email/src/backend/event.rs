@@ -0,0 +1,236 @@
+//! # Backend event
+//!
+//! Module dedicated to backend operation events. When a
+//! [`BackendEventBus`] is given to the
+//! [`BackendBuilder`](super::BackendBuilder), the built
+//! [`Backend`](super::Backend) emits a [`BackendEvent`] when each
+//! operation starts, succeeds or fails.
+//!
+//! Applications can subscribe to the bus in order to implement
+//! activity indicators, logging or offline detection without
+//! wrapping each backend call.
+
+use std::{
+    fmt,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::sync::broadcast::{self, Receiver, Sender};
+use tracing::trace;
+
+use crate::{AnyResult, ErrorKind};
+
+/// The default capacity of the event bus.
+pub const DEFAULT_EVENT_BUS_CAPACITY: usize = 64;
+
+/// The backend event.
+///
+/// Operations are identified by a unique id, so that events of
+/// operations running concurrently can be matched together.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BackendEvent {
+    /// The operation started.
+    OperationStarted {
+        /// The unique id of the operation.
+        id: u64,
+        /// The name of the operation, for example `list_envelopes`.
+        name: &'static str,
+    },
+
+    /// The operation succeeded.
+    OperationSucceeded {
+        /// The unique id of the operation.
+        id: u64,
+        /// The name of the operation, for example `list_envelopes`.
+        name: &'static str,
+        /// The time spent by the operation.
+        duration: Duration,
+    },
+
+    /// The operation failed.
+    OperationFailed {
+        /// The unique id of the operation.
+        id: u64,
+        /// The name of the operation, for example `list_envelopes`.
+        name: &'static str,
+        /// The time spent by the operation.
+        duration: Duration,
+        /// The kind of the error, useful to detect offline states
+        /// (see [`ErrorKind::Network`]).
+        kind: ErrorKind,
+        /// The human-readable description of the error.
+        error: String,
+    },
+}
+
+impl BackendEvent {
+    /// Returns the unique id of the operation.
+    pub fn id(&self) -> u64 {
+        match self {
+            Self::OperationStarted { id, .. }
+            | Self::OperationSucceeded { id, .. }
+            | Self::OperationFailed { id, .. } => *id,
+        }
+    }
+
+    /// Returns the name of the operation.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::OperationStarted { name, .. }
+            | Self::OperationSucceeded { name, .. }
+            | Self::OperationFailed { name, .. } => name,
+        }
+    }
+}
+
+impl fmt::Display for BackendEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OperationStarted { id, name } => write!(f, "Started {name} #{id}"),
+            Self::OperationSucceeded { id, name, duration } => {
+                write!(f, "Succeeded {name} #{id} in {duration:?}")
+            }
+            Self::OperationFailed {
+                id,
+                name,
+                duration,
+                error,
+                ..
+            } => write!(f, "Failed {name} #{id} in {duration:?}: {error}"),
+        }
+    }
+}
+
+/// The backend event bus.
+///
+/// Wrapper around a tokio broadcast channel. The bus can be cloned
+/// and shared between multiple backends, operation ids stay unique
+/// across them.
+///
+/// Events are dropped when there is no subscriber. Subscribers that
+/// do not keep up with the bus lose the oldest events (see
+/// [`broadcast::error::RecvError::Lagged`]).
+#[derive(Clone)]
+pub struct BackendEventBus {
+    sender: Sender<BackendEvent>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl BackendEventBus {
+    /// Creates a new event bus able to hold the given number of
+    /// events per subscriber.
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+
+        Self {
+            sender,
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// Subscribes to the bus.
+    ///
+    /// The receiver gets events emitted after the subscription.
+    pub fn subscribe(&self) -> Receiver<BackendEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Emits the given event.
+    pub fn emit(&self, event: BackendEvent) {
+        trace!(%event, "emit backend event");
+        // an error only means that there is no subscriber
+        let _ = self.sender.send(event);
+    }
+
+    /// Runs the given operation, emitting its start and its outcome.
+    pub(crate) async fn observe<T>(
+        &self,
+        name: &'static str,
+        f: impl Future<Output = AnyResult<T>>,
+    ) -> AnyResult<T> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.emit(BackendEvent::OperationStarted { id, name });
+
+        let start = Instant::now();
+        let res = f.await;
+        let duration = start.elapsed();
+
+        match &res {
+            Ok(_) => self.emit(BackendEvent::OperationSucceeded { id, name, duration }),
+            Err(err) => self.emit(BackendEvent::OperationFailed {
+                id,
+                name,
+                duration,
+                kind: err.kind(),
+                error: err.to_string(),
+            }),
+        }
+
+        res
+    }
+}
+
+impl Default for BackendEventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_BUS_CAPACITY)
+    }
+}
+
+impl fmt::Debug for BackendEventBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackendEventBus")
+            .field("subscribers", &self.sender.receiver_count())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BackendEvent, BackendEventBus};
+    use crate::{backend::Error, ErrorKind};
+
+    #[tokio::test]
+    async fn observe_operations() {
+        let bus = BackendEventBus::default();
+        let mut events = bus.subscribe();
+
+        bus.observe("list_folders", async { Ok(()) }).await.unwrap();
+        bus.observe::<()>("add_folder", async {
+            Err(Error::AddFolderNotAvailableError.into())
+        })
+        .await
+        .unwrap_err();
+
+        assert_eq!(
+            events.recv().await.unwrap(),
+            BackendEvent::OperationStarted {
+                id: 1,
+                name: "list_folders"
+            }
+        );
+
+        let event = events.recv().await.unwrap();
+        assert!(matches!(
+            event,
+            BackendEvent::OperationSucceeded { id: 1, .. }
+        ));
+
+        let event = events.recv().await.unwrap();
+        assert_eq!((event.id(), event.name()), (2, "add_folder"));
+
+        let event = events.recv().await.unwrap();
+        assert!(matches!(
+            event,
+            BackendEvent::OperationFailed {
+                id: 2,
+                kind: ErrorKind::Config,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,371 @@
+//! # Account group
+//!
+//! Module dedicated to account groups. An [`AccountGroup`] aggregates
+//! the backends of several accounts, and exposes them as a single
+//! backend: envelopes are listed from all accounts at once, merged,
+//! sorted and paginated, and folders can be watched across all
+//! accounts. This allows clients to build unified views (like a
+//! unified inbox) without re-implementing the merge logic.
+//!
+//! Envelope identifiers are prefixed with the name of the account
+//! they belong to, separated by [`GROUP_ID_SEPARATOR`]. Use
+//! [`AccountGroup::split_id`] to find back the account backend and
+//! the original identifier.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::future::try_join_all;
+#[cfg(feature = "watch")]
+use tokio::sync::oneshot::{self, Receiver, Sender};
+use tracing::{debug, info};
+
+use super::{Error, Result};
+#[cfg(feature = "watch")]
+use crate::envelope::watch::WatchEnvelopes;
+use crate::{
+    envelope::{
+        list::{ListEnvelopes, ListEnvelopesOptions},
+        Envelopes,
+    },
+    AnyResult,
+};
+
+/// The separator between the account name and the envelope
+/// identifier of group envelope identifiers.
+pub const GROUP_ID_SEPARATOR: char = ':';
+
+/// The backend features required by an account of a group.
+#[cfg(not(feature = "watch"))]
+pub trait AccountGroupBackend: ListEnvelopes {}
+
+#[cfg(not(feature = "watch"))]
+impl<T: ListEnvelopes> AccountGroupBackend for T {}
+
+/// The backend features required by an account of a group.
+#[cfg(feature = "watch")]
+pub trait AccountGroupBackend: ListEnvelopes + WatchEnvelopes {}
+
+#[cfg(feature = "watch")]
+impl<T: ListEnvelopes + WatchEnvelopes> AccountGroupBackend for T {}
+
+/// An account of a group.
+#[derive(Clone)]
+struct AccountGroupMember {
+    name: String,
+    backend: Arc<dyn AccountGroupBackend>,
+}
+
+/// The account group.
+///
+/// Accounts are kept in insertion order, which is used to break ties
+/// when sorting merged envelopes.
+#[derive(Clone, Default)]
+pub struct AccountGroup {
+    accounts: Vec<AccountGroupMember>,
+}
+
+impl AccountGroup {
+    /// Creates a new empty account group.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the backend of the given account to the group.
+    ///
+    /// The account name must be unique within the group, and must not
+    /// contain the [`GROUP_ID_SEPARATOR`].
+    pub fn add_account(
+        &mut self,
+        name: impl ToString,
+        backend: Arc<dyn AccountGroupBackend>,
+    ) -> Result<()> {
+        let name = name.to_string();
+
+        if name.is_empty() || name.contains(GROUP_ID_SEPARATOR) {
+            return Err(Error::AddGroupAccountInvalidNameError(name));
+        }
+
+        if self.get_account(&name).is_some() {
+            return Err(Error::AddGroupAccountAlreadyExistsError(name));
+        }
+
+        self.accounts.push(AccountGroupMember { name, backend });
+        Ok(())
+    }
+
+    /// Adds the backend of the given account to the group, using the
+    /// builder pattern.
+    pub fn with_account(
+        mut self,
+        name: impl ToString,
+        backend: Arc<dyn AccountGroupBackend>,
+    ) -> Result<Self> {
+        self.add_account(name, backend)?;
+        Ok(self)
+    }
+
+    /// Returns the names of the accounts of the group.
+    pub fn account_names(&self) -> impl Iterator<Item = &str> {
+        self.accounts.iter().map(|account| account.name.as_str())
+    }
+
+    /// Returns the backend of the given account.
+    pub fn get_account(&self, name: &str) -> Option<&Arc<dyn AccountGroupBackend>> {
+        self.accounts
+            .iter()
+            .find(|account| account.name == name)
+            .map(|account| &account.backend)
+    }
+
+    /// Splits the given group envelope identifier into the backend of
+    /// its account and the original envelope identifier.
+    pub fn split_id<'a>(&self, id: &'a str) -> Result<(&Arc<dyn AccountGroupBackend>, &'a str)> {
+        id.split_once(GROUP_ID_SEPARATOR)
+            .and_then(|(name, id)| Some((self.get_account(name)?, id)))
+            .ok_or_else(|| Error::FindGroupAccountError(id.to_owned()))
+    }
+}
+
+/// Builds the group envelope identifier of the given account envelope
+/// identifier.
+pub fn group_id(account: &str, id: &str) -> String {
+    format!("{account}{GROUP_ID_SEPARATOR}{id}")
+}
+
+#[async_trait]
+impl ListEnvelopes for AccountGroup {
+    /// List envelopes of the given folder from all accounts.
+    ///
+    /// Each account is asked for all envelopes up to the end of the
+    /// requested page, so that the merged page is exact. Accounts are
+    /// queried concurrently, and the first failure aborts the whole
+    /// listing.
+    async fn list_envelopes(
+        &self,
+        folder: &str,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<Envelopes> {
+        info!("listing envelopes from folder {folder} of account group");
+
+        let account_opts = ListEnvelopesOptions {
+            page: 0,
+            page_size: (opts.page + 1) * opts.page_size,
+            ..opts.clone()
+        };
+
+        let envelopes = try_join_all(self.accounts.iter().map(|account| {
+            let opts = account_opts.clone();
+            async move {
+                let envelopes = account
+                    .backend
+                    .list_envelopes(folder, opts)
+                    .await
+                    .map_err(|err| Error::ListGroupEnvelopesError(err, account.name.clone()))?;
+                debug!(
+                    account = account.name.as_str(),
+                    "found {} envelopes",
+                    envelopes.len()
+                );
+
+                let envelopes = envelopes.into_iter().map(|mut envelope| {
+                    envelope.id = group_id(&account.name, &envelope.id);
+                    envelope
+                });
+
+                Result::Ok(envelopes)
+            }
+        }))
+        .await?;
+
+        let mut envelopes: Envelopes = envelopes.into_iter().flatten().collect();
+        opts.sort_envelopes(&mut envelopes);
+
+        let page_begin = (opts.page * opts.page_size).min(envelopes.len());
+        let page_end = envelopes.len().min(if opts.page_size == 0 {
+            envelopes.len()
+        } else {
+            page_begin + opts.page_size
+        });
+
+        *envelopes = envelopes[page_begin..page_end].into();
+
+        Ok(envelopes)
+    }
+}
+
+#[cfg(feature = "watch")]
+#[async_trait]
+impl WatchEnvelopes for AccountGroup {
+    /// Watch the given folder of all accounts.
+    ///
+    /// Each account runs its own watcher, which executes its own
+    /// hooks. A shutdown request is forwarded to all watchers, and
+    /// the shutdown is signaled once they all stopped. The first
+    /// failure stops all watchers.
+    async fn watch_envelopes(
+        &self,
+        folder: &str,
+        wait_for_shutdown_request: Receiver<()>,
+        shutdown: Sender<()>,
+    ) -> AnyResult<()> {
+        info!("watching folder {folder} of account group");
+
+        let mut shutdown_requests = Vec::with_capacity(self.accounts.len());
+        let mut shutdowns = Vec::with_capacity(self.accounts.len());
+
+        let watchers = self
+            .accounts
+            .iter()
+            .map(|account| {
+                let (shutdown_request_tx, shutdown_request_rx) = oneshot::channel();
+                let (shutdown_tx, shutdown_rx) = oneshot::channel();
+                shutdown_requests.push(shutdown_request_tx);
+                shutdowns.push(shutdown_rx);
+
+                async move {
+                    account
+                        .backend
+                        .watch_envelopes(folder, shutdown_request_rx, shutdown_tx)
+                        .await
+                        .map_err(|err| Error::WatchGroupEnvelopesError(err, account.name.clone()))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let forward_shutdown = async move {
+            let _ = wait_for_shutdown_request.await;
+            debug!("shutdown requested, stopping account group watchers…");
+
+            for shutdown_request in shutdown_requests {
+                let _ = shutdown_request.send(());
+            }
+
+            for shutdown in shutdowns {
+                let _ = shutdown.await;
+            }
+
+            Result::Ok(())
+        };
+
+        let res = futures::try_join!(try_join_all(watchers), forward_shutdown);
+        let _ = shutdown.send(());
+
+        res?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    #[cfg(feature = "watch")]
+    use tokio::sync::oneshot::{Receiver, Sender};
+
+    use super::{AccountGroup, GROUP_ID_SEPARATOR};
+    #[cfg(feature = "watch")]
+    use crate::envelope::watch::WatchEnvelopes;
+    use crate::{
+        envelope::{
+            list::{ListEnvelopes, ListEnvelopesOptions},
+            Envelope, Envelopes,
+        },
+        AnyResult,
+    };
+
+    struct TestBackend(Vec<(&'static str, &'static str)>);
+
+    #[async_trait]
+    impl ListEnvelopes for TestBackend {
+        async fn list_envelopes(
+            &self,
+            _: &str,
+            opts: ListEnvelopesOptions,
+        ) -> AnyResult<Envelopes> {
+            let mut envelopes: Envelopes = self
+                .0
+                .iter()
+                .map(|(id, date)| Envelope {
+                    id: id.to_string(),
+                    date: date.parse().unwrap(),
+                    ..Default::default()
+                })
+                .collect();
+
+            opts.sort_envelopes(&mut envelopes);
+            if opts.page_size > 0 {
+                envelopes.truncate(opts.page_size);
+            }
+
+            Ok(envelopes)
+        }
+    }
+
+    #[cfg(feature = "watch")]
+    #[async_trait]
+    impl WatchEnvelopes for TestBackend {
+        async fn watch_envelopes(&self, _: &str, _: Receiver<()>, _: Sender<()>) -> AnyResult<()> {
+            Ok(())
+        }
+    }
+
+    fn group() -> AccountGroup {
+        AccountGroup::new()
+            .with_account(
+                "a",
+                Arc::new(TestBackend(vec![
+                    ("1", "2024-01-01T10:00:00+00:00"),
+                    ("2", "2024-01-03T10:00:00+00:00"),
+                ])),
+            )
+            .unwrap()
+            .with_account(
+                "b",
+                Arc::new(TestBackend(vec![
+                    ("1", "2024-01-02T10:00:00+00:00"),
+                    ("2", "2024-01-04T10:00:00+00:00"),
+                ])),
+            )
+            .unwrap()
+    }
+
+    fn ids(envelopes: &Envelopes) -> Vec<&str> {
+        envelopes.iter().map(|e| e.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn merge_envelopes() {
+        let group = group();
+
+        let envelopes = group
+            .list_envelopes("INBOX", ListEnvelopesOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(ids(&envelopes), vec!["b:2", "a:2", "b:1", "a:1"]);
+
+        let opts = ListEnvelopesOptions {
+            page_size: 3,
+            page: 1,
+            ..Default::default()
+        };
+        let envelopes = group.list_envelopes("INBOX", opts).await.unwrap();
+        assert_eq!(ids(&envelopes), vec!["a:1"]);
+    }
+
+    #[test]
+    fn split_ids() {
+        let group = group();
+
+        let (_, id) = group.split_id("b:1").unwrap();
+        assert_eq!(id, "1");
+        assert!(group.split_id("c:1").is_err());
+        assert!(group.split_id("1").is_err());
+
+        let backend = Arc::new(TestBackend(vec![]));
+        let name = format!("c{GROUP_ID_SEPARATOR}d");
+        assert!(group.clone().with_account(name, backend.clone()).is_err());
+        assert!(group.with_account("a", backend).is_err());
+    }
+}
@@ -17,7 +17,7 @@
 use crate::envelope::watch::WatchEnvelopes;
 use crate::{
     envelope::{get::GetEnvelope, list::ListEnvelopes},
-    flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags},
+    flag::{add::AddFlags, list::ListFlags, remove::RemoveFlags, set::SetFlags},
     folder::{
         add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders,
         purge::PurgeFolder,
@@ -80,6 +80,7 @@ fn map_feature<T: ?Sized + 'static>(
     some_feature_mapper!(AddFlags);
     some_feature_mapper!(SetFlags);
     some_feature_mapper!(RemoveFlags);
+    some_feature_mapper!(ListFlags);
     some_feature_mapper!(AddMessage);
     some_feature_mapper!(SendMessage);
     some_feature_mapper!(PeekMessages);
@@ -144,6 +145,7 @@ fn map_feature<T: ?Sized + 'static>(
     feature_mapper!(AddFlags);
     feature_mapper!(SetFlags);
     feature_mapper!(RemoveFlags);
+    feature_mapper!(ListFlags);
     feature_mapper!(AddMessage);
     feature_mapper!(SendMessage);
     feature_mapper!(PeekMessages);
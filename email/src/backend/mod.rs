@@ -29,6 +29,23 @@
 //!
 //! See a full example at `../../tests/dynamic_backend.rs`.
 //!
+//! When the composition of contexts itself is only known at runtime
+//! (for example a store and a sender chosen from a user
+//! configuration file), use [`BackendBuilder::new_dyn`] and
+//! [`BackendBuilder::with_context_dyn`] instead of defining a custom
+//! context. See the [`dynamic`] module.
+//!
+//! To aggregate the backends of several accounts into a single one
+//! (for example to build a unified inbox), see the [`group`] module.
+//!
+//! To serve features from the local synchronization cache when the
+//! remote backend is unreachable, see the `cached` module (requires
+//! the `sync` cargo feature).
+//!
+//! To observe operations performed by a backend (for example to
+//! build an activity indicator), see the `event` module (requires
+//! the `events` cargo feature).
+//!
 //! ## Static backend
 //!
 //! A static backend is composed of features defined at compilation
@@ -39,9 +56,15 @@
 //!
 //! See a full example at `../../tests/static_backend.rs`.
 
+#[cfg(feature = "sync")]
+pub mod cached;
 pub mod context;
+pub mod dynamic;
 mod error;
+#[cfg(feature = "events")]
+pub mod event;
 pub mod feature;
+pub mod group;
 pub mod mapper;
 pub mod macros {
     pub use email_macros::BackendContext;
@@ -49,18 +72,23 @@ pub mod macros {
 
 #[cfg(feature = "sync")]
 use std::hash::DefaultHasher;
-use std::sync::Arc;
+use std::{future::Future, sync::Arc};
 
 use async_trait::async_trait;
+use futures::future::BoxFuture;
 use paste::paste;
 #[cfg(feature = "watch")]
 use tokio::sync::oneshot::{Receiver, Sender};
+use tracing::{debug, debug_span, Span};
 
 #[doc(inline)]
 pub use self::error::{Error, Result};
+#[cfg(feature = "events")]
+use self::event::{BackendEvent, BackendEventBus};
 use self::{
     context::{BackendContext, BackendContextBuilder},
     feature::{BackendFeature, BackendFeatureSource, CheckUp},
+    mapper::SomeBackendContextBuilderMapper,
 };
 #[cfg(feature = "watch")]
 use crate::envelope::watch::WatchEnvelopes;
@@ -75,16 +103,25 @@ pub mod macros {
         list::{ListEnvelopes, ListEnvelopesOptions},
         Envelope, Envelopes, Id, SingleId,
     },
-    flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags, Flags},
+    flag::{add::AddFlags, list::ListFlags, remove::RemoveFlags, set::SetFlags, Flags},
     folder::{
         add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders,
-        purge::PurgeFolder, Folders,
+        namespace::Namespaces, purge::PurgeFolder, Folders,
     },
     message::{
-        add::AddMessage, copy::CopyMessages, delete::DeleteMessages, get::GetMessages,
-        peek::PeekMessages, r#move::MoveMessages, remove::RemoveMessages, send::SendMessage,
+        add::{AddMessage, AddMessageOptions},
+        copy::CopyMessages,
+        delete::DeleteMessages,
+        get::GetMessages,
+        peek::PeekMessages,
+        r#move::MoveMessages,
+        remove::RemoveMessages,
+        send::SendMessage,
         Messages,
     },
+    metrics::{self, measure_backend_operation, Metric},
+    retry::{OperationClass, RetryPolicies, RetryPolicy},
+    search_query::SearchEmailsQuery,
     AnyResult,
 };
 
@@ -104,6 +141,12 @@ pub struct Backend<C>
     pub account_config: Arc<AccountConfig>,
     /// The backend context.
     pub context: Arc<C>,
+    /// The function rebuilding the backend context, used by
+    /// [`Backend::reconnect`].
+    pub rebuild_context: Option<BackendContextRebuilder<C>>,
+
+    /// The check up backend feature.
+    pub check_up: Option<BackendFeature<C, dyn CheckUp>>,
 
     /// The add folder backend feature.
     pub add_folder: Option<BackendFeature<C, dyn AddFolder>>,
@@ -133,6 +176,8 @@ pub struct Backend<C>
     pub set_flags: Option<BackendFeature<C, dyn SetFlags>>,
     /// The remove flags backend feature.
     pub remove_flags: Option<BackendFeature<C, dyn RemoveFlags>>,
+    /// The list flags backend feature.
+    pub list_flags: Option<BackendFeature<C, dyn ListFlags>>,
 
     /// The add message backend feature.
     pub add_message: Option<BackendFeature<C, dyn AddMessage>>,
@@ -150,83 +195,262 @@ pub struct Backend<C>
     pub delete_messages: Option<BackendFeature<C, dyn DeleteMessages>>,
     /// The delete messages backend feature.
     pub remove_messages: Option<BackendFeature<C, dyn RemoveMessages>>,
+
+    /// The retry policies of backend operations.
+    pub retry_policies: RetryPolicies,
+
+    /// The bus operation events are emitted to, if any.
+    #[cfg(feature = "events")]
+    pub event_bus: Option<BackendEventBus>,
 }
 
+impl<C: BackendContext> Backend<C> {
+    /// Subscribe to the operation events of the backend.
+    ///
+    /// Returns `None` if the backend has been built without event
+    /// bus (see [`BackendBuilder::with_event_bus`]).
+    #[cfg(feature = "events")]
+    pub fn subscribe(&self) -> Option<tokio::sync::broadcast::Receiver<BackendEvent>> {
+        self.event_bus.as_ref().map(BackendEventBus::subscribe)
+    }
+
+    /// Run the given backend operation inside the given span, then
+    /// emit metrics and events about it.
+    async fn measure_operation<T>(
+        &self,
+        name: &'static str,
+        span: Span,
+        f: impl Future<Output = AnyResult<T>>,
+    ) -> AnyResult<T> {
+        #[cfg(feature = "events")]
+        if let Some(bus) = self.event_bus.as_ref() {
+            return bus
+                .observe(name, measure_backend_operation(name, span, f))
+                .await;
+        }
+
+        measure_backend_operation(name, span, f).await
+    }
+
+    /// Run the given backend operation inside the given span,
+    /// retrying it according to the retry policy of its class.
+    async fn run_operation<T, F, Fut>(
+        &self,
+        name: &'static str,
+        class: OperationClass,
+        span: Span,
+        f: F,
+    ) -> AnyResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = AnyResult<T>>,
+    {
+        let policy = self.retry_policies.get(class);
+        self.measure_operation(name, span, policy.retry(name, f))
+            .await
+    }
+
+    /// Rebuild the backend context in place if it is dead.
+    ///
+    /// Contexts usually hold long-lived clients or sessions, which
+    /// may die after a network change or a laptop sleep. The context
+    /// is first checked up (see [`CheckUp`]): if the check up fails,
+    /// the whole context is rebuilt using the context builder the
+    /// backend has been built with. Features called afterwards use
+    /// the new context.
+    ///
+    /// Returns `true` if the context has been rebuilt.
+    pub async fn reconnect(&mut self) -> AnyResult<bool> {
+        let err = match self.check_up().await {
+            Ok(()) => return Ok(false),
+            Err(err) => err,
+        };
+
+        debug!(?err, "backend context is dead, rebuilding it");
+
+        let rebuild_context = self
+            .rebuild_context
+            .as_ref()
+            .ok_or(Error::ReconnectNotAvailableError)?;
+
+        let span = debug_span!("backend", op = "reconnect");
+        let ctx = self
+            .measure_operation("reconnect", span, rebuild_context())
+            .await?;
+        self.context = Arc::new(ctx);
+
+        Ok(true)
+    }
+}
+
+/// The backend context rebuilder.
+///
+/// A function building a fresh backend context, used by
+/// [`Backend::reconnect`].
+pub type BackendContextRebuilder<C> =
+    Arc<dyn Fn() -> BoxFuture<'static, AnyResult<C>> + Send + Sync>;
+
 impl<C: BackendContext> HasAccountConfig for Backend<C> {
     fn account_config(&self) -> &AccountConfig {
         &self.account_config
     }
 }
 
+#[async_trait]
+impl<C: BackendContext> CheckUp for Backend<C> {
+    async fn check_up(&self) -> AnyResult<()> {
+        let span = debug_span!("backend", op = "check_up");
+        self.measure_operation("check_up", span, async move {
+            match self.check_up.as_ref().and_then(|f| f(&self.context)) {
+                Some(feature) => feature.check_up().await,
+                None => Ok(()),
+            }
+        })
+        .await
+    }
+}
+
 #[async_trait]
 impl<C: BackendContext> AddFolder for Backend<C> {
     async fn add_folder(&self, folder: &str) -> AnyResult<()> {
-        self.add_folder
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::AddFolderNotAvailableError)?
-            .add_folder(folder)
-            .await
+        let span = debug_span!("backend", op = "add_folder", folder);
+        self.run_operation(
+            "add_folder",
+            OperationClass::Write,
+            span,
+            move || async move {
+                self.add_folder
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::AddFolderNotAvailableError)?
+                    .add_folder(folder)
+                    .await
+            },
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> ListFolders for Backend<C> {
     async fn list_folders(&self) -> AnyResult<Folders> {
-        self.list_folders
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::ListFoldersNotAvailableError)?
-            .list_folders()
-            .await
+        let span = debug_span!("backend", op = "list_folders");
+        self.run_operation(
+            "list_folders",
+            OperationClass::Read,
+            span,
+            move || async move {
+                self.list_folders
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::ListFoldersNotAvailableError)?
+                    .list_folders()
+                    .await
+            },
+        )
+        .await
+    }
+
+    async fn list_namespaces(&self) -> AnyResult<Namespaces> {
+        let span = debug_span!("backend", op = "list_namespaces");
+        self.run_operation(
+            "list_namespaces",
+            OperationClass::Read,
+            span,
+            move || async move {
+                self.list_folders
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::ListFoldersNotAvailableError)?
+                    .list_namespaces()
+                    .await
+            },
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> ExpungeFolder for Backend<C> {
     async fn expunge_folder(&self, folder: &str) -> AnyResult<()> {
-        self.expunge_folder
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::ExpungeFolderNotAvailableError)?
-            .expunge_folder(folder)
-            .await
+        let span = debug_span!("backend", op = "expunge_folder", folder);
+        self.run_operation(
+            "expunge_folder",
+            OperationClass::Write,
+            span,
+            move || async move {
+                self.expunge_folder
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::ExpungeFolderNotAvailableError)?
+                    .expunge_folder(folder)
+                    .await
+            },
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> PurgeFolder for Backend<C> {
     async fn purge_folder(&self, folder: &str) -> AnyResult<()> {
-        self.purge_folder
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::PurgeFolderNotAvailableError)?
-            .purge_folder(folder)
-            .await
+        let span = debug_span!("backend", op = "purge_folder", folder);
+        self.run_operation(
+            "purge_folder",
+            OperationClass::Write,
+            span,
+            move || async move {
+                self.purge_folder
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::PurgeFolderNotAvailableError)?
+                    .purge_folder(folder)
+                    .await
+            },
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> DeleteFolder for Backend<C> {
     async fn delete_folder(&self, folder: &str) -> AnyResult<()> {
-        self.delete_folder
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::DeleteFolderNotAvailableError)?
-            .delete_folder(folder)
-            .await
+        let span = debug_span!("backend", op = "delete_folder", folder);
+        self.run_operation(
+            "delete_folder",
+            OperationClass::Write,
+            span,
+            move || async move {
+                self.delete_folder
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::DeleteFolderNotAvailableError)?
+                    .delete_folder(folder)
+                    .await
+            },
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> GetEnvelope for Backend<C> {
     async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope> {
-        self.get_envelope
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::GetEnvelopeNotAvailableError)?
-            .get_envelope(folder, id)
-            .await
+        let span = debug_span!("backend", op = "get_envelope", folder, id = id.as_str());
+        self.run_operation(
+            "get_envelope",
+            OperationClass::Read,
+            span,
+            move || async move {
+                self.get_envelope
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::GetEnvelopeNotAvailableError)?
+                    .get_envelope(folder, id)
+                    .await
+            },
+        )
+        .await
     }
 }
 
@@ -237,12 +461,19 @@ async fn list_envelopes(
         folder: &str,
         opts: ListEnvelopesOptions,
     ) -> AnyResult<Envelopes> {
-        self.list_envelopes
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::ListEnvelopesNotAvailableError)?
-            .list_envelopes(folder, opts)
-            .await
+        let span = debug_span!("backend", op = "list_envelopes", folder);
+        self.run_operation("list_envelopes", OperationClass::Read, span, move || {
+            let opts = opts.clone();
+            async move {
+                self.list_envelopes
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::ListEnvelopesNotAvailableError)?
+                    .list_envelopes(folder, opts)
+                    .await
+            }
+        })
+        .await
     }
 }
 
@@ -254,12 +485,19 @@ async fn thread_envelopes(
         folder: &str,
         opts: ListEnvelopesOptions,
     ) -> AnyResult<ThreadedEnvelopes> {
-        self.thread_envelopes
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::ThreadEnvelopesNotAvailableError)?
-            .thread_envelopes(folder, opts)
-            .await
+        let span = debug_span!("backend", op = "thread_envelopes", folder);
+        self.run_operation("thread_envelopes", OperationClass::Read, span, move || {
+            let opts = opts.clone();
+            async move {
+                self.thread_envelopes
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::ThreadEnvelopesNotAvailableError)?
+                    .thread_envelopes(folder, opts)
+                    .await
+            }
+        })
+        .await
     }
 
     async fn thread_envelope(
@@ -268,12 +506,20 @@ async fn thread_envelope(
         id: SingleId,
         opts: ListEnvelopesOptions,
     ) -> AnyResult<ThreadedEnvelopes> {
-        self.thread_envelopes
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::ThreadEnvelopesNotAvailableError)?
-            .thread_envelope(folder, id, opts)
-            .await
+        let span = debug_span!("backend", op = "thread_envelope", folder, id = id.as_str());
+        self.run_operation("thread_envelope", OperationClass::Read, span, move || {
+            let id = id.clone();
+            let opts = opts.clone();
+            async move {
+                self.thread_envelopes
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::ThreadEnvelopesNotAvailableError)?
+                    .thread_envelope(folder, id, opts)
+                    .await
+            }
+        })
+        .await
     }
 }
 
@@ -298,36 +544,153 @@ async fn watch_envelopes(
 #[async_trait]
 impl<C: BackendContext> AddFlags for Backend<C> {
     async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
-        self.add_flags
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::AddFlagsNotAvailableError)?
-            .add_flags(folder, id, flags)
-            .await
+        let span = debug_span!("backend", op = "add_flags", folder, %id);
+        self.run_operation(
+            "add_flags",
+            OperationClass::Write,
+            span,
+            move || async move {
+                self.add_flags
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::AddFlagsNotAvailableError)?
+                    .add_flags(folder, id, flags)
+                    .await
+            },
+        )
+        .await
+    }
+
+    async fn add_flags_matching(
+        &self,
+        folder: &str,
+        query: &SearchEmailsQuery,
+        flags: &Flags,
+    ) -> AnyResult<()> {
+        let span = debug_span!("backend", op = "add_flags_matching", folder);
+        self.run_operation(
+            "add_flags_matching",
+            OperationClass::Write,
+            span,
+            move || async move {
+                self.add_flags
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::AddFlagsNotAvailableError)?
+                    .add_flags_matching(folder, query, flags)
+                    .await
+            },
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> SetFlags for Backend<C> {
     async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
-        self.set_flags
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::SetFlagsNotAvailableError)?
-            .set_flags(folder, id, flags)
-            .await
+        let span = debug_span!("backend", op = "set_flags", folder, %id);
+        self.run_operation(
+            "set_flags",
+            OperationClass::Write,
+            span,
+            move || async move {
+                self.set_flags
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::SetFlagsNotAvailableError)?
+                    .set_flags(folder, id, flags)
+                    .await
+            },
+        )
+        .await
+    }
+
+    async fn set_flags_matching(
+        &self,
+        folder: &str,
+        query: &SearchEmailsQuery,
+        flags: &Flags,
+    ) -> AnyResult<()> {
+        let span = debug_span!("backend", op = "set_flags_matching", folder);
+        self.run_operation(
+            "set_flags_matching",
+            OperationClass::Write,
+            span,
+            move || async move {
+                self.set_flags
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::SetFlagsNotAvailableError)?
+                    .set_flags_matching(folder, query, flags)
+                    .await
+            },
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> RemoveFlags for Backend<C> {
     async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
-        self.remove_flags
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::RemoveFlagsNotAvailableError)?
-            .remove_flags(folder, id, flags)
-            .await
+        let span = debug_span!("backend", op = "remove_flags", folder, %id);
+        self.run_operation(
+            "remove_flags",
+            OperationClass::Write,
+            span,
+            move || async move {
+                self.remove_flags
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::RemoveFlagsNotAvailableError)?
+                    .remove_flags(folder, id, flags)
+                    .await
+            },
+        )
+        .await
+    }
+
+    async fn remove_flags_matching(
+        &self,
+        folder: &str,
+        query: &SearchEmailsQuery,
+        flags: &Flags,
+    ) -> AnyResult<()> {
+        let span = debug_span!("backend", op = "remove_flags_matching", folder);
+        self.run_operation(
+            "remove_flags_matching",
+            OperationClass::Write,
+            span,
+            move || async move {
+                self.remove_flags
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::RemoveFlagsNotAvailableError)?
+                    .remove_flags_matching(folder, query, flags)
+                    .await
+            },
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext> ListFlags for Backend<C> {
+    async fn list_flags(&self, folder: &str) -> AnyResult<Flags> {
+        let span = debug_span!("backend", op = "list_flags", folder);
+        self.run_operation(
+            "list_flags",
+            OperationClass::Read,
+            span,
+            move || async move {
+                self.list_flags
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::ListFlagsNotAvailableError)?
+                    .list_flags(folder)
+                    .await
+            },
+        )
+        .await
     }
 }
 
@@ -339,96 +702,236 @@ async fn add_message_with_flags(
         msg: &[u8],
         flags: &Flags,
     ) -> AnyResult<SingleId> {
-        self.add_message
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::AddMessageNotAvailableError)?
-            .add_message_with_flags(folder, msg, flags)
-            .await
+        let span = debug_span!("backend", op = "add_message_with_flags", folder);
+        self.run_operation(
+            "add_message_with_flags",
+            OperationClass::Write,
+            span,
+            move || async move {
+                let id = self
+                    .add_message
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::AddMessageNotAvailableError)?
+                    .add_message_with_flags(folder, msg, flags)
+                    .await?;
+                metrics::emit(Metric::BytesTransferred(msg.len()));
+                Ok(id)
+            },
+        )
+        .await
+    }
+
+    async fn add_message_with_options(
+        &self,
+        folder: &str,
+        msg: &[u8],
+        opts: &AddMessageOptions,
+    ) -> AnyResult<SingleId> {
+        let span = debug_span!("backend", op = "add_message_with_options", folder);
+        self.run_operation(
+            "add_message_with_options",
+            OperationClass::Write,
+            span,
+            move || async move {
+                let id = self
+                    .add_message
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::AddMessageNotAvailableError)?
+                    .add_message_with_options(folder, msg, opts)
+                    .await?;
+                metrics::emit(Metric::BytesTransferred(msg.len()));
+                Ok(id)
+            },
+        )
+        .await
+    }
+
+    async fn add_messages_with_flags(
+        &self,
+        folder: &str,
+        msgs: &[(&[u8], &Flags)],
+    ) -> AnyResult<Vec<AnyResult<SingleId>>> {
+        let span = debug_span!("backend", op = "add_messages_with_flags", folder);
+        self.run_operation(
+            "add_messages_with_flags",
+            OperationClass::Write,
+            span,
+            move || async move {
+                let ids = self
+                    .add_message
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::AddMessageNotAvailableError)?
+                    .add_messages_with_flags(folder, msgs)
+                    .await?;
+                let size = msgs
+                    .iter()
+                    .zip(&ids)
+                    .filter(|(_, id)| id.is_ok())
+                    .map(|((msg, _), _)| msg.len())
+                    .sum();
+                metrics::emit(Metric::BytesTransferred(size));
+                Ok(ids)
+            },
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> SendMessage for Backend<C> {
     async fn send_message(&self, msg: &[u8]) -> AnyResult<()> {
-        self.send_message
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::SendMessageNotAvailableError)?
-            .send_message(msg)
-            .await
+        let span = debug_span!("backend", op = "send_message");
+        self.run_operation(
+            "send_message",
+            OperationClass::Send,
+            span,
+            move || async move {
+                self.send_message
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::SendMessageNotAvailableError)?
+                    .send_message(msg)
+                    .await?;
+                metrics::emit(Metric::BytesTransferred(msg.len()));
+                Ok(())
+            },
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> PeekMessages for Backend<C> {
     async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
-        self.peek_messages
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::PeekMessagesNotAvailableError)?
-            .peek_messages(folder, id)
-            .await
+        let span = debug_span!("backend", op = "peek_messages", folder, %id);
+        self.run_operation(
+            "peek_messages",
+            OperationClass::Read,
+            span,
+            move || async move {
+                let msgs = self
+                    .peek_messages
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::PeekMessagesNotAvailableError)?
+                    .peek_messages(folder, id)
+                    .await?;
+                metrics::record_fetched_messages(&msgs);
+                Ok(msgs)
+            },
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> GetMessages for Backend<C> {
     async fn get_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
-        self.get_messages
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::GetMessagesNotAvailableError)?
-            .get_messages(folder, id)
-            .await
+        let span = debug_span!("backend", op = "get_messages", folder, %id);
+        self.run_operation(
+            "get_messages",
+            OperationClass::Read,
+            span,
+            move || async move {
+                let msgs = self
+                    .get_messages
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::GetMessagesNotAvailableError)?
+                    .get_messages(folder, id)
+                    .await?;
+                metrics::record_fetched_messages(&msgs);
+                Ok(msgs)
+            },
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> CopyMessages for Backend<C> {
     async fn copy_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
-        self.copy_messages
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::CopyMessagesNotAvailableError)?
-            .copy_messages(from_folder, to_folder, id)
-            .await
+        let span = debug_span!("backend", op = "copy_messages", from_folder, to_folder, %id);
+        self.run_operation(
+            "copy_messages",
+            OperationClass::Write,
+            span,
+            move || async move {
+                self.copy_messages
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::CopyMessagesNotAvailableError)?
+                    .copy_messages(from_folder, to_folder, id)
+                    .await
+            },
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> MoveMessages for Backend<C> {
     async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
-        self.move_messages
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::MoveMessagesNotAvailableError)?
-            .move_messages(from_folder, to_folder, id)
-            .await
+        let span = debug_span!("backend", op = "move_messages", from_folder, to_folder, %id);
+        self.run_operation(
+            "move_messages",
+            OperationClass::Write,
+            span,
+            move || async move {
+                self.move_messages
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::MoveMessagesNotAvailableError)?
+                    .move_messages(from_folder, to_folder, id)
+                    .await
+            },
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> DeleteMessages for Backend<C> {
     async fn delete_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
-        self.delete_messages
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::DeleteMessagesNotAvailableError)?
-            .delete_messages(folder, id)
-            .await
+        let span = debug_span!("backend", op = "delete_messages", folder, %id);
+        self.run_operation(
+            "delete_messages",
+            OperationClass::Write,
+            span,
+            move || async move {
+                self.delete_messages
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::DeleteMessagesNotAvailableError)?
+                    .delete_messages(folder, id)
+                    .await
+            },
+        )
+        .await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> RemoveMessages for Backend<C> {
     async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
-        self.remove_messages
-            .as_ref()
-            .and_then(|feature| feature(&self.context))
-            .ok_or(Error::RemoveMessagesNotAvailableError)?
-            .remove_messages(folder, id)
-            .await
+        let span = debug_span!("backend", op = "remove_messages", folder, %id);
+        self.run_operation(
+            "remove_messages",
+            OperationClass::Write,
+            span,
+            move || async move {
+                self.remove_messages
+                    .as_ref()
+                    .and_then(|feature| feature(&self.context))
+                    .ok_or(Error::RemoveMessagesNotAvailableError)?
+                    .remove_messages(folder, id)
+                    .await
+            },
+        )
+        .await
     }
 }
 
@@ -536,6 +1039,8 @@ pub struct BackendBuilder<CB>
     pub set_flags: BackendFeatureSource<CB::Context, dyn SetFlags>,
     /// The remove flags backend builder feature.
     pub remove_flags: BackendFeatureSource<CB::Context, dyn RemoveFlags>,
+    /// The list flags backend builder feature.
+    pub list_flags: BackendFeatureSource<CB::Context, dyn ListFlags>,
 
     /// The add message backend builder feature.
     pub add_message: BackendFeatureSource<CB::Context, dyn AddMessage>,
@@ -553,6 +1058,13 @@ pub struct BackendBuilder<CB>
     pub delete_messages: BackendFeatureSource<CB::Context, dyn DeleteMessages>,
     /// The remove messages backend builder feature.
     pub remove_messages: BackendFeatureSource<CB::Context, dyn RemoveMessages>,
+
+    /// The retry policies of backend operations.
+    pub retry_policies: RetryPolicies,
+
+    /// The bus operation events are emitted to, if any.
+    #[cfg(feature = "events")]
+    pub event_bus: Option<BackendEventBus>,
 }
 
 impl<CB> BackendBuilder<CB>
@@ -574,6 +1086,7 @@ impl<CB> BackendBuilder<CB>
     feature_accessors!(AddFlags);
     feature_accessors!(SetFlags);
     feature_accessors!(RemoveFlags);
+    feature_accessors!(ListFlags);
     feature_accessors!(AddMessage);
     feature_accessors!(SendMessage);
     feature_accessors!(PeekMessages);
@@ -610,6 +1123,7 @@ pub fn new(account_config: Arc<AccountConfig>, ctx_builder: CB) -> Self {
             add_flags: BackendFeatureSource::Context,
             set_flags: BackendFeatureSource::Context,
             remove_flags: BackendFeatureSource::Context,
+            list_flags: BackendFeatureSource::Context,
 
             add_message: BackendFeatureSource::Context,
             send_message: BackendFeatureSource::Context,
@@ -619,12 +1133,164 @@ pub fn new(account_config: Arc<AccountConfig>, ctx_builder: CB) -> Self {
             move_messages: BackendFeatureSource::Context,
             delete_messages: BackendFeatureSource::Context,
             remove_messages: BackendFeatureSource::Context,
+
+            retry_policies: Default::default(),
+
+            #[cfg(feature = "events")]
+            event_bus: None,
         }
     }
 
     /// Disable all features for this backend builder.
     pub fn without_features(mut self) -> Self {
+        self.set_check_up(BackendFeatureSource::None);
+        self.set_add_folder(BackendFeatureSource::None);
         self.set_list_folders(BackendFeatureSource::None);
+        self.set_expunge_folder(BackendFeatureSource::None);
+        self.set_purge_folder(BackendFeatureSource::None);
+        self.set_delete_folder(BackendFeatureSource::None);
+        self.set_get_envelope(BackendFeatureSource::None);
+        self.set_list_envelopes(BackendFeatureSource::None);
+        #[cfg(feature = "thread")]
+        self.set_thread_envelopes(BackendFeatureSource::None);
+        #[cfg(feature = "watch")]
+        self.set_watch_envelopes(BackendFeatureSource::None);
+        self.set_add_flags(BackendFeatureSource::None);
+        self.set_set_flags(BackendFeatureSource::None);
+        self.set_remove_flags(BackendFeatureSource::None);
+        self.set_list_flags(BackendFeatureSource::None);
+        self.set_add_message(BackendFeatureSource::None);
+        self.set_send_message(BackendFeatureSource::None);
+        self.set_peek_messages(BackendFeatureSource::None);
+        self.set_get_messages(BackendFeatureSource::None);
+        self.set_copy_messages(BackendFeatureSource::None);
+        self.set_move_messages(BackendFeatureSource::None);
+        self.set_delete_messages(BackendFeatureSource::None);
+        self.set_remove_messages(BackendFeatureSource::None);
+        self
+    }
+
+    /// Set the retry policy of the given operation class.
+    ///
+    /// By default, no operation is retried. Only idempotent
+    /// operations ([`OperationClass::Read`]) should usually be
+    /// retried.
+    pub fn set_retry_policy(&mut self, class: OperationClass, policy: RetryPolicy) {
+        self.retry_policies.set(class, policy);
+    }
+
+    /// Set the retry policy of the given operation class, using the
+    /// builder pattern.
+    pub fn with_retry_policy(mut self, class: OperationClass, policy: RetryPolicy) -> Self {
+        self.set_retry_policy(class, policy);
+        self
+    }
+
+    /// Set the bus operation events are emitted to.
+    ///
+    /// By default, no event is emitted. The same bus can be shared
+    /// between multiple backends.
+    #[cfg(feature = "events")]
+    pub fn set_event_bus(&mut self, bus: BackendEventBus) {
+        self.event_bus = Some(bus);
+    }
+
+    /// Set the bus operation events are emitted to, using the
+    /// builder pattern.
+    #[cfg(feature = "events")]
+    pub fn with_event_bus(mut self, bus: BackendEventBus) -> Self {
+        self.set_event_bus(bus);
+        self
+    }
+
+    /// Take all features from the context builder.
+    ///
+    /// This is the default behaviour of [`BackendBuilder::new`]. It
+    /// can be used to restore features after disabling some of them.
+    pub fn with_default_features(mut self) -> Self {
+        self.set_check_up(BackendFeatureSource::Context);
+        self.set_add_folder(BackendFeatureSource::Context);
+        self.set_list_folders(BackendFeatureSource::Context);
+        self.set_expunge_folder(BackendFeatureSource::Context);
+        self.set_purge_folder(BackendFeatureSource::Context);
+        self.set_delete_folder(BackendFeatureSource::Context);
+        self.set_get_envelope(BackendFeatureSource::Context);
+        self.set_list_envelopes(BackendFeatureSource::Context);
+        #[cfg(feature = "thread")]
+        self.set_thread_envelopes(BackendFeatureSource::Context);
+        #[cfg(feature = "watch")]
+        self.set_watch_envelopes(BackendFeatureSource::Context);
+        self.set_add_flags(BackendFeatureSource::Context);
+        self.set_set_flags(BackendFeatureSource::Context);
+        self.set_remove_flags(BackendFeatureSource::Context);
+        self.set_list_flags(BackendFeatureSource::Context);
+        self.set_add_message(BackendFeatureSource::Context);
+        self.set_send_message(BackendFeatureSource::Context);
+        self.set_peek_messages(BackendFeatureSource::Context);
+        self.set_get_messages(BackendFeatureSource::Context);
+        self.set_copy_messages(BackendFeatureSource::Context);
+        self.set_move_messages(BackendFeatureSource::Context);
+        self.set_delete_messages(BackendFeatureSource::Context);
+        self.set_remove_messages(BackendFeatureSource::Context);
+        self
+    }
+
+    /// Take all features supported by the given subcontext builder.
+    ///
+    /// This is useful when the context is composed of multiple
+    /// subcontexts: instead of overriding every single feature in
+    /// the context builder, all features implemented by the
+    /// subcontext builder returned by `f` are wired at once. Features
+    /// not supported by the subcontext builder are left untouched.
+    ///
+    /// See [`SomeBackendContextBuilderMapper`].
+    pub fn with_features_from<CB2>(mut self, f: impl Fn(&CB) -> &Option<CB2>) -> Self
+    where
+        CB: SomeBackendContextBuilderMapper<CB2>,
+        CB::Context: AsRef<Option<CB2::Context>> + 'static,
+        CB2: BackendContextBuilder,
+        CB2::Context: BackendContext + 'static,
+    {
+        macro_rules! map_feature_from {
+            ($feat:ty) => {
+                paste! {
+                    let cb = f(&self.ctx_builder);
+                    let feature = <CB as SomeBackendContextBuilderMapper<CB2>>::[<$feat:snake _with_some>](
+                        &self.ctx_builder,
+                        cb,
+                    );
+                    if let Some(feature) = feature {
+                        self.[<set_ $feat:snake>](BackendFeatureSource::Backend(feature));
+                    }
+                }
+            };
+        }
+
+        map_feature_from!(CheckUp);
+        map_feature_from!(AddFolder);
+        map_feature_from!(ListFolders);
+        map_feature_from!(ExpungeFolder);
+        map_feature_from!(PurgeFolder);
+        map_feature_from!(DeleteFolder);
+        map_feature_from!(GetEnvelope);
+        map_feature_from!(ListEnvelopes);
+        #[cfg(feature = "thread")]
+        map_feature_from!(ThreadEnvelopes);
+        #[cfg(feature = "watch")]
+        map_feature_from!(WatchEnvelopes);
+        map_feature_from!(AddFlags);
+        map_feature_from!(SetFlags);
+        map_feature_from!(RemoveFlags);
+        map_feature_from!(ListFlags);
+        map_feature_from!(AddMessage);
+        map_feature_from!(SendMessage);
+        map_feature_from!(PeekMessages);
+        map_feature_from!(GetMessages);
+        map_feature_from!(CopyMessages);
+        map_feature_from!(MoveMessages);
+        map_feature_from!(DeleteMessages);
+        map_feature_from!(RemoveMessages);
+
         self
     }
 
@@ -636,7 +1302,16 @@ pub async fn check_up(self) -> AnyResult<()> {
         }
     }
 
-    pub async fn build(self) -> AnyResult<Backend<CB::Context>> {
+    pub async fn build(self) -> AnyResult<Backend<CB::Context>>
+    where
+        CB: 'static,
+    {
+        let ctx_builder = self.ctx_builder.clone();
+        let rebuild_context: BackendContextRebuilder<CB::Context> =
+            Arc::new(move || ctx_builder.clone().build());
+
+        let check_up = self.get_check_up();
+
         let add_folder = self.get_add_folder();
         let list_folders = self.get_list_folders();
         let expunge_folder = self.get_expunge_folder();
@@ -653,6 +1328,7 @@ pub async fn build(self) -> AnyResult<Backend<CB::Context>> {
         let add_flags = self.get_add_flags();
         let set_flags = self.get_set_flags();
         let remove_flags = self.get_remove_flags();
+        let list_flags = self.get_list_flags();
 
         let add_message = self.get_add_message();
         let send_message = self.get_send_message();
@@ -666,6 +1342,9 @@ pub async fn build(self) -> AnyResult<Backend<CB::Context>> {
         Ok(Backend {
             account_config: self.account_config,
             context: Arc::new(self.ctx_builder.build().await?),
+            rebuild_context: Some(rebuild_context),
+
+            check_up,
 
             add_folder,
             list_folders,
@@ -683,6 +1362,7 @@ pub async fn build(self) -> AnyResult<Backend<CB::Context>> {
             add_flags,
             set_flags,
             remove_flags,
+            list_flags,
 
             add_message,
             send_message,
@@ -692,6 +1372,11 @@ pub async fn build(self) -> AnyResult<Backend<CB::Context>> {
             move_messages,
             delete_messages,
             remove_messages,
+
+            retry_policies: self.retry_policies,
+
+            #[cfg(feature = "events")]
+            event_bus: self.event_bus,
         })
     }
 }
@@ -724,6 +1409,7 @@ fn clone(&self) -> Self {
             add_flags: self.add_flags.clone(),
             set_flags: self.set_flags.clone(),
             remove_flags: self.remove_flags.clone(),
+            list_flags: self.list_flags.clone(),
 
             add_message: self.add_message.clone(),
             send_message: self.send_message.clone(),
@@ -733,6 +1419,11 @@ fn clone(&self) -> Self {
             move_messages: self.move_messages.clone(),
             delete_messages: self.delete_messages.clone(),
             remove_messages: self.remove_messages.clone(),
+
+            retry_policies: self.retry_policies.clone(),
+
+            #[cfg(feature = "events")]
+            event_bus: self.event_bus.clone(),
         }
     }
 }
@@ -746,3 +1437,86 @@ fn sync_hash(&self, state: &mut DefaultHasher) {
         self.ctx_builder.sync_hash(state)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use async_trait::async_trait;
+
+    use super::{
+        context::{BackendContext, BackendContextBuilder},
+        feature::{BackendFeature, CheckUp},
+        BackendBuilder,
+    };
+    use crate::{account::config::AccountConfig, AnyResult};
+
+    struct TestContext {
+        alive: Arc<AtomicBool>,
+    }
+
+    impl BackendContext for TestContext {}
+
+    #[derive(Clone)]
+    struct TestContextBuilder {
+        builds: Arc<AtomicUsize>,
+    }
+
+    struct CheckUpTest(Arc<AtomicBool>);
+
+    #[async_trait]
+    impl CheckUp for CheckUpTest {
+        async fn check_up(&self) -> AnyResult<()> {
+            if self.0.load(Ordering::SeqCst) {
+                Ok(())
+            } else {
+                Err(super::Error::ReconnectNotAvailableError.into())
+            }
+        }
+    }
+
+    #[async_trait]
+    impl BackendContextBuilder for TestContextBuilder {
+        type Context = TestContext;
+
+        fn check_up(&self) -> Option<BackendFeature<Self::Context, dyn CheckUp>> {
+            Some(Arc::new(|ctx: &TestContext| {
+                Some(Box::new(CheckUpTest(ctx.alive.clone())) as Box<dyn CheckUp>)
+            }))
+        }
+
+        async fn build(self) -> AnyResult<Self::Context> {
+            self.builds.fetch_add(1, Ordering::SeqCst);
+            Ok(TestContext {
+                alive: Arc::new(AtomicBool::new(true)),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn reconnect_dead_context() {
+        let builds = Arc::new(AtomicUsize::new(0));
+        let ctx_builder = TestContextBuilder {
+            builds: builds.clone(),
+        };
+
+        let mut backend = BackendBuilder::new(Arc::new(AccountConfig::default()), ctx_builder)
+            .build()
+            .await
+            .unwrap();
+
+        backend.check_up().await.unwrap();
+        assert!(!backend.reconnect().await.unwrap());
+        assert_eq!(builds.load(Ordering::SeqCst), 1);
+
+        backend.context.alive.store(false, Ordering::SeqCst);
+        assert!(backend.check_up().await.is_err());
+        assert!(backend.reconnect().await.unwrap());
+        assert_eq!(builds.load(Ordering::SeqCst), 2);
+
+        backend.check_up().await.unwrap();
+    }
+}
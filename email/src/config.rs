@@ -34,11 +34,18 @@ pub struct Config {
     /// The default downloads directory.
     ///
     /// It is mostly used for downloading messages
-    /// attachments. Defaults to the system temporary directory
-    /// (usually `/tmp`). This downloads directory is used by default
-    /// for all accounts.
+    /// attachments. Defaults to the `downloads` directory of the
+    /// account runtime directory. This downloads directory is used by
+    /// default for all accounts.
     pub downloads_dir: Option<PathBuf>,
 
+    /// The default runtime directory.
+    ///
+    /// It holds short-lived files like drafts, temporary attachments
+    /// or lock files. This runtime directory is used by default for
+    /// all accounts.
+    pub runtime_dir: Option<PathBuf>,
+
     /// The map of account-specific configurations.
     pub accounts: HashMap<String, AccountConfig>,
 }
@@ -75,6 +82,11 @@ pub fn account(&self, name: impl AsRef<str>) -> Result<AccountConfig> {
                 .as_ref()
                 .map(ToOwned::to_owned)
                 .or_else(|| self.downloads_dir.as_ref().map(ToOwned::to_owned)),
+            runtime_dir: account_config
+                .runtime_dir
+                .as_ref()
+                .map(ToOwned::to_owned)
+                .or_else(|| self.runtime_dir.as_ref().map(ToOwned::to_owned)),
             folder: account_config.folder.clone(),
             envelope: account_config.envelope.clone(),
             flag: account_config.flag.clone(),
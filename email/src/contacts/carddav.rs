@@ -0,0 +1,231 @@
+//! Module dedicated to CardDAV synchronization of contacts.
+//!
+//! This module contains a minimal CardDAV ([RFC 6352]) client able to
+//! push harvested contacts to an address book collection, and to pull
+//! contacts from it. Authentication is done using HTTP Basic.
+//!
+//! [RFC 6352]: https://www.rfc-editor.org/rfc/rfc6352
+
+use http::{ureq::http::Request, Client as HttpClient};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tracing::debug;
+
+use super::{Contacts, Error, Result};
+use crate::account::config::passwd::PasswordConfig;
+
+/// The REPORT request body used to fetch all the vCards of an
+/// address book collection.
+const ADDRESSBOOK_QUERY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<C:addressbook-query xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:carddav">
+  <D:prop>
+    <C:address-data/>
+  </D:prop>
+</C:addressbook-query>"#;
+
+/// Matches the content of address data elements, whatever the
+/// namespace prefix used by the server.
+static ADDRESS_DATA: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?s)<(?:[\w-]+:)?address-data[^>]*>(.*?)</(?:[\w-]+:)?address-data>").unwrap()
+});
+
+/// The CardDAV configuration.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct CardDavConfig {
+    /// The URL of the address book collection.
+    pub url: String,
+
+    /// The CardDAV server login.
+    pub login: String,
+
+    /// The CardDAV server password.
+    pub passwd: PasswordConfig,
+}
+
+/// The CardDAV client.
+#[derive(Clone, Debug)]
+pub struct CardDavClient {
+    config: CardDavConfig,
+    http: HttpClient,
+}
+
+impl CardDavClient {
+    /// Creates a new CardDAV client from the given configuration.
+    pub fn new(config: CardDavConfig) -> Self {
+        Self {
+            config,
+            http: HttpClient::new(),
+        }
+    }
+
+    /// Builds the HTTP Basic authorization header value.
+    async fn authorization(&self) -> Result<String> {
+        let passwd = self
+            .config
+            .passwd
+            .get()
+            .await
+            .map_err(Error::GetCardDavPasswordError)?;
+        let passwd = passwd.lines().next().unwrap_or_default();
+        let credentials = format!("{}:{passwd}", self.config.login);
+        Ok(format!("Basic {}", base64(credentials.as_bytes())))
+    }
+
+    /// Pushes the given contacts to the address book collection.
+    ///
+    /// Each contact is stored in its own vCard resource, named after
+    /// the contact unique identifier. Existing resources are
+    /// overridden.
+    pub async fn push(&self, contacts: &Contacts) -> Result<()> {
+        let auth = self.authorization().await?;
+        let base_url = self.config.url.trim_end_matches('/');
+
+        for contact in contacts.values() {
+            let url = format!("{base_url}/{}.vcf", urlencoding::encode(&contact.uid()));
+            let vcard = contact.to_vcard();
+            let auth = auth.clone();
+
+            debug!(url, "pushing contact to carddav server");
+
+            let url_clone = url.clone();
+            let res = self
+                .http
+                .send(move |agent| {
+                    agent
+                        .put(url_clone)
+                        .header("Authorization", auth)
+                        .header("Content-Type", "text/vcard; charset=utf-8")
+                        .send(vcard)
+                })
+                .await
+                .map_err(|err| Error::SendCardDavRequestError(err, url.clone()))?;
+
+            let status = res.status();
+
+            if !status.is_success() {
+                return Err(Error::PushCardDavContactError(status, url));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pulls all the contacts of the address book collection.
+    pub async fn pull(&self) -> Result<Contacts> {
+        let auth = self.authorization().await?;
+        let url = self.config.url.clone();
+
+        debug!(url, "pulling contacts from carddav server");
+
+        let url_clone = url.clone();
+        let res = self
+            .http
+            .send(move |agent| {
+                let req = Request::builder()
+                    .method("REPORT")
+                    .uri(url_clone)
+                    .header("Authorization", auth)
+                    .header("Content-Type", "application/xml; charset=utf-8")
+                    .header("Depth", "1")
+                    .body(ADDRESSBOOK_QUERY)?;
+                agent.run(req)
+            })
+            .await
+            .map_err(|err| Error::SendCardDavRequestError(err, url.clone()))?;
+
+        let status = res.status();
+
+        if !status.is_success() {
+            return Err(Error::PullCardDavContactsError(status, url));
+        }
+
+        let body = res
+            .into_body()
+            .read_to_string()
+            .map_err(|err| Error::ReadCardDavResponseError(err, url))?;
+
+        let mut contacts = Contacts::default();
+
+        for data in ADDRESS_DATA.captures_iter(&body) {
+            contacts.merge_vcard(&unescape_xml(&data[1]));
+        }
+
+        Ok(contacts)
+    }
+}
+
+/// Unescapes the XML text content of an address data element.
+fn unescape_xml(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|text| text.strip_suffix("]]>"));
+
+    match text {
+        Some(cdata) => cdata.to_owned(),
+        None => text
+            .unwrap_or_default()
+            .replace("&#13;", "\r")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&"),
+    }
+}
+
+/// Encodes the given bytes using the standard base64 alphabet, with
+/// padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or_default(),
+            chunk.get(2).copied().unwrap_or_default(),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn base64() {
+        assert_eq!(super::base64(b""), "");
+        assert_eq!(super::base64(b"f"), "Zg==");
+        assert_eq!(super::base64(b"fo"), "Zm8=");
+        assert_eq!(super::base64(b"foo"), "Zm9v");
+        assert_eq!(super::base64(b"user:passwd"), "dXNlcjpwYXNzd2Q=");
+    }
+
+    #[test]
+    fn unescape_xml() {
+        let text = "BEGIN:VCARD&#13;\nFN:A &amp; B&#13;\nEND:VCARD";
+        assert_eq!(
+            super::unescape_xml(text),
+            "BEGIN:VCARD\r\nFN:A & B\r\nEND:VCARD"
+        );
+
+        let cdata = "<![CDATA[FN:A & B]]>";
+        assert_eq!(super::unescape_xml(cdata), "FN:A & B");
+    }
+}
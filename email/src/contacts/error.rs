@@ -0,0 +1,76 @@
+use std::{any::Any, io, path::PathBuf, result};
+
+#[cfg(feature = "carddav")]
+use http::ureq::http::StatusCode;
+use thiserror::Error;
+
+use crate::{AnyBoxedError, AnyError, ErrorKind};
+
+/// The global `Result` alias of the module.
+pub type Result<T> = result::Result<T, Error>;
+
+/// The global `Error` enum of the module.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot read contacts at {1}")]
+    ReadContactsError(#[source] io::Error, PathBuf),
+    #[error("cannot write contacts at {1}")]
+    WriteContactsError(#[source] io::Error, PathBuf),
+
+    #[cfg(feature = "carddav")]
+    #[error("cannot get carddav password")]
+    GetCardDavPasswordError(#[source] secret::Error),
+    #[cfg(feature = "carddav")]
+    #[error("cannot send carddav request to {1}")]
+    SendCardDavRequestError(#[source] http::Error, String),
+    #[cfg(feature = "carddav")]
+    #[error("cannot push contact to {1}: {0}")]
+    PushCardDavContactError(StatusCode, String),
+    #[cfg(feature = "carddav")]
+    #[error("cannot pull contacts from {1}: {0}")]
+    PullCardDavContactsError(StatusCode, String),
+    #[cfg(feature = "carddav")]
+    #[error("cannot read carddav response from {1}")]
+    ReadCardDavResponseError(#[source] http::ureq::Error, String),
+}
+
+impl Error {
+    /// Returns the kind of the error.
+    ///
+    /// See [`ErrorKind`] for the available kinds.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::ReadContactsError(..) | Self::WriteContactsError(..) => ErrorKind::Other,
+            #[cfg(feature = "carddav")]
+            Self::GetCardDavPasswordError(_) => ErrorKind::Auth,
+            #[cfg(feature = "carddav")]
+            Self::SendCardDavRequestError(..) | Self::ReadCardDavResponseError(..) => {
+                ErrorKind::Network
+            }
+            #[cfg(feature = "carddav")]
+            Self::PushCardDavContactError(status, _)
+            | Self::PullCardDavContactsError(status, _) => match status.as_u16() {
+                401 | 403 => ErrorKind::Auth,
+                404 => ErrorKind::NotFound,
+                409 | 412 => ErrorKind::Conflict,
+                _ => ErrorKind::Protocol,
+            },
+        }
+    }
+}
+
+impl AnyError for Error {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn kind(&self) -> ErrorKind {
+        Error::kind(self)
+    }
+}
+
+impl From<Error> for AnyBoxedError {
+    fn from(err: Error) -> Self {
+        Box::new(err)
+    }
+}
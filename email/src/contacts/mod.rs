@@ -0,0 +1,312 @@
+//! # Contacts
+//!
+//! Module dedicated to address book harvesting and autocompletion.
+//!
+//! Contacts are harvested from the senders and recipients of
+//! envelopes, then ranked by frequency and recency (frecency) so that
+//! clients can autocomplete addresses with [`Contacts::complete`].
+//!
+//! When synchronizing, contacts are automatically harvested from new
+//! envelopes and persisted in the cache directory of each side.
+//!
+//! Contacts can be exported to and imported from vCard, and shared
+//! with other mail clients through a CardDAV server (requires the
+//! `carddav` cargo feature).
+
+#[cfg(feature = "carddav")]
+pub mod carddav;
+mod error;
+mod vcard;
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, FixedOffset, Utc};
+
+#[doc(inline)]
+pub use self::error::{Error, Result};
+use crate::envelope::{Address, Envelope};
+
+/// The name of the file containing harvested contacts, at the root of
+/// the Maildir cache directory.
+pub const CONTACTS_FILE_NAME: &str = ".contacts";
+
+/// The number of days after which the rank of a contact is halved.
+const RANK_HALF_LIFE_DAYS: f64 = 30.;
+
+/// The harvested contact.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Contact {
+    /// The email address of the contact.
+    pub addr: String,
+
+    /// The last known display name of the contact.
+    pub name: Option<String>,
+
+    /// The number of times the contact has been seen.
+    pub count: u64,
+
+    /// The UNIX timestamp of the last time the contact has been seen.
+    pub last_seen: i64,
+}
+
+impl Contact {
+    /// Computes the frecency rank of the contact at the given UNIX
+    /// timestamp.
+    ///
+    /// The rank is the number of times the contact has been seen,
+    /// halved every [`RANK_HALF_LIFE_DAYS`] elapsed since the last
+    /// time it has been seen.
+    pub fn rank(&self, now: i64) -> f64 {
+        let age_days = (now - self.last_seen).max(0) as f64 / 86400.;
+        self.count as f64 * 0.5f64.powf(age_days / RANK_HALF_LIFE_DAYS)
+    }
+
+    /// Returns `true` if the email address or one of the words of the
+    /// display name starts with the given lowercase prefix.
+    fn matches(&self, prefix: &str) -> bool {
+        if self.addr.to_lowercase().starts_with(prefix) {
+            return true;
+        }
+
+        match &self.name {
+            Some(name) => {
+                let name = name.to_lowercase();
+                name.starts_with(prefix) || name.split_whitespace().any(|w| w.starts_with(prefix))
+            }
+            None => false,
+        }
+    }
+}
+
+/// The harvested contacts, indexed by lowercase email address.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Contacts(BTreeMap<String, Contact>);
+
+impl Contacts {
+    /// Returns the path of the contacts file for the given cache
+    /// root directory.
+    pub fn path(root_dir: impl AsRef<Path>) -> PathBuf {
+        root_dir.as_ref().join(CONTACTS_FILE_NAME)
+    }
+
+    /// Reads contacts from the given cache root directory.
+    ///
+    /// A missing contacts file is considered empty. Malformed lines
+    /// are skipped.
+    pub fn read(root_dir: impl AsRef<Path>) -> Result<Self> {
+        let path = Self::path(root_dir);
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(Error::ReadContactsError(err, path)),
+        };
+
+        let mut contacts = Self::default();
+
+        for line in contents.lines() {
+            let mut fields = line.splitn(4, '\t');
+
+            let (Some(addr), Some(count), Some(last_seen), name) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+
+            let (Ok(count), Ok(last_seen)) = (count.parse(), last_seen.parse()) else {
+                continue;
+            };
+
+            let contact = Contact {
+                addr: addr.to_owned(),
+                name: name.filter(|name| !name.is_empty()).map(ToOwned::to_owned),
+                count,
+                last_seen,
+            };
+
+            contacts.0.insert(addr.to_lowercase(), contact);
+        }
+
+        Ok(contacts)
+    }
+
+    /// Writes contacts to the given cache root directory.
+    pub fn write(&self, root_dir: impl AsRef<Path>) -> Result<()> {
+        let path = Self::path(root_dir);
+        let mut contents = String::new();
+
+        for contact in self.0.values() {
+            contents.push_str(&sanitize(&contact.addr));
+            contents.push('\t');
+            contents.push_str(&contact.count.to_string());
+            contents.push('\t');
+            contents.push_str(&contact.last_seen.to_string());
+            contents.push('\t');
+            contents.push_str(&sanitize(contact.name.as_deref().unwrap_or_default()));
+            contents.push('\n');
+        }
+
+        fs::write(&path, contents).map_err(|err| Error::WriteContactsError(err, path))
+    }
+
+    /// Harvests the given address seen at the given date.
+    pub fn add(&mut self, addr: &Address, date: &DateTime<FixedOffset>) {
+        if addr.addr.trim().is_empty() {
+            return;
+        }
+
+        let last_seen = date.timestamp();
+        let name = addr.name.as_ref().filter(|name| !name.trim().is_empty());
+
+        let contact = self
+            .0
+            .entry(addr.addr.to_lowercase())
+            .or_insert_with(|| Contact {
+                addr: addr.addr.clone(),
+                name: None,
+                count: 0,
+                last_seen,
+            });
+
+        contact.count += 1;
+
+        if last_seen >= contact.last_seen {
+            contact.last_seen = last_seen;
+
+            if let Some(name) = name {
+                contact.name = Some(name.clone());
+            }
+        } else if contact.name.is_none() {
+            contact.name = name.cloned();
+        }
+    }
+
+    /// Harvests senders and recipients of the given envelope.
+    pub fn harvest(&mut self, envelope: &Envelope) {
+        self.add(&envelope.from, &envelope.date);
+
+        for addr in envelope.to_addrs.iter().chain(&envelope.cc_addrs) {
+            self.add(addr, &envelope.date);
+        }
+    }
+
+    /// Lists contacts matching the given prefix, from the best ranked
+    /// to the worst.
+    ///
+    /// The prefix is matched case-insensitively against the email
+    /// address and the words of the display name.
+    pub fn complete(&self, prefix: &str) -> Vec<&Contact> {
+        self.complete_at(prefix, Utc::now().timestamp())
+    }
+
+    fn complete_at(&self, prefix: &str, now: i64) -> Vec<&Contact> {
+        let prefix = prefix.trim().to_lowercase();
+
+        let mut contacts: Vec<_> = self
+            .0
+            .values()
+            .filter(|contact| contact.matches(&prefix))
+            .collect();
+
+        contacts.sort_by(|a, b| {
+            b.rank(now)
+                .total_cmp(&a.rank(now))
+                .then_with(|| b.last_seen.cmp(&a.last_seen))
+                .then_with(|| a.addr.cmp(&b.addr))
+        });
+
+        contacts
+    }
+}
+
+impl Deref for Contacts {
+    type Target = BTreeMap<String, Contact>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> FromIterator<&'a Envelope> for Contacts {
+    fn from_iter<T: IntoIterator<Item = &'a Envelope>>(iter: T) -> Self {
+        let mut contacts = Self::default();
+
+        for envelope in iter {
+            contacts.harvest(envelope);
+        }
+
+        contacts
+    }
+}
+
+/// Replaces tabulations and line breaks, which are used as
+/// separators by the contacts file.
+fn sanitize(field: &str) -> String {
+    field.replace(['\t', '\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::{Contact, Contacts};
+    use crate::envelope::Address;
+
+    #[test]
+    fn rank_half_life() {
+        let contact = Contact {
+            addr: String::from("alice@localhost"),
+            name: None,
+            count: 8,
+            last_seen: 0,
+        };
+
+        let days = |n: i64| n * 86400;
+
+        assert_eq!(contact.rank(0), 8.);
+        assert_eq!(contact.rank(days(30)), 4.);
+        assert_eq!(contact.rank(days(60)), 2.);
+        assert_eq!(contact.rank(days(90)), 1.);
+        // future timestamps do not increase the rank
+        assert_eq!(contact.rank(-days(30)), 8.);
+    }
+
+    #[test]
+    fn complete_contacts() {
+        let old = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap();
+        let new = DateTime::parse_from_rfc3339("2024-06-01T00:00:00Z").unwrap();
+        let now = new.timestamp();
+
+        let alice = Address::new(Some("Alice Smith"), "alice@localhost");
+        let alex = Address::new_nameless("alex@localhost");
+        let bob = Address::new(Some("Bob"), "bob@localhost");
+
+        let mut contacts = Contacts::default();
+        contacts.add(&alice, &old);
+        contacts.add(&alice, &old);
+        contacts.add(&alice, &old);
+        contacts.add(&alex, &new);
+        contacts.add(&bob, &new);
+
+        let addrs = |prefix| -> Vec<String> {
+            contacts
+                .complete_at(prefix, now)
+                .into_iter()
+                .map(|c| c.addr.clone())
+                .collect()
+        };
+
+        // alex has been seen once recently, which beats alice seen
+        // three times five months ago
+        assert_eq!(addrs("al"), vec!["alex@localhost", "alice@localhost"]);
+        assert_eq!(addrs("SMI"), vec!["alice@localhost"]);
+        assert_eq!(addrs("bob"), vec!["bob@localhost"]);
+        assert_eq!(addrs("carl"), Vec::<String>::new());
+        assert_eq!(addrs("").len(), 3);
+    }
+}
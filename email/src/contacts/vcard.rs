@@ -0,0 +1,211 @@
+//! Module dedicated to vCard serialization of contacts.
+//!
+//! Contacts are serialized as vCard 3.0 ([RFC 2426]), which is the
+//! version supported by most mail clients and CardDAV servers. Only
+//! the display name and the email address are exported.
+//!
+//! [RFC 2426]: https://www.rfc-editor.org/rfc/rfc2426
+
+use super::{Contact, Contacts};
+
+impl Contact {
+    /// Returns the unique identifier of the contact, used as vCard
+    /// `UID`.
+    pub fn uid(&self) -> String {
+        self.addr.to_lowercase()
+    }
+
+    /// Serializes the contact as a vCard.
+    pub fn to_vcard(&self) -> String {
+        let name = self.name.as_deref().unwrap_or(&self.addr);
+
+        let mut vcard = String::new();
+        vcard.push_str("BEGIN:VCARD\r\n");
+        vcard.push_str("VERSION:3.0\r\n");
+        vcard.push_str(&format!("UID:{}\r\n", escape(&self.uid())));
+        vcard.push_str(&format!("FN:{}\r\n", escape(name)));
+        vcard.push_str(&format!("EMAIL;TYPE=INTERNET:{}\r\n", escape(&self.addr)));
+        vcard.push_str("END:VCARD\r\n");
+        vcard
+    }
+}
+
+impl Contacts {
+    /// Serializes all contacts as a vCard stream.
+    pub fn to_vcard(&self) -> String {
+        self.values().map(Contact::to_vcard).collect()
+    }
+
+    /// Merges contacts from the given vCard stream.
+    ///
+    /// Each email address of each vCard becomes a contact. Unknown
+    /// contacts are added as never seen, so they can be completed
+    /// but are ranked after harvested ones. Names of known contacts
+    /// are only filled when missing.
+    pub fn merge_vcard(&mut self, vcard: &str) {
+        let mut name = None;
+        let mut addrs = Vec::new();
+
+        for line in unfold(vcard) {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+
+            // properties may be grouped (group.EMAIL) and may have
+            // parameters (EMAIL;TYPE=INTERNET)
+            let key = key.split(';').next().unwrap_or_default();
+            let key = key.rsplit('.').next().unwrap_or_default();
+
+            match key.to_uppercase().as_str() {
+                "BEGIN" => {
+                    name = None;
+                    addrs.clear();
+                }
+                "FN" => {
+                    name = Some(unescape(value)).filter(|name| !name.trim().is_empty());
+                }
+                "EMAIL" => {
+                    addrs.push(unescape(value));
+                }
+                "END" => {
+                    for addr in addrs.drain(..) {
+                        self.merge(addr, name.clone());
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn merge(&mut self, addr: String, name: Option<String>) {
+        let addr = addr.trim().to_owned();
+
+        if addr.is_empty() {
+            return;
+        }
+
+        let contact = self
+            .0
+            .entry(addr.to_lowercase())
+            .or_insert_with(|| Contact {
+                addr,
+                name: None,
+                count: 0,
+                last_seen: 0,
+            });
+
+        // exported contacts without name use their address as
+        // display name
+        if contact.name.is_none() {
+            contact.name = name.filter(|name| !name.eq_ignore_ascii_case(&contact.addr));
+        }
+    }
+}
+
+/// Unfolds vCard lines, as defined in [RFC 2425 section 5.8.1].
+///
+/// [RFC 2425 section 5.8.1]: https://www.rfc-editor.org/rfc/rfc2425#section-5.8.1
+fn unfold(vcard: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in vcard.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(folded), Some(last)) => last.push_str(folded),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+
+    lines
+}
+
+/// Escapes a vCard text value.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ',' => escaped.push_str("\\,"),
+            ';' => escaped.push_str("\\;"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => (),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Unescapes a vCard text value.
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => (),
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::Contacts;
+    use crate::envelope::Address;
+
+    #[test]
+    fn vcard_round_trip() {
+        let date = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap();
+
+        let mut contacts = Contacts::default();
+        contacts.add(
+            &Address::new(Some("Smith, Alice"), "alice@localhost"),
+            &date,
+        );
+        contacts.add(&Address::new_nameless("bob@localhost"), &date);
+
+        let vcard = contacts.to_vcard();
+        assert!(vcard.contains("FN:Smith\\, Alice\r\n"));
+        assert!(vcard.contains("FN:bob@localhost\r\n"));
+
+        let mut merged = Contacts::default();
+        merged.merge_vcard(&vcard);
+
+        let alice = merged.get("alice@localhost").unwrap();
+        assert_eq!(alice.name.as_deref(), Some("Smith, Alice"));
+        assert_eq!(alice.count, 0);
+        let bob = merged.get("bob@localhost").unwrap();
+        assert_eq!(bob.name, None);
+    }
+
+    #[test]
+    fn merge_folded_grouped_vcard() {
+        let vcard = concat!(
+            "BEGIN:VCARD\r\n",
+            "VERSION:4.0\r\n",
+            "FN:Carl\r\n",
+            " Jones\r\n",
+            "item1.EMAIL;TYPE=work:carl@localhost\r\n",
+            "EMAIL:carl@remote\r\n",
+            "END:VCARD\r\n",
+        );
+
+        let mut contacts = Contacts::default();
+        contacts.merge_vcard(vcard);
+
+        assert_eq!(contacts.len(), 2);
+        let carl = contacts.get("carl@remote").unwrap();
+        assert_eq!(carl.name.as_deref(), Some("CarlJones"));
+    }
+}
@@ -0,0 +1,217 @@
+//! # Doctor
+//!
+//! Module dedicated to configuration diagnosis. It exposes the
+//! [`DoctorReport`] returned by
+//! [`AccountConfig::validate`](crate::account::config::AccountConfig::validate)
+//! and by backend configurations `check_up` functions, so that
+//! applications can build a `doctor` command without re-implementing
+//! the checks.
+//!
+//! Contrary to the [`CheckUp`](crate::backend::feature::CheckUp)
+//! backend feature, a diagnosis never fails: every issue found is
+//! collected as a [`Problem`].
+
+use std::{fmt, vec};
+
+use crate::{AnyError, ErrorKind};
+
+/// The severity of a problem.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Severity {
+    /// The problem is worth knowing, but nothing needs to be done.
+    Info,
+
+    /// The problem may lead to unexpected behaviours.
+    Warning,
+
+    /// The problem prevents the account from working.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Info => write!(f, "info"),
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found during a diagnosis.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct Problem {
+    /// The severity of the problem.
+    pub severity: Severity,
+
+    /// The part of the configuration the problem is related to, for
+    /// example `imap` or `folder.aliases`.
+    pub scope: String,
+
+    /// The human-readable description of the problem.
+    pub message: String,
+
+    /// The kind of the error that caused the problem, if any.
+    #[cfg_attr(
+        feature = "derive",
+        serde(skip_serializing_if = "Option::is_none", serialize_with = "kind_code")
+    )]
+    pub kind: Option<ErrorKind>,
+}
+
+impl Problem {
+    /// Creates a new problem.
+    pub fn new(severity: Severity, scope: impl ToString, message: impl ToString) -> Self {
+        Self {
+            severity,
+            scope: scope.to_string(),
+            message: message.to_string(),
+            kind: None,
+        }
+    }
+
+    /// Creates a new problem with the [`Severity::Info`] severity.
+    pub fn info(scope: impl ToString, message: impl ToString) -> Self {
+        Self::new(Severity::Info, scope, message)
+    }
+
+    /// Creates a new problem with the [`Severity::Warning`] severity.
+    pub fn warning(scope: impl ToString, message: impl ToString) -> Self {
+        Self::new(Severity::Warning, scope, message)
+    }
+
+    /// Creates a new problem with the [`Severity::Error`] severity.
+    pub fn error(scope: impl ToString, message: impl ToString) -> Self {
+        Self::new(Severity::Error, scope, message)
+    }
+
+    /// Creates a new problem with the [`Severity::Error`] severity
+    /// from the given error.
+    ///
+    /// The kind of the problem is taken from the error.
+    pub fn from_error<E: AnyError + ?Sized>(scope: impl ToString, err: &E) -> Self {
+        Self::error(scope, err).with_kind(err.kind())
+    }
+
+    /// Defines the kind of the error that caused the problem, using
+    /// the builder pattern.
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.severity, self.scope, self.message)
+    }
+}
+
+#[cfg(feature = "derive")]
+fn kind_code<S: serde::Serializer>(kind: &Option<ErrorKind>, s: S) -> Result<S::Ok, S::Error> {
+    match kind {
+        Some(kind) => s.serialize_some(kind.code()),
+        None => s.serialize_none(),
+    }
+}
+
+/// The report of a diagnosis.
+///
+/// It is a structured list of problems. An empty report means that
+/// no problem has been found.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "derive", derive(serde::Serialize), serde(transparent))]
+pub struct DoctorReport {
+    problems: Vec<Problem>,
+}
+
+impl DoctorReport {
+    /// Creates a new, empty report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the given problem to the report.
+    pub fn push(&mut self, problem: Problem) {
+        self.problems.push(problem)
+    }
+
+    /// Adds all problems of the given report to the current one.
+    pub fn merge(&mut self, report: DoctorReport) {
+        self.problems.extend(report.problems)
+    }
+
+    /// Returns the problems of the report.
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems
+    }
+
+    /// Returns `true` if the report does not contain any problem.
+    pub fn is_empty(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Returns the highest severity of the report, if any.
+    pub fn severity(&self) -> Option<Severity> {
+        self.problems.iter().map(|p| p.severity).max()
+    }
+
+    /// Returns `true` if the report contains at least one problem
+    /// with the [`Severity::Error`] severity.
+    pub fn has_errors(&self) -> bool {
+        self.severity() == Some(Severity::Error)
+    }
+}
+
+impl From<Vec<Problem>> for DoctorReport {
+    fn from(problems: Vec<Problem>) -> Self {
+        Self { problems }
+    }
+}
+
+impl Extend<Problem> for DoctorReport {
+    fn extend<T: IntoIterator<Item = Problem>>(&mut self, iter: T) {
+        self.problems.extend(iter)
+    }
+}
+
+impl IntoIterator for DoctorReport {
+    type Item = Problem;
+    type IntoIter = vec::IntoIter<Problem>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.problems.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DoctorReport, Problem, Severity};
+
+    #[test]
+    fn report_severity() {
+        let mut report = DoctorReport::new();
+        assert_eq!(report.severity(), None);
+        assert!(!report.has_errors());
+
+        report.push(Problem::info("account", "info"));
+        report.push(Problem::warning("folder.aliases", "warning"));
+        assert_eq!(report.severity(), Some(Severity::Warning));
+        assert!(!report.has_errors());
+
+        report.merge(vec![Problem::error("imap", "error")].into());
+        assert_eq!(report.severity(), Some(Severity::Error));
+        assert!(report.has_errors());
+        assert_eq!(report.problems().len(), 3);
+    }
+}
@@ -1,10 +1,12 @@
 use async_trait::async_trait;
-use imap_client::imap_next::imap_types::sequence::{Sequence, SequenceSet};
 use tracing::{debug, info};
-use utf7_imap::encode_utf7_imap as encode_utf7;
 
-use super::{AddFlags, Flags};
-use crate::{envelope::Id, imap::ImapContext, AnyResult, Error};
+use super::{
+    gmail::{add_gmail_labels, split_gmail_labels},
+    imap::{search_imap_uid_set, to_imap_uid_set},
+    AddFlags, Flags,
+};
+use crate::{envelope::Id, imap::ImapContext, search_query::SearchEmailsQuery, AnyResult};
 
 #[derive(Clone, Debug)]
 pub struct AddImapFlags {
@@ -34,31 +36,51 @@ async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()>
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
+        let folder_encoded = client.encode_mailbox(&folder);
         debug!("utf7 encoded folder: {folder_encoded}");
 
-        let uids: SequenceSet = match id {
-            Id::Single(id) => Sequence::try_from(id.as_str())
-                .map_err(Error::ParseSequenceError)?
-                .into(),
-            Id::Multiple(ids) => ids
-                .iter()
-                .filter_map(|id| {
-                    let seq = Sequence::try_from(id.as_str());
-
-                    if let Err(err) = &seq {
-                        debug!(?id, ?err, "skipping invalid sequence");
-                    }
-
-                    seq.ok()
-                })
-                .collect::<Vec<_>>()
-                .try_into()
-                .map_err(Error::ParseSequenceError)?,
-        };
+        let uids = to_imap_uid_set(id)?;
+        let (flags, labels) = split_gmail_labels(&client, flags);
 
         client.select_mailbox(&folder_encoded).await?;
-        client.add_flags(uids, flags.to_imap_flags_iter()).await?;
+        client
+            .add_flags(uids.clone(), flags.to_imap_flags_iter())
+            .await?;
+        add_gmail_labels(&mut client, &uids, &labels).await?;
+
+        Ok(())
+    }
+
+    async fn add_flags_matching(
+        &self,
+        folder: &str,
+        query: &SearchEmailsQuery,
+        flags: &Flags,
+    ) -> AnyResult<()> {
+        info!("adding imap flag(s) {flags} to envelopes matching query from folder {folder}");
+
+        let mut client = self.ctx.client().await;
+        let config = &client.account_config;
+
+        let folder = config.get_folder_alias(folder);
+        let folder_encoded = client.encode_mailbox(&folder);
+        debug!("utf7 encoded folder: {folder_encoded}");
+
+        let data = client.select_mailbox(&folder_encoded).await?;
+
+        if data.exists.unwrap_or_default() == 0 {
+            return Ok(());
+        }
+
+        let Some(uids) = search_imap_uid_set(&mut client, query).await? else {
+            return Ok(());
+        };
+
+        let (flags, labels) = split_gmail_labels(&client, flags);
+        client
+            .add_flags_silently(uids.clone(), flags.to_imap_flags_iter())
+            .await?;
+        add_gmail_labels(&mut client, &uids, &labels).await?;
 
         Ok(())
     }
@@ -8,7 +8,7 @@
 use async_trait::async_trait;
 
 use super::{Flag, Flags};
-use crate::{envelope::Id, AnyResult};
+use crate::{email::error::Error, envelope::Id, search_query::SearchEmailsQuery, AnyResult};
 
 #[async_trait]
 pub trait AddFlags: Send + Sync {
@@ -21,4 +21,21 @@ pub trait AddFlags: Send + Sync {
     async fn add_flag(&self, folder: &str, id: &Id, flag: Flag) -> AnyResult<()> {
         self.add_flags(folder, id, &Flags::from_iter([flag])).await
     }
+
+    /// Add the given flags to all envelopes matching the given
+    /// query from the given folder.
+    ///
+    /// Contrary to [`AddFlags::add_flags`], envelopes do not need to be
+    /// listed first: backends supporting it apply flags in a single
+    /// server-side command (`UID STORE` for IMAP). An empty query
+    /// matches all envelopes of the folder.
+    async fn add_flags_matching(
+        &self,
+        folder: &str,
+        query: &SearchEmailsQuery,
+        flags: &Flags,
+    ) -> AnyResult<()> {
+        let _ = (folder, query, flags);
+        Err(Error::AddFlagsMatchingNotSupportedError.into())
+    }
 }
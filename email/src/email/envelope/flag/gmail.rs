@@ -0,0 +1,461 @@
+//! Module dedicated to Gmail labels.
+//!
+//! Gmail does not store IMAP keywords. Instead, user labels are
+//! exposed as mailboxes, and a few system labels match standard
+//! flags (`\Starred` is `\Flagged`, `\Draft` is `\Draft`). This
+//! module maps labels to flags and back, and stores custom flags as
+//! labels using the `X-GM-LABELS` message attribute when enabled by
+//! the `extensions.gmail.labels` IMAP option.
+//!
+//! See <https://developers.google.com/gmail/imap/imap-extensions>.
+
+use std::{collections::HashMap, num::NonZeroU32};
+
+use imap_client::imap_next::imap_types::sequence::SequenceSet;
+use tracing::debug;
+
+use super::{Flag, Flags};
+use crate::{
+    envelope::Envelopes,
+    folder::{Folder, INBOX},
+    imap::{
+        encoding::MailboxEncoding,
+        raw::{self, Value},
+        ImapClient,
+    },
+    AnyResult,
+};
+
+/// The capability advertised by Gmail IMAP servers.
+pub const GMAIL_CAPABILITY: &str = "X-GM-EXT-1";
+
+/// The message attribute holding Gmail labels, used by both FETCH
+/// and STORE.
+const GMAIL_LABELS: &str = "X-GM-LABELS";
+
+/// The system label of messages from the inbox.
+const INBOX_LABEL: &str = "\\Inbox";
+
+/// The prefixes of Gmail system mailboxes, which are not labels.
+const SYSTEM_MAILBOX_PREFIXES: [&str; 2] = ["[Gmail]", "[Google Mail]"];
+
+impl Flag {
+    /// Parses a flag from the given Gmail label.
+    ///
+    /// `\Starred` and `\Draft` map to their standard flags and user
+    /// labels map to custom flags, whereas other system labels (like
+    /// `\Inbox` or `\Important`) have no flag equivalent.
+    pub fn from_gmail_label(label: &str) -> Option<Self> {
+        match label {
+            "\\Starred" => Some(Flag::Flagged),
+            "\\Draft" => Some(Flag::Draft),
+            label if label.starts_with('\\') => None,
+            label => Some(Flag::custom(label)),
+        }
+    }
+
+    /// Returns the Gmail label matching the flag.
+    ///
+    /// Flags stored as regular IMAP flags by Gmail (`\Seen`,
+    /// `\Answered` and `\Deleted`) have no label equivalent.
+    pub fn to_gmail_label(&self) -> Option<String> {
+        match self {
+            Flag::Flagged => Some(String::from("\\Starred")),
+            Flag::Draft => Some(String::from("\\Draft")),
+            Flag::Custom(label) => Some(label.clone()),
+            Flag::Seen | Flag::Answered | Flag::Deleted => None,
+        }
+    }
+}
+
+impl Flags {
+    /// Parses flags from the given Gmail labels, skipping labels
+    /// without flag equivalent.
+    pub fn from_gmail_labels(labels: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        labels
+            .into_iter()
+            .filter_map(|label| Flag::from_gmail_label(label.as_ref()))
+            .collect()
+    }
+
+    /// Splits the flags into standard flags, stored as IMAP flags by
+    /// Gmail, and user labels.
+    pub fn split_gmail_labels(&self) -> (Flags, Vec<String>) {
+        let mut flags = Flags::default();
+        let mut labels = Vec::new();
+
+        for flag in self.iter() {
+            match flag {
+                Flag::Custom(label) => labels.push(label.clone()),
+                flag => {
+                    flags.insert(flag.clone());
+                }
+            }
+        }
+
+        (flags, labels)
+    }
+}
+
+/// Returns `true` if the given folder is a user label.
+///
+/// The inbox, special-use folders and folders under `[Gmail]` are
+/// system labels.
+fn is_label_folder(folder: &Folder) -> bool {
+    folder.kind.is_none()
+        && !folder.name.eq_ignore_ascii_case(INBOX)
+        && !SYSTEM_MAILBOX_PREFIXES
+            .iter()
+            .any(|prefix| folder.name.starts_with(prefix))
+}
+
+/// Returns `true` if the given label is a system label, like
+/// `\Inbox` or `\Starred`.
+fn is_system_label(label: &str) -> bool {
+    label.starts_with('\\')
+}
+
+/// Encodes the given labels as a parenthesized list.
+///
+/// System labels are sent as atoms, user labels are encoded like
+/// mailbox names then quoted. Labels which cannot be sent to the
+/// server are skipped.
+fn encode_labels<'a>(
+    encoding: MailboxEncoding,
+    labels: impl IntoIterator<Item = &'a str>,
+) -> String {
+    let labels: Vec<String> = labels
+        .into_iter()
+        .filter_map(|label| {
+            if is_system_label(label) {
+                return Some(label.to_owned());
+            }
+
+            let encoded = encoding.encode(label);
+
+            if raw::quotable(&encoded) {
+                Some(raw::quote(&encoded))
+            } else {
+                debug!("skipping invalid label {label}");
+                None
+            }
+        })
+        .collect();
+
+    format!("({})", labels.join(" "))
+}
+
+/// Decodes the given label value of a FETCH response.
+fn decode_label(encoding: MailboxEncoding, value: &Value) -> Option<String> {
+    let label = value.as_str()?;
+
+    if is_system_label(label) {
+        Some(label.to_owned())
+    } else {
+        Some(encoding.decode(label))
+    }
+}
+
+/// Parses the UID and the labels of the given untagged FETCH
+/// response.
+fn parse_fetch_labels(encoding: MailboxEncoding, res: &[u8]) -> Option<(NonZeroU32, Vec<String>)> {
+    let values = match raw::parse_values(res) {
+        Ok(values) => values,
+        Err(err) => {
+            debug!("skipping invalid response: {err}");
+            return None;
+        }
+    };
+
+    let [_, Value::Atom(kind), Value::List(items)] = values.as_slice() else {
+        return None;
+    };
+
+    if !kind.eq_ignore_ascii_case("FETCH") {
+        return None;
+    }
+
+    let mut uid = None;
+    let mut labels = None;
+
+    for item in items.chunks(2) {
+        match item {
+            [Value::Atom(name), Value::Atom(id)] if name.eq_ignore_ascii_case("UID") => {
+                uid = id.parse().ok();
+            }
+            [Value::Atom(name), Value::List(values)] if name.eq_ignore_ascii_case(GMAIL_LABELS) => {
+                labels = Some(
+                    values
+                        .iter()
+                        .filter_map(|label| decode_label(encoding, label))
+                        .collect(),
+                );
+            }
+            _ => (),
+        }
+    }
+
+    Some((uid?, labels?))
+}
+
+/// Splits the given flags into IMAP flags and Gmail labels.
+///
+/// Labels are only split when the client stores custom flags as
+/// Gmail labels, otherwise all flags are kept as IMAP flags.
+pub(crate) fn split_gmail_labels(client: &ImapClient, flags: &Flags) -> (Flags, Vec<String>) {
+    if client.gmail_labels_enabled() {
+        flags.split_gmail_labels()
+    } else {
+        (flags.clone(), Vec::new())
+    }
+}
+
+/// Lists the user labels of the account, from their mailboxes.
+pub(crate) async fn list_gmail_labels(client: &mut ImapClient) -> AnyResult<Vec<String>> {
+    let config = client.account_config.clone();
+    let folders = client.list_all_mailboxes(&config).await?;
+
+    Ok(folders
+        .into_iter()
+        .filter(is_label_folder)
+        .map(|folder| folder.name)
+        .collect())
+}
+
+/// Fetches the labels of the messages matching the given UIDs from
+/// the selected mailbox, indexed by UID.
+pub(crate) async fn fetch_gmail_labels(
+    client: &mut ImapClient,
+    uids: &SequenceSet,
+) -> AnyResult<HashMap<NonZeroU32, Vec<String>>> {
+    let uids = raw::encode_sequence_set(uids);
+    let cmd = format!("UID FETCH {uids} (UID {GMAIL_LABELS})");
+
+    let encoding = client.mailbox_encoding();
+    let labels = client
+        .execute_raw(cmd)
+        .await?
+        .iter()
+        .filter_map(|res| parse_fetch_labels(encoding, res))
+        .collect();
+
+    Ok(labels)
+}
+
+/// Stores the given labels to the messages matching the given UIDs
+/// from the selected mailbox.
+///
+/// The operation is `+` to add labels, `-` to remove them, or empty
+/// to replace them.
+async fn store_gmail_labels<'a>(
+    client: &mut ImapClient,
+    uids: &SequenceSet,
+    op: &str,
+    labels: impl IntoIterator<Item = &'a str>,
+) -> AnyResult<()> {
+    let labels = encode_labels(client.mailbox_encoding(), labels);
+    let uids = raw::encode_sequence_set(uids);
+    let cmd = format!("UID STORE {uids} {op}{GMAIL_LABELS} {labels}");
+
+    client.execute_raw(cmd).await?;
+
+    Ok(())
+}
+
+/// Adds the given labels to the messages matching the given UIDs
+/// from the selected mailbox.
+pub(crate) async fn add_gmail_labels(
+    client: &mut ImapClient,
+    uids: &SequenceSet,
+    labels: &[String],
+) -> AnyResult<()> {
+    if labels.is_empty() {
+        return Ok(());
+    }
+
+    store_gmail_labels(client, uids, "+", labels.iter().map(String::as_str)).await
+}
+
+/// Removes the given labels from the messages matching the given
+/// UIDs from the selected mailbox.
+pub(crate) async fn remove_gmail_labels(
+    client: &mut ImapClient,
+    uids: &SequenceSet,
+    labels: &[String],
+) -> AnyResult<()> {
+    if labels.is_empty() {
+        return Ok(());
+    }
+
+    store_gmail_labels(client, uids, "-", labels.iter().map(String::as_str)).await
+}
+
+/// Replaces the user labels of the messages matching the given UIDs
+/// from the given selected mailbox by the given ones.
+///
+/// System labels (like `\Inbox` or `\Important`) are kept: messages
+/// are grouped by system labels, and each group gets one STORE.
+///
+/// Does nothing when the client does not store custom flags as Gmail
+/// labels.
+pub(crate) async fn set_gmail_labels(
+    client: &mut ImapClient,
+    mbox: &str,
+    uids: &SequenceSet,
+    labels: &[String],
+) -> AnyResult<()> {
+    if !client.gmail_labels_enabled() {
+        return Ok(());
+    }
+
+    let mut groups: HashMap<Vec<String>, Vec<NonZeroU32>> = HashMap::new();
+
+    for (uid, labels) in fetch_gmail_labels(client, uids).await? {
+        let mut system: Vec<String> = labels
+            .into_iter()
+            .filter(|label| is_system_label(label))
+            .collect();
+
+        // the label of the selected mailbox may be omitted by the
+        // server, so it is added back to be sure it is kept
+        if mbox.eq_ignore_ascii_case(INBOX) && !system.iter().any(|l| l == INBOX_LABEL) {
+            system.push(INBOX_LABEL.to_owned());
+        }
+
+        system.sort();
+        groups.entry(system).or_default().push(uid);
+    }
+
+    for (system, uids) in groups {
+        let Ok(uids) = SequenceSet::try_from(uids) else {
+            continue;
+        };
+
+        let labels = system.iter().chain(labels).map(String::as_str);
+        store_gmail_labels(client, &uids, "", labels).await?;
+    }
+
+    Ok(())
+}
+
+/// Adds the labels of the given envelopes from the given mailbox to
+/// their flags.
+///
+/// Labels are fetched using the `X-GM-LABELS` attribute of the
+/// envelopes, which costs a single FETCH.
+pub(crate) async fn attach_gmail_labels(
+    client: &mut ImapClient,
+    mbox: &str,
+    envelopes: &mut Envelopes,
+) -> AnyResult<()> {
+    let uids: Vec<NonZeroU32> = envelopes.iter().filter_map(|e| e.id.parse().ok()).collect();
+
+    let Ok(uids) = SequenceSet::try_from(uids) else {
+        return Ok(());
+    };
+
+    client.select_mailbox(mbox).await?;
+    let mut labels = fetch_gmail_labels(client, &uids).await?;
+
+    for envelope in envelopes.iter_mut() {
+        let Ok(uid) = envelope.id.parse() else {
+            continue;
+        };
+
+        if let Some(labels) = labels.remove(&uid) {
+            let flags = labels
+                .iter()
+                .filter_map(|label| Flag::from_gmail_label(label));
+            envelope.flags.extend(flags);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::{encode_labels, parse_fetch_labels, Flag, Flags};
+    use crate::imap::encoding::MailboxEncoding;
+
+    #[test]
+    fn labels_to_flags() {
+        assert_eq!(Flag::from_gmail_label("\\Starred"), Some(Flag::Flagged));
+        assert_eq!(Flag::from_gmail_label("\\Draft"), Some(Flag::Draft));
+        assert_eq!(Flag::from_gmail_label("\\Important"), None);
+        assert_eq!(Flag::from_gmail_label("\\Inbox"), None);
+        assert_eq!(
+            Flag::from_gmail_label("Work/Projects"),
+            Some(Flag::custom("Work/Projects")),
+        );
+    }
+
+    #[test]
+    fn flags_to_labels() {
+        assert_eq!(Flag::Flagged.to_gmail_label().as_deref(), Some("\\Starred"));
+        assert_eq!(Flag::Draft.to_gmail_label().as_deref(), Some("\\Draft"));
+        assert_eq!(Flag::Seen.to_gmail_label(), None);
+        assert_eq!(Flag::Answered.to_gmail_label(), None);
+        assert_eq!(Flag::Deleted.to_gmail_label(), None);
+        assert_eq!(
+            Flag::custom("receipts").to_gmail_label().as_deref(),
+            Some("receipts"),
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let labels = ["\\Starred", "\\Draft", "receipts", "Work/Projects"];
+        let flags = Flags::from_gmail_labels(labels);
+
+        assert_eq!(
+            flags,
+            Flags::from_iter([
+                Flag::Flagged,
+                Flag::Draft,
+                Flag::custom("receipts"),
+                Flag::custom("Work/Projects"),
+            ]),
+        );
+
+        let mut round_trip: Vec<String> = flags.iter().filter_map(Flag::to_gmail_label).collect();
+        round_trip.sort();
+        let mut labels = labels.map(String::from).to_vec();
+        labels.sort();
+
+        assert_eq!(round_trip, labels);
+    }
+
+    #[test]
+    fn split_labels() {
+        let flags = Flags::from_iter([Flag::Seen, Flag::Flagged, Flag::custom("receipts")]);
+        let (flags, labels) = flags.split_gmail_labels();
+
+        assert_eq!(flags, Flags::from_iter([Flag::Seen, Flag::Flagged]));
+        assert_eq!(labels, vec![String::from("receipts")]);
+    }
+
+    #[test]
+    fn fetch_labels() {
+        let res = b"3 FETCH (X-GM-LABELS (\\Inbox \\Starred \"&AMk-t&AOk-\" receipts) UID 42)";
+        let (uid, labels) = parse_fetch_labels(MailboxEncoding::Utf7, res).unwrap();
+
+        assert_eq!(uid, NonZeroU32::new(42).unwrap());
+        assert_eq!(labels, ["\\Inbox", "\\Starred", "Été", "receipts"]);
+
+        let res = b"3 FETCH (FLAGS (\\Seen) UID 42)";
+        assert_eq!(parse_fetch_labels(MailboxEncoding::Utf7, res), None);
+    }
+
+    #[test]
+    fn store_labels() {
+        let labels = ["\\Inbox", "Été", "Work \"stuff\""];
+
+        assert_eq!(
+            encode_labels(MailboxEncoding::Utf7, labels),
+            "(\\Inbox \"&AMk-t&AOk-\" \"Work \\\"stuff\\\"\")",
+        );
+    }
+}
@@ -3,17 +3,78 @@
 //! This module contains flag-related mapping functions from the
 //! [imap] crate types.
 
-use std::fmt;
+use std::{fmt, num::NonZeroU32};
 
 use imap_client::imap_next::imap_types::{
     error::ValidationError,
     flag::{Flag as ImapFlag, FlagFetch},
     search::SearchKey,
+    sequence::{SeqOrUid, Sequence, SequenceSet},
 };
+use once_cell::sync::Lazy;
 use tracing::{debug, trace};
 
 use super::{Flag, Flags};
-use crate::email::error::Error;
+use crate::{
+    email::error::{Error, Result},
+    envelope::Id,
+    imap::ImapClient,
+    search_query::SearchEmailsQuery,
+    AnyResult,
+};
+
+/// The UID sequence set matching all envelopes of a mailbox (`1:*`).
+static ALL_UIDS: Lazy<SequenceSet> =
+    Lazy::new(|| Sequence::Range(SeqOrUid::Value(NonZeroU32::MIN), SeqOrUid::Asterisk).into());
+
+/// Builds the UID sequence set matching the given envelope id.
+///
+/// A single id can be a UID range or set, like `1:*` or `1,3:5`. Id
+/// sets are mapped as it is, without expanding their ranges.
+pub(crate) fn to_imap_uid_set(id: &Id) -> Result<SequenceSet> {
+    let uids = match id {
+        Id::Single(id) => SequenceSet::try_from(id.as_str()).map_err(Error::ParseSequenceError)?,
+        Id::Set(set) => {
+            SequenceSet::try_from(set.to_string().as_str()).map_err(Error::ParseSequenceError)?
+        }
+        Id::Multiple(ids) => ids
+            .iter()
+            .filter_map(|id| {
+                let seq = SequenceSet::try_from(id.as_str());
+
+                if let Err(err) = &seq {
+                    debug!(?id, ?err, "skipping invalid sequence");
+                }
+
+                seq.ok()
+            })
+            .flat_map(|seq| seq.0.into_inner())
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(Error::ParseSequenceError)?,
+    };
+
+    Ok(uids)
+}
+
+/// Searches the UID sequence set of envelopes matching the given
+/// query, from the mailbox selected by the given client.
+///
+/// An empty query matches all envelopes without searching, and
+/// `None` is returned when no envelope matches.
+pub(crate) async fn search_imap_uid_set(
+    client: &mut ImapClient,
+    query: &SearchEmailsQuery,
+) -> AnyResult<Option<SequenceSet>> {
+    if query.filter.is_none() {
+        return Ok(Some(ALL_UIDS.clone()));
+    }
+
+    let uids = client.search_uids(query.to_imap_search_criteria()).await?;
+    debug!(?uids, "found {} envelopes matching query", uids.len());
+
+    Ok(SequenceSet::try_from(uids).ok())
+}
 
 impl Flags {
     pub fn from_imap_flag_fetches(fetches: &[FlagFetch<'_>]) -> Self {
@@ -28,6 +89,20 @@ pub fn from_imap_flag_fetches(fetches: &[FlagFetch<'_>]) -> Self {
         }))
     }
 
+    pub fn from_imap_flags(flags: &[ImapFlag<'_>]) -> Self {
+        Flags::from_iter(
+            flags
+                .iter()
+                .filter_map(|flag| match Flag::try_from_imap_flag(flag) {
+                    Ok(flag) => Some(flag),
+                    Err(_err) => {
+                        trace!("{_err:?}");
+                        None
+                    }
+                }),
+        )
+    }
+
     pub fn to_imap_flags_iter(
         &self,
     ) -> impl IntoIterator<Item = ImapFlag<'static>> + fmt::Debug + Clone + '_ {
@@ -57,15 +132,26 @@ pub fn to_imap_string(&self) -> String {
 
     pub fn try_from_imap_fetch(fetch: &FlagFetch<'_>) -> Result<Self, Error> {
         match fetch {
-            FlagFetch::Flag(ImapFlag::Seen) => Ok(Flag::Seen),
-            FlagFetch::Flag(ImapFlag::Answered) => Ok(Flag::Answered),
-            FlagFetch::Flag(ImapFlag::Flagged) => Ok(Flag::Flagged),
-            FlagFetch::Flag(ImapFlag::Deleted) => Ok(Flag::Deleted),
-            FlagFetch::Flag(ImapFlag::Draft) => Ok(Flag::Draft),
-            FlagFetch::Flag(flag) => Err(Error::ParseFlagImapError(flag.to_string())),
+            FlagFetch::Flag(flag) => Self::try_from_imap_flag(flag),
             FlagFetch::Recent => Err(Error::ParseFlagImapError("\\Recent".into())),
         }
     }
+
+    /// Parses a flag from an IMAP flag.
+    ///
+    /// Keywords are parsed as custom flags, whereas unknown system
+    /// flags (starting with a backslash) are rejected.
+    pub fn try_from_imap_flag(flag: &ImapFlag<'_>) -> Result<Self, Error> {
+        match flag {
+            ImapFlag::Seen => Ok(Flag::Seen),
+            ImapFlag::Answered => Ok(Flag::Answered),
+            ImapFlag::Flagged => Ok(Flag::Flagged),
+            ImapFlag::Deleted => Ok(Flag::Deleted),
+            ImapFlag::Draft => Ok(Flag::Draft),
+            ImapFlag::Keyword(keyword) => Ok(Flag::custom(keyword)),
+            flag => Err(Error::ParseFlagImapError(flag.to_string())),
+        }
+    }
 }
 
 impl TryFrom<Flag> for ImapFlag<'static> {
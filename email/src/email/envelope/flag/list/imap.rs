@@ -0,0 +1,74 @@
+use async_trait::async_trait;
+use imap_client::imap_next::imap_types::flag::FlagPerm;
+use tracing::{debug, info};
+
+use super::{Flags, ListFlags};
+use crate::{
+    flag::{gmail::list_gmail_labels, Flag},
+    imap::ImapContext,
+    AnyResult,
+};
+
+#[derive(Clone, Debug)]
+pub struct ListImapFlags {
+    ctx: ImapContext,
+}
+
+impl ListImapFlags {
+    pub fn new(ctx: &ImapContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &ImapContext) -> Box<dyn ListFlags> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn ListFlags>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl ListFlags for ListImapFlags {
+    async fn list_flags(&self, folder: &str) -> AnyResult<Flags> {
+        info!("listing imap flags from folder {folder}");
+
+        let mut client = self.ctx.client().await;
+        let config = &client.account_config;
+
+        let folder = config.get_folder_alias(folder);
+        let folder_encoded = client.encode_mailbox(&folder);
+        debug!("utf7 encoded folder: {folder_encoded}");
+
+        let data = client.examine_mailbox(&folder_encoded).await?;
+
+        // PERMANENTFLAGS is optional: servers not sending it allow
+        // all flags from FLAGS to be stored permanently
+        let flags = match data.permanent_flags {
+            Some(flags) => flags
+                .into_iter()
+                .filter_map(|flag| match flag {
+                    FlagPerm::Flag(flag) => Some(flag),
+                    FlagPerm::Asterisk => {
+                        debug!("folder {folder} accepts new keywords");
+                        None
+                    }
+                })
+                .collect(),
+            None => data.flags.unwrap_or_default(),
+        };
+
+        let mut flags = Flags::from_imap_flags(&flags);
+
+        if client.gmail_labels_enabled() {
+            flags.extend(
+                list_gmail_labels(&mut client)
+                    .await?
+                    .into_iter()
+                    .map(Flag::Custom),
+            );
+        }
+
+        Ok(flags)
+    }
+}
@@ -0,0 +1,52 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::{Flags, ListFlags};
+use crate::{
+    flag::{maildir::read_mdir_keywords, Flag},
+    maildir::MaildirContextSync,
+    AnyResult,
+};
+
+#[derive(Clone)]
+pub struct ListMaildirFlags {
+    ctx: MaildirContextSync,
+}
+
+impl ListMaildirFlags {
+    pub fn new(ctx: &MaildirContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MaildirContextSync) -> Box<dyn ListFlags> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MaildirContextSync) -> Option<Box<dyn ListFlags>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl ListFlags for ListMaildirFlags {
+    async fn list_flags(&self, folder: &str) -> AnyResult<Flags> {
+        info!("listing maildir flags from folder {folder}");
+
+        let ctx = self.ctx.lock().await;
+        let mdir = ctx.get_maildir_from_folder_alias(folder)?;
+
+        let mut flags = Flags::from_iter([
+            Flag::Seen,
+            Flag::Answered,
+            Flag::Flagged,
+            Flag::Deleted,
+            Flag::Draft,
+        ]);
+
+        // keywords are the ones registered in the Dovecot keywords
+        // file of the folder
+        flags.extend(read_mdir_keywords(mdir.path()).iter().cloned());
+
+        Ok(flags)
+    }
+}
@@ -0,0 +1,22 @@
+#[cfg(feature = "imap")]
+pub mod imap;
+#[cfg(feature = "maildir")]
+pub mod maildir;
+#[cfg(feature = "notmuch")]
+pub mod notmuch;
+
+use async_trait::async_trait;
+
+use super::Flags;
+use crate::AnyResult;
+
+#[async_trait]
+pub trait ListFlags: Send + Sync {
+    /// List flags that can be permanently stored in the given
+    /// folder.
+    ///
+    /// Custom flags represent keywords (IMAP) or tags (Notmuch). The
+    /// returned list only contains keywords known by the backend: a
+    /// backend may still accept new ones.
+    async fn list_flags(&self, folder: &str) -> AnyResult<Flags>;
+}
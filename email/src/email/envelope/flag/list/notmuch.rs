@@ -0,0 +1,44 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::{Flags, ListFlags};
+use crate::{email::error::Error, notmuch::NotmuchContextSync, AnyResult};
+
+#[derive(Clone)]
+pub struct ListNotmuchFlags {
+    ctx: NotmuchContextSync,
+}
+
+impl ListNotmuchFlags {
+    pub fn new(ctx: &NotmuchContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NotmuchContextSync) -> Box<dyn ListFlags> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NotmuchContextSync) -> Option<Box<dyn ListFlags>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl ListFlags for ListNotmuchFlags {
+    async fn list_flags(&self, folder: &str) -> AnyResult<Flags> {
+        info!("listing notmuch flags from folder {folder}");
+
+        let ctx = self.ctx.lock().await;
+        let db = ctx.open_db()?;
+
+        // tags are global to the database, they do not depend on
+        // the folder
+        let tags = &ctx.notmuch_config.tags;
+        let mut flags = tags.to_flags(db.all_tags().map_err(Error::NotMuchFailure)?);
+        flags.extend(tags.iter().map(|(flag, _)| flag));
+
+        db.close().map_err(Error::NotMuchFailure)?;
+
+        Ok(flags)
+    }
+}
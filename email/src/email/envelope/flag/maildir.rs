@@ -3,7 +3,12 @@
 //! This module contains flag-related mapping functions from the
 //! [maildirpp] crate types.
 
-use std::collections::HashSet;
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+};
 
 use maildirs::MaildirEntry;
 use tracing::debug;
@@ -11,6 +16,207 @@
 use super::{Flag, Flags};
 use crate::email::error::{Error, Result};
 
+/// The name of the file mapping keyword letters of Maildir file names
+/// to keywords, as defined by Dovecot.
+///
+/// Each line is composed of an index, from 0 to 25 (matching letters
+/// `a` to `z`), followed by a space and the keyword: `0 $Forwarded`.
+pub const DOVECOT_KEYWORDS_FILE: &str = "dovecot-keywords";
+
+/// The default Maildir info separator, used when the file name does
+/// not contain info yet.
+#[cfg(not(windows))]
+const DEFAULT_INFO_SEPARATOR: &str = ":";
+#[cfg(windows)]
+const DEFAULT_INFO_SEPARATOR: &str = ";";
+
+/// The letters of standard Maildir flags mapped to [Flag]s.
+const STANDARD_FLAG_LETTERS: [char; 5] = ['D', 'F', 'R', 'S', 'T'];
+
+/// The kind of flags update applied by [update_mdir_entry_flags].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum FlagsUpdate {
+    /// Add the given flags to the existing ones.
+    Add,
+
+    /// Replace the existing flags by the given ones.
+    Set,
+
+    /// Remove the given flags from the existing ones.
+    Remove,
+}
+
+/// Update the flags of the given Maildir entry by only renaming its
+/// file, which preserves both its contents and its modification time.
+///
+/// Standard flags are mapped to their uppercase letters, whereas
+/// custom flags (keywords) are mapped to lowercase letters using the
+/// [DOVECOT_KEYWORDS_FILE] of the Maildir. Letters not mapped to any
+/// flag (like `P` for passed) are always preserved. Entries located
+/// in `new` are moved to `cur`, as their info is now set.
+///
+/// The file is not touched at all if its path does not change.
+pub(crate) fn update_mdir_entry_flags(
+    entry: &MaildirEntry,
+    flags: &Flags,
+    update: FlagsUpdate,
+) -> maildirs::Result<()> {
+    let path = entry.path();
+    let id = entry.id()?;
+    let (sep, letters) = split_info(entry)?;
+
+    let mut letters: BTreeSet<char> = letters.chars().collect();
+
+    if update == FlagsUpdate::Set {
+        letters.retain(|c| !STANDARD_FLAG_LETTERS.contains(c) && !c.is_ascii_lowercase());
+    }
+
+    let root = mdir_root(path);
+    let mut keywords = None;
+
+    for flag in flags.iter() {
+        let letter = match flag {
+            Flag::Seen => Some('S'),
+            Flag::Answered => Some('R'),
+            Flag::Flagged => Some('F'),
+            Flag::Deleted => Some('T'),
+            Flag::Draft => Some('D'),
+            Flag::Custom(keyword) => {
+                let Some(root) = root else {
+                    debug!("cannot find maildir root of {path:?}, skipping keyword {keyword}");
+                    continue;
+                };
+
+                let keywords = keywords.get_or_insert_with(|| read_keywords(root));
+
+                if update == FlagsUpdate::Remove {
+                    find_keyword(keywords, keyword)
+                } else {
+                    find_or_insert_keyword(root, keywords, keyword)?
+                }
+            }
+        };
+
+        let Some(letter) = letter else {
+            continue;
+        };
+
+        if update == FlagsUpdate::Remove {
+            letters.remove(&letter);
+        } else {
+            letters.insert(letter);
+        }
+    }
+
+    let letters: String = letters.into_iter().collect();
+    let mut next_path = path.with_file_name(format!("{id}{sep}2,{letters}"));
+
+    if let Some(dir) = next_path.parent().filter(|dir| dir.ends_with("new")) {
+        next_path = dir
+            .with_file_name("cur")
+            .join(next_path.file_name().unwrap());
+    }
+
+    if next_path != path {
+        fs::rename(path, &next_path)?;
+    }
+
+    Ok(())
+}
+
+/// Split the info of the given entry into its separator and its flag
+/// letters.
+fn split_info(entry: &MaildirEntry) -> maildirs::Result<(&str, &str)> {
+    let file_name = entry.file_name()?;
+    let id = entry.id()?;
+
+    Ok(match file_name[id.len()..].split_once("2,") {
+        Some((sep, letters)) if !sep.is_empty() => (sep, letters),
+        _ => (DEFAULT_INFO_SEPARATOR, ""),
+    })
+}
+
+/// Return the Maildir root of the given entry path.
+fn mdir_root(path: &Path) -> Option<&Path> {
+    path.parent()?.parent()
+}
+
+/// Return the keyword letter matching the given index.
+fn keyword_letter(index: usize) -> char {
+    (b'a' + index as u8) as char
+}
+
+/// Read the keywords of the given Maildir root, indexed by letter.
+fn read_keywords(root: &Path) -> Vec<Option<String>> {
+    let mut keywords = vec![None; 26];
+
+    let Ok(contents) = fs::read_to_string(root.join(DOVECOT_KEYWORDS_FILE)) else {
+        return keywords;
+    };
+
+    for line in contents.lines() {
+        let Some((index, keyword)) = line.split_once(' ') else {
+            continue;
+        };
+
+        let Ok(index) = index.parse::<usize>() else {
+            continue;
+        };
+
+        if let Some(slot) = keywords.get_mut(index) {
+            *slot = Some(keyword.to_owned());
+        }
+    }
+
+    keywords
+}
+
+/// Read the keywords registered in the [DOVECOT_KEYWORDS_FILE] of
+/// the given Maildir root, as custom flags.
+pub(crate) fn read_mdir_keywords(root: &Path) -> Flags {
+    read_keywords(root)
+        .into_iter()
+        .flatten()
+        .map(Flag::Custom)
+        .collect()
+}
+
+/// Find the letter of the given keyword.
+fn find_keyword(keywords: &[Option<String>], keyword: &str) -> Option<char> {
+    keywords
+        .iter()
+        .position(|k| k.as_deref() == Some(keyword))
+        .map(keyword_letter)
+}
+
+/// Find the letter of the given keyword, or register the keyword in
+/// the keywords file of the given Maildir root if it does not exist
+/// yet.
+fn find_or_insert_keyword(
+    root: &Path,
+    keywords: &mut [Option<String>],
+    keyword: &str,
+) -> maildirs::Result<Option<char>> {
+    if let Some(letter) = find_keyword(keywords, keyword) {
+        return Ok(Some(letter));
+    }
+
+    let Some(index) = keywords.iter().position(Option::is_none) else {
+        debug!("no keyword letter left in {root:?}, skipping keyword {keyword}");
+        return Ok(None);
+    };
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(root.join(DOVECOT_KEYWORDS_FILE))?;
+    writeln!(file, "{index} {keyword}")?;
+
+    keywords[index] = Some(keyword.to_owned());
+
+    Ok(Some(keyword_letter(index)))
+}
+
 impl TryFrom<MaildirEntry> for Flags {
     type Error = Error;
 
@@ -19,7 +225,7 @@ fn try_from(entry: MaildirEntry) -> Result<Self> {
             .flags()
             .map_err(|err| Error::GetMaildirFlagsError(err, entry.path().to_owned()))?;
 
-        let flags = flags
+        let mut flags: Flags = flags
             .iter()
             .filter_map(|flag| match Flag::try_from(*flag) {
                 Ok(flag) => Some(flag),
@@ -31,6 +237,24 @@ fn try_from(entry: MaildirEntry) -> Result<Self> {
             })
             .collect();
 
+        let (_, letters) = split_info(&entry)
+            .map_err(|err| Error::GetMaildirFlagsError(err, entry.path().to_owned()))?;
+
+        let keyword_letters: Vec<char> = letters.chars().filter(char::is_ascii_lowercase).collect();
+
+        if !keyword_letters.is_empty() {
+            if let Some(root) = mdir_root(entry.path()) {
+                let keywords = read_keywords(root);
+
+                for letter in keyword_letters {
+                    let index = (letter as u8 - b'a') as usize;
+                    if let Some(Some(keyword)) = keywords.get(index) {
+                        flags.insert(Flag::custom(keyword));
+                    }
+                }
+            }
+        }
+
         Ok(flags)
     }
 }
@@ -101,3 +325,111 @@ fn try_from(flag: Flag) -> Result<Self> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, File},
+        path::{Path, PathBuf},
+        time::{Duration, SystemTime},
+    };
+
+    use maildirs::MaildirEntry;
+
+    use super::{update_mdir_entry_flags, FlagsUpdate, DOVECOT_KEYWORDS_FILE};
+    use crate::envelope::{Flag, Flags};
+
+    const CONTENTS: &[u8] = b"Subject: test\r\n\r\nHello, world!\r\n";
+
+    fn mtime(path: &Path) -> SystemTime {
+        fs::metadata(path).unwrap().modified().unwrap()
+    }
+
+    /// Update the flags of the given entry, and check that its new
+    /// path matches the expected one, that its contents and its
+    /// modification time are preserved.
+    fn update(path: &Path, flags: Flags, update: FlagsUpdate, expected: &Path) -> Flags {
+        let mtime_before = mtime(path);
+
+        let entry = MaildirEntry::new(path);
+        update_mdir_entry_flags(&entry, &flags, update).unwrap();
+
+        assert!(!path.exists() || path == expected);
+        assert_eq!(fs::read(expected).unwrap(), CONTENTS);
+        assert_eq!(mtime(expected), mtime_before);
+
+        Flags::try_from(MaildirEntry::new(expected)).unwrap()
+    }
+
+    fn create(root: &Path, path: &str) -> PathBuf {
+        for dir in ["cur", "new", "tmp"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+
+        let path = root.join(path);
+        fs::write(&path, CONTENTS).unwrap();
+
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        path
+    }
+
+    #[test]
+    fn update_flags_only_renames() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let path = create(root, "new/1234.host");
+
+        let expected = root.join("cur/1234.host:2,Sa");
+        let flags = Flags::from_iter([Flag::Seen, Flag::custom("$Forwarded")]);
+        let flags = update(&path, flags.clone(), FlagsUpdate::Add, &expected);
+        assert_eq!(
+            flags,
+            Flags::from_iter([Flag::Seen, Flag::custom("$Forwarded")])
+        );
+
+        let path = expected;
+        let expected = root.join("cur/1234.host:2,Fb");
+        let flags = Flags::from_iter([Flag::Flagged, Flag::custom("$Label")]);
+        let flags = update(&path, flags, FlagsUpdate::Set, &expected);
+        assert_eq!(
+            flags,
+            Flags::from_iter([Flag::Flagged, Flag::custom("$Label")])
+        );
+
+        let path = expected;
+        let expected = root.join("cur/1234.host:2,b");
+        let flags = Flags::from_iter([Flag::Flagged, Flag::custom("$Unknown")]);
+        let flags = update(&path, flags, FlagsUpdate::Remove, &expected);
+        assert_eq!(flags, Flags::from_iter([Flag::custom("$Label")]));
+
+        let path = expected;
+        let flags = Flags::from_iter([Flag::custom("$Label")]);
+        update(&path, flags, FlagsUpdate::Add, &path);
+
+        let keywords = fs::read_to_string(root.join(DOVECOT_KEYWORDS_FILE)).unwrap();
+        assert_eq!(keywords, "0 $Forwarded\n1 $Label\n");
+    }
+
+    #[test]
+    fn update_flags_preserves_unknown_letters() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let path = create(root, "cur/5678.host:2,PS");
+
+        let expected = root.join("cur/5678.host:2,DP");
+        let flags = update(
+            &path,
+            Flags::from_iter([Flag::Draft]),
+            FlagsUpdate::Set,
+            &expected,
+        );
+        assert_eq!(flags, Flags::from_iter([Flag::Draft]));
+    }
+}
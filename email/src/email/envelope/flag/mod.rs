@@ -6,7 +6,10 @@
 pub mod add;
 pub mod config;
 #[cfg(feature = "imap")]
+pub mod gmail;
+#[cfg(feature = "imap")]
 pub mod imap;
+pub mod list;
 #[cfg(feature = "maildir")]
 pub mod maildir;
 #[cfg(feature = "notmuch")]
@@ -30,6 +33,15 @@
 #[doc(inline)]
 pub use self::sync::sync;
 use crate::email::error::Error;
+#[cfg(any(feature = "maildir", feature = "notmuch"))]
+use crate::{
+    envelope::{
+        list::{ListEnvelopes, ListEnvelopesOptions},
+        Id,
+    },
+    search_query::SearchEmailsQuery,
+    AnyResult,
+};
 
 /// The email envelope flag.
 ///
@@ -60,6 +72,9 @@ pub enum Flag {
     Draft,
 
     /// Flag used for all other use cases.
+    ///
+    /// Custom flags represent IMAP keywords (which Gmail exposes as
+    /// labels) and Notmuch tags.
     Custom(String),
 }
 
@@ -68,6 +83,11 @@ impl Flag {
     pub fn custom(flag: impl ToString) -> Self {
         Self::Custom(flag.to_string())
     }
+
+    /// Returns `true` if the flag is a custom flag (keyword).
+    pub fn is_custom(&self) -> bool {
+        matches!(self, Self::Custom(_))
+    }
 }
 
 /// Parse a flag from a string. If the string does not match any of
@@ -205,6 +225,16 @@ fn from_str(s: &str) -> Result<Self, Error> {
     }
 }
 
+impl Flags {
+    /// Returns an iterator over custom flags (keywords) only.
+    pub fn keywords(&self) -> impl Iterator<Item = &str> {
+        self.iter().filter_map(|flag| match flag {
+            Flag::Custom(keyword) => Some(keyword.as_str()),
+            _ => None,
+        })
+    }
+}
+
 impl FromIterator<Flag> for Flags {
     fn from_iter<T: IntoIterator<Item = Flag>>(iter: T) -> Self {
         Self(iter.into_iter().collect())
@@ -216,3 +246,142 @@ fn from(val: Flags) -> Self {
         val.iter().map(|flag| flag.to_string()).collect()
     }
 }
+
+/// Lists the id of envelopes matching the given query from the given
+/// folder.
+///
+/// Backends which cannot apply flags server-side rely on this
+/// function to implement the `*_flags_matching` variants. `None` is
+/// returned when no envelope matches.
+#[cfg(any(feature = "maildir", feature = "notmuch"))]
+pub(crate) async fn list_matching_ids(
+    list: &dyn ListEnvelopes,
+    folder: &str,
+    query: &SearchEmailsQuery,
+) -> AnyResult<Option<Id>> {
+    let opts = ListEnvelopesOptions {
+        page_size: 0,
+        page: 0,
+        query: Some(query.clone()),
+        preview: None,
+    };
+
+    let envelopes = list.list_envelopes(folder, opts).await?;
+
+    if envelopes.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(Id::multiple(envelopes.iter().map(|e| e.id.clone()))))
+}
+
+#[cfg(all(test, feature = "maildir"))]
+mod tests {
+    use std::{path::Path, sync::Arc};
+
+    use super::{add::AddFlags, remove::RemoveFlags, set::SetFlags, Flag, Flags};
+    use crate::{
+        account::config::AccountConfig,
+        backend::{Backend, BackendBuilder},
+        envelope::list::{maildir::ListMaildirEnvelopes, ListEnvelopes, ListEnvelopesOptions},
+        folder::add::AddFolder,
+        maildir::{config::MaildirConfig, MaildirContextBuilder, MaildirContextSync},
+        message::add::AddMessage,
+        search_query::SearchEmailsQuery,
+    };
+
+    async fn backend(root: &Path) -> Backend<MaildirContextSync> {
+        let account_config = Arc::new(AccountConfig::default());
+        let mdir_config = Arc::new(MaildirConfig {
+            root_dir: root.to_owned(),
+            maildirpp: false,
+            layout: None,
+            uid_db: false,
+            envelopes: Default::default(),
+        });
+        let ctx = MaildirContextBuilder::new(account_config.clone(), mdir_config);
+        let backend = BackendBuilder::new(account_config, ctx)
+            .build()
+            .await
+            .unwrap();
+
+        backend.add_folder("INBOX").await.unwrap();
+        for subject in ["alpha", "beta", "alpha again"] {
+            let raw = format!("Subject: {subject}\r\n\r\nHello!\r\n");
+            backend.add_message("INBOX", raw.as_bytes()).await.unwrap();
+        }
+
+        backend
+    }
+
+    async fn flags_of(backend: &Backend<MaildirContextSync>, subject: &str) -> Flags {
+        let envelopes = backend
+            .list_envelopes("INBOX", ListEnvelopesOptions::default())
+            .await
+            .unwrap();
+        let envelope = envelopes.iter().find(|e| e.subject == subject).unwrap();
+        envelope.flags.clone()
+    }
+
+    #[tokio::test]
+    async fn list_matching_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = backend(dir.path()).await;
+        let list = ListMaildirEnvelopes::new(&backend.context);
+
+        let query = "subject alpha".parse::<SearchEmailsQuery>().unwrap();
+        let id = super::list_matching_ids(&list, "INBOX", &query)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(id.iter().count(), 2);
+
+        let query = "subject gamma".parse::<SearchEmailsQuery>().unwrap();
+        let id = super::list_matching_ids(&list, "INBOX", &query)
+            .await
+            .unwrap();
+        assert_eq!(id, None);
+    }
+
+    #[tokio::test]
+    async fn bulk_flags_matching() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = backend(dir.path()).await;
+        let all = SearchEmailsQuery {
+            filter: None,
+            sort: None,
+        };
+
+        let alpha = "subject alpha".parse::<SearchEmailsQuery>().unwrap();
+        let flags = Flags::from_iter([Flag::Flagged]);
+        backend
+            .add_flags_matching("INBOX", &alpha, &flags)
+            .await
+            .unwrap();
+        assert!(flags_of(&backend, "alpha").await.contains(&Flag::Flagged));
+        assert!(flags_of(&backend, "alpha again")
+            .await
+            .contains(&Flag::Flagged));
+        assert!(!flags_of(&backend, "beta").await.contains(&Flag::Flagged));
+
+        let beta = "subject beta".parse::<SearchEmailsQuery>().unwrap();
+        let flags = Flags::from_iter([Flag::Seen, Flag::Answered]);
+        backend
+            .set_flags_matching("INBOX", &beta, &flags)
+            .await
+            .unwrap();
+        assert_eq!(flags_of(&backend, "beta").await, flags);
+
+        let flags = Flags::from_iter([Flag::Flagged, Flag::Seen]);
+        backend
+            .remove_flags_matching("INBOX", &all, &flags)
+            .await
+            .unwrap();
+        assert!(flags_of(&backend, "alpha").await.is_empty());
+        assert!(flags_of(&backend, "alpha again").await.is_empty());
+        assert_eq!(
+            flags_of(&backend, "beta").await,
+            Flags::from_iter([Flag::Answered]),
+        );
+    }
+}
@@ -5,39 +5,20 @@
 
 use notmuch::Message;
 
-use crate::flag::Flags;
+use crate::{flag::Flags, notmuch::config::NotmuchTagsConfig};
 
-use super::Flag;
+impl Flags {
+    /// Builds flags from the given Notmuch tags, using the default
+    /// tags mapping.
+    ///
+    /// See [`NotmuchTagsConfig::to_flags`] for custom mappings.
+    pub fn from_notmuch_tags(tags: impl IntoIterator<Item = String>) -> Self {
+        NotmuchTagsConfig::default().to_flags(tags)
+    }
+}
 
 impl From<&Message> for Flags {
     fn from(msg: &Message) -> Self {
-        let mut flags = Flags::default();
-        let mut unread = false;
-
-        for tag in msg.tags() {
-            match tag.as_str() {
-                "draft" => {
-                    flags.insert(Flag::Draft);
-                }
-                "flagged" => {
-                    flags.insert(Flag::Flagged);
-                }
-                "replied" => {
-                    flags.insert(Flag::Answered);
-                }
-                "unread" => {
-                    unread = true;
-                }
-                flag => {
-                    flags.insert(Flag::custom(flag));
-                }
-            }
-        }
-
-        if !unread {
-            flags.insert(Flag::Seen);
-        }
-
-        flags
+        Flags::from_notmuch_tags(msg.tags())
     }
 }
@@ -1,10 +1,20 @@
-use std::collections::HashSet;
-
 use async_trait::async_trait;
 use tracing::info;
 
 use super::{Flags, RemoveFlags};
-use crate::{email::error::Error, envelope::Id, maildir::MaildirContextSync, AnyResult};
+use crate::{
+    email::error::Error,
+    envelope::{
+        flag::list_matching_ids,
+        flag::maildir::{update_mdir_entry_flags, FlagsUpdate},
+        list::maildir::ListMaildirEnvelopes,
+        maildir::find_mdir_entries,
+        Id,
+    },
+    maildir::MaildirContextSync,
+    search_query::SearchEmailsQuery,
+    AnyResult,
+};
 
 #[derive(Clone)]
 pub struct RemoveMaildirFlags {
@@ -32,20 +42,36 @@ async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<
 
         let ctx = self.ctx.lock().await;
         let mdir = ctx.get_maildir_from_folder_alias(folder)?;
+        let uids = ctx.uid_db(&mdir)?;
 
-        id.iter()
-            .filter_map(|id| mdir.find(id).ok().flatten())
-            .try_for_each(|mut entry| {
-                entry.remove_flags(HashSet::from(flags)).map_err(|err| {
-                    Error::RemoveFlagsMaildirError(
-                        err,
-                        folder.to_owned(),
-                        id.to_string(),
-                        flags.clone(),
-                    )
-                })
-            })?;
+        find_mdir_entries(&mdir, uids.as_ref(), id)?.try_for_each(|entry| {
+            update_mdir_entry_flags(&entry, flags, FlagsUpdate::Remove).map_err(|err| {
+                Error::RemoveFlagsMaildirError(
+                    err,
+                    folder.to_owned(),
+                    id.to_string(),
+                    flags.clone(),
+                )
+            })
+        })?;
 
         Ok(())
     }
+
+    async fn remove_flags_matching(
+        &self,
+        folder: &str,
+        query: &SearchEmailsQuery,
+        flags: &Flags,
+    ) -> AnyResult<()> {
+        info!(
+            "removing maildir flag(s) {flags} from envelopes matching query from folder {folder}"
+        );
+
+        let list = ListMaildirEnvelopes::new(&self.ctx);
+        match list_matching_ids(&list, folder, query).await? {
+            Some(id) => self.remove_flags(folder, &id, flags).await,
+            None => Ok(()),
+        }
+    }
 }
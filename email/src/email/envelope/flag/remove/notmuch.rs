@@ -4,7 +4,11 @@
 
 use super::{Flags, RemoveFlags};
 use crate::{
-    email::error::Error, envelope::Id, flag::Flag, folder::FolderKind, notmuch::NotmuchContextSync,
+    email::error::Error,
+    envelope::{list::notmuch::ListNotmuchEnvelopes, Id},
+    flag::{list_matching_ids, Flag},
+    notmuch::NotmuchContextSync,
+    search_query::SearchEmailsQuery,
     AnyResult,
 };
 
@@ -32,16 +36,11 @@ impl RemoveFlags for RemoveNotmuchFlags {
     async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
         info!("removing notmuch flag(s) {flags} to envelope {id} from folder {folder}");
 
-        let config = &self.ctx.account_config;
         let ctx = self.ctx.lock().await;
+        let tags = &ctx.notmuch_config.tags;
         let db = ctx.open_db()?;
 
-        let ref folder = config.get_folder_alias(folder);
-        let folder_query = if ctx.maildirpp() && FolderKind::matches_inbox(folder) {
-            String::from("folder:\"\"")
-        } else {
-            format!("folder:{folder:?}")
-        };
+        let folder_query = ctx.folder_query(folder);
         let mid_query = format!("mid:\"/^({})$/\"", id.join("|"));
         let query = [folder_query, mid_query].join(" and ");
         debug!("notmuch query: {query:?}");
@@ -55,40 +54,35 @@ async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<
             let mut entry = MaildirEntry::new(msg.filename());
 
             for flag in flags.iter() {
+                tags.get_tag(flag).unset(&msg)?;
+
                 match flag {
                     Flag::Seen => {
-                        msg.add_tag("unread").map_err(Error::NotMuchFailure)?;
                         entry
                             .remove_flag(maildirs::Flag::Seen)
                             .map_err(Error::MaildirppFailure)?;
                     }
                     Flag::Answered => {
-                        msg.remove_tag("replied").map_err(Error::NotMuchFailure)?;
                         entry
                             .remove_flag(maildirs::Flag::Replied)
                             .map_err(Error::MaildirppFailure)?;
                     }
                     Flag::Flagged => {
-                        msg.remove_tag("flagged").map_err(Error::NotMuchFailure)?;
                         entry
                             .remove_flag(maildirs::Flag::Flagged)
                             .map_err(Error::MaildirppFailure)?;
                     }
                     Flag::Deleted => {
-                        msg.remove_tag("deleted").map_err(Error::NotMuchFailure)?;
                         entry
                             .remove_flag(maildirs::Flag::Trashed)
                             .map_err(Error::MaildirppFailure)?;
                     }
                     Flag::Draft => {
-                        msg.remove_tag("draft").map_err(Error::NotMuchFailure)?;
                         entry
                             .remove_flag(maildirs::Flag::Draft)
                             .map_err(Error::MaildirppFailure)?;
                     }
-                    Flag::Custom(tag) => {
-                        msg.remove_tag(tag).map_err(Error::NotMuchFailure)?;
-                    }
+                    Flag::Custom(_) => (),
                 }
 
                 if msg.filename() != entry.path() {
@@ -103,4 +97,21 @@ async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<
 
         Ok(())
     }
+
+    async fn remove_flags_matching(
+        &self,
+        folder: &str,
+        query: &SearchEmailsQuery,
+        flags: &Flags,
+    ) -> AnyResult<()> {
+        info!(
+            "removing notmuch flag(s) {flags} from envelopes matching query from folder {folder}"
+        );
+
+        let list = ListNotmuchEnvelopes::new(&self.ctx);
+        match list_matching_ids(&list, folder, query).await? {
+            Some(id) => self.remove_flags(folder, &id, flags).await,
+            None => Ok(()),
+        }
+    }
 }
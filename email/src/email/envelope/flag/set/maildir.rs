@@ -1,10 +1,20 @@
-use std::collections::HashSet;
-
 use async_trait::async_trait;
 use tracing::info;
 
 use super::{Flags, SetFlags};
-use crate::{email::error::Error, envelope::Id, maildir::MaildirContextSync, AnyResult};
+use crate::{
+    email::error::Error,
+    envelope::{
+        flag::list_matching_ids,
+        flag::maildir::{update_mdir_entry_flags, FlagsUpdate},
+        list::maildir::ListMaildirEnvelopes,
+        maildir::find_mdir_entries,
+        Id,
+    },
+    maildir::MaildirContextSync,
+    search_query::SearchEmailsQuery,
+    AnyResult,
+};
 
 #[derive(Clone)]
 pub struct SetMaildirFlags {
@@ -32,20 +42,29 @@ async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()>
 
         let ctx = self.ctx.lock().await;
         let mdir = ctx.get_maildir_from_folder_alias(folder)?;
+        let uids = ctx.uid_db(&mdir)?;
 
-        id.iter()
-            .filter_map(|id| mdir.find(id).ok().flatten())
-            .try_for_each(|mut entry| {
-                entry.update_flags(HashSet::from(flags)).map_err(|err| {
-                    Error::SetFlagsMaildirError(
-                        err,
-                        folder.to_owned(),
-                        id.to_string(),
-                        flags.clone(),
-                    )
-                })
-            })?;
+        find_mdir_entries(&mdir, uids.as_ref(), id)?.try_for_each(|entry| {
+            update_mdir_entry_flags(&entry, flags, FlagsUpdate::Set).map_err(|err| {
+                Error::SetFlagsMaildirError(err, folder.to_owned(), id.to_string(), flags.clone())
+            })
+        })?;
 
         Ok(())
     }
+
+    async fn set_flags_matching(
+        &self,
+        folder: &str,
+        query: &SearchEmailsQuery,
+        flags: &Flags,
+    ) -> AnyResult<()> {
+        info!("setting maildir flag(s) {flags} to envelopes matching query from folder {folder}");
+
+        let list = ListMaildirEnvelopes::new(&self.ctx);
+        match list_matching_ids(&list, folder, query).await? {
+            Some(id) => self.set_flags(folder, &id, flags).await,
+            None => Ok(()),
+        }
+    }
 }
@@ -8,7 +8,7 @@
 use async_trait::async_trait;
 
 use super::{Flag, Flags};
-use crate::{envelope::Id, AnyResult};
+use crate::{email::error::Error, envelope::Id, search_query::SearchEmailsQuery, AnyResult};
 
 #[async_trait]
 pub trait SetFlags: Send + Sync {
@@ -25,4 +25,23 @@ pub trait SetFlags: Send + Sync {
     async fn set_flag(&self, folder: &str, id: &Id, flag: Flag) -> AnyResult<()> {
         self.set_flags(folder, id, &Flags::from_iter([flag])).await
     }
+
+    /// Set the given flags to all envelopes matching the given
+    /// query from the given folder.
+    ///
+    /// This function replaces any exsting flags by the given ones.
+    ///
+    /// Contrary to [`SetFlags::set_flags`], envelopes do not need to be
+    /// listed first: backends supporting it apply flags in a single
+    /// server-side command (`UID STORE` for IMAP). An empty query
+    /// matches all envelopes of the folder.
+    async fn set_flags_matching(
+        &self,
+        folder: &str,
+        query: &SearchEmailsQuery,
+        flags: &Flags,
+    ) -> AnyResult<()> {
+        let _ = (folder, query, flags);
+        Err(Error::SetFlagsMatchingNotSupportedError.into())
+    }
 }
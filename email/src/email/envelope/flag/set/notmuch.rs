@@ -4,7 +4,11 @@
 
 use super::{Flags, SetFlags};
 use crate::{
-    email::error::Error, envelope::Id, flag::Flag, folder::FolderKind, notmuch::NotmuchContextSync,
+    email::error::Error,
+    envelope::{list::notmuch::ListNotmuchEnvelopes, Id},
+    flag::{list_matching_ids, Flag},
+    notmuch::NotmuchContextSync,
+    search_query::SearchEmailsQuery,
     AnyResult,
 };
 
@@ -32,16 +36,11 @@ impl SetFlags for SetNotmuchFlags {
     async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
         info!("setting notmuch flag(s) {flags} to envelope {id} from folder {folder}");
 
-        let config = &self.ctx.account_config;
         let ctx = self.ctx.lock().await;
+        let tags = &ctx.notmuch_config.tags;
         let db = ctx.open_db()?;
 
-        let ref folder = config.get_folder_alias(folder);
-        let folder_query = if ctx.maildirpp() && FolderKind::matches_inbox(folder) {
-            String::from("folder:\"\"")
-        } else {
-            format!("folder:{folder:?}")
-        };
+        let folder_query = ctx.folder_query(folder);
         let mid_query = format!("mid:\"/^({})$/\"", id.join("|"));
         let query = [folder_query, mid_query].join(" and ");
         debug!("notmuch query: {query:?}");
@@ -54,7 +53,11 @@ async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()>
         for mut msg in msgs {
             let mut entry = MaildirEntry::new(msg.filename());
             msg.remove_all_tags().map_err(Error::NotMuchFailure)?;
-            msg.add_tag("unread").map_err(Error::NotMuchFailure)?;
+
+            for (_, tag) in tags.iter() {
+                tag.unset(&msg)?;
+            }
+
             entry
                 .remove_flags(entry.flags().map_err(Error::MaildirppFailure)?)
                 .map_err(Error::MaildirppFailure)?;
@@ -63,40 +66,35 @@ async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()>
                 .map_err(Error::NotMuchFailure)?;
 
             for flag in flags.iter() {
+                tags.get_tag(flag).set(&msg)?;
+
                 match flag {
                     Flag::Seen => {
-                        msg.remove_tag("unread").map_err(Error::NotMuchFailure)?;
                         entry
                             .insert_flag(maildirs::Flag::Seen)
                             .map_err(Error::MaildirppFailure)?;
                     }
                     Flag::Answered => {
-                        msg.add_tag("replied").map_err(Error::NotMuchFailure)?;
                         entry
                             .insert_flag(maildirs::Flag::Replied)
                             .map_err(Error::MaildirppFailure)?;
                     }
                     Flag::Flagged => {
-                        msg.add_tag("flagged").map_err(Error::NotMuchFailure)?;
                         entry
                             .insert_flag(maildirs::Flag::Flagged)
                             .map_err(Error::MaildirppFailure)?;
                     }
                     Flag::Deleted => {
-                        msg.add_tag("deleted").map_err(Error::NotMuchFailure)?;
                         entry
                             .insert_flag(maildirs::Flag::Trashed)
                             .map_err(Error::MaildirppFailure)?;
                     }
                     Flag::Draft => {
-                        msg.add_tag("draft").map_err(Error::NotMuchFailure)?;
                         entry
                             .insert_flag(maildirs::Flag::Draft)
                             .map_err(Error::MaildirppFailure)?;
                     }
-                    Flag::Custom(tag) => {
-                        msg.add_tag(tag).map_err(Error::NotMuchFailure)?;
-                    }
+                    Flag::Custom(_) => (),
                 }
 
                 if msg.filename() != entry.path() {
@@ -111,4 +109,19 @@ async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()>
 
         Ok(())
     }
+
+    async fn set_flags_matching(
+        &self,
+        folder: &str,
+        query: &SearchEmailsQuery,
+        flags: &Flags,
+    ) -> AnyResult<()> {
+        info!("setting notmuch flag(s) {flags} to envelopes matching query from folder {folder}");
+
+        let list = ListNotmuchEnvelopes::new(&self.ctx);
+        match list_matching_ids(&list, folder, query).await? {
+            Some(id) => self.set_flags(folder, &id, flags).await,
+            None => Ok(()),
+        }
+    }
 }
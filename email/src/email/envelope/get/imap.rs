@@ -1,6 +1,5 @@
 use async_trait::async_trait;
 use tracing::{debug, info};
-use utf7_imap::encode_utf7_imap as encode_utf7;
 
 use super::{Envelope, GetEnvelope};
 use crate::{envelope::SingleId, imap::ImapContext, AnyResult};
@@ -33,7 +32,7 @@ async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope>
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
+        let folder_encoded = client.encode_mailbox(&folder);
         debug!("utf7 encoded folder: {folder_encoded}");
 
         client.select_mailbox(&folder_encoded).await?;
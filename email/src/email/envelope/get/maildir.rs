@@ -2,7 +2,11 @@
 use tracing::{info, trace};
 
 use super::{Envelope, GetEnvelope};
-use crate::{envelope::SingleId, maildir::MaildirContextSync, AnyResult, Error};
+use crate::{
+    envelope::{maildir::find_mdir_entries, Id, SingleId},
+    maildir::MaildirContextSync,
+    AnyResult, Error,
+};
 
 #[derive(Clone)]
 pub struct GetMaildirEnvelope {
@@ -31,8 +35,22 @@ async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope>
         let session = self.ctx.lock().await;
         let mdir = session.get_maildir_from_folder_alias(folder)?;
 
-        let entry = mdir.get(id.to_string()).map_err(Error::from)?;
-        let envelope = Envelope::try_from(entry)?;
+        let envelope = match session.uid_db(&mdir)? {
+            Some(uids) => {
+                let entry = find_mdir_entries(&mdir, Some(&uids), &Id::from(id))?
+                    .next()
+                    .ok_or_else(|| {
+                        Error::GetEnvelopeMaildirError(mdir.path().into(), id.clone())
+                    })?;
+                let mut envelope = Envelope::try_from(entry)?;
+                envelope.id = id.to_string();
+                envelope
+            }
+            None => {
+                let entry = mdir.get(id.to_string()).map_err(Error::from)?;
+                Envelope::try_from(entry)?
+            }
+        };
         trace!("maildir envelope: {envelope:#?}");
 
         Ok(envelope)
@@ -37,6 +37,8 @@ async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope>
                 .ok_or_else(|| {
                     Error::FindEnvelopeEmptyNotmuchError(folder.to_owned(), id.to_string())
                 })?,
+            &ctx.notmuch_config.tags,
+            None,
         );
         trace!("notmuch envelope: {envelope:#?}");
 
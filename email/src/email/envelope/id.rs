@@ -1,12 +1,24 @@
 use std::{
+    borrow::Cow,
     fmt,
     ops::{Deref, DerefMut},
+    str::FromStr,
 };
 
+use crate::email::error::Error;
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Id {
     Single(SingleId),
     Multiple(MultipleIds),
+
+    /// A set of numeric id ranges, like `1:100`, `100:*` or
+    /// `1,3:5,10:*`.
+    ///
+    /// Sets are mapped to IMAP sequence sets as it is, and matched
+    /// lazily by other backends (see [`Id::contains`]), so that
+    /// ranges never need to be enumerated up front.
+    Set(IdSet),
 }
 
 impl Id {
@@ -18,16 +30,69 @@ pub fn multiple(ids: impl Into<MultipleIds>) -> Self {
         Self::Multiple(ids.into())
     }
 
+    pub fn set(set: impl Into<IdSet>) -> Self {
+        Self::Set(set.into())
+    }
+
+    /// Parse the given id.
+    ///
+    /// The id is parsed as an [`IdSet`] if it contains ranges or
+    /// multiple ids (like `1:100` or `1,3`), otherwise it is kept as
+    /// a single id.
+    pub fn parse(id: impl AsRef<str>) -> Self {
+        let id = id.as_ref();
+
+        if id.contains([':', ',']) {
+            if let Ok(set) = id.parse() {
+                return Self::Set(set);
+            }
+        }
+
+        Self::single(id)
+    }
+
     pub fn join(&self, sep: impl AsRef<str>) -> String {
         match self {
             Self::Single(id) => id.to_string(),
             Self::Multiple(ids) => ids.join(sep.as_ref()),
+            Self::Set(_) => self.iter().collect::<Vec<_>>().join(sep.as_ref()),
         }
     }
 
+    /// Iterate over ids.
+    ///
+    /// Ranges of id sets are expanded lazily. Open ranges like
+    /// `100:*` cannot be expanded and are skipped: use
+    /// [`Id::contains`] instead to match ids against them.
     pub fn iter(&self) -> IdIterator {
         IdIterator::new(self)
     }
+
+    /// Return `true` if the given id is part of this id.
+    pub fn contains(&self, id: impl AsRef<str>) -> bool {
+        let id = id.as_ref();
+
+        match self {
+            Self::Single(this) => this.as_str() == id,
+            Self::Multiple(ids) => ids.iter().any(|this| this == id),
+            Self::Set(set) => set.contains(id),
+        }
+    }
+
+    /// Return the position of the given id in this id, if it is part
+    /// of it.
+    ///
+    /// This is useful to return results in the same order as the
+    /// given ids.
+    pub fn position(&self, id: impl AsRef<str>) -> Option<usize> {
+        let id = id.as_ref();
+
+        match self {
+            Self::Single(this) => (this.as_str() == id).then_some(0),
+            Self::Multiple(ids) => ids.iter().position(|this| this == id),
+            Self::Set(set) => set.position(id),
+        }
+    }
 }
 
 impl fmt::Display for Id {
@@ -35,6 +100,7 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Single(id) => write!(f, "{}", id.deref()),
             Self::Multiple(ids) => write!(f, "{ids}"),
+            Self::Set(set) => write!(f, "{set}"),
         }
     }
 }
@@ -63,6 +129,18 @@ fn from(ids: &MultipleIds) -> Self {
     }
 }
 
+impl From<IdSet> for Id {
+    fn from(set: IdSet) -> Self {
+        Self::Set(set)
+    }
+}
+
+impl From<&IdSet> for Id {
+    fn from(set: &IdSet) -> Self {
+        Self::Set(set.clone())
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct SingleId(String);
 
@@ -127,36 +205,278 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     }
 }
 
+/// A range of numeric ids.
+///
+/// The range is inclusive. A range without end is open: it matches
+/// all ids greater than or equal to its start, like the IMAP `*`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct IdRange {
+    pub start: u32,
+    pub end: Option<u32>,
+}
+
+impl IdRange {
+    pub fn new(start: u32, end: Option<u32>) -> Self {
+        match end {
+            Some(end) if end < start => Self {
+                start: end,
+                end: Some(start),
+            },
+            end => Self { start, end },
+        }
+    }
+
+    pub fn single(id: u32) -> Self {
+        Self::new(id, Some(id))
+    }
+
+    pub fn is_bounded(&self) -> bool {
+        self.end.is_some()
+    }
+
+    pub fn contains(&self, id: u32) -> bool {
+        id >= self.start && !matches!(self.end, Some(end) if id > end)
+    }
+
+    /// Return the number of ids of the range, or `None` if the range
+    /// is open.
+    pub fn size(&self) -> Option<usize> {
+        self.end
+            .map(|end| end.saturating_sub(self.start) as usize + 1)
+    }
+}
+
+impl fmt::Display for IdRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.end {
+            Some(end) if end == self.start => write!(f, "{end}"),
+            Some(end) => write!(f, "{}:{end}", self.start),
+            None => write!(f, "{}:*", self.start),
+        }
+    }
+}
+
+impl FromStr for IdRange {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || Error::ParseIdSetError(s.to_owned());
+
+        let parse = |id: &str| match id.trim() {
+            "*" => Ok(None),
+            id => match id.parse::<u32>() {
+                Ok(0) | Err(_) => Err(err()),
+                Ok(id) => Ok(Some(id)),
+            },
+        };
+
+        match s.split_once(':') {
+            None => Ok(Self::single(parse(s)?.ok_or_else(err)?)),
+            Some((start, end)) => match (parse(start)?, parse(end)?) {
+                (Some(start), end) | (end @ None, Some(start)) => Ok(Self::new(start, end)),
+                (None, None) => Err(err()),
+            },
+        }
+    }
+}
+
+/// A set of numeric id ranges.
+///
+/// The set follows the IMAP sequence set syntax: ranges are
+/// separated by commas, bounds by colons, and `*` represents the
+/// greatest id: `1,3:5,10:*`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IdSet(Vec<IdRange>);
+
+impl IdSet {
+    /// Return `true` if none of the ranges is open.
+    pub fn is_bounded(&self) -> bool {
+        self.iter().all(IdRange::is_bounded)
+    }
+
+    /// Return `true` if the given id is part of one of the ranges.
+    ///
+    /// Non-numeric ids never match.
+    pub fn contains(&self, id: impl AsRef<str>) -> bool {
+        match id.as_ref().parse::<u32>() {
+            Ok(id) => self.iter().any(|range| range.contains(id)),
+            Err(_) => false,
+        }
+    }
+
+    /// Return the position of the given id in the expanded set.
+    pub fn position(&self, id: impl AsRef<str>) -> Option<usize> {
+        let id = id.as_ref().parse::<u32>().ok()?;
+        let mut offset = 0usize;
+
+        for range in self.iter() {
+            if range.contains(id) {
+                return Some(offset.saturating_add((id - range.start) as usize));
+            }
+
+            offset = offset.saturating_add(range.size().unwrap_or(usize::MAX));
+        }
+
+        None
+    }
+
+    /// Lazily expand the bounded ranges of the set into ids.
+    pub fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.iter()
+            .filter_map(|range| range.end.map(|end| range.start..=end))
+            .flatten()
+    }
+}
+
+impl Deref for IdSet {
+    type Target = Vec<IdRange>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for IdSet {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T: IntoIterator<Item = IdRange>> From<T> for IdSet {
+    fn from(ranges: T) -> Self {
+        Self(ranges.into_iter().collect())
+    }
+}
+
+impl fmt::Display for IdSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, range) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{range}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for IdSet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ranges = s
+            .split(',')
+            .map(IdRange::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if ranges.is_empty() {
+            return Err(Error::ParseIdSetError(s.to_owned()));
+        }
+
+        Ok(Self(ranges))
+    }
+}
+
 pub struct IdIterator<'a> {
     id: &'a Id,
     index: usize,
+    set: Option<Box<dyn Iterator<Item = u32> + 'a>>,
 }
 
 impl<'a> IdIterator<'a> {
     pub fn new(id: &'a Id) -> Self {
-        Self { id, index: 0 }
+        let set = match id {
+            Id::Set(set) => Some(Box::new(set.ids()) as Box<dyn Iterator<Item = u32> + 'a>),
+            _ => None,
+        };
+
+        Self { id, index: 0, set }
     }
 }
 
 impl<'a> Iterator for IdIterator<'a> {
-    type Item = &'a str;
+    type Item = Cow<'a, str>;
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.id {
             Id::Single(_) if self.index > 0 => None,
             Id::Single(SingleId(id)) => {
                 self.index = 1;
-                Some(id.as_str())
+                Some(Cow::Borrowed(id.as_str()))
             }
             Id::Multiple(MultipleIds(ids)) => {
                 if self.index < ids.len() {
-                    let id = Some(ids[self.index].as_str());
+                    let id = Some(Cow::Borrowed(ids[self.index].as_str()));
                     self.index += 1;
                     id
                 } else {
                     None
                 }
             }
+            Id::Set(_) => {
+                let id = self.set.as_mut()?.next()?;
+                Some(Cow::Owned(id.to_string()))
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Id, IdRange, IdSet};
+
+    #[test]
+    fn parse_id_set() {
+        let set: IdSet = "1,3:5,10:*".parse().unwrap();
+
+        assert_eq!(
+            *set,
+            vec![
+                IdRange::single(1),
+                IdRange::new(3, Some(5)),
+                IdRange::new(10, None),
+            ]
+        );
+        assert_eq!(set.to_string(), "1,3:5,10:*");
+        assert!(!set.is_bounded());
+
+        let set: IdSet = "5:3,*:7".parse().unwrap();
+        assert_eq!(set.to_string(), "3:5,7:*");
+
+        assert!("".parse::<IdSet>().is_err());
+        assert!("0:3".parse::<IdSet>().is_err());
+        assert!("*".parse::<IdSet>().is_err());
+        assert!("1:a".parse::<IdSet>().is_err());
+    }
+
+    #[test]
+    fn parse_id() {
+        assert_eq!(Id::parse("42"), Id::single("42"));
+        assert_eq!(
+            Id::parse("<abc:def@localhost>"),
+            Id::single("<abc:def@localhost>")
+        );
+        assert_eq!(Id::parse("1:3"), Id::set([IdRange::new(1, Some(3))]));
+    }
+
+    #[test]
+    fn lazy_id_set() {
+        let id = Id::parse("1,3:5,10:*");
+
+        assert!(id.contains("4"));
+        assert!(id.contains("4242"));
+        assert!(!id.contains("6"));
+        assert!(!id.contains("abc"));
+
+        assert_eq!(id.position("1"), Some(0));
+        assert_eq!(id.position("5"), Some(3));
+        assert_eq!(id.position("12"), Some(6));
+        assert_eq!(id.position("2"), None);
+
+        let ids: Vec<_> = id.iter().collect();
+        assert_eq!(ids, vec!["1", "3", "4", "5"]);
+
+        let id = Id::parse("1:3");
+        assert_eq!(id.join(","), "1,2,3");
+    }
+}
@@ -6,9 +6,10 @@
 use std::{collections::HashMap, num::NonZeroU32};
 
 use imap_client::imap_next::imap_types::{
-    body::{BodyStructure, Disposition},
-    core::Vec1,
-    fetch::{MacroOrMessageDataItemNames, MessageDataItem, MessageDataItemName},
+    body::{BodyStructure, Disposition, SpecificFields},
+    core::{AString, IString, Vec1},
+    envelope::Address as ImapAddress,
+    fetch::{MacroOrMessageDataItemNames, MessageDataItem, MessageDataItemName, Part, Section},
 };
 use once_cell::sync::Lazy;
 
@@ -19,17 +20,61 @@
 };
 
 /// The IMAP fetch items needed to retrieve everything we need to
-/// build an envelope: UID, flags and envelope (Message-ID, From, To,
-/// Subject, Date).
+/// build an envelope: UID, flags, envelope (Message-ID, From, To, Cc,
+/// Subject, Date), body structure, size, internal date and priority
+/// headers (Importance, X-Priority).
 pub static FETCH_ENVELOPES: Lazy<MacroOrMessageDataItemNames<'static>> = Lazy::new(|| {
+    let priority_headers = ["Importance", "X-Priority"]
+        .into_iter()
+        .map(|name| AString::try_from(name).unwrap())
+        .collect::<Vec<_>>();
+
     MacroOrMessageDataItemNames::MessageDataItemNames(vec![
         MessageDataItemName::Uid,
         MessageDataItemName::Flags,
         MessageDataItemName::Envelope,
         MessageDataItemName::BodyStructure,
+        MessageDataItemName::Rfc822Size,
+        MessageDataItemName::InternalDate,
+        MessageDataItemName::BodyExt {
+            section: Some(Section::HeaderFields(
+                None,
+                Vec1::try_from(priority_headers).unwrap(),
+            )),
+            partial: None,
+            peek: true,
+        },
     ])
 });
 
+/// The number of octets fetched on top of the preview length, to
+/// cover the headers and MIME boundaries of nested parts.
+const PREVIEW_EXTRA_OCTETS: u32 = 1024;
+
+/// Builds the IMAP fetch items needed to generate previews of the
+/// given length: UID, body structure and the beginning of the first
+/// part of the message.
+///
+/// The first part is the body of single part messages, and usually
+/// the text part of multipart ones. The body structure describes how
+/// to decode it, see [`Envelope::preview_from_imap_data_items`].
+pub fn fetch_previews_items(len: usize) -> MacroOrMessageDataItemNames<'static> {
+    let octets = u32::try_from(len)
+        .unwrap_or(u32::MAX)
+        .saturating_mul(4)
+        .saturating_add(PREVIEW_EXTRA_OCTETS);
+
+    MacroOrMessageDataItemNames::MessageDataItemNames(vec![
+        MessageDataItemName::Uid,
+        MessageDataItemName::BodyStructure,
+        MessageDataItemName::BodyExt {
+            section: Some(Section::Part(Part(Vec1::from(NonZeroU32::MIN)))),
+            partial: Some((0, NonZeroU32::new(octets).unwrap())),
+            peek: true,
+        },
+    ])
+}
+
 impl Envelopes {
     pub fn from_imap_data_items(fetches: HashMap<NonZeroU32, Vec1<MessageDataItem>>) -> Self {
         fetches
@@ -54,6 +99,9 @@ pub fn from_imap_data_items(items: &[MessageDataItem]) -> Self {
         let mut flags = Flags::default();
         let mut msg = Vec::default();
         let mut has_attachment = false;
+        let mut size = None;
+        let mut internal_date = None;
+        let mut priority_headers = Vec::default();
 
         for item in items {
             match item {
@@ -76,90 +124,186 @@ pub fn from_imap_data_items(items: &[MessageDataItem]) -> Self {
                         msg.push(b'\n');
                     }
 
-                    let from = envelope
-                        .from
-                        .iter()
-                        .filter_map(|imap_addr| {
-                            let mut addr = Vec::default();
-
-                            if let Some(name) = imap_addr.name.0.as_ref() {
-                                addr.push(b'"');
-                                addr.extend(name.as_ref());
-                                addr.push(b'"');
-                                addr.push(b' ');
-                            }
-
-                            addr.push(b'<');
-                            addr.extend(imap_addr.mailbox.0.as_ref()?.as_ref());
-                            addr.push(b'@');
-                            addr.extend(imap_addr.host.0.as_ref()?.as_ref());
-                            addr.push(b'>');
-
-                            Some(addr)
-                        })
-                        .fold(b"From: ".to_vec(), |mut addrs, addr| {
-                            if !addrs.is_empty() {
-                                addrs.push(b',')
-                            }
-                            addrs.extend(addr);
-                            addrs
-                        });
-                    msg.extend(&from);
-                    msg.push(b'\n');
-
-                    let to = envelope
-                        .to
-                        .iter()
-                        .filter_map(|imap_addr| {
-                            let mut addr = Vec::default();
-
-                            if let Some(name) = imap_addr.name.0.as_ref() {
-                                addr.push(b'"');
-                                addr.extend(name.as_ref());
-                                addr.push(b'"');
-                                addr.push(b' ');
-                            }
-
-                            addr.push(b'<');
-                            addr.extend(imap_addr.mailbox.0.as_ref()?.as_ref());
-                            addr.push(b'@');
-                            addr.extend(imap_addr.host.0.as_ref()?.as_ref());
-                            addr.push(b'>');
-
-                            Some(addr)
-                        })
-                        .fold(b"To: ".to_vec(), |mut addrs, addr| {
-                            if !addrs.is_empty() {
-                                addrs.push(b',')
-                            }
-                            addrs.extend(addr);
-                            addrs
-                        });
-                    msg.extend(&to);
-                    msg.push(b'\n');
+                    msg.extend(imap_addrs_header(b"From", &envelope.from));
+                    msg.extend(imap_addrs_header(b"To", &envelope.to));
+                    msg.extend(imap_addrs_header(b"Cc", &envelope.cc));
 
                     if let Some(subject) = envelope.subject.0.as_ref() {
                         msg.extend(b"Subject: ");
                         msg.extend(subject.as_ref());
                         msg.push(b'\n');
                     }
-
-                    msg.push(b'\n');
                 }
                 MessageDataItem::BodyStructure(body) => {
                     has_attachment = has_at_least_one_attachment([body]);
                 }
+                MessageDataItem::Rfc822Size(n) => {
+                    size = Some(*n as usize);
+                }
+                MessageDataItem::InternalDate(date) => {
+                    internal_date = Some(*date.as_ref());
+                }
+                MessageDataItem::BodyExt {
+                    section: Some(Section::HeaderFields(..)),
+                    data,
+                    ..
+                } => {
+                    if let Some(headers) = data.0.as_ref() {
+                        priority_headers = headers.as_ref().trim_ascii_end().to_vec();
+                    }
+                }
                 _ => (),
             }
         }
 
+        if !priority_headers.is_empty() {
+            msg.extend(priority_headers);
+            msg.push(b'\n');
+        }
+
+        msg.push(b'\n');
+
         let msg = Message::from(msg);
         let mut env = Envelope::from_msg(id, flags, msg);
         env.has_attachment = has_attachment;
+        env.size = size;
+        env.internal_date = internal_date;
         env
     }
 }
 
+impl Envelope {
+    /// Extracts the envelope identifier and the body preview from the
+    /// given IMAP data items, fetched with [`fetch_previews_items`].
+    pub fn preview_from_imap_data_items(
+        items: &[MessageDataItem],
+        len: usize,
+    ) -> Option<(String, String)> {
+        let mut id = None;
+        let mut headers = Vec::new();
+        let mut part = None;
+
+        for item in items {
+            match item {
+                MessageDataItem::Uid(uid) => {
+                    id = Some(uid.to_string());
+                }
+                MessageDataItem::BodyStructure(body) => {
+                    headers = first_part_headers(body);
+                }
+                MessageDataItem::BodyExt { data, .. } => {
+                    part = data.0.as_ref().map(|data| data.as_ref());
+                }
+                _ => (),
+            }
+        }
+
+        // the first part is fetched without its headers, so they are
+        // rebuilt from the body structure in order to decode it
+        let mut msg = headers;
+        msg.extend(b"\r\n");
+        msg.extend(part?);
+
+        let preview = Message::from(msg).preview(len)?;
+        Some((id?, preview))
+    }
+}
+
+/// Builds the MIME headers of the first part of the given body
+/// structure, so that this part can be parsed on its own.
+fn first_part_headers(body: &BodyStructure) -> Vec<u8> {
+    let part = match body {
+        BodyStructure::Single { .. } => body,
+        BodyStructure::Multi { bodies, .. } => bodies.as_ref().first().unwrap_or(body),
+    };
+
+    let mut headers = b"Content-Type: ".to_vec();
+
+    match part {
+        BodyStructure::Single { body, .. } => {
+            match &body.specific {
+                SpecificFields::Basic { r#type, subtype } => {
+                    headers.extend(r#type.as_ref());
+                    headers.push(b'/');
+                    headers.extend(subtype.as_ref());
+                }
+                SpecificFields::Message { .. } => {
+                    headers.extend(b"message/rfc822");
+                }
+                SpecificFields::Text { subtype, .. } => {
+                    headers.extend(b"text/");
+                    headers.extend(subtype.as_ref());
+                }
+            }
+
+            extend_content_type_params(&mut headers, &body.basic.parameter_list);
+            headers.extend(b"\r\nContent-Transfer-Encoding: ");
+            headers.extend(body.basic.content_transfer_encoding.as_ref());
+        }
+        BodyStructure::Multi {
+            subtype,
+            extension_data,
+            ..
+        } => {
+            headers.extend(b"multipart/");
+            headers.extend(subtype.as_ref());
+
+            if let Some(data) = extension_data {
+                extend_content_type_params(&mut headers, &data.parameter_list);
+            }
+        }
+    }
+
+    headers.extend(b"\r\n");
+    headers
+}
+
+/// Appends the given parameters to a raw Content-Type header.
+fn extend_content_type_params(headers: &mut Vec<u8>, params: &[(IString, IString)]) {
+    for (name, value) in params {
+        headers.extend(b"; ");
+        headers.extend(name.as_ref());
+        headers.extend(b"=\"");
+        headers.extend(value.as_ref());
+        headers.push(b'"');
+    }
+}
+
+/// Builds a raw address header from the given IMAP addresses.
+fn imap_addrs_header(name: &[u8], imap_addrs: &[ImapAddress]) -> Vec<u8> {
+    let mut header = name.to_vec();
+    header.extend(b": ");
+
+    let addrs = imap_addrs.iter().filter_map(|imap_addr| {
+        let mut addr = Vec::default();
+
+        if let Some(name) = imap_addr.name.0.as_ref() {
+            addr.push(b'"');
+            addr.extend(name.as_ref());
+            addr.push(b'"');
+            addr.push(b' ');
+        }
+
+        addr.push(b'<');
+        addr.extend(imap_addr.mailbox.0.as_ref()?.as_ref());
+        addr.push(b'@');
+        addr.extend(imap_addr.host.0.as_ref()?.as_ref());
+        addr.push(b'>');
+
+        Some(addr)
+    });
+
+    for (i, addr) in addrs.enumerate() {
+        if i > 0 {
+            header.push(b',');
+        }
+        header.extend(addr);
+    }
+
+    header.push(b'\n');
+    header
+}
+
 fn has_at_least_one_attachment<'a, B>(bodies: B) -> bool
 where
     B: IntoIterator<Item = &'a BodyStructure<'a>>,
@@ -205,3 +349,163 @@ fn is_attachment(disp: Option<&Disposition>) -> bool {
 
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use imap_client::imap_next::imap_types::{
+        body::{BasicFields, Body, BodyStructure, MultiPartExtensionData, SpecificFields},
+        core::{IString, NString, Vec1},
+        envelope::{Address as ImapAddress, Envelope as ImapEnvelope},
+        fetch::{MessageDataItem, Part, Section},
+    };
+
+    use crate::envelope::{Address, Envelope};
+
+    fn istr(s: &'static str) -> IString<'static> {
+        IString::try_from(s).unwrap()
+    }
+
+    fn nstr(s: &'static str) -> NString<'static> {
+        NString(Some(istr(s)))
+    }
+
+    fn addr(name: &'static str, mailbox: &'static str) -> ImapAddress<'static> {
+        ImapAddress {
+            name: nstr(name),
+            adl: NString(None),
+            mailbox: nstr(mailbox),
+            host: nstr("localhost"),
+        }
+    }
+
+    fn text_part(subtype: &'static str, encoding: &'static str) -> BodyStructure<'static> {
+        BodyStructure::Single {
+            body: Body {
+                basic: BasicFields {
+                    parameter_list: vec![(istr("charset"), istr("utf-8"))],
+                    id: NString(None),
+                    description: NString(None),
+                    content_transfer_encoding: istr(encoding),
+                    size: 0,
+                },
+                specific: SpecificFields::Text {
+                    subtype: istr(subtype),
+                    number_of_lines: 0,
+                },
+            },
+            extension_data: None,
+        }
+    }
+
+    fn first_part(data: &'static str) -> MessageDataItem<'static> {
+        MessageDataItem::BodyExt {
+            section: Some(Section::Part(Part(Vec1::from(NonZeroU32::MIN)))),
+            origin: Some(0),
+            data: nstr(data),
+        }
+    }
+
+    #[test]
+    fn envelope_addrs_and_size() {
+        let items = [
+            MessageDataItem::Uid(NonZeroU32::new(42).unwrap()),
+            MessageDataItem::Envelope(ImapEnvelope {
+                date: NString(None),
+                subject: nstr("Hello"),
+                from: vec![addr("Alice", "alice")],
+                sender: vec![],
+                reply_to: vec![],
+                to: vec![addr("Bob", "bob"), addr("Carol", "carol")],
+                cc: vec![addr("Dave", "dave")],
+                bcc: vec![],
+                in_reply_to: NString(None),
+                message_id: nstr("<id@localhost>"),
+            }),
+            MessageDataItem::Rfc822Size(1234),
+        ];
+
+        let envelope = Envelope::from_imap_data_items(&items);
+
+        assert_eq!(envelope.id, "42");
+        assert_eq!(
+            envelope.to_addrs,
+            vec![
+                Address::new(Some("Bob"), "bob@localhost"),
+                Address::new(Some("Carol"), "carol@localhost"),
+            ],
+        );
+        assert_eq!(
+            envelope.cc_addrs,
+            vec![Address::new(Some("Dave"), "dave@localhost")],
+        );
+        assert_eq!(envelope.size, Some(1234));
+    }
+
+    #[test]
+    fn preview_from_single_part() {
+        let items = [
+            MessageDataItem::Uid(NonZeroU32::new(42).unwrap()),
+            MessageDataItem::BodyStructure(text_part("plain", "quoted-printable")),
+            first_part("Caf=C3=A9 au lait,=\r\n please.\r\n"),
+        ];
+
+        let (id, preview) = Envelope::preview_from_imap_data_items(&items, 80).unwrap();
+
+        assert_eq!(id, "42");
+        assert_eq!(preview, "Café au lait, please.");
+    }
+
+    #[test]
+    fn preview_from_multipart() {
+        let alternative = BodyStructure::Multi {
+            bodies: Vec1::try_from(vec![text_part("plain", "7bit"), text_part("html", "7bit")])
+                .unwrap(),
+            subtype: istr("alternative"),
+            extension_data: Some(MultiPartExtensionData {
+                parameter_list: vec![(istr("boundary"), istr("inner"))],
+                tail: None,
+            }),
+        };
+
+        let mixed = BodyStructure::Multi {
+            bodies: Vec1::try_from(vec![alternative, text_part("plain", "base64")]).unwrap(),
+            subtype: istr("mixed"),
+            extension_data: Some(MultiPartExtensionData {
+                parameter_list: vec![(istr("boundary"), istr("outer"))],
+                tail: None,
+            }),
+        };
+
+        let items = [
+            MessageDataItem::Uid(NonZeroU32::new(42).unwrap()),
+            MessageDataItem::BodyStructure(mixed),
+            first_part(concat!(
+                "--inner\r\n",
+                "Content-Type: text/plain; charset=utf-8\r\n",
+                "\r\n",
+                "Plain text body.\r\n",
+                "--inner\r\n",
+                "Content-Type: text/html; charset=utf-8\r\n",
+                "\r\n",
+                "<p>HTML body.</p>\r\n",
+                "--inner--\r\n",
+            )),
+        ];
+
+        let (_, preview) = Envelope::preview_from_imap_data_items(&items, 80).unwrap();
+
+        assert_eq!(preview, "Plain text body.");
+    }
+
+    #[test]
+    fn preview_without_body() {
+        let items = [
+            MessageDataItem::Uid(NonZeroU32::new(42).unwrap()),
+            MessageDataItem::BodyStructure(text_part("plain", "7bit")),
+        ];
+
+        assert_eq!(Envelope::preview_from_imap_data_items(&items, 80), None);
+    }
+}
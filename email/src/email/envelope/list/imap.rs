@@ -10,12 +10,12 @@
     sequence::{SeqOrUid, Sequence, SequenceSet},
 };
 use tracing::{debug, info, instrument, trace};
-use utf7_imap::encode_utf7_imap as encode_utf7;
 
 use super::{Envelopes, ListEnvelopes, ListEnvelopesOptions};
 use crate::{
     email::error::Error,
     envelope::Envelope,
+    flag::gmail::attach_gmail_labels,
     imap,
     imap::ImapContext,
     search_query::{
@@ -26,7 +26,8 @@
     AnyResult, Result,
 };
 
-static MAX_SEQUENCE_SIZE: u8 = u8::MAX; // 255
+/// The maximum number of UIDs sent in a single FETCH command.
+pub(crate) static MAX_SEQUENCE_SIZE: u8 = u8::MAX; // 255
 
 #[derive(Clone, Debug)]
 pub struct ListImapEnvelopes {
@@ -61,7 +62,7 @@ async fn list_envelopes(
         let mut client = self.ctx.client().await;
 
         let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
+        let folder_encoded = client.encode_mailbox(&folder);
         debug!(name = folder_encoded, "UTF7-encoded mailbox");
 
         let data = client.select_mailbox(folder_encoded.clone()).await?;
@@ -72,7 +73,7 @@ async fn list_envelopes(
             return Ok(Envelopes::default());
         }
 
-        let envelopes = if let Some(query) = opts.query.as_ref() {
+        let mut envelopes = if let Some(query) = opts.query.as_ref() {
             let sort_supported = client.ext_sort_supported();
             let sort_criteria = query.to_imap_sort_criteria();
             let search_criteria = query.to_imap_search_criteria();
@@ -159,10 +160,32 @@ async fn list_envelopes(
         } else {
             let seq = build_sequence(opts.page, opts.page_size, folder_size)?;
             let mut envelopes = client.fetch_envelopes_by_sequence(seq.into()).await?;
+            drop(client);
             envelopes.sort_by(|a, b| b.date.cmp(&a.date));
             envelopes
         };
 
+        if let Some(len) = opts.preview {
+            let uids: Vec<NonZeroU32> =
+                envelopes.iter().filter_map(|e| e.id.parse().ok()).collect();
+
+            if !uids.is_empty() {
+                let mut client = self.ctx.client().await;
+                client.select_mailbox(&folder_encoded).await?;
+                let mut previews = client.fetch_previews(&uids, len).await?;
+
+                for envelope in envelopes.iter_mut() {
+                    envelope.preview = previews.remove(&envelope.id);
+                }
+            }
+        }
+
+        let mut client = self.ctx.client().await;
+
+        if client.gmail_labels_enabled() {
+            attach_gmail_labels(&mut client, &folder_encoded, &mut envelopes).await?;
+        }
+
         debug!("found {} imap envelopes", envelopes.len());
         trace!("{envelopes:#?}");
 
@@ -260,6 +283,14 @@ pub fn to_imap_sort_criterion(&self) -> SortCriterion {
                 reverse: true,
                 key: SortKey::Date,
             },
+            SearchEmailsSorter(InternalDate, Ascending) => SortCriterion {
+                reverse: false,
+                key: SortKey::Arrival,
+            },
+            SearchEmailsSorter(InternalDate, Descending) => SortCriterion {
+                reverse: true,
+                key: SortKey::Arrival,
+            },
             SearchEmailsSorter(From, Ascending) => SortCriterion {
                 reverse: false,
                 key: SortKey::From,
@@ -49,8 +49,10 @@ async fn list_envelopes(
         let ctx = self.ctx.lock().await;
         let mdir = ctx.get_maildir_from_folder_alias(folder)?;
 
-        let entries = mdir.read().map_err(Error::ListMaildirEntriesError)?;
-        let mut envelopes = Envelopes::from_mdir_entries(entries, opts.query.as_ref());
+        let mut envelopes = ctx.read_envelopes(&mdir, opts.query.as_ref(), opts.preview)?;
+        if let Some(uids) = ctx.uid_db(&mdir)? {
+            envelopes.map_mdir_uids(&uids);
+        }
         debug!("found {} maildir envelopes", envelopes.len());
         trace!("{envelopes:#?}");
 
@@ -33,6 +33,13 @@ pub struct ListEnvelopesOptions {
     pub page_size: usize,
     pub page: usize,
     pub query: Option<SearchEmailsQuery>,
+
+    /// Generates a plain text preview of the message body, up to
+    /// the given number of characters.
+    ///
+    /// Previews require to fetch (part of) message bodies, which
+    /// makes listings more expensive. Defaults to no preview.
+    pub preview: Option<usize>,
 }
 
 impl SearchEmailsSorter {
@@ -43,6 +50,12 @@ pub fn cmp_envelopes(&self, a: &Envelope, b: &Envelope) -> Ordering {
         match self {
             SearchEmailsSorter(Date, Ascending) => a.date.cmp(&b.date),
             SearchEmailsSorter(Date, Descending) => b.date.cmp(&a.date),
+            SearchEmailsSorter(InternalDate, Ascending) => {
+                a.internal_date_or_date().cmp(&b.internal_date_or_date())
+            }
+            SearchEmailsSorter(InternalDate, Descending) => {
+                b.internal_date_or_date().cmp(&a.internal_date_or_date())
+            }
             SearchEmailsSorter(From, Ascending) => a.from.cmp(&b.from),
             SearchEmailsSorter(From, Descending) => b.from.cmp(&a.from),
             SearchEmailsSorter(To, Ascending) => a.to.cmp(&b.to),
@@ -1,11 +1,11 @@
 use async_trait::async_trait;
 use chrono::TimeDelta;
+use notmuch::Exclude;
 use tracing::{debug, info, trace};
 
 use super::{Envelopes, ListEnvelopes, ListEnvelopesOptions};
 use crate::{
     email::error::Error,
-    folder::FolderKind,
     notmuch::NotmuchContextSync,
     search_query::{filter::SearchEmailsFilterQuery, SearchEmailsQuery},
     AnyResult,
@@ -40,15 +40,9 @@ async fn list_envelopes(
         info!("listing notmuch envelopes from folder {folder}");
 
         let ctx = self.ctx.lock().await;
-        let config = &ctx.account_config;
         let db = ctx.open_db()?;
 
-        let ref folder = config.get_folder_alias(folder);
-        let mut final_query = if ctx.maildirpp() && FolderKind::matches_inbox(folder) {
-            String::from("folder:\"\"")
-        } else {
-            format!("folder:{folder:?}")
-        };
+        let mut final_query = ctx.folder_query(folder);
 
         if let Some(query) = opts.query.as_ref() {
             let query = query.to_notmuch_search_query();
@@ -62,11 +56,23 @@ async fn list_envelopes(
             .create_query(&final_query)
             .map_err(Error::NotMuchFailure)?;
 
+        let exclude_tags = ctx.notmuch_config.find_exclude_tags(&db);
+        debug!("excluding notmuch tags {exclude_tags:?}");
+
+        for tag in &exclude_tags {
+            query_builder
+                .add_tag_exclude(tag)
+                .map_err(Error::NotMuchFailure)?;
+        }
+
+        query_builder.set_omit_excluded(Exclude::True);
+
         let msgs = query_builder.search_messages().map_err(|err| {
             Error::SearchMessagesInvalidQueryNotmuch(err, folder.to_owned(), final_query.clone())
         })?;
 
-        let mut envelopes = Envelopes::from_notmuch_msgs(msgs);
+        let mut envelopes =
+            Envelopes::from_notmuch_msgs(msgs, &ctx.notmuch_config.tags, opts.preview);
 
         debug!(
             "found {} notmuch envelopes matching query {final_query}",
@@ -3,60 +3,358 @@
 //! This module contains envelope-related mapping functions from the
 //! [maildirpp] crate types.
 
-use maildirs::MaildirEntry;
-use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufRead, BufReader},
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+use chrono::DateTime;
+use maildirs::{Maildir, MaildirEntry};
+use rayon::{prelude::*, ThreadPoolBuilder};
+use tracing::debug;
 
+#[cfg(feature = "maildir-cache")]
+use crate::maildir::cache::MaildirEnvelopeCache;
 use crate::{
-    envelope::{Envelope, Envelopes, Flags},
+    envelope::{Envelope, Envelopes, Flags, Id},
+    maildir::{
+        config::MaildirEnvelopesConfig,
+        uid::{entry_name, MaildirUidDb},
+        MaildirContext,
+    },
     message::Message,
     search_query::SearchEmailsQuery,
-    Error, Result,
+    AnyResult, Error, Result,
 };
 
+/// Find the entries of the given Maildir matching the given id.
+///
+/// Single and multiple ids are looked up one by one, whereas id sets
+/// are matched lazily against the entries of the Maildir, so that
+/// their ranges never need to be expanded.
+///
+/// When a UID database is given, ids are UIDs resolved against it.
+pub(crate) fn find_mdir_entries<'a>(
+    mdir: &'a Maildir,
+    uids: Option<&MaildirUidDb>,
+    id: &'a Id,
+) -> Result<Box<dyn Iterator<Item = MaildirEntry> + 'a>> {
+    if let Some(uids) = uids {
+        let names = uids.find_names(id);
+        let entries = mdir
+            .read()
+            .map_err(Error::ListMaildirEntriesError)?
+            .filter(move |entry| entry.id().is_ok_and(|id| names.contains(entry_name(id))));
+        return Ok(Box::new(entries));
+    }
+
+    match id {
+        Id::Set(set) => {
+            let entries = mdir
+                .read()
+                .map_err(Error::ListMaildirEntriesError)?
+                .filter(|entry| entry.id().is_ok_and(|id| set.contains(id)));
+            Ok(Box::new(entries))
+        }
+        id => {
+            let entries = id.iter().filter_map(|id| mdir.find(id).ok().flatten());
+            Ok(Box::new(entries))
+        }
+    }
+}
+
+/// The part of a Maildir envelope which does not depend on the entry
+/// file name, as stored in the envelope cache.
+///
+/// Records are keyed by the modification time and the size of the
+/// entry file, which are preserved by flag updates.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MaildirEnvelopeRecord {
+    /// The modification time of the entry file, in nanoseconds since
+    /// the Unix epoch.
+    pub mtime: i64,
+
+    /// The size of the entry file, in bytes.
+    pub size: usize,
+
+    /// True if the message contains at least one attachment.
+    pub has_attachment: bool,
+
+    /// The raw headers of the message.
+    pub headers: Vec<u8>,
+}
+
+impl MaildirEnvelopeRecord {
+    fn matches(&self, mtime: i64, size: usize) -> bool {
+        self.mtime == mtime && self.size == size
+    }
+}
+
+impl MaildirContext {
+    /// Reads the envelopes of the given Maildir matching the given
+    /// optional query.
+    ///
+    /// Entries are read in parallel, using the concurrency and the
+    /// envelope cache defined in [`MaildirEnvelopesConfig`]. The
+    /// cache is bypassed when a body preview is requested.
+    pub fn read_envelopes(
+        &self,
+        mdir: &Maildir,
+        query: Option<&SearchEmailsQuery>,
+        preview: Option<usize>,
+    ) -> AnyResult<Envelopes> {
+        let config = &self.maildir_config.envelopes;
+        let entries = mdir.read().map_err(Error::ListMaildirEntriesError)?;
+
+        #[cfg(feature = "maildir-cache")]
+        if let (Some(path), None) = (&config.cache, preview) {
+            let mut cache = MaildirEnvelopeCache::open(path, mdir.path())?;
+            let prev_records = cache.load()?;
+            let (envelopes, records) =
+                Envelopes::read_mdir_entries(entries, query, preview, config, &prev_records);
+            cache.save(&prev_records, &records)?;
+            return Ok(envelopes);
+        }
+
+        let (envelopes, _) =
+            Envelopes::read_mdir_entries(entries, query, preview, config, &HashMap::new());
+        Ok(envelopes)
+    }
+}
+
 impl Envelopes {
+    /// Replaces Maildir ids of envelopes by their UID from the given
+    /// UID database.
+    pub fn map_mdir_uids(&mut self, uids: &MaildirUidDb) {
+        for envelope in self.iter_mut() {
+            if let Some(uid) = uids.get_uid(&envelope.id) {
+                envelope.id = uid.to_string();
+            }
+        }
+    }
+
     pub fn from_mdir_entries(
         entries: impl Iterator<Item = MaildirEntry>,
         query: Option<&SearchEmailsQuery>,
+        preview: Option<usize>,
     ) -> Self {
-        Envelopes::from_iter(
+        let config = MaildirEnvelopesConfig::default();
+        Self::read_mdir_entries(entries, query, preview, &config, &HashMap::new()).0
+    }
+
+    /// Reads envelopes from the given Maildir entries, in parallel.
+    ///
+    /// Entries matching a record of the given cache are built from
+    /// it, without reading their file. Returns the envelopes matching
+    /// the given optional query, together with the records of all
+    /// entries.
+    pub(crate) fn read_mdir_entries(
+        entries: impl Iterator<Item = MaildirEntry>,
+        query: Option<&SearchEmailsQuery>,
+        preview: Option<usize>,
+        config: &MaildirEnvelopesConfig,
+        cache: &HashMap<String, MaildirEnvelopeRecord>,
+    ) -> (Self, HashMap<String, MaildirEnvelopeRecord>) {
+        let entries: Vec<_> = entries.collect();
+
+        let read = || {
             entries
-                .collect::<Vec<_>>()
                 .into_par_iter()
                 .filter_map(|entry| {
+                    let name = entry_name(entry.id().ok()?).to_owned();
                     let msg_path = entry.path().to_owned();
-                    let envelope = Envelope::try_from(entry).ok()?;
-                    if let Some(query) = query {
-                        query
+                    let cached = cache.get(&name);
+                    let (envelope, record) =
+                        Envelope::read_mdir_entry(entry, cached, preview, config.headers_only)
+                            .ok()?;
+
+                    let envelope = match query {
+                        Some(query) => query
                             .matches_maildir_search_query(&envelope, msg_path.as_ref())
-                            .then_some(envelope)
-                    } else {
-                        Some(envelope)
-                    }
+                            .then_some(envelope),
+                        None => Some(envelope),
+                    };
+
+                    Some((envelope, (name, record)))
                 })
-                .collect::<Vec<_>>(),
-        )
+                .collect::<Vec<_>>()
+        };
+
+        let results = match config.concurrency {
+            Some(n) => match ThreadPoolBuilder::new().num_threads(n).build() {
+                Ok(pool) => pool.install(read),
+                Err(_err) => {
+                    debug!("cannot build maildir thread pool, using global one: {_err}");
+                    read()
+                }
+            },
+            None => read(),
+        };
+
+        let mut envelopes = Vec::with_capacity(results.len());
+        let mut records = HashMap::with_capacity(results.len());
+
+        for (envelope, (name, record)) in results {
+            envelopes.extend(envelope);
+            records.insert(name, record);
+        }
+
+        (Envelopes::from_iter(envelopes), records)
     }
 }
 
-impl TryFrom<MaildirEntry> for Envelope {
-    type Error = Error;
+impl Envelope {
+    /// Builds an envelope from the given Maildir entry, with an
+    /// optional body preview of the given length.
+    pub fn from_mdir_entry(entry: MaildirEntry, preview: Option<usize>) -> Result<Self> {
+        Ok(Self::read_mdir_entry(entry, None, preview, false)?.0)
+    }
 
-    fn try_from(entry: MaildirEntry) -> Result<Self> {
+    /// Reads an envelope from the given Maildir entry, together with
+    /// its cache record.
+    ///
+    /// The entry file is not read when the given cache record is up
+    /// to date and no preview is requested. Otherwise only the
+    /// headers are read when `headers_only` is enabled and no preview
+    /// is requested, in which case attachments are guessed from the
+    /// `Content-Type` header.
+    fn read_mdir_entry(
+        entry: MaildirEntry,
+        cached: Option<&MaildirEnvelopeRecord>,
+        preview: Option<usize>,
+        headers_only: bool,
+    ) -> Result<(Self, MaildirEnvelopeRecord)> {
         let id = entry.id()?.to_owned();
-        let msg = Message::from(entry.read()?);
 
-        let has_attachment = {
-            let attachments = msg.attachments();
+        let metadata = fs::metadata(entry.path())?;
+        let size = metadata.len() as usize;
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as i64)
+            .unwrap_or_default();
 
-            match attachments {
-                Ok(attachments) => !attachments.is_empty(),
-                Err(_) => false,
+        let (record, preview) = match cached {
+            Some(record) if preview.is_none() && record.matches(mtime, size) => {
+                (record.clone(), None)
+            }
+            _ if preview.is_none() && headers_only => {
+                let headers = read_mdir_headers(entry.path())
+                    .map_err(|err| Error::ReadMaildirEntryError(err, entry.path().to_owned()))?;
+                let has_attachment = guess_attachment(&Message::from(headers.as_slice()));
+                let record = MaildirEnvelopeRecord {
+                    mtime,
+                    size,
+                    has_attachment,
+                    headers,
+                };
+                (record, None)
+            }
+            _ => {
+                let raw = entry.read()?;
+                let msg = Message::from(raw.as_slice());
+                let has_attachment = msg.attachments().is_ok_and(|a| !a.is_empty());
+                let preview = preview.and_then(|len| msg.preview(len));
+                let record = MaildirEnvelopeRecord {
+                    mtime,
+                    size: raw.len(),
+                    has_attachment,
+                    headers: split_headers(&raw).to_vec(),
+                };
+                (record, preview)
             }
         };
 
         let flags = Flags::try_from(entry)?;
+        let msg = Message::from(record.headers.as_slice());
         let mut env = Envelope::from_msg(id, flags, msg);
-        env.has_attachment = has_attachment;
-        Ok(env)
+        env.has_attachment = record.has_attachment;
+        env.size = Some(record.size);
+        env.preview = preview;
+        env.internal_date =
+            (mtime > 0).then(|| DateTime::from_timestamp_nanos(mtime).fixed_offset());
+
+        Ok((env, record))
+    }
+}
+
+impl TryFrom<MaildirEntry> for Envelope {
+    type Error = Error;
+
+    fn try_from(entry: MaildirEntry) -> Result<Self> {
+        Envelope::from_mdir_entry(entry, None)
+    }
+}
+
+/// Reads the headers of the message file at the given path, stopping
+/// at the first empty line.
+fn read_mdir_headers(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut headers = Vec::new();
+
+    loop {
+        let len = reader.read_until(b'\n', &mut headers)?;
+        let line = &headers[headers.len() - len..];
+
+        if len == 0 || line == b"\n" || line == b"\r\n" {
+            break;
+        }
+    }
+
+    Ok(headers)
+}
+
+/// Returns the headers of the given raw message, including the empty
+/// line separating them from the body.
+fn split_headers(raw: &[u8]) -> &[u8] {
+    raw.windows(2)
+        .position(|w| w == b"\n\n")
+        .map(|pos| pos + 2)
+        .into_iter()
+        .chain(
+            raw.windows(4)
+                .position(|w| w == b"\r\n\r\n")
+                .map(|pos| pos + 4),
+        )
+        .min()
+        .map(|end| &raw[..end])
+        .unwrap_or(raw)
+}
+
+/// Guesses if a message contains attachments from its headers.
+///
+/// Messages are considered as having attachments when their content
+/// type is `multipart/mixed`, or when it is neither `text/*` nor
+/// `multipart/*`.
+fn guess_attachment(msg: &Message) -> bool {
+    let Some(ctype) = msg.parsed().ok().and_then(|msg| msg.content_type()) else {
+        return false;
+    };
+
+    match ctype.ctype() {
+        t if t.eq_ignore_ascii_case("multipart") => ctype
+            .subtype()
+            .is_some_and(|subtype| subtype.eq_ignore_ascii_case("mixed")),
+        t => !t.eq_ignore_ascii_case("text"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_headers;
+
+    #[test]
+    fn split_raw_headers() {
+        let raw = b"Subject: a\r\nFrom: b\r\n\r\nbody\r\n\r\nend";
+        assert_eq!(split_headers(raw), b"Subject: a\r\nFrom: b\r\n\r\n");
+
+        let raw = b"Subject: a\n\nbody";
+        assert_eq!(split_headers(raw), b"Subject: a\n\n");
+
+        let raw = b"Subject: a\n";
+        assert_eq!(split_headers(raw), b"Subject: a\n");
     }
 }
@@ -16,6 +16,7 @@
 pub mod maildir;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
+pub mod priority;
 #[cfg(feature = "sync")]
 pub mod sync;
 #[cfg(feature = "thread")]
@@ -40,7 +41,8 @@
 pub use self::{
     address::Address,
     flag::{Flag, Flags},
-    id::{Id, MultipleIds, SingleId},
+    id::{Id, IdRange, IdSet, MultipleIds, SingleId},
+    priority::Priority,
 };
 use crate::{
     account::config::AccountConfig, date::from_mail_parser_to_chrono_datetime, message::Message,
@@ -68,16 +70,41 @@ pub struct Envelope {
     pub from: Address,
     /// The first address from the email message header To.
     pub to: Address,
+    /// All the addresses from the email message header To.
+    pub to_addrs: Vec<Address>,
+    /// All the addresses from the email message header Cc.
+    pub cc_addrs: Vec<Address>,
     /// The Subject header from the email message.
     pub subject: String,
     /// The Date header from the email message.
+    ///
+    /// The date keeps the timezone offset of the header, as written
+    /// by the sender. See [`Envelope::format_date`] to display it in
+    /// the local timezone.
     pub date: DateTime<FixedOffset>,
+    /// The date the message has been received, when known.
+    ///
+    /// It matches the IMAP `INTERNALDATE` for the IMAP backend, and
+    /// the modification time of the message file for the Maildir and
+    /// Notmuch backends.
+    pub internal_date: Option<DateTime<FixedOffset>>,
+    /// The priority, from the Importance or X-Priority header.
+    pub priority: Priority,
 
     /// True if the current envelope contains at least one attachment.
     ///
     /// An attachment is defined here as a MIME part that is not a
     /// `text/*`.
     pub has_attachment: bool,
+
+    /// The size of the email message in bytes, when known.
+    pub size: Option<usize>,
+
+    /// A short plain text preview of the email message body.
+    ///
+    /// Only generated when requested, see
+    /// [`ListEnvelopesOptions::preview`](list::ListEnvelopesOptions::preview).
+    pub preview: Option<String>,
 }
 
 impl Envelope {
@@ -151,6 +178,9 @@ pub fn from_msg(id: impl ToString, flags: Flags, msg: Message) -> Envelope {
                 }
             };
 
+            envelope.to_addrs = from_mail_parser_addresses(msg.to());
+            envelope.cc_addrs = from_mail_parser_addresses(msg.cc());
+
             envelope.subject = msg.subject().map(ToOwned::to_owned).unwrap_or_default();
 
             match msg.date() {
@@ -173,6 +203,9 @@ pub fn from_msg(id: impl ToString, flags: Flags, msg: Message) -> Envelope {
                 });
 
             envelope.in_reply_to = msg.in_reply_to().as_text().map(|mid| format!("<{mid}>"));
+
+            envelope.priority =
+                Priority::from_headers(msg.header_raw("Importance"), msg.header_raw("X-Priority"));
         } else {
             trace!("cannot parse message header, skipping it");
         };
@@ -207,6 +240,12 @@ pub fn set_date(&mut self, date: &mail_parser::DateTime) {
         });
     }
 
+    /// Returns the internal date of the envelope, or the date of the
+    /// `Date` header when the internal date is unknown.
+    pub fn internal_date_or_date(&self) -> DateTime<FixedOffset> {
+        self.internal_date.unwrap_or(self.date)
+    }
+
     /// Format the envelope date according to the datetime format and
     /// timezone from the [account configuration](crate::AccountConfig).
     pub fn format_date(&self, config: &AccountConfig) -> String {
@@ -248,6 +287,20 @@ pub fn as_threaded(&self) -> ThreadedEnvelope {
     }
 }
 
+/// Collects all the addresses from the given [`mail_parser`]
+/// address header.
+fn from_mail_parser_addresses(addrs: Option<&mail_parser::Address>) -> Vec<Address> {
+    addrs
+        .into_iter()
+        .flat_map(|addrs| addrs.iter())
+        .filter_map(|addr| {
+            let email = addr.address.as_ref()?;
+            let name = addr.name.as_ref();
+            Some(Address::new(name, email))
+        })
+        .collect()
+}
+
 // NOTE: this is useful for the sync, not sure how relevant it is for
 // the rest.
 impl PartialEq for Envelope {
@@ -318,6 +371,12 @@ pub struct ThreadedEnvelope<'a> {
 
 #[cfg(feature = "thread")]
 impl ThreadedEnvelope<'_> {
+    /// Returns the internal date of the envelope, or the date of the
+    /// `Date` header when the internal date is unknown.
+    pub fn internal_date_or_date(&self) -> DateTime<FixedOffset> {
+        self.internal_date.unwrap_or(self.date)
+    }
+
     /// Format the envelope date according to the datetime format and
     /// timezone from the [account configuration](crate::AccountConfig).
     pub fn format_date(&self, config: &AccountConfig) -> String {
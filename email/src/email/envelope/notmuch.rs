@@ -3,31 +3,63 @@
 //! This module contains envelope-related mapping functions from the
 //! [notmuch] crate types.
 
+use std::fs;
+
+use chrono::{DateTime, Utc};
 use tracing::debug;
 
 use crate::{
     envelope::{Envelope, Envelopes},
-    flag::{Flag, Flags},
+    flag::Flag,
     message::Message,
+    notmuch::config::NotmuchTagsConfig,
 };
 
 impl Envelopes {
-    pub fn from_notmuch_msgs(msgs: notmuch::Messages) -> Self {
-        msgs.map(Envelope::from_notmuch_msg).collect()
+    pub fn from_notmuch_msgs(
+        msgs: notmuch::Messages,
+        tags: &NotmuchTagsConfig,
+        preview: Option<usize>,
+    ) -> Self {
+        msgs.map(|msg| Envelope::from_notmuch_msg(msg, tags, preview))
+            .collect()
     }
 }
 
 impl Envelope {
-    pub fn from_notmuch_msg(msg: notmuch::Message) -> Self {
+    pub fn from_notmuch_msg(
+        msg: notmuch::Message,
+        tags: &NotmuchTagsConfig,
+        preview: Option<usize>,
+    ) -> Self {
         let id = msg.id();
-        let flags = Flags::from(&msg);
+        let flags = tags.to_flags(msg.tags());
         let has_attachment = flags.contains(&Flag::custom("attachment"));
 
         let message_id = get_header(&msg, "Message-ID");
         let subject = get_header(&msg, "Subject");
         let from = get_header(&msg, "From");
+        let to = get_header(&msg, "To");
+        let cc = get_header(&msg, "Cc");
         let date = get_header(&msg, "Date");
-        let headers = [message_id, subject, from, date].join("\r\n") + "\r\n\r\n";
+        let importance = get_header(&msg, "Importance");
+        let x_priority = get_header(&msg, "X-Priority");
+        let headers = [
+            message_id, subject, from, to, cc, date, importance, x_priority,
+        ]
+        .join("\r\n")
+            + "\r\n\r\n";
+
+        let path = msg.filename();
+        let metadata = fs::metadata(&path).ok();
+        let size = metadata.as_ref().map(|meta| meta.len() as usize);
+        let internal_date = metadata
+            .and_then(|meta| meta.modified().ok())
+            .map(|mtime| DateTime::<Utc>::from(mtime).fixed_offset());
+        let preview = preview.and_then(|len| {
+            let bytes = fs::read(&path).ok()?;
+            Message::from(bytes).preview(len)
+        });
 
         // parse a fake message from the built header in order to
         // extract the envelope
@@ -35,6 +67,9 @@ pub fn from_notmuch_msg(msg: notmuch::Message) -> Self {
 
         let mut env = Envelope::from_msg(id, flags, msg);
         env.has_attachment = has_attachment;
+        env.size = size;
+        env.preview = preview;
+        env.internal_date = internal_date;
         env
     }
 }
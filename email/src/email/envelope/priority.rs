@@ -0,0 +1,105 @@
+//! Module dedicated to email envelope priority.
+//!
+//! The priority is taken from the `Importance` header or, when
+//! missing, from the non-standard but widespread `X-Priority` header.
+
+use std::{fmt, str::FromStr};
+
+use crate::email::error::Error;
+
+/// The email envelope priority.
+///
+/// Priorities are ordered from the highest to the lowest, so that
+/// sorting envelopes by priority shows urgent ones first.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl Priority {
+    /// Parse the given `Importance` header value.
+    pub fn from_importance(val: &str) -> Option<Self> {
+        val.parse().ok()
+    }
+
+    /// Parse the given `X-Priority` header value.
+    ///
+    /// The value starts with a digit from 1 (highest) to 5 (lowest),
+    /// optionally followed by a comment like `1 (Highest)`.
+    pub fn from_x_priority(val: &str) -> Option<Self> {
+        match val.split_whitespace().next()? {
+            "1" | "2" => Some(Self::High),
+            "3" => Some(Self::Normal),
+            "4" | "5" => Some(Self::Low),
+            _ => None,
+        }
+    }
+
+    /// Build the priority from the given `Importance` and
+    /// `X-Priority` header values, the first one taking precedence.
+    pub fn from_headers(importance: Option<&str>, x_priority: Option<&str>) -> Self {
+        importance
+            .and_then(Self::from_importance)
+            .or_else(|| x_priority.and_then(Self::from_x_priority))
+            .unwrap_or_default()
+    }
+
+    /// Return `true` if the priority is high.
+    pub fn is_high(&self) -> bool {
+        matches!(self, Self::High)
+    }
+}
+
+impl FromStr for Priority {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            s if s.eq_ignore_ascii_case("high") => Ok(Self::High),
+            s if s.eq_ignore_ascii_case("normal") => Ok(Self::Normal),
+            s if s.eq_ignore_ascii_case("low") => Ok(Self::Low),
+            s => Err(Error::ParsePriorityError(s.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::High => write!(f, "high"),
+            Self::Normal => write!(f, "normal"),
+            Self::Low => write!(f, "low"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Priority;
+
+    #[test]
+    fn from_headers() {
+        assert_eq!(Priority::from_headers(None, None), Priority::Normal);
+        assert_eq!(Priority::from_headers(Some(" High"), None), Priority::High);
+        assert_eq!(
+            Priority::from_headers(None, Some("5 (Lowest)")),
+            Priority::Low
+        );
+        assert_eq!(
+            Priority::from_headers(Some("low"), Some("1")),
+            Priority::Low
+        );
+        assert_eq!(
+            Priority::from_headers(Some("urgent"), Some("2")),
+            Priority::High
+        );
+    }
+}
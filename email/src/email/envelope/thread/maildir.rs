@@ -6,11 +6,9 @@
 
 use super::ThreadEnvelopes;
 use crate::{
-    envelope::{
-        list::ListEnvelopesOptions, Envelopes, SingleId, ThreadedEnvelope, ThreadedEnvelopes,
-    },
+    envelope::{list::ListEnvelopesOptions, SingleId, ThreadedEnvelope, ThreadedEnvelopes},
     maildir::MaildirContextSync,
-    AnyResult, Error,
+    AnyResult,
 };
 
 #[derive(Clone)]
@@ -43,11 +41,13 @@ async fn thread_envelopes(
         let ctx = self.ctx.lock().await;
         let mdir = ctx.get_maildir_from_folder_alias(folder)?;
 
-        let entries = mdir.read().map_err(Error::MaildirsError)?;
-        let envelopes = Envelopes::from_mdir_entries(entries, opts.query.as_ref())
-            .into_iter()
-            .map(|e| (e.id.clone(), e))
-            .collect();
+        let mut envelopes = ctx.read_envelopes(&mdir, opts.query.as_ref(), None)?;
+
+        if let Some(uids) = ctx.uid_db(&mdir)? {
+            envelopes.map_mdir_uids(&uids);
+        }
+
+        let envelopes = envelopes.into_iter().map(|e| (e.id.clone(), e)).collect();
 
         let envelopes = ThreadedEnvelopes::new(envelopes, move |envelopes| {
             let msg_id_mapping: HashMap<_, _> = envelopes
@@ -121,11 +121,13 @@ async fn thread_envelope(
         let ctx = self.ctx.lock().await;
         let mdir = ctx.get_maildir_from_folder_alias(folder)?;
 
-        let entries = mdir.read().map_err(Error::MaildirsError)?;
-        let envelopes = Envelopes::from_mdir_entries(entries, opts.query.as_ref())
-            .into_iter()
-            .map(|e| (e.id.clone(), e))
-            .collect();
+        let mut envelopes = ctx.read_envelopes(&mdir, opts.query.as_ref(), None)?;
+
+        if let Some(uids) = ctx.uid_db(&mdir)? {
+            envelopes.map_mdir_uids(&uids);
+        }
+
+        let envelopes = envelopes.into_iter().map(|e| (e.id.clone(), e)).collect();
 
         let envelopes = ThreadedEnvelopes::new(envelopes, move |envelopes| {
             let msg_id_mapping: HashMap<_, _> = envelopes
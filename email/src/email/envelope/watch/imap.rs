@@ -3,7 +3,6 @@
 use async_trait::async_trait;
 use tokio::sync::oneshot::{Receiver, Sender};
 use tracing::{debug, info};
-use utf7_imap::encode_utf7_imap as encode_utf7;
 
 use super::WatchEnvelopes;
 use crate::{envelope::Envelope, imap::ImapContext, AnyResult};
@@ -37,7 +36,7 @@ pub async fn watch_envelopes_loop(
         let mut client = self.ctx.client().await;
 
         let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
+        let folder_encoded = client.encode_mailbox(&folder);
         debug!("utf7 encoded folder: {folder_encoded}");
 
         let envelopes_count = client
@@ -1,17 +1,18 @@
-use std::{collections::HashMap, sync::mpsc};
+use std::collections::HashMap;
 
 use async_trait::async_trait;
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
-use tokio::sync::oneshot::{Receiver, Sender};
+use tokio::{
+    select,
+    sync::{
+        mpsc,
+        oneshot::{Receiver, Sender},
+    },
+};
 use tracing::{debug, info, trace};
 
 use super::WatchEnvelopes;
-use crate::{
-    email::error::Error,
-    envelope::{Envelope, Envelopes},
-    maildir::MaildirContextSync,
-    AnyResult,
-};
+use crate::{email::error::Error, envelope::Envelope, maildir::MaildirContextSync, AnyResult};
 
 pub struct WatchMaildirEnvelopes {
     ctx: MaildirContextSync,
@@ -36,8 +37,8 @@ impl WatchEnvelopes for WatchMaildirEnvelopes {
     async fn watch_envelopes(
         &self,
         folder: &str,
-        _wait_for_shutdown_request: Receiver<()>,
-        _shutdown: Sender<()>,
+        mut wait_for_shutdown_request: Receiver<()>,
+        shutdown: Sender<()>,
     ) -> AnyResult<()> {
         info!("maildir: watching folder {folder} for email changes");
 
@@ -45,26 +46,48 @@ async fn watch_envelopes(
         let config = &session.account_config;
 
         let mdir = session.get_maildir_from_folder_alias(folder)?;
-        let entries = mdir.read().map_err(Error::MaildirsError)?;
-        let envelopes = Envelopes::from_mdir_entries(entries, None);
+        let mut envelopes = session.read_envelopes(&mdir, None, None)?;
+
+        if let Some(uids) = session.uid_db(&mdir)? {
+            envelopes.map_mdir_uids(&uids);
+        }
+
         let mut envelopes: HashMap<String, Envelope> =
             HashMap::from_iter(envelopes.into_iter().map(|e| (e.id.clone(), e)));
 
-        let (tx, rx) = mpsc::channel();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let handler = move |res| {
+            let _ = tx.send(res);
+        };
         let mut watcher =
-            RecommendedWatcher::new(tx, Default::default()).map_err(Error::NotifyFailure)?;
+            RecommendedWatcher::new(handler, Default::default()).map_err(Error::NotifyFailure)?;
         watcher
             .watch(mdir.path(), RecursiveMode::Recursive)
             .map_err(Error::NotifyFailure)?;
         debug!("watching maildir folder {folder:?}…");
 
-        for res in rx {
+        loop {
+            let res = select! {
+                res = rx.recv() => match res {
+                    Some(res) => res,
+                    None => break,
+                },
+                _ = &mut wait_for_shutdown_request => {
+                    debug!("shutdown requested, stopping maildir watcher…");
+                    break;
+                }
+            };
+
             match res {
                 Ok(_evt) => {
                     trace!("received filesystem change event: {_evt:?}");
 
-                    let entries = mdir.read().map_err(Error::MaildirsError)?;
-                    let next_envelopes = Envelopes::from_mdir_entries(entries, None);
+                    let mut next_envelopes = session.read_envelopes(&mdir, None, None)?;
+
+                    if let Some(uids) = session.uid_db(&mdir)? {
+                        next_envelopes.map_mdir_uids(&uids);
+                    }
+
                     let next_envelopes: HashMap<String, Envelope> =
                         HashMap::from_iter(next_envelopes.into_iter().map(|e| (e.id.clone(), e)));
 
@@ -79,6 +102,8 @@ async fn watch_envelopes(
             }
         }
 
+        let _ = shutdown.send(());
+
         Ok(())
     }
 }
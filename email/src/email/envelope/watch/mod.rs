@@ -35,6 +35,7 @@ async fn exec_hooks(
                 info!(id, "new message detected");
                 debug!("processing received envelope event…");
                 config.exec_received_envelope_hook(envelope).await;
+                crate::watch::service::emit_received(envelope);
             } else {
                 // TODO
                 // debug!("processing any envelope event…");
@@ -1,6 +1,8 @@
 use std::{any::Any, io, path::PathBuf, result};
 
 use chumsky::error::Rich;
+#[cfg(feature = "unsubscribe")]
+use http::ureq::http::StatusCode;
 #[cfg(feature = "imap")]
 use imap_client::imap_next::imap_types::error::ValidationError;
 use thiserror::Error;
@@ -10,7 +12,7 @@
 use crate::flag::Flags;
 use crate::{
     envelope::{Id, SingleId},
-    AnyBoxedError, AnyError,
+    AnyBoxedError, AnyError, ErrorKind,
 };
 
 /// The global `Result` alias of the module.
@@ -46,10 +48,15 @@ pub enum Error {
     #[error("cannot list maildir entries")]
     ListMaildirEntriesError(#[source] maildirs::Error),
     #[cfg(feature = "maildir")]
+    #[error("cannot read maildir entry {1}")]
+    ReadMaildirEntryError(#[source] io::Error, PathBuf),
+    #[cfg(feature = "maildir")]
     #[error("cannot get flags from maildir entry {0}")]
     GetMaildirFlagsError(#[source] maildirs::Error, PathBuf),
     #[error("cannot find message associated to envelope {0}")]
     FindMessageError(String),
+    #[error("cannot parse id set `{0}`")]
+    ParseIdSetError(String),
     #[error("cannot parse search emails query `{1}`")]
     ParseError(Vec<Rich<'static, char>>, String),
     #[error("cannot interpret message as template")]
@@ -98,6 +105,36 @@ pub enum Error {
     InterpretEmailAsTplError(#[source] mml::Error),
     #[error("cannot parse email message")]
     ParseEmailMessageError,
+    #[cfg(feature = "calendar")]
+    #[error("cannot find calendar invitation in message")]
+    FindCalendarInvitationError,
+    #[cfg(feature = "calendar")]
+    #[error("cannot find organizer of calendar event {0}")]
+    FindCalendarOrganizerError(String),
+    #[cfg(feature = "calendar")]
+    #[error("cannot find attendee {0} in calendar event")]
+    FindCalendarAttendeeError(String),
+    #[cfg(feature = "calendar")]
+    #[error("cannot build calendar reply message")]
+    BuildCalendarReplyError(#[source] io::Error),
+    #[cfg(feature = "unsubscribe")]
+    #[error("cannot find List-Unsubscribe header in message")]
+    FindListUnsubscribeError,
+    #[cfg(feature = "unsubscribe")]
+    #[error("cannot parse unsubscribe mailto URI {0}")]
+    ParseMailtoError(String),
+    #[cfg(feature = "unsubscribe")]
+    #[error("cannot build unsubscribe message")]
+    BuildUnsubscribeMessageError(#[source] io::Error),
+    #[cfg(feature = "unsubscribe")]
+    #[error("cannot send unsubscribe request to {1}")]
+    SendUnsubscribeRequestError(#[source] http::Error, String),
+    #[cfg(feature = "unsubscribe")]
+    #[error("cannot unsubscribe using {1}: server responded with status {0}")]
+    UnsubscribeRequestStatusError(StatusCode, String),
+    #[cfg(feature = "unsubscribe")]
+    #[error("cannot unsubscribe automatically, visit {0} instead")]
+    UnsubscribeManuallyError(String),
     #[error("cannot get notmuch message filename from {0}")]
     GetMessageFilenameNotmuchError(PathBuf),
     #[cfg(feature = "notmuch")]
@@ -152,11 +189,21 @@ pub enum Error {
     ParseFlagMaildirError(String),
     #[error("cannot parse imap flag {0}")]
     ParseFlagImapError(String),
+    #[error("cannot parse priority {0}")]
+    ParsePriorityError(String),
+    #[error("cannot add flags to envelopes matching query: feature not supported by backend")]
+    AddFlagsMatchingNotSupportedError,
+    #[error("cannot set flags to envelopes matching query: feature not supported by backend")]
+    SetFlagsMatchingNotSupportedError,
+    #[error("cannot remove flags from envelopes matching query: feature not supported by backend")]
+    RemoveFlagsMatchingNotSupportedError,
     #[cfg(feature = "maildir")]
     #[error("cannot add maildir flags {3} to envelope(s) {2} from folder {1}")]
     AddFlagsMaildirError(#[source] maildirs::Error, String, String, Flags),
     #[error("invalid input: {0}")]
     InvalidInput(String),
+    #[error("cannot add invalid header {0}")]
+    InvalidHeaderError(String),
     #[error("failed to get envelopes: {0}")]
     FailedToGetEnvelopes(#[source] JoinError),
     #[cfg(feature = "notmuch")]
@@ -181,6 +228,25 @@ pub enum Error {
     ListRightEnvelopesCachedError(#[source] AnyBoxedError),
     #[error("cannot list envelopes from right sync backend")]
     ListRightEnvelopesError(#[source] AnyBoxedError),
+    #[error("cannot add message to folder {0}: batch upload failed")]
+    AddMessagesBatchError(String),
+    #[error("cannot read sync id mapping at {1}")]
+    ReadIdMappingError(#[source] io::Error, PathBuf),
+    #[error("cannot write sync id mapping at {1}")]
+    WriteIdMappingError(#[source] io::Error, PathBuf),
+    #[error("cannot save sync conflict at {1}")]
+    SaveConflictError(#[source] io::Error, PathBuf),
+
+    #[error("cannot create backup directory {1}")]
+    CreateBackupDirError(#[source] io::Error, PathBuf),
+    #[error("cannot read backup directory {1}")]
+    ReadBackupDirError(#[source] io::Error, PathBuf),
+    #[error("cannot read backup file {1}")]
+    ReadBackupFileError(#[source] io::Error, PathBuf),
+    #[error("cannot write backup file {1}")]
+    WriteBackupFileError(#[source] io::Error, PathBuf),
+    #[error("cannot set internal date of message file {1}")]
+    SetMessageFileDateError(#[source] io::Error, PathBuf),
 
     #[cfg(feature = "maildir")]
     #[error(transparent)]
@@ -190,10 +256,79 @@ pub enum Error {
     IoError(#[from] io::Error),
 }
 
+impl Error {
+    /// Returns the kind of the error.
+    ///
+    /// See [`ErrorKind`] for the available kinds.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::AcountError(err) | Self::DecryptPartError(err) => err.kind(),
+            Self::ListLeftEnvelopesCachedError(err)
+            | Self::ListLeftEnvelopesError(err)
+            | Self::ListRightEnvelopesCachedError(err)
+            | Self::ListRightEnvelopesError(err) => err.kind(),
+            Self::FindMessageError(_)
+            | Self::GetMessageFilenameNotmuchError(_)
+            | Self::GetUidMissingImapError(_)
+            | Self::GetEnvelopeMissingError(_)
+            | Self::FindEnvelopeEmptyNotmuchError(..)
+            | Self::GetEnvelopeMaildirError(..)
+            | Self::GetFirstEnvelopeImapError(..)
+            | Self::GetEnvelopesOutOfBoundsNotmuchError(..)
+            | Self::GetEnvelopesOutOfBoundsMaildirError(..)
+            | Self::BuildPageRangeOutOfBoundsImapError(_)
+            | Self::GetAddedMessageUidFromRangeImapError(_) => ErrorKind::NotFound,
+            #[cfg(feature = "calendar")]
+            Self::FindCalendarInvitationError
+            | Self::FindCalendarOrganizerError(_)
+            | Self::FindCalendarAttendeeError(_) => ErrorKind::NotFound,
+            #[cfg(feature = "unsubscribe")]
+            Self::FindListUnsubscribeError => ErrorKind::NotFound,
+            #[cfg(feature = "unsubscribe")]
+            Self::SendUnsubscribeRequestError(..) | Self::UnsubscribeRequestStatusError(..) => {
+                ErrorKind::Network
+            }
+            #[cfg(feature = "unsubscribe")]
+            Self::ParseMailtoError(_) => ErrorKind::Protocol,
+            Self::ParseIdSetError(_)
+            | Self::ParseError(..)
+            | Self::ParseEmailError
+            | Self::ParseEmailEmptyRawError
+            | Self::ParseEmailFromEmptyEntriesError
+            | Self::ParseEmailMessageError
+            | Self::ChumskyError(_)
+            | Self::ParseSubfolderMaildirError(..)
+            | Self::ParseFlagError(_)
+            | Self::ParseFlagMaildirError(_)
+            | Self::ParseFlagImapError(_)
+            | Self::ParsePriorityError(_)
+            | Self::InvalidInput(_)
+            | Self::InvalidHeaderError(_)
+            | Self::GetMultipartContentTypeError
+            | Self::GetEncryptedPartMultipartError => ErrorKind::Protocol,
+            #[cfg(feature = "imap")]
+            Self::SortUidsError(..) | Self::SearchUidsError(..) | Self::ParseSequenceError(_) => {
+                ErrorKind::Protocol
+            }
+            #[cfg(feature = "notmuch")]
+            Self::SearchMessagesInvalidQueryNotmuch(..) => ErrorKind::Protocol,
+            Self::GetAddedMessageUidImapError
+            | Self::AddFlagsMatchingNotSupportedError
+            | Self::SetFlagsMatchingNotSupportedError
+            | Self::RemoveFlagsMatchingNotSupportedError => ErrorKind::Config,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
 impl AnyError for Error {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn kind(&self) -> ErrorKind {
+        Error::kind(self)
+    }
 }
 
 impl From<Error> for AnyBoxedError {
@@ -2,9 +2,8 @@
 
 use async_trait::async_trait;
 use tracing::{debug, info};
-use utf7_imap::encode_utf7_imap as encode_utf7;
 
-use super::{AddMessage, Flags};
+use super::{AddMessage, AddMessageOptions, Flags};
 use crate::{envelope::SingleId, imap::ImapContext, AnyResult};
 
 #[derive(Clone, Debug)]
@@ -40,7 +39,7 @@ async fn add_message_with_flags(
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
+        let folder_encoded = client.encode_mailbox(&folder);
         debug!("utf7 encoded folder: {folder_encoded}");
 
         let uid = client
@@ -53,4 +52,71 @@ async fn add_message_with_flags(
 
         Ok(SingleId::from(uid.to_string()))
     }
+
+    async fn add_message_with_options(
+        &self,
+        folder: &str,
+        msg: &[u8],
+        opts: &AddMessageOptions,
+    ) -> AnyResult<SingleId> {
+        let Some(date) = opts.internal_date else {
+            return self.add_message_with_flags(folder, msg, &opts.flags).await;
+        };
+
+        let flags = &opts.flags;
+        info!("adding imap message to folder {folder} with flags {flags} and date {date}");
+
+        let mut client = self.ctx.client().await;
+        let config = &client.account_config;
+
+        let folder = config.get_folder_alias(folder);
+        let folder_encoded = client.encode_mailbox(&folder);
+        debug!("utf7 encoded folder: {folder_encoded}");
+
+        let uid = client
+            .add_message_with_date(
+                &folder_encoded,
+                flags.to_imap_flags_iter().into_iter().collect(),
+                msg.to_vec(),
+                date,
+            )
+            .await?;
+
+        Ok(SingleId::from(uid.to_string()))
+    }
+
+    async fn add_messages_with_flags(
+        &self,
+        folder: &str,
+        msgs: &[(&[u8], &Flags)],
+    ) -> AnyResult<Vec<AnyResult<SingleId>>> {
+        info!("adding {} imap messages to folder {folder}", msgs.len());
+
+        let mut client = self.ctx.client().await;
+        let config = &client.account_config;
+
+        let folder = config.get_folder_alias(folder);
+        let folder_encoded = client.encode_mailbox(&folder);
+        debug!("utf7 encoded folder: {folder_encoded}");
+
+        let msgs = msgs
+            .iter()
+            .map(|(msg, flags)| {
+                (
+                    flags.to_imap_flags_iter().into_iter().collect(),
+                    msg.to_vec(),
+                )
+            })
+            .collect();
+
+        let uids = client.add_messages(&folder_encoded, msgs).await?;
+
+        Ok(uids
+            .into_iter()
+            .map(|uid| {
+                let uid = uid?;
+                Ok(SingleId::from(uid.to_string()))
+            })
+            .collect())
+    }
 }
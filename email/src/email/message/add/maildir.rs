@@ -1,8 +1,19 @@
+use std::{fs::File, path::Path, time::SystemTime};
+
 use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
 use tracing::info;
 
-use super::{AddMessage, Flags};
-use crate::{email::error::Error, envelope::SingleId, maildir::MaildirContextSync, AnyResult};
+use super::{AddMessage, AddMessageOptions, Flags};
+use crate::{
+    email::error::Error,
+    envelope::{
+        flag::maildir::{update_mdir_entry_flags, FlagsUpdate},
+        SingleId,
+    },
+    maildir::MaildirContextSync,
+    AnyResult,
+};
 
 #[derive(Clone)]
 pub struct AddMaildirMessage {
@@ -31,6 +42,21 @@ async fn add_message_with_flags(
         raw_msg: &[u8],
         flags: &Flags,
     ) -> AnyResult<SingleId> {
+        let opts = AddMessageOptions {
+            flags: flags.clone(),
+            ..Default::default()
+        };
+
+        self.add_message_with_options(folder, raw_msg, &opts).await
+    }
+
+    async fn add_message_with_options(
+        &self,
+        folder: &str,
+        raw_msg: &[u8],
+        opts: &AddMessageOptions,
+    ) -> AnyResult<SingleId> {
+        let flags = &opts.flags;
         info!("adding maildir message to folder {folder} with flags {flags}");
 
         let ctx = self.ctx.lock().await;
@@ -47,6 +73,107 @@ async fn add_message_with_flags(
                 Error::StoreWithFlagsMaildirError(err, folder.to_owned(), flags.clone())
             })?;
 
-        Ok(SingleId::from(entry.id().unwrap()))
+        if let Some(date) = &opts.internal_date {
+            set_message_file_date(entry.path(), date)?;
+        }
+
+        // keywords have no standard Maildir flag letter, they are
+        // added in a second time using the Dovecot keywords file
+        let keywords: Flags = flags.iter().filter(|f| f.is_custom()).cloned().collect();
+
+        if !keywords.is_empty() {
+            update_mdir_entry_flags(&entry, &keywords, FlagsUpdate::Add).map_err(|err| {
+                Error::StoreWithFlagsMaildirError(err, folder.to_owned(), flags.clone())
+            })?;
+        }
+
+        let id = entry.id().unwrap();
+
+        if let Some(mut uids) = ctx.uid_db(&mdir)? {
+            let uid = uids.insert(id);
+            uids.save()?;
+            return Ok(SingleId::from(uid));
+        }
+
+        Ok(SingleId::from(id))
+    }
+}
+
+/// Sets the modification time of the given message file to the given
+/// internal date, which is how Maildir based backends store it.
+pub(crate) fn set_message_file_date(
+    path: &Path,
+    date: &DateTime<FixedOffset>,
+) -> Result<(), Error> {
+    let mtime = SystemTime::from(*date);
+
+    File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(mtime))
+        .map_err(|err| Error::SetMessageFileDateError(err, path.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Arc, time::SystemTime};
+
+    use chrono::DateTime;
+
+    use super::{set_message_file_date, AddMaildirMessage};
+    use crate::{
+        account::config::AccountConfig,
+        backend::context::BackendContextBuilder,
+        envelope::list::{maildir::ListMaildirEnvelopes, ListEnvelopes, ListEnvelopesOptions},
+        flag::{Flag, Flags},
+        folder::add::{maildir::AddMaildirFolder, AddFolder},
+        maildir::{config::MaildirConfig, MaildirContextBuilder},
+        message::add::AddMessage,
+    };
+
+    #[test]
+    fn set_file_date() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("msg");
+        fs::write(&path, "Subject: test\n\nbody\n").unwrap();
+
+        let date = DateTime::parse_from_rfc3339("2020-02-03T04:05:06+02:00").unwrap();
+        set_message_file_date(&path, &date).unwrap();
+
+        let mtime = fs::metadata(&path).unwrap().modified().unwrap();
+        assert_eq!(mtime, SystemTime::from(date));
+    }
+
+    #[tokio::test]
+    async fn add_message_with_keywords() {
+        let dir = tempfile::tempdir().unwrap();
+        let mdir_config = Arc::new(MaildirConfig {
+            root_dir: dir.path().to_owned(),
+            maildirpp: false,
+            layout: None,
+            uid_db: false,
+            envelopes: Default::default(),
+        });
+        let ctx = MaildirContextBuilder::new(Arc::new(AccountConfig::default()), mdir_config)
+            .build()
+            .await
+            .unwrap();
+        AddMaildirFolder::new(&ctx)
+            .add_folder("INBOX")
+            .await
+            .unwrap();
+
+        let flags = Flags::from_iter([Flag::Seen, Flag::custom("receipts")]);
+        AddMaildirMessage::new(&ctx)
+            .add_message_with_flags("INBOX", b"Subject: keywords\r\n\r\nHello!\r\n", &flags)
+            .await
+            .unwrap();
+
+        let envelopes = ListMaildirEnvelopes::new(&ctx)
+            .list_envelopes("INBOX", ListEnvelopesOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(envelopes.len(), 1);
+        assert_eq!(envelopes[0].flags, flags);
     }
 }
@@ -7,6 +7,7 @@
 pub mod notmuch;
 
 use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
 
 use crate::{
     envelope::SingleId,
@@ -14,6 +15,20 @@
     AnyResult,
 };
 
+/// The options used to add a message.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AddMessageOptions {
+    /// The flags of the message.
+    pub flags: Flags,
+
+    /// The internal date of the message, which is the date the
+    /// message has been received.
+    ///
+    /// Defaults to the current date. Setting it preserves original
+    /// delivery dates when migrating or restoring messages.
+    pub internal_date: Option<DateTime<FixedOffset>>,
+}
+
 #[async_trait]
 pub trait AddMessage: Send + Sync {
     /// Add the given raw email message with the given flags to the
@@ -42,4 +57,82 @@ async fn add_message(&self, folder: &str, msg: &[u8]) -> AnyResult<SingleId> {
         self.add_message_with_flags(folder, msg, &Default::default())
             .await
     }
+
+    /// Add the given raw email message to the given folder, using
+    /// the given options.
+    ///
+    /// The default implementation ignores the internal date, backends
+    /// able to store it should override it.
+    async fn add_message_with_options(
+        &self,
+        folder: &str,
+        msg: &[u8],
+        opts: &AddMessageOptions,
+    ) -> AnyResult<SingleId> {
+        self.add_message_with_flags(folder, msg, &opts.flags).await
+    }
+
+    /// Add the given raw email messages with their flags to the
+    /// given folder.
+    ///
+    /// Returns one result per message, in order, so that a failing
+    /// message does not hide the identifiers of the messages already
+    /// added. The outer error is reserved to failures affecting the
+    /// whole batch. The default implementation adds messages one by
+    /// one, backends able to upload messages in bulk should override
+    /// it.
+    async fn add_messages_with_flags(
+        &self,
+        folder: &str,
+        msgs: &[(&[u8], &Flags)],
+    ) -> AnyResult<Vec<AnyResult<SingleId>>> {
+        let mut ids = Vec::with_capacity(msgs.len());
+
+        for (msg, flags) in msgs {
+            ids.push(self.add_message_with_flags(folder, msg, flags).await);
+        }
+
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::AddMessage;
+    use crate::{email::Error, envelope::SingleId, flag::Flags, AnyResult};
+
+    struct FailingAdd;
+
+    #[async_trait]
+    impl AddMessage for FailingAdd {
+        async fn add_message_with_flags(
+            &self,
+            _folder: &str,
+            msg: &[u8],
+            _flags: &Flags,
+        ) -> AnyResult<SingleId> {
+            match msg {
+                b"fail" => Err(Error::FindMessageError(String::from("fail")).into()),
+                msg => Ok(SingleId::from(String::from_utf8_lossy(msg).to_string())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn add_messages_keeps_ids_after_failure() {
+        let flags = Flags::default();
+        let msgs: [(&[u8], &Flags); 3] = [(b"1", &flags), (b"fail", &flags), (b"3", &flags)];
+
+        let ids = FailingAdd
+            .add_messages_with_flags("INBOX", &msgs)
+            .await
+            .unwrap();
+
+        assert_eq!(ids.len(), 3);
+        assert_eq!(ids[0].as_ref().unwrap().as_str(), "1");
+        assert!(ids[1].is_err());
+        assert_eq!(ids[2].as_ref().unwrap().as_str(), "3");
+    }
 }
@@ -0,0 +1,392 @@
+//! # Authentication
+//!
+//! Module dedicated to inbound message authentication. The
+//! `Authentication-Results` headers ([RFC 8601]) added by receiving
+//! servers are parsed into [`AuthenticationResults`], then summed up
+//! into an [`AuthenticationVerdict`] telling whether the message
+//! passed or failed SPF, DKIM and DMARC checks.
+//!
+//! Since any server on the path can add such headers, only the ones
+//! added by trusted authentication services should be considered. By
+//! default, only the topmost header is considered, as it is the one
+//! added by the last receiving server.
+//!
+//! [RFC 8601]: https://www.rfc-editor.org/rfc/rfc8601
+
+use std::fmt;
+
+use super::Message;
+use crate::email::error::Error;
+
+/// The name of the authentication results header.
+const AUTHENTICATION_RESULTS: &str = "Authentication-Results";
+
+/// The result of an authentication method.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AuthenticationStatus {
+    Pass,
+    Fail,
+    SoftFail,
+    Neutral,
+    None,
+    TempError,
+    PermError,
+    Policy,
+    Other(String),
+}
+
+impl AuthenticationStatus {
+    /// Return `true` if the status is a failure.
+    ///
+    /// Soft failures, neutral and error statuses are not considered
+    /// as failures.
+    pub fn is_fail(&self) -> bool {
+        matches!(self, Self::Fail)
+    }
+
+    /// Return `true` if the status is a pass.
+    pub fn is_pass(&self) -> bool {
+        matches!(self, Self::Pass)
+    }
+}
+
+impl From<&str> for AuthenticationStatus {
+    fn from(status: &str) -> Self {
+        match status.to_ascii_lowercase().as_str() {
+            "pass" => Self::Pass,
+            "fail" | "hardfail" => Self::Fail,
+            "softfail" => Self::SoftFail,
+            "neutral" => Self::Neutral,
+            "none" => Self::None,
+            "temperror" => Self::TempError,
+            "permerror" => Self::PermError,
+            "policy" => Self::Policy,
+            status => Self::Other(status.to_owned()),
+        }
+    }
+}
+
+impl fmt::Display for AuthenticationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pass => write!(f, "pass"),
+            Self::Fail => write!(f, "fail"),
+            Self::SoftFail => write!(f, "softfail"),
+            Self::Neutral => write!(f, "neutral"),
+            Self::None => write!(f, "none"),
+            Self::TempError => write!(f, "temperror"),
+            Self::PermError => write!(f, "permerror"),
+            Self::Policy => write!(f, "policy"),
+            Self::Other(status) => write!(f, "{status}"),
+        }
+    }
+}
+
+/// The result of a single authentication method, like `dkim=pass
+/// header.d=example.com`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuthenticationResult {
+    /// The authentication method (spf, dkim, dmarc, arc…), in lower
+    /// case and without version.
+    pub method: String,
+
+    /// The result of the method.
+    pub status: AuthenticationStatus,
+
+    /// The reason of the result, if given.
+    pub reason: Option<String>,
+
+    /// The properties of the result, like `header.d` or
+    /// `smtp.mailfrom`.
+    pub properties: Vec<(String, String)>,
+}
+
+impl AuthenticationResult {
+    /// Find the value of the given property.
+    pub fn property(&self, name: &str) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, val)| val.as_str())
+    }
+}
+
+/// The content of an `Authentication-Results` header.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AuthenticationResults {
+    /// The identifier of the authentication service which added the
+    /// header, usually the host name of the receiving server.
+    pub authserv_id: String,
+
+    /// The results of the authentication methods.
+    pub results: Vec<AuthenticationResult>,
+}
+
+impl AuthenticationResults {
+    /// Parses the given `Authentication-Results` header value.
+    ///
+    /// Returns `None` if the header does not contain any
+    /// authentication service identifier.
+    pub fn parse(header: &str) -> Option<Self> {
+        let header = strip_comments(header);
+        let mut statements = split_outside_quotes(&header, |c| c == ';').into_iter();
+
+        let authserv_id = statements.next()?.split_whitespace().next()?.to_owned();
+
+        let results = statements
+            .filter_map(|statement| {
+                let mut tokens = split_outside_quotes(statement, char::is_whitespace).into_iter();
+                let (method, status) = tokens.next()?.split_once('=')?;
+                let method = method.split('/').next()?.trim().to_ascii_lowercase();
+
+                let mut result = AuthenticationResult {
+                    method,
+                    status: AuthenticationStatus::from(status.trim()),
+                    reason: None,
+                    properties: Vec::new(),
+                };
+
+                for (key, val) in tokens.filter_map(|token| token.split_once('=')) {
+                    let val = unquote(val);
+
+                    if key.eq_ignore_ascii_case("reason") {
+                        result.reason = Some(val);
+                    } else {
+                        result.properties.push((key.to_ascii_lowercase(), val));
+                    }
+                }
+
+                Some(result)
+            })
+            .collect();
+
+        Some(Self {
+            authserv_id,
+            results,
+        })
+    }
+}
+
+/// The authentication verdict of a message.
+///
+/// Each field contains the status of the corresponding method, or
+/// `None` if the method has not been evaluated.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AuthenticationVerdict {
+    /// The SPF status.
+    pub spf: Option<AuthenticationStatus>,
+
+    /// The DKIM status.
+    ///
+    /// Messages can carry several DKIM signatures: the status is a
+    /// pass if at least one signature passed.
+    pub dkim: Option<AuthenticationStatus>,
+
+    /// The DMARC status.
+    pub dmarc: Option<AuthenticationStatus>,
+}
+
+impl AuthenticationVerdict {
+    /// Builds the verdict from the given authentication results.
+    pub fn from_results<'a>(results: impl IntoIterator<Item = &'a AuthenticationResults>) -> Self {
+        let mut verdict = Self::default();
+
+        for result in results.into_iter().flat_map(|results| &results.results) {
+            let status = match result.method.as_str() {
+                "spf" => &mut verdict.spf,
+                "dkim" => &mut verdict.dkim,
+                "dmarc" => &mut verdict.dmarc,
+                _ => continue,
+            };
+
+            if !status.as_ref().is_some_and(AuthenticationStatus::is_pass) {
+                *status = Some(result.status.clone());
+            }
+        }
+
+        verdict
+    }
+
+    /// Return `true` if at least one method failed.
+    pub fn is_fail(&self) -> bool {
+        self.failures().next().is_some()
+    }
+
+    /// Returns the names of the methods that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &'static str> + '_ {
+        [
+            ("spf", &self.spf),
+            ("dkim", &self.dkim),
+            ("dmarc", &self.dmarc),
+        ]
+        .into_iter()
+        .filter(|(_, status)| status.as_ref().is_some_and(AuthenticationStatus::is_fail))
+        .map(|(method, _)| method)
+    }
+}
+
+impl Message<'_> {
+    /// Parses all the `Authentication-Results` headers of the
+    /// message, from the topmost to the bottommost one.
+    pub fn authentication_results(&self) -> Result<Vec<AuthenticationResults>, Error> {
+        let msg = self.parsed()?;
+
+        let results = msg
+            .headers()
+            .iter()
+            .filter(|header| {
+                header
+                    .name
+                    .as_str()
+                    .eq_ignore_ascii_case(AUTHENTICATION_RESULTS)
+            })
+            .filter_map(|header| {
+                let raw = msg
+                    .raw_message
+                    .get(header.offset_start..header.offset_end)?;
+                AuthenticationResults::parse(&String::from_utf8_lossy(raw))
+            })
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Builds the authentication verdict of the message, from the
+    /// `Authentication-Results` headers added by the given trusted
+    /// authentication services.
+    ///
+    /// When no trusted service is given, only the topmost header is
+    /// considered.
+    pub fn authentication_verdict(
+        &self,
+        trusted_authserv_ids: &[impl AsRef<str>],
+    ) -> Result<AuthenticationVerdict, Error> {
+        let results = self.authentication_results()?;
+
+        let verdict = if trusted_authserv_ids.is_empty() {
+            AuthenticationVerdict::from_results(results.first())
+        } else {
+            AuthenticationVerdict::from_results(results.iter().filter(|results| {
+                trusted_authserv_ids
+                    .iter()
+                    .any(|id| id.as_ref().eq_ignore_ascii_case(&results.authserv_id))
+            }))
+        };
+
+        Ok(verdict)
+    }
+}
+
+/// Removes comments (text between parentheses, which can be nested)
+/// from the given header value, except inside quoted strings.
+fn strip_comments(header: &str) -> String {
+    let mut stripped = String::with_capacity(header.len());
+    let mut depth = 0;
+    let mut in_quotes = false;
+
+    for c in header.chars() {
+        match c {
+            '"' if depth == 0 => {
+                in_quotes = !in_quotes;
+                stripped.push(c);
+            }
+            '(' if !in_quotes => depth += 1,
+            ')' if !in_quotes && depth > 0 => depth -= 1,
+            _ if depth > 0 => (),
+            _ => stripped.push(c),
+        }
+    }
+
+    stripped
+}
+
+/// Splits the given value at the characters matching the given
+/// predicate, except inside quoted strings. Empty parts are skipped.
+fn split_outside_quotes(value: &str, is_sep: impl Fn(char) -> bool) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+
+    for (i, c) in value.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        } else if !in_quotes && is_sep(c) {
+            parts.push(&value[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+
+    parts.push(&value[start..]);
+    parts
+        .into_iter()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Removes the quotes surrounding the given value, if any.
+fn unquote(val: &str) -> String {
+    let val = val.trim();
+    val.strip_prefix('"')
+        .and_then(|val| val.strip_suffix('"'))
+        .unwrap_or(val)
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuthenticationResults, AuthenticationStatus, AuthenticationVerdict};
+    use crate::message::Message;
+
+    #[test]
+    fn parse_header() {
+        let results = AuthenticationResults::parse(concat!(
+            " mx.localhost (version 1);\r\n",
+            "\tspf=pass (sender is (nested) authorized) smtp.mailfrom=alice@localhost;\r\n",
+            "\tdkim=fail reason=\"bad signature; expired\" header.d=localhost header.s=sel;\r\n",
+            "\tdmarc=pass header.from=localhost"
+        ))
+        .unwrap();
+
+        assert_eq!(results.authserv_id, "mx.localhost");
+        assert_eq!(results.results.len(), 3);
+        assert_eq!(results.results[0].method, "spf");
+        assert_eq!(
+            results.results[0].property("smtp.mailfrom"),
+            Some("alice@localhost")
+        );
+        assert_eq!(results.results[1].status, AuthenticationStatus::Fail);
+        assert_eq!(
+            results.results[1].reason.as_deref(),
+            Some("bad signature; expired")
+        );
+        assert_eq!(results.results[1].property("header.s"), Some("sel"));
+
+        let results = AuthenticationResults::parse("mx.localhost; none").unwrap();
+        assert!(results.results.is_empty());
+
+        let verdict = AuthenticationVerdict::from_results([&results]);
+        assert_eq!(verdict, AuthenticationVerdict::default());
+    }
+
+    #[test]
+    fn message_verdict() {
+        let msg = Message::from(concat!(
+            "Authentication-Results: mx.localhost; dkim=fail header.d=a; dkim=pass header.d=b; spf=fail\r\n",
+            "Authentication-Results: attacker.localhost; spf=pass; dmarc=pass\r\n",
+            "Subject: test\r\n",
+            "\r\n",
+            "body\r\n",
+        ));
+
+        let verdict = msg.authentication_verdict(&[] as &[&str]).unwrap();
+        assert_eq!(verdict.dkim, Some(AuthenticationStatus::Pass));
+        assert_eq!(verdict.spf, Some(AuthenticationStatus::Fail));
+        assert_eq!(verdict.dmarc, None);
+        assert_eq!(verdict.failures().collect::<Vec<_>>(), vec!["spf"]);
+
+        let verdict = msg.authentication_verdict(&["attacker.localhost"]).unwrap();
+        assert_eq!(verdict.spf, Some(AuthenticationStatus::Pass));
+        assert!(!verdict.is_fail());
+    }
+}
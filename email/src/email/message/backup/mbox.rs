@@ -0,0 +1,138 @@
+//! # Mbox
+//!
+//! Module dedicated to mbox archives. Archives use the mboxrd
+//! variant: message lines starting with `From `, optionally quoted
+//! with `>`, are quoted with one more `>` so that they cannot be
+//! confused with message separators, and can be restored as they
+//! were.
+
+use chrono::DateTime;
+use mail_parser::MessageParser;
+
+/// The sender used in separator lines of messages without sender.
+const DEFAULT_SENDER: &str = "MAILER-DAEMON";
+
+/// Appends the given raw message to the given mbox archive.
+pub fn write_message(mbox: &mut Vec<u8>, msg: &[u8]) {
+    mbox.extend_from_slice(&separator(msg));
+
+    for line in msg.split_inclusive(|b| *b == b'\n') {
+        if is_from_line(line) {
+            mbox.push(b'>');
+        }
+        mbox.extend_from_slice(line);
+    }
+
+    if !msg.ends_with(b"\n") {
+        mbox.push(b'\n');
+    }
+
+    mbox.push(b'\n');
+}
+
+/// Reads the raw messages of the given mbox archive.
+///
+/// Content preceding the first separator line is ignored.
+pub fn read_messages(mbox: &[u8]) -> Vec<Vec<u8>> {
+    let mut msgs = Vec::new();
+    let mut msg: Option<Vec<u8>> = None;
+
+    for line in mbox.split_inclusive(|b| *b == b'\n') {
+        if line.starts_with(b"From ") {
+            if let Some(msg) = msg.replace(Vec::new()) {
+                msgs.push(trim_separator(msg));
+            }
+            continue;
+        }
+
+        let Some(msg) = msg.as_mut() else {
+            continue;
+        };
+
+        if line.starts_with(b">") && is_from_line(line) {
+            msg.extend_from_slice(&line[1..]);
+        } else {
+            msg.extend_from_slice(line);
+        }
+    }
+
+    if let Some(msg) = msg {
+        msgs.push(trim_separator(msg));
+    }
+
+    msgs
+}
+
+/// Builds the separator line of the given raw message, made of the
+/// sender address and the date of the message.
+fn separator(msg: &[u8]) -> Vec<u8> {
+    let headers = MessageParser::new().parse_headers(msg);
+
+    let sender = headers
+        .as_ref()
+        .and_then(|msg| msg.from()?.first()?.address.clone())
+        .filter(|addr| !addr.contains(char::is_whitespace))
+        .unwrap_or(DEFAULT_SENDER.into());
+
+    let date = headers
+        .as_ref()
+        .and_then(|msg| msg.date())
+        .and_then(|date| DateTime::from_timestamp(date.to_timestamp(), 0))
+        .unwrap_or_default()
+        .format("%a %b %e %H:%M:%S %Y");
+
+    format!("From {sender} {date}\n").into_bytes()
+}
+
+/// Returns `true` if the given line, once unquoted, starts with
+/// `From `.
+fn is_from_line(line: &[u8]) -> bool {
+    let unquoted = match line.iter().position(|b| *b != b'>') {
+        Some(pos) => &line[pos..],
+        None => return false,
+    };
+
+    unquoted.starts_with(b"From ")
+}
+
+/// Removes the blank line separating the given message from the next
+/// one.
+fn trim_separator(mut msg: Vec<u8>) -> Vec<u8> {
+    if msg.ends_with(b"\n\n") {
+        msg.pop();
+    } else if msg.ends_with(b"\r\n\r\n") {
+        msg.truncate(msg.len() - 2);
+    }
+
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_messages, write_message};
+
+    #[test]
+    fn write_then_read() {
+        let msgs: [&[u8]; 3] = [
+            b"From: alice@localhost\nDate: Thu, 1 Feb 2024 10:00:00 +0000\n\nFrom the start\n>From quoted\n",
+            b"Subject: no sender\r\n\r\nbody\r\n",
+            b"Subject: no final newline\n\nbody",
+        ];
+
+        let mut mbox = Vec::new();
+        for msg in msgs {
+            write_message(&mut mbox, msg);
+        }
+
+        let mbox_str = String::from_utf8_lossy(&mbox);
+        assert!(mbox_str.starts_with("From alice@localhost Thu Feb  1 10:00:00 2024\n"));
+        assert!(mbox_str.contains("\n>From the start\n>>From quoted\n"));
+        assert!(mbox_str.contains("\nFrom MAILER-DAEMON "));
+
+        let read = read_messages(&mbox);
+        assert_eq!(read.len(), 3);
+        assert_eq!(read[0], msgs[0]);
+        assert_eq!(read[1], msgs[1]);
+        assert_eq!(read[2], b"Subject: no final newline\n\nbody\n");
+    }
+}
@@ -0,0 +1,338 @@
+//! # Backup
+//!
+//! Module dedicated to messages backup. Messages can be exported to
+//! and imported from individual `.eml` files or mbox archives, using
+//! the [`ExportMessages`] and [`ImportMessages`] features. Both are
+//! implemented for any backend exposing the underlying features.
+//!
+//! Messages are de-duplicated using their `Message-ID` header:
+//! exporting to an existing backup only adds messages missing from
+//! it, and importing only adds messages missing from the target
+//! folder.
+
+pub mod mbox;
+
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fmt,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use mail_parser::MessageParser;
+use tracing::{debug, info};
+
+use super::{add::AddMessage, peek::PeekMessages};
+use crate::{
+    email::error::Error,
+    envelope::{list::ListEnvelopes, Id},
+    AnyResult,
+};
+
+/// The backup format.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum BackupFormat {
+    /// One `.eml` file per message, inside a directory.
+    #[default]
+    Eml,
+
+    /// All messages inside a single mbox archive (mboxrd variant).
+    Mbox,
+}
+
+impl BackupFormat {
+    /// Detects the format of the given backup.
+    ///
+    /// Directories and `.eml` files are considered as EML backups,
+    /// other files as mbox archives.
+    pub fn detect(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+
+        if path.is_dir() || has_eml_extension(path) {
+            Self::Eml
+        } else {
+            Self::Mbox
+        }
+    }
+
+    /// Reads the raw messages of the given backup.
+    ///
+    /// EML files of a directory are read in file name order.
+    pub fn read_messages(&self, path: impl AsRef<Path>) -> Result<Vec<Vec<u8>>, Error> {
+        let path = path.as_ref();
+
+        match self {
+            Self::Eml if path.is_dir() => {
+                let mut paths = fs::read_dir(path)
+                    .map_err(|err| Error::ReadBackupDirError(err, path.to_owned()))?
+                    .filter_map(|entry| Some(entry.ok()?.path()))
+                    .filter(|path| path.is_file() && has_eml_extension(path))
+                    .collect::<Vec<_>>();
+                paths.sort();
+
+                paths.iter().map(read_file).collect()
+            }
+            Self::Eml => Ok(vec![read_file(path)?]),
+            Self::Mbox => Ok(mbox::read_messages(&read_file(path)?)),
+        }
+    }
+}
+
+impl fmt::Display for BackupFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Eml => write!(f, "eml"),
+            Self::Mbox => write!(f, "mbox"),
+        }
+    }
+}
+
+/// The backup event.
+///
+/// Represents the progress of messages export and import.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum BackupEvent {
+    /// The number of messages to process has been found.
+    FoundMessages(usize),
+    /// The message matching the given id has been exported.
+    ExportedMessage(String),
+    /// A message has been imported under the given id.
+    ImportedMessage(String),
+    /// The message matching the given Message-ID has been skipped,
+    /// because it is already present in the destination.
+    SkippedDuplicateMessage(String),
+}
+
+impl fmt::Display for BackupEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FoundMessages(n) => write!(f, "Found {n} messages"),
+            Self::ExportedMessage(id) => write!(f, "Exported message {id}"),
+            Self::ImportedMessage(id) => write!(f, "Imported message {id}"),
+            Self::SkippedDuplicateMessage(mid) => write!(f, "Skipped duplicate message {mid}"),
+        }
+    }
+}
+
+/// The backup event handler.
+pub type BackupEventHandler = dyn Fn(BackupEvent) + Send + Sync;
+
+#[async_trait]
+pub trait ExportMessages: PeekMessages {
+    /// Export messages matching the given ids from the given folder
+    /// to the given destination.
+    ///
+    /// The destination is a directory for the EML format, and a file
+    /// for the mbox format. Returns the number of exported messages.
+    async fn export_messages(
+        &self,
+        folder: &str,
+        id: &Id,
+        format: BackupFormat,
+        dest: &Path,
+    ) -> AnyResult<usize> {
+        self.export_messages_with_handler(folder, id, format, dest, &|_| ())
+            .await
+    }
+
+    /// Export messages like [`ExportMessages::export_messages`],
+    /// sending progress events to the given handler.
+    async fn export_messages_with_handler(
+        &self,
+        folder: &str,
+        id: &Id,
+        format: BackupFormat,
+        dest: &Path,
+        handler: &BackupEventHandler,
+    ) -> AnyResult<usize> {
+        info!("exporting messages {id} from folder {folder} as {format} to {dest:?}");
+
+        let mut message_ids = HashSet::new();
+
+        if dest.exists() {
+            for msg in format.read_messages(dest)? {
+                message_ids.extend(find_message_id(&msg));
+            }
+            debug!("found {} messages in existing backup", message_ids.len());
+        } else if let BackupFormat::Eml = format {
+            fs::create_dir_all(dest)
+                .map_err(|err| Error::CreateBackupDirError(err, dest.to_owned()))?;
+        }
+
+        let ids: Vec<String> = id.iter().map(Cow::into_owned).collect();
+        handler(BackupEvent::FoundMessages(ids.len()));
+
+        let mut mbox = Vec::new();
+        let mut count = 0;
+
+        for id in ids {
+            let msgs = self.peek_messages(folder, &Id::single(id.as_str())).await?;
+            let msg = msgs
+                .first()
+                .ok_or_else(|| Error::FindMessageError(id.clone()))?;
+            let msg = msg.raw()?;
+
+            if let Some(mid) = find_message_id(msg) {
+                if !message_ids.insert(mid.clone()) {
+                    handler(BackupEvent::SkippedDuplicateMessage(mid));
+                    continue;
+                }
+            }
+
+            match format {
+                BackupFormat::Eml => {
+                    let path = dest.join(eml_file_name(&id));
+                    fs::write(&path, msg).map_err(|err| Error::WriteBackupFileError(err, path))?;
+                }
+                BackupFormat::Mbox => {
+                    mbox::write_message(&mut mbox, msg);
+                }
+            }
+
+            count += 1;
+            handler(BackupEvent::ExportedMessage(id));
+        }
+
+        if !mbox.is_empty() {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(dest)
+                .and_then(|mut file| file.write_all(&mbox))
+                .map_err(|err| Error::WriteBackupFileError(err, dest.to_owned()))?;
+        }
+
+        Ok(count)
+    }
+}
+
+impl<T: PeekMessages + ?Sized> ExportMessages for T {}
+
+#[async_trait]
+pub trait ImportMessages: AddMessage + ListEnvelopes {
+    /// Import messages from the given source to the given folder.
+    ///
+    /// The format of the source is detected using
+    /// [`BackupFormat::detect`]. Returns the number of imported
+    /// messages.
+    async fn import_messages(&self, src: &Path, folder: &str) -> AnyResult<usize> {
+        self.import_messages_with_handler(src, folder, &|_| ())
+            .await
+    }
+
+    /// Import messages like [`ImportMessages::import_messages`],
+    /// sending progress events to the given handler.
+    async fn import_messages_with_handler(
+        &self,
+        src: &Path,
+        folder: &str,
+        handler: &BackupEventHandler,
+    ) -> AnyResult<usize> {
+        let format = BackupFormat::detect(src);
+        info!("importing {format} messages from {src:?} to folder {folder}");
+
+        let msgs = format.read_messages(src)?;
+        handler(BackupEvent::FoundMessages(msgs.len()));
+
+        let mut message_ids: HashSet<String> = self
+            .list_envelopes(folder, Default::default())
+            .await?
+            .iter()
+            .map(|envelope| envelope.message_id.clone())
+            .collect();
+
+        let mut count = 0;
+
+        for msg in msgs {
+            if let Some(mid) = find_message_id(&msg) {
+                if !message_ids.insert(mid.clone()) {
+                    handler(BackupEvent::SkippedDuplicateMessage(mid));
+                    continue;
+                }
+            }
+
+            let id = self.add_message(folder, &msg).await?;
+
+            count += 1;
+            handler(BackupEvent::ImportedMessage(id.as_str().to_owned()));
+        }
+
+        Ok(count)
+    }
+}
+
+impl<T: AddMessage + ListEnvelopes + ?Sized> ImportMessages for T {}
+
+/// Finds the Message-ID of the given raw message, formatted the same
+/// way as [`Envelope::message_id`](crate::envelope::Envelope).
+fn find_message_id(msg: &[u8]) -> Option<String> {
+    let headers = MessageParser::new().parse_headers(msg)?;
+    let mid = headers.message_id()?;
+    Some(format!("<{mid}>"))
+}
+
+/// Builds the EML file name of the message matching the given id.
+fn eml_file_name(id: &str) -> String {
+    let id: String = id
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+
+    format!("{id}.eml")
+}
+
+fn has_eml_extension(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("eml"))
+}
+
+fn read_file(path: impl AsRef<Path>) -> Result<Vec<u8>, Error> {
+    let path = path.as_ref();
+    fs::read(path).map_err(|err| Error::ReadBackupFileError(err, PathBuf::from(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{eml_file_name, find_message_id, BackupFormat};
+
+    #[test]
+    fn detect_and_read() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("2.eml"), "Message-ID: <b@localhost>\n\nb\n").unwrap();
+        fs::write(dir.path().join("1.eml"), "Message-ID: <a@localhost>\n\na\n").unwrap();
+        fs::write(dir.path().join("ignored.txt"), "ignored").unwrap();
+
+        assert_eq!(BackupFormat::detect(dir.path()), BackupFormat::Eml);
+        assert_eq!(
+            BackupFormat::detect(dir.path().join("1.eml")),
+            BackupFormat::Eml
+        );
+        assert_eq!(
+            BackupFormat::detect(dir.path().join("archive.mbox")),
+            BackupFormat::Mbox
+        );
+
+        let msgs = BackupFormat::Eml.read_messages(dir.path()).unwrap();
+        let mids: Vec<_> = msgs.iter().filter_map(|msg| find_message_id(msg)).collect();
+        assert_eq!(mids, vec!["<a@localhost>", "<b@localhost>"]);
+    }
+
+    #[test]
+    fn eml_file_names() {
+        assert_eq!(eml_file_name("42"), "42.eml");
+        assert_eq!(eml_file_name("a/b:c"), "a_b_c.eml");
+    }
+}
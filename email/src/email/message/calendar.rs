@@ -0,0 +1,608 @@
+//! # Calendar
+//!
+//! Module dedicated to calendar invitations (iCalendar, [RFC 5545]).
+//!
+//! Invitations are detected from `text/calendar` parts of messages,
+//! and their events metadata (organizer, time, attendees) are parsed
+//! into [`CalendarInvitation`]. Invitations can be answered using
+//! [`SendCalendarReply`], which generates an iTIP REPLY ([RFC 5546])
+//! and sends it through the send pipeline.
+//!
+//! [RFC 5545]: https://www.rfc-editor.org/rfc/rfc5545
+//! [RFC 5546]: https://www.rfc-editor.org/rfc/rfc5546
+
+use std::fmt;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use mail_builder::{
+    headers::{address::Address, content_type::ContentType},
+    mime::MimePart,
+    MessageBuilder,
+};
+
+use super::{send::SendMessageThenSaveCopy, Message};
+use crate::{account::config::AccountConfig, email::error::Error, AnyResult};
+
+/// The iCalendar date-time format, without the `Z` UTC suffix.
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// The iCalendar date format.
+const DATE_FORMAT: &str = "%Y%m%d";
+
+/// The calendar invitation.
+///
+/// Represents the event (VEVENT) of a calendar part.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CalendarInvitation {
+    /// The iTIP method of the calendar (REQUEST, CANCEL…).
+    pub method: Option<String>,
+
+    /// The unique identifier of the event.
+    pub uid: String,
+
+    /// The revision sequence number of the event.
+    pub sequence: Option<u32>,
+
+    /// The summary of the event.
+    pub summary: Option<String>,
+
+    /// The location of the event.
+    pub location: Option<String>,
+
+    /// The start of the event.
+    pub start: Option<CalendarDateTime>,
+
+    /// The end of the event.
+    pub end: Option<CalendarDateTime>,
+
+    /// The organizer of the event.
+    pub organizer: Option<CalendarAttendee>,
+
+    /// The attendees of the event.
+    pub attendees: Vec<CalendarAttendee>,
+}
+
+/// The calendar event attendee (or organizer).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CalendarAttendee {
+    /// The email address of the attendee.
+    pub addr: String,
+
+    /// The common name of the attendee.
+    pub name: Option<String>,
+
+    /// The participation status of the attendee.
+    pub partstat: Option<String>,
+
+    /// The participation role of the attendee.
+    pub role: Option<String>,
+
+    /// Whether a reply is expected from the attendee.
+    pub rsvp: bool,
+}
+
+/// The calendar date-time.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CalendarDateTime {
+    /// The date-time is in UTC.
+    Utc(DateTime<Utc>),
+
+    /// The date-time is floating, or relative to the given time zone
+    /// identifier.
+    Local(NaiveDateTime, Option<String>),
+
+    /// The event lasts all day.
+    Date(NaiveDate),
+}
+
+impl CalendarDateTime {
+    fn parse(value: &str, tzid: Option<String>) -> Option<Self> {
+        if let Some(value) = value.strip_suffix(['Z', 'z']) {
+            let date = NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT).ok()?;
+            return Some(Self::Utc(date.and_utc()));
+        }
+
+        match NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT) {
+            Ok(date) => Some(Self::Local(date, tzid)),
+            Err(_) => NaiveDate::parse_from_str(value, DATE_FORMAT)
+                .ok()
+                .map(Self::Date),
+        }
+    }
+
+    /// Serializes the date-time as an iCalendar property line.
+    fn to_ics_line(&self, name: &str) -> String {
+        match self {
+            Self::Utc(date) => format!("{name}:{}Z\r\n", date.format(DATE_TIME_FORMAT)),
+            Self::Local(date, None) => format!("{name}:{}\r\n", date.format(DATE_TIME_FORMAT)),
+            Self::Local(date, Some(tzid)) => {
+                let date = date.format(DATE_TIME_FORMAT);
+                format!("{name};TZID={}:{date}\r\n", quote_param(tzid))
+            }
+            Self::Date(date) => format!("{name};VALUE=DATE:{}\r\n", date.format(DATE_FORMAT)),
+        }
+    }
+}
+
+/// The participation status sent when replying to an invitation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CalendarPartStat {
+    Accepted,
+    Declined,
+    Tentative,
+}
+
+impl CalendarPartStat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Accepted => "ACCEPTED",
+            Self::Declined => "DECLINED",
+            Self::Tentative => "TENTATIVE",
+        }
+    }
+
+    fn verb(&self) -> &'static str {
+        match self {
+            Self::Accepted => "Accepted",
+            Self::Declined => "Declined",
+            Self::Tentative => "Tentatively accepted",
+        }
+    }
+}
+
+impl fmt::Display for CalendarPartStat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl CalendarInvitation {
+    /// Parses the events of the given iCalendar content.
+    pub fn parse_all(ics: &str) -> Vec<Self> {
+        let mut invitations = Vec::new();
+        let mut method = None;
+        let mut event: Option<Self> = None;
+
+        for line in unfold(ics) {
+            let Some(prop) = Property::parse(&line) else {
+                continue;
+            };
+
+            match (prop.name.as_str(), event.as_mut()) {
+                ("METHOD", None) => {
+                    method = Some(prop.value.to_uppercase());
+                }
+                ("BEGIN", None) if prop.value.eq_ignore_ascii_case("VEVENT") => {
+                    event = Some(Self {
+                        method: method.clone(),
+                        ..Default::default()
+                    });
+                }
+                ("END", Some(_)) if prop.value.eq_ignore_ascii_case("VEVENT") => {
+                    invitations.extend(event.take());
+                }
+                ("UID", Some(event)) => {
+                    event.uid = unescape(&prop.value);
+                }
+                ("SEQUENCE", Some(event)) => {
+                    event.sequence = prop.value.trim().parse().ok();
+                }
+                ("SUMMARY", Some(event)) => {
+                    event.summary = Some(unescape(&prop.value));
+                }
+                ("LOCATION", Some(event)) => {
+                    event.location = Some(unescape(&prop.value));
+                }
+                ("DTSTART", Some(event)) => {
+                    event.start = CalendarDateTime::parse(&prop.value, prop.param("TZID"));
+                }
+                ("DTEND", Some(event)) => {
+                    event.end = CalendarDateTime::parse(&prop.value, prop.param("TZID"));
+                }
+                ("ORGANIZER", Some(event)) => {
+                    event.organizer = Some(prop.to_attendee());
+                }
+                ("ATTENDEE", Some(event)) => {
+                    event.attendees.push(prop.to_attendee());
+                }
+                _ => (),
+            }
+        }
+
+        invitations
+    }
+
+    /// Finds the attendee matching the given email address.
+    pub fn find_attendee(&self, addr: &str) -> Option<&CalendarAttendee> {
+        self.attendees
+            .iter()
+            .find(|attendee| attendee.addr.eq_ignore_ascii_case(addr))
+    }
+
+    /// Builds the iCalendar REPLY of the given attendee with the
+    /// given participation status.
+    pub fn to_reply_ics(&self, attendee: &CalendarAttendee, partstat: CalendarPartStat) -> String {
+        let now = CalendarDateTime::Utc(Utc::now());
+
+        let mut ics = String::new();
+        ics.push_str("BEGIN:VCALENDAR\r\n");
+        ics.push_str("VERSION:2.0\r\n");
+        ics.push_str(concat!(
+            "PRODID:-//pimalaya//email-lib ",
+            env!("CARGO_PKG_VERSION"),
+            "//EN\r\n"
+        ));
+        ics.push_str("METHOD:REPLY\r\n");
+        ics.push_str("BEGIN:VEVENT\r\n");
+        ics.push_str(&format!("UID:{}\r\n", escape(&self.uid)));
+
+        if let Some(sequence) = self.sequence {
+            ics.push_str(&format!("SEQUENCE:{sequence}\r\n"));
+        }
+
+        ics.push_str(&now.to_ics_line("DTSTAMP"));
+
+        if let Some(start) = &self.start {
+            ics.push_str(&start.to_ics_line("DTSTART"));
+        }
+
+        if let Some(end) = &self.end {
+            ics.push_str(&end.to_ics_line("DTEND"));
+        }
+
+        if let Some(summary) = &self.summary {
+            ics.push_str(&format!("SUMMARY:{}\r\n", escape(summary)));
+        }
+
+        if let Some(organizer) = &self.organizer {
+            ics.push_str(&organizer.to_ics_line("ORGANIZER", None));
+        }
+
+        ics.push_str(&attendee.to_ics_line("ATTENDEE", Some(partstat)));
+        ics.push_str("END:VEVENT\r\n");
+        ics.push_str("END:VCALENDAR\r\n");
+        ics
+    }
+}
+
+impl CalendarAttendee {
+    fn to_ics_line(&self, name: &str, partstat: Option<CalendarPartStat>) -> String {
+        let mut line = String::from(name);
+
+        if let Some(cn) = &self.name {
+            line.push_str(&format!(";CN={}", quote_param(cn)));
+        }
+
+        if let Some(partstat) = partstat {
+            line.push_str(&format!(";PARTSTAT={partstat}"));
+        }
+
+        line.push_str(&format!(":mailto:{}\r\n", self.addr));
+        line
+    }
+}
+
+impl Message<'_> {
+    /// Returns the calendar invitations of the message, found in its
+    /// `text/calendar` parts.
+    pub fn calendar_invitations(&self) -> Result<Vec<CalendarInvitation>, Error> {
+        let invitations = self
+            .parsed()?
+            .parts
+            .iter()
+            .filter(|part| {
+                part.content_type().is_some_and(|ctype| {
+                    let subtype = ctype.subtype().unwrap_or_default();
+                    (ctype.ctype().eq_ignore_ascii_case("text")
+                        && subtype.eq_ignore_ascii_case("calendar"))
+                        || (ctype.ctype().eq_ignore_ascii_case("application")
+                            && subtype.eq_ignore_ascii_case("ics"))
+                })
+            })
+            .flat_map(|part| {
+                let ics = String::from_utf8_lossy(part.contents());
+                CalendarInvitation::parse_all(&ics)
+            })
+            .collect();
+
+        Ok(invitations)
+    }
+
+    /// Builds the reply message to the first calendar invitation of
+    /// the message, on behalf of the given account.
+    ///
+    /// The reply is sent to the organizer of the event, and contains
+    /// both a human-readable text part and the iCalendar REPLY part.
+    pub fn to_calendar_reply(
+        &self,
+        config: &AccountConfig,
+        partstat: CalendarPartStat,
+    ) -> Result<Vec<u8>, Error> {
+        let invitation = self
+            .calendar_invitations()?
+            .into_iter()
+            .find(|invitation| !invitation.uid.is_empty())
+            .ok_or(Error::FindCalendarInvitationError)?;
+
+        let organizer = invitation
+            .organizer
+            .as_ref()
+            .ok_or_else(|| Error::FindCalendarOrganizerError(invitation.uid.clone()))?;
+
+        let attendee = invitation
+            .find_attendee(&config.email)
+            .cloned()
+            .ok_or_else(|| Error::FindCalendarAttendeeError(config.email.clone()))?;
+
+        let attendee = CalendarAttendee {
+            name: attendee.name.or_else(|| config.display_name.clone()),
+            ..attendee
+        };
+
+        let summary = invitation.summary.as_deref().unwrap_or_default();
+        let who = attendee.name.as_deref().unwrap_or(&attendee.addr);
+        let subject = format!("{}: {summary}", partstat.verb());
+        let text = format!(
+            "{who} has {} the invitation: {summary}\n",
+            partstat.verb().to_lowercase()
+        );
+        let ics = invitation.to_reply_ics(&attendee, partstat);
+
+        let mut builder = MessageBuilder::new()
+            .from(config)
+            .to(Address::new_address(
+                organizer.name.clone(),
+                organizer.addr.clone(),
+            ))
+            .subject(subject)
+            .body(MimePart::new(
+                "multipart/alternative",
+                vec![
+                    MimePart::new("text/plain", text),
+                    MimePart::new(
+                        ContentType::new("text/calendar")
+                            .attribute("method", "REPLY")
+                            .attribute("charset", "utf-8"),
+                        ics,
+                    ),
+                ],
+            ));
+
+        if let Some(message_id) = self.parsed()?.message_id() {
+            builder = builder
+                .in_reply_to(message_id.to_owned())
+                .references(message_id.to_owned());
+        }
+
+        builder
+            .write_to_vec()
+            .map_err(Error::BuildCalendarReplyError)
+    }
+}
+
+#[async_trait]
+pub trait SendCalendarReply: SendMessageThenSaveCopy {
+    /// Replies to the calendar invitation of the given message with
+    /// the given participation status, then saves a copy of the
+    /// reply to the Sent folder.
+    async fn send_calendar_reply(
+        &self,
+        msg: &Message,
+        partstat: CalendarPartStat,
+    ) -> AnyResult<()> {
+        let reply = msg.to_calendar_reply(self.account_config(), partstat)?;
+        self.send_message_then_save_copy(&reply).await
+    }
+}
+
+impl<T: SendMessageThenSaveCopy> SendCalendarReply for T {}
+
+/// The iCalendar content line.
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    /// Parses an unfolded content line. Parameter values may be
+    /// quoted, in which case they can contain colons and semicolons.
+    fn parse(line: &str) -> Option<Self> {
+        let mut in_quotes = false;
+        let mut parts = Vec::new();
+        let mut start = 0;
+
+        for (i, c) in line.char_indices() {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                ';' if !in_quotes => {
+                    parts.push(&line[start..i]);
+                    start = i + 1;
+                }
+                ':' if !in_quotes => {
+                    parts.push(&line[start..i]);
+
+                    let mut parts = parts.into_iter();
+                    let name = parts.next()?.trim().to_uppercase();
+                    let params = parts
+                        .filter_map(|param| param.split_once('='))
+                        .map(|(k, v)| (k.trim().to_uppercase(), v.trim_matches('"').to_owned()))
+                        .collect();
+                    let value = line[i + 1..].to_owned();
+
+                    return Some(Self {
+                        name,
+                        params,
+                        value,
+                    });
+                }
+                _ => (),
+            }
+        }
+
+        None
+    }
+
+    fn param(&self, name: &str) -> Option<String> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, val)| val.clone())
+    }
+
+    fn to_attendee(&self) -> CalendarAttendee {
+        let value = self.value.trim();
+        let addr = match value.get(..7) {
+            Some(scheme) if scheme.eq_ignore_ascii_case("mailto:") => &value[7..],
+            _ => value,
+        };
+
+        CalendarAttendee {
+            addr: addr.to_owned(),
+            name: self.param("CN"),
+            partstat: self.param("PARTSTAT").map(|p| p.to_uppercase()),
+            role: self.param("ROLE").map(|r| r.to_uppercase()),
+            rsvp: self
+                .param("RSVP")
+                .is_some_and(|rsvp| rsvp.eq_ignore_ascii_case("TRUE")),
+        }
+    }
+}
+
+/// Unfolds iCalendar content lines, as defined in [RFC 5545 section
+/// 3.1].
+///
+/// [RFC 5545 section 3.1]: https://www.rfc-editor.org/rfc/rfc5545#section-3.1
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in ics.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(folded), Some(last)) => last.push_str(folded),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+
+    lines
+}
+
+/// Escapes an iCalendar text value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Unescapes an iCalendar text value.
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => (),
+        }
+    }
+
+    unescaped
+}
+
+/// Quotes a parameter value, when needed.
+fn quote_param(value: &str) -> String {
+    if value.contains([':', ';', ',']) {
+        format!("\"{}\"", value.replace('"', ""))
+    } else {
+        value.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    use super::{CalendarDateTime, CalendarInvitation, CalendarPartStat};
+
+    const INVITATION: &str = concat!(
+        "BEGIN:VCALENDAR\r\n",
+        "METHOD:REQUEST\r\n",
+        "BEGIN:VTIMEZONE\r\n",
+        "TZID:Europe/Paris\r\n",
+        "END:VTIMEZONE\r\n",
+        "BEGIN:VEVENT\r\n",
+        "UID:event-1@localhost\r\n",
+        "SEQUENCE:2\r\n",
+        "SUMMARY:Weekly sync\\, team\r\n",
+        "DTSTART;TZID=Europe/Paris:20240102T100000\r\n",
+        "DTEND:20240102T100000Z\r\n",
+        "ORGANIZER;CN=\"Smith, Alice\":mailto:alice@localhost\r\n",
+        "ATTENDEE;CN=Bob;PARTSTAT=NEEDS-ACTION;ROLE=REQ-PARTICIPANT;RSVP=TRUE:mai\r\n",
+        " lto:bob@localhost\r\n",
+        "END:VEVENT\r\n",
+        "END:VCALENDAR\r\n",
+    );
+
+    #[test]
+    fn parse_invitation() {
+        let invitations = CalendarInvitation::parse_all(INVITATION);
+        assert_eq!(invitations.len(), 1);
+
+        let invitation = &invitations[0];
+        assert_eq!(invitation.method.as_deref(), Some("REQUEST"));
+        assert_eq!(invitation.uid, "event-1@localhost");
+        assert_eq!(invitation.sequence, Some(2));
+        assert_eq!(invitation.summary.as_deref(), Some("Weekly sync, team"));
+
+        let start = NaiveDate::from_ymd_opt(2024, 1, 2)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap();
+        assert_eq!(
+            invitation.start,
+            Some(CalendarDateTime::Local(start, Some("Europe/Paris".into())))
+        );
+        assert_eq!(
+            invitation.end,
+            Some(CalendarDateTime::Utc(
+                Utc.with_ymd_and_hms(2024, 1, 2, 10, 0, 0).unwrap()
+            ))
+        );
+
+        let organizer = invitation.organizer.as_ref().unwrap();
+        assert_eq!(organizer.addr, "alice@localhost");
+        assert_eq!(organizer.name.as_deref(), Some("Smith, Alice"));
+
+        let bob = invitation.find_attendee("BOB@localhost").unwrap();
+        assert_eq!(bob.name.as_deref(), Some("Bob"));
+        assert_eq!(bob.partstat.as_deref(), Some("NEEDS-ACTION"));
+        assert!(bob.rsvp);
+    }
+
+    #[test]
+    fn build_reply_ics() {
+        let invitation = &CalendarInvitation::parse_all(INVITATION)[0];
+        let bob = invitation.find_attendee("bob@localhost").unwrap();
+        let ics = invitation.to_reply_ics(bob, CalendarPartStat::Declined);
+
+        assert!(ics.contains("METHOD:REPLY\r\n"));
+        assert!(ics.contains("UID:event-1@localhost\r\n"));
+        assert!(ics.contains("SEQUENCE:2\r\n"));
+        assert!(ics.contains("DTSTART;TZID=Europe/Paris:20240102T100000\r\n"));
+        assert!(ics.contains("ORGANIZER;CN=\"Smith, Alice\":mailto:alice@localhost\r\n"));
+        assert!(ics.contains("ATTENDEE;CN=Bob;PARTSTAT=DECLINED:mailto:bob@localhost\r\n"));
+
+        // the reply can be parsed back
+        let reply = &CalendarInvitation::parse_all(&ics)[0];
+        assert_eq!(reply.method.as_deref(), Some("REPLY"));
+        assert_eq!(reply.attendees[0].partstat.as_deref(), Some("DECLINED"));
+    }
+}
@@ -0,0 +1,433 @@
+//! Module dedicated to email message diffing.
+//!
+//! This module exposes a [MessageDiff] structure which describes what
+//! changed between two revisions of the same message (or template):
+//! headers are compared by name, and bodies are compared line by
+//! line. The diff can be consumed in a structured way, or rendered
+//! as a unified diff using its [Display](fmt::Display)
+//! implementation.
+
+use std::fmt;
+
+use similar::{Algorithm, DiffTag};
+
+/// The amount of unchanged lines displayed around body changes when
+/// rendering a unified diff.
+pub const DEFAULT_CONTEXT_LINES: usize = 3;
+
+/// The change of a single header between two messages.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HeaderDiff {
+    /// The header only exists in the new message.
+    Added { name: String, value: String },
+
+    /// The header only exists in the old message.
+    Removed { name: String, value: String },
+
+    /// The header exists in both messages, with different values.
+    Changed {
+        name: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl HeaderDiff {
+    /// Returns the name of the header concerned by the change.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Added { name, .. } => name,
+            Self::Removed { name, .. } => name,
+            Self::Changed { name, .. } => name,
+        }
+    }
+}
+
+/// A line of a body diff.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LineDiff {
+    /// The line exists in both bodies.
+    Equal(String),
+
+    /// The line only exists in the new body.
+    Added(String),
+
+    /// The line only exists in the old body.
+    Removed(String),
+}
+
+impl LineDiff {
+    /// Returns `true` if the line is not the same in both bodies.
+    pub fn is_change(&self) -> bool {
+        !matches!(self, Self::Equal(_))
+    }
+}
+
+/// The structured diff between two messages.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MessageDiff {
+    /// The header changes, in order of appearance.
+    pub headers: Vec<HeaderDiff>,
+
+    /// The body lines, including unchanged ones.
+    pub body: Vec<LineDiff>,
+}
+
+impl MessageDiff {
+    /// Builds a diff from the given headers and bodies.
+    ///
+    /// Headers are compared by case-insensitive name. When a header
+    /// appears more than once, its values are joined with a new
+    /// line before being compared.
+    pub fn new<'a>(
+        old_headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+        old_body: &str,
+        new_headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+        new_body: &str,
+    ) -> Self {
+        let old_headers = group_headers(old_headers);
+        let new_headers = group_headers(new_headers);
+
+        let mut headers = Vec::new();
+
+        for (name, old) in &old_headers {
+            match find_header(&new_headers, name) {
+                None => headers.push(HeaderDiff::Removed {
+                    name: name.clone(),
+                    value: old.clone(),
+                }),
+                Some(new) if new != old => headers.push(HeaderDiff::Changed {
+                    name: name.clone(),
+                    old: old.clone(),
+                    new: new.clone(),
+                }),
+                Some(_) => (),
+            }
+        }
+
+        for (name, new) in &new_headers {
+            if find_header(&old_headers, name).is_none() {
+                headers.push(HeaderDiff::Added {
+                    name: name.clone(),
+                    value: new.clone(),
+                })
+            }
+        }
+
+        let body = diff_lines(old_body, new_body);
+
+        Self { headers, body }
+    }
+
+    /// Returns `true` if both messages are identical.
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty() && !self.body.iter().any(LineDiff::is_change)
+    }
+
+    /// Renders the diff as a unified diff, keeping the given amount
+    /// of unchanged lines around body changes.
+    pub fn to_unified(&self, context: usize) -> String {
+        let mut out = String::new();
+
+        if self.is_empty() {
+            return out;
+        }
+
+        out.push_str("--- a\n+++ b\n");
+
+        for header in &self.headers {
+            match header {
+                HeaderDiff::Added { name, value } => {
+                    push_prefixed(&mut out, '+', &format!("{name}: {value}"));
+                }
+                HeaderDiff::Removed { name, value } => {
+                    push_prefixed(&mut out, '-', &format!("{name}: {value}"));
+                }
+                HeaderDiff::Changed { name, old, new } => {
+                    push_prefixed(&mut out, '-', &format!("{name}: {old}"));
+                    push_prefixed(&mut out, '+', &format!("{name}: {new}"));
+                }
+            }
+        }
+
+        for (start, end) in hunks(&self.body, context) {
+            let (mut old_start, mut new_start) = (1, 1);
+            for line in &self.body[..start] {
+                match line {
+                    LineDiff::Equal(_) => {
+                        old_start += 1;
+                        new_start += 1;
+                    }
+                    LineDiff::Added(_) => new_start += 1,
+                    LineDiff::Removed(_) => old_start += 1,
+                }
+            }
+
+            let lines = &self.body[start..end];
+            let old_len = lines
+                .iter()
+                .filter(|l| !matches!(l, LineDiff::Added(_)))
+                .count();
+            let new_len = lines
+                .iter()
+                .filter(|l| !matches!(l, LineDiff::Removed(_)))
+                .count();
+
+            out.push_str(&format!(
+                "@@ -{old_start},{old_len} +{new_start},{new_len} @@\n"
+            ));
+
+            for line in lines {
+                match line {
+                    LineDiff::Equal(line) => push_prefixed(&mut out, ' ', line),
+                    LineDiff::Added(line) => push_prefixed(&mut out, '+', line),
+                    LineDiff::Removed(line) => push_prefixed(&mut out, '-', line),
+                }
+            }
+        }
+
+        out
+    }
+}
+
+impl fmt::Display for MessageDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_unified(DEFAULT_CONTEXT_LINES))
+    }
+}
+
+/// Splits a raw message (or template) into unfolded headers and
+/// body.
+pub(crate) fn split_headers(content: &str) -> (Vec<(&str, String)>, &str) {
+    let mut headers: Vec<(&str, String)> = Vec::new();
+    let mut body = "";
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        if line.is_empty() {
+            body = &content[offset..];
+            break;
+        }
+
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+                continue;
+            }
+        }
+
+        match line.split_once(':') {
+            Some((name, value)) => headers.push((name.trim(), value.trim().to_owned())),
+            None => {
+                // not a header: the content has no header section
+                return (Vec::new(), content);
+            }
+        }
+    }
+
+    (headers, body)
+}
+
+fn group_headers<'a>(
+    headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Vec<(String, String)> {
+    let mut grouped: Vec<(String, String)> = Vec::new();
+
+    for (name, value) in headers {
+        match grouped
+            .iter_mut()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        {
+            Some((_, values)) => {
+                values.push('\n');
+                values.push_str(value);
+            }
+            None => grouped.push((name.to_owned(), value.to_owned())),
+        }
+    }
+
+    grouped
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a String> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v)
+}
+
+fn push_prefixed(out: &mut String, prefix: char, content: &str) {
+    for line in content.split('\n') {
+        out.push(prefix);
+        out.push_str(line);
+        out.push('\n');
+    }
+}
+
+/// Computes the line diff of the two given texts.
+///
+/// Uses the Myers algorithm, which runs in linear space and in a time
+/// proportional to the size of the texts times the number of
+/// changes, so that large bodies with few changes stay cheap.
+fn diff_lines(old: &str, new: &str) -> Vec<LineDiff> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let mut diff = Vec::with_capacity(old.len().max(new.len()));
+    let mut push = |lines: &[&str], line: fn(String) -> LineDiff| {
+        diff.extend(lines.iter().map(|l| line(l.to_string())));
+    };
+
+    for op in similar::capture_diff_slices(Algorithm::Myers, &old, &new) {
+        match op.as_tag_tuple() {
+            (DiffTag::Equal, old_range, _) => push(&old[old_range], LineDiff::Equal),
+            (DiffTag::Delete, old_range, _) => push(&old[old_range], LineDiff::Removed),
+            (DiffTag::Insert, _, new_range) => push(&new[new_range], LineDiff::Added),
+            (DiffTag::Replace, old_range, new_range) => {
+                push(&old[old_range], LineDiff::Removed);
+                push(&new[new_range], LineDiff::Added);
+            }
+        }
+    }
+
+    diff
+}
+
+/// Groups changed lines into hunks, returned as ranges of indexes.
+fn hunks(lines: &[LineDiff], context: usize) -> Vec<(usize, usize)> {
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+
+    for (i, _) in lines.iter().enumerate().filter(|(_, l)| l.is_change()) {
+        let start = i.saturating_sub(context);
+        let end = (i + 1 + context).min(lines.len());
+
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    hunks
+}
+
+#[cfg(test)]
+mod tests {
+    use concat_with::concat_line;
+
+    use super::{split_headers, HeaderDiff, LineDiff, MessageDiff};
+
+    fn diff(old: &str, new: &str) -> MessageDiff {
+        let (old_headers, old_body) = split_headers(old);
+        let (new_headers, new_body) = split_headers(new);
+        MessageDiff::new(
+            old_headers.iter().map(|(k, v)| (*k, v.as_str())),
+            old_body,
+            new_headers.iter().map(|(k, v)| (*k, v.as_str())),
+            new_body,
+        )
+    }
+
+    #[test]
+    fn same_content() {
+        let tpl = concat_line!("From: from@localhost", "Subject: subject", "", "Hello!");
+        assert!(diff(tpl, tpl).is_empty());
+        assert_eq!(diff(tpl, tpl).to_string(), "");
+    }
+
+    #[test]
+    fn headers() {
+        let old = concat_line!(
+            "From: from@localhost",
+            "To: to@localhost",
+            "Subject: subject",
+            "",
+            "Hello!",
+        );
+        let new = concat_line!(
+            "From: from@localhost",
+            "subject: new",
+            "  subject",
+            "Cc: cc@localhost",
+            "",
+            "Hello!",
+        );
+
+        assert_eq!(
+            diff(old, new).headers,
+            vec![
+                HeaderDiff::Removed {
+                    name: "To".into(),
+                    value: "to@localhost".into(),
+                },
+                HeaderDiff::Changed {
+                    name: "Subject".into(),
+                    old: "subject".into(),
+                    new: "new subject".into(),
+                },
+                HeaderDiff::Added {
+                    name: "Cc".into(),
+                    value: "cc@localhost".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn body() {
+        let old = concat_line!("Subject: subject", "", "Hello!", "", "Bye.", "-- ", "Me");
+        let new = concat_line!("Subject: subject", "", "Hello,", "", "Bye.", "-- ", "Me");
+
+        let diff = diff(old, new);
+
+        assert_eq!(
+            diff.body,
+            vec![
+                LineDiff::Removed("Hello!".into()),
+                LineDiff::Added("Hello,".into()),
+                LineDiff::Equal("".into()),
+                LineDiff::Equal("Bye.".into()),
+                LineDiff::Equal("-- ".into()),
+                LineDiff::Equal("Me".into()),
+            ]
+        );
+
+        assert_eq!(
+            diff.to_unified(1),
+            concat_line!(
+                "--- a",
+                "+++ b",
+                "@@ -1,2 +1,2 @@",
+                "-Hello!",
+                "+Hello,",
+                " ",
+                "",
+            )
+        );
+    }
+
+    #[test]
+    fn large_body() {
+        let old: String = (0..20_000).map(|i| format!("line {i}\n")).collect();
+        let new = old.replace("line 10000\n", "line 10000 edited\nline 10000 bis\n");
+
+        let diff = diff(
+            &format!("Subject: subject\n\n{old}"),
+            &format!("Subject: subject\n\n{new}"),
+        );
+        let changes: Vec<_> = diff.body.iter().filter(|l| l.is_change()).collect();
+
+        assert_eq!(diff.body.len(), 20_002);
+        assert_eq!(
+            changes,
+            vec![
+                &LineDiff::Removed("line 10000".into()),
+                &LineDiff::Added("line 10000 edited".into()),
+                &LineDiff::Added("line 10000 bis".into()),
+            ]
+        );
+    }
+}
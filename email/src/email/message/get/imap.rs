@@ -1,10 +1,8 @@
 use async_trait::async_trait;
-use imap_client::imap_next::imap_types::sequence::{Sequence, SequenceSet};
 use tracing::{debug, info};
-use utf7_imap::encode_utf7_imap as encode_utf7;
 
 use super::{GetMessages, Messages};
-use crate::{envelope::Id, imap::ImapContext, AnyResult};
+use crate::{envelope::Id, flag::imap::to_imap_uid_set, imap::ImapContext, AnyResult};
 
 #[derive(Clone, Debug)]
 pub struct GetImapMessages {
@@ -34,18 +32,10 @@ async fn get_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
-        let folder_encoded = encode_utf7(folder.clone());
+        let folder_encoded = client.encode_mailbox(&folder);
         debug!("utf7 encoded folder: {folder_encoded}");
 
-        let uids: SequenceSet = match id {
-            Id::Single(id) => Sequence::try_from(id.as_str()).unwrap().into(),
-            Id::Multiple(ids) => ids
-                .iter()
-                .filter_map(|id| Sequence::try_from(id.as_str()).ok())
-                .collect::<Vec<_>>()
-                .try_into()
-                .unwrap(),
-        };
+        let uids = to_imap_uid_set(id)?;
 
         client.select_mailbox(&folder_encoded).await?;
         let msgs = client.fetch_messages(uids).await?;
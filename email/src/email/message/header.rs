@@ -0,0 +1,334 @@
+//! # Header
+//!
+//! Module dedicated to raw message headers. [`Message::headers`]
+//! exposes the header fields of a message in order, with their raw
+//! bytes untouched, which the parsed message does not allow.
+//!
+//! Some workflows annotate stored messages, for example by adding an
+//! `X-Label` header. Since most backends cannot modify a stored
+//! message, the [`EditMessageHeaders`] feature re-appends an edited
+//! copy of the message, then removes the original one.
+
+use std::ops::Range;
+
+use async_trait::async_trait;
+use tracing::debug;
+
+use super::{
+    add::{AddMessage, AddMessageOptions},
+    peek::PeekMessages,
+    remove::RemoveMessages,
+    Message,
+};
+use crate::{
+    email::error::Error,
+    envelope::{get::GetEnvelope, Id, SingleId},
+    AnyResult,
+};
+
+/// The raw header field.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RawHeader<'a> {
+    /// The name of the header, as written in the message.
+    pub name: String,
+
+    /// The raw value of the header.
+    ///
+    /// Contains the bytes following the colon, including folding
+    /// line breaks, without the final line break.
+    pub raw_value: &'a [u8],
+}
+
+impl RawHeader<'_> {
+    /// Returns `true` if the header has the given name, ignoring
+    /// case.
+    pub fn is(&self, name: impl AsRef<str>) -> bool {
+        self.name.eq_ignore_ascii_case(name.as_ref())
+    }
+
+    /// Returns the unfolded and trimmed value of the header.
+    ///
+    /// Encoded words are not decoded, use the parsed message for
+    /// that.
+    pub fn value(&self) -> String {
+        let value = String::from_utf8_lossy(self.raw_value);
+        let value = value.replace("\r\n", "").replace('\n', "");
+        value.trim().to_owned()
+    }
+}
+
+impl Message<'_> {
+    /// Returns the header fields of the message, in order.
+    pub fn headers(&self) -> Result<Vec<RawHeader<'_>>, Error> {
+        let raw = self.raw()?;
+        let (fields, _) = split_fields(raw);
+
+        let headers = fields
+            .into_iter()
+            .map(|field| {
+                let field = trim_eol(&raw[field]);
+                let colon = field.iter().position(|b| *b == b':').unwrap_or(field.len());
+                RawHeader {
+                    name: String::from_utf8_lossy(&field[..colon]).trim().to_owned(),
+                    raw_value: field.get(colon + 1..).unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        Ok(headers)
+    }
+}
+
+/// The header edition.
+///
+/// Describes headers to remove from and to add to a raw message,
+/// using the builder pattern. Removals are applied first, so that a
+/// header can be replaced by removing then adding it.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HeadersEdit {
+    /// The names of the headers to remove, matched ignoring case.
+    pub removed: Vec<String>,
+
+    /// The headers to add at the end of the header section.
+    pub added: Vec<(String, String)>,
+}
+
+impl HeadersEdit {
+    /// Creates a new empty header edition.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the given header, using the builder pattern.
+    pub fn with_added_header(mut self, name: impl ToString, value: impl ToString) -> Self {
+        self.added.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Removes all headers matching the given name, using the
+    /// builder pattern.
+    pub fn with_removed_header(mut self, name: impl ToString) -> Self {
+        self.removed.push(name.to_string());
+        self
+    }
+
+    /// Returns `true` if the edition does not change anything.
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.added.is_empty()
+    }
+
+    /// Applies the edition to the given raw message.
+    ///
+    /// Kept headers and the body are copied byte for byte. Added
+    /// headers use the line break style of the message.
+    pub fn apply(&self, raw: &[u8]) -> Result<Vec<u8>, Error> {
+        for (name, value) in &self.added {
+            validate_header(name, value)?;
+        }
+
+        let (fields, body_start) = split_fields(raw);
+        let eol: &[u8] = if raw.windows(2).any(|w| w == b"\r\n") {
+            b"\r\n"
+        } else {
+            b"\n"
+        };
+
+        let mut edited = Vec::with_capacity(raw.len());
+
+        for field in fields {
+            let field = &raw[field];
+            let colon = field.iter().position(|b| *b == b':').unwrap_or(field.len());
+            let name = String::from_utf8_lossy(&field[..colon]);
+            let name = name.trim();
+
+            if self.removed.iter().any(|r| r.eq_ignore_ascii_case(name)) {
+                continue;
+            }
+
+            edited.extend_from_slice(field);
+
+            if !field.ends_with(b"\n") {
+                edited.extend_from_slice(eol);
+            }
+        }
+
+        for (name, value) in &self.added {
+            edited.extend_from_slice(name.as_bytes());
+            edited.extend_from_slice(b": ");
+            edited.extend_from_slice(value.as_bytes());
+            edited.extend_from_slice(eol);
+        }
+
+        if body_start < raw.len() {
+            edited.extend_from_slice(&raw[body_start..]);
+        } else {
+            edited.extend_from_slice(eol);
+        }
+
+        Ok(edited)
+    }
+}
+
+/// Feature to edit the headers of a stored message.
+#[async_trait]
+pub trait EditMessageHeaders: GetEnvelope + PeekMessages + AddMessage + RemoveMessages {
+    /// Edit the headers of the message matching the given id from
+    /// the given folder.
+    ///
+    /// The edited message is added to the same folder with the same
+    /// flags and internal date, then the original message is
+    /// definitely removed. Returns the id of the edited message.
+    async fn edit_message_headers(
+        &self,
+        folder: &str,
+        id: &SingleId,
+        edit: &HeadersEdit,
+    ) -> AnyResult<SingleId> {
+        if edit.is_empty() {
+            debug!(
+                "no header to edit for message {} from folder {folder}",
+                id.as_str()
+            );
+            return Ok(id.clone());
+        }
+
+        let envelope = self.get_envelope(folder, id).await?;
+        let msgs = self.peek_messages(folder, &Id::from(id)).await?;
+        let msg = msgs
+            .first()
+            .ok_or_else(|| Error::FindMessageError(id.to_string()))?;
+        let msg = edit.apply(msg.raw()?)?;
+
+        let opts = AddMessageOptions {
+            flags: envelope.flags,
+            internal_date: envelope.internal_date,
+        };
+
+        let new_id = self.add_message_with_options(folder, &msg, &opts).await?;
+        debug!(
+            "added edited message {} to folder {folder}",
+            new_id.as_str()
+        );
+
+        self.remove_messages(folder, &Id::from(id)).await?;
+        debug!(
+            "removed original message {} from folder {folder}",
+            id.as_str()
+        );
+
+        Ok(new_id)
+    }
+}
+
+impl<T: GetEnvelope + PeekMessages + AddMessage + RemoveMessages + ?Sized> EditMessageHeaders
+    for T
+{
+}
+
+/// Splits the header section of the given raw message into header
+/// fields.
+///
+/// Returns the byte ranges of the fields, including their line
+/// breaks, and the offset the rest of the message starts at. The
+/// header section ends at the first empty line, or at the first line
+/// that is neither a field nor a continuation.
+fn split_fields(raw: &[u8]) -> (Vec<Range<usize>>, usize) {
+    let mut fields: Vec<Range<usize>> = Vec::new();
+    let mut offset = 0;
+
+    for line in raw.split_inclusive(|b| *b == b'\n') {
+        let start = offset;
+        let content = trim_eol(line);
+
+        if content.is_empty() {
+            break;
+        }
+
+        if matches!(content[0], b' ' | b'\t') {
+            match fields.last_mut() {
+                Some(field) => field.end = start + line.len(),
+                None => break,
+            }
+        } else if content.contains(&b':') {
+            fields.push(start..start + line.len());
+        } else {
+            break;
+        }
+
+        offset += line.len();
+    }
+
+    (fields, offset)
+}
+
+/// Trims the final line break of the given line.
+fn trim_eol(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// Validates the given header before adding it to a message.
+fn validate_header(name: &str, value: &str) -> Result<(), Error> {
+    let valid_name = !name.is_empty() && name.bytes().all(|b| b.is_ascii_graphic() && b != b':');
+
+    if !valid_name {
+        return Err(Error::InvalidHeaderError(name.to_owned()));
+    }
+
+    if value.contains(['\r', '\n']) {
+        return Err(Error::InvalidHeaderError(name.to_owned()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HeadersEdit;
+    use crate::{email::error::Error, message::Message};
+
+    const RAW: &str = "From: alice@localhost\r\n\
+        To: bob@localhost\r\n\
+        Subject: long\r\n subject\r\n\
+        X-Label: old\r\n\
+        \r\n\
+        Hello, world!\r\n";
+
+    #[test]
+    fn headers() {
+        let msg = Message::from(RAW);
+        let headers = msg.headers().unwrap();
+
+        let names: Vec<_> = headers.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, ["From", "To", "Subject", "X-Label"]);
+
+        assert_eq!(headers[2].raw_value, b" long\r\n subject");
+        assert_eq!(headers[2].value(), "long subject");
+        assert!(headers[3].is("x-label"));
+    }
+
+    #[test]
+    fn edit_headers() {
+        let edit = HeadersEdit::new()
+            .with_removed_header("x-label")
+            .with_added_header("X-Label", "important");
+
+        let edited = edit.apply(RAW.as_bytes()).unwrap();
+
+        assert_eq!(
+            String::from_utf8(edited).unwrap(),
+            "From: alice@localhost\r\n\
+            To: bob@localhost\r\n\
+            Subject: long\r\n subject\r\n\
+            X-Label: important\r\n\
+            \r\n\
+            Hello, world!\r\n"
+        );
+
+        let err = HeadersEdit::new()
+            .with_added_header("X-Label", "bad\r\nBcc: eve@localhost")
+            .apply(RAW.as_bytes())
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidHeaderError(_)));
+    }
+}
@@ -8,10 +8,16 @@
 
 pub mod add;
 pub mod attachment;
+pub mod authentication;
+pub mod backup;
+#[cfg(feature = "calendar")]
+pub mod calendar;
 pub mod config;
 pub mod copy;
 pub mod delete;
+pub mod diff;
 pub mod get;
+pub mod header;
 #[cfg(feature = "imap")]
 pub mod imap;
 pub mod r#move;
@@ -21,6 +27,8 @@
 #[cfg(feature = "sync")]
 pub mod sync;
 pub mod template;
+#[cfg(feature = "unsubscribe")]
+pub mod unsubscribe;
 
 use std::{
     borrow::Cow,
@@ -43,6 +51,7 @@
 
 use self::{
     attachment::Attachment,
+    diff::{split_headers, MessageDiff},
     template::{
         forward::ForwardTemplateBuilder, new::NewTemplateBuilder, reply::ReplyTemplateBuilder,
     },
@@ -78,6 +87,13 @@ pub fn raw(&self) -> Result<&[u8], Error> {
         self.parsed().map(|parsed| parsed.raw_message())
     }
 
+    /// Returns a plain text preview of the message body, up to the
+    /// given number of characters.
+    pub fn preview(&self, len: usize) -> Option<String> {
+        let preview = self.parsed().ok()?.body_preview(len)?;
+        Some(preview.trim().to_owned())
+    }
+
     /// Downloads parts in the given destination.
     pub fn download_parts(&self, dest: impl AsRef<Path>) -> Result<PathBuf, Error> {
         let dest = dest.as_ref();
@@ -248,6 +264,36 @@ pub fn attachments(&self) -> Result<Vec<Attachment>, Error> {
             .collect())
     }
 
+    /// Returns the text body of the message, made of all its text
+    /// parts.
+    pub fn text_body(&self) -> Result<String, Error> {
+        let parsed = self.parsed()?;
+        let body = (0..parsed.text_body.len())
+            .filter_map(|pos| parsed.body_text(pos))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(body)
+    }
+
+    /// Computes the diff between the current message and the given
+    /// one.
+    ///
+    /// Headers are compared using their raw values, while bodies
+    /// are compared using their decoded text parts.
+    pub fn diff(&self, other: &Message) -> Result<MessageDiff, Error> {
+        let old_raw = String::from_utf8_lossy(self.raw()?);
+        let new_raw = String::from_utf8_lossy(other.raw()?);
+        let (old_headers, _) = split_headers(&old_raw);
+        let (new_headers, _) = split_headers(&new_raw);
+
+        Ok(MessageDiff::new(
+            old_headers.iter().map(|(name, val)| (*name, val.as_str())),
+            &self.text_body()?,
+            new_headers.iter().map(|(name, val)| (*name, val.as_str())),
+            &other.text_body()?,
+        ))
+    }
+
     /// Creates a new template builder from an account configuration.
     pub fn new_tpl_builder(config: Arc<AccountConfig>) -> NewTemplateBuilder {
         NewTemplateBuilder::new(config)
@@ -1,10 +1,8 @@
 use async_trait::async_trait;
-use imap_client::imap_next::imap_types::sequence::{Sequence, SequenceSet};
 use tracing::{debug, info};
-use utf7_imap::encode_utf7_imap as encode_utf7;
 
 use super::MoveMessages;
-use crate::{envelope::Id, imap::ImapContext, AnyResult};
+use crate::{envelope::Id, flag::imap::to_imap_uid_set, imap::ImapContext, AnyResult};
 
 #[derive(Clone, Debug)]
 pub struct MoveImapMessages {
@@ -34,22 +32,14 @@ async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> An
         let config = &client.account_config;
 
         let from_folder = config.get_folder_alias(from_folder);
-        let from_folder_encoded = encode_utf7(from_folder.clone());
+        let from_folder_encoded = client.encode_mailbox(&from_folder);
         debug!("utf7 encoded from folder: {from_folder_encoded}");
 
         let to_folder = config.get_folder_alias(to_folder);
-        let to_folder_encoded = encode_utf7(to_folder.clone());
+        let to_folder_encoded = client.encode_mailbox(&to_folder);
         debug!("utf7 encoded to folder: {to_folder_encoded}");
 
-        let uids: SequenceSet = match id {
-            Id::Single(id) => Sequence::try_from(id.as_str()).unwrap().into(),
-            Id::Multiple(ids) => ids
-                .iter()
-                .filter_map(|id| Sequence::try_from(id.as_str()).ok())
-                .collect::<Vec<_>>()
-                .try_into()
-                .unwrap(),
-        };
+        let uids = to_imap_uid_set(id)?;
 
         client.select_mailbox(&from_folder_encoded).await?;
         client.move_messages(uids, &to_folder_encoded).await?;
@@ -2,7 +2,12 @@
 use tracing::info;
 
 use super::MoveMessages;
-use crate::{email::error::Error, envelope::Id, maildir::MaildirContextSync, AnyResult};
+use crate::{
+    email::error::Error,
+    envelope::{maildir::find_mdir_entries, Id},
+    maildir::MaildirContextSync,
+    AnyResult,
+};
 
 #[derive(Clone)]
 pub struct MoveMaildirMessages {
@@ -31,20 +36,19 @@ async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> An
         let ctx = self.ctx.lock().await;
         let from_mdir = ctx.get_maildir_from_folder_alias(from_folder)?;
         let to_mdir = ctx.get_maildir_from_folder_alias(to_folder)?;
-
-        id.iter()
-            .filter_map(|id| from_mdir.find(id).ok().flatten())
-            .try_for_each(|entry| {
-                entry.r#move(&to_mdir).map_err(|err| {
-                    Error::MoveMessagesMaildirError(
-                        err,
-                        from_folder.to_owned(),
-                        to_folder.to_owned(),
-                        entry.path().to_owned(),
-                    )
-                })?;
-                AnyResult::Ok(())
+        let uids = ctx.uid_db(&from_mdir)?;
+
+        find_mdir_entries(&from_mdir, uids.as_ref(), id)?.try_for_each(|entry| {
+            entry.r#move(&to_mdir).map_err(|err| {
+                Error::MoveMessagesMaildirError(
+                    err,
+                    from_folder.to_owned(),
+                    to_folder.to_owned(),
+                    entry.path().to_owned(),
+                )
             })?;
+            AnyResult::Ok(())
+        })?;
 
         Ok(())
     }
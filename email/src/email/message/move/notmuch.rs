@@ -3,9 +3,7 @@
 use tracing::{debug, info};
 
 use super::MoveMessages;
-use crate::{
-    email::error::Error, envelope::Id, folder::FolderKind, notmuch::NotmuchContextSync, AnyResult,
-};
+use crate::{email::error::Error, envelope::Id, notmuch::NotmuchContextSync, AnyResult};
 
 #[derive(Clone)]
 pub struct MoveNotmuchMessages {
@@ -31,20 +29,15 @@ impl MoveMessages for MoveNotmuchMessages {
     async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
         info!("moving notmuch messages {id} from folder {from_folder} to folder {to_folder}");
 
-        let config = &self.ctx.account_config;
         let ctx = self.ctx.lock().await;
+        ctx.check_writable_folder(to_folder)?;
 
         let mdir_ctx = &ctx.mdir_ctx;
         let mdir_to = mdir_ctx.get_maildir_from_folder_alias(to_folder)?;
 
         let db = ctx.open_db()?;
 
-        let ref from_folder = config.get_folder_alias(from_folder);
-        let folder_query = if ctx.maildirpp() && FolderKind::matches_inbox(from_folder) {
-            String::from("folder:\"\"")
-        } else {
-            format!("folder:{from_folder:?}")
-        };
+        let folder_query = ctx.folder_query(from_folder);
         let mid_query = format!("mid:\"/^({})$/\"", id.join("|"));
         let query = [folder_query, mid_query].join(" and ");
         let query_builder = db.create_query(&query).map_err(Error::NotMuchFailure)?;
@@ -30,6 +30,7 @@ async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
 
         let ctx = self.ctx.lock().await;
         let mdir = ctx.get_maildir_from_folder_alias(folder)?;
+        let uids = ctx.uid_db(&mdir)?;
 
         let mut msgs: Vec<(usize, maildirs::MaildirEntry)> = mdir
             .read()
@@ -39,16 +40,15 @@ async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
                 match entry.0.id() {
                     Err(_) => None,
                     Ok(id) => {
-                        entry.1 = id.to_owned();
+                        entry.1 = match &uids {
+                            Some(uids) => uids.get_uid(id)?.to_string(),
+                            None => id.to_owned(),
+                        };
                         Some(entry)
                     }
                 }
             })
-            .filter_map(|(entry, entry_id)| {
-                id.iter()
-                    .position(|id| id == entry_id)
-                    .map(|pos| (pos, entry))
-            })
+            .filter_map(|(entry, entry_id)| id.position(entry_id).map(|pos| (pos, entry)))
             .collect();
         msgs.sort_by_key(|(pos, _)| *pos);
 
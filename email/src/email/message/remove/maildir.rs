@@ -2,7 +2,12 @@
 use tracing::info;
 
 use super::RemoveMessages;
-use crate::{email::error::Error, envelope::Id, maildir::MaildirContextSync, AnyResult};
+use crate::{
+    email::error::Error,
+    envelope::{maildir::find_mdir_entries, Id},
+    maildir::MaildirContextSync,
+    AnyResult,
+};
 
 #[derive(Clone)]
 pub struct RemoveMaildirMessages {
@@ -30,14 +35,13 @@ async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
 
         let ctx = self.ctx.lock().await;
         let mdir = ctx.get_maildir_from_folder_alias(folder)?;
+        let uids = ctx.uid_db(&mdir)?;
 
-        id.iter()
-            .filter_map(|id| mdir.find(id).ok().flatten())
-            .try_for_each(|entry| {
-                entry.remove().map_err(|err| {
-                    Error::RemoveMaildirMessageError(err, folder.to_owned(), id.to_string())
-                })
-            })?;
+        find_mdir_entries(&mdir, uids.as_ref(), id)?.try_for_each(|entry| {
+            entry.remove().map_err(|err| {
+                Error::RemoveMaildirMessageError(err, folder.to_owned(), id.to_string())
+            })
+        })?;
 
         Ok(())
     }
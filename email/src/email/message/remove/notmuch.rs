@@ -34,7 +34,9 @@ async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
         let ctx = self.ctx.lock().await;
         let db = ctx.open_db()?;
 
-        let folder_query = if FolderKind::matches_inbox(folder) {
+        let folder_query = if let Some(query) = ctx.saved_search_query(folder) {
+            query
+        } else if FolderKind::matches_inbox(folder) {
             "folder:\"\"".to_owned()
         } else {
             let folder = config.get_folder_alias(folder);
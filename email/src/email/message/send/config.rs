@@ -17,4 +17,10 @@ pub struct MessageSendConfig {
     /// (stdin) and returns the modified raw message to the standard
     /// output (stdout).
     pub pre_hook: Option<Command>,
+
+    /// Should encrypt outgoing messages to the sender's own key as
+    /// well, so that the copy saved to the sent folder remains
+    /// readable.
+    #[cfg(feature = "pgp")]
+    pub pgp_encrypt_to_self: Option<bool>,
 }
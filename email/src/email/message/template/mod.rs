@@ -14,6 +14,8 @@
     ops::{Deref, DerefMut},
 };
 
+use super::diff::{split_headers, MessageDiff};
+
 pub use mml::{
     message::{FilterHeaders, FilterParts},
     MimeInterpreter,
@@ -46,6 +48,24 @@ pub fn append(&mut self, section: impl AsRef<str>) {
             self.content.push_str(section.as_ref())
         }
     }
+
+    /// Computes the diff between the current template and the given
+    /// one.
+    ///
+    /// Headers are compared by name, and bodies line by line. Since
+    /// templates are not compiled, MML parts are compared as plain
+    /// text.
+    pub fn diff(&self, other: &Template) -> MessageDiff {
+        let (old_headers, old_body) = split_headers(&self.content);
+        let (new_headers, new_body) = split_headers(&other.content);
+
+        MessageDiff::new(
+            old_headers.iter().map(|(name, val)| (*name, val.as_str())),
+            old_body,
+            new_headers.iter().map(|(name, val)| (*name, val.as_str())),
+            new_body,
+        )
+    }
 }
 
 impl Deref for Template {
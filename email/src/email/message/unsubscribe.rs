@@ -0,0 +1,306 @@
+//! # Unsubscribe
+//!
+//! Module dedicated to mailing lists unsubscription ([RFC 2369]).
+//!
+//! The `List-Unsubscribe` and `List-Unsubscribe-Post` headers of
+//! messages are parsed into [`ListUnsubscribe`]. Lists can then be
+//! unsubscribed using [`Unsubscribe`], which performs the HTTPS
+//! one-click POST ([RFC 8058]) when supported, or sends the mailto
+//! message otherwise.
+//!
+//! [RFC 2369]: https://www.rfc-editor.org/rfc/rfc2369
+//! [RFC 8058]: https://www.rfc-editor.org/rfc/rfc8058
+
+use std::fmt;
+
+use async_trait::async_trait;
+use http::Client as HttpClient;
+use mail_builder::{headers::address::Address, MessageBuilder};
+use tracing::{debug, info};
+
+use super::{send::SendMessage, Message};
+use crate::{
+    account::config::{AccountConfig, HasAccountConfig},
+    email::error::Error,
+    AnyResult,
+};
+
+/// The `List-Unsubscribe-Post` value announcing one-click
+/// unsubscription.
+const ONE_CLICK: &str = "List-Unsubscribe=One-Click";
+
+/// The subject of mailto messages not specifying any.
+const DEFAULT_SUBJECT: &str = "unsubscribe";
+
+/// The unsubscription method.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum UnsubscribeMethod {
+    /// Send a message to the given mailto URI.
+    Mailto(String),
+
+    /// Visit or post to the given HTTP(S) URI.
+    Http(String),
+}
+
+impl fmt::Display for UnsubscribeMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mailto(uri) | Self::Http(uri) => write!(f, "{uri}"),
+        }
+    }
+}
+
+/// The mailing list unsubscription information of a message.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ListUnsubscribe {
+    /// The unsubscription methods, in order of preference of the
+    /// list.
+    pub methods: Vec<UnsubscribeMethod>,
+
+    /// Whether the list supports one-click unsubscription, using a
+    /// POST request to the HTTPS method.
+    pub one_click: bool,
+}
+
+impl ListUnsubscribe {
+    /// Parses the given `List-Unsubscribe` and
+    /// `List-Unsubscribe-Post` header values.
+    ///
+    /// Returns `None` if no supported method is found.
+    pub fn from_headers(
+        list_unsubscribe: &str,
+        list_unsubscribe_post: Option<&str>,
+    ) -> Option<Self> {
+        let methods: Vec<_> = list_unsubscribe
+            .split(',')
+            .filter_map(|uri| {
+                let uri = uri.trim().strip_prefix('<')?.strip_suffix('>')?.trim();
+                let uri: String = uri.split_whitespace().collect();
+                let scheme = uri.split(':').next()?.to_ascii_lowercase();
+
+                match scheme.as_str() {
+                    "mailto" => Some(UnsubscribeMethod::Mailto(uri)),
+                    "http" | "https" => Some(UnsubscribeMethod::Http(uri)),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        if methods.is_empty() {
+            return None;
+        }
+
+        let one_click =
+            list_unsubscribe_post.is_some_and(|post| post.trim().eq_ignore_ascii_case(ONE_CLICK));
+
+        Some(Self { methods, one_click })
+    }
+
+    /// Returns the first mailto URI.
+    pub fn mailto(&self) -> Option<&str> {
+        self.methods.iter().find_map(|method| match method {
+            UnsubscribeMethod::Mailto(uri) => Some(uri.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Returns the first HTTPS URI usable for one-click
+    /// unsubscription, if the list supports it.
+    pub fn one_click_uri(&self) -> Option<&str> {
+        if !self.one_click {
+            return None;
+        }
+
+        self.methods.iter().find_map(|method| match method {
+            UnsubscribeMethod::Http(uri) if uri.to_ascii_lowercase().starts_with("https:") => {
+                Some(uri.as_str())
+            }
+            _ => None,
+        })
+    }
+
+    /// Returns the first HTTP(S) URI.
+    pub fn http(&self) -> Option<&str> {
+        self.methods.iter().find_map(|method| match method {
+            UnsubscribeMethod::Http(uri) => Some(uri.as_str()),
+            _ => None,
+        })
+    }
+}
+
+impl Message<'_> {
+    /// Parses the mailing list unsubscription information from the
+    /// `List-Unsubscribe` and `List-Unsubscribe-Post` headers.
+    pub fn list_unsubscribe(&self) -> Result<Option<ListUnsubscribe>, Error> {
+        let msg = self.parsed()?;
+
+        let Some(list_unsubscribe) = msg.header_raw("List-Unsubscribe") else {
+            return Ok(None);
+        };
+
+        let post = msg.header_raw("List-Unsubscribe-Post");
+
+        Ok(ListUnsubscribe::from_headers(list_unsubscribe, post))
+    }
+
+    /// Builds the unsubscription message of the given mailto URI.
+    ///
+    /// The recipient, the subject and the body are taken from the
+    /// URI ([RFC 6068]).
+    ///
+    /// [RFC 6068]: https://www.rfc-editor.org/rfc/rfc6068
+    pub fn to_unsubscribe_message(config: &AccountConfig, mailto: &str) -> Result<Vec<u8>, Error> {
+        let mailto = mailto.get("mailto:".len()..).unwrap_or_default();
+        let (to, query) = mailto.split_once('?').unwrap_or((mailto, ""));
+        let to = urlencoding::decode(to).map_err(|_| Error::ParseMailtoError(mailto.to_owned()))?;
+
+        if to.is_empty() {
+            return Err(Error::ParseMailtoError(mailto.to_owned()));
+        }
+
+        let mut subject = DEFAULT_SUBJECT.to_owned();
+        let mut body = String::new();
+
+        for (key, val) in query.split('&').filter_map(|param| param.split_once('=')) {
+            let val =
+                urlencoding::decode(val).map_err(|_| Error::ParseMailtoError(mailto.to_owned()))?;
+
+            match key.to_ascii_lowercase().as_str() {
+                "subject" => subject = val.into_owned(),
+                "body" => body = val.into_owned(),
+                _ => (),
+            }
+        }
+
+        let to: Vec<Address> = to
+            .split(',')
+            .map(|addr| Address::new_address(None::<String>, addr.trim().to_owned()))
+            .collect();
+
+        MessageBuilder::new()
+            .from(config)
+            .to(to)
+            .subject(subject)
+            .text_body(body)
+            .write_to_vec()
+            .map_err(Error::BuildUnsubscribeMessageError)
+    }
+}
+
+#[async_trait]
+pub trait Unsubscribe: HasAccountConfig + SendMessage {
+    /// Unsubscribes from the mailing list of the given message.
+    ///
+    /// The HTTPS one-click POST is performed when supported by the
+    /// list, otherwise the mailto message is sent. Lists only
+    /// providing a web page cannot be unsubscribed automatically, in
+    /// which case an error containing the page URI is returned.
+    ///
+    /// Returns the method used.
+    async fn unsubscribe(&self, msg: &Message) -> AnyResult<UnsubscribeMethod> {
+        let list = msg
+            .list_unsubscribe()?
+            .ok_or(Error::FindListUnsubscribeError)?;
+
+        if let Some(uri) = list.one_click_uri() {
+            info!("unsubscribing using one-click post to {uri}");
+            post_one_click(uri).await?;
+            return Ok(UnsubscribeMethod::Http(uri.to_owned()));
+        }
+
+        if let Some(uri) = list.mailto() {
+            info!("unsubscribing by sending message to {uri}");
+            let msg = Message::to_unsubscribe_message(self.account_config(), uri)?;
+            self.send_message(&msg).await?;
+            return Ok(UnsubscribeMethod::Mailto(uri.to_owned()));
+        }
+
+        let uri = list.http().unwrap_or_default().to_owned();
+        Err(Error::UnsubscribeManuallyError(uri).into())
+    }
+}
+
+impl<T: HasAccountConfig + SendMessage> Unsubscribe for T {}
+
+/// Performs the one-click unsubscription POST request to the given
+/// URI ([RFC 8058]).
+///
+/// [RFC 8058]: https://www.rfc-editor.org/rfc/rfc8058
+async fn post_one_click(uri: &str) -> Result<(), Error> {
+    let uri_clone = uri.to_owned();
+    let res = HttpClient::new()
+        .send(move |agent| {
+            agent
+                .post(uri_clone)
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .send(ONE_CLICK)
+        })
+        .await
+        .map_err(|err| Error::SendUnsubscribeRequestError(err, uri.to_owned()))?;
+
+    let status = res.status();
+    debug!("one-click unsubscription responded with status {status}");
+
+    if !status.is_success() {
+        return Err(Error::UnsubscribeRequestStatusError(status, uri.to_owned()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ListUnsubscribe, UnsubscribeMethod};
+    use crate::{account::config::AccountConfig, message::Message};
+
+    #[test]
+    fn parse_headers() {
+        let list = ListUnsubscribe::from_headers(
+            " <mailto:leave@lists.localhost?subject=unsub>,\r\n <https://lists.localhost/u/42>",
+            Some("List-Unsubscribe=One-Click"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            list.methods,
+            vec![
+                UnsubscribeMethod::Mailto("mailto:leave@lists.localhost?subject=unsub".into()),
+                UnsubscribeMethod::Http("https://lists.localhost/u/42".into()),
+            ]
+        );
+        assert_eq!(list.one_click_uri(), Some("https://lists.localhost/u/42"));
+
+        let list = ListUnsubscribe::from_headers("<https://lists.localhost/u/42>", None).unwrap();
+        assert_eq!(list.one_click_uri(), None);
+        assert_eq!(list.mailto(), None);
+
+        assert_eq!(
+            ListUnsubscribe::from_headers("<ftp://localhost>", None),
+            None
+        );
+    }
+
+    #[test]
+    fn build_unsubscribe_message() {
+        let config = AccountConfig {
+            email: "me@localhost".into(),
+            ..Default::default()
+        };
+
+        let msg = Message::to_unsubscribe_message(
+            &config,
+            "mailto:leave@lists.localhost?subject=Leave%20list&body=please",
+        )
+        .unwrap();
+        let msg = mail_parser::MessageParser::new().parse(&msg).unwrap();
+
+        assert_eq!(msg.subject(), Some("Leave list"));
+        assert_eq!(msg.body_text(0).as_deref(), Some("please"));
+        assert_eq!(
+            msg.to().and_then(|to| to.first()?.address.as_deref()),
+            Some("leave@lists.localhost")
+        );
+
+        assert!(Message::to_unsubscribe_message(&config, "mailto:?subject=x").is_err());
+    }
+}
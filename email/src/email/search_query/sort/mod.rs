@@ -15,8 +15,8 @@
 
 /// The search emails sorter.
 ///
-/// The sorter is composed of a kind (date, from, to, subject) and an
-/// order (ascending, descending).
+/// The sorter is composed of a kind (date, internal date, from, to,
+/// subject) and an order (ascending, descending).
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub struct SearchEmailsSorter(
     /// The search emails sorter kind.
@@ -58,6 +58,13 @@ pub enum SearchEmailsSorterKind {
     /// Sort emails by message header `Date`.
     Date,
 
+    /// Sort emails by internal date, which is the date the message
+    /// has been received.
+    ///
+    /// Envelopes without internal date are sorted using their
+    /// message header `Date`.
+    InternalDate,
+
     /// Sort emails by envelope sender.
     From,
 
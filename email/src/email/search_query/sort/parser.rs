@@ -17,10 +17,11 @@
 ///
 /// # Kinds
 ///
-/// There is actually 4 kinds, as defined in
+/// There is actually 5 kinds, as defined in
 /// [`SearchEmailsSorterKind`]:
 ///
 /// - `date [order]`
+/// - `internal-date [order]`
 /// - `from [order]`
 /// - `to [order]`
 /// - `subject [order]`
@@ -42,7 +43,7 @@
 #[doc = include_str!("./grammar.abnf")]
 /// ```
 pub fn query<'a>() -> impl Parser<'a, &'a str, Vec<SearchEmailsSorter>, ParserError<'a>> + Clone {
-    choice((date(), from(), to(), subject()))
+    choice((date(), internal_date(), from(), to(), subject()))
         .separated_by(
             just(' ')
                 .labelled("space between sorters")
@@ -77,6 +78,28 @@ fn date_kind<'a>() -> impl Parser<'a, &'a str, SearchEmailsSorterKind, ParserErr
         .to(SearchEmailsSorterKind::Date)
 }
 
+fn internal_date<'a>() -> impl Parser<'a, &'a str, SearchEmailsSorter, ParserError<'a>> + Clone {
+    choice((
+        internal_date_kind()
+            .then(
+                just(' ')
+                    .labelled("space after `internal-date`")
+                    .repeated()
+                    .at_least(1)
+                    .ignore_then(choice((ascending(), descending()))),
+            )
+            .map(SearchEmailsSorter::from),
+        internal_date_kind().map(SearchEmailsSorter::from),
+    ))
+}
+
+fn internal_date_kind<'a>(
+) -> impl Parser<'a, &'a str, SearchEmailsSorterKind, ParserError<'a>> + Clone {
+    just("internal-date")
+        .labelled("`internal-date`")
+        .to(SearchEmailsSorterKind::InternalDate)
+}
+
 fn from<'a>() -> impl Parser<'a, &'a str, SearchEmailsSorter, ParserError<'a>> + Clone {
     choice((
         from_kind()
@@ -206,4 +229,17 @@ fn mixed_sorters() {
             ])
         );
     }
+
+    #[test]
+    fn internal_date_sorters() {
+        assert_eq!(
+            super::query()
+                .parse("internal-date desc date")
+                .into_result(),
+            Ok(vec![
+                SearchEmailsSorter(InternalDate, Descending),
+                SearchEmailsSorter(Date, Ascending),
+            ])
+        );
+    }
 }
@@ -0,0 +1,179 @@
+//! Module dedicated to email synchronization conflicts.
+//!
+//! A conflict occurs when the same email (same Message-ID) is added
+//! on both sides with different contents. Since the synchronization
+//! cannot determine which version should be kept, both versions are
+//! saved in the `Conflicts` directory of the left cache directory, so
+//! that users can resolve the conflict manually.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{Error, Result};
+use crate::envelope::Envelope;
+
+/// The name of the directory containing conflicts, at the root of
+/// the left Maildir cache directory.
+pub const CONFLICTS_DIR_NAME: &str = "Conflicts";
+
+/// The name of the file containing the left version of a conflict.
+pub const LEFT_FILE_NAME: &str = "left.eml";
+
+/// The name of the file containing the right version of a conflict.
+pub const RIGHT_FILE_NAME: &str = "right.eml";
+
+/// The name of the file containing the metadata of a conflict.
+pub const METADATA_FILE_NAME: &str = "metadata";
+
+/// The email synchronization conflict.
+///
+/// Both versions of the email are saved in a dedicated directory,
+/// alongside a metadata file linking them to their folder and to
+/// their identifier on both sides.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EmailSyncConflict {
+    /// The folder both versions belong to.
+    pub folder: String,
+
+    /// The Message-ID shared by both versions.
+    pub message_id: String,
+
+    /// The identifier of the left version.
+    pub left_id: String,
+
+    /// The identifier of the right version.
+    pub right_id: String,
+
+    /// The directory containing both versions and the metadata.
+    pub dir: PathBuf,
+}
+
+impl EmailSyncConflict {
+    /// Creates a new conflict between the given left and right
+    /// envelopes, located in the given cache root directory.
+    pub fn new(
+        root_dir: impl AsRef<Path>,
+        folder: &str,
+        left: &Envelope,
+        right: &Envelope,
+    ) -> Self {
+        let dir = root_dir
+            .as_ref()
+            .join(CONFLICTS_DIR_NAME)
+            .join(sanitize(folder))
+            .join(sanitize(&left.message_id));
+
+        Self {
+            folder: folder.to_owned(),
+            message_id: left.message_id.clone(),
+            left_id: left.id.clone(),
+            right_id: right.id.clone(),
+            dir,
+        }
+    }
+
+    /// Returns the path of the left version.
+    pub fn left_path(&self) -> PathBuf {
+        self.dir.join(LEFT_FILE_NAME)
+    }
+
+    /// Returns the path of the right version.
+    pub fn right_path(&self) -> PathBuf {
+        self.dir.join(RIGHT_FILE_NAME)
+    }
+
+    /// Returns the path of the metadata file.
+    pub fn metadata_path(&self) -> PathBuf {
+        self.dir.join(METADATA_FILE_NAME)
+    }
+
+    /// Saves the given left and right raw versions, as well as the
+    /// metadata of the conflict.
+    ///
+    /// Saving an existing conflict overrides it, so that it always
+    /// reflects the latest versions of both sides.
+    pub fn save(&self, left: &[u8], right: &[u8]) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|err| Error::SaveConflictError(err, self.dir.clone()))?;
+
+        let path = self.left_path();
+        fs::write(&path, left).map_err(|err| Error::SaveConflictError(err, path))?;
+
+        let path = self.right_path();
+        fs::write(&path, right).map_err(|err| Error::SaveConflictError(err, path))?;
+
+        let metadata = format!(
+            "Folder: {}\nMessage-ID: {}\nLeft-ID: {}\nRight-ID: {}\n",
+            self.folder, self.message_id, self.left_id, self.right_id,
+        );
+
+        let path = self.metadata_path();
+        fs::write(&path, metadata).map_err(|err| Error::SaveConflictError(err, path))?;
+
+        Ok(())
+    }
+}
+
+/// Turns the given folder name or Message-ID into a valid file name.
+fn sanitize(name: &str) -> String {
+    let name = name.trim().trim_start_matches('<').trim_end_matches('>');
+
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            c if c.is_alphanumeric() => c,
+            '.' | '-' | '_' | '@' | '+' => c,
+            _ => '_',
+        })
+        .collect();
+
+    match name.trim_start_matches('.') {
+        "" => String::from("_"),
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::EmailSyncConflict;
+    use crate::envelope::Envelope;
+
+    #[test]
+    fn save_conflict() {
+        let dir = tempdir().unwrap();
+        let dir = dir.path();
+
+        let left = Envelope {
+            id: "left-id".into(),
+            message_id: "<id@localhost>".into(),
+            ..Envelope::default()
+        };
+
+        let right = Envelope {
+            id: "42".into(),
+            message_id: "<id@localhost>".into(),
+            ..Envelope::default()
+        };
+
+        let conflict = EmailSyncConflict::new(dir, "Archives/2024", &left, &right);
+        conflict.save(b"left", b"right").unwrap();
+
+        let conflict_dir = dir
+            .join("Conflicts")
+            .join("Archives_2024")
+            .join("id@localhost");
+        assert_eq!(conflict.dir, conflict_dir);
+        assert_eq!(fs::read(conflict_dir.join("left.eml")).unwrap(), b"left");
+        assert_eq!(fs::read(conflict_dir.join("right.eml")).unwrap(), b"right");
+        assert_eq!(
+            fs::read_to_string(conflict_dir.join("metadata")).unwrap(),
+            "Folder: Archives/2024\nMessage-ID: <id@localhost>\nLeft-ID: left-id\nRight-ID: 42\n",
+        );
+    }
+}
@@ -49,6 +49,11 @@ pub enum EmailSyncHunk {
     /// The envelope matching the given identifier from the given
     /// folder needs to be deleted from the given target.
     Delete(FolderName, Id, SyncDestination),
+
+    /// The same email has been added on both sides with the given
+    /// left and right envelopes. Identical emails are cached on both
+    /// sides, different ones are saved as a conflict.
+    SaveConflict(FolderName, Envelope, Envelope),
 }
 
 impl fmt::Display for EmailSyncHunk {
@@ -86,6 +91,13 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             Self::Delete(folder, id, target) => {
                 write!(f, "Deleting {target} email {id} ({folder})")
             }
+            Self::SaveConflict(folder, left, right) => {
+                let (left_id, right_id) = (&left.id, &right.id);
+                write!(
+                    f,
+                    "Checking conflict between left email {left_id} and right email {right_id} ({folder})"
+                )
+            }
         }
     }
 }
@@ -99,6 +111,7 @@ pub fn folder(&self) -> &str {
             Self::UpdateFlags(folder, _, _) => folder.as_str(),
             Self::Uncache(folder, _, _) => folder.as_str(),
             Self::Delete(folder, _, _) => folder.as_str(),
+            Self::SaveConflict(folder, _, _) => folder.as_str(),
         }
     }
 }
@@ -0,0 +1,145 @@
+//! Module dedicated to email synchronization id mapping.
+//!
+//! The id mapping correlates the identifier of an email on the left
+//! (local) side with the identifier of the same email on the right
+//! (remote) side. The mapping is updated at every synchronization and
+//! persisted in the left cache directory, so that clients can find the
+//! remote identifier of an email they added locally, and vice versa.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{Error, Result};
+
+/// The name of the file containing the id mapping, at the root of the
+/// left Maildir cache directory.
+pub const ID_MAPPING_FILE_NAME: &str = ".id-mapping";
+
+/// The email synchronization id mapping.
+///
+/// Left identifiers are mapped to right identifiers, folder by
+/// folder.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EmailSyncIdMapping(BTreeMap<String, BTreeMap<String, String>>);
+
+impl EmailSyncIdMapping {
+    /// Returns the path of the id mapping file for the given cache
+    /// root directory.
+    pub fn path(root_dir: impl AsRef<Path>) -> PathBuf {
+        root_dir.as_ref().join(ID_MAPPING_FILE_NAME)
+    }
+
+    /// Reads the id mapping from the given cache root directory.
+    ///
+    /// A missing id mapping file is considered empty.
+    pub fn read(root_dir: impl AsRef<Path>) -> Result<Self> {
+        let path = Self::path(root_dir);
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(Error::ReadIdMappingError(err, path)),
+        };
+
+        let mut mapping = Self::default();
+
+        for line in contents.lines() {
+            // folder names may contain tabulations, identifiers
+            // cannot
+            let mut fields = line.rsplitn(3, '\t');
+
+            if let (Some(right_id), Some(left_id), Some(folder)) =
+                (fields.next(), fields.next(), fields.next())
+            {
+                mapping.insert(folder, left_id, right_id);
+            }
+        }
+
+        Ok(mapping)
+    }
+
+    /// Writes the id mapping to the given cache root directory.
+    pub fn write(&self, root_dir: impl AsRef<Path>) -> Result<()> {
+        let path = Self::path(root_dir);
+        let mut contents = String::new();
+
+        for (folder, ids) in &self.0 {
+            for (left_id, right_id) in ids {
+                contents.push_str(folder);
+                contents.push('\t');
+                contents.push_str(left_id);
+                contents.push('\t');
+                contents.push_str(right_id);
+                contents.push('\n');
+            }
+        }
+
+        fs::write(&path, contents).map_err(|err| Error::WriteIdMappingError(err, path))
+    }
+
+    /// Maps the given left identifier to the given right identifier.
+    pub fn insert(
+        &mut self,
+        folder: impl ToString,
+        left_id: impl ToString,
+        right_id: impl ToString,
+    ) {
+        self.0
+            .entry(folder.to_string())
+            .or_default()
+            .insert(left_id.to_string(), right_id.to_string());
+    }
+
+    /// Replaces the whole mapping of the given folder.
+    pub fn replace_folder(
+        &mut self,
+        folder: impl ToString,
+        ids: impl IntoIterator<Item = (String, String)>,
+    ) {
+        self.0.insert(folder.to_string(), BTreeMap::from_iter(ids));
+    }
+
+    /// Finds the right identifier mapped to the given left
+    /// identifier.
+    pub fn left_to_right(&self, folder: &str, left_id: &str) -> Option<&str> {
+        self.0.get(folder)?.get(left_id).map(String::as_str)
+    }
+
+    /// Finds the left identifier mapped to the given right
+    /// identifier.
+    pub fn right_to_left(&self, folder: &str, right_id: &str) -> Option<&str> {
+        self.0
+            .get(folder)?
+            .iter()
+            .find(|(_, id)| *id == right_id)
+            .map(|(id, _)| id.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::EmailSyncIdMapping;
+
+    #[test]
+    fn read_write_id_mapping() {
+        let dir = tempdir().unwrap();
+        let dir = dir.path();
+
+        let mut mapping = EmailSyncIdMapping::default();
+        mapping.insert("INBOX", "abc", "1");
+        mapping.insert("INBOX", "def", "2");
+        mapping.insert("with\ttab", "ghi", "1");
+        mapping.write(dir).unwrap();
+
+        let mapping = EmailSyncIdMapping::read(dir).unwrap();
+        assert_eq!(mapping.left_to_right("INBOX", "def"), Some("2"));
+        assert_eq!(mapping.right_to_left("INBOX", "1"), Some("abc"));
+        assert_eq!(mapping.right_to_left("with\ttab", "1"), Some("ghi"));
+        assert_eq!(mapping.left_to_right("Sent", "abc"), None);
+    }
+}
@@ -2,36 +2,51 @@
 //!
 //! Module dedicated to email synchronization.
 
+pub mod conflict;
 pub mod hunk;
+pub mod id_mapping;
 pub mod patch;
 pub mod report;
 
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::Path,
     string::String,
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
-use futures::{stream::FuturesUnordered, StreamExt};
-use tracing::{debug, trace};
+use futures::{future::join_all, stream::FuturesUnordered, StreamExt};
+use tracing::{debug, trace, warn};
 
-use self::{hunk::EmailSyncHunk, report::EmailSyncReport};
+use self::{
+    conflict::EmailSyncConflict,
+    hunk::{EmailSyncHunk, RefreshSourceCache},
+    id_mapping::EmailSyncIdMapping,
+    patch::into_sync_entry,
+    report::EmailSyncReport,
+};
 #[doc(inline)]
 pub use super::{Error, Result};
 use crate::{
-    backend::context::BackendContextBuilder,
+    backend::context::{BackendContext, BackendContextBuilder},
+    contacts::Contacts,
     envelope::{
         get::GetEnvelope,
         list::{ListEnvelopes, ListEnvelopesOptions},
         Envelope, Id, SingleId,
     },
-    flag::{add::AddFlags, set::SetFlags, Flag},
+    flag::{add::AddFlags, set::SetFlags, Flag, Flags},
+    folder::sync::hunk::FolderName,
     message::{add::AddMessage, peek::PeekMessages},
     search_query::SearchEmailsQuery,
     sync::{pool::SyncPoolContext, SyncDestination, SyncEvent},
-    AnyBoxedError,
+    AnyBoxedError, AnyResult,
 };
 
+/// The maximum number of messages added at once to the same folder
+/// when copying messages.
+const COPY_BATCH_SIZE: usize = 100;
+
 /// Errors related to email synchronization.
 
 pub(crate) async fn sync<L, R>(
@@ -43,6 +58,20 @@ pub(crate) async fn sync<L, R>(
     R: BackendContextBuilder + 'static,
 {
     let mut report = EmailSyncReport::default();
+
+    let left_contacts_dir = &ctx_ref.left_cache.context.maildir_config.root_dir;
+    let right_contacts_dir = &ctx_ref.right_cache.context.maildir_config.root_dir;
+    let contacts = Mutex::new((
+        read_contacts(left_contacts_dir),
+        read_contacts(right_contacts_dir),
+    ));
+
+    let id_mapping_dir = left_contacts_dir;
+    let id_mapping = Arc::new(Mutex::new(EmailSyncIdMapping::read(id_mapping_dir)?));
+
+    let conflicts_dir = left_contacts_dir.clone();
+    let conflicts = Arc::new(Mutex::new(Vec::new()));
+
     let patch = FuturesUnordered::from_iter(folders.iter().map(|folder| {
         let ctx = ctx_ref.clone();
         let folder_ref = folder.clone();
@@ -59,6 +88,7 @@ pub(crate) async fn sync<L, R>(
                                 filter: ctx.envelope_filters.clone().into(),
                                 sort: None,
                             }),
+                            preview: None,
                         },
                     )
                     .await
@@ -70,7 +100,7 @@ pub(crate) async fn sync<L, R>(
                         }
                     })?
                     .into_iter()
-                    .map(|e| (e.message_id.clone(), e)),
+                    .map(into_sync_entry),
             );
 
             SyncEvent::ListedLeftCachedEnvelopes(folder_ref.clone(), envelopes.len())
@@ -94,6 +124,7 @@ pub(crate) async fn sync<L, R>(
                                 filter: ctx.envelope_filters.clone().into(),
                                 sort: None,
                             }),
+                            preview: None,
                         },
                     )
                     .await
@@ -105,7 +136,7 @@ pub(crate) async fn sync<L, R>(
                         }
                     })?
                     .into_iter()
-                    .map(|e| (e.message_id.clone(), e)),
+                    .map(into_sync_entry),
             );
 
             SyncEvent::ListedLeftEnvelopes(folder_ref.clone(), envelopes.len())
@@ -129,6 +160,7 @@ pub(crate) async fn sync<L, R>(
                                 filter: ctx.envelope_filters.clone().into(),
                                 sort: None,
                             }),
+                            preview: None,
                         },
                     )
                     .await
@@ -140,7 +172,7 @@ pub(crate) async fn sync<L, R>(
                         }
                     })?
                     .into_iter()
-                    .map(|e| (e.message_id.clone(), e)),
+                    .map(into_sync_entry),
             );
 
             SyncEvent::ListedRightCachedEnvelopes(folder_ref.clone(), envelopes.len())
@@ -164,6 +196,7 @@ pub(crate) async fn sync<L, R>(
                                 filter: ctx.envelope_filters.clone().into(),
                                 sort: None,
                             }),
+                            preview: None,
                         },
                     )
                     .await
@@ -175,7 +208,7 @@ pub(crate) async fn sync<L, R>(
                         }
                     })?
                     .into_iter()
-                    .map(|e| (e.message_id.clone(), e)),
+                    .map(into_sync_entry),
             );
 
             SyncEvent::ListedRightEnvelopes(folder_ref.clone(), envelopes.len())
@@ -200,7 +233,36 @@ pub(crate) async fn sync<L, R>(
         let task = async {
             let (folder, envelopes) = patch?;
             let (lc, l, rc, r) = envelopes.map_err(|e| Error::FailedToGetEnvelopes(e))?;
-            let patch = patch::build(&folder, lc?, l?, rc?, r?);
+            let (lc, l, rc, r) = (lc?, l?, rc?, r?);
+
+            // contacts are harvested from envelopes that are not
+            // cached yet, so that they are counted only once
+            if let Ok(mut contacts) = contacts.lock() {
+                let (left_contacts, right_contacts) = &mut *contacts;
+
+                for (id, envelope) in &l {
+                    if !lc.contains_key(id) {
+                        left_contacts.harvest(envelope);
+                    }
+                }
+
+                for (id, envelope) in &r {
+                    if !rc.contains_key(id) {
+                        right_contacts.harvest(envelope);
+                    }
+                }
+            }
+
+            if let Ok(mut id_mapping) = id_mapping.lock() {
+                let ids = l.iter().filter_map(|(msg_id, left)| {
+                    let right = r.get(msg_id)?;
+                    Some((left.id.clone(), right.id.clone()))
+                });
+
+                id_mapping.replace_folder(&folder, ids);
+            }
+
+            let patch = patch::build(&folder, lc, l, rc, r);
             Ok::<(String, HashSet<Vec<EmailSyncHunk>>), AnyBoxedError>((folder, patch))
         };
         match task.await {
@@ -225,10 +287,60 @@ pub(crate) async fn sync<L, R>(
         .emit(&ctx_ref.handler)
         .await;
 
-    report.patch = FuturesUnordered::from_iter(patch.into_values().flatten().map(|hunk| {
+    // messages copied to the same folder of the same side are
+    // processed in batches, so that backends able to add messages
+    // in bulk save round-trips
+    let mut hunks = Vec::new();
+    let mut copies: BTreeMap<_, Vec<_>> = BTreeMap::new();
+
+    for hunk in patch.into_values().flatten() {
+        match split_hunk(hunk) {
+            SplitHunk::Copy(key, copy) => copies.entry(key).or_default().push(copy),
+            SplitHunk::Single(hunk, single) => hunks.push((hunk, single)),
+        }
+    }
+
+    let batches = copies
+        .into_iter()
+        .flat_map(|((folder, source, target), copies)| {
+            copies
+                .chunks(COPY_BATCH_SIZE)
+                .map(|batch| {
+                    (
+                        folder.clone(),
+                        source.clone(),
+                        target.clone(),
+                        batch.to_vec(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        });
+
+    let copy_tasks = batches.map(|(folder, source, target, batch)| {
+        let ctx = ctx_ref.clone();
+        let id_mapping = id_mapping.clone();
+        tokio::spawn(async move {
+            let outputs = if ctx.dry_run {
+                batch.into_iter().map(|copy| (copy.hunk, None)).collect()
+            } else {
+                copy_then_cache(&ctx, &id_mapping, &folder, &source, &target, batch).await
+            };
+
+            for (hunk, _) in &outputs {
+                SyncEvent::ProcessedEmailHunk(hunk.clone())
+                    .emit(&ctx.handler)
+                    .await;
+            }
+
+            outputs
+        })
+    });
+
+    let hunk_tasks = hunks.into_iter().map(|(hunk, single)| {
         let ctx = ctx_ref.clone();
+        let conflicts_dir = conflicts_dir.clone();
+        let conflicts = conflicts.clone();
         tokio::spawn(async move {
-            let hunk_clone = hunk.clone();
             let handler = ctx.handler.clone();
 
             let task = async move {
@@ -236,8 +348,8 @@ pub(crate) async fn sync<L, R>(
                     return Ok(());
                 }
 
-                match hunk_clone {
-                    EmailSyncHunk::GetThenCache(folder, id, SyncDestination::Left) => {
+                match single {
+                    SingleHunk::GetThenCache(folder, id, SyncDestination::Left) => {
                         let envelope = ctx.left.get_envelope(&folder, &SingleId::from(id)).await?;
                         let flags = envelope.flags.clone();
                         let msg = envelope.to_sync_cache_msg();
@@ -245,7 +357,7 @@ pub(crate) async fn sync<L, R>(
                             .add_message_with_flags(&folder, msg.as_bytes(), &flags)
                             .await?;
                     }
-                    EmailSyncHunk::GetThenCache(folder, id, SyncDestination::Right) => {
+                    SingleHunk::GetThenCache(folder, id, SyncDestination::Right) => {
                         let envelope = ctx.right.get_envelope(&folder, &SingleId::from(id)).await?;
                         let flags = envelope.flags.clone();
                         let msg = envelope.to_sync_cache_msg();
@@ -253,111 +365,84 @@ pub(crate) async fn sync<L, R>(
                             .add_message_with_flags(&folder, msg.as_bytes(), &flags)
                             .await?;
                     }
-                    EmailSyncHunk::CopyThenCache(
-                        folder,
-                        envelope,
-                        source,
-                        target,
-                        refresh_source_cache,
-                    ) => {
-                        let id = Id::single(&envelope.id);
-                        let msgs = match source {
-                            SyncDestination::Left => {
-                                if refresh_source_cache {
-                                    let flags = envelope.flags.clone();
-                                    let msg = envelope.to_sync_cache_msg();
-                                    ctx.left_cache
-                                        .add_message_with_flags(&folder, msg.as_bytes(), &flags)
-                                        .await?;
-                                };
-                                ctx.left.peek_messages(&folder, &id).await?
-                            }
-                            SyncDestination::Right => {
-                                if refresh_source_cache {
-                                    let flags = envelope.flags.clone();
-                                    let msg = envelope.to_sync_cache_msg();
-                                    ctx.right_cache
-                                        .add_message_with_flags(&folder, msg.as_bytes(), &flags)
-                                        .await?;
-                                };
-                                ctx.right.peek_messages(&folder, &id).await?
-                            }
-                        };
-
-                        let msgs = msgs.to_vec();
-                        let msg = msgs
-                            .first()
-                            .ok_or_else(|| Error::FindMessageError(envelope.id.clone()))?;
-
-                        match target {
-                            SyncDestination::Left => {
-                                let id = ctx
-                                    .left
-                                    .add_message_with_flags(&folder, msg.raw()?, &envelope.flags)
-                                    .await?;
-                                let envelope =
-                                    ctx.left.get_envelope(&folder, &SingleId::from(id)).await?;
-                                let flags = envelope.flags.clone();
-                                let msg = envelope.to_sync_cache_msg();
-                                ctx.left_cache
-                                    .add_message_with_flags(&folder, msg.as_bytes(), &flags)
-                                    .await?;
-                            }
-                            SyncDestination::Right => {
-                                let id = ctx
-                                    .right
-                                    .add_message_with_flags(&folder, msg.raw()?, &envelope.flags)
-                                    .await?;
-                                let envelope =
-                                    ctx.right.get_envelope(&folder, &SingleId::from(id)).await?;
-                                let flags = envelope.flags.clone();
-                                let msg = envelope.to_sync_cache_msg();
-                                ctx.right_cache
-                                    .add_message_with_flags(&folder, msg.as_bytes(), &flags)
-                                    .await?;
-                            }
-                        };
-                    }
-                    EmailSyncHunk::Uncache(folder, id, SyncDestination::Left) => {
+                    SingleHunk::Uncache(folder, id, SyncDestination::Left) => {
                         ctx.left_cache
                             .add_flag(&folder, &Id::single(id), Flag::Deleted)
                             .await?;
                     }
-                    EmailSyncHunk::Delete(folder, id, SyncDestination::Left) => {
+                    SingleHunk::Delete(folder, id, SyncDestination::Left) => {
                         ctx.left
                             .add_flag(&folder, &Id::single(id), Flag::Deleted)
                             .await?;
                     }
-                    EmailSyncHunk::Uncache(folder, id, SyncDestination::Right) => {
+                    SingleHunk::Uncache(folder, id, SyncDestination::Right) => {
                         ctx.right_cache
                             .add_flag(&folder, &Id::single(id), Flag::Deleted)
                             .await?;
                     }
-                    EmailSyncHunk::Delete(folder, id, SyncDestination::Right) => {
+                    SingleHunk::Delete(folder, id, SyncDestination::Right) => {
                         ctx.right
                             .add_flag(&folder, &Id::single(id), Flag::Deleted)
                             .await?;
                     }
-                    EmailSyncHunk::UpdateCachedFlags(folder, envelope, SyncDestination::Left) => {
+                    SingleHunk::UpdateCachedFlags(folder, envelope, SyncDestination::Left) => {
                         ctx.left_cache
                             .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
                             .await?;
                     }
-                    EmailSyncHunk::UpdateFlags(folder, envelope, SyncDestination::Left) => {
+                    SingleHunk::UpdateFlags(folder, envelope, SyncDestination::Left) => {
                         ctx.left
                             .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
                             .await?;
                     }
-                    EmailSyncHunk::UpdateCachedFlags(folder, envelope, SyncDestination::Right) => {
+                    SingleHunk::UpdateCachedFlags(folder, envelope, SyncDestination::Right) => {
                         ctx.right_cache
                             .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
                             .await?;
                     }
-                    EmailSyncHunk::UpdateFlags(folder, envelope, SyncDestination::Right) => {
+                    SingleHunk::UpdateFlags(folder, envelope, SyncDestination::Right) => {
                         ctx.right
                             .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
                             .await?;
                     }
+                    SingleHunk::SaveConflict(folder, left, right) => {
+                        let left_msgs = ctx.left.peek_messages(&folder, &Id::single(&left.id));
+                        let right_msgs = ctx.right.peek_messages(&folder, &Id::single(&right.id));
+                        let (left_msgs, right_msgs) = tokio::try_join!(left_msgs, right_msgs)?;
+
+                        let left_msgs = left_msgs.to_vec();
+                        let left_msg = left_msgs
+                            .first()
+                            .ok_or_else(|| Error::FindMessageError(left.id.clone()))?;
+
+                        let right_msgs = right_msgs.to_vec();
+                        let right_msg = right_msgs
+                            .first()
+                            .ok_or_else(|| Error::FindMessageError(right.id.clone()))?;
+
+                        if left_msg.raw()? == right_msg.raw()? {
+                            let msg = left.to_sync_cache_msg();
+                            ctx.left_cache
+                                .add_message_with_flags(&folder, msg.as_bytes(), &left.flags)
+                                .await?;
+
+                            let msg = right.to_sync_cache_msg();
+                            ctx.right_cache
+                                .add_message_with_flags(&folder, msg.as_bytes(), &right.flags)
+                                .await?;
+                        } else {
+                            // conflicting emails are not cached, so that
+                            // the conflict is detected again until users
+                            // remove one of the versions
+                            let conflict =
+                                EmailSyncConflict::new(&conflicts_dir, &folder, &left, &right);
+                            conflict.save(left_msg.raw()?, right_msg.raw()?)?;
+
+                            if let Ok(mut conflicts) = conflicts.lock() {
+                                conflicts.push(conflict);
+                            }
+                        }
+                    }
                 };
 
                 Ok(())
@@ -369,28 +454,313 @@ pub(crate) async fn sync<L, R>(
                 .emit(&handler)
                 .await;
 
-            match output {
-                Ok(()) => (hunk, None),
-                Err(err) => (hunk, Some(err)),
-            }
+            vec![(hunk, output.err())]
         })
-    }))
-    .filter_map(|res| async {
-        match res {
-            Ok(res) => Some(res),
-            Err(err) => {
-                debug!("cannot process email hunk: {err}");
-                trace!("{err:?}");
-                None
+    });
+
+    report.patch = FuturesUnordered::from_iter(copy_tasks.chain(hunk_tasks))
+        .filter_map(|res| async {
+            match res {
+                Ok(res) => Some(res),
+                Err(err) => {
+                    debug!("cannot process email hunk: {err}");
+                    trace!("{err:?}");
+                    None
+                }
             }
-        }
-    })
-    .collect::<Vec<_>>()
-    .await;
+        })
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
 
     SyncEvent::ProcessedAllEmailHunks
         .emit(&ctx_ref.handler)
         .await;
 
+    if let Ok(mut conflicts) = conflicts.lock() {
+        report.conflicts = std::mem::take(&mut *conflicts);
+    }
+
+    if !ctx_ref.dry_run {
+        if let Ok(id_mapping) = id_mapping.lock() {
+            if let Err(err) = id_mapping.write(id_mapping_dir) {
+                warn!("cannot write email sync id mapping, ignoring it: {err}");
+                trace!("{err:?}");
+            }
+        }
+
+        if let Ok((left_contacts, right_contacts)) = contacts.into_inner() {
+            write_contacts(&left_contacts, left_contacts_dir);
+            write_contacts(&right_contacts, right_contacts_dir);
+        }
+    }
+
     Ok(report)
 }
+
+/// An [`EmailSyncHunk`] processed on its own.
+///
+/// Copies have no variant here: they are processed in batches by
+/// [`copy_then_cache`].
+enum SingleHunk {
+    GetThenCache(FolderName, String, SyncDestination),
+    UpdateCachedFlags(FolderName, Envelope, SyncDestination),
+    UpdateFlags(FolderName, Envelope, SyncDestination),
+    Uncache(FolderName, String, SyncDestination),
+    Delete(FolderName, String, SyncDestination),
+    SaveConflict(FolderName, Envelope, Envelope),
+}
+
+/// A copy of a message from a source to a target, batched with the
+/// other copies sharing the same folder, source and target.
+struct EmailSyncCopy {
+    hunk: EmailSyncHunk,
+    envelope: Envelope,
+    refresh: RefreshSourceCache,
+}
+
+/// An [`EmailSyncHunk`] split by the way it is processed.
+enum SplitHunk {
+    Copy(
+        (FolderName, SyncDestination, SyncDestination),
+        EmailSyncCopy,
+    ),
+    Single(EmailSyncHunk, SingleHunk),
+}
+
+fn split_hunk(hunk: EmailSyncHunk) -> SplitHunk {
+    let single = match hunk.clone() {
+        EmailSyncHunk::CopyThenCache(folder, envelope, source, target, refresh) => {
+            let key = (folder, source, target);
+            let copy = EmailSyncCopy {
+                hunk,
+                envelope,
+                refresh,
+            };
+            return SplitHunk::Copy(key, copy);
+        }
+        EmailSyncHunk::GetThenCache(folder, id, source) => {
+            SingleHunk::GetThenCache(folder, id, source)
+        }
+        EmailSyncHunk::UpdateCachedFlags(folder, envelope, target) => {
+            SingleHunk::UpdateCachedFlags(folder, envelope, target)
+        }
+        EmailSyncHunk::UpdateFlags(folder, envelope, target) => {
+            SingleHunk::UpdateFlags(folder, envelope, target)
+        }
+        EmailSyncHunk::Uncache(folder, id, target) => SingleHunk::Uncache(folder, id, target),
+        EmailSyncHunk::Delete(folder, id, target) => SingleHunk::Delete(folder, id, target),
+        EmailSyncHunk::SaveConflict(folder, left, right) => {
+            SingleHunk::SaveConflict(folder, left, right)
+        }
+    };
+
+    SplitHunk::Single(hunk, single)
+}
+
+/// Pairs the given copies with the result of their upload, in order.
+///
+/// Copies left without result, when the target returns fewer results
+/// than messages, are considered failed.
+fn pair_added<T>(
+    folder: &str,
+    copies: Vec<T>,
+    ids: Vec<AnyResult<SingleId>>,
+) -> Vec<(T, AnyResult<SingleId>)> {
+    let mut ids = ids.into_iter();
+
+    copies
+        .into_iter()
+        .map(|copy| {
+            let id = ids
+                .next()
+                .unwrap_or_else(|| Err(Error::AddMessagesBatchError(folder.to_owned()).into()));
+            (copy, id)
+        })
+        .collect()
+}
+
+/// Copies the messages of the given [`EmailSyncHunk::CopyThenCache`]
+/// hunks from the source to the target, then caches them.
+///
+/// All hunks share the same folder, source and target. Messages are
+/// added to the target using a single
+/// [`AddMessage::add_messages_with_flags`] call, then the ones
+/// successfully added are cached, even if others failed.
+async fn copy_then_cache<L: BackendContext, R: BackendContext>(
+    ctx: &SyncPoolContext<L, R>,
+    id_mapping: &Mutex<EmailSyncIdMapping>,
+    folder: &str,
+    source: &SyncDestination,
+    target: &SyncDestination,
+    batch: Vec<EmailSyncCopy>,
+) -> Vec<(EmailSyncHunk, Option<AnyBoxedError>)> {
+    let (source_backend, source_cache): (&dyn PeekMessages, _) = match source {
+        SyncDestination::Left => (&ctx.left, &ctx.left_cache),
+        SyncDestination::Right => (&ctx.right, &ctx.right_cache),
+    };
+
+    let (target_add, target_get, target_cache): (&dyn AddMessage, &dyn GetEnvelope, _) =
+        match target {
+            SyncDestination::Left => (&ctx.left, &ctx.left, &ctx.left_cache),
+            SyncDestination::Right => (&ctx.right, &ctx.right, &ctx.right_cache),
+        };
+
+    let fetches = join_all(batch.into_iter().map(|copy| async move {
+        let EmailSyncCopy {
+            hunk,
+            envelope,
+            refresh,
+        } = copy;
+
+        let task = async {
+            if refresh {
+                let flags = envelope.flags.clone();
+                let msg = envelope.to_sync_cache_msg();
+                source_cache
+                    .add_message_with_flags(folder, msg.as_bytes(), &flags)
+                    .await?;
+            }
+
+            let msgs = source_backend
+                .peek_messages(folder, &Id::single(&envelope.id))
+                .await?;
+            let msgs = msgs.to_vec();
+            let msg = msgs
+                .first()
+                .ok_or_else(|| Error::FindMessageError(envelope.id.clone()))?;
+
+            AnyResult::Ok(msg.raw()?.to_vec())
+        };
+
+        let output = task.await;
+        (hunk, envelope, output)
+    }))
+    .await;
+
+    let mut outputs = Vec::new();
+    let mut copies = Vec::new();
+
+    for (hunk, envelope, output) in fetches {
+        match output {
+            Ok(msg) => copies.push((hunk, envelope, msg)),
+            Err(err) => outputs.push((hunk, Some(err))),
+        }
+    }
+
+    if copies.is_empty() {
+        return outputs;
+    }
+
+    let msgs: Vec<(&[u8], &Flags)> = copies
+        .iter()
+        .map(|(_, envelope, msg)| (msg.as_slice(), &envelope.flags))
+        .collect();
+
+    let ids = match target_add.add_messages_with_flags(folder, &msgs).await {
+        Ok(ids) => ids,
+        Err(err) => {
+            // errors cannot be cloned, so only the first hunk of the
+            // batch holds the cause
+            let mut copies = copies.into_iter();
+
+            if let Some((hunk, ..)) = copies.next() {
+                outputs.push((hunk, Some(err)));
+            }
+
+            for (hunk, ..) in copies {
+                let err = Error::AddMessagesBatchError(folder.to_owned());
+                outputs.push((hunk, Some(err.into())));
+            }
+
+            return outputs;
+        }
+    };
+
+    let mut added = Vec::new();
+
+    for ((hunk, envelope, _), id) in pair_added(folder, copies, ids) {
+        match id {
+            Ok(id) => added.push((hunk, envelope, id)),
+            Err(err) => outputs.push((hunk, Some(err))),
+        }
+    }
+
+    let caches = join_all(added.into_iter().map(|(hunk, envelope, id)| async move {
+        let task = async {
+            let added = target_get.get_envelope(folder, &id).await?;
+
+            if let Ok(mut id_mapping) = id_mapping.lock() {
+                match target {
+                    SyncDestination::Left => id_mapping.insert(folder, &added.id, &envelope.id),
+                    SyncDestination::Right => id_mapping.insert(folder, &envelope.id, &added.id),
+                }
+            }
+
+            let flags = added.flags.clone();
+            let msg = added.to_sync_cache_msg();
+            target_cache
+                .add_message_with_flags(folder, msg.as_bytes(), &flags)
+                .await?;
+
+            AnyResult::Ok(())
+        };
+
+        (hunk, task.await.err())
+    }))
+    .await;
+
+    outputs.extend(caches);
+    outputs
+}
+
+/// Reads harvested contacts from the given cache root directory.
+///
+/// Contacts are not critical to the synchronization, so errors are
+/// logged and contacts are considered empty.
+fn read_contacts(root_dir: &Path) -> Contacts {
+    Contacts::read(root_dir).unwrap_or_else(|err| {
+        debug!("cannot read contacts, ignoring them: {err}");
+        trace!("{err:?}");
+        Contacts::default()
+    })
+}
+
+/// Writes harvested contacts to the given cache root directory.
+///
+/// Contacts are not critical to the synchronization, so errors are
+/// logged.
+fn write_contacts(contacts: &Contacts, root_dir: &Path) {
+    if let Err(err) = contacts.write(root_dir) {
+        debug!("cannot write contacts, ignoring them: {err}");
+        trace!("{err:?}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pair_added;
+    use crate::{email::Error, envelope::SingleId};
+
+    #[test]
+    fn pair_added_keeps_successes() {
+        let ids = vec![
+            Ok(SingleId::from("1")),
+            Err(Error::FindMessageError(String::from("b")).into()),
+            Ok(SingleId::from("3")),
+        ];
+
+        let pairs = pair_added("INBOX", vec!["a", "b", "c", "d"], ids);
+        let pairs: Vec<_> = pairs
+            .iter()
+            .map(|(copy, id)| (*copy, id.as_ref().ok().map(SingleId::as_str)))
+            .collect();
+
+        assert_eq!(
+            pairs,
+            vec![("a", Some("1")), ("b", None), ("c", Some("3")), ("d", None)]
+        );
+    }
+}
@@ -4,12 +4,18 @@
 //! structure of the module is the [`EmailSyncPatch`], which
 //! represents a list of changes (hunks).
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use super::*;
-use crate::flag;
+use crate::{
+    flag::{self, sync::config::FlagSyncPermissions},
+    message::sync::config::MessageSyncPermissions,
+};
 
 /// Alias for an envelope hash map where the key is its identifier.
+///
+/// The identifier is the Message-ID of the envelope, which is the
+/// only identifier shared by both sides. See [`into_sync_entry`].
 pub type Envelopes = HashMap<String, Envelope>;
 
 /// An email synchronization patch is just a list of email
@@ -21,7 +27,12 @@
 ///
 /// Contains the core algorithm of the email synchronization. It has
 /// been exported in a dedicated function so that it can be easily
-/// tested.
+/// tested, and reused by custom synchronization engines.
+///
+/// The function is pure: it takes the envelopes of the given folder
+/// for both sides (left and right) and their respective caches, and
+/// returns the hunks needed to bring all of them in sync. Applying
+/// hunks is up to the caller.
 pub fn build(
     folder: impl ToString,
     left_cached: Envelopes,
@@ -129,45 +140,18 @@ pub fn build(
             // 0101
             //
             // The message_id exists in both local and remote sides, which
-            // means a new (same) email has been added both sides and
-            // the most recent needs to be kept.
-            //
-            // NOTE: this case should never happen: new emails
-            // internal identifier are unique and should (in theory)
-            // never conflict, but we implement this case for the sake
-            // of exhaustiveness.
+            // means a new (same) email has been added both sides, for
+            // example when synchronizing with a fresh cache. Since we
+            // cannot determine which side is the most up-to-date, the
+            // contents of both sides are compared when applying the
+            // patch: identical emails are cached, different ones are
+            // saved as a conflict for manual resolution.
             (None, Some(local), None, Some(remote)) => {
-                if local.date > remote.date {
-                    patch.insert(vec![
-                        EmailSyncHunk::Delete(
-                            folder.to_string(),
-                            remote.id.clone(),
-                            SyncDestination::Right,
-                        ),
-                        EmailSyncHunk::CopyThenCache(
-                            folder.to_string(),
-                            local.clone(),
-                            SyncDestination::Left,
-                            SyncDestination::Right,
-                            true,
-                        ),
-                    ]);
-                } else {
-                    patch.insert(vec![
-                        EmailSyncHunk::Delete(
-                            folder.to_string(),
-                            local.id.clone(),
-                            SyncDestination::Left,
-                        ),
-                        EmailSyncHunk::CopyThenCache(
-                            folder.to_string(),
-                            remote.clone(),
-                            SyncDestination::Right,
-                            SyncDestination::Left,
-                            true,
-                        ),
-                    ]);
-                }
+                patch.insert(vec![EmailSyncHunk::SaveConflict(
+                    folder.to_string(),
+                    local.clone(),
+                    remote.clone(),
+                )]);
             }
 
             // 0110
@@ -501,6 +485,43 @@ pub fn build(
     patch
 }
 
+/// Turns the given envelope into an entry of the [`Envelopes`] map
+/// used to build the synchronization patch.
+///
+/// Custom flags (keywords) are kept: Maildir caches store them in
+/// their Dovecot keywords file.
+pub fn into_sync_entry(envelope: Envelope) -> (String, Envelope) {
+    (envelope.message_id.clone(), envelope)
+}
+
+/// Removes from the given patch the hunks not allowed by the given
+/// left and right flag and message permissions.
+pub fn apply_permissions(
+    patch: &mut BTreeSet<EmailSyncHunk>,
+    left_flag: &FlagSyncPermissions,
+    left_message: &MessageSyncPermissions,
+    right_flag: &FlagSyncPermissions,
+    right_message: &MessageSyncPermissions,
+) {
+    use EmailSyncHunk::*;
+    use SyncDestination::*;
+
+    patch.retain(|hunk| match hunk {
+        GetThenCache(_, _, Left) => left_message.create,
+        GetThenCache(_, _, Right) => right_message.create,
+        CopyThenCache(_, _, _, Left, _) => left_message.create,
+        CopyThenCache(_, _, _, Right, _) => right_message.create,
+        UpdateCachedFlags(_, _, Left) => left_flag.update,
+        UpdateCachedFlags(_, _, Right) => right_flag.update,
+        UpdateFlags(_, _, Left) => left_flag.update,
+        UpdateFlags(_, _, Right) => right_flag.update,
+        Uncache(_, _, Left) | Delete(_, _, Left) => left_message.delete,
+        Uncache(_, _, Right) | Delete(_, _, Right) => right_message.delete,
+        // conflicts only touch caches and the conflicts directory
+        SaveConflict(_, _, _) => true,
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::{EmailSyncHunk, EmailSyncPatch, Envelopes};
@@ -695,188 +716,44 @@ fn build_patch_0100() {
     #[test]
     fn build_patch_0101() {
         let local_cache = Envelopes::default();
-        let local = Envelopes::from_iter([
-            (
-                "message_id-1".into(),
-                Envelope {
-                    id: "local-id-1".into(),
-                    flags: "seen".into(),
-                    date: "2022-01-01T00:00:00-00:00".parse().unwrap(),
-                    ..Envelope::default()
-                },
-            ),
-            (
-                "message_id-2".into(),
-                Envelope {
-                    id: "local-id-2".into(),
-                    flags: "seen".into(),
-                    date: "2022-01-01T00:00:00-00:00".parse().unwrap(),
-                    ..Envelope::default()
-                },
-            ),
-            (
-                "message_id-3".into(),
-                Envelope {
-                    id: "local-id-3".into(),
-                    flags: "seen".into(),
-                    ..Envelope::default()
-                },
-            ),
-            (
-                "message_id-4".into(),
-                Envelope {
-                    id: "local-id-4".into(),
-                    flags: "seen".into(),
-                    ..Envelope::default()
-                },
-            ),
-            (
-                "message_id-5".into(),
-                Envelope {
-                    id: "local-id-5".into(),
-                    flags: "seen".into(),
-                    date: "2022-01-01T00:00:00-00:00".parse().unwrap(),
-                    ..Envelope::default()
-                },
-            ),
-        ]);
+        let local = Envelopes::from_iter([(
+            "message_id".into(),
+            Envelope {
+                id: "local-id".into(),
+                flags: "seen".into(),
+                date: "2022-01-01T00:00:00-00:00".parse().unwrap(),
+                ..Envelope::default()
+            },
+        )]);
         let remote_cache = Envelopes::default();
-        let remote = Envelopes::from_iter([
-            (
-                "message_id-1".into(),
-                Envelope {
-                    id: "remote-id-1".into(),
-                    flags: "seen".into(),
-                    ..Envelope::default()
-                },
-            ),
-            (
-                "message_id-2".into(),
-                Envelope {
-                    id: "remote-id-2".into(),
-                    flags: "seen".into(),
-                    date: "2021-01-01T00:00:00-00:00".parse().unwrap(),
-                    ..Envelope::default()
-                },
-            ),
-            (
-                "message_id-3".into(),
-                Envelope {
-                    id: "remote-id-3".into(),
-                    flags: "seen".into(),
-                    ..Envelope::default()
-                },
-            ),
-            (
-                "message_id-4".into(),
+        let remote = Envelopes::from_iter([(
+            "message_id".into(),
+            Envelope {
+                id: "remote-id".into(),
+                flags: "flagged".into(),
+                date: "2021-01-01T00:00:00-00:00".parse().unwrap(),
+                ..Envelope::default()
+            },
+        )]);
+
+        assert_eq!(
+            super::build("inbox", local_cache, local, remote_cache, remote),
+            EmailSyncPatch::from_iter([vec![EmailSyncHunk::SaveConflict(
+                "inbox".into(),
                 Envelope {
-                    id: "remote-id-4".into(),
+                    id: "local-id".into(),
                     flags: "seen".into(),
                     date: "2022-01-01T00:00:00-00:00".parse().unwrap(),
                     ..Envelope::default()
                 },
-            ),
-            (
-                "message_id-5".into(),
                 Envelope {
-                    id: "remote-id-5".into(),
-                    flags: "seen".into(),
-                    date: "2022-01-01T00:00:00-00:00".parse().unwrap(),
+                    id: "remote-id".into(),
+                    flags: "flagged".into(),
+                    date: "2021-01-01T00:00:00-00:00".parse().unwrap(),
                     ..Envelope::default()
                 },
-            ),
-        ]);
-
-        let patch = super::build("inbox", local_cache, local, remote_cache, remote)
-            .into_iter()
-            .flatten()
-            .collect::<Vec<_>>();
-
-        assert_eq!(patch.len(), 10);
-        assert!(patch.contains(&EmailSyncHunk::Delete(
-            "inbox".into(),
-            "remote-id-1".into(),
-            SyncDestination::Right
-        )));
-        assert!(patch.contains(&EmailSyncHunk::CopyThenCache(
-            "inbox".into(),
-            Envelope {
-                id: "local-id-1".into(),
-                flags: "seen".into(),
-                date: "2022-01-01T00:00:00-00:00".parse().unwrap(),
-                ..Envelope::default()
-            },
-            SyncDestination::Left,
-            SyncDestination::Right,
-            true,
-        )));
-        assert!(patch.contains(&EmailSyncHunk::Delete(
-            "inbox".into(),
-            "remote-id-2".into(),
-            SyncDestination::Right
-        )));
-        assert!(patch.contains(&EmailSyncHunk::CopyThenCache(
-            "inbox".into(),
-            Envelope {
-                id: "local-id-2".into(),
-                flags: "seen".into(),
-                date: "2022-01-01T00:00:00-00:00".parse().unwrap(),
-                ..Envelope::default()
-            },
-            SyncDestination::Left,
-            SyncDestination::Right,
-            true,
-        )));
-        assert!(patch.contains(&EmailSyncHunk::Delete(
-            "inbox".into(),
-            "local-id-3".into(),
-            SyncDestination::Left
-        )));
-        assert!(patch.contains(&EmailSyncHunk::CopyThenCache(
-            "inbox".into(),
-            Envelope {
-                id: "remote-id-3".into(),
-                flags: "seen".into(),
-                ..Envelope::default()
-            },
-            SyncDestination::Right,
-            SyncDestination::Left,
-            true,
-        )));
-        assert!(patch.contains(&EmailSyncHunk::Delete(
-            "inbox".into(),
-            "local-id-4".into(),
-            SyncDestination::Left
-        )));
-        assert!(patch.contains(&EmailSyncHunk::CopyThenCache(
-            "inbox".into(),
-            Envelope {
-                id: "remote-id-4".into(),
-                flags: "seen".into(),
-                date: "2022-01-01T00:00:00-00:00".parse().unwrap(),
-                ..Envelope::default()
-            },
-            SyncDestination::Right,
-            SyncDestination::Left,
-            true,
-        )));
-        assert!(patch.contains(&EmailSyncHunk::Delete(
-            "inbox".into(),
-            "local-id-5".into(),
-            SyncDestination::Left
-        )));
-        assert!(patch.contains(&EmailSyncHunk::CopyThenCache(
-            "inbox".into(),
-            Envelope {
-                id: "remote-id-5".into(),
-                flags: "seen".into(),
-                date: "2022-01-01T00:00:00-00:00".parse().unwrap(),
-                ..Envelope::default()
-            },
-            SyncDestination::Right,
-            SyncDestination::Left,
-            true,
-        )));
+            )]]),
+        );
     }
 
     #[test]
@@ -1285,4 +1162,46 @@ fn build_patch_1110() {
             ])
         );
     }
+
+    #[test]
+    fn build_patch_1111_keywords() {
+        let envelope = |id: &str, flags: &Flags| Envelope {
+            id: id.into(),
+            message_id: "message_id".into(),
+            flags: flags.clone(),
+            ..Envelope::default()
+        };
+
+        let seen = Flags::from_iter([Flag::Seen]);
+        let labelled = Flags::from_iter([Flag::Seen, Flag::custom("receipts")]);
+
+        let local_cache =
+            Envelopes::from_iter([super::into_sync_entry(envelope("local-cache-id", &seen))]);
+        let local = Envelopes::from_iter([super::into_sync_entry(envelope("local-id", &seen))]);
+        let remote_cache =
+            Envelopes::from_iter([super::into_sync_entry(envelope("remote-cache-id", &seen))]);
+        let remote =
+            Envelopes::from_iter([super::into_sync_entry(envelope("remote-id", &labelled))]);
+
+        assert_eq!(
+            super::build("inbox", local_cache, local, remote_cache, remote),
+            EmailSyncPatch::from_iter([
+                vec![EmailSyncHunk::UpdateCachedFlags(
+                    "inbox".into(),
+                    envelope("local-cache-id", &labelled),
+                    SyncDestination::Left,
+                )],
+                vec![EmailSyncHunk::UpdateFlags(
+                    "inbox".into(),
+                    envelope("local-id", &labelled),
+                    SyncDestination::Left,
+                )],
+                vec![EmailSyncHunk::UpdateCachedFlags(
+                    "inbox".into(),
+                    envelope("remote-cache-id", &labelled),
+                    SyncDestination::Right,
+                )],
+            ])
+        );
+    }
 }
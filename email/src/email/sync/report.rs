@@ -3,7 +3,7 @@
 //! Module dedicated to email synchronization reporting. The main
 //! structure of this module is [`EmailSyncReport`].
 
-use super::hunk::EmailSyncHunk;
+use super::{conflict::EmailSyncConflict, hunk::EmailSyncHunk};
 use crate::AnyBoxedError;
 
 /// The email synchronization report.
@@ -11,4 +11,7 @@
 pub struct EmailSyncReport {
     /// The list of processed hunks associated with an optional error.
     pub patch: Vec<(EmailSyncHunk, Option<AnyBoxedError>)>,
+
+    /// The list of conflicts saved for manual resolution.
+    pub conflicts: Vec<EmailSyncConflict>,
 }
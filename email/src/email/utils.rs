@@ -1,19 +1,37 @@
 //! Module dedicated to email utils.
 
-use std::{env, fs, io, path::PathBuf};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
 
 use tracing::debug;
 
+use crate::account::runtime::RuntimeDir;
+
 /// Gets the local draft file path.
-pub fn local_draft_path() -> PathBuf {
-    let path = env::temp_dir().join("himalaya-draft.eml");
+///
+/// The draft is located in the global runtime directory, either the
+/// given one or the default one (see [`RuntimeDir::global`]). Prefer
+/// [`crate::account::config::AccountConfig::get_local_draft_path`]
+/// when an account is available.
+pub fn local_draft_path(runtime_dir: Option<&Path>) -> PathBuf {
+    let path = RuntimeDir::global(runtime_dir)
+        .map(|dir| dir.join("draft.eml"))
+        .unwrap_or_else(|_err| {
+            debug!("cannot get global runtime directory: {_err}");
+            debug!("{_err:?}");
+            env::temp_dir().join("himalaya-draft.eml")
+        });
     debug!("local draft path: {}", path.display());
     path
 }
 
 /// Removes the local draft.
-pub fn remove_local_draft() -> io::Result<()> {
-    let path = local_draft_path();
+///
+/// See [`local_draft_path`].
+pub fn remove_local_draft(runtime_dir: Option<&Path>) -> io::Result<()> {
+    let path = local_draft_path(runtime_dir);
     fs::remove_file(path)?;
     Ok(())
 }
@@ -1,4 +1,4 @@
-use std::{any::Any, error, result};
+use std::{any::Any, error, fmt, result};
 
 use tokio::task::JoinError;
 
@@ -16,6 +16,73 @@
 /// features.
 pub trait AnyError: error::Error + Any + Send + Sync {
     fn as_any(&self) -> &dyn Any;
+
+    /// Returns the kind of the error.
+    ///
+    /// Defaults to [`ErrorKind::Other`].
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// The kind of an error.
+///
+/// Module errors are specific to their backend or feature. This
+/// taxonomy classifies them into broader categories, so they can be
+/// handled programmatically without matching every single variant.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The error is caused by an authentication failure: invalid
+    /// credentials, missing secret, OAuth 2.0 failure etc.
+    Auth,
+
+    /// The error is caused by the network: connection failure,
+    /// request timeout etc.
+    Network,
+
+    /// The error is caused by a missing resource: account, folder,
+    /// envelope, message etc.
+    NotFound,
+
+    /// The error is caused by a conflicting state: resource already
+    /// exists, lock already taken etc.
+    Conflict,
+
+    /// The error is caused by an unexpected server response or by a
+    /// malformed input.
+    Protocol,
+
+    /// The error is caused by an invalid or incomplete
+    /// configuration.
+    Config,
+
+    /// The error does not fit in any other kind.
+    Other,
+}
+
+impl ErrorKind {
+    /// Returns the stable code of the error kind.
+    ///
+    /// Codes are suitable for logs, exit codes mapping or
+    /// serialization.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Auth => "auth",
+            Self::Network => "network",
+            Self::NotFound => "not-found",
+            Self::Conflict => "conflict",
+            Self::Protocol => "protocol",
+            Self::Config => "config",
+            Self::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
 }
 
 impl AnyError for JoinError {
@@ -38,3 +105,21 @@ fn from(err: JoinError) -> Self {
         Box::new(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use crate::{backend, folder, AnyBoxedError, ErrorKind};
+
+    #[test]
+    fn kind_through_boxed_errors() {
+        let err: AnyBoxedError = backend::Error::ListFoldersNotAvailableError.into();
+        assert_eq!(err.kind(), ErrorKind::Config);
+
+        let err: AnyBoxedError = folder::Error::ListLeftFoldersError(err).into();
+        assert_eq!(err.kind(), ErrorKind::Config);
+        assert_eq!(err.kind().code(), "config");
+        assert!(err.source().is_some());
+    }
+}
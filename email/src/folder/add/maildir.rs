@@ -30,8 +30,7 @@ async fn add_folder(&self, folder: &str) -> AnyResult<()> {
         let ctx = self.ctx.lock().await;
         let config = &ctx.account_config;
 
-        ctx.root
-            .create(config.get_folder_alias(folder))
+        ctx.create_folder(&config.get_folder_alias(folder))
             .map_err(|e| Error::CreateFolderStructureMaildirError(e, ctx.root.path().to_owned()))?;
 
         Ok(())
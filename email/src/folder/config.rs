@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
-use super::list::config::FolderListConfig;
 #[cfg(feature = "sync")]
 use super::sync::config::FolderSyncConfig;
+use super::{list::config::FolderListConfig, retention::config::FolderRetentionConfig};
 
 /// The folder configuration.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -27,4 +27,7 @@ pub struct FolderConfig {
     #[cfg(feature = "sync")]
     /// The configuration dedicated to folder synchronization.
     pub sync: Option<FolderSyncConfig>,
+
+    /// The configuration dedicated to folder retention.
+    pub retention: Option<FolderRetentionConfig>,
 }
@@ -0,0 +1,306 @@
+//! # Folder de-duplication
+//!
+//! Module dedicated to folder de-duplication. Duplicate messages
+//! (left by double imports or interrupted synchronizations for
+//! example) are grouped by Message-ID or by content hash. One message
+//! per group is kept according to a [`DedupeKeep`] strategy, and an
+//! action is applied to the other ones.
+
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    hash::{Hash, Hasher},
+};
+
+use async_trait::async_trait;
+use tracing::{debug, info};
+
+use crate::{
+    envelope::{
+        list::{ListEnvelopes, ListEnvelopesOptions},
+        Envelope, Id,
+    },
+    flag::{add::AddFlags, Flag},
+    message::{delete::DeleteMessages, peek::PeekMessages, remove::RemoveMessages},
+    AnyResult,
+};
+
+/// The key used to group duplicate messages.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DedupeKey {
+    /// Messages sharing the same `Message-ID` header are duplicates.
+    ///
+    /// Messages without `Message-ID` are never considered as
+    /// duplicates.
+    #[default]
+    MessageId,
+
+    /// Messages sharing the same content are duplicates.
+    ///
+    /// The content hash is computed from the body and the `From`,
+    /// `To`, `Cc`, `Subject` and `Date` headers, so that headers
+    /// added during delivery (like `Received`) are ignored. Computing
+    /// it requires to fetch every message of the folder.
+    ContentHash,
+}
+
+/// The strategy used to choose the message kept in a group of
+/// duplicates.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DedupeKeep {
+    /// Keep the message with the oldest date.
+    #[default]
+    Oldest,
+
+    /// Keep the message with the newest date.
+    Newest,
+
+    /// Keep the message with the most flags, so that flags like
+    /// answered or flagged are not lost. Ties are broken by keeping
+    /// the oldest message.
+    MostFlags,
+}
+
+/// The action applied to duplicate messages.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum DedupeAction {
+    /// Add the given flag to duplicates, for manual review.
+    Flag(Flag),
+
+    /// Delete duplicates.
+    ///
+    /// See [`DeleteMessages`] for the semantic of deletion.
+    #[default]
+    Delete,
+
+    /// Definitely remove duplicates.
+    Remove,
+}
+
+/// The de-duplication strategy.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DedupeStrategy {
+    /// The key used to group duplicates.
+    pub key: DedupeKey,
+
+    /// The strategy used to choose the message to keep.
+    pub keep: DedupeKeep,
+
+    /// The action applied to duplicates.
+    pub action: DedupeAction,
+
+    /// Find duplicates without applying any action.
+    pub dry_run: bool,
+}
+
+/// A group of duplicate messages.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DedupeGroup {
+    /// The key shared by messages of the group.
+    pub key: String,
+
+    /// The id of the message kept.
+    pub kept: String,
+
+    /// The ids of the duplicate messages.
+    pub duplicates: Vec<String>,
+}
+
+/// The de-duplication report.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DedupeReport {
+    /// The groups of duplicate messages found.
+    pub groups: Vec<DedupeGroup>,
+
+    /// Whether the action has been applied to duplicates.
+    pub applied: bool,
+}
+
+impl DedupeReport {
+    /// Returns the ids of all duplicate messages.
+    pub fn duplicates(&self) -> Vec<String> {
+        self.groups
+            .iter()
+            .flat_map(|group| group.duplicates.iter().cloned())
+            .collect()
+    }
+}
+
+/// Feature to de-duplicate messages of a folder.
+///
+/// This feature is implemented for any backend exposing the
+/// underlying features.
+#[async_trait]
+pub trait DedupeFolder:
+    ListEnvelopes + PeekMessages + AddFlags + DeleteMessages + RemoveMessages
+{
+    /// Find duplicate messages of the given folder, then apply the
+    /// action of the given strategy to them.
+    async fn dedupe_folder(
+        &self,
+        folder: &str,
+        strategy: &DedupeStrategy,
+    ) -> AnyResult<DedupeReport> {
+        info!("de-duplicating folder {folder}");
+
+        let envelopes = self
+            .list_envelopes(folder, ListEnvelopesOptions::default())
+            .await?;
+        debug!("found {} envelopes", envelopes.len());
+
+        let mut groups: BTreeMap<String, Vec<Envelope>> = BTreeMap::new();
+
+        for envelope in envelopes {
+            let key = match strategy.key {
+                DedupeKey::MessageId => message_id_key(&envelope),
+                DedupeKey::ContentHash => {
+                    let msgs = self
+                        .peek_messages(folder, &Id::single(&envelope.id))
+                        .await?;
+                    msgs.first()
+                        .and_then(|msg| content_hash_key(msg.raw().ok()?))
+                }
+            };
+
+            if let Some(key) = key {
+                groups.entry(key).or_default().push(envelope);
+            }
+        }
+
+        let groups: Vec<DedupeGroup> = groups
+            .into_iter()
+            .filter(|(_, envelopes)| envelopes.len() > 1)
+            .map(|(key, envelopes)| group_duplicates(key, envelopes, strategy.keep))
+            .collect();
+
+        let mut report = DedupeReport {
+            groups,
+            applied: false,
+        };
+
+        let duplicates = report.duplicates();
+        debug!("found {} duplicates", duplicates.len());
+
+        if strategy.dry_run || duplicates.is_empty() {
+            return Ok(report);
+        }
+
+        let id = Id::multiple(duplicates);
+
+        match &strategy.action {
+            DedupeAction::Flag(flag) => self.add_flag(folder, &id, flag.clone()).await?,
+            DedupeAction::Delete => self.delete_messages(folder, &id).await?,
+            DedupeAction::Remove => self.remove_messages(folder, &id).await?,
+        }
+
+        report.applied = true;
+        Ok(report)
+    }
+}
+
+impl<T> DedupeFolder for T where
+    T: ListEnvelopes + PeekMessages + AddFlags + DeleteMessages + RemoveMessages
+{
+}
+
+/// Returns the Message-ID of the given envelope, unless it has been
+/// generated because the message does not have any.
+fn message_id_key(envelope: &Envelope) -> Option<String> {
+    let mid = envelope.message_id.trim();
+
+    if mid.is_empty() || mid == "<>" || mid.ends_with("@generated>") {
+        None
+    } else {
+        Some(mid.to_owned())
+    }
+}
+
+/// Returns the content hash of the given raw message.
+fn content_hash_key(raw: &[u8]) -> Option<String> {
+    let msg = mail_parser::MessageParser::new().parse(raw)?;
+    let mut hasher = DefaultHasher::new();
+
+    for name in ["From", "To", "Cc", "Subject", "Date"] {
+        msg.header_raw(name).map(str::trim).hash(&mut hasher);
+    }
+
+    let body_offset = msg.root_part().raw_body_offset();
+    raw.get(body_offset..).unwrap_or_default().hash(&mut hasher);
+
+    Some(format!("{:x}", hasher.finish()))
+}
+
+/// Splits the given group of envelopes into the kept envelope and
+/// its duplicates, according to the given strategy.
+fn group_duplicates(key: String, mut envelopes: Vec<Envelope>, keep: DedupeKeep) -> DedupeGroup {
+    envelopes.sort_by(|a, b| {
+        let cmp = match keep {
+            DedupeKeep::Oldest => a.date.cmp(&b.date),
+            DedupeKeep::Newest => b.date.cmp(&a.date),
+            DedupeKeep::MostFlags => b.flags.len().cmp(&a.flags.len()).then(a.date.cmp(&b.date)),
+        };
+
+        cmp.then_with(|| a.id.cmp(&b.id))
+    });
+
+    let mut ids = envelopes.into_iter().map(|envelope| envelope.id);
+    let kept = ids.next().unwrap_or_default();
+
+    DedupeGroup {
+        key,
+        kept,
+        duplicates: ids.collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{content_hash_key, group_duplicates, message_id_key, DedupeKeep};
+    use crate::{
+        envelope::Envelope,
+        flag::{Flag, Flags},
+    };
+
+    fn envelope(id: &str, date: &str, flags: &[Flag]) -> Envelope {
+        Envelope {
+            id: id.into(),
+            message_id: "<a@localhost>".into(),
+            date: date.parse().unwrap(),
+            flags: Flags::from_iter(flags.iter().cloned()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn keep_strategies() {
+        let envelopes = vec![
+            envelope("1", "2024-01-02T10:00:00+00:00", &[]),
+            envelope("2", "2024-01-01T10:00:00+00:00", &[]),
+            envelope("3", "2024-01-03T10:00:00+00:00", &[Flag::Answered]),
+        ];
+
+        let group = group_duplicates("key".into(), envelopes.clone(), DedupeKeep::Oldest);
+        assert_eq!(group.kept, "2");
+        assert_eq!(group.duplicates, vec!["1", "3"]);
+
+        let group = group_duplicates("key".into(), envelopes.clone(), DedupeKeep::Newest);
+        assert_eq!(group.kept, "3");
+
+        let group = group_duplicates("key".into(), envelopes, DedupeKeep::MostFlags);
+        assert_eq!(group.kept, "3");
+        assert_eq!(group.duplicates, vec!["2", "1"]);
+    }
+
+    #[test]
+    fn keys() {
+        let mut envelope = envelope("1", "2024-01-01T10:00:00+00:00", &[]);
+        assert_eq!(message_id_key(&envelope).unwrap(), "<a@localhost>");
+        envelope.message_id = "<1a2b@generated>".into();
+        assert_eq!(message_id_key(&envelope), None);
+
+        let a = content_hash_key(b"Received: a\r\nSubject: test\r\n\r\nbody").unwrap();
+        let b = content_hash_key(b"Received: b\r\nSubject: test\r\n\r\nbody").unwrap();
+        let c = content_hash_key(b"Received: a\r\nSubject: test\r\n\r\nother").unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+}
@@ -30,17 +30,16 @@ impl DeleteFolder for DeleteMaildirFolder {
     async fn delete_folder(&self, folder: &str) -> AnyResult<()> {
         let ctx = self.ctx.lock().await;
         let config = &ctx.account_config;
-        let maildirpp = ctx.maildir_config.maildirpp;
+        let inbox_root = ctx.is_inbox_root();
 
         let folder = config.get_folder_alias(folder);
 
-        if maildirpp && FolderKind::matches_inbox(&folder) {
+        if inbox_root && FolderKind::matches_inbox(&folder) {
             let path = ctx.root.path().to_owned();
             return Err(Error::DeleteMaildirInboxForbiddenError(path).into());
         }
 
-        ctx.root
-            .remove(&folder)
+        ctx.remove_folder(&folder)
             .map_err(|err| Error::DeleteMaildirFolderError(err, folder))?;
 
         Ok(())
@@ -0,0 +1,51 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::DeleteFolder;
+use crate::{
+    folder::{error::Error, FolderKind},
+    notmuch::NotmuchContextSync,
+    AnyResult,
+};
+
+pub struct DeleteNotmuchFolder {
+    ctx: NotmuchContextSync,
+}
+
+impl DeleteNotmuchFolder {
+    pub fn new(ctx: &NotmuchContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NotmuchContextSync) -> Box<dyn DeleteFolder> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NotmuchContextSync) -> Option<Box<dyn DeleteFolder>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl DeleteFolder for DeleteNotmuchFolder {
+    async fn delete_folder(&self, folder: &str) -> AnyResult<()> {
+        info!("deleting notmuch folder {folder} via maildir");
+
+        let ctx = self.ctx.lock().await;
+        let mdir_ctx = &ctx.mdir_ctx;
+        let folder = ctx.find_maildir_folder(folder);
+
+        if mdir_ctx.is_inbox_root() && FolderKind::matches_inbox(&folder) {
+            let path = mdir_ctx.root.path().to_owned();
+            return Err(Error::DeleteMaildirInboxForbiddenError(path).into());
+        }
+
+        mdir_ctx
+            .remove_folder(&folder)
+            .map_err(|err| Error::DeleteMaildirFolderError(err, folder))?;
+
+        ctx.reindex().await?;
+
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@
 use thiserror::Error;
 use tokio::task::JoinError;
 
-use crate::{AnyBoxedError, AnyError};
+use crate::{AnyBoxedError, AnyError, ErrorKind};
 
 /// The global `Result` alias of the module.
 pub type Result<T> = result::Result<T, Error>;
@@ -29,6 +29,14 @@ pub enum Error {
     #[cfg(feature = "maildir")]
     #[error("cannot remove maildir entry at {1}")]
     RemoveMaildirEntryError(#[source] maildirs::Error, std::path::PathBuf),
+    #[cfg(feature = "notmuch")]
+    #[error("cannot expunge notmuch folder {1}")]
+    ExpungeNotmuchFolderError(#[source] notmuch::Error, String),
+    #[cfg(feature = "notmuch")]
+    #[error("cannot remove notmuch message file at {1}")]
+    RemoveNotmuchFileError(#[source] std::io::Error, std::path::PathBuf),
+    #[error("cannot format retention target folder from pattern {0}")]
+    FormatRetentionTargetError(String),
     #[error("cannot parse folder kind {0}")]
     ParseFolderKindError(String),
     #[error("cannot get uid of imap folder {0}: uid is missing")]
@@ -44,6 +52,10 @@ pub enum Error {
     ListRightFoldersCachedError(#[source] AnyBoxedError),
     #[error("cannot sync: cannot list folders from right backend")]
     ListRightFoldersError(#[source] AnyBoxedError),
+    #[error("cannot read folder tombstones at {1}")]
+    ReadFolderTombstonesError(#[source] std::io::Error, std::path::PathBuf),
+    #[error("cannot write folder tombstones at {1}")]
+    WriteFolderTombstonesError(#[source] std::io::Error, std::path::PathBuf),
 
     // ======== v2
     #[error("cannot parse IMAP mailbox {0}: mailbox not selectable")]
@@ -54,10 +66,36 @@ pub enum Error {
     MaildirsError(#[from] maildirs::Error),
 }
 
+impl Error {
+    /// Returns the kind of the error.
+    ///
+    /// See [`ErrorKind`] for the available kinds.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::ListLeftFoldersCachedError(err)
+            | Self::ListLeftFoldersError(err)
+            | Self::ListRightFoldersCachedError(err)
+            | Self::ListRightFoldersError(err) => err.kind(),
+            #[cfg(feature = "maildir")]
+            Self::DeleteMaildirInboxForbiddenError(_) => ErrorKind::Conflict,
+            Self::ParseFolderKindError(_) | Self::ParseImapFolderNotSelectableError(_) => {
+                ErrorKind::Protocol
+            }
+            Self::GetUidMissingImapError(_) => ErrorKind::NotFound,
+            Self::FormatRetentionTargetError(_) => ErrorKind::Config,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
 impl AnyError for Error {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn kind(&self) -> ErrorKind {
+        Error::kind(self)
+    }
 }
 
 impl From<Error> for AnyBoxedError {
@@ -0,0 +1,89 @@
+use std::fs;
+
+use async_trait::async_trait;
+use tracing::{debug, info};
+
+use super::ExpungeFolder;
+use crate::{
+    folder::{error::Error, FolderKind},
+    notmuch::NotmuchContextSync,
+    AnyResult,
+};
+
+pub struct ExpungeNotmuchFolder {
+    ctx: NotmuchContextSync,
+}
+
+impl ExpungeNotmuchFolder {
+    pub fn new(ctx: &NotmuchContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NotmuchContextSync) -> Box<dyn ExpungeFolder> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NotmuchContextSync) -> Option<Box<dyn ExpungeFolder>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl ExpungeFolder for ExpungeNotmuchFolder {
+    async fn expunge_folder(&self, folder: &str) -> AnyResult<()> {
+        info!("expunging notmuch folder {folder} via maildir");
+
+        let ctx = self.ctx.lock().await;
+        let folder = ctx.find_maildir_folder(folder);
+        let mdir_path = ctx
+            .mdir_ctx
+            .get_maildir_from_folder(&folder)?
+            .path()
+            .to_owned();
+
+        let folder_query = if ctx.maildirpp() && FolderKind::matches_inbox(&folder) {
+            String::from("folder:\"\"")
+        } else {
+            format!("folder:{folder:?}")
+        };
+
+        let deleted = &ctx.notmuch_config.tags.deleted;
+        let deleted_query = if deleted.negated {
+            format!("not tag:{:?}", deleted.name)
+        } else {
+            format!("tag:{:?}", deleted.name)
+        };
+
+        let query = format!("{folder_query} and {deleted_query}");
+        debug!("notmuch query: {query:?}");
+
+        // NOTE: the database is not `Send`, so it needs to be closed
+        // before re-indexing.
+        {
+            let db = ctx.open_db()?;
+            let map_err = |err| Error::ExpungeNotmuchFolderError(err, folder.clone());
+
+            let query_builder = db.create_query(&query).map_err(map_err)?;
+            let msgs = query_builder.search_messages().map_err(map_err)?;
+
+            for msg in msgs {
+                // NOTE: a message may have multiple files, only the
+                // ones located in the expunged folder are removed.
+                for path in msg.filenames() {
+                    if path.parent().and_then(|dir| dir.parent()) != Some(mdir_path.as_path()) {
+                        continue;
+                    }
+
+                    fs::remove_file(&path)
+                        .map_err(|err| Error::RemoveNotmuchFileError(err, path))?;
+                }
+            }
+
+            db.close().map_err(map_err)?;
+        }
+
+        ctx.reindex().await?;
+
+        Ok(())
+    }
+}
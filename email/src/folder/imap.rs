@@ -4,25 +4,36 @@
     mailbox::Mailbox,
 };
 use tracing::debug;
-use utf7_imap::decode_utf7_imap as decode_utf7;
 
 use super::{Error, FolderKind, Result};
 use crate::{
     account::config::AccountConfig,
-    folder::{Folder, Folders},
+    folder::{namespace::Namespaces, Folder, Folders},
+    imap::encoding::MailboxEncoding,
 };
 
 pub type ImapMailboxes = Vec<ImapMailbox>;
 
 impl Folders {
-    pub fn from_imap_mailboxes(config: &AccountConfig, mboxes: ImapMailboxes) -> Self {
+    /// Builds folders from IMAP mailboxes.
+    ///
+    /// Folder names are decoded using the given mailbox encoding,
+    /// then made relative to the personal namespace.
+    pub fn from_imap_mailboxes(
+        config: &AccountConfig,
+        namespaces: &Namespaces,
+        encoding: MailboxEncoding,
+        mboxes: ImapMailboxes,
+    ) -> Self {
         mboxes
             .into_iter()
-            .filter_map(|mbox| match Folder::try_from_imap_mailbox(config, &mbox) {
-                Ok(folder) => Some(folder),
-                Err(_err) => {
-                    debug!("skipping IMAP mailbox {:?}: {_err}", mbox.0.clone());
-                    None
+            .filter_map(|mbox| {
+                match Folder::try_from_imap_mailbox(config, namespaces, encoding, &mbox) {
+                    Ok(folder) => Some(folder),
+                    Err(_err) => {
+                        debug!("skipping IMAP mailbox {:?}: {_err}", mbox.0.clone());
+                        None
+                    }
                 }
             })
             .collect()
@@ -38,6 +49,8 @@ pub fn from_imap_mailboxes(config: &AccountConfig, mboxes: ImapMailboxes) -> Sel
 impl Folder {
     fn try_from_imap_mailbox(
         config: &AccountConfig,
+        namespaces: &Namespaces,
+        encoding: MailboxEncoding,
         (mbox, _delim, attrs): &ImapMailbox,
     ) -> Result<Self> {
         let mbox = match mbox {
@@ -51,7 +64,7 @@ fn try_from_imap_mailbox(
             return Err(Error::ParseImapFolderNotSelectableError(mbox.clone()));
         }
 
-        let name = decode_utf7(mbox.into());
+        let name = namespaces.to_folder(encoding.decode(mbox));
 
         let kind = config
             .find_folder_kind_from_alias(&name)
@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use tracing::info;
 
-use super::{Folders, ListFolders};
+use super::{Folders, ListFolders, Namespaces};
 use crate::{imap::ImapContext, AnyResult};
 
 #[derive(Debug, Clone)]
@@ -35,4 +35,9 @@ async fn list_folders(&self) -> AnyResult<Folders> {
 
         Ok(folders)
     }
+
+    async fn list_namespaces(&self) -> AnyResult<Namespaces> {
+        info!("listing imap namespaces");
+        Ok(self.ctx.namespaces().clone())
+    }
 }
@@ -8,11 +8,20 @@
 
 use async_trait::async_trait;
 
-use super::Folders;
+use super::{namespace::Namespaces, Folders};
 use crate::AnyResult;
 
 #[async_trait]
 pub trait ListFolders: Send + Sync {
     /// List all available folders (alias mailboxes).
     async fn list_folders(&self) -> AnyResult<Folders>;
+
+    /// List the namespaces folders belong to.
+    ///
+    /// Folder names are relative to the personal namespace. The
+    /// default implementation returns no namespace, which means
+    /// that folders live at the root level.
+    async fn list_namespaces(&self) -> AnyResult<Namespaces> {
+        Ok(Namespaces::default())
+    }
 }
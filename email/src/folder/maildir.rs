@@ -3,6 +3,8 @@
 //! This module contains folder-related mapping functions from the
 //! [maildirpp] crate types.
 
+use std::path::PathBuf;
+
 use maildirs::Maildir;
 
 use crate::{
@@ -16,17 +18,28 @@
 impl Folders {
     /// Parse folders from submaildirs.
     ///
-    /// Folders are parsed in parallel, using [`rayon`]. Only parses
-    /// direct submaildirs (no recursion).
+    /// Submaildirs are discovered according to the layout of the
+    /// Maildir tree, see [`MaildirLayout`](crate::maildir::layout::MaildirLayout).
+    /// Without layout, only direct submaildirs are parsed (no
+    /// recursion).
     pub fn from_maildir_context(ctx: &MaildirContext) -> Self {
-        Folders::from_iter(ctx.root.iter().map(|entry| {
+        let folders: Vec<(String, PathBuf)> = match ctx.layout() {
+            Some(layout) => layout.list_folders(ctx.root.path()),
+            None => ctx
+                .root
+                .iter()
+                .map(|entry| (entry.name, entry.maildir.path().to_owned()))
+                .collect(),
+        };
+
+        Folders::from_iter(folders.into_iter().map(|(name, path)| {
             Folder {
                 kind: ctx
                     .account_config
-                    .find_folder_kind_from_alias(&entry.name)
-                    .or_else(|| entry.name.parse().ok()),
-                name: entry.name,
-                desc: entry.maildir.path().display().to_string(),
+                    .find_folder_kind_from_alias(&name)
+                    .or_else(|| name.parse().ok()),
+                name,
+                desc: path.display().to_string(),
             }
         }))
     }
@@ -10,10 +10,18 @@
 //! Backend features reside in their own module as well: [`add`],
 //! [`list`], [`expunge`], [`purge`], [`delete`].
 //!
+//! The [`retention`] module applies retention rules to folders, like
+//! archiving or deleting old messages, and the [`dedupe`] module
+//! removes duplicate messages from folders.
+//!
+//! The [`namespace`] module exposes the namespaces folders belong
+//! to, see [`ListFolders::list_namespaces`](list::ListFolders::list_namespaces).
+//!
 //! Finally, the [`sync`] module contains everything needed to
 //! synchronize a remote folder with a local one.
 pub mod add;
 pub mod config;
+pub mod dedupe;
 pub mod delete;
 mod error;
 pub mod expunge;
@@ -22,7 +30,9 @@
 pub mod list;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+pub mod namespace;
 pub mod purge;
+pub mod retention;
 #[cfg(feature = "sync")]
 pub mod sync;
 
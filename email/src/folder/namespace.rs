@@ -0,0 +1,189 @@
+//! # Folder namespace
+//!
+//! Module dedicated to folder namespaces, as defined by the IMAP
+//! NAMESPACE extension (RFC 2342). Some providers store personal
+//! folders under a prefix (like `INBOX.`), or expose folders of
+//! other users and shared folders under their own prefixes.
+//!
+//! Folder names used across the library are relative to the
+//! personal namespace: backends apply the personal prefix when
+//! sending folder names to the server, and strip it when listing
+//! folders.
+
+use std::ops::Deref;
+
+use super::INBOX;
+
+/// The namespace kind enumeration.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum NamespaceKind {
+    /// The namespace of the folders owned by the user.
+    Personal,
+
+    /// The namespace of the folders owned by other users.
+    OtherUsers,
+
+    /// The namespace of the folders shared between users.
+    Shared,
+}
+
+/// The folder namespace.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct Namespace {
+    /// The kind of the namespace.
+    pub kind: NamespaceKind,
+
+    /// The prefix of the folders belonging to the namespace,
+    /// including the trailing hierarchy delimiter (for example
+    /// `INBOX.`). An empty prefix means that folders live at the
+    /// root level.
+    pub prefix: String,
+
+    /// The hierarchy delimiter of the namespace, if any.
+    pub delimiter: Option<char>,
+}
+
+impl Namespace {
+    /// Creates a new namespace.
+    pub fn new(kind: NamespaceKind, prefix: impl ToString, delimiter: Option<char>) -> Self {
+        Self {
+            kind,
+            prefix: prefix.to_string(),
+            delimiter,
+        }
+    }
+
+    /// Creates a new personal namespace.
+    pub fn personal(prefix: impl ToString, delimiter: Option<char>) -> Self {
+        Self::new(NamespaceKind::Personal, prefix, delimiter)
+    }
+
+    /// Returns `true` if the given mailbox belongs to the namespace.
+    ///
+    /// Namespaces with an empty prefix contain all mailboxes.
+    pub fn contains(&self, mbox: &str) -> bool {
+        mbox.starts_with(&self.prefix)
+    }
+}
+
+/// The list of folder namespaces.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "derive", derive(serde::Serialize), serde(transparent))]
+pub struct Namespaces(Vec<Namespace>);
+
+impl Namespaces {
+    /// Returns the personal namespace, if any.
+    pub fn personal(&self) -> Option<&Namespace> {
+        self.iter().find(|ns| ns.kind == NamespaceKind::Personal)
+    }
+
+    /// Returns the prefix of the personal namespace.
+    ///
+    /// Returns an empty string when there is no personal namespace.
+    pub fn personal_prefix(&self) -> &str {
+        self.personal()
+            .map(|ns| ns.prefix.as_str())
+            .unwrap_or_default()
+    }
+
+    /// Turns the given folder name into a mailbox name, by
+    /// prepending the personal prefix.
+    ///
+    /// The inbox, folders already prefixed and folders belonging to
+    /// other namespaces are left untouched.
+    pub fn to_mailbox(&self, folder: impl AsRef<str>) -> String {
+        let folder = folder.as_ref();
+        let prefix = self.personal_prefix();
+
+        let untouched = prefix.is_empty()
+            || folder.eq_ignore_ascii_case(INBOX)
+            || folder.starts_with(prefix)
+            || self
+                .iter()
+                .filter(|ns| ns.kind != NamespaceKind::Personal && !ns.prefix.is_empty())
+                .any(|ns| ns.contains(folder));
+
+        if untouched {
+            folder.to_owned()
+        } else {
+            format!("{prefix}{folder}")
+        }
+    }
+
+    /// Turns the given mailbox name into a folder name, by stripping
+    /// the personal prefix.
+    pub fn to_folder(&self, mbox: impl AsRef<str>) -> String {
+        let mbox = mbox.as_ref();
+
+        match mbox.strip_prefix(self.personal_prefix()) {
+            Some(folder) if !folder.is_empty() => folder.to_owned(),
+            _ => mbox.to_owned(),
+        }
+    }
+}
+
+impl Deref for Namespaces {
+    type Target = Vec<Namespace>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl IntoIterator for Namespaces {
+    type Item = Namespace;
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl FromIterator<Namespace> for Namespaces {
+    fn from_iter<T: IntoIterator<Item = Namespace>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl From<Vec<Namespace>> for Namespaces {
+    fn from(namespaces: Vec<Namespace>) -> Self {
+        Self(namespaces)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Namespace, NamespaceKind, Namespaces};
+
+    #[test]
+    fn mailbox_names() {
+        let namespaces = Namespaces::from(vec![
+            Namespace::personal("INBOX.", Some('.')),
+            Namespace::new(NamespaceKind::Shared, "Shared.", Some('.')),
+        ]);
+
+        assert_eq!(namespaces.to_mailbox("Sent"), "INBOX.Sent");
+        assert_eq!(namespaces.to_mailbox("INBOX"), "INBOX");
+        assert_eq!(namespaces.to_mailbox("inbox"), "inbox");
+        assert_eq!(namespaces.to_mailbox("INBOX.Sent"), "INBOX.Sent");
+        assert_eq!(namespaces.to_mailbox("Shared.Team"), "Shared.Team");
+
+        assert_eq!(namespaces.to_folder("INBOX.Sent"), "Sent");
+        assert_eq!(namespaces.to_folder("INBOX"), "INBOX");
+        assert_eq!(namespaces.to_folder("Shared.Team"), "Shared.Team");
+
+        let namespaces = Namespaces::default();
+        assert_eq!(namespaces.to_mailbox("Sent"), "Sent");
+        assert_eq!(namespaces.to_folder("Sent"), "Sent");
+    }
+}
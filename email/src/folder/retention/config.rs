@@ -0,0 +1,73 @@
+//! # Folder retention config
+
+use std::collections::BTreeMap;
+
+/// The folder retention configuration.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct FolderRetentionConfig {
+    /// The retention rules, indexed by folder name or alias.
+    ///
+    /// For example, `inbox = { older-than = 90, action.move =
+    /// "Archive/%Y" }` or `trash = { older-than = 30, action =
+    /// "remove" }`.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub rules: BTreeMap<String, FolderRetentionRule>,
+
+    /// Apply retention rules to the right backend after every
+    /// synchronization.
+    ///
+    /// Changes are propagated to the left backend at the next
+    /// synchronization.
+    #[cfg(feature = "sync")]
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub after_sync: bool,
+}
+
+/// The folder retention rule.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct FolderRetentionRule {
+    /// The minimum age of messages the rule applies to, in days.
+    ///
+    /// The age of a message is computed from its `Date` header.
+    pub older_than: u32,
+
+    /// The action applied to matching messages.
+    pub action: FolderRetentionAction,
+}
+
+/// The folder retention action.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum FolderRetentionAction {
+    /// Move messages to the given folder.
+    ///
+    /// The folder name is a [`chrono` format string] applied to the
+    /// date of each message, so that `Archive/%Y` moves messages to
+    /// one folder per year. Missing folders are created.
+    ///
+    /// [`chrono` format string]: https://docs.rs/chrono/latest/chrono/format/strftime/index.html
+    Move(String),
+
+    /// Delete messages.
+    ///
+    /// See [`DeleteMessages`](crate::message::delete::DeleteMessages)
+    /// for the semantic of deletion.
+    Delete,
+
+    /// Definitely remove messages.
+    Remove,
+}
@@ -0,0 +1,306 @@
+//! # Folder retention
+//!
+//! Module dedicated to folder retention. Retention rules are
+//! configured per folder (see [`config::FolderRetentionConfig`]), and
+//! apply an action to messages older than a given number of days:
+//! moving them to an archive folder, deleting or removing them.
+//!
+//! Rules are applied on demand with
+//! [`ApplyFolderRetention::apply_folder_retention`], or automatically
+//! after synchronization when enabled. Both produce a
+//! [`FolderRetentionReport`] of the actions taken.
+
+pub mod config;
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{self, Write},
+};
+
+use async_trait::async_trait;
+use chrono::{Duration, Local, NaiveDate};
+use tracing::{debug, info};
+
+use self::config::{FolderRetentionAction, FolderRetentionConfig, FolderRetentionRule};
+use super::{add::AddFolder, list::ListFolders, Error};
+use crate::{
+    account::config::HasAccountConfig,
+    envelope::{
+        list::{ListEnvelopes, ListEnvelopesOptions},
+        Envelopes, Id,
+    },
+    message::{delete::DeleteMessages, r#move::MoveMessages, remove::RemoveMessages},
+    search_query::{filter::SearchEmailsFilterQuery, SearchEmailsQuery},
+    AnyBoxedError, AnyResult,
+};
+
+/// The folder retention hunk.
+///
+/// Represents an action taken by a retention rule.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FolderRetentionHunk {
+    /// Messages matching the given ids have been moved from the first
+    /// folder to the second one.
+    MoveMessages(String, String, Vec<String>),
+
+    /// Messages matching the given ids have been deleted from the
+    /// given folder.
+    DeleteMessages(String, Vec<String>),
+
+    /// Messages matching the given ids have been removed from the
+    /// given folder.
+    RemoveMessages(String, Vec<String>),
+}
+
+impl fmt::Display for FolderRetentionHunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MoveMessages(folder, target, ids) => {
+                let n = ids.len();
+                write!(f, "Moving {n} messages from folder {folder} to {target}")
+            }
+            Self::DeleteMessages(folder, ids) => {
+                let n = ids.len();
+                write!(f, "Deleting {n} messages from folder {folder}")
+            }
+            Self::RemoveMessages(folder, ids) => {
+                let n = ids.len();
+                write!(f, "Removing {n} messages from folder {folder}")
+            }
+        }
+    }
+}
+
+/// The folder retention report.
+#[derive(Debug, Default)]
+pub struct FolderRetentionReport {
+    /// The list of actions taken, associated with an optional error.
+    pub hunks: Vec<(FolderRetentionHunk, Option<AnyBoxedError>)>,
+
+    /// The list of folders retention rules could not be applied to,
+    /// associated with the error.
+    pub errors: Vec<(String, AnyBoxedError)>,
+}
+
+/// Feature to apply retention rules to folders.
+///
+/// This feature is implemented for any backend exposing the
+/// underlying features.
+#[async_trait]
+pub trait ApplyFolderRetention:
+    HasAccountConfig
+    + ListFolders
+    + AddFolder
+    + ListEnvelopes
+    + MoveMessages
+    + DeleteMessages
+    + RemoveMessages
+{
+    /// Apply the retention rules of the account configuration.
+    async fn apply_folder_retention(&self) -> FolderRetentionReport {
+        let config = self
+            .account_config()
+            .folder
+            .as_ref()
+            .and_then(|c| c.retention.clone())
+            .unwrap_or_default();
+
+        self.apply_folder_retention_config(&config, Local::now().date_naive())
+            .await
+    }
+
+    /// Apply the retention rules of the given configuration, using
+    /// the given date as the current date.
+    async fn apply_folder_retention_config(
+        &self,
+        config: &FolderRetentionConfig,
+        today: NaiveDate,
+    ) -> FolderRetentionReport {
+        let mut report = FolderRetentionReport::default();
+        let mut folders = None;
+
+        for (folder, rule) in &config.rules {
+            info!("applying retention rule to folder {folder}");
+
+            let envelopes = match self.list_expired_envelopes(folder, rule, today).await {
+                Ok(envelopes) if envelopes.is_empty() => {
+                    debug!("no expired envelope found in folder {folder}");
+                    continue;
+                }
+                Ok(envelopes) => envelopes,
+                Err(err) => {
+                    report.errors.push((folder.clone(), err));
+                    continue;
+                }
+            };
+
+            match &rule.action {
+                FolderRetentionAction::Move(pattern) => {
+                    let targets = match group_by_target(&envelopes, pattern) {
+                        Ok(targets) => targets,
+                        Err(err) => {
+                            report.errors.push((folder.clone(), err));
+                            continue;
+                        }
+                    };
+
+                    if folders.is_none() {
+                        match self.list_folder_names().await {
+                            Ok(names) => folders = Some(names),
+                            Err(err) => {
+                                report.errors.push((folder.clone(), err));
+                                continue;
+                            }
+                        }
+                    }
+
+                    let Some(folders) = folders.as_mut() else {
+                        continue;
+                    };
+
+                    for (target, ids) in targets {
+                        let res = self
+                            .move_expired_messages(folders, folder, &target, &ids)
+                            .await;
+                        let hunk = FolderRetentionHunk::MoveMessages(folder.clone(), target, ids);
+                        report.hunks.push((hunk, res.err()));
+                    }
+                }
+                FolderRetentionAction::Delete => {
+                    let ids = envelope_ids(&envelopes);
+                    let res = self
+                        .delete_messages(folder, &Id::multiple(ids.clone()))
+                        .await;
+                    let hunk = FolderRetentionHunk::DeleteMessages(folder.clone(), ids);
+                    report.hunks.push((hunk, res.err()));
+                }
+                FolderRetentionAction::Remove => {
+                    let ids = envelope_ids(&envelopes);
+                    let res = self
+                        .remove_messages(folder, &Id::multiple(ids.clone()))
+                        .await;
+                    let hunk = FolderRetentionHunk::RemoveMessages(folder.clone(), ids);
+                    report.hunks.push((hunk, res.err()));
+                }
+            }
+        }
+
+        report
+    }
+
+    /// List envelopes of the given folder matched by the given rule.
+    async fn list_expired_envelopes(
+        &self,
+        folder: &str,
+        rule: &FolderRetentionRule,
+        today: NaiveDate,
+    ) -> AnyResult<Envelopes> {
+        let before = today - Duration::days(rule.older_than.into());
+
+        let opts = ListEnvelopesOptions {
+            query: Some(SearchEmailsQuery {
+                filter: Some(SearchEmailsFilterQuery::BeforeDate(before)),
+                sort: None,
+            }),
+            ..Default::default()
+        };
+
+        let mut envelopes = self.list_envelopes(folder, opts).await?;
+        envelopes.retain(|envelope| envelope.date.date_naive() < before);
+
+        Ok(envelopes)
+    }
+
+    /// List names of existing folders, in order to create missing
+    /// archive folders.
+    async fn list_folder_names(&self) -> AnyResult<HashSet<String>> {
+        let folders = self.list_folders().await?;
+        Ok(folders.iter().map(|folder| folder.name.clone()).collect())
+    }
+
+    /// Move messages matching the given ids to the given target,
+    /// creating it if needed.
+    async fn move_expired_messages(
+        &self,
+        folders: &mut HashSet<String>,
+        folder: &str,
+        target: &str,
+        ids: &[String],
+    ) -> AnyResult<()> {
+        let target_name = self.account_config().get_folder_alias(target);
+
+        if !folders.contains(&target_name) {
+            debug!("creating missing retention folder {target}");
+            self.add_folder(target).await?;
+            folders.insert(target_name);
+        }
+
+        self.move_messages(folder, target, &Id::multiple(ids.to_vec()))
+            .await
+    }
+}
+
+impl<T> ApplyFolderRetention for T where
+    T: HasAccountConfig
+        + ListFolders
+        + AddFolder
+        + ListEnvelopes
+        + MoveMessages
+        + DeleteMessages
+        + RemoveMessages
+{
+}
+
+/// Groups ids of the given envelopes by target folder, formatting
+/// the given pattern with the date of each envelope.
+fn group_by_target(
+    envelopes: &Envelopes,
+    pattern: &str,
+) -> AnyResult<BTreeMap<String, Vec<String>>> {
+    let mut targets: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for envelope in envelopes.iter() {
+        let mut target = String::new();
+        write!(target, "{}", envelope.date.format(pattern))
+            .map_err(|_| Error::FormatRetentionTargetError(pattern.to_owned()))?;
+        targets.entry(target).or_default().push(envelope.id.clone());
+    }
+
+    Ok(targets)
+}
+
+fn envelope_ids(envelopes: &Envelopes) -> Vec<String> {
+    envelopes
+        .iter()
+        .map(|envelope| envelope.id.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::group_by_target;
+    use crate::envelope::{Envelope, Envelopes};
+
+    #[test]
+    fn group_envelopes_by_target() {
+        let envelopes: Envelopes = [
+            ("1", "2022-06-01T10:00:00+00:00"),
+            ("2", "2023-01-01T10:00:00+00:00"),
+            ("3", "2022-12-31T10:00:00+00:00"),
+        ]
+        .into_iter()
+        .map(|(id, date)| Envelope {
+            id: id.into(),
+            date: date.parse().unwrap(),
+            ..Default::default()
+        })
+        .collect();
+
+        let targets = group_by_target(&envelopes, "Archive/%Y").unwrap();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets["Archive/2022"], vec!["1", "3"]);
+        assert_eq!(targets["Archive/2023"], vec!["2"]);
+
+        assert!(group_by_target(&envelopes, "Archive/%Q").is_err());
+    }
+}
@@ -2,7 +2,7 @@
 
 use std::collections::BTreeSet;
 
-#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(
     feature = "derive",
     derive(serde::Serialize, serde::Deserialize),
@@ -14,6 +14,33 @@ pub struct FolderSyncConfig {
 
     #[cfg_attr(feature = "derive", serde(default))]
     pub permissions: FolderSyncPermissions,
+
+    /// Propagates folder deletions from one side to the other.
+    ///
+    /// When disabled, a folder deleted on one side is kept on the
+    /// other side, but it is not re-created on the side it has been
+    /// deleted from either.
+    #[cfg_attr(
+        feature = "derive",
+        serde(default = "FolderSyncConfig::default_propagate_deletions")
+    )]
+    pub propagate_deletions: bool,
+}
+
+impl FolderSyncConfig {
+    pub fn default_propagate_deletions() -> bool {
+        true
+    }
+}
+
+impl Default for FolderSyncConfig {
+    fn default() -> Self {
+        Self {
+            filter: Default::default(),
+            permissions: Default::default(),
+            propagate_deletions: Self::default_propagate_deletions(),
+        }
+    }
 }
 
 /// The folder synchronization strategy.
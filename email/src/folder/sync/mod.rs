@@ -7,13 +7,14 @@
 pub mod hunk;
 pub mod patch;
 pub mod report;
+pub mod tombstone;
 
 use std::{collections::HashSet, sync::Arc};
 
 use futures::{stream::FuturesUnordered, StreamExt};
 use tracing::{debug, trace};
 
-use self::{hunk::FolderSyncHunk, report::FolderSyncReport};
+use self::{hunk::FolderSyncHunk, report::FolderSyncReport, tombstone::FolderSyncTombstones};
 use super::{
     add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders, Folder,
 };
@@ -172,13 +173,41 @@ pub(crate) async fn sync<L, R>(
 
     SyncEvent::ListedAllFolders.emit(&ctx_ref.handler).await;
 
-    let mut patch = patch::build(
+    let (left_cached_folders, left_folders, right_cached_folders, right_folders) = (
         left_cached_folders?,
         left_folders?,
         right_cached_folders?,
         right_folders?,
     );
 
+    let left_tombstones_dir = &ctx_ref.left_cache.context.maildir_config.root_dir;
+    let left_tombstones = FolderSyncTombstones::read(left_tombstones_dir)?;
+    let left_deleted = patch::deleted(
+        &left_cached_folders,
+        &left_folders,
+        &left_tombstones,
+        &right_folders,
+    );
+
+    let right_tombstones_dir = &ctx_ref.right_cache.context.maildir_config.root_dir;
+    let right_tombstones = FolderSyncTombstones::read(right_tombstones_dir)?;
+    let right_deleted = patch::deleted(
+        &right_cached_folders,
+        &right_folders,
+        &right_tombstones,
+        &left_folders,
+    );
+
+    let mut patch = patch::build_with_tombstones(
+        left_cached_folders,
+        left_folders,
+        &left_tombstones,
+        right_cached_folders,
+        right_folders,
+        &right_tombstones,
+        ctx_ref.propagate_folder_deletions,
+    );
+
     ctx_ref.apply_folder_permissions(&mut patch);
 
     SyncEvent::GeneratedFolderPatch(patch.clone())
@@ -264,6 +293,53 @@ pub(crate) async fn sync<L, R>(
         .emit(&ctx_ref.handler)
         .await;
 
+    if !ctx_ref.dry_run {
+        let deleted_from = |destination: SyncDestination| {
+            report
+                .patch
+                .iter()
+                .filter_map(move |(hunk, err)| match hunk {
+                    FolderSyncHunk::Delete(folder, dest)
+                        if *dest == destination && err.is_none() =>
+                    {
+                        Some(folder.as_str())
+                    }
+                    _ => None,
+                })
+                .collect::<HashSet<_>>()
+        };
+
+        // A folder deleted from one side stays tombstoned until its
+        // deletion has been successfully propagated to the other
+        // side. Tombstones of folders out of the sync scope are
+        // kept as it is.
+        let deleted_from_right = deleted_from(SyncDestination::Right);
+        let left_tombstones: FolderSyncTombstones = left_tombstones
+            .iter()
+            .filter(|folder| !ctx_ref.folder_filters.matches(folder))
+            .cloned()
+            .chain(
+                left_deleted
+                    .into_iter()
+                    .filter(|folder| !deleted_from_right.contains(folder.as_str())),
+            )
+            .collect();
+        left_tombstones.write(left_tombstones_dir)?;
+
+        let deleted_from_left = deleted_from(SyncDestination::Left);
+        let right_tombstones: FolderSyncTombstones = right_tombstones
+            .iter()
+            .filter(|folder| !ctx_ref.folder_filters.matches(folder))
+            .cloned()
+            .chain(
+                right_deleted
+                    .into_iter()
+                    .filter(|folder| !deleted_from_left.contains(folder.as_str())),
+            )
+            .collect();
+        right_tombstones.write(right_tombstones_dir)?;
+    }
+
     Ok(report)
 }
 
@@ -8,7 +8,11 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 
-use super::hunk::{FolderName, FolderSyncHunk, FoldersName};
+use super::{
+    config::FolderSyncPermissions,
+    hunk::{FolderName, FolderSyncHunk, FoldersName},
+    tombstone::FolderSyncTombstones,
+};
 use crate::sync::SyncDestination;
 
 /// A folder synchronization patch is just a list of folder
@@ -23,7 +27,13 @@
 ///
 /// Contains the core algorithm of the folder synchronization. It has
 /// been exported in a dedicated function so that it can be easily
-/// tested.
+/// tested, and reused by custom synchronization engines.
+///
+/// The function is pure: it takes the folder names of both sides
+/// (left and right) and of their respective caches, as they were at
+/// the end of the previous synchronization, and returns the hunks
+/// needed to bring all of them in sync. Applying hunks is up to the
+/// caller.
 pub fn build(
     local_cache: FoldersName,
     local: FoldersName,
@@ -147,11 +157,105 @@ pub fn build(
     BTreeMap::from_iter(patches)
 }
 
+/// Returns the folders deleted from one side but still present on
+/// the other side.
+///
+/// A folder is considered deleted when it is missing from the side
+/// but either present in its cache or tombstoned.
+pub fn deleted(
+    cache: &FoldersName,
+    folders: &FoldersName,
+    tombstones: &FolderSyncTombstones,
+    other: &FoldersName,
+) -> BTreeSet<FolderName> {
+    other
+        .iter()
+        .filter(|folder| !folders.contains(*folder))
+        .filter(|folder| cache.contains(*folder) || tombstones.contains(*folder))
+        .cloned()
+        .collect()
+}
+
+/// Folder synchronization patch builder, aware of tombstones.
+///
+/// Same as [`build`], except that folders deleted from one side
+/// (see [`deleted`]) are deleted from the other side instead of
+/// being re-created, or just left untouched when deletions should
+/// not be propagated.
+pub fn build_with_tombstones(
+    left_cache: FoldersName,
+    left: FoldersName,
+    left_tombstones: &FolderSyncTombstones,
+    right_cache: FoldersName,
+    right: FoldersName,
+    right_tombstones: &FolderSyncTombstones,
+    propagate_deletions: bool,
+) -> FolderSyncPatches {
+    let left_deleted = deleted(&left_cache, &left, left_tombstones, &right);
+    let right_deleted = deleted(&right_cache, &right, right_tombstones, &left);
+
+    let mut patches = build(left_cache.clone(), left, right_cache.clone(), right);
+
+    let deleted = left_deleted
+        .into_iter()
+        .map(|folder| (folder, SyncDestination::Left, SyncDestination::Right))
+        .chain(
+            right_deleted
+                .into_iter()
+                .map(|folder| (folder, SyncDestination::Right, SyncDestination::Left)),
+        );
+
+    for (folder, from, to) in deleted {
+        let (from_cache, to_cache) = match from {
+            SyncDestination::Left => (&left_cache, &right_cache),
+            SyncDestination::Right => (&right_cache, &left_cache),
+        };
+
+        let mut patch = BTreeSet::new();
+
+        if from_cache.contains(&folder) {
+            patch.insert(FolderSyncHunk::Uncache(folder.clone(), from));
+        }
+
+        if propagate_deletions {
+            if to_cache.contains(&folder) {
+                patch.insert(FolderSyncHunk::Uncache(folder.clone(), to.clone()));
+            }
+
+            patch.insert(FolderSyncHunk::Delete(folder.clone(), to));
+        }
+
+        patches.insert(folder, patch);
+    }
+
+    patches
+}
+
+/// Removes from the given patches the hunks not allowed by the given
+/// left and right folder permissions.
+pub fn apply_permissions(
+    patches: &mut FolderSyncPatches,
+    left: &FolderSyncPermissions,
+    right: &FolderSyncPermissions,
+) {
+    use FolderSyncHunk::*;
+    use SyncDestination::*;
+
+    for (_, patch) in patches.iter_mut() {
+        patch.retain(|hunk| match hunk {
+            Create(_, Left) | Cache(_, Left) => left.create,
+            Create(_, Right) | Cache(_, Right) => right.create,
+            Delete(_, Left) | Uncache(_, Left) => left.delete,
+            Delete(_, Right) | Uncache(_, Right) => right.delete,
+        });
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::{BTreeMap, BTreeSet};
 
-    use super::{FolderSyncHunk, FoldersName};
+    use super::{FolderSyncHunk, FolderSyncTombstones, FoldersName};
     use crate::sync::SyncDestination;
 
     #[test]
@@ -420,4 +524,83 @@ fn build_folder_patch() {
             BTreeMap::from_iter([("folder".into(), BTreeSet::from_iter([]))])
         );
     }
+
+    #[test]
+    fn build_folder_patch_with_tombstones() {
+        let tombstones = FolderSyncTombstones::from_iter(["folder".into()]);
+
+        // deleted from left, right still cached
+        assert_eq!(
+            super::build_with_tombstones(
+                FoldersName::from_iter(["folder".into()]),
+                FoldersName::default(),
+                &FolderSyncTombstones::default(),
+                FoldersName::from_iter(["folder".into()]),
+                FoldersName::from_iter(["folder".into()]),
+                &FolderSyncTombstones::default(),
+                true,
+            ),
+            BTreeMap::from_iter([(
+                "folder".into(),
+                BTreeSet::from_iter([
+                    FolderSyncHunk::Uncache("folder".into(), SyncDestination::Left),
+                    FolderSyncHunk::Uncache("folder".into(), SyncDestination::Right),
+                    FolderSyncHunk::Delete("folder".into(), SyncDestination::Right),
+                ]),
+            )]),
+        );
+
+        // deleted from left, uncached but deletion not propagated yet
+        assert_eq!(
+            super::build_with_tombstones(
+                FoldersName::default(),
+                FoldersName::default(),
+                &tombstones,
+                FoldersName::default(),
+                FoldersName::from_iter(["folder".into()]),
+                &FolderSyncTombstones::default(),
+                true,
+            ),
+            BTreeMap::from_iter([(
+                "folder".into(),
+                BTreeSet::from_iter([FolderSyncHunk::Delete(
+                    "folder".into(),
+                    SyncDestination::Right
+                )]),
+            )]),
+        );
+
+        // deleted from right, propagation disabled
+        assert_eq!(
+            super::build_with_tombstones(
+                FoldersName::from_iter(["folder".into()]),
+                FoldersName::from_iter(["folder".into()]),
+                &FolderSyncTombstones::default(),
+                FoldersName::default(),
+                FoldersName::default(),
+                &tombstones,
+                false,
+            ),
+            BTreeMap::from_iter([("folder".into(), BTreeSet::from_iter([]))]),
+        );
+
+        // re-created on left after deletion
+        assert_eq!(
+            super::build_with_tombstones(
+                FoldersName::default(),
+                FoldersName::from_iter(["folder".into()]),
+                &tombstones,
+                FoldersName::default(),
+                FoldersName::default(),
+                &FolderSyncTombstones::default(),
+                true,
+            ),
+            super::build(
+                FoldersName::default(),
+                FoldersName::from_iter(["folder".into()]),
+                FoldersName::default(),
+                FoldersName::default(),
+            ),
+        );
+    }
 }
@@ -0,0 +1,95 @@
+//! Module dedicated to email folders synchronization tombstones.
+//!
+//! A tombstone records that a folder has been deleted from one side
+//! while the other side still owns a copy of it. Tombstones are
+//! persisted in the cache directory of the side the folder has been
+//! deleted from, so that the remaining copy is deleted exactly once
+//! instead of being re-created at the next synchronization.
+
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+};
+
+use super::{hunk::FolderName, Error, Result};
+
+/// The name of the file containing tombstones, at the root of the
+/// Maildir cache directory.
+pub const FOLDER_TOMBSTONES_FILE_NAME: &str = ".folder-tombstones";
+
+/// The set of folder synchronization tombstones.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FolderSyncTombstones(BTreeSet<FolderName>);
+
+impl FolderSyncTombstones {
+    /// Returns the path of the tombstones file for the given cache
+    /// root directory.
+    pub fn path(root_dir: impl AsRef<Path>) -> PathBuf {
+        root_dir.as_ref().join(FOLDER_TOMBSTONES_FILE_NAME)
+    }
+
+    /// Reads tombstones from the given cache root directory.
+    ///
+    /// A missing tombstones file is considered empty.
+    pub fn read(root_dir: impl AsRef<Path>) -> Result<Self> {
+        let path = Self::path(root_dir);
+
+        match fs::read_to_string(&path) {
+            Ok(contents) => Ok(contents
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(ToOwned::to_owned)
+                .collect()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(Error::ReadFolderTombstonesError(err, path)),
+        }
+    }
+
+    /// Writes tombstones to the given cache root directory.
+    ///
+    /// The tombstones file is removed when there is no tombstone
+    /// left.
+    pub fn write(&self, root_dir: impl AsRef<Path>) -> Result<()> {
+        let path = Self::path(root_dir);
+
+        let res = if self.is_empty() {
+            match fs::remove_file(&path) {
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+                res => res,
+            }
+        } else {
+            let mut contents = String::new();
+
+            for folder in self.iter() {
+                contents.push_str(folder);
+                contents.push('\n');
+            }
+
+            fs::write(&path, contents)
+        };
+
+        res.map_err(|err| Error::WriteFolderTombstonesError(err, path))
+    }
+}
+
+impl Deref for FolderSyncTombstones {
+    type Target = BTreeSet<FolderName>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for FolderSyncTombstones {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl FromIterator<FolderName> for FolderSyncTombstones {
+    fn from_iter<T: IntoIterator<Item = FolderName>>(iter: T) -> Self {
+        Self(BTreeSet::from_iter(iter))
+    }
+}
@@ -0,0 +1,48 @@
+use std::{any::Any, io, net::SocketAddr, result};
+
+use thiserror::Error;
+
+use crate::{AnyBoxedError, AnyError, ErrorKind};
+
+/// The global `Result` alias of the module.
+pub type Result<T> = result::Result<T, Error>;
+
+/// The global `Error` enum of the module.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot bind health server to {1}")]
+    BindHealthServerError(#[source] io::Error, SocketAddr),
+    #[error("cannot get health server local address")]
+    GetHealthServerAddrError(#[source] io::Error),
+    #[error("cannot accept health server connection")]
+    AcceptHealthServerConnectionError(#[source] io::Error),
+}
+
+impl Error {
+    /// Returns the kind of the error.
+    ///
+    /// See [`ErrorKind`] for the available kinds.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::BindHealthServerError(..)
+            | Self::GetHealthServerAddrError(_)
+            | Self::AcceptHealthServerConnectionError(_) => ErrorKind::Network,
+        }
+    }
+}
+
+impl AnyError for Error {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn kind(&self) -> ErrorKind {
+        Error::kind(self)
+    }
+}
+
+impl From<Error> for AnyBoxedError {
+    fn from(err: Error) -> Self {
+        Box::new(err)
+    }
+}
@@ -0,0 +1,400 @@
+//! # Health
+//!
+//! Module dedicated to health reporting, for users running the
+//! library as a background service.
+//!
+//! The [`Health`] state gathers account connectivity, last
+//! synchronization time, queue sizes and watch statuses. It can be
+//! rendered as JSON or as Prometheus text, and exposed through a
+//! tiny HTTP endpoint with [`HealthServer`]:
+//!
+//! - `GET /health` returns the JSON report;
+//! - `GET /metrics` returns the Prometheus report.
+
+mod error;
+mod server;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::{Arc, RwLock},
+};
+
+use chrono::{DateTime, Utc};
+
+#[doc(inline)]
+pub use self::{
+    error::{Error, Result},
+    server::HealthServer,
+};
+#[cfg(feature = "sync")]
+use crate::sync::report::SyncReport;
+use crate::watch::service::WatchStatus;
+
+/// The health state, shared between the service and the health
+/// server.
+///
+/// The state is cheap to clone: all clones share the same report.
+#[derive(Clone, Debug, Default)]
+pub struct Health(Arc<RwLock<HealthReport>>);
+
+impl Health {
+    /// Create a new, empty health state.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return a snapshot of the current health report.
+    pub fn report(&self) -> HealthReport {
+        self.0.read().unwrap().clone()
+    }
+
+    fn update(&self, account: impl ToString, f: impl FnOnce(&mut AccountHealth)) {
+        let mut report = self.0.write().unwrap();
+        let account = report.accounts.entry(account.to_string()).or_default();
+        f(account)
+    }
+
+    /// Mark the given account as connected or disconnected.
+    ///
+    /// When connecting, the last error is cleared.
+    pub fn set_connected(&self, account: impl ToString, connected: bool) {
+        self.update(account, |account| {
+            account.connected = connected;
+            if connected {
+                account.last_error = None;
+            }
+        })
+    }
+
+    /// Mark the given account as disconnected because of the given
+    /// error.
+    pub fn set_error(&self, account: impl ToString, err: impl ToString) {
+        self.update(account, |account| {
+            account.connected = false;
+            account.last_error = Some(err.to_string());
+        })
+    }
+
+    /// Set the last synchronization time of the given account.
+    pub fn set_last_sync(&self, account: impl ToString, date: DateTime<Utc>) {
+        self.update(account, |account| account.last_sync = Some(date))
+    }
+
+    /// Record the given synchronization report of the given account.
+    ///
+    /// The last synchronization time is set to now, and the number
+    /// of failed hunks is saved as synchronization errors.
+    #[cfg(feature = "sync")]
+    pub fn record_sync(&self, account: impl ToString, report: &SyncReport) {
+        let folder_errors = report
+            .folder
+            .patch
+            .iter()
+            .filter(|(_, err)| err.is_some())
+            .count();
+        let email_errors = report
+            .email
+            .patch
+            .iter()
+            .filter(|(_, err)| err.is_some())
+            .count();
+
+        self.update(account, |account| {
+            account.last_sync = Some(Utc::now());
+            account.sync_errors = folder_errors + email_errors;
+        })
+    }
+
+    /// Set the size of the given queue of the given account.
+    pub fn set_queue_size(&self, account: impl ToString, queue: impl ToString, size: usize) {
+        self.update(account, |account| {
+            account.queues.insert(queue.to_string(), size);
+        })
+    }
+
+    /// Set the watch statuses of the given account, as returned by
+    /// [`WatchService::status`](crate::watch::service::WatchService::status).
+    pub fn set_watch_status(&self, account: impl ToString, statuses: HashMap<String, WatchStatus>) {
+        self.update(account, |account| {
+            account.watch = statuses.into_iter().collect();
+        })
+    }
+
+    /// Remove the given account from the health state.
+    pub fn remove_account(&self, account: impl AsRef<str>) {
+        let mut report = self.0.write().unwrap();
+        report.accounts.remove(account.as_ref());
+    }
+}
+
+/// The health report.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HealthReport {
+    /// The health of each account, by account name.
+    pub accounts: BTreeMap<String, AccountHealth>,
+}
+
+/// The health of an account.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AccountHealth {
+    /// Whether the account is connected to its backend.
+    pub connected: bool,
+
+    /// The last connection error, if any.
+    pub last_error: Option<String>,
+
+    /// The last time the account has been synchronized.
+    pub last_sync: Option<DateTime<Utc>>,
+
+    /// The number of hunks that failed during the last
+    /// synchronization.
+    pub sync_errors: usize,
+
+    /// The size of each queue, by queue name.
+    pub queues: BTreeMap<String, usize>,
+
+    /// The watch status of each folder, by folder name.
+    pub watch: BTreeMap<String, WatchStatus>,
+}
+
+impl HealthReport {
+    /// Render the report as JSON.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"accounts\":{");
+
+        for (n, (name, account)) in self.accounts.iter().enumerate() {
+            if n > 0 {
+                json.push(',');
+            }
+
+            let _ = write!(json, "{}:{{", json_string(name));
+            let _ = write!(json, "\"connected\":{},", account.connected);
+
+            json.push_str("\"last_error\":");
+            match &account.last_error {
+                Some(err) => json.push_str(&json_string(err)),
+                None => json.push_str("null"),
+            }
+
+            json.push_str(",\"last_sync\":");
+            match &account.last_sync {
+                Some(date) => json.push_str(&json_string(&date.to_rfc3339())),
+                None => json.push_str("null"),
+            }
+
+            let _ = write!(json, ",\"sync_errors\":{}", account.sync_errors);
+
+            json.push_str(",\"queues\":{");
+            for (n, (queue, size)) in account.queues.iter().enumerate() {
+                if n > 0 {
+                    json.push(',');
+                }
+                let _ = write!(json, "{}:{size}", json_string(queue));
+            }
+
+            json.push_str("},\"watch\":{");
+            for (n, (folder, status)) in account.watch.iter().enumerate() {
+                if n > 0 {
+                    json.push(',');
+                }
+                let _ = write!(json, "{}:", json_string(folder));
+                match status {
+                    WatchStatus::Reconnecting(attempt) => {
+                        let _ = write!(
+                            json,
+                            "{{\"status\":\"reconnecting\",\"attempt\":{attempt}}}"
+                        );
+                    }
+                    status => {
+                        let _ = write!(json, "{{\"status\":\"{}\"}}", watch_status_name(status));
+                    }
+                }
+            }
+
+            json.push_str("}}");
+        }
+
+        json.push_str("}}");
+        json
+    }
+
+    /// Render the report as Prometheus text.
+    pub fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+
+        metric_header(
+            &mut text,
+            "email_account_connected",
+            "Whether the account is connected (1) or not (0).",
+        );
+        for (name, account) in &self.accounts {
+            let _ = writeln!(
+                text,
+                "email_account_connected{{account={}}} {}",
+                json_string(name),
+                account.connected as u8
+            );
+        }
+
+        metric_header(
+            &mut text,
+            "email_account_last_sync_timestamp_seconds",
+            "The last time the account has been synchronized.",
+        );
+        for (name, account) in &self.accounts {
+            if let Some(date) = &account.last_sync {
+                let _ = writeln!(
+                    text,
+                    "email_account_last_sync_timestamp_seconds{{account={}}} {}",
+                    json_string(name),
+                    date.timestamp()
+                );
+            }
+        }
+
+        metric_header(
+            &mut text,
+            "email_account_sync_errors",
+            "The number of hunks that failed during the last synchronization.",
+        );
+        for (name, account) in &self.accounts {
+            let _ = writeln!(
+                text,
+                "email_account_sync_errors{{account={}}} {}",
+                json_string(name),
+                account.sync_errors
+            );
+        }
+
+        metric_header(&mut text, "email_queue_size", "The size of the queue.");
+        for (name, account) in &self.accounts {
+            for (queue, size) in &account.queues {
+                let _ = writeln!(
+                    text,
+                    "email_queue_size{{account={},queue={}}} {size}",
+                    json_string(name),
+                    json_string(queue),
+                );
+            }
+        }
+
+        metric_header(
+            &mut text,
+            "email_watch_status",
+            "The watch status of the folder (1 for the current status).",
+        );
+        for (name, account) in &self.accounts {
+            for (folder, status) in &account.watch {
+                let _ = writeln!(
+                    text,
+                    "email_watch_status{{account={},folder={},status=\"{}\"}} 1",
+                    json_string(name),
+                    json_string(folder),
+                    watch_status_name(status),
+                );
+            }
+        }
+
+        text
+    }
+}
+
+fn watch_status_name(status: &WatchStatus) -> &'static str {
+    match status {
+        WatchStatus::Running => "running",
+        WatchStatus::Reconnecting(_) => "reconnecting",
+        WatchStatus::Stopped => "stopped",
+    }
+}
+
+fn metric_header(text: &mut String, name: &str, help: &str) {
+    let _ = writeln!(text, "# HELP {name} {help}");
+    let _ = writeln!(text, "# TYPE {name} gauge");
+}
+
+/// Quote and escape the given string.
+///
+/// The result is both a valid JSON string and a valid Prometheus
+/// label value.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::{TimeZone, Utc};
+
+    use super::Health;
+    use crate::watch::service::WatchStatus;
+
+    fn health() -> Health {
+        let health = Health::new();
+
+        health.set_connected("work", true);
+        health.set_last_sync("work", Utc.timestamp_opt(1700000000, 0).unwrap());
+        health.set_queue_size("work", "outbox", 2);
+        health.set_watch_status(
+            "work",
+            HashMap::from_iter([
+                ("INBOX".into(), WatchStatus::Running),
+                ("Sent".into(), WatchStatus::Reconnecting(3)),
+            ]),
+        );
+        health.set_error("perso", "connection \"refused\"");
+
+        health
+    }
+
+    #[test]
+    fn json() {
+        let expected_json = concat!(
+            "{\"accounts\":{",
+            "\"perso\":{\"connected\":false,\"last_error\":\"connection \\\"refused\\\"\",",
+            "\"last_sync\":null,\"sync_errors\":0,\"queues\":{},\"watch\":{}},",
+            "\"work\":{\"connected\":true,\"last_error\":null,",
+            "\"last_sync\":\"2023-11-14T22:13:20+00:00\",\"sync_errors\":0,",
+            "\"queues\":{\"outbox\":2},",
+            "\"watch\":{\"INBOX\":{\"status\":\"running\"},",
+            "\"Sent\":{\"status\":\"reconnecting\",\"attempt\":3}}}",
+            "}}",
+        );
+
+        assert_eq!(health().report().to_json(), expected_json);
+    }
+
+    #[test]
+    fn prometheus() {
+        let text = health().report().to_prometheus();
+
+        assert!(text.contains("email_account_connected{account=\"perso\"} 0\n"));
+        assert!(text.contains("email_account_connected{account=\"work\"} 1\n"));
+        assert!(text
+            .contains("email_account_last_sync_timestamp_seconds{account=\"work\"} 1700000000\n"));
+        assert!(!text.contains("email_account_last_sync_timestamp_seconds{account=\"perso\"}"));
+        assert!(text.contains("email_queue_size{account=\"work\",queue=\"outbox\"} 2\n"));
+        assert!(text.contains(
+            "email_watch_status{account=\"work\",folder=\"Sent\",status=\"reconnecting\"} 1\n"
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+//! # Health server
+//!
+//! Module dedicated to the health HTTP endpoint. The server is
+//! intentionally minimal: it only answers `GET` requests on
+//! `/health` and `/metrics`, one request per connection.
+
+use std::net::SocketAddr;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{debug, info};
+
+use super::{Error, Health, Result};
+
+/// The maximum size of a request head.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// The health HTTP server.
+pub struct HealthServer {
+    listener: TcpListener,
+    health: Health,
+}
+
+impl HealthServer {
+    /// Bind a new health server to the given address.
+    ///
+    /// Use port `0` to let the system pick a free port, see
+    /// [`HealthServer::local_addr`].
+    pub async fn bind(addr: SocketAddr, health: Health) -> Result<Self> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|err| Error::BindHealthServerError(err, addr))?;
+
+        Ok(Self { listener, health })
+    }
+
+    /// Return the address the server is bound to.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener
+            .local_addr()
+            .map_err(Error::GetHealthServerAddrError)
+    }
+
+    /// Serve health requests until the task is dropped.
+    ///
+    /// Each connection is handled in its own task.
+    pub async fn serve(self) -> Result<()> {
+        info!(addr = ?self.listener.local_addr().ok(), "serving health endpoint");
+
+        loop {
+            let (stream, peer) = self
+                .listener
+                .accept()
+                .await
+                .map_err(Error::AcceptHealthServerConnectionError)?;
+
+            let health = self.health.clone();
+
+            tokio::spawn(async move {
+                if let Err(err) = handle(stream, health).await {
+                    debug!(?peer, "cannot handle health request: {err}");
+                }
+            });
+        }
+    }
+}
+
+async fn handle(mut stream: TcpStream, health: Health) -> std::io::Result<()> {
+    let mut buf = Vec::new();
+    let mut chunk = [0; 1024];
+
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") && buf.len() < MAX_REQUEST_SIZE {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let head = String::from_utf8_lossy(&buf);
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default();
+
+    let (status, ctype, body) = match (method, path) {
+        ("GET", "/health") => ("200 OK", "application/json", health.report().to_json()),
+        ("GET", "/metrics") => (
+            "200 OK",
+            "text/plain; version=0.0.4",
+            health.report().to_prometheus(),
+        ),
+        ("GET", _) => ("404 Not Found", "text/plain", String::from("not found\n")),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            String::from("method not allowed\n"),
+        ),
+    };
+
+    let res = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {ctype}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+
+    stream.write_all(res.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::HealthServer;
+    use crate::health::Health;
+
+    async fn get(server: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(server).await.unwrap();
+        let req = format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        stream.write_all(req.as_bytes()).await.unwrap();
+
+        let mut res = String::new();
+        stream.read_to_string(&mut res).await.unwrap();
+        res
+    }
+
+    #[tokio::test]
+    async fn serve() {
+        let health = Health::new();
+        health.set_connected("work", true);
+
+        let server = HealthServer::bind(([127, 0, 0, 1], 0).into(), health.clone())
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let task = tokio::spawn(server.serve());
+
+        let res = get(addr, "/health").await;
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(res.contains("Content-Type: application/json\r\n"));
+        assert!(res.ends_with("\"work\":{\"connected\":true,\"last_error\":null,\"last_sync\":null,\"sync_errors\":0,\"queues\":{},\"watch\":{}}}}"));
+
+        health.set_connected("work", false);
+
+        let res = get(addr, "/metrics").await;
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(res.contains("email_account_connected{account=\"work\"} 0\n"));
+
+        let res = get(addr, "/unknown").await;
+        assert!(res.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        task.abort();
+    }
+}
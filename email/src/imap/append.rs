@@ -0,0 +1,483 @@
+//! # IMAP batched APPEND
+//!
+//! Module dedicated to appending many messages at once. The IMAP
+//! client resolves one task at a time, and its APPEND task carries a
+//! single message, so appending messages one by one costs at least
+//! one round-trip per message.
+//!
+//! An [`AppendBatch`] encodes a whole batch of messages up front and
+//! drives the connection stream itself: when the server advertises
+//! MULTIAPPEND (RFC 3502), the batch is sent as a single APPEND
+//! command carrying all the messages, otherwise one APPEND command
+//! per message is written at once using non-synchronizing literals
+//! (RFC 7888), and the tagged responses are collected afterwards.
+
+use std::{collections::VecDeque, num::NonZeroU32};
+
+use imap_client::{
+    imap_next::{Interrupt, Io, State},
+    imap_types::flag::Flag,
+};
+use tracing::debug;
+
+use super::{
+    raw::{self, Response, ResponseReader, StatusKind},
+    Error, Result, LITERAL_MINUS_MAX_SIZE,
+};
+
+/// The maximum size of the messages sent in a single batch.
+///
+/// A single message bigger than this size is sent alone.
+pub(crate) const APPEND_BATCH_MAX_SIZE: usize = 8 * 1024 * 1024;
+
+/// The server capabilities driving the encoding of a batch.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct AppendCapabilities {
+    pub multiappend: bool,
+    pub literal_plus: bool,
+    pub literal_minus: bool,
+    pub binary: bool,
+}
+
+impl AppendCapabilities {
+    /// Returns `true` if batching saves round-trips compared to
+    /// appending messages one by one.
+    pub fn batch_supported(&self) -> bool {
+        self.multiappend || self.literal_plus || self.literal_minus
+    }
+
+    /// Returns `true` if the given message can be sent as part of a
+    /// batch.
+    ///
+    /// Messages containing NUL bytes require BINARY.
+    pub fn message_supported(&self, msg: &[u8]) -> bool {
+        self.binary || !msg.contains(&0)
+    }
+
+    fn non_sync_literal_supported(&self, size: usize) -> bool {
+        self.literal_plus || (self.literal_minus && size <= LITERAL_MINUS_MAX_SIZE)
+    }
+}
+
+/// Returns `true` if the given mailbox name can be sent as a quoted
+/// string.
+pub(crate) fn mailbox_supported(mbox: &str) -> bool {
+    !mbox.is_empty() && raw::quotable(mbox)
+}
+
+/// A chunk of bytes to write.
+#[derive(Debug)]
+struct Chunk {
+    /// The index of the command the chunk belongs to.
+    cmd: usize,
+    /// Whether a continuation request is needed before writing the
+    /// chunk, which is the case after a synchronizing literal.
+    sync: bool,
+    bytes: Vec<u8>,
+}
+
+/// An APPEND command of a batch.
+#[derive(Debug)]
+struct Command {
+    tag: String,
+    /// The number of messages carried by the command.
+    count: usize,
+    /// The tagged response of the command, once received.
+    status: Option<Status>,
+}
+
+/// The tagged response of an APPEND command.
+#[derive(Debug)]
+enum Status {
+    Ok(Option<Vec<NonZeroU32>>),
+    Rejected(Option<String>, String),
+}
+
+/// The state appending a batch of messages to a mailbox.
+///
+/// The batch is meant to be driven by the stream of the connection,
+/// see [`imap_client::stream::Stream::next`]. All the bytes that can
+/// be written without waiting for a continuation request are emitted
+/// at once, so a batch costs a single round-trip unless the server
+/// only accepts synchronizing literals.
+#[derive(Debug)]
+pub(crate) struct AppendBatch {
+    chunks: VecDeque<Chunk>,
+    commands: Vec<Command>,
+    /// The number of continuation requests received and not yet
+    /// consumed by a synchronizing chunk.
+    continuations: usize,
+    reader: ResponseReader,
+}
+
+impl AppendBatch {
+    /// Encodes the given messages to append to the given mailbox.
+    ///
+    /// The mailbox is expected to be encoded and quotable, see
+    /// [`mailbox_supported`].
+    pub fn new(
+        mbox: &str,
+        msgs: Vec<(Vec<Flag<'static>>, Vec<u8>)>,
+        capabilities: AppendCapabilities,
+    ) -> Self {
+        let mut batch = Self {
+            chunks: VecDeque::new(),
+            commands: Vec::new(),
+            continuations: 0,
+            reader: ResponseReader::default(),
+        };
+
+        let mbox = raw::quote(mbox);
+
+        if capabilities.multiappend {
+            batch.push_command(&mbox, msgs, capabilities);
+        } else {
+            for msg in msgs {
+                batch.push_command(&mbox, vec![msg], capabilities);
+            }
+        }
+
+        batch
+    }
+
+    fn push_command(
+        &mut self,
+        mbox: &str,
+        msgs: Vec<(Vec<Flag<'static>>, Vec<u8>)>,
+        capabilities: AppendCapabilities,
+    ) {
+        let cmd = self.commands.len();
+        let tag = raw::next_tag();
+
+        let mut sync = false;
+        let mut bytes = format!("{tag} APPEND {mbox}").into_bytes();
+
+        self.commands.push(Command {
+            tag,
+            count: msgs.len(),
+            status: None,
+        });
+
+        for (flags, msg) in msgs {
+            let flags: Vec<_> = flags.iter().map(ToString::to_string).collect();
+            bytes.extend(format!(" ({}) ", flags.join(" ")).into_bytes());
+
+            if capabilities.binary {
+                bytes.push(b'~');
+            }
+
+            if capabilities.non_sync_literal_supported(msg.len()) {
+                bytes.extend(format!("{{{}+}}\r\n", msg.len()).into_bytes());
+            } else {
+                bytes.extend(format!("{{{}}}\r\n", msg.len()).into_bytes());
+                self.chunks.push_back(Chunk { cmd, sync, bytes });
+                sync = true;
+                bytes = Vec::new();
+            }
+
+            bytes.extend(msg);
+        }
+
+        bytes.extend(b"\r\n");
+        self.chunks.push_back(Chunk { cmd, sync, bytes });
+    }
+
+    /// Returns the number of APPEND commands of the batch.
+    pub fn commands_count(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Consumes the batch and returns one result per message, in
+    /// order.
+    ///
+    /// The messages of commands without tagged response get the
+    /// given interruption reason: they may or may not have been
+    /// added.
+    pub fn into_results(self, reason: Option<&str>) -> Vec<Result<NonZeroU32>> {
+        let mut results = Vec::new();
+
+        for cmd in self.commands {
+            match cmd.status {
+                Some(Status::Ok(Some(uids))) if uids.len() == cmd.count => {
+                    results.extend(uids.into_iter().map(Ok));
+                }
+                Some(Status::Ok(_)) => {
+                    results.extend((0..cmd.count).map(|_| Err(Error::FindAppendedMessageUidError)));
+                }
+                Some(Status::Rejected(code, text)) => {
+                    results.extend(
+                        (0..cmd.count).map(|_| {
+                            Err(Error::AddMessagesRejectedError(code.clone(), text.clone()))
+                        }),
+                    );
+                }
+                None => {
+                    let reason = reason.unwrap_or("missing tagged response");
+                    results.extend(
+                        (0..cmd.count)
+                            .map(|_| Err(Error::AddMessagesInterruptedError(reason.into()))),
+                    );
+                }
+            }
+        }
+
+        results
+    }
+
+    fn done(&self) -> bool {
+        self.commands.iter().all(|cmd| cmd.status.is_some())
+    }
+
+    fn process_response(&mut self, res: Response) {
+        let (tag, status) = match res {
+            Response::Continuation => {
+                self.continuations += 1;
+                return;
+            }
+            Response::Untagged(_) => return,
+            Response::Tagged(tag, status) => (tag, status),
+        };
+
+        let Some(cmd) = self.commands.iter().position(|cmd| cmd.tag == tag) else {
+            debug!("ignoring response with unknown tag {tag}");
+            return;
+        };
+
+        let status = match status.kind {
+            StatusKind::Ok => Status::Ok(status.code.as_deref().and_then(parse_appenduid)),
+            StatusKind::No | StatusKind::Bad => Status::Rejected(status.code, status.text),
+        };
+
+        self.commands[cmd].status = Some(status);
+
+        // the server may reject a command while waiting for the
+        // continuation of one of its synchronizing literals, in
+        // which case the rest of the command must not be sent
+        self.chunks.retain(|chunk| chunk.cmd != cmd);
+    }
+}
+
+impl State for AppendBatch {
+    type Event = ();
+    type Error = String;
+
+    fn enqueue_input(&mut self, bytes: &[u8]) {
+        self.reader.enqueue(bytes);
+    }
+
+    fn next(&mut self) -> std::result::Result<Self::Event, Interrupt<Self::Error>> {
+        while let Some(res) = self.reader.next().map_err(Interrupt::Error)? {
+            self.process_response(res);
+        }
+
+        if self.done() {
+            return Ok(());
+        }
+
+        let mut output = Vec::new();
+
+        while let Some(chunk) = self.chunks.front() {
+            if chunk.sync {
+                if self.continuations == 0 {
+                    break;
+                }
+                self.continuations -= 1;
+            }
+
+            if let Some(chunk) = self.chunks.pop_front() {
+                output.extend(chunk.bytes);
+            }
+        }
+
+        if output.is_empty() {
+            Err(Interrupt::Io(Io::NeedMoreInput))
+        } else {
+            Err(Interrupt::Io(Io::Output(output)))
+        }
+    }
+}
+
+/// Parses the UIDs of an APPENDUID response code (RFC 4315).
+///
+/// The UID set is expanded in order, so that UIDs match the order of
+/// the appended messages.
+fn parse_appenduid(code: &str) -> Option<Vec<NonZeroU32>> {
+    let mut args = code.split_ascii_whitespace();
+
+    if !args.next()?.eq_ignore_ascii_case("APPENDUID") {
+        return None;
+    }
+
+    let _uidvalidity = args.next()?;
+    let mut uids = Vec::new();
+
+    for range in args.next()?.split(',') {
+        match range.split_once(':') {
+            Some((from, to)) => {
+                let from: u32 = from.parse().ok()?;
+                let to: u32 = to.parse().ok()?;
+
+                if from <= to {
+                    uids.extend((from..=to).filter_map(NonZeroU32::new));
+                } else {
+                    uids.extend((to..=from).rev().filter_map(NonZeroU32::new));
+                }
+            }
+            None => uids.push(range.parse().ok()?),
+        }
+    }
+
+    Some(uids)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use imap_client::{
+        imap_next::{Interrupt, Io, State},
+        imap_types::flag::Flag,
+    };
+
+    use super::{AppendBatch, AppendCapabilities};
+    use crate::{imap::Error, ErrorKind};
+
+    fn msgs(count: usize) -> Vec<(Vec<Flag<'static>>, Vec<u8>)> {
+        (1..=count)
+            .map(|n| {
+                let msg = format!("Subject: {n}\r\n\r\nHello, world!\r\n");
+                (vec![Flag::Seen], msg.into_bytes())
+            })
+            .collect()
+    }
+
+    fn tags(batch: &AppendBatch) -> Vec<String> {
+        batch.commands.iter().map(|cmd| cmd.tag.clone()).collect()
+    }
+
+    /// Drives the batch until it needs more input, and returns the
+    /// bytes written in between.
+    fn output(batch: &mut AppendBatch) -> Option<String> {
+        match batch.next() {
+            Err(Interrupt::Io(Io::Output(bytes))) => Some(String::from_utf8(bytes).unwrap()),
+            Err(Interrupt::Io(Io::NeedMoreInput)) => None,
+            res => panic!("unexpected result {res:?}"),
+        }
+    }
+
+    fn uids(results: Vec<crate::imap::Result<NonZeroU32>>) -> Vec<u32> {
+        results.into_iter().map(|uid| uid.unwrap().get()).collect()
+    }
+
+    #[test]
+    fn multiappend() {
+        let capabilities = AppendCapabilities {
+            multiappend: true,
+            literal_plus: true,
+            ..Default::default()
+        };
+
+        let mut batch = AppendBatch::new("INBOX", msgs(10), capabilities);
+        assert_eq!(batch.commands_count(), 1);
+
+        let tag = tags(&batch).remove(0);
+        let output = output(&mut batch).unwrap();
+        assert_eq!(output.matches(" APPEND \"INBOX\"").count(), 1);
+        assert_eq!(output.matches("(\\Seen) {").count(), 10);
+        assert!(output.starts_with(&format!("{tag} APPEND \"INBOX\" (\\Seen) {{")));
+
+        // everything is written in a single round-trip
+        assert_eq!(batch.next(), Err(Interrupt::Io(Io::NeedMoreInput)));
+
+        batch.enqueue_input(b"* 10 EXISTS\r\n");
+        batch.enqueue_input(format!("{tag} OK [APPENDUID 7 1:3,5:11] done\r\n").as_bytes());
+        assert_eq!(batch.next(), Ok(()));
+
+        let uids = uids(batch.into_results(None));
+        assert_eq!(uids, [1, 2, 3, 5, 6, 7, 8, 9, 10, 11]);
+    }
+
+    #[test]
+    fn pipelined_literal_plus() {
+        let capabilities = AppendCapabilities {
+            literal_plus: true,
+            ..Default::default()
+        };
+
+        let mut batch = AppendBatch::new("INBOX", msgs(10), capabilities);
+        assert_eq!(batch.commands_count(), 10);
+
+        let tags = tags(&batch);
+        let output = output(&mut batch).unwrap();
+        assert_eq!(output.matches(" APPEND \"INBOX\"").count(), 10);
+        assert_eq!(output.matches("+}\r\n").count(), 10);
+
+        // all commands are written in a single round-trip
+        assert_eq!(batch.next(), Err(Interrupt::Io(Io::NeedMoreInput)));
+
+        for (n, tag) in tags.iter().enumerate().rev() {
+            let res = format!("{tag} OK [APPENDUID 7 {}] done\r\n", n + 1);
+            batch.enqueue_input(res.as_bytes());
+        }
+        assert_eq!(batch.next(), Ok(()));
+
+        let uids = uids(batch.into_results(None));
+        assert_eq!(uids, (1..=10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn sync_literal() {
+        let capabilities = AppendCapabilities {
+            multiappend: true,
+            ..Default::default()
+        };
+
+        let mut batch = AppendBatch::new("Sent", msgs(2), capabilities);
+        let tag = tags(&batch).remove(0);
+
+        let output = output(&mut batch).unwrap();
+        assert!(output.ends_with("{29}\r\n"));
+        assert_eq!(batch.next(), Err(Interrupt::Io(Io::NeedMoreInput)));
+
+        batch.enqueue_input(b"+ Ready for literal data\r\n");
+        let output = output(&mut batch).unwrap();
+        assert!(output.starts_with("Subject: 1\r\n"));
+        assert!(output.ends_with("{29}\r\n"));
+
+        batch.enqueue_input(b"+ Ready for literal data\r\n");
+        let output = output(&mut batch).unwrap();
+        assert!(output.starts_with("Subject: 2\r\n"));
+        assert!(output.ends_with("\r\n\r\n"));
+
+        batch.enqueue_input(format!("{tag} OK [APPENDUID 7 4:5] done\r\n").as_bytes());
+        assert_eq!(batch.next(), Ok(()));
+        assert_eq!(uids(batch.into_results(None)), [4, 5]);
+    }
+
+    #[test]
+    fn rejected() {
+        let capabilities = AppendCapabilities {
+            literal_plus: true,
+            ..Default::default()
+        };
+
+        let mut batch = AppendBatch::new("Archives", msgs(3), capabilities);
+        let tags = tags(&batch);
+        output(&mut batch).unwrap();
+
+        batch.enqueue_input(format!("{} OK [APPENDUID 7 1] done\r\n", tags[0]).as_bytes());
+        batch.enqueue_input(format!("{} NO [TRYCREATE] no mailbox\r\n", tags[1]).as_bytes());
+        assert_eq!(batch.next(), Err(Interrupt::Io(Io::NeedMoreInput)));
+
+        let mut results = batch.into_results(Some("stream closed")).into_iter();
+        assert_eq!(results.next().unwrap().unwrap().get(), 1);
+
+        let err = results.next().unwrap().unwrap_err();
+        assert!(matches!(err, Error::AddMessagesRejectedError(..)));
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+
+        let err = results.next().unwrap().unwrap_err();
+        assert!(matches!(err, Error::AddMessagesInterruptedError(..)));
+        assert_eq!(err.kind(), ErrorKind::Network);
+    }
+}
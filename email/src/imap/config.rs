@@ -3,11 +3,20 @@
 //! This module contains the implementation of the IMAP backend and
 //! all associated structures related to it.
 
+use std::{fmt, sync::Arc};
+
+use imap_client::imap_next::imap_types::auth::AuthMechanism;
+use secret::Secret;
+
 #[doc(inline)]
-use super::{Error, Result};
+use super::{throttle::AuthThrottle, Error, ImapClientBuilder, Result};
 #[cfg(feature = "oauth2")]
-use crate::account::config::oauth2::OAuth2Config;
-use crate::{account::config::passwd::PasswordConfig, tls::Encryption};
+use crate::account::config::oauth2::{OAuth2Config, OAuth2Method};
+use crate::{
+    account::config::passwd::PasswordConfig,
+    doctor::{DoctorReport, Problem},
+    tls::Encryption,
+};
 
 /// Errors related to the IMAP backend configuration.
 
@@ -40,8 +49,19 @@ pub struct ImapConfig {
     ///
     /// Authentication can be done using password or OAuth 2.0.
     /// See [ImapAuthConfig].
+    #[cfg_attr(feature = "derive", serde(default))]
     pub auth: ImapAuthConfig,
 
+    /// The IMAP authentication mechanisms, by order of preference.
+    ///
+    /// The first mechanism both advertised by the server and
+    /// compatible with the authentication configuration is used,
+    /// falling back to the next ones on failure. Defaults to PLAIN
+    /// then LOGIN for password authentication, and to the configured
+    /// OAuth 2.0 method then the other one for OAuth 2.0
+    /// authentication. See [ImapAuthMechanism].
+    pub auth_mechanisms: Option<Vec<ImapAuthMechanism>>,
+
     /// The IMAP extensions configuration.
     pub extensions: Option<ImapExtensionsConfig>,
 
@@ -56,6 +76,28 @@ pub struct ImapConfig {
     /// Defines the number of clients that are created and managed
     /// simultaneously by the IMAP context. Defaults to 1.
     pub clients_pool_size: Option<u8>,
+
+    /// The IMAP protocol strictness.
+    ///
+    /// Defines how malformed server responses are handled. Defaults
+    /// to lenient. See [ImapStrictness].
+    pub strictness: Option<ImapStrictness>,
+
+    /// The IMAP authentication throttling configuration.
+    ///
+    /// Defines how many consecutive authentication failures are
+    /// allowed before further automatic attempts are refused, to
+    /// prevent providers from locking the account. See
+    /// [ImapAuthThrottleConfig].
+    pub auth_throttle: Option<ImapAuthThrottleConfig>,
+
+    /// The IMAP personal namespace prefix.
+    ///
+    /// Defines the prefix of personal mailboxes, including the
+    /// hierarchy delimiter (for example `INBOX.`). Folder names are
+    /// relative to this prefix. When not defined, the prefix is
+    /// deduced from the mailbox hierarchy.
+    pub namespace: Option<String>,
 }
 
 impl ImapConfig {
@@ -63,6 +105,71 @@ pub fn clients_pool_size(&self) -> u8 {
         self.clients_pool_size.unwrap_or(1)
     }
 
+    /// Checks that the IMAP server is reachable and that the
+    /// credentials are valid.
+    ///
+    /// A single IMAP client is built then authenticated, regardless
+    /// of the clients pool size.
+    pub async fn check_up(&self) -> DoctorReport {
+        let mut report = DoctorReport::new();
+
+        if self.host.trim().is_empty() {
+            report.push(Problem::error("imap.host", "IMAP host is empty"));
+            return report;
+        }
+
+        let mut client_builder = ImapClientBuilder::new(Arc::new(self.clone()), None);
+
+        if let Err(err) = client_builder.build().await {
+            report.push(Problem::from_error("imap", &err));
+        }
+
+        report
+    }
+
+    /// Find the authentication mechanisms, by order of preference.
+    pub fn auth_mechanisms(&self) -> Vec<ImapAuthMechanism> {
+        if let Some(mechanisms) = self.auth_mechanisms.as_ref() {
+            return mechanisms.clone();
+        }
+
+        match &self.auth {
+            ImapAuthConfig::Password(_) => {
+                vec![ImapAuthMechanism::Plain, ImapAuthMechanism::Login]
+            }
+            #[cfg(feature = "oauth2")]
+            ImapAuthConfig::OAuth2(oauth2) => match oauth2.method {
+                OAuth2Method::XOAuth2 => {
+                    vec![ImapAuthMechanism::XOAuth2, ImapAuthMechanism::OAuthBearer]
+                }
+                OAuth2Method::OAuthBearer => {
+                    vec![ImapAuthMechanism::OAuthBearer, ImapAuthMechanism::XOAuth2]
+                }
+            },
+        }
+    }
+
+    /// Negotiates the authentication mechanisms to try, by order of
+    /// preference, from the mechanisms advertised by the server.
+    ///
+    /// Mechanisms not advertised by the server, or not compatible
+    /// with the authentication configuration, are discarded.
+    pub fn negotiate_auth_mechanisms(
+        &self,
+        advertised: &[AuthMechanism<'_>],
+        login_supported: bool,
+    ) -> Vec<ImapAuthMechanism> {
+        self.auth_mechanisms()
+            .into_iter()
+            .filter(|mechanism| mechanism.is_compatible(&self.auth))
+            .filter(|mechanism| mechanism.is_advertised(advertised, login_supported))
+            .collect()
+    }
+
+    pub fn strictness(&self) -> ImapStrictness {
+        self.strictness.unwrap_or_default()
+    }
+
     pub fn send_id_after_auth(&self) -> bool {
         self.extensions
             .as_ref()
@@ -71,6 +178,30 @@ pub fn send_id_after_auth(&self) -> bool {
             .unwrap_or_default()
     }
 
+    /// Return `true` if UTF-8 mailbox names should be enabled when
+    /// the server supports them.
+    ///
+    /// Defaults to `true`.
+    pub fn accept_utf8(&self) -> bool {
+        self.extensions
+            .as_ref()
+            .and_then(|ext| ext.utf8.as_ref())
+            .and_then(|utf8| utf8.accept)
+            .unwrap_or(true)
+    }
+
+    /// Return `true` if custom flags should be stored as Gmail
+    /// labels when the server supports them.
+    ///
+    /// Defaults to `false`.
+    pub fn gmail_labels(&self) -> bool {
+        self.extensions
+            .as_ref()
+            .and_then(|ext| ext.gmail.as_ref())
+            .and_then(|gmail| gmail.labels)
+            .unwrap_or_default()
+    }
+
     /// Return `true` if TLS or StartTLS is enabled.
     pub fn is_encryption_enabled(&self) -> bool {
         matches!(
@@ -101,6 +232,30 @@ pub async fn build_credentials(&self) -> Result<String> {
     pub fn find_watch_timeout(&self) -> Option<u64> {
         self.watch.as_ref().and_then(|c| c.find_timeout())
     }
+
+    /// Find the maximum number of consecutive authentication
+    /// failures.
+    pub fn find_auth_max_failures(&self) -> u32 {
+        self.auth_throttle
+            .as_ref()
+            .and_then(|c| c.max_failures)
+            .unwrap_or(ImapAuthThrottleConfig::DEFAULT_MAX_FAILURES)
+    }
+
+    /// Find the authentication cooldown, in seconds.
+    pub fn find_auth_cooldown(&self) -> u64 {
+        self.auth_throttle
+            .as_ref()
+            .and_then(|c| c.cooldown)
+            .unwrap_or(ImapAuthThrottleConfig::DEFAULT_COOLDOWN)
+    }
+
+    /// Reset consecutive authentication failures, so that automatic
+    /// attempts are allowed again before the end of the cooldown
+    /// period.
+    pub fn reset_auth_failures(&self) {
+        AuthThrottle::new(self).reset()
+    }
 }
 
 #[cfg(feature = "sync")]
@@ -200,6 +355,31 @@ pub fn replace_empty_secrets(&mut self, name: impl AsRef<str>) -> Result<()> {
 
         Ok(())
     }
+
+    /// Returns the secrets whose keyring entry is derived from the
+    /// account name, along with the suffix of their entry key (for
+    /// example `imap-passwd` for the `{name}-imap-passwd` entry).
+    ///
+    /// See [`AccountConfig::rename`].
+    ///
+    /// [`AccountConfig::rename`]: crate::account::config::AccountConfig::rename
+    pub fn account_secrets_mut(&mut self) -> Vec<(&'static str, &mut Secret)> {
+        match self {
+            Self::Password(passwd) => vec![("imap-passwd", &mut passwd.0)],
+            #[cfg(feature = "oauth2")]
+            Self::OAuth2(config) => {
+                let mut secrets = Vec::with_capacity(3);
+
+                if let Some(secret) = config.client_secret.as_mut() {
+                    secrets.push(("imap-oauth2-client-secret", secret));
+                }
+
+                secrets.push(("imap-oauth2-access-token", &mut config.access_token));
+                secrets.push(("imap-oauth2-refresh-token", &mut config.refresh_token));
+                secrets
+            }
+        }
+    }
 }
 
 impl Default for ImapAuthConfig {
@@ -241,6 +421,127 @@ fn from(config: ImapAuthConfigDerive) -> Self {
     }
 }
 
+/// The IMAP authentication mechanism.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum ImapAuthMechanism {
+    /// The SASL XOAUTH2 mechanism, using an OAuth 2.0 access token.
+    #[cfg_attr(feature = "derive", serde(alias = "XOAUTH2"))]
+    XOAuth2,
+
+    /// The SASL OAUTHBEARER mechanism ([RFC 7628]), using an OAuth
+    /// 2.0 access token.
+    ///
+    /// [RFC 7628]: https://www.rfc-editor.org/rfc/rfc7628
+    #[cfg_attr(feature = "derive", serde(alias = "OAUTHBEARER"))]
+    OAuthBearer,
+
+    /// The SASL PLAIN mechanism, using a password.
+    #[cfg_attr(feature = "derive", serde(alias = "PLAIN"))]
+    Plain,
+
+    /// The IMAP LOGIN command, using a password.
+    ///
+    /// Available unless the server advertises the LOGINDISABLED
+    /// capability.
+    #[cfg_attr(feature = "derive", serde(alias = "LOGIN"))]
+    Login,
+}
+
+impl ImapAuthMechanism {
+    /// Return `true` if the mechanism can be used with the given
+    /// authentication configuration.
+    pub fn is_compatible(&self, auth: &ImapAuthConfig) -> bool {
+        match auth {
+            ImapAuthConfig::Password(_) => matches!(self, Self::Plain | Self::Login),
+            #[cfg(feature = "oauth2")]
+            ImapAuthConfig::OAuth2(_) => matches!(self, Self::XOAuth2 | Self::OAuthBearer),
+        }
+    }
+
+    /// Return `true` if the mechanism is advertised by the server,
+    /// either via the `AUTH=` capabilities or, for LOGIN, via the
+    /// absence of the LOGINDISABLED capability.
+    pub fn is_advertised(&self, advertised: &[AuthMechanism<'_>], login_supported: bool) -> bool {
+        match self {
+            Self::Login => login_supported,
+            mechanism => advertised.iter().any(|advertised| {
+                advertised
+                    .to_string()
+                    .eq_ignore_ascii_case(&mechanism.to_string())
+            }),
+        }
+    }
+}
+
+impl fmt::Display for ImapAuthMechanism {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::XOAuth2 => write!(f, "XOAUTH2"),
+            Self::OAuthBearer => write!(f, "OAUTHBEARER"),
+            Self::Plain => write!(f, "PLAIN"),
+            Self::Login => write!(f, "LOGIN"),
+        }
+    }
+}
+
+/// The IMAP protocol strictness.
+///
+/// Some servers send responses that violate the RFC, like FETCH
+/// responses missing requested data items. The strictness defines
+/// whether such responses should be rejected or recovered from.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum ImapStrictness {
+    /// Malformed responses are rejected with an error.
+    Strict,
+
+    /// Malformed responses are recovered from on a best-effort
+    /// basis, and a warning is collected by the IMAP context.
+    #[default]
+    Lenient,
+}
+
+/// The IMAP authentication throttling options.
+///
+/// Consecutive authentication failures are tracked per account. Once
+/// the maximum number of failures is reached, further automatic
+/// attempts are refused until the end of the cooldown period.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct ImapAuthThrottleConfig {
+    /// The maximum number of consecutive authentication failures.
+    ///
+    /// Defaults to 3. Use 0 to disable throttling.
+    pub max_failures: Option<u32>,
+
+    /// The cooldown period, in seconds.
+    ///
+    /// Defaults to 15 min.
+    pub cooldown: Option<u64>,
+}
+
+impl ImapAuthThrottleConfig {
+    /// The default maximum number of consecutive authentication
+    /// failures.
+    pub const DEFAULT_MAX_FAILURES: u32 = 3;
+
+    /// The default cooldown period, in seconds.
+    pub const DEFAULT_COOLDOWN: u64 = 15 * 60;
+}
+
 /// The IMAP watch options (IDLE).
 ///
 /// Options dedicated to the IMAP IDLE mode, which is used to watch
@@ -275,6 +576,8 @@ pub fn find_timeout(&self) -> Option<u64> {
 )]
 pub struct ImapExtensionsConfig {
     id: Option<ImapIdExtensionConfig>,
+    utf8: Option<ImapUtf8ExtensionConfig>,
+    gmail: Option<ImapGmailExtensionConfig>,
 }
 
 /// The IMAP configuration dedicated to the ID extension.
@@ -291,3 +594,104 @@ pub struct ImapIdExtensionConfig {
     /// authentication.
     send_after_auth: Option<bool>,
 }
+
+/// The IMAP configuration dedicated to the UTF8 extension.
+///
+/// https://www.rfc-editor.org/rfc/rfc6855.html
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct ImapUtf8ExtensionConfig {
+    /// Enables `UTF8=ACCEPT` straight after authentication when
+    /// advertised by the server, so that mailbox names are exchanged
+    /// in UTF-8 instead of modified UTF-7. Defaults to `true`.
+    accept: Option<bool>,
+}
+
+/// The IMAP configuration dedicated to the Gmail extensions.
+///
+/// https://developers.google.com/gmail/imap/imap-extensions
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct ImapGmailExtensionConfig {
+    /// Stores custom flags as Gmail labels when the server
+    /// advertises `X-GM-EXT-1`, since Gmail does not store IMAP
+    /// keywords. Labels are read and stored through the
+    /// `X-GM-LABELS` message attribute, so listing envelopes costs a
+    /// single extra FETCH. Defaults to `false`.
+    labels: Option<bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use imap_client::imap_next::imap_types::auth::AuthMechanism;
+    #[cfg(feature = "derive")]
+    use secret::Secret;
+
+    #[cfg(feature = "derive")]
+    use super::{ImapAuthConfig, PasswordConfig};
+    use super::{ImapAuthMechanism, ImapConfig};
+
+    #[test]
+    fn negotiate_auth_mechanisms() {
+        let config = ImapConfig::default();
+
+        let mechanisms = config.negotiate_auth_mechanisms(&[AuthMechanism::Plain], true);
+        assert_eq!(
+            mechanisms,
+            vec![ImapAuthMechanism::Plain, ImapAuthMechanism::Login]
+        );
+
+        // LOGINDISABLED
+        let mechanisms = config.negotiate_auth_mechanisms(&[AuthMechanism::Plain], false);
+        assert_eq!(mechanisms, vec![ImapAuthMechanism::Plain]);
+
+        let config = ImapConfig {
+            auth_mechanisms: Some(vec![
+                ImapAuthMechanism::XOAuth2,
+                ImapAuthMechanism::Login,
+                ImapAuthMechanism::Plain,
+            ]),
+            ..Default::default()
+        };
+
+        // OAuth 2.0 mechanisms are not compatible with passwords
+        let mechanisms =
+            config.negotiate_auth_mechanisms(&[AuthMechanism::XOAuth2, AuthMechanism::Plain], true);
+        assert_eq!(
+            mechanisms,
+            vec![ImapAuthMechanism::Login, ImapAuthMechanism::Plain]
+        );
+
+        let mechanisms = config.negotiate_auth_mechanisms(&[AuthMechanism::XOAuth2], false);
+        assert!(mechanisms.is_empty());
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn toml_round_trip() {
+        let config = ImapConfig {
+            host: "localhost".into(),
+            port: 993,
+            login: "login".into(),
+            auth: ImapAuthConfig::Password(PasswordConfig(Secret::new_raw("s3cr3t"))),
+            ..Default::default()
+        };
+
+        // raw secrets are serialized as they are, so that the
+        // configuration can be written back to its file
+        let toml = toml::to_string(&config).unwrap();
+        assert!(toml.contains("s3cr3t"));
+        assert_eq!(toml::from_str::<ImapConfig>(&toml).unwrap(), config);
+
+        // but they never appear in the debug output
+        assert!(!format!("{config:?}").contains("s3cr3t"));
+    }
+}
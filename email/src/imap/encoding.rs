@@ -0,0 +1,193 @@
+//! # IMAP mailbox encoding
+//!
+//! Module dedicated to the encoding of IMAP mailbox names. Folder
+//! names are manipulated as UTF-8 strings across the library, and
+//! converted from and to the encoding expected by the server only
+//! when talking to it: the modified UTF-7 encoding defined by RFC
+//! 3501 (section 5.1.3), or plain UTF-8 once the `UTF8=ACCEPT`
+//! capability is enabled (RFC 6855).
+
+use utf7_imap::encode_utf7_imap as encode_utf7;
+
+/// The capability enabling UTF-8 mailbox names (RFC 6855).
+pub const UTF8_ACCEPT: &str = "UTF8=ACCEPT";
+
+/// The IMAP mailbox encoding enumeration.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum MailboxEncoding {
+    /// The modified UTF-7 encoding, used by default.
+    #[default]
+    Utf7,
+
+    /// The UTF-8 encoding, used when `UTF8=ACCEPT` is enabled.
+    Utf8,
+}
+
+impl MailboxEncoding {
+    /// Encodes the given folder name into a mailbox name.
+    ///
+    /// The folder name is normalized first, so that names already
+    /// encoded using the modified UTF-7 encoding are not encoded
+    /// twice.
+    pub fn encode(&self, folder: impl AsRef<str>) -> String {
+        let folder = normalize(folder);
+
+        match self {
+            Self::Utf7 => encode_utf7(folder),
+            Self::Utf8 => folder,
+        }
+    }
+
+    /// Decodes the given mailbox name into a folder name.
+    ///
+    /// Mailbox names that are not valid modified UTF-7 strings are
+    /// returned untouched.
+    pub fn decode(&self, mbox: impl AsRef<str>) -> String {
+        let mbox = mbox.as_ref();
+
+        match self {
+            Self::Utf7 => decode_utf7(mbox).unwrap_or_else(|| mbox.to_owned()),
+            Self::Utf8 => mbox.to_owned(),
+        }
+    }
+}
+
+/// Normalizes the given folder name into a UTF-8 string.
+///
+/// Names containing valid modified UTF-7 sequences, like `&ZeVnLIqe-`
+/// or `&-`, are decoded. Other names, including names containing a
+/// bare `&`, are left untouched.
+pub fn normalize(folder: impl AsRef<str>) -> String {
+    let folder = folder.as_ref();
+
+    if !folder.contains('&') {
+        return folder.to_owned();
+    }
+
+    decode_utf7(folder).unwrap_or_else(|| folder.to_owned())
+}
+
+/// Decodes the given modified UTF-7 string.
+///
+/// Returns `None` if the string is not a valid, canonical modified
+/// UTF-7 string.
+fn decode_utf7(mbox: &str) -> Option<String> {
+    if !mbox.bytes().all(|b| (0x20..0x7f).contains(&b)) {
+        return None;
+    }
+
+    let mut chunks = mbox.split('&');
+    let mut decoded = chunks.next().unwrap_or_default().to_owned();
+
+    for chunk in chunks {
+        let (encoded, rest) = chunk.split_once('-')?;
+
+        if encoded.is_empty() {
+            decoded.push('&');
+        } else {
+            decoded.push_str(&decode_modified_base64(encoded)?);
+        }
+
+        decoded.push_str(rest);
+    }
+
+    // non-canonical encodings (like encoded printable ASCII
+    // characters) are rejected, so that names containing sequences
+    // that only look like modified UTF-7 are left untouched
+    if encode_utf7(decoded.clone()) != mbox {
+        return None;
+    }
+
+    Some(decoded)
+}
+
+/// Decodes the given modified BASE64 string into a UTF-16BE string.
+fn decode_modified_base64(encoded: &str) -> Option<String> {
+    let mut units = Vec::with_capacity(encoded.len() * 3 / 8);
+    let mut bits = 0u32;
+    let mut bits_len = 0;
+
+    for b in encoded.bytes() {
+        let val = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' => 62,
+            b',' => 63,
+            _ => return None,
+        };
+
+        bits = (bits << 6) | val as u32;
+        bits_len += 6;
+
+        if bits_len >= 16 {
+            bits_len -= 16;
+            units.push((bits >> bits_len) as u16);
+            bits &= (1 << bits_len) - 1;
+        }
+    }
+
+    // remaining bits are padding, they must be zero
+    if bits_len >= 6 || bits != 0 {
+        return None;
+    }
+
+    char::decode_utf16(units).collect::<Result<_, _>>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize, MailboxEncoding};
+
+    #[test]
+    fn utf7_encoding() {
+        let encoding = MailboxEncoding::Utf7;
+
+        for (folder, mbox) in [
+            ("INBOX", "INBOX"),
+            ("Sent Items", "Sent Items"),
+            ("Tom & Jerry", "Tom &- Jerry"),
+            ("R&D", "R&-D"),
+            ("Отправленные", "&BB4EQgQ,BEAEMAQyBDsENQQ9BD0ESwQ1-"),
+            ("台北日本語", "&U,BTF2XlZyyKng-"),
+            ("Archive/2024 📦", "Archive/2024 &2D3c5g-"),
+            ("Café & Crème", "Caf&AOk- &- Cr&AOg-me"),
+        ] {
+            assert_eq!(encoding.encode(folder), mbox, "encode {folder}");
+            assert_eq!(encoding.decode(mbox), folder, "decode {mbox}");
+
+            // already encoded names are not encoded twice
+            assert_eq!(encoding.encode(mbox), mbox, "re-encode {mbox}");
+        }
+
+        // invalid modified UTF-7 names are left untouched
+        assert_eq!(encoding.decode("R&D-Team"), "R&D-Team");
+        assert_eq!(encoding.decode("Tom&Jerry"), "Tom&Jerry");
+        assert_eq!(encoding.decode("&AGEAYgBj-"), "&AGEAYgBj-");
+        assert_eq!(encoding.decode("Café"), "Café");
+    }
+
+    #[test]
+    fn utf8_encoding() {
+        let encoding = MailboxEncoding::Utf8;
+
+        for folder in ["INBOX", "Tom & Jerry", "台北日本語", "Archive/2024 📦"] {
+            assert_eq!(encoding.encode(folder), folder);
+            assert_eq!(encoding.decode(folder), folder);
+        }
+
+        // names encoded using modified UTF-7 are normalized
+        assert_eq!(encoding.encode("&ZeVnLIqe-"), "日本語");
+        assert_eq!(encoding.encode("R&-D"), "R&D");
+        assert_eq!(encoding.decode("R&-D"), "R&-D");
+    }
+
+    #[test]
+    fn normalization() {
+        assert_eq!(normalize("Sent"), "Sent");
+        assert_eq!(normalize("&2D3c5g-"), "📦");
+        assert_eq!(normalize("R&D"), "R&D");
+        assert_eq!(normalize("R&D-Team"), "R&D-Team");
+        assert_eq!(normalize("日本語 & more"), "日本語 & more");
+    }
+}
@@ -1,17 +1,20 @@
-use std::{any::Any, collections::HashSet, result};
+use std::{any::Any, collections::HashSet, result, time::Duration};
 
+use chrono::{DateTime, FixedOffset};
 use imap_client::{
     client::tokio::ClientError,
     imap_next::{
         client::Error as ClientFlowError,
-        imap_types::{auth::AuthMechanism, error::ValidationError},
+        imap_types::{auth::AuthMechanism, error::ValidationError, response::Code},
     },
     stream::Error as StreamError,
+    tasks::tasks::TaskError,
 };
 use thiserror::Error;
 use tokio::task::JoinError;
 
-use crate::{account, AnyBoxedError, AnyError};
+use super::config::ImapAuthMechanism;
+use crate::{account, AnyBoxedError, AnyError, ErrorKind};
 
 /// The global `Result` alias of the module.
 pub type Result<T> = result::Result<T, Error>;
@@ -55,6 +58,8 @@ pub enum Error {
     ExecuteActionOAuthError(#[source] AnyBoxedError),
 
     // ================ v2
+    #[error("cannot handle malformed IMAP response: {0}")]
+    MalformedResponseError(String),
 
     // parse
     #[error("cannot parse IMAP mailbox {1}")]
@@ -129,6 +134,20 @@ pub enum Error {
     AddMessageError(#[source] ClientError),
     #[error("cannot add IMAP message: request timed out")]
     AddMessageTimedOutError,
+    #[error("cannot add IMAP messages: {1}")]
+    AddMessagesRejectedError(Option<String>, String),
+    #[error("cannot add IMAP messages: {0}")]
+    AddMessagesInterruptedError(String),
+    #[error("cannot execute IMAP command: {0}")]
+    ExecuteRawCommandError(String),
+    #[error("cannot execute IMAP command: request timed out")]
+    ExecuteRawCommandTimedOutError,
+    #[error("cannot execute IMAP command: {1}")]
+    RawCommandRejectedError(Option<String>, String),
+    #[error("cannot build IMAP literal from message")]
+    BuildMessageLiteralError(#[source] ValidationError),
+    #[error("cannot build IMAP internal date from {1}: {0}")]
+    BuildInternalDateError(String, DateTime<FixedOffset>),
     #[error("cannot copy IMAP message(s)")]
     CopyMessagesError(#[source] ClientError),
     #[error("cannot copy IMAP message(s): request timed out")]
@@ -182,6 +201,12 @@ pub enum Error {
     AuthenticateXOAuth2NotSupportedError(HashSet<AuthMechanism<'static>>),
     #[error("OAuthBearer authentication not supported (available: {0:?})")]
     AuthenticateOAuthBearerNotSupportedError(HashSet<AuthMechanism<'static>>),
+    #[error("cannot negotiate IMAP auth mechanism: none of {0:?} is both advertised by the server and compatible with the auth configuration (advertised: {1:?})")]
+    NegotiateAuthMechanismError(Vec<ImapAuthMechanism>, HashSet<AuthMechanism<'static>>),
+    #[error("IMAP auth mechanism {0} is not compatible with the auth configuration")]
+    AuthMechanismNotCompatibleError(ImapAuthMechanism),
+    #[error("IMAP authentication of {0} throttled after {1} consecutive failures, retry in {2:?}")]
+    AuthThrottledError(String, u32, Duration),
 
     // tasks
     #[error("cannot execute IMAP action")]
@@ -191,10 +216,176 @@ pub enum Error {
     BuildSessionRetryError(u8),
 }
 
+impl Error {
+    /// Returns the kind of the error.
+    ///
+    /// See [`ErrorKind`] for the available kinds.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::BuildTlsClientMissingProvider => ErrorKind::Config,
+            Self::BuildClientError(err) => err.kind(),
+            Self::JoinClientError(_) => ErrorKind::Other,
+            Self::BuildInsecureClientError(..)
+            | Self::BuildStartTlsClientError(..)
+            | Self::BuildTlsClientError(..)
+            | Self::RequestRetryError(_)
+            | Self::ClientRetryError(_)
+            | Self::RequestRetryTimeoutError
+            | Self::ReceiveGreetingTaskError(_)
+            | Self::StartIdleError(_)
+            | Self::StopIdleError(_)
+            | Self::IdleInterruptedError
+            | Self::BuildSessionRetryError(_) => ErrorKind::Network,
+            Self::CreateMailboxTimedOutError
+            | Self::SelectMailboxTimedOutError
+            | Self::ExamineMailboxTimedOutError
+            | Self::ListMailboxesTimedOutError
+            | Self::ExpungeMailboxTimedOutError
+            | Self::DeleteMailboxTimedOutError
+            | Self::FetchMessagesTimedOutError
+            | Self::ThreadMessagesTimedOutError
+            | Self::StoreFlagsTimedOutError
+            | Self::AddMessageTimedOutError
+            | Self::AddMessagesInterruptedError(_)
+            | Self::ExecuteRawCommandError(_)
+            | Self::ExecuteRawCommandTimedOutError
+            | Self::CopyMessagesTimedOutError
+            | Self::MoveMessagesTimedOutError
+            | Self::NoOpTimedOutError
+            | Self::SortUidsTimedOutError
+            | Self::SearchUidsTimedOutError => ErrorKind::Network,
+            Self::GetPasswdImapError(_)
+            | Self::GetPasswdEmptyImapError
+            | Self::ResetPasswordError(_)
+            | Self::ResetOAuthSecretsError(_)
+            | Self::RefreshAccessTokenError(_)
+            | Self::AccessTokenNotAvailable(_)
+            | Self::ReplacingUnidentifiedFailed(_)
+            | Self::ExecuteActionPasswordError(_)
+            | Self::ExecuteActionOAuthError(_)
+            | Self::AuthenticateError(_)
+            | Self::LoginError(_)
+            | Self::AuthenticatePlainError(_)
+            | Self::AuthenticateXOauth2Error(_)
+            | Self::AuthenticateOAuthBearerError(_)
+            | Self::LoginNotSupportedError
+            | Self::AuthenticatePlainNotSupportedError(_)
+            | Self::AuthenticateXOAuth2NotSupportedError(_)
+            | Self::AuthenticateOAuthBearerNotSupportedError(_)
+            | Self::NegotiateAuthMechanismError(..)
+            | Self::AuthMechanismNotCompatibleError(_)
+            | Self::AuthThrottledError(..) => ErrorKind::Auth,
+            Self::ExecuteActionRetryError(err) | Self::ExecuteActionV2Error(err) => err.kind(),
+            Self::FindAppendedMessageUidError => ErrorKind::NotFound,
+            Self::AddMessagesRejectedError(Some(code), _)
+                if code.eq_ignore_ascii_case("TRYCREATE") =>
+            {
+                ErrorKind::NotFound
+            }
+            Self::AddMessagesRejectedError(..) | Self::RawCommandRejectedError(..) => {
+                ErrorKind::Protocol
+            }
+            Self::SelectMailboxError(err)
+            | Self::ExamineMailboxError(err)
+            | Self::CreateMailboxError(err)
+            | Self::DeleteMailboxError(err)
+            | Self::ExpungeMailboxError(err)
+            | Self::AddMessageError(err)
+            | Self::AppendMessageError(err)
+            | Self::CopyMessagesError(err)
+            | Self::MoveMessagesError(err) => no_response_kind(err).unwrap_or(ErrorKind::Protocol),
+            Self::MalformedResponseError(_)
+            | Self::ParseMailboxError(..)
+            | Self::BuildMessageLiteralError(_)
+            | Self::BuildInternalDateError(..)
+            | Self::EnableCapabilityError(_)
+            | Self::ListMailboxesError(_)
+            | Self::FetchMessagesError(_)
+            | Self::ThreadMessagesError(_)
+            | Self::StoreFlagsError(_)
+            | Self::ExchangeIdsError(_)
+            | Self::SearchMessagesError(_)
+            | Self::SearchUidsError(_)
+            | Self::SortMessagesError(_)
+            | Self::SortUidsError(_)
+            | Self::NoOpError(_)
+            | Self::ExecuteNoOpError(_)
+            | Self::ExecuteNoOpAfterAppendError(_)
+            | Self::ExecuteCheckAfterAppendError(_) => ErrorKind::Protocol,
+        }
+    }
+
+    /// Return `true` if the error is caused by the server rejecting
+    /// the credentials.
+    pub fn is_auth_failure(&self) -> bool {
+        matches!(
+            self,
+            Self::LoginError(_)
+                | Self::AuthenticatePlainError(_)
+                | Self::AuthenticateXOauth2Error(_)
+                | Self::AuthenticateOAuthBearerError(_)
+        )
+    }
+
+    /// Return `true` if the error is caused by the server not
+    /// supporting the authentication mechanism, in which case another
+    /// mechanism can be tried.
+    ///
+    /// A BAD response, or a NO response with the `[CANNOT]` code (RFC
+    /// 5530), means that the mechanism is not supported, whereas any
+    /// other NO response (like `[AUTHENTICATIONFAILED]`) means that
+    /// the credentials were rejected.
+    pub fn is_auth_mechanism_unsupported(&self) -> bool {
+        let (Self::LoginError(err)
+        | Self::AuthenticatePlainError(err)
+        | Self::AuthenticateXOauth2Error(err)
+        | Self::AuthenticateOAuthBearerError(err)) = self
+        else {
+            return false;
+        };
+
+        match err {
+            ClientError::ResolveTask(TaskError::UnexpectedBadResponse(_)) => true,
+            ClientError::ResolveTask(TaskError::UnexpectedNoResponse(body)) => matches!(
+                &body.code,
+                Some(Code::Other(code)) if code.inner().eq_ignore_ascii_case(b"CANNOT")
+            ),
+            _ => false,
+        }
+    }
+}
+
+/// Returns the kind matching the response code of the NO response
+/// the given client error originates from, if any.
+///
+/// `[TRYCREATE]` (RFC 3501) and `[NONEXISTENT]` (RFC 5530) mean that
+/// the target mailbox does not exist, whereas `[ALREADYEXISTS]` (RFC
+/// 5530) means that it already does.
+fn no_response_kind(err: &ClientError) -> Option<ErrorKind> {
+    let ClientError::ResolveTask(TaskError::UnexpectedNoResponse(body)) = err else {
+        return None;
+    };
+
+    match body.code.as_ref()? {
+        Code::TryCreate => Some(ErrorKind::NotFound),
+        Code::Other(code) if code.inner().eq_ignore_ascii_case(b"NONEXISTENT") => {
+            Some(ErrorKind::NotFound)
+        }
+        Code::Other(code) if code.inner().eq_ignore_ascii_case(b"ALREADYEXISTS") => {
+            Some(ErrorKind::Conflict)
+        }
+        _ => None,
+    }
+}
+
 impl AnyError for Error {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn kind(&self) -> ErrorKind {
+        Error::kind(self)
+    }
 }
 
 impl From<Error> for AnyBoxedError {
@@ -1,5 +1,9 @@
+mod append;
 pub mod config;
+pub mod encoding;
 mod error;
+pub(crate) mod raw;
+mod throttle;
 
 use std::{
     collections::HashMap, env, fmt, io::ErrorKind::ConnectionReset, num::NonZeroU32, sync::Arc,
@@ -7,23 +11,31 @@
 };
 
 use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, Timelike};
 use futures::{stream::FuturesUnordered, StreamExt};
 use imap_client::{
     client::tokio::{Client, ClientError},
     imap_next::imap_types::{
-        auth::AuthMechanism,
-        core::{IString, NString, Vec1},
+        core::{IString, Literal, LiteralMode, NString, QuotedChar, Vec1},
+        datetime::DateTime as ImapDateTime,
         extensions::{
+            binary::{Literal8, LiteralOrLiteral8},
+            enable::{CapabilityEnable, Utf8Kind},
             sort::SortCriterion,
             thread::{Thread, ThreadingAlgorithm},
         },
-        fetch::MessageDataItem,
+        fetch::{MacroOrMessageDataItemNames, MessageDataItem, MessageDataItemName},
         flag::{Flag, StoreType},
+        mailbox::Mailbox,
+        response::Capability,
         search::SearchKey,
         sequence::SequenceSet,
     },
     stream::Error as StreamError,
-    tasks::{tasks::select::SelectDataUnvalidated, SchedulerError},
+    tasks::{
+        tasks::{appenduid::AppendUidTask, select::SelectDataUnvalidated},
+        SchedulerError,
+    },
 };
 use once_cell::sync::Lazy;
 use tokio::{
@@ -33,11 +45,16 @@
 };
 use tracing::{debug, instrument, trace, warn};
 
-use self::config::{ImapAuthConfig, ImapConfig};
 #[doc(inline)]
 pub use self::error::{Error, Result};
-#[cfg(feature = "oauth2")]
-use crate::account::config::oauth2::OAuth2Method;
+#[doc(inline)]
+pub use self::throttle::AuthThrottle;
+use self::{
+    append::{AppendBatch, AppendCapabilities, APPEND_BATCH_MAX_SIZE},
+    config::{ImapAuthConfig, ImapAuthMechanism, ImapConfig, ImapStrictness},
+    encoding::{MailboxEncoding, UTF8_ACCEPT},
+    raw::{RawCommand, StatusKind},
+};
 #[cfg(feature = "thread")]
 use crate::envelope::thread::{imap::ThreadImapEnvelopes, ThreadEnvelopes};
 #[cfg(feature = "watch")]
@@ -50,12 +67,17 @@
     },
     envelope::{
         get::{imap::GetImapEnvelope, GetEnvelope},
-        imap::FETCH_ENVELOPES,
-        list::{imap::ListImapEnvelopes, ListEnvelopes},
+        imap::{fetch_previews_items, FETCH_ENVELOPES},
+        list::{
+            imap::{ListImapEnvelopes, MAX_SEQUENCE_SIZE},
+            ListEnvelopes,
+        },
         Envelope, Envelopes,
     },
     flag::{
         add::{imap::AddImapFlags, AddFlags},
+        gmail::GMAIL_CAPABILITY,
+        list::{imap::ListImapFlags, ListFlags},
         remove::{imap::RemoveImapFlags, RemoveFlags},
         set::{imap::SetImapFlags, SetFlags},
     },
@@ -63,9 +85,11 @@
         add::{imap::AddImapFolder, AddFolder},
         delete::{imap::DeleteImapFolder, DeleteFolder},
         expunge::{imap::ExpungeImapFolder, ExpungeFolder},
+        imap::ImapMailboxes,
         list::{imap::ListImapFolders, ListFolders},
+        namespace::{Namespace, Namespaces},
         purge::{imap::PurgeImapFolder, PurgeFolder},
-        Folders,
+        Folders, INBOX,
     },
     message::{
         add::{imap::AddImapMessage, AddMessage},
@@ -83,6 +107,10 @@
     AnyResult,
 };
 
+/// The maximum size of a non-synchronizing literal when the server
+/// only advertises LITERAL-.
+const LITERAL_MINUS_MAX_SIZE: usize = 4096;
+
 static ID_PARAMS: Lazy<Vec<(IString<'static>, NString<'static>)>> = Lazy::new(|| {
     vec![
         (
@@ -120,6 +148,27 @@
     ]
 });
 
+/// The warnings collected by IMAP clients while recovering from
+/// malformed server responses.
+#[derive(Clone, Debug, Default)]
+pub struct ImapWarnings(Arc<std::sync::Mutex<Vec<String>>>);
+
+impl ImapWarnings {
+    fn push(&self, warning: String) {
+        if let Ok(mut warnings) = self.0.lock() {
+            warnings.push(warning);
+        }
+    }
+
+    /// Takes the collected warnings, leaving the collection empty.
+    pub fn take(&self) -> Vec<String> {
+        self.0
+            .lock()
+            .map(|mut warnings| std::mem::take(&mut *warnings))
+            .unwrap_or_default()
+    }
+}
+
 enum ImapRetryState<T> {
     Retry,
     TimedOut,
@@ -149,9 +198,154 @@ pub struct ImapClient {
     mailbox: Option<String>,
 
     retry: Retry,
+
+    /// The protocol strictness.
+    strictness: ImapStrictness,
+
+    /// The warnings collected while recovering from malformed
+    /// responses, shared with the IMAP context.
+    warnings: ImapWarnings,
+
+    /// The namespaces of the server, shared with the IMAP context.
+    namespaces: Namespaces,
+}
+
+/// Handles a malformed server response according to the given
+/// strictness.
+///
+/// In strict mode, an error is returned. In lenient mode, a warning
+/// is collected and the caller is expected to recover.
+fn malformed(strictness: ImapStrictness, warnings: &ImapWarnings, reason: String) -> Result<()> {
+    match strictness {
+        ImapStrictness::Strict => Err(Error::MalformedResponseError(reason)),
+        ImapStrictness::Lenient => {
+            warn!("{reason}, recovering");
+            warnings.push(reason);
+            Ok(())
+        }
+    }
+}
+
+/// Lists the names of the requested data items missing from the
+/// given FETCH response items.
+///
+/// Only the data items needed to build envelopes are checked: UID,
+/// FLAGS, ENVELOPE, BODYSTRUCTURE, RFC822.SIZE and INTERNALDATE.
+fn missing_fetch_items(
+    requested: &MacroOrMessageDataItemNames,
+    items: &[MessageDataItem],
+) -> Vec<&'static str> {
+    let MacroOrMessageDataItemNames::MessageDataItemNames(names) = requested else {
+        return Vec::new();
+    };
+
+    names
+        .iter()
+        .filter_map(|name| {
+            let (label, found) = match name {
+                MessageDataItemName::Uid => (
+                    "UID",
+                    items.iter().any(|i| matches!(i, MessageDataItem::Uid(_))),
+                ),
+                MessageDataItemName::Flags => (
+                    "FLAGS",
+                    items.iter().any(|i| matches!(i, MessageDataItem::Flags(_))),
+                ),
+                MessageDataItemName::Envelope => (
+                    "ENVELOPE",
+                    items
+                        .iter()
+                        .any(|i| matches!(i, MessageDataItem::Envelope(_))),
+                ),
+                MessageDataItemName::BodyStructure => (
+                    "BODYSTRUCTURE",
+                    items
+                        .iter()
+                        .any(|i| matches!(i, MessageDataItem::BodyStructure(_))),
+                ),
+                MessageDataItemName::Rfc822Size => (
+                    "RFC822.SIZE",
+                    items
+                        .iter()
+                        .any(|i| matches!(i, MessageDataItem::Rfc822Size(_))),
+                ),
+                MessageDataItemName::InternalDate => (
+                    "INTERNALDATE",
+                    items
+                        .iter()
+                        .any(|i| matches!(i, MessageDataItem::InternalDate(_))),
+                ),
+                _ => return None,
+            };
+
+            (!found).then_some(label)
+        })
+        .collect()
+}
+
+/// Checks that the given FETCH response items contain the requested
+/// data items, according to the given strictness.
+///
+/// Returns `false` when the items cannot be recovered from and should
+/// be skipped, which is the case when the UID is missing. Other
+/// missing items fall back to their default values.
+fn check_fetch_items(
+    strictness: ImapStrictness,
+    warnings: &ImapWarnings,
+    id: impl fmt::Display,
+    requested: &MacroOrMessageDataItemNames,
+    items: &[MessageDataItem],
+) -> Result<bool> {
+    let mut recoverable = true;
+
+    for name in missing_fetch_items(requested, items) {
+        let reason = format!("missing {name} in FETCH response for message {id}");
+        malformed(strictness, warnings, reason)?;
+        recoverable &= name != "UID";
+    }
+
+    Ok(recoverable)
 }
 
 impl ImapClient {
+    /// Handles a malformed server response according to the client
+    /// strictness.
+    ///
+    /// In strict mode, an error is returned. In lenient mode, a
+    /// warning is collected and the caller is expected to recover.
+    fn malformed(&self, reason: String) -> Result<()> {
+        malformed(self.strictness, &self.warnings, reason)
+    }
+
+    /// Checks the data items of an envelope FETCH response.
+    ///
+    /// Returns `false` when the items cannot be recovered from and
+    /// should be skipped.
+    fn check_envelope_items(
+        &self,
+        id: impl fmt::Display,
+        items: &[MessageDataItem],
+    ) -> Result<bool> {
+        check_fetch_items(self.strictness, &self.warnings, id, &FETCH_ENVELOPES, items)
+    }
+
+    /// Checks the data items of envelope FETCH responses, skipping
+    /// unrecoverable ones.
+    fn check_envelope_fetches<'a>(
+        &self,
+        fetches: HashMap<NonZeroU32, Vec1<MessageDataItem<'a>>>,
+    ) -> Result<HashMap<NonZeroU32, Vec1<MessageDataItem<'a>>>> {
+        let mut checked = HashMap::with_capacity(fetches.len());
+
+        for (seq, items) in fetches {
+            if self.check_envelope_items(seq, items.as_ref())? {
+                checked.insert(seq, items);
+            }
+        }
+
+        Ok(checked)
+    }
+
     async fn retry<T>(
         &mut self,
         res: retry::Result<std::result::Result<T, ClientError>>,
@@ -184,17 +378,7 @@ async fn retry<T>(
                     }
                 };
 
-                debug!("re-connecting…");
-
-                self.inner = self.client_builder.build().await?;
-
-                if let Some(mbox) = &self.mailbox {
-                    self.inner
-                        .select(mbox.clone())
-                        .await
-                        .map_err(Error::SelectMailboxError)?;
-                }
-
+                self.reconnect().await?;
                 self.retry.attempts = 0;
                 Ok(ImapRetryState::Retry)
             }
@@ -204,10 +388,95 @@ async fn retry<T>(
         }
     }
 
+    /// Replaces the inner client by a new one, then selects back the
+    /// previously selected mailbox.
+    async fn reconnect(&mut self) -> Result<()> {
+        debug!("re-connecting…");
+
+        self.inner = self.client_builder.build().await?;
+
+        if let Some(mbox) = &self.mailbox {
+            self.inner
+                .select(mbox.clone())
+                .await
+                .map_err(Error::SelectMailboxError)?;
+        }
+
+        Ok(())
+    }
+
     pub fn ext_sort_supported(&self) -> bool {
         self.inner.state.ext_sort_supported()
     }
 
+    /// Returns `true` if custom flags are stored as Gmail labels.
+    ///
+    /// Requires the `extensions.gmail.labels` option, and the server
+    /// to advertise the `X-GM-EXT-1` capability.
+    pub fn gmail_labels_enabled(&self) -> bool {
+        self.imap_config.gmail_labels()
+            && self.inner.state.capabilities_iter().any(|capability| {
+                capability
+                    .to_string()
+                    .eq_ignore_ascii_case(GMAIL_CAPABILITY)
+            })
+    }
+
+    /// Returns the encoding of mailbox names of the current session.
+    pub fn mailbox_encoding(&self) -> MailboxEncoding {
+        self.client_builder.mailbox_encoding
+    }
+
+    /// Turns the given folder name into an IMAP mailbox name.
+    ///
+    /// The personal namespace prefix is prepended when missing, then
+    /// the name is encoded using the mailbox encoding of the session,
+    /// see [`MailboxEncoding::encode`].
+    pub fn encode_mailbox(&self, folder: impl AsRef<str>) -> String {
+        let mbox = self.namespaces.to_mailbox(folder);
+        self.mailbox_encoding().encode(mbox)
+    }
+
+    /// Returns `true` if a message of the given size can be sent
+    /// using a non-synchronizing literal.
+    ///
+    /// LITERAL+ allows non-synchronizing literals of any size, while
+    /// LITERAL- restricts them to 4096 bytes (RFC 7888).
+    fn non_sync_literal_supported(&self, size: usize) -> bool {
+        self.inner
+            .state
+            .capabilities_iter()
+            .any(|capability| match capability {
+                Capability::LiteralPlus => true,
+                Capability::LiteralMinus => size <= LITERAL_MINUS_MAX_SIZE,
+                _ => false,
+            })
+    }
+
+    /// Builds the literal of a message to append, using a
+    /// non-synchronizing literal when advertised by the server so
+    /// that the message is sent without waiting for a continuation
+    /// request.
+    fn append_literal(&self, msg: Vec<u8>) -> Result<LiteralOrLiteral8<'static>> {
+        let mode = if self.non_sync_literal_supported(msg.len()) {
+            LiteralMode::NonSync
+        } else {
+            LiteralMode::Sync
+        };
+
+        if self.inner.state.ext_binary_supported() {
+            let data = msg.into();
+            return Ok(LiteralOrLiteral8::Literal8(Literal8 { data, mode }));
+        }
+
+        let literal = Literal::try_from(msg).map_err(Error::BuildMessageLiteralError)?;
+
+        Ok(LiteralOrLiteral8::Literal(match mode {
+            LiteralMode::Sync => literal,
+            LiteralMode::NonSync => literal.into_non_sync(),
+        }))
+    }
+
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn noop(&mut self) -> Result<()> {
         self.retry.reset();
@@ -281,11 +550,10 @@ pub async fn create_mailbox(&mut self, mbox: impl ToString) -> Result<()> {
         }
     }
 
-    #[instrument(skip_all, fields(client = self.id))]
-    pub async fn list_all_mailboxes(&mut self, config: &AccountConfig) -> Result<Folders> {
+    async fn list_mailboxes(&mut self) -> Result<ImapMailboxes> {
         self.retry.reset();
 
-        let mboxes = loop {
+        loop {
             let res = self.retry.timeout(self.inner.list("", "*")).await;
 
             match self.retry(res).await? {
@@ -293,13 +561,61 @@ pub async fn list_all_mailboxes(&mut self, config: &AccountConfig) -> Result<Fol
                 ImapRetryState::TimedOut => break Err(Error::ListMailboxesTimedOutError),
                 ImapRetryState::Ok(res) => break res.map_err(Error::ListMailboxesError),
             }
-        }?;
-
-        let folders = Folders::from_imap_mailboxes(config, mboxes);
+        }
+    }
 
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn list_all_mailboxes(&mut self, config: &AccountConfig) -> Result<Folders> {
+        let mboxes = self.list_mailboxes().await?;
+        let encoding = self.mailbox_encoding();
+        let folders = Folders::from_imap_mailboxes(config, &self.namespaces, encoding, mboxes);
         Ok(folders)
     }
 
+    /// Deduces the namespaces of the server from the mailbox
+    /// hierarchy.
+    ///
+    /// The NAMESPACE command is not supported by the underlying IMAP
+    /// client yet. Instead, personal mailboxes are considered
+    /// prefixed by the inbox when all mailboxes are children of the
+    /// inbox, like on Courier or Cyrus servers.
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn detect_namespaces(&mut self) -> Result<Namespaces> {
+        let mboxes = self.list_mailboxes().await?;
+
+        let delimiter = mboxes
+            .iter()
+            .find_map(|(_, delim, _)| delim.as_ref())
+            .map(QuotedChar::inner);
+
+        let prefix = match delimiter {
+            None => String::new(),
+            Some(delim) => {
+                let prefix = format!("{INBOX}{delim}");
+
+                let mut mboxes = mboxes
+                    .iter()
+                    .filter_map(|(mbox, _, _)| match mbox {
+                        Mailbox::Inbox => None,
+                        Mailbox::Other(mbox) => Some(String::from_utf8_lossy(mbox.as_ref())),
+                    })
+                    .peekable();
+
+                if mboxes.peek().is_some() && mboxes.all(|mbox| mbox.starts_with(&prefix)) {
+                    prefix
+                } else {
+                    String::new()
+                }
+            }
+        };
+
+        debug!("detected personal namespace prefix {prefix:?}");
+
+        Ok(Namespaces::from(vec![Namespace::personal(
+            prefix, delimiter,
+        )]))
+    }
+
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn expunge_mailbox(&mut self, mbox: impl ToString) -> Result<usize> {
         self.select_mailbox(mbox).await?;
@@ -374,6 +690,8 @@ pub async fn fetch_envelopes(&mut self, uids: SequenceSet) -> Result<Envelopes>
             }
         }?;
 
+        let fetches = self.check_envelope_fetches(fetches)?;
+
         Ok(Envelopes::from_imap_data_items(fetches))
     }
 
@@ -395,7 +713,8 @@ pub async fn fetch_envelopes_map(
             }
         }?;
 
-        let map = fetches
+        let map = self
+            .check_envelope_fetches(fetches)?
             .into_values()
             .map(|items| {
                 let envelope = Envelope::from_imap_data_items(items.as_ref());
@@ -406,6 +725,45 @@ pub async fn fetch_envelopes_map(
         Ok(map)
     }
 
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn fetch_previews(
+        &mut self,
+        uids: &[NonZeroU32],
+        len: usize,
+    ) -> Result<HashMap<String, String>> {
+        let items = fetch_previews_items(len);
+        let mut previews = HashMap::with_capacity(uids.len());
+
+        for uids in uids.chunks(MAX_SEQUENCE_SIZE as usize) {
+            let Ok(uids) = SequenceSet::try_from(uids.to_vec()) else {
+                continue;
+            };
+
+            self.retry.reset();
+
+            let fetches = loop {
+                let res = self
+                    .retry
+                    .timeout(self.inner.uid_fetch(uids.clone(), items.clone()))
+                    .await;
+
+                match self.retry(res).await? {
+                    ImapRetryState::Retry => continue,
+                    ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
+                    ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
+                }
+            }?;
+
+            previews.extend(
+                fetches.into_values().filter_map(|items| {
+                    Envelope::preview_from_imap_data_items(items.as_ref(), len)
+                }),
+            );
+        }
+
+        Ok(previews)
+    }
+
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn fetch_first_envelope(&mut self, uid: u32) -> Result<Envelope> {
         let items = loop {
@@ -422,7 +780,14 @@ pub async fn fetch_first_envelope(&mut self, uid: u32) -> Result<Envelope> {
             }
         }?;
 
-        Ok(Envelope::from_imap_data_items(items.as_ref()))
+        let mut envelope = Envelope::from_imap_data_items(items.as_ref());
+
+        if !self.check_envelope_items(uid, items.as_ref())? {
+            // the requested UID is known, so it can be restored
+            envelope.id = uid.to_string();
+        }
+
+        Ok(envelope)
     }
 
     #[instrument(skip_all, fields(client = self.id))]
@@ -440,6 +805,8 @@ pub async fn fetch_envelopes_by_sequence(&mut self, seq: SequenceSet) -> Result<
             }
         }?;
 
+        let fetches = self.check_envelope_fetches(fetches)?;
+
         Ok(Envelopes::from_imap_data_items(fetches))
     }
 
@@ -511,7 +878,15 @@ pub async fn sort_envelopes(
             }
         }?;
 
-        Ok(Envelopes::from(fetches))
+        let mut checked = Vec::with_capacity(fetches.len());
+
+        for (i, items) in fetches.into_iter().enumerate() {
+            if self.check_envelope_items(i + 1, items.as_ref())? {
+                checked.push(items);
+            }
+        }
+
+        Ok(Envelopes::from(checked))
     }
 
     #[instrument(skip_all, fields(client = self.id))]
@@ -741,6 +1116,286 @@ pub async fn add_message(
         id.ok_or(Error::FindAppendedMessageUidError)
     }
 
+    /// Adds the given message with the given flags and internal date
+    /// to the given mailbox.
+    ///
+    /// The internal date is sent as the date argument of the APPEND
+    /// command, truncated to the second. Finding the UID of the added
+    /// message this way requires UIDPLUS: without it, the message is
+    /// added using [`ImapClient::add_message`] and the server sets
+    /// the internal date.
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn add_message_with_date(
+        &mut self,
+        mbox: impl ToString,
+        flags: Vec<Flag<'static>>,
+        msg: Vec<u8>,
+        date: DateTime<FixedOffset>,
+    ) -> Result<NonZeroU32> {
+        let mbox = mbox.to_string();
+
+        if !self.inner.state.ext_uidplus_supported() {
+            warn!("UIDPLUS not supported, ignoring internal date {date}");
+            return self.add_message(&mbox, flags, msg).await;
+        }
+
+        let mailbox = Mailbox::try_from(mbox.clone())
+            .map_err(|err| Error::ParseMailboxError(err, mbox.clone()))?;
+
+        let date = date.with_nanosecond(0).unwrap_or(date);
+        let date = ImapDateTime::try_from(date)
+            .map_err(|err| Error::BuildInternalDateError(err.to_string(), date))?;
+
+        let task = AppendUidTask::new(mailbox, self.append_literal(msg)?)
+            .with_flags(flags)
+            .with_date(date);
+
+        self.retry.reset();
+
+        let uid = loop {
+            let res = self.retry.timeout(self.inner.resolve(task.clone())).await;
+
+            match self.retry(res).await? {
+                ImapRetryState::Retry => continue,
+                ImapRetryState::TimedOut => break Err(Error::AddMessageTimedOutError),
+                ImapRetryState::Ok(res) => {
+                    break res
+                        .and_then(|res| res.map_err(ClientError::ResolveTask))
+                        .map_err(Error::AddMessageError)
+                }
+            }
+        }?;
+
+        let (uid, _) = uid.ok_or(Error::FindAppendedMessageUidError)?;
+        Ok(uid)
+    }
+
+    /// Returns the server capabilities driving batched APPEND, see
+    /// [`AppendBatch`].
+    fn append_capabilities(&self) -> AppendCapabilities {
+        let mut capabilities = AppendCapabilities {
+            binary: self.inner.state.ext_binary_supported(),
+            ..Default::default()
+        };
+
+        for capability in self.inner.state.capabilities_iter() {
+            match capability {
+                Capability::LiteralPlus => capabilities.literal_plus = true,
+                Capability::LiteralMinus => capabilities.literal_minus = true,
+                capability if capability.to_string().eq_ignore_ascii_case("MULTIAPPEND") => {
+                    capabilities.multiappend = true
+                }
+                _ => (),
+            }
+        }
+
+        capabilities
+    }
+
+    /// Adds the given messages with their flags to the given
+    /// mailbox, using the same connection for the whole batch.
+    ///
+    /// Messages are grouped into batches of at most 8 MiB. When the
+    /// server advertises MULTIAPPEND, each batch is sent as a single
+    /// APPEND command carrying all its messages. Otherwise, when the
+    /// server advertises LITERAL+ (or LITERAL-), the APPEND commands
+    /// of a batch are written at once and their tagged responses are
+    /// collected afterwards. Either way, a batch costs a single
+    /// round-trip.
+    ///
+    /// Returns one result per message, in order, so that a failing
+    /// message does not hide the UIDs of the messages already added.
+    /// Without UIDPLUS, or without any of the capabilities above,
+    /// messages are added one by one.
+    #[instrument(skip_all, fields(client = self.id, count = msgs.len()))]
+    pub async fn add_messages(
+        &mut self,
+        mbox: impl ToString,
+        msgs: Vec<(Vec<Flag<'static>>, Vec<u8>)>,
+    ) -> Result<Vec<Result<NonZeroU32>>> {
+        let mbox = mbox.to_string();
+        let mut uids = Vec::with_capacity(msgs.len());
+
+        if !self.inner.state.ext_uidplus_supported() {
+            for (flags, msg) in msgs {
+                uids.push(self.add_message(&mbox, flags, msg).await);
+            }
+
+            return Ok(uids);
+        }
+
+        let mailbox = Mailbox::try_from(mbox.clone())
+            .map_err(|err| Error::ParseMailboxError(err, mbox.clone()))?;
+
+        let capabilities = self.append_capabilities();
+
+        if !capabilities.batch_supported() || !append::mailbox_supported(&mbox) {
+            for (flags, msg) in msgs {
+                let uid = self.append_uid(mailbox.clone(), flags, msg).await;
+                uids.push(uid);
+            }
+
+            return Ok(uids);
+        }
+
+        let mut msgs = msgs.into_iter().peekable();
+
+        while msgs.peek().is_some() {
+            let mut batch = Vec::new();
+            let mut size = 0;
+
+            while let Some((_, msg)) = msgs.peek() {
+                if !capabilities.message_supported(msg) {
+                    break;
+                }
+
+                if !batch.is_empty() && size + msg.len() > APPEND_BATCH_MAX_SIZE {
+                    break;
+                }
+
+                size += msg.len();
+                batch.extend(msgs.next());
+            }
+
+            if batch.is_empty() {
+                if let Some((flags, msg)) = msgs.next() {
+                    let uid = self.append_uid(mailbox.clone(), flags, msg).await;
+                    uids.push(uid);
+                }
+                continue;
+            }
+
+            uids.extend(self.append_batch(&mbox, batch, capabilities).await?);
+        }
+
+        Ok(uids)
+    }
+
+    /// Appends the given batch of messages to the given mailbox, then
+    /// returns one result per message.
+    ///
+    /// A batch is never retried, since some of its messages may have
+    /// been added already. When the batch is interrupted, the client
+    /// re-connects and the messages without tagged response are
+    /// reported as failed.
+    async fn append_batch(
+        &mut self,
+        mbox: &str,
+        msgs: Vec<(Vec<Flag<'static>>, Vec<u8>)>,
+        capabilities: AppendCapabilities,
+    ) -> Result<Vec<Result<NonZeroU32>>> {
+        let mut batch = AppendBatch::new(mbox, msgs, capabilities);
+        debug!(commands = batch.commands_count(), "appending batch");
+
+        let res = self.retry.timeout(self.inner.stream.next(&mut batch)).await;
+
+        let reason = match res {
+            Ok(Ok(())) => return Ok(batch.into_results(None)),
+            Ok(Err(StreamError::State(reason))) => reason,
+            Ok(Err(StreamError::Io(err))) => err.to_string(),
+            Ok(Err(StreamError::Closed)) => String::from("stream closed"),
+            Err(_) => String::from("request timed out"),
+        };
+
+        warn!("cannot append batch, re-connecting: {reason}");
+        self.reconnect().await?;
+
+        Ok(batch.into_results(Some(&reason)))
+    }
+
+    /// Executes the given raw command, then returns its untagged
+    /// responses, see [`RawCommand`].
+    ///
+    /// Meant for commands that the IMAP client cannot express, like
+    /// the Gmail extensions. Commands must be idempotent, since they
+    /// are sent again after a time out or a re-connection.
+    #[instrument(skip_all, fields(client = self.id))]
+    pub(crate) async fn execute_raw(&mut self, cmd: impl AsRef<str>) -> Result<Vec<Vec<u8>>> {
+        let cmd = cmd.as_ref();
+        debug!("executing raw command {cmd}");
+
+        self.retry.reset();
+
+        loop {
+            let mut command = RawCommand::new(cmd);
+            let res = self
+                .retry
+                .timeout(self.inner.stream.next(&mut command))
+                .await;
+
+            match self.retry.next(res) {
+                RetryState::Retry => {
+                    debug!(attempt = self.retry.attempts, "request timed out");
+                    continue;
+                }
+                RetryState::TimedOut => {
+                    break Err(Error::ExecuteRawCommandTimedOutError);
+                }
+                RetryState::Ok(Err(StreamError::Closed)) => {
+                    debug!("stream closed");
+                    self.reconnect().await?;
+                    continue;
+                }
+                RetryState::Ok(Err(StreamError::Io(err))) if err.kind() == ConnectionReset => {
+                    debug!("connection reset");
+                    self.reconnect().await?;
+                    continue;
+                }
+                RetryState::Ok(Err(StreamError::Io(err))) => {
+                    break Err(Error::ExecuteRawCommandError(err.to_string()));
+                }
+                RetryState::Ok(Err(StreamError::State(err))) => {
+                    break Err(Error::ExecuteRawCommandError(err));
+                }
+                RetryState::Ok(Ok(())) => {
+                    let Some((status, untagged)) = command.into_responses() else {
+                        let err = String::from("missing tagged response");
+                        break Err(Error::ExecuteRawCommandError(err));
+                    };
+
+                    break match status.kind {
+                        StatusKind::Ok => Ok(untagged),
+                        StatusKind::No | StatusKind::Bad => {
+                            Err(Error::RawCommandRejectedError(status.code, status.text))
+                        }
+                    };
+                }
+            }
+        }
+    }
+
+    /// Appends the given message with the given flags to the given
+    /// mailbox, then returns its UID.
+    ///
+    /// Requires UIDPLUS.
+    async fn append_uid(
+        &mut self,
+        mailbox: Mailbox<'static>,
+        flags: Vec<Flag<'static>>,
+        msg: Vec<u8>,
+    ) -> Result<NonZeroU32> {
+        let task = AppendUidTask::new(mailbox, self.append_literal(msg)?).with_flags(flags);
+
+        self.retry.reset();
+
+        let uid = loop {
+            let res = self.retry.timeout(self.inner.resolve(task.clone())).await;
+
+            match self.retry(res).await? {
+                ImapRetryState::Retry => continue,
+                ImapRetryState::TimedOut => break Err(Error::AddMessageTimedOutError),
+                ImapRetryState::Ok(res) => {
+                    break res
+                        .and_then(|res| res.map_err(ClientError::ResolveTask))
+                        .map_err(Error::AddMessageError)
+                }
+            }
+        }?;
+
+        let (uid, _) = uid.ok_or(Error::FindAppendedMessageUidError)?;
+        Ok(uid)
+    }
+
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn fetch_messages(&mut self, uids: SequenceSet) -> Result<Messages> {
         let mut fetches = loop {
@@ -842,10 +1497,32 @@ pub struct ImapContext {
     /// The IMAP configuration.
     pub imap_config: Arc<ImapConfig>,
 
+    /// The protocol strictness.
+    pub strictness: ImapStrictness,
+
     clients: Vec<Arc<Mutex<ImapClient>>>,
+
+    warnings: ImapWarnings,
+
+    namespaces: Namespaces,
 }
 
 impl ImapContext {
+    /// Returns the namespaces of the server.
+    ///
+    /// Namespaces are resolved when building the context, either
+    /// from [`ImapConfig::namespace`] or from the mailbox hierarchy,
+    /// see [`ImapClient::detect_namespaces`].
+    pub fn namespaces(&self) -> &Namespaces {
+        &self.namespaces
+    }
+
+    /// Takes the warnings collected while recovering from malformed
+    /// server responses in lenient mode.
+    pub fn take_warnings(&self) -> Vec<String> {
+        self.warnings.take()
+    }
+
     pub async fn client(&self) -> MutexGuard<'_, ImapClient> {
         loop {
             let lock = self
@@ -881,17 +1558,21 @@ pub struct ImapContextBuilder {
     prebuilt_credentials: Option<String>,
 
     pool_size: u8,
+
+    strictness: ImapStrictness,
 }
 
 impl ImapContextBuilder {
     pub fn new(account_config: Arc<AccountConfig>, imap_config: Arc<ImapConfig>) -> Self {
         let pool_size = imap_config.clients_pool_size();
+        let strictness = imap_config.strictness();
 
         Self {
             account_config,
             imap_config,
             prebuilt_credentials: None,
             pool_size,
+            strictness,
         }
     }
 
@@ -909,6 +1590,11 @@ pub fn with_pool_size(mut self, pool_size: u8) -> Self {
         self.pool_size = pool_size;
         self
     }
+
+    pub fn with_strictness(mut self, strictness: ImapStrictness) -> Self {
+        self.strictness = strictness;
+        self
+    }
 }
 
 #[cfg(feature = "sync")]
@@ -976,6 +1662,10 @@ fn remove_flags(&self) -> Option<BackendFeature<Self::Context, dyn RemoveFlags>>
         Some(Arc::new(RemoveImapFlags::some_new_boxed))
     }
 
+    fn list_flags(&self) -> Option<BackendFeature<Self::Context, dyn ListFlags>> {
+        Some(Arc::new(ListImapFlags::some_new_boxed))
+    }
+
     fn add_message(&self) -> Option<BackendFeature<Self::Context, dyn AddMessage>> {
         Some(Arc::new(AddImapMessage::some_new_boxed))
     }
@@ -1010,6 +1700,9 @@ async fn build(self) -> AnyResult<Self::Context> {
 
         debug!("building {} IMAP clients", self.pool_size);
 
+        let strictness = self.strictness;
+        let warnings = ImapWarnings::default();
+
         let clients = FuturesUnordered::from_iter((0..self.pool_size).map(move |i| {
             let mut client_builder = client_builder.clone();
             tokio::spawn(async move {
@@ -1028,17 +1721,46 @@ async fn build(self) -> AnyResult<Self::Context> {
                 inner,
                 mailbox: Default::default(),
                 retry: Default::default(),
+                strictness,
+                warnings: warnings.clone(),
+                namespaces: Default::default(),
             }))),
         })
         .collect::<Vec<_>>()
         .await
         .into_iter()
-        .collect::<Result<_>>()?;
+        .collect::<Result<Vec<_>>>()?;
+
+        let namespaces = match &self.imap_config.namespace {
+            Some(prefix) => {
+                let delimiter = prefix.chars().last();
+                Namespaces::from(vec![Namespace::personal(prefix, delimiter)])
+            }
+            None => match clients.first() {
+                None => Namespaces::default(),
+                Some(client) => client
+                    .lock()
+                    .await
+                    .detect_namespaces()
+                    .await
+                    .unwrap_or_else(|err| {
+                        warn!("cannot detect IMAP namespaces, using root level: {err}");
+                        Namespaces::default()
+                    }),
+            },
+        };
+
+        for client in &clients {
+            client.lock().await.namespaces = namespaces.clone();
+        }
 
         Ok(ImapContext {
             account_config: self.account_config,
             imap_config: self.imap_config,
+            strictness,
             clients,
+            warnings,
+            namespaces,
         })
     }
 }
@@ -1075,6 +1797,9 @@ async fn check_up(&self) -> AnyResult<()> {
 pub struct ImapClientBuilder {
     pub config: Arc<ImapConfig>,
     pub credentials: Option<String>,
+
+    /// The encoding of mailbox names of the last built session.
+    pub mailbox_encoding: MailboxEncoding,
 }
 
 impl ImapClientBuilder {
@@ -1082,6 +1807,7 @@ pub fn new(config: Arc<ImapConfig>, credentials: Option<String>) -> Self {
         Self {
             config,
             credentials,
+            mailbox_encoding: Default::default(),
         }
     }
 
@@ -1094,6 +1820,9 @@ pub fn new(config: Arc<ImapConfig>, credentials: Option<String>) -> Self {
     /// a row.
     #[instrument(name = "client::build", skip(self))]
     pub async fn build(&mut self) -> Result<Client> {
+        let throttle = AuthThrottle::new(&self.config);
+        throttle.check()?;
+
         let mut client = match &self.config.encryption {
             Some(Encryption::None) => Client::insecure(&self.config.host, self.config.port)
                 .await
@@ -1157,10 +1886,116 @@ pub async fn build(&mut self) -> Result<Client> {
             .state
             .set_some_idle_timeout(self.config.find_watch_timeout().map(Duration::from_secs));
 
-        match &self.config.auth {
-            ImapAuthConfig::Password(passwd) => {
-                debug!("using password authentication");
+        match self.authenticate(&mut client).await {
+            Ok(()) => throttle.reset(),
+            Err(err) => {
+                if err.is_auth_failure() {
+                    throttle.record_failure();
+                }
+
+                return Err(err);
+            }
+        }
+
+        if self.config.send_id_after_auth() {
+            let params = ID_PARAMS.clone();
+            debug!(?params, "client identity");
 
+            let params = client
+                .id(Some(ID_PARAMS.clone()))
+                .await
+                .map_err(Error::ExchangeIdsError)?;
+
+            debug!(?params, "server identity");
+        }
+
+        self.mailbox_encoding = MailboxEncoding::Utf7;
+
+        let utf8_supported = client.state.ext_enable_supported()
+            && client
+                .state
+                .capabilities_iter()
+                .any(|capability| capability.to_string().eq_ignore_ascii_case(UTF8_ACCEPT));
+
+        if self.config.accept_utf8() && utf8_supported {
+            debug!("enabling UTF8 capability…");
+
+            let enabled = client
+                .enable(Some(CapabilityEnable::Utf8(Utf8Kind::Accept)))
+                .await
+                .map_err(Error::EnableCapabilityError)?
+                .unwrap_or_default();
+
+            if enabled.contains(&CapabilityEnable::Utf8(Utf8Kind::Accept)) {
+                self.mailbox_encoding = MailboxEncoding::Utf8;
+            } else {
+                warn!("cannot enable UTF8 capability, using modified UTF-7");
+            }
+        }
+
+        Ok(client)
+    }
+
+    /// Authenticates the given client, using either password or
+    /// OAuth 2.0.
+    ///
+    /// The mechanism is negotiated from the capabilities advertised
+    /// by the server and the configured preference order. When the
+    /// server does not support a mechanism, the next negotiated
+    /// mechanism is tried. When the server rejects the credentials,
+    /// authentication stops straight away, so that a wrong password
+    /// is not sent once per mechanism.
+    async fn authenticate(&mut self, client: &mut Client) -> Result<()> {
+        let advertised: Vec<_> = client.state.supported_auth_mechanisms().cloned().collect();
+        let login_supported = client.state.login_supported();
+
+        debug!(?advertised, login_supported, "supported auth mechanisms");
+
+        let mechanisms = self
+            .config
+            .negotiate_auth_mechanisms(&advertised, login_supported);
+
+        if mechanisms.is_empty() {
+            let preferred = self.config.auth_mechanisms();
+            let advertised = advertised.into_iter().collect();
+            return Err(Error::NegotiateAuthMechanismError(preferred, advertised));
+        }
+
+        let mut mechanisms = mechanisms.into_iter().peekable();
+
+        while let Some(mechanism) = mechanisms.next() {
+            debug!(%mechanism, "trying auth mechanism…");
+
+            match self.authenticate_with(client, mechanism).await {
+                Ok(()) => {
+                    debug!(%mechanism, "authentication succeeded!");
+                    return Ok(());
+                }
+                Err(err) if err.is_auth_mechanism_unsupported() && mechanisms.peek().is_some() => {
+                    warn!(%mechanism, "auth mechanism not supported, trying next mechanism");
+                    debug!("{err:?}");
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Authenticates the given client using the given mechanism.
+    async fn authenticate_with(
+        &mut self,
+        client: &mut Client,
+        mechanism: ImapAuthMechanism,
+    ) -> Result<()> {
+        let config = self.config.clone();
+        let login = config.login.as_str();
+
+        match (&config.auth, mechanism) {
+            (
+                ImapAuthConfig::Password(passwd),
+                ImapAuthMechanism::Plain | ImapAuthMechanism::Login,
+            ) => {
                 let passwd = match self.credentials.as_ref() {
                     Some(passwd) => passwd.to_string(),
                     None => passwd
@@ -1173,176 +2008,264 @@ pub async fn build(&mut self) -> Result<Client> {
                         .to_owned(),
                 };
 
-                let mechanisms: Vec<_> =
-                    client.state.supported_auth_mechanisms().cloned().collect();
-                let mut authenticated = false;
-
-                debug!(?mechanisms, "supported auth mechanisms");
-
-                for mechanism in mechanisms {
-                    debug!(?mechanism, "trying auth mechanism…");
-
-                    let auth = match mechanism {
-                        AuthMechanism::Plain => {
-                            client
-                                .authenticate_plain(self.config.login.as_str(), passwd.as_str())
-                                .await
-                        }
-                        // TODO
-                        // AuthMechanism::Login => {
-                        //     client
-                        //         .authenticate_login(self.config.login.as_str(), passwd.as_str())
-                        //         .await
-                        // }
-                        _ => {
-                            continue;
-                        }
-                    };
-
-                    if let Err(ref err) = auth {
-                        warn!(?mechanism, ?err, "authentication failed");
-                    }
-
-                    if auth.is_ok() {
-                        debug!(?mechanism, "authentication succeeded!");
-                        authenticated = true;
-                        break;
-                    }
+                if let ImapAuthMechanism::Plain = mechanism {
+                    client
+                        .authenticate_plain(login, passwd.as_str())
+                        .await
+                        .map_err(Error::AuthenticatePlainError)?;
+                } else {
+                    client
+                        .login(login, passwd.as_str())
+                        .await
+                        .map_err(Error::LoginError)?;
                 }
-
-                if !authenticated {
-                    if !client.state.login_supported() {
-                        return Err(Error::LoginNotSupportedError);
-                    }
-
-                    debug!("trying login…");
+            }
+            #[cfg(feature = "oauth2")]
+            (ImapAuthConfig::OAuth2(oauth2), ImapAuthMechanism::XOAuth2) => {
+                // prebuilt credentials are not used, since the
+                // access token may need to be refreshed
+                let access_token = oauth2
+                    .access_token()
+                    .await
+                    .map_err(Error::RefreshAccessTokenError)?;
+
+                let auth = client
+                    .authenticate_xoauth2(login, access_token.as_str())
+                    .await;
+
+                if auth.is_err() {
+                    warn!("authentication failed, refreshing access token and retrying…");
+
+                    let access_token = oauth2
+                        .refresh_access_token()
+                        .await
+                        .map_err(Error::RefreshAccessTokenError)?;
 
                     client
-                        .login(self.config.login.as_str(), passwd.as_str())
+                        .authenticate_xoauth2(login, access_token.as_str())
                         .await
-                        .map_err(Error::LoginError)?;
+                        .map_err(Error::AuthenticateXOauth2Error)?;
 
-                    debug!("login succeeded!");
+                    self.credentials = Some(access_token);
                 }
             }
             #[cfg(feature = "oauth2")]
-            ImapAuthConfig::OAuth2(oauth2) => {
-                debug!("using OAuth 2.0 authentication");
-
-                match oauth2.method {
-                    OAuth2Method::XOAuth2 => {
-                        if !client.state.supports_auth_mechanism(AuthMechanism::XOAuth2) {
-                            let auth = client.state.supported_auth_mechanisms().cloned().collect();
-                            return Err(Error::AuthenticateXOAuth2NotSupportedError(auth));
-                        }
+            (ImapAuthConfig::OAuth2(oauth2), ImapAuthMechanism::OAuthBearer) => {
+                // prebuilt credentials are not used, since the
+                // access token may need to be refreshed
+                let access_token = oauth2
+                    .access_token()
+                    .await
+                    .map_err(Error::RefreshAccessTokenError)?;
+
+                let host = config.host.as_str();
+                let auth = client
+                    .authenticate_oauthbearer(login, host, config.port, access_token.as_str())
+                    .await;
+
+                if auth.is_err() {
+                    warn!("authentication failed, refreshing access token and retrying…");
+
+                    let access_token = oauth2
+                        .refresh_access_token()
+                        .await
+                        .map_err(Error::RefreshAccessTokenError)?;
 
-                        debug!("using XOAUTH2 auth mechanism");
-
-                        let access_token = match self.credentials.as_ref() {
-                            Some(access_token) => access_token.to_string(),
-                            None => oauth2
-                                .access_token()
-                                .await
-                                .map_err(Error::RefreshAccessTokenError)?,
-                        };
-
-                        let auth = client
-                            .authenticate_xoauth2(self.config.login.as_str(), access_token.as_str())
-                            .await;
-
-                        if auth.is_err() {
-                            warn!("authentication failed, refreshing access token and retrying…");
-
-                            let access_token = oauth2
-                                .refresh_access_token()
-                                .await
-                                .map_err(Error::RefreshAccessTokenError)?;
-
-                            client
-                                .authenticate_xoauth2(
-                                    self.config.login.as_str(),
-                                    access_token.as_str(),
-                                )
-                                .await
-                                .map_err(Error::AuthenticateXOauth2Error)?;
-
-                            self.credentials = Some(access_token);
-                        }
-                    }
-                    OAuth2Method::OAuthBearer => {
-                        if !client
-                            .state
-                            .supports_auth_mechanism("OAUTHBEARER".try_into().unwrap())
-                        {
-                            let auth = client.state.supported_auth_mechanisms().cloned().collect();
-                            return Err(Error::AuthenticateOAuthBearerNotSupportedError(auth));
-                        }
+                    client
+                        .authenticate_oauthbearer(login, host, config.port, access_token.as_str())
+                        .await
+                        .map_err(Error::AuthenticateOAuthBearerError)?;
 
-                        debug!("using OAUTHBEARER auth mechanism");
-
-                        let access_token = match self.credentials.as_ref() {
-                            Some(access_token) => access_token.to_string(),
-                            None => oauth2
-                                .access_token()
-                                .await
-                                .map_err(Error::RefreshAccessTokenError)?,
-                        };
-
-                        let auth = client
-                            .authenticate_oauthbearer(
-                                self.config.login.as_str(),
-                                self.config.host.as_str(),
-                                self.config.port,
-                                access_token.as_str(),
-                            )
-                            .await;
-
-                        if auth.is_err() {
-                            warn!("authentication failed, refreshing access token and retrying");
-
-                            let access_token = oauth2
-                                .refresh_access_token()
-                                .await
-                                .map_err(Error::RefreshAccessTokenError)?;
-
-                            client
-                                .authenticate_oauthbearer(
-                                    self.config.login.as_str(),
-                                    self.config.host.as_str(),
-                                    self.config.port,
-                                    access_token.as_str(),
-                                )
-                                .await
-                                .map_err(Error::AuthenticateOAuthBearerError)?;
-
-                            self.credentials = Some(access_token);
-                        }
-                    }
+                    self.credentials = Some(access_token);
                 }
             }
-        };
+            (_, mechanism) => {
+                return Err(Error::AuthMechanismNotCompatibleError(mechanism));
+            }
+        }
 
-        if self.config.send_id_after_auth() {
-            let params = ID_PARAMS.clone();
-            debug!(?params, "client identity");
+        Ok(())
+    }
+}
 
-            let params = client
-                .id(Some(ID_PARAMS.clone()))
-                .await
-                .map_err(Error::ExchangeIdsError)?;
+#[cfg(test)]
+mod tests {
+    use std::{num::NonZeroU32, sync::Arc};
 
-            debug!(?params, "server identity");
+    use imap_client::imap_next::imap_types::{
+        fetch::MessageDataItem,
+        flag::{Flag, FlagFetch},
+    };
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+        task::JoinHandle,
+    };
+
+    use super::{
+        check_fetch_items, missing_fetch_items, ImapClientBuilder, ImapConfig, ImapStrictness,
+        ImapWarnings, FETCH_ENVELOPES,
+    };
+    use crate::tls::Encryption;
+
+    fn uid() -> MessageDataItem<'static> {
+        MessageDataItem::Uid(NonZeroU32::new(42).unwrap())
+    }
+
+    fn flags() -> MessageDataItem<'static> {
+        MessageDataItem::Flags(vec![FlagFetch::Flag(Flag::Seen)])
+    }
+
+    #[test]
+    fn missing_items() {
+        let items = [uid(), flags(), MessageDataItem::Rfc822Size(1234)];
+
+        assert_eq!(
+            missing_fetch_items(&FETCH_ENVELOPES, &items),
+            vec!["ENVELOPE", "BODYSTRUCTURE", "INTERNALDATE"],
+        );
+    }
+
+    #[test]
+    fn strict_rejects_missing_items() {
+        let warnings = ImapWarnings::default();
+
+        for items in [
+            vec![flags(), MessageDataItem::Rfc822Size(1234)],
+            vec![uid(), MessageDataItem::Rfc822Size(1234)],
+            vec![uid(), flags()],
+        ] {
+            let res = check_fetch_items(
+                ImapStrictness::Strict,
+                &warnings,
+                42,
+                &FETCH_ENVELOPES,
+                &items,
+            );
+
+            assert!(res.is_err());
         }
 
-        // TODO: make it customizable
-        //
-        // debug!("enabling UTF8 capability…");
-        //
-        // client
-        //     .enable(Some(CapabilityEnable::Utf8(Utf8Kind::Accept)))
-        //     .await
-        //     .map_err(Error::EnableCapabilityError)?;
+        assert!(warnings.take().is_empty());
+    }
 
-        Ok(client)
+    #[test]
+    fn lenient_recovers_from_missing_items() {
+        let warnings = ImapWarnings::default();
+
+        let items = [uid(), MessageDataItem::Rfc822Size(1234)];
+        let res = check_fetch_items(
+            ImapStrictness::Lenient,
+            &warnings,
+            42,
+            &FETCH_ENVELOPES,
+            &items,
+        );
+
+        assert!(res.unwrap());
+
+        let warnings = warnings.take();
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings.contains(&String::from(
+            "missing FLAGS in FETCH response for message 42"
+        )));
+        assert!(warnings.contains(&String::from(
+            "missing BODYSTRUCTURE in FETCH response for message 42"
+        )));
+    }
+
+    #[test]
+    fn lenient_skips_missing_uid() {
+        let warnings = ImapWarnings::default();
+
+        let items = [flags(), MessageDataItem::Rfc822Size(1234)];
+        let res = check_fetch_items(
+            ImapStrictness::Lenient,
+            &warnings,
+            42,
+            &FETCH_ENVELOPES,
+            &items,
+        );
+
+        assert!(!res.unwrap());
+        assert_eq!(
+            warnings.take()[0],
+            "missing UID in FETCH response for message 42",
+        );
+    }
+
+    /// Spawns a fake IMAP server advertising `AUTH=PLAIN` and LOGIN,
+    /// answering authentication commands with the given response.
+    ///
+    /// Returns the port of the server, and a handle resolving to the
+    /// number of authentication attempts received.
+    async fn spawn_auth_server(response: &'static str) -> (u16, JoinHandle<usize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut attempts = 0;
+
+            let greeting = "* OK [CAPABILITY IMAP4rev1 AUTH=PLAIN SASL-IR] ready\r\n";
+            writer.write_all(greeting.as_bytes()).await.unwrap();
+
+            while let Ok(Some(line)) = lines.next_line().await {
+                let (tag, cmd) = line.split_once(' ').unwrap_or((&line, ""));
+                let cmd = cmd.to_ascii_uppercase();
+
+                let res = if cmd.starts_with("AUTHENTICATE") || cmd.starts_with("LOGIN") {
+                    attempts += 1;
+                    format!("{tag} {response}\r\n")
+                } else if cmd.starts_with("CAPABILITY") {
+                    format!("* CAPABILITY IMAP4rev1 AUTH=PLAIN SASL-IR\r\n{tag} OK done\r\n")
+                } else {
+                    format!("{tag} OK done\r\n")
+                };
+
+                if writer.write_all(res.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+
+            attempts
+        });
+
+        (port, handle)
+    }
+
+    /// Builds a client authenticating with a wrong password against a
+    /// fake server answering authentication commands with the given
+    /// response, then returns the number of attempts.
+    async fn wrong_password_attempts(response: &'static str) -> usize {
+        let (port, server) = spawn_auth_server(response).await;
+
+        let config = ImapConfig {
+            host: "127.0.0.1".into(),
+            port,
+            encryption: Some(Encryption::None),
+            login: format!("auth-{port}"),
+            ..Default::default()
+        };
+
+        let mut builder = ImapClientBuilder::new(Arc::new(config), Some("wrong".into()));
+        let err = builder.build().await.unwrap_err();
+        assert!(err.is_auth_failure());
+
+        server.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn wrong_password_is_sent_once() {
+        let response = "NO [AUTHENTICATIONFAILED] Invalid credentials";
+        assert_eq!(wrong_password_attempts(response).await, 1);
+    }
+
+    #[tokio::test]
+    async fn unsupported_mechanism_falls_back() {
+        let response = "BAD Unsupported mechanism";
+        assert_eq!(wrong_password_attempts(response).await, 2);
     }
 }
@@ -0,0 +1,444 @@
+//! # IMAP raw commands
+//!
+//! Module dedicated to the IMAP commands that the IMAP client cannot
+//! express, like batched APPEND (see [`super::append`]) or the Gmail
+//! extensions. Commands are written as raw bytes on the stream of the
+//! connection, and responses are read back one by one, including the
+//! literals they carry.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use imap_client::{
+    imap_next::{Interrupt, Io, State},
+    imap_types::sequence::{SeqOrUid, Sequence, SequenceSet},
+};
+use tracing::{debug, trace};
+
+/// The counter used to generate raw command tags.
+///
+/// Tags are prefixed so that they cannot collide with the ones
+/// generated by the IMAP client.
+static TAG_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Generates a new raw command tag.
+pub(crate) fn next_tag() -> String {
+    format!("RAW{}", TAG_COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Encodes the given string as an IMAP quoted string.
+pub(crate) fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+/// Returns `true` if the given string can be sent as a quoted
+/// string.
+///
+/// Quoted strings cannot contain control characters. Non-ASCII
+/// characters are only expected once `UTF8=ACCEPT` is enabled, since
+/// mailbox names are encoded using modified UTF-7 otherwise.
+pub(crate) fn quotable(s: &str) -> bool {
+    s.bytes().all(|b| b >= 0x20 && b != 0x7f)
+}
+
+/// Encodes the given sequence set, like `1:3,5,7:*`.
+pub(crate) fn encode_sequence_set(set: &SequenceSet) -> String {
+    fn encode(id: &SeqOrUid) -> String {
+        match id {
+            SeqOrUid::Value(id) => id.to_string(),
+            SeqOrUid::Asterisk => String::from("*"),
+        }
+    }
+
+    let seqs: Vec<_> = set
+        .0
+        .as_ref()
+        .iter()
+        .map(|seq| match seq {
+            Sequence::Single(id) => encode(id),
+            Sequence::Range(from, to) => format!("{}:{}", encode(from), encode(to)),
+        })
+        .collect();
+
+    seqs.join(",")
+}
+
+/// The kind of a tagged response.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum StatusKind {
+    Ok,
+    No,
+    Bad,
+}
+
+/// The status of a tagged response.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Status {
+    pub kind: StatusKind,
+    /// The response code, without brackets.
+    pub code: Option<String>,
+    pub text: String,
+}
+
+/// A server response.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Response {
+    /// A continuation request.
+    Continuation,
+    /// An untagged response, without its leading `* `.
+    Untagged(Vec<u8>),
+    /// A tagged response.
+    Tagged(String, Status),
+}
+
+/// The reader of server responses.
+#[derive(Debug, Default)]
+pub(crate) struct ResponseReader {
+    input: Vec<u8>,
+}
+
+impl ResponseReader {
+    pub fn enqueue(&mut self, bytes: &[u8]) {
+        self.input.extend_from_slice(bytes);
+    }
+
+    /// Returns the next complete response, if any.
+    ///
+    /// A BYE response, as well as a malformed response, is returned
+    /// as an error.
+    pub fn next(&mut self) -> Result<Option<Response>, String> {
+        let Some(res) = self.take_response() else {
+            return Ok(None);
+        };
+
+        trace!("received {}", String::from_utf8_lossy(&res));
+
+        if res.starts_with(b"+") {
+            return Ok(Some(Response::Continuation));
+        }
+
+        if let Some(res) = res.strip_prefix(b"* ") {
+            if let Some(text) = strip_prefix_ignore_case(res, b"BYE") {
+                let text = String::from_utf8_lossy(text);
+                return Err(format!("connection closed: {}", text.trim()));
+            }
+
+            return Ok(Some(Response::Untagged(res.to_vec())));
+        }
+
+        let res = String::from_utf8_lossy(&res);
+
+        let (tag, rest) = res
+            .split_once(' ')
+            .ok_or_else(|| format!("invalid response {res}"))?;
+        let (kind, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+
+        let kind = if kind.eq_ignore_ascii_case("OK") {
+            StatusKind::Ok
+        } else if kind.eq_ignore_ascii_case("NO") {
+            StatusKind::No
+        } else if kind.eq_ignore_ascii_case("BAD") {
+            StatusKind::Bad
+        } else {
+            return Err(format!("invalid tagged response {res}"));
+        };
+
+        let (code, text) = match rest.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+            Some((code, text)) => (Some(code.to_owned()), text.trim_start()),
+            None => (None, rest),
+        };
+
+        let status = Status {
+            kind,
+            code,
+            text: text.to_owned(),
+        };
+
+        Ok(Some(Response::Tagged(tag.to_owned(), status)))
+    }
+
+    /// Takes the next complete response out of the input buffer.
+    ///
+    /// Literals sent by the server are part of the response.
+    fn take_response(&mut self) -> Option<Vec<u8>> {
+        let mut start = 0;
+
+        loop {
+            let end = start + self.input[start..].windows(2).position(|w| w == b"\r\n")?;
+
+            match literal_size(&self.input[start..end]) {
+                Some(size) if self.input.len() < end + 2 + size => return None,
+                Some(size) => start = end + 2 + size,
+                None => {
+                    let res = self.input[..end].to_vec();
+                    self.input.drain(..end + 2);
+                    return Some(res);
+                }
+            }
+        }
+    }
+}
+
+/// Strips the given prefix from the given bytes, ignoring case.
+fn strip_prefix_ignore_case<'a>(bytes: &'a [u8], prefix: &[u8]) -> Option<&'a [u8]> {
+    let head = bytes.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &bytes[prefix.len()..])
+}
+
+/// Returns the size of the literal ending the given line, if any.
+fn literal_size(line: &[u8]) -> Option<usize> {
+    let line = line.strip_suffix(b"}")?;
+    let start = line.iter().rposition(|b| *b == b'{')?;
+    let size = &line[start + 1..];
+    let size = size.strip_suffix(b"+").unwrap_or(size);
+    std::str::from_utf8(size).ok()?.parse().ok()
+}
+
+/// A value of an untagged response.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Value {
+    /// An atom, like `FETCH`, `NIL` or `\Inbox`.
+    Atom(String),
+    /// A quoted string or a literal.
+    String(String),
+    /// A parenthesized list.
+    List(Vec<Value>),
+}
+
+impl Value {
+    /// Returns the inner string of an atom or of a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Atom(s) | Self::String(s) => Some(s),
+            Self::List(_) => None,
+        }
+    }
+}
+
+/// Parses the values of the given untagged response.
+pub(crate) fn parse_values(bytes: &[u8]) -> Result<Vec<Value>, String> {
+    let mut lists = vec![Vec::new()];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b' ' => {
+                i += 1;
+            }
+            b'(' => {
+                lists.push(Vec::new());
+                i += 1;
+            }
+            b')' => {
+                let list = lists.pop().filter(|_| !lists.is_empty());
+                let list = list.ok_or_else(|| String::from("unbalanced parenthesis"))?;
+                lists.last_mut().unwrap().push(Value::List(list));
+                i += 1;
+            }
+            b'"' => {
+                let mut s = Vec::new();
+                i += 1;
+
+                loop {
+                    match bytes.get(i) {
+                        None => return Err(String::from("unterminated quoted string")),
+                        Some(b'"') => break,
+                        Some(b'\\') => {
+                            s.extend(bytes.get(i + 1));
+                            i += 2;
+                        }
+                        Some(b) => {
+                            s.push(*b);
+                            i += 1;
+                        }
+                    }
+                }
+
+                i += 1;
+                let s = String::from_utf8_lossy(&s).into_owned();
+                lists.last_mut().unwrap().push(Value::String(s));
+            }
+            b'{' => {
+                let end = bytes[i..]
+                    .windows(2)
+                    .position(|w| w == b"\r\n")
+                    .map(|end| i + end)
+                    .ok_or_else(|| String::from("invalid literal"))?;
+                let size = literal_size(&bytes[i..end])
+                    .ok_or_else(|| String::from("invalid literal size"))?;
+                let s = bytes
+                    .get(end + 2..end + 2 + size)
+                    .ok_or_else(|| String::from("truncated literal"))?;
+                let s = String::from_utf8_lossy(s).into_owned();
+                lists.last_mut().unwrap().push(Value::String(s));
+                i = end + 2 + size;
+            }
+            _ => {
+                let end = bytes[i..]
+                    .iter()
+                    .position(|b| matches!(b, b' ' | b'(' | b')'))
+                    .map_or(bytes.len(), |end| i + end);
+                let atom = String::from_utf8_lossy(&bytes[i..end]).into_owned();
+                lists.last_mut().unwrap().push(Value::Atom(atom));
+                i = end;
+            }
+        }
+    }
+
+    match lists.pop() {
+        Some(values) if lists.is_empty() => Ok(values),
+        _ => Err(String::from("unbalanced parenthesis")),
+    }
+}
+
+/// The state sending a raw command, then collecting its untagged
+/// responses until its tagged response is received.
+///
+/// Commands carrying literals are not supported.
+#[derive(Debug)]
+pub(crate) struct RawCommand {
+    tag: String,
+    output: Option<Vec<u8>>,
+    reader: ResponseReader,
+    untagged: Vec<Vec<u8>>,
+    status: Option<Status>,
+}
+
+impl RawCommand {
+    /// Builds a raw command from the given command, without tag nor
+    /// trailing CRLF.
+    pub fn new(cmd: impl AsRef<str>) -> Self {
+        let tag = next_tag();
+        let output = format!("{tag} {}\r\n", cmd.as_ref()).into_bytes();
+
+        Self {
+            tag,
+            output: Some(output),
+            reader: ResponseReader::default(),
+            untagged: Vec::new(),
+            status: None,
+        }
+    }
+
+    /// Consumes the command and returns its status and its untagged
+    /// responses, once its tagged response has been received.
+    pub fn into_responses(self) -> Option<(Status, Vec<Vec<u8>>)> {
+        Some((self.status?, self.untagged))
+    }
+}
+
+impl State for RawCommand {
+    type Event = ();
+    type Error = String;
+
+    fn enqueue_input(&mut self, bytes: &[u8]) {
+        self.reader.enqueue(bytes);
+    }
+
+    fn next(&mut self) -> Result<Self::Event, Interrupt<Self::Error>> {
+        while let Some(res) = self.reader.next().map_err(Interrupt::Error)? {
+            match res {
+                Response::Continuation => {
+                    let err = String::from("unexpected continuation request");
+                    return Err(Interrupt::Error(err));
+                }
+                Response::Untagged(res) => {
+                    self.untagged.push(res);
+                }
+                Response::Tagged(tag, status) if tag == self.tag => {
+                    self.status = Some(status);
+                    return Ok(());
+                }
+                Response::Tagged(tag, _) => {
+                    debug!("ignoring response with unknown tag {tag}");
+                }
+            }
+        }
+
+        match self.output.take() {
+            Some(output) => Err(Interrupt::Io(Io::Output(output))),
+            None => Err(Interrupt::Io(Io::NeedMoreInput)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use imap_client::imap_next::{Interrupt, Io, State};
+
+    use super::{parse_values, RawCommand, StatusKind, Value};
+
+    #[test]
+    fn values() {
+        let values = parse_values(
+            b"12 FETCH (X-GM-LABELS (\\Inbox \"Work \\\"stuff\\\"\" {4}\r\nlist) UID 42)",
+        )
+        .unwrap();
+
+        assert_eq!(
+            values,
+            vec![
+                Value::Atom("12".into()),
+                Value::Atom("FETCH".into()),
+                Value::List(vec![
+                    Value::Atom("X-GM-LABELS".into()),
+                    Value::List(vec![
+                        Value::Atom("\\Inbox".into()),
+                        Value::String("Work \"stuff\"".into()),
+                        Value::String("list".into()),
+                    ]),
+                    Value::Atom("UID".into()),
+                    Value::Atom("42".into()),
+                ]),
+            ],
+        );
+
+        assert!(parse_values(b"1 FETCH (UID 1").is_err());
+        assert!(parse_values(b"1 FETCH UID 1)").is_err());
+    }
+
+    #[test]
+    fn command() {
+        let mut cmd = RawCommand::new("UID FETCH 1:2 (X-GM-LABELS)");
+        let tag = cmd.tag.clone();
+
+        let Err(Interrupt::Io(Io::Output(output))) = cmd.next() else {
+            panic!("expected output");
+        };
+        assert_eq!(
+            output,
+            format!("{tag} UID FETCH 1:2 (X-GM-LABELS)\r\n").as_bytes()
+        );
+        assert_eq!(cmd.next(), Err(Interrupt::Io(Io::NeedMoreInput)));
+
+        cmd.enqueue_input(b"* 1 FETCH (X-GM-LABELS ({3}\r\nfoo) UID 1)\r\n* 2 FET");
+        assert_eq!(cmd.next(), Err(Interrupt::Io(Io::NeedMoreInput)));
+
+        cmd.enqueue_input(b"CH (X-GM-LABELS () UID 2)\r\n");
+        cmd.enqueue_input(format!("{tag} OK [READ-ONLY] done\r\n").as_bytes());
+        assert_eq!(cmd.next(), Ok(()));
+
+        let (status, untagged) = cmd.into_responses().unwrap();
+        assert_eq!(status.kind, StatusKind::Ok);
+        assert_eq!(status.code.as_deref(), Some("READ-ONLY"));
+        assert_eq!(status.text, "done");
+        assert_eq!(
+            untagged,
+            vec![
+                b"1 FETCH (X-GM-LABELS ({3}\r\nfoo) UID 1)".to_vec(),
+                b"2 FETCH (X-GM-LABELS () UID 2)".to_vec(),
+            ],
+        );
+    }
+}
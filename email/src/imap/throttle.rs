@@ -0,0 +1,204 @@
+//! # IMAP authentication throttling
+//!
+//! Module dedicated to the IMAP authentication throttling. Consecutive
+//! authentication failures are tracked per account, and further
+//! automatic attempts are refused during a cooldown period, so that a
+//! stale password does not trigger provider lockouts during scheduled
+//! synchronizations.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use tracing::{debug, warn};
+
+use super::{config::ImapConfig, Error, Result};
+
+/// The consecutive authentication failures of all accounts, indexed
+/// by account key (see [`AuthThrottle::new`]).
+///
+/// The state is global so that it survives IMAP contexts being
+/// rebuilt, which is what happens during scheduled synchronizations.
+static AUTH_FAILURES: Lazy<Mutex<HashMap<String, AuthFailures>>> = Lazy::new(Default::default);
+
+/// The consecutive authentication failures of an account.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct AuthFailures {
+    /// The number of consecutive failures.
+    count: u32,
+
+    /// The instant of the last failure.
+    last: Instant,
+}
+
+impl AuthFailures {
+    /// Return the remaining cooldown at the given instant, if the
+    /// given maximum number of failures has been reached.
+    fn remaining_cooldown(&self, max: u32, cooldown: Duration, now: Instant) -> Option<Duration> {
+        if max == 0 || self.count < max {
+            return None;
+        }
+
+        cooldown
+            .checked_sub(now.saturating_duration_since(self.last))
+            .filter(|remaining| !remaining.is_zero())
+    }
+}
+
+/// The authentication throttle of an IMAP account.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuthThrottle {
+    key: String,
+    max_failures: u32,
+    cooldown: Duration,
+}
+
+impl AuthThrottle {
+    /// Create a new authentication throttle from the given IMAP
+    /// configuration.
+    ///
+    /// Accounts are identified by their login, host and port.
+    pub fn new(config: &ImapConfig) -> Self {
+        Self {
+            key: format!("{}@{}:{}", config.login, config.host, config.port),
+            max_failures: config.find_auth_max_failures(),
+            cooldown: Duration::from_secs(config.find_auth_cooldown()),
+        }
+    }
+
+    /// Check that a new authentication attempt is allowed.
+    ///
+    /// Returns [`Error::AuthThrottledError`] if the maximum number of
+    /// consecutive failures has been reached and the cooldown period
+    /// is not over yet.
+    pub fn check(&self) -> Result<()> {
+        self.check_at(Instant::now())
+    }
+
+    fn check_at(&self, now: Instant) -> Result<()> {
+        let failures = AUTH_FAILURES.lock().unwrap();
+
+        let Some(failures) = failures.get(&self.key) else {
+            return Ok(());
+        };
+
+        match failures.remaining_cooldown(self.max_failures, self.cooldown, now) {
+            None => Ok(()),
+            Some(remaining) => Err(Error::AuthThrottledError(
+                self.key.clone(),
+                failures.count,
+                remaining,
+            )),
+        }
+    }
+
+    /// Record an authentication failure.
+    pub fn record_failure(&self) {
+        self.record_failure_at(Instant::now())
+    }
+
+    fn record_failure_at(&self, now: Instant) {
+        let mut failures = AUTH_FAILURES.lock().unwrap();
+
+        let failures = failures.entry(self.key.clone()).or_insert(AuthFailures {
+            count: 0,
+            last: now,
+        });
+
+        failures.count += 1;
+        failures.last = now;
+
+        warn!(
+            account = %self.key,
+            count = failures.count,
+            "recorded IMAP authentication failure"
+        );
+    }
+
+    /// Reset consecutive authentication failures.
+    ///
+    /// This is done automatically after a successful authentication,
+    /// but can also be done manually, for example after the password
+    /// has been updated.
+    pub fn reset(&self) {
+        if AUTH_FAILURES.lock().unwrap().remove(&self.key).is_some() {
+            debug!(account = %self.key, "reset IMAP authentication failures");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::AuthThrottle;
+    use crate::imap::{
+        config::{ImapAuthThrottleConfig, ImapConfig},
+        Error,
+    };
+
+    #[test]
+    fn throttle() {
+        let config = ImapConfig {
+            host: "localhost".into(),
+            port: 143,
+            login: "throttle".into(),
+            auth_throttle: Some(ImapAuthThrottleConfig {
+                max_failures: Some(2),
+                cooldown: Some(60),
+            }),
+            ..Default::default()
+        };
+
+        let throttle = AuthThrottle::new(&config);
+        let now = Instant::now();
+
+        assert!(throttle.check_at(now).is_ok());
+
+        throttle.record_failure_at(now);
+        assert!(throttle.check_at(now).is_ok());
+
+        throttle.record_failure_at(now);
+        let err = throttle.check_at(now + Duration::from_secs(10));
+        assert!(matches!(
+            err,
+            Err(Error::AuthThrottledError(_, 2, remaining)) if remaining == Duration::from_secs(50)
+        ));
+
+        assert!(throttle.check_at(now + Duration::from_secs(60)).is_ok());
+
+        throttle.record_failure_at(now + Duration::from_secs(60));
+        assert!(throttle.check_at(now + Duration::from_secs(61)).is_err());
+
+        throttle.reset();
+        assert!(throttle.check_at(now + Duration::from_secs(61)).is_ok());
+    }
+
+    #[test]
+    fn throttle_disabled() {
+        let config = ImapConfig {
+            host: "localhost".into(),
+            port: 143,
+            login: "throttle-disabled".into(),
+            auth_throttle: Some(ImapAuthThrottleConfig {
+                max_failures: Some(0),
+                cooldown: None,
+            }),
+            ..Default::default()
+        };
+
+        let throttle = AuthThrottle::new(&config);
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            throttle.record_failure_at(now);
+        }
+
+        assert!(throttle.check_at(now).is_ok());
+
+        throttle.reset();
+    }
+}
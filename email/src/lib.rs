@@ -36,6 +36,7 @@
 //! - [`AddFlags`](crate::flag::add::AddFlags)
 //! - [`SetFlags`](crate::flag::set::SetFlags)
 //! - [`RemoveFlags`](crate::flag::remove::RemoveFlags)
+//! - [`ListFlags`](crate::flag::list::ListFlags)
 //!
 //! ### Message
 //!
@@ -53,13 +54,18 @@
 pub mod autoconfig;
 pub mod backend;
 pub mod config;
+pub mod contacts;
+pub mod doctor;
 pub mod email;
 mod error;
 pub mod folder;
+#[cfg(feature = "health")]
+pub mod health;
 #[cfg(feature = "imap")]
 pub mod imap;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+pub mod metrics;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
 pub mod retry;
@@ -71,6 +77,8 @@
 pub mod smtp;
 #[cfg(feature = "sync")]
 pub mod sync;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 #[cfg(any(feature = "imap", feature = "smtp"))]
 pub mod tls;
 pub mod watch;
@@ -78,5 +86,5 @@
 #[doc(inline)]
 pub use crate::{
     email::{envelope::flag, message::template, *},
-    error::{AnyBoxedError, AnyError, AnyResult},
+    error::{AnyBoxedError, AnyError, AnyResult, ErrorKind},
 };
@@ -0,0 +1,131 @@
+//! # Maildir envelope cache
+//!
+//! Module dedicated to the Maildir envelope cache. The cache is a
+//! SQLite database holding the headers of Maildir entries, keyed by
+//! the modification time and the size of their file, so that
+//! unchanged entries do not need to be read again when listing
+//! envelopes.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use rusqlite::{params, Connection};
+use shellexpand_utils::shellexpand_path;
+use tracing::debug;
+
+use super::{Error, Result};
+use crate::envelope::maildir::MaildirEnvelopeRecord;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS envelopes (
+    folder TEXT NOT NULL,
+    name TEXT NOT NULL,
+    mtime INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    has_attachment INTEGER NOT NULL,
+    headers BLOB NOT NULL,
+    PRIMARY KEY (folder, name)
+)";
+
+/// The Maildir envelope cache, scoped to one Maildir.
+pub struct MaildirEnvelopeCache {
+    path: PathBuf,
+    folder: String,
+    conn: Connection,
+}
+
+impl MaildirEnvelopeCache {
+    /// Opens the envelope cache at the given path, scoped to the
+    /// Maildir at the given path.
+    ///
+    /// The cache database is created if it does not exist yet.
+    pub fn open(path: impl AsRef<Path>, mdir: impl AsRef<Path>) -> Result<Self> {
+        let path = shellexpand_path(path.as_ref());
+        let folder = mdir.as_ref().to_string_lossy().into_owned();
+
+        let conn = Connection::open(&path)
+            .and_then(|conn| conn.execute(SCHEMA, []).map(|_| conn))
+            .map_err(|err| Error::OpenEnvelopeCacheError(err, path.clone()))?;
+
+        Ok(Self { path, folder, conn })
+    }
+
+    /// Loads the records of the Maildir, indexed by entry name.
+    pub fn load(&self) -> Result<HashMap<String, MaildirEnvelopeRecord>> {
+        let map_err = |err| Error::QueryEnvelopeCacheError(err, self.path.clone());
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, mtime, size, has_attachment, headers FROM envelopes WHERE folder = ?1")
+            .map_err(map_err)?;
+
+        let records = stmt
+            .query_map([&self.folder], |row| {
+                let record = MaildirEnvelopeRecord {
+                    mtime: row.get(1)?,
+                    size: row.get::<_, i64>(2)? as usize,
+                    has_attachment: row.get(3)?,
+                    headers: row.get(4)?,
+                };
+                Ok((row.get(0)?, record))
+            })
+            .map_err(map_err)?
+            .collect::<rusqlite::Result<HashMap<_, _>>>()
+            .map_err(map_err)?;
+
+        debug!("loaded {} maildir envelope cache records", records.len());
+        Ok(records)
+    }
+
+    /// Saves the given records of the Maildir, given the records
+    /// previously loaded with [`MaildirEnvelopeCache::load`].
+    ///
+    /// Only changed records are written, and records of entries which
+    /// do not exist anymore are removed.
+    pub fn save(
+        &mut self,
+        prev_records: &HashMap<String, MaildirEnvelopeRecord>,
+        next_records: &HashMap<String, MaildirEnvelopeRecord>,
+    ) -> Result<()> {
+        let map_err = |err| Error::QueryEnvelopeCacheError(err, self.path.clone());
+
+        let tx = self.conn.transaction().map_err(map_err)?;
+
+        {
+            let mut delete = tx
+                .prepare("DELETE FROM envelopes WHERE folder = ?1 AND name = ?2")
+                .map_err(map_err)?;
+
+            for name in prev_records.keys() {
+                if !next_records.contains_key(name) {
+                    delete.execute([&self.folder, name]).map_err(map_err)?;
+                }
+            }
+
+            let mut upsert = tx
+                .prepare("INSERT OR REPLACE INTO envelopes VALUES (?1, ?2, ?3, ?4, ?5, ?6)")
+                .map_err(map_err)?;
+
+            for (name, record) in next_records {
+                if prev_records.get(name) == Some(record) {
+                    continue;
+                }
+
+                upsert
+                    .execute(params![
+                        self.folder,
+                        name,
+                        record.mtime,
+                        record.size as i64,
+                        record.has_attachment,
+                        record.headers,
+                    ])
+                    .map_err(map_err)?;
+            }
+        }
+
+        tx.commit().map_err(map_err)
+    }
+}
@@ -5,6 +5,14 @@
 
 use std::path::PathBuf;
 
+use shellexpand_utils::shellexpand_path;
+
+use super::layout::MaildirLayout;
+use crate::{
+    doctor::{DoctorReport, Problem},
+    ErrorKind,
+};
+
 /// The Maildir backend configuration.
 #[derive(Debug, Default, Clone, Eq, PartialEq)]
 #[cfg_attr(
@@ -21,13 +29,111 @@ pub struct MaildirConfig {
     /// variables and tilde `~` are replaced by their values.
     pub root_dir: PathBuf,
 
+    /// Use the Maildir++ layout.
+    ///
+    /// The inbox is the root Maildir, and other folders are stored
+    /// as dot-prefixed directories. Ignored when
+    /// [`MaildirConfig::layout`] is defined.
     #[cfg_attr(feature = "derive", serde(default))]
     pub maildirpp: bool,
+
+    /// The layout of the Maildir tree.
+    ///
+    /// Defines how folder names map to Maildir directories, so that
+    /// trees created by other tools (Dovecot, mbsync…) can be used
+    /// as they are. When undefined, folders keep the naming of
+    /// previous versions, driven by [`MaildirConfig::maildirpp`].
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub layout: Option<MaildirLayout>,
+
+    /// Maintain a UID database in each Maildir.
+    ///
+    /// When enabled, envelope ids are numeric UIDs stored in the
+    /// [`UID_DB_FILE`](super::uid::UID_DB_FILE) of each Maildir
+    /// instead of file names, so that they remain stable when other
+    /// tools rename message files.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub uid_db: bool,
+
+    /// The envelopes listing configuration.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub envelopes: MaildirEnvelopesConfig,
+}
+
+impl MaildirConfig {
+    /// Returns `true` if the inbox is the root Maildir.
+    pub fn is_inbox_root(&self) -> bool {
+        match self.layout {
+            Some(layout) => layout.is_inbox_root(),
+            None => self.maildirpp,
+        }
+    }
+
+    /// Checks that the Maildir root directory exists.
+    ///
+    /// When the inbox is the root Maildir, a missing root directory
+    /// is not an error since it is created when configuring the
+    /// backend.
+    pub fn check_up(&self) -> DoctorReport {
+        let mut report = DoctorReport::new();
+        let root_dir = shellexpand_path(&self.root_dir);
+
+        if root_dir.is_dir() {
+            return report;
+        }
+
+        let msg = format!(
+            "Maildir root directory {} does not exist",
+            root_dir.display()
+        );
+
+        if self.is_inbox_root() {
+            report.push(Problem::info("maildir.root-dir", msg));
+        } else {
+            let problem = Problem::error("maildir.root-dir", msg).with_kind(ErrorKind::NotFound);
+            report.push(problem);
+        }
+
+        report
+    }
+}
+
+/// The Maildir envelopes listing configuration.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct MaildirEnvelopesConfig {
+    /// Only read the headers of message files.
+    ///
+    /// Listing envelopes is much faster on large folders, since
+    /// message bodies are not read anymore. In return, attachments
+    /// are guessed from the `Content-Type` header. Message files are
+    /// fully read anyway when a body preview is requested.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub headers_only: bool,
+
+    /// The number of threads used to read message files.
+    ///
+    /// Defaults to the number of logical CPUs.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub concurrency: Option<usize>,
+
+    /// The path of the SQLite envelope cache.
+    ///
+    /// When defined, envelopes are cached by message file
+    /// modification time and size, so that unchanged message files
+    /// are not read again. Path is shell-expanded.
+    #[cfg(feature = "maildir-cache")]
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub cache: Option<PathBuf>,
 }
 
 #[cfg(feature = "sync")]
 impl crate::sync::hash::SyncHash for MaildirConfig {
     fn sync_hash(&self, state: &mut std::hash::DefaultHasher) {
-        std::hash::Hash::hash(&shellexpand_utils::shellexpand_path(&self.root_dir), state);
+        std::hash::Hash::hash(&shellexpand_path(&self.root_dir), state);
     }
 }
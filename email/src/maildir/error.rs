@@ -1,8 +1,8 @@
-use std::{any::Any, path::PathBuf, result};
+use std::{any::Any, io, path::PathBuf, result};
 
 use thiserror::Error;
 
-use crate::{AnyBoxedError, AnyError};
+use crate::{AnyBoxedError, AnyError, ErrorKind};
 
 /// The global `Result` alias of the module.
 pub type Result<T> = result::Result<T, Error>;
@@ -14,8 +14,20 @@ pub enum Error {
     CheckConfigurationInvalidPathError(#[source] shellexpand_utils::Error),
     #[error("error while checking up current maildir directory")]
     CheckUpCurrentDirectoryError(#[source] maildirs::Error),
+    #[error("cannot find maildir root directory at {0}")]
+    CheckUpRootDirNotFoundError(PathBuf),
     #[error("cannot create maildir folder structure at {0}")]
     CreateFolderStructureError(#[source] maildirs::Error, PathBuf),
+    #[error("cannot read maildir uid database at {1}")]
+    ReadUidDbError(#[source] io::Error, PathBuf),
+    #[error("cannot write maildir uid database at {1}")]
+    WriteUidDbError(#[source] io::Error, PathBuf),
+    #[cfg(feature = "maildir-cache")]
+    #[error("cannot open maildir envelope cache at {1}")]
+    OpenEnvelopeCacheError(#[source] rusqlite::Error, PathBuf),
+    #[cfg(feature = "maildir-cache")]
+    #[error("cannot query maildir envelope cache at {1}")]
+    QueryEnvelopeCacheError(#[source] rusqlite::Error, PathBuf),
 
     #[error(transparent)]
     ExpandPathError(#[from] shellexpand_utils::Error),
@@ -23,10 +35,37 @@ pub enum Error {
     MaildirError(#[from] maildirs::Error),
 }
 
+impl Error {
+    /// Returns the kind of the error.
+    ///
+    /// See [`ErrorKind`] for the available kinds.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::CheckConfigurationInvalidPathError(_) | Self::ExpandPathError(_) => {
+                ErrorKind::Config
+            }
+            Self::CheckUpRootDirNotFoundError(_) => ErrorKind::NotFound,
+            Self::CheckUpCurrentDirectoryError(_)
+            | Self::CreateFolderStructureError(..)
+            | Self::ReadUidDbError(..)
+            | Self::WriteUidDbError(..)
+            | Self::MaildirError(_) => ErrorKind::Other,
+            #[cfg(feature = "maildir-cache")]
+            Self::OpenEnvelopeCacheError(..) | Self::QueryEnvelopeCacheError(..) => {
+                ErrorKind::Other
+            }
+        }
+    }
+}
+
 impl AnyError for Error {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn kind(&self) -> ErrorKind {
+        Error::kind(self)
+    }
 }
 
 impl From<Error> for AnyBoxedError {
@@ -0,0 +1,277 @@
+//! # Maildir layout
+//!
+//! Module dedicated to Maildir layouts. A layout defines how folder
+//! names map to Maildir directories inside the root directory, which
+//! varies across the tools managing Maildir trees (Dovecot, Courier,
+//! mbsync, offlineimap…).
+//!
+//! Whatever the layout, folder names use `/` as hierarchy delimiter.
+
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use maildirs::Maildir;
+
+use crate::folder::{FolderKind, INBOX};
+
+/// The Maildir layout enumeration.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "derive", derive(serde::Serialize, serde::Deserialize))]
+pub enum MaildirLayout {
+    /// The Maildir++ layout, used by Courier and by Dovecot by
+    /// default.
+    ///
+    /// The inbox is the root Maildir. Other folders are Maildirs
+    /// located at the root level, prefixed by a dot and using dots
+    /// as hierarchy delimiter: the folder `a/b` lives in `.a.b`.
+    #[cfg_attr(feature = "derive", serde(rename = "maildir++", alias = "maildirpp"))]
+    Maildirpp,
+
+    /// The file system layout, where folders are nested directories.
+    ///
+    /// The folder `a/b` lives in `a/b`, and the inbox in `INBOX`.
+    /// This is the layout of mbsync's `SubFolders Verbatim`.
+    #[default]
+    #[cfg_attr(feature = "derive", serde(rename = "fs"))]
+    Fs,
+
+    /// The Dovecot file system layout (`LAYOUT=fs`).
+    ///
+    /// Same as [`MaildirLayout::Fs`], except that the inbox is the
+    /// root Maildir.
+    #[cfg_attr(feature = "derive", serde(rename = "dovecot-fs"))]
+    DovecotFs,
+}
+
+impl MaildirLayout {
+    /// Returns `true` if the inbox is the root Maildir.
+    pub fn is_inbox_root(&self) -> bool {
+        matches!(self, Self::Maildirpp | Self::DovecotFs)
+    }
+
+    /// Returns the path of the Maildir matching the given folder.
+    pub fn folder_path(&self, root: impl AsRef<Path>, folder: impl AsRef<str>) -> PathBuf {
+        let root = root.as_ref();
+        let folder = folder.as_ref();
+
+        if self.is_inbox_root() && FolderKind::matches_inbox(folder) {
+            return root.to_owned();
+        }
+
+        let components = Path::new(folder)
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(component) => Some(component),
+                _ => None,
+            });
+
+        match self {
+            Self::Maildirpp => {
+                let name = components
+                    .map(|component| component.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(".");
+                root.join(format!(".{name}"))
+            }
+            Self::Fs | Self::DovecotFs => {
+                let mut path = root.to_owned();
+                path.extend(components);
+                path
+            }
+        }
+    }
+
+    /// Lists the folders of the Maildir tree located at the given
+    /// root directory, as `(name, path)` tuples sorted by name.
+    ///
+    /// Directories which are not Maildirs are skipped, as well as
+    /// hidden directories when using file system layouts.
+    pub fn list_folders(&self, root: impl AsRef<Path>) -> Vec<(String, PathBuf)> {
+        let root = root.as_ref();
+        let mut folders = Vec::new();
+
+        if self.is_inbox_root() && Maildir::from(root).exists() {
+            folders.push((INBOX.to_owned(), root.to_owned()));
+        }
+
+        match self {
+            Self::Maildirpp => {
+                for (file_name, path) in read_subdirs(root) {
+                    let Some(name) = file_name.strip_prefix('.') else {
+                        continue;
+                    };
+
+                    if name.is_empty() || name.starts_with('.') {
+                        continue;
+                    }
+
+                    if Maildir::from(&path).exists() {
+                        folders.push((name.replace('.', "/"), path));
+                    }
+                }
+            }
+            Self::Fs | Self::DovecotFs => {
+                list_fs_folders(root, None, &mut folders);
+            }
+        }
+
+        folders.sort_by(|(a, _), (b, _)| a.cmp(b));
+        folders
+    }
+}
+
+/// Lists recursively the Maildirs located in the given directory,
+/// using the file system layout.
+///
+/// The `cur`, `new` and `tmp` directories of Maildirs are skipped.
+/// Symbolic links to Maildirs are listed, but not followed further
+/// to prevent infinite loops.
+fn list_fs_folders(dir: &Path, name: Option<&str>, folders: &mut Vec<(String, PathBuf)>) {
+    let is_maildir = Maildir::from(dir).exists();
+
+    for (file_name, path) in read_subdirs(dir) {
+        if file_name.starts_with('.') {
+            continue;
+        }
+
+        if is_maildir && matches!(file_name.as_str(), "cur" | "new" | "tmp") {
+            continue;
+        }
+
+        let subname = match name {
+            Some(name) => format!("{name}/{file_name}"),
+            None => file_name,
+        };
+
+        if Maildir::from(&path).exists() {
+            folders.push((subname.clone(), path.clone()));
+        }
+
+        if !path.is_symlink() {
+            list_fs_folders(&path, Some(&subname), folders);
+        }
+    }
+}
+
+/// Reads the UTF-8 named subdirectories of the given directory, as
+/// `(file name, path)` tuples.
+///
+/// Entries that cannot be read are skipped.
+fn read_subdirs(dir: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+
+            if !path.is_dir() {
+                return None;
+            }
+
+            let file_name = path.file_name()?.to_str()?.to_owned();
+            Some((file_name, path))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use maildirs::Maildir;
+
+    use super::MaildirLayout;
+
+    fn create_maildirs(root: &Path, paths: &[&str]) {
+        for path in paths {
+            Maildir::from(root.join(path)).create_all().unwrap();
+        }
+    }
+
+    fn list_folders(layout: MaildirLayout, root: &Path) -> Vec<(String, String)> {
+        layout
+            .list_folders(root)
+            .into_iter()
+            .map(|(name, path)| {
+                let path = path.strip_prefix(root).unwrap();
+                (name, path.to_string_lossy().into_owned())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn folder_paths() {
+        let root = Path::new("/mail");
+
+        let layout = MaildirLayout::Maildirpp;
+        assert_eq!(layout.folder_path(root, "INBOX"), root);
+        assert_eq!(layout.folder_path(root, "Sent"), root.join(".Sent"));
+        assert_eq!(layout.folder_path(root, "a/b"), root.join(".a.b"));
+
+        let layout = MaildirLayout::Fs;
+        assert_eq!(layout.folder_path(root, "INBOX"), root.join("INBOX"));
+        assert_eq!(layout.folder_path(root, "a/b"), root.join("a/b"));
+        assert_eq!(layout.folder_path(root, "../a"), root.join("a"));
+
+        let layout = MaildirLayout::DovecotFs;
+        assert_eq!(layout.folder_path(root, "inbox"), root);
+        assert_eq!(layout.folder_path(root, "a/b"), root.join("a/b"));
+    }
+
+    #[test]
+    fn maildirpp_folders() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+
+        create_maildirs(root, &["", ".Sent", ".a", ".a.b", ".notmuch/xapian"]);
+
+        assert_eq!(
+            list_folders(MaildirLayout::Maildirpp, root),
+            vec![
+                ("INBOX".into(), "".into()),
+                ("Sent".into(), ".Sent".into()),
+                ("a".into(), ".a".into()),
+                ("a/b".into(), ".a.b".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn fs_folders() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+
+        create_maildirs(root, &["INBOX", "a", "a/b", "c/d", ".hidden"]);
+
+        assert_eq!(
+            list_folders(MaildirLayout::Fs, root),
+            vec![
+                ("INBOX".into(), "INBOX".into()),
+                ("a".into(), "a".into()),
+                ("a/b".into(), "a/b".into()),
+                ("c/d".into(), "c/d".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn dovecot_fs_folders() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+
+        create_maildirs(root, &["", "new/folder", "a", "a/b", "a/cur/nested"]);
+
+        assert_eq!(
+            list_folders(MaildirLayout::DovecotFs, root),
+            vec![
+                ("INBOX".into(), "".into()),
+                ("a".into(), "a".into()),
+                ("a/b".into(), "a/b".into()),
+            ]
+        );
+    }
+}
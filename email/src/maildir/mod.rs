@@ -1,5 +1,9 @@
+#[cfg(feature = "maildir-cache")]
+pub mod cache;
 pub mod config;
 mod error;
+pub mod layout;
+pub mod uid;
 
 use std::{ops::Deref, path::PathBuf, sync::Arc};
 
@@ -9,9 +13,9 @@
 use tokio::sync::Mutex;
 use tracing::info;
 
-use self::config::MaildirConfig;
 #[doc(inline)]
 pub use self::error::{Error, Result};
+use self::{config::MaildirConfig, layout::MaildirLayout, uid::MaildirUidDb};
 #[cfg(feature = "thread")]
 use crate::envelope::thread::{maildir::ThreadMaildirEnvelopes, ThreadEnvelopes};
 #[cfg(feature = "watch")]
@@ -28,6 +32,7 @@
     },
     flag::{
         add::{maildir::AddMaildirFlags, AddFlags},
+        list::{maildir::ListMaildirFlags, ListFlags},
         remove::{maildir::RemoveMaildirFlags, RemoveFlags},
         set::{maildir::SetMaildirFlags, SetFlags},
     },
@@ -66,18 +71,88 @@ pub struct MaildirContext {
 }
 
 impl MaildirContext {
+    /// Returns the layout of the Maildir tree.
+    ///
+    /// Returns `None` when no layout is configured, in which case
+    /// folders keep the naming of previous versions, see
+    /// [`MaildirConfig::layout`].
+    pub fn layout(&self) -> Option<MaildirLayout> {
+        self.maildir_config.layout
+    }
+
+    /// Returns `true` if the inbox is the root Maildir.
+    pub fn is_inbox_root(&self) -> bool {
+        self.maildir_config.is_inbox_root()
+    }
+
     /// Create a maildir instance from a folder name.
+    ///
+    /// The folder name is mapped to a Maildir directory using the
+    /// layout of the Maildir tree, see [`MaildirLayout`].
+    pub fn get_maildir_from_folder(&self, folder: &str) -> Result<Maildir> {
+        let Some(layout) = self.layout() else {
+            // If the folder matches to the inbox folder kind, create a
+            // maildir instance from the root folder.
+            if self.maildir_config.maildirpp && FolderKind::matches_inbox(folder) {
+                return Ok(Maildir::from(try_shellexpand_path(self.root.path())?));
+            }
+
+            return Ok(self.root.get(folder)?);
+        };
+
+        let mdir = Maildir::from(layout.folder_path(self.root.path(), folder));
+
+        if !mdir.exists() {
+            let folder = folder.to_owned();
+            return Err(maildirs::Error::GetMaildirByNameNotFoundError(folder).into());
+        }
+
+        Ok(mdir)
+    }
+
+    /// Create a maildir instance from a folder alias.
     pub fn get_maildir_from_folder_alias(&self, folder: &str) -> Result<Maildir> {
         let folder = self.account_config.get_folder_alias(folder);
+        self.get_maildir_from_folder(&folder)
+    }
+
+    /// Create the Maildir matching the given folder name.
+    pub fn create_folder(&self, folder: &str) -> maildirs::Result<()> {
+        match self.layout() {
+            Some(layout) => {
+                Maildir::from(layout.folder_path(self.root.path(), folder)).create_all()
+            }
+            None => {
+                self.root.create(folder)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Remove the Maildir matching the given folder name.
+    pub fn remove_folder(&self, folder: &str) -> maildirs::Result<()> {
+        match self.layout() {
+            Some(layout) => Maildir::from(layout.folder_path(self.root.path(), folder)).remove(),
+            None => self.root.remove(folder),
+        }
+    }
 
-        // If the folder matches to the inbox folder kind, create a
-        // maildir instance from the root folder.
-        if self.maildir_config.maildirpp && FolderKind::matches_inbox(&folder) {
-            return Ok(Maildir::from(try_shellexpand_path(self.root.path())?));
+    /// Opens the UID database of the given Maildir, synchronized
+    /// with its entries.
+    ///
+    /// Returns `None` when the UID database is disabled, see
+    /// [`MaildirConfig::uid_db`].
+    pub fn uid_db(&self, mdir: &Maildir) -> Result<Option<MaildirUidDb>> {
+        if !self.maildir_config.uid_db {
+            return Ok(None);
         }
 
-        let mdir = self.root.get(folder)?;
-        Ok(mdir)
+        let entries: Vec<_> = mdir.read()?.collect();
+        let mut db = MaildirUidDb::open(mdir)?;
+        db.sync(entries.iter().filter_map(|entry| entry.id().ok()));
+        db.save()?;
+
+        Ok(Some(db))
     }
 }
 
@@ -147,7 +222,7 @@ impl BackendContextBuilder for MaildirContextBuilder {
     async fn configure(&mut self) -> AnyResult<()> {
         let mdir = self.maildir();
 
-        if self.mdir_config.maildirpp {
+        if self.mdir_config.is_inbox_root() {
             Maildir::from(mdir.path())
                 .create_all()
                 .map_err(|err| Error::CreateFolderStructureError(err, mdir.path().to_owned()))?;
@@ -218,6 +293,10 @@ fn remove_flags(&self) -> Option<BackendFeature<Self::Context, dyn RemoveFlags>>
         Some(Arc::new(RemoveMaildirFlags::some_new_boxed))
     }
 
+    fn list_flags(&self) -> Option<BackendFeature<Self::Context, dyn ListFlags>> {
+        Some(Arc::new(ListMaildirFlags::some_new_boxed))
+    }
+
     fn add_message(&self) -> Option<BackendFeature<Self::Context, dyn AddMessage>> {
         Some(Arc::new(AddMaildirMessage::some_new_boxed))
     }
@@ -285,14 +364,12 @@ pub fn some_new_boxed(ctx: &MaildirContextSync) -> Option<Box<dyn CheckUp>> {
 #[async_trait]
 impl CheckUp for CheckUpMaildir {
     async fn check_up(&self) -> AnyResult<()> {
-        // FIXME
-        //
-        // let ctx = self.ctx.lock().await;
-
-        // ctx.root
-        //     .list_cur()
-        //     .try_for_each(|e| e.map(|_| ()))
-        //     .map_err(Error::CheckUpCurrentDirectoryError)?;
+        let ctx = self.ctx.lock().await;
+        let root = ctx.root.path();
+
+        if !root.is_dir() {
+            return Err(Error::CheckUpRootDirNotFoundError(root.to_owned()).into());
+        }
 
         Ok(())
     }
@@ -0,0 +1,276 @@
+//! # Maildir UID database
+//!
+//! Module dedicated to the Maildir UID database. Maildir entries are
+//! identified by the unique part of their file name, which other
+//! tools may change when renaming messages (mbsync for example
+//! stores the remote UID in the file name). The UID database maps
+//! these file names to numeric UIDs which remain stable across flag
+//! updates and renames, the same way IMAP UIDs do.
+//!
+//! The database is stored in the [`UID_DB_FILE`] of each Maildir,
+//! and is composed of a header line `V<uidvalidity> N<next-uid>`
+//! followed by one `<uid> <name>` line per entry.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use maildirs::Maildir;
+use tracing::{debug, warn};
+
+use super::{Error, Result};
+use crate::envelope::Id;
+
+/// The name of the file holding the UID database of a Maildir.
+pub const UID_DB_FILE: &str = "email-lib-uidlist";
+
+/// The Maildir UID database.
+///
+/// UIDs are assigned in ascending order and never reused. When the
+/// database cannot be read, a new one is created with a new UID
+/// validity, which tells consumers that previous UIDs are obsolete.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MaildirUidDb {
+    path: PathBuf,
+    uid_validity: u32,
+    next_uid: u32,
+    uids: BTreeMap<u32, String>,
+    names: HashMap<String, u32>,
+    dirty: bool,
+}
+
+impl MaildirUidDb {
+    /// Opens the UID database of the given Maildir.
+    ///
+    /// The database is created in memory if it does not exist yet,
+    /// and written on the first [`MaildirUidDb::save`].
+    pub fn open(mdir: &Maildir) -> Result<Self> {
+        let path = mdir.path().join(UID_DB_FILE);
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                debug!("no maildir uid database found at {path:?}, creating a new one");
+                return Ok(Self::new(path));
+            }
+            Err(err) => return Err(Error::ReadUidDbError(err, path)),
+        };
+
+        match Self::parse(path.clone(), &contents) {
+            Some(db) => Ok(db),
+            None => {
+                warn!("invalid maildir uid database at {path:?}, resetting uid validity");
+                Ok(Self::new(path))
+            }
+        }
+    }
+
+    fn new(path: PathBuf) -> Self {
+        let uid_validity = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as u32)
+            .unwrap_or(1)
+            .max(1);
+
+        Self {
+            path,
+            uid_validity,
+            next_uid: 1,
+            uids: BTreeMap::new(),
+            names: HashMap::new(),
+            dirty: true,
+        }
+    }
+
+    fn parse(path: PathBuf, contents: &str) -> Option<Self> {
+        let mut lines = contents.lines();
+        let (uid_validity, next_uid) = lines.next()?.split_once(' ')?;
+        let uid_validity = uid_validity.strip_prefix('V')?.parse().ok()?;
+        let mut next_uid: u32 = next_uid.strip_prefix('N')?.parse().ok()?;
+
+        let mut uids = BTreeMap::new();
+        let mut names = HashMap::new();
+
+        for line in lines.filter(|line| !line.is_empty()) {
+            let (uid, name) = line.split_once(' ')?;
+            let uid: u32 = uid.parse().ok()?;
+            next_uid = next_uid.max(uid + 1);
+            uids.insert(uid, name.to_owned());
+            names.insert(name.to_owned(), uid);
+        }
+
+        Some(Self {
+            path,
+            uid_validity,
+            next_uid,
+            uids,
+            names,
+            dirty: false,
+        })
+    }
+
+    /// Returns the UID validity of the database.
+    pub fn uid_validity(&self) -> u32 {
+        self.uid_validity
+    }
+
+    /// Returns the UID of the entry matching the given Maildir id.
+    pub fn get_uid(&self, id: impl AsRef<str>) -> Option<u32> {
+        self.names.get(entry_name(id.as_ref())).copied()
+    }
+
+    /// Returns the name of the entry matching the given UID.
+    pub fn get_name(&self, uid: u32) -> Option<&str> {
+        self.uids.get(&uid).map(String::as_str)
+    }
+
+    /// Returns the names of the entries matching the given id, which
+    /// is made of UIDs.
+    pub fn find_names(&self, id: &Id) -> HashSet<String> {
+        self.uids
+            .iter()
+            .filter(|(uid, _)| id.contains(uid.to_string()))
+            .map(|(_, name)| name.clone())
+            .collect()
+    }
+
+    /// Returns the UID of the entry matching the given Maildir id,
+    /// assigning a new one if the entry is unknown.
+    pub fn insert(&mut self, id: impl AsRef<str>) -> u32 {
+        let name = entry_name(id.as_ref());
+
+        if let Some(uid) = self.names.get(name) {
+            return *uid;
+        }
+
+        let uid = self.next_uid;
+        self.next_uid += 1;
+        self.uids.insert(uid, name.to_owned());
+        self.names.insert(name.to_owned(), uid);
+        self.dirty = true;
+        uid
+    }
+
+    /// Synchronizes the database with the given Maildir ids.
+    ///
+    /// Unknown entries are assigned new UIDs, sorted by name so that
+    /// UIDs follow the delivery order. Entries which do not exist
+    /// anymore are removed from the database.
+    pub fn sync<'a>(&mut self, ids: impl IntoIterator<Item = &'a str>) {
+        let names: HashSet<&str> = ids.into_iter().map(entry_name).collect();
+
+        let len = self.uids.len();
+        self.uids.retain(|_, name| names.contains(name.as_str()));
+        self.names.retain(|name, _| names.contains(name.as_str()));
+        self.dirty |= len != self.uids.len();
+
+        let mut unknown: Vec<&str> = names
+            .into_iter()
+            .filter(|name| !self.names.contains_key(*name))
+            .collect();
+        unknown.sort_unstable();
+
+        for name in unknown {
+            self.insert(name);
+        }
+    }
+
+    /// Writes the database to its file, if it changed since it has
+    /// been opened.
+    ///
+    /// The database is written to a temporary file first, then
+    /// renamed, so that it is never left half written.
+    pub fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let mut contents = format!("V{} N{}\n", self.uid_validity, self.next_uid);
+
+        for (uid, name) in &self.uids {
+            contents.push_str(&format!("{uid} {name}\n"));
+        }
+
+        let tmp_path = self.path.with_extension("tmp");
+
+        fs::write(&tmp_path, contents)
+            .and_then(|()| fs::rename(&tmp_path, &self.path))
+            .map_err(|err| Error::WriteUidDbError(err, self.path.clone()))?;
+
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Returns the path of the database file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Returns the name of the given Maildir id, as stored in the UID
+/// database.
+///
+/// The UID stored by mbsync in file names (`,U=<uid>`) is stripped,
+/// since mbsync changes it when the message is re-synchronized.
+pub fn entry_name(id: &str) -> &str {
+    match id.rsplit_once(",U=") {
+        Some((name, uid)) if !uid.is_empty() && uid.bytes().all(|b| b.is_ascii_digit()) => name,
+        _ => id,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use maildirs::Maildir;
+
+    use super::{entry_name, MaildirUidDb};
+    use crate::envelope::Id;
+
+    #[test]
+    fn entry_names() {
+        assert_eq!(entry_name("1700000000.123_1.host"), "1700000000.123_1.host");
+        assert_eq!(
+            entry_name("1700000000.123_1.host,U=42"),
+            "1700000000.123_1.host"
+        );
+        assert_eq!(entry_name("1700000000,U=42.host"), "1700000000,U=42.host");
+    }
+
+    #[test]
+    fn stable_uids() {
+        let root = tempfile::tempdir().unwrap();
+        let mdir = Maildir::from(root.path());
+
+        let mut db = MaildirUidDb::open(&mdir).unwrap();
+        db.sync(["b", "a", "c,U=7"]);
+        assert_eq!(db.get_uid("a"), Some(1));
+        assert_eq!(db.get_uid("b"), Some(2));
+        assert_eq!(db.get_uid("c,U=12"), Some(3));
+        db.save().unwrap();
+
+        let mut db = MaildirUidDb::open(&mdir).unwrap();
+        db.sync(["c", "b", "d"]);
+        assert_eq!(db.get_uid("a"), None);
+        assert_eq!(db.get_uid("b"), Some(2));
+        assert_eq!(db.get_uid("c"), Some(3));
+        assert_eq!(db.get_uid("d"), Some(4));
+        assert_eq!(db.get_name(4), Some("d"));
+
+        let names = db.find_names(&Id::parse("3:*"));
+        assert_eq!(names.len(), 2);
+        assert!(names.contains("c") && names.contains("d"));
+    }
+
+    #[test]
+    fn invalid_db() {
+        let root = tempfile::tempdir().unwrap();
+        let mdir = Maildir::from(root.path());
+        std::fs::write(root.path().join(super::UID_DB_FILE), "garbage").unwrap();
+
+        let db = MaildirUidDb::open(&mdir).unwrap();
+        assert_eq!(db.get_name(1), None);
+    }
+}
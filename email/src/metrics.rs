@@ -0,0 +1,215 @@
+//! # Metrics
+//!
+//! Module dedicated to metrics. Backend operations, synchronization
+//! phases and request retries emit [`Metric`]s through an optional,
+//! global [`MetricsHook`]. Applications can use it to forward metrics
+//! to their own system, in order to build dashboards or to debug slow
+//! synchronizations.
+//!
+//! Metrics come in addition to `tracing` spans: each backend
+//! operation runs inside a `backend` span, each synchronization phase
+//! runs inside a `sync_phase` span.
+
+use std::{
+    fmt,
+    future::Future,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use tracing::{debug, trace, Instrument, Span};
+
+use crate::{message::Messages, AnyResult};
+
+/// The global metrics hook.
+static METRICS_HOOK: RwLock<Option<MetricsHook>> = RwLock::new(None);
+
+/// The metric emitted by the library.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Metric {
+    /// A backend operation finished, successfully or not.
+    BackendOperation {
+        /// The name of the operation, for example `list_envelopes`.
+        name: &'static str,
+        /// The time spent by the operation.
+        duration: Duration,
+        /// Whether the operation succeeded or not.
+        success: bool,
+    },
+
+    /// A synchronization phase finished, successfully or not.
+    SyncPhase {
+        /// The name of the phase, for example `folders`.
+        name: &'static str,
+        /// The time spent by the phase.
+        duration: Duration,
+        /// Whether the phase succeeded or not.
+        success: bool,
+    },
+
+    /// Messages have been fetched from a backend.
+    MessagesFetched(usize),
+
+    /// Bytes have been transferred from or to a backend.
+    BytesTransferred(usize),
+
+    /// A request timed out and is about to be retried.
+    Retry {
+        /// The number of the attempt, starting from 1.
+        attempt: u8,
+    },
+}
+
+/// The metrics hook.
+///
+/// Wrapper around a function called every time the library emits a
+/// [`Metric`]. The function is called synchronously, it should not
+/// block.
+#[derive(Clone)]
+pub struct MetricsHook(Arc<dyn Fn(&Metric) + Send + Sync>);
+
+impl MetricsHook {
+    /// Creates a new metrics hook from the given function.
+    pub fn new(f: impl Fn(&Metric) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    /// Calls the hook with the given metric.
+    pub fn emit(&self, metric: &Metric) {
+        (self.0)(metric)
+    }
+}
+
+impl fmt::Debug for MetricsHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MetricsHook()")
+    }
+}
+
+impl PartialEq for MetricsHook {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for MetricsHook {}
+
+/// Defines the global metrics hook.
+///
+/// Pass `None` to remove the current hook.
+pub fn set_metrics_hook(hook: Option<MetricsHook>) {
+    match METRICS_HOOK.write() {
+        Ok(mut global) => *global = hook,
+        Err(err) => debug!(?err, "cannot set metrics hook"),
+    }
+}
+
+/// Emits the given metric using the global metrics hook, if any.
+pub fn emit(metric: Metric) {
+    trace!(?metric, "emit metric");
+
+    let hook = match METRICS_HOOK.read() {
+        Ok(hook) => hook.clone(),
+        Err(err) => {
+            debug!(?err, "cannot get metrics hook");
+            return;
+        }
+    };
+
+    if let Some(hook) = hook {
+        hook.emit(&metric);
+    }
+}
+
+/// Runs the given backend operation inside the given span, then
+/// emits a [`Metric::BackendOperation`].
+pub(crate) async fn measure_backend_operation<T>(
+    name: &'static str,
+    span: Span,
+    f: impl Future<Output = AnyResult<T>>,
+) -> AnyResult<T> {
+    let start = Instant::now();
+    let res = f.instrument(span).await;
+    let duration = start.elapsed();
+    let success = res.is_ok();
+
+    debug!(name, ?duration, success, "backend operation finished");
+    emit(Metric::BackendOperation {
+        name,
+        duration,
+        success,
+    });
+
+    res
+}
+
+/// Runs the given synchronization phase inside a `sync_phase` span, then
+/// emits a [`Metric::SyncPhase`].
+#[cfg(feature = "sync")]
+pub(crate) async fn measure_sync_phase<T, E>(
+    name: &'static str,
+    f: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let start = Instant::now();
+    let res = f
+        .instrument(tracing::info_span!("sync_phase", phase = name))
+        .await;
+    let duration = start.elapsed();
+    let success = res.is_ok();
+
+    debug!(name, ?duration, success, "sync phase finished");
+    emit(Metric::SyncPhase {
+        name,
+        duration,
+        success,
+    });
+
+    res
+}
+
+/// Emits [`Metric::MessagesFetched`] and [`Metric::BytesTransferred`]
+/// for the given fetched messages.
+pub(crate) fn record_fetched_messages(msgs: &Messages) {
+    let msgs = msgs.to_vec();
+    let bytes = msgs
+        .iter()
+        .filter_map(|msg| msg.raw().ok())
+        .map(<[u8]>::len)
+        .sum();
+
+    emit(Metric::MessagesFetched(msgs.len()));
+    emit(Metric::BytesTransferred(bytes));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::Span;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn measure_backend_operation_emits_metric() {
+        let metrics = Arc::new(Mutex::new(Vec::new()));
+
+        let hook = {
+            let metrics = metrics.clone();
+            MetricsHook::new(move |metric| metrics.lock().unwrap().push(metric.clone()))
+        };
+
+        set_metrics_hook(Some(hook));
+        let res = measure_backend_operation("test", Span::none(), async { Ok(42) }).await;
+        set_metrics_hook(None);
+
+        assert_eq!(res.unwrap(), 42);
+        assert!(metrics.lock().unwrap().iter().any(|metric| matches!(
+            metric,
+            Metric::BackendOperation {
+                name: "test",
+                success: true,
+                ..
+            }
+        )));
+    }
+}
@@ -3,13 +3,22 @@
 //! This module contains the configuration specific to the Notmuch
 //! backend.
 
-use std::path::{Path, PathBuf};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+};
 
-use notmuch::{Database, DatabaseMode};
+use notmuch::{ConfigKey, Database, DatabaseMode, Message};
+use process::Command;
 use shellexpand_utils::shellexpand_path;
 
 #[doc(inline)]
 pub use super::{Error, Result};
+use crate::{
+    doctor::{DoctorReport, Problem},
+    flag::{Flag, Flags},
+};
 
 /// The Notmuch backend config.
 #[derive(Debug, Default, Clone, Eq, PartialEq)]
@@ -24,7 +33,9 @@ pub struct NotmuchConfig {
     /// The path should point to the root directory containing the
     /// Notmuch database (usually the root Maildir directory). Path is
     /// shell-expanded, which means environment variables and tilde
-    /// `~` are replaced by their values.
+    /// `~` are replaced by their values. Defaults to the
+    /// `database.path` option of the Notmuch configuration file if
+    /// omitted.
     #[cfg_attr(feature = "derive", serde(alias = "db-path"))]
     pub database_path: Option<PathBuf>,
 
@@ -32,7 +43,8 @@ pub struct NotmuchConfig {
     ///
     /// Path is shell-expanded, which means environment variables and
     /// tilde `~` are replaced by their values. Defaults to
-    /// `database_path` if omitted.
+    /// `database_path` if omitted, otherwise to the
+    /// `database.mail_root` option of the Notmuch configuration file.
     pub maildir_path: Option<PathBuf>,
 
     /// Override the default Notmuch configuration file path.
@@ -44,8 +56,46 @@ pub struct NotmuchConfig {
     /// Override the default Notmuch profile name.
     pub profile: Option<String>,
 
+    /// The tags excluded from envelope listing.
+    ///
+    /// Messages having one of these tags are omitted from envelope
+    /// listing, unless the search query explicitly mentions them.
+    /// Defaults to the `search.exclude_tags` option of the Notmuch
+    /// configuration file. Use an empty list to disable exclusion.
+    pub exclude_tags: Option<Vec<String>>,
+
     #[cfg_attr(feature = "derive", serde(default))]
     pub maildirpp: bool,
+
+    /// The mapping between Notmuch tags and flags.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub tags: NotmuchTagsConfig,
+
+    /// The saved searches exposed as virtual folders.
+    ///
+    /// Keys are folder names, values are Notmuch queries, for
+    /// example `unread = "tag:unread"` or `flagged-this-week =
+    /// "tag:flagged and date:1w.."`. Virtual folders are listed
+    /// apart from Maildir folders, take precedence over Maildir
+    /// folders of the same name and are read-only.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub saved_searches: BTreeMap<String, String>,
+
+    /// Enable folder deletion and expunge.
+    ///
+    /// These operations are applied to the underlying Maildir tree,
+    /// then the Notmuch database is re-indexed using the
+    /// [`NotmuchConfig::index_cmd`]. Disabled by default, since
+    /// message files are definitely deleted.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub manage_folders: bool,
+
+    /// The command used to re-index the Notmuch database after a
+    /// folder operation.
+    ///
+    /// Defaults to `notmuch new`, using the configuration file path
+    /// and the profile defined in this configuration.
+    pub index_cmd: Option<Command>,
 }
 
 impl NotmuchConfig {
@@ -62,23 +112,132 @@ pub fn get_default_database_path() -> Result<PathBuf> {
         .to_owned())
     }
 
+    /// Open the Notmuch database discovered from the Notmuch
+    /// configuration file, in read-only mode.
+    ///
+    /// The custom configuration file path and profile are taken into
+    /// account.
+    fn open_discovered_database(&self) -> Result<Database> {
+        Database::open_with_config(
+            None::<PathBuf>,
+            DatabaseMode::ReadOnly,
+            self.find_config_path(),
+            self.find_profile(),
+        )
+        .map_err(Error::OpenDatabaseError)
+    }
+
+    /// Discover the Notmuch database path from the Notmuch
+    /// configuration file (`database.path`).
+    pub fn discover_database_path(&self) -> Result<PathBuf> {
+        let db = self.open_discovered_database()?;
+        Ok(db.path().to_owned())
+    }
+
+    /// Discover the Maildir path from the Notmuch configuration file
+    /// (`database.mail_root`, which defaults to `database.path`).
+    pub fn discover_maildir_path(&self) -> Result<PathBuf> {
+        let db = self.open_discovered_database()?;
+
+        match db.config(ConfigKey::MailRoot) {
+            Some(path) if !path.is_empty() => Ok(PathBuf::from(path)),
+            _ => Ok(db.path().to_owned()),
+        }
+    }
+
     /// Try to get the reference to the Notmuch database path.
+    ///
+    /// Tries `database_path` first, otherwise discovers it from the
+    /// Notmuch configuration file.
     pub fn try_get_database_path(&self) -> Result<PathBuf> {
         match self.database_path.as_ref() {
             Some(path) => Ok(shellexpand_path(path)),
-            None => Self::get_default_database_path(),
+            None => self.discover_database_path(),
         }
     }
 
     /// Try to get the reference to the Maildir path.
     ///
-    /// Tries `maildir_path` first, otherwise falls back to
-    /// `database_path`.
+    /// Tries `maildir_path` first, then `database_path`, otherwise
+    /// discovers it from the Notmuch configuration file.
     pub fn try_get_maildir_path(&self) -> Result<PathBuf> {
-        match self.maildir_path.as_ref() {
-            Some(path) => Ok(shellexpand_path(path)),
-            None => self.try_get_database_path(),
+        match (self.maildir_path.as_ref(), self.database_path.as_ref()) {
+            (Some(path), _) => Ok(shellexpand_path(path)),
+            (None, Some(path)) => Ok(shellexpand_path(path)),
+            (None, None) => self.discover_maildir_path(),
+        }
+    }
+
+    /// Checks that the Notmuch database can be opened and that the
+    /// Maildir path exists.
+    pub fn check_up(&self) -> DoctorReport {
+        let mut report = DoctorReport::new();
+
+        let db = Database::open_with_config(
+            self.database_path.as_ref().map(shellexpand_path),
+            DatabaseMode::ReadOnly,
+            self.find_config_path(),
+            self.find_profile(),
+        );
+
+        if let Err(err) = db.map_err(Error::OpenDatabaseError) {
+            report.push(Problem::from_error("notmuch.database-path", &err));
+            return report;
+        }
+
+        match self.try_get_maildir_path() {
+            Ok(path) if !path.is_dir() => {
+                let msg = format!("Maildir path {} does not exist", path.display());
+                report.push(Problem::warning("notmuch.maildir-path", msg));
+            }
+            Ok(_) => (),
+            Err(err) => report.push(Problem::from_error("notmuch.maildir-path", &err)),
+        }
+
+        report
+    }
+
+    /// Find the tags excluded from envelope listing.
+    ///
+    /// Uses `exclude_tags` if defined, otherwise falls back to the
+    /// `search.exclude_tags` option of the configuration of the given
+    /// database.
+    pub fn find_exclude_tags(&self, db: &Database) -> Vec<String> {
+        match self.exclude_tags.as_ref() {
+            Some(tags) => tags.clone(),
+            None => db
+                .config_values(ConfigKey::ExcludeTags)
+                .map(|tags| tags.filter(|tag| !tag.is_empty()).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Find the query of the saved search matching the given folder
+    /// name.
+    pub fn find_saved_search(&self, folder: &str) -> Option<&str> {
+        self.saved_searches.get(folder).map(String::as_str)
+    }
+
+    /// Find the command used to re-index the Notmuch database.
+    ///
+    /// Uses `index_cmd` if defined, otherwise falls back to
+    /// `notmuch new`.
+    pub fn find_index_cmd(&self) -> Command {
+        if let Some(cmd) = self.index_cmd.as_ref() {
+            return cmd.clone();
         }
+
+        let mut cmd = Command::new("notmuch new --quiet").with_output_piped(true);
+
+        if let Some(path) = self.find_config_path() {
+            cmd.set_env("NOTMUCH_CONFIG", shellexpand_path(path).display());
+        }
+
+        if let Some(profile) = self.find_profile() {
+            cmd.set_env("NOTMUCH_PROFILE", profile);
+        }
+
+        cmd
     }
 
     /// Find the Notmuch configuration path reference.
@@ -91,3 +250,288 @@ pub fn find_profile(&self) -> Option<&str> {
         self.profile.as_deref()
     }
 }
+
+/// The Notmuch tags configuration.
+///
+/// Defines the bidirectional mapping between Notmuch tags and flags
+/// (Maildir flags and IMAP keywords). This mapping is used when
+/// reading and writing flags, hence by synchronization as well.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct NotmuchTagsConfig {
+    /// The tag mapped to the seen flag.
+    ///
+    /// Defaults to `!unread`.
+    #[cfg_attr(feature = "derive", serde(default = "NotmuchTagsConfig::default_seen"))]
+    pub seen: NotmuchTag,
+
+    /// The tag mapped to the answered flag.
+    ///
+    /// Defaults to `replied`.
+    #[cfg_attr(
+        feature = "derive",
+        serde(default = "NotmuchTagsConfig::default_answered")
+    )]
+    pub answered: NotmuchTag,
+
+    /// The tag mapped to the flagged flag.
+    ///
+    /// Defaults to `flagged`.
+    #[cfg_attr(
+        feature = "derive",
+        serde(default = "NotmuchTagsConfig::default_flagged")
+    )]
+    pub flagged: NotmuchTag,
+
+    /// The tag mapped to the deleted flag.
+    ///
+    /// Defaults to `deleted`.
+    #[cfg_attr(
+        feature = "derive",
+        serde(default = "NotmuchTagsConfig::default_deleted")
+    )]
+    pub deleted: NotmuchTag,
+
+    /// The tag mapped to the draft flag.
+    ///
+    /// Defaults to `draft`.
+    #[cfg_attr(
+        feature = "derive",
+        serde(default = "NotmuchTagsConfig::default_draft")
+    )]
+    pub draft: NotmuchTag,
+
+    /// The custom tags mapped to keywords.
+    ///
+    /// Keys are Notmuch tags, values are keywords (custom flags).
+    /// Tags not listed here are mapped to the keyword of the same
+    /// name.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub keywords: BTreeMap<String, String>,
+}
+
+impl NotmuchTagsConfig {
+    pub fn default_seen() -> NotmuchTag {
+        NotmuchTag::negated("unread")
+    }
+
+    pub fn default_answered() -> NotmuchTag {
+        NotmuchTag::new("replied")
+    }
+
+    pub fn default_flagged() -> NotmuchTag {
+        NotmuchTag::new("flagged")
+    }
+
+    pub fn default_deleted() -> NotmuchTag {
+        NotmuchTag::new("deleted")
+    }
+
+    pub fn default_draft() -> NotmuchTag {
+        NotmuchTag::new("draft")
+    }
+
+    /// Iterates over system flags and their associated tag.
+    pub fn iter(&self) -> impl Iterator<Item = (Flag, &NotmuchTag)> {
+        [
+            (Flag::Seen, &self.seen),
+            (Flag::Answered, &self.answered),
+            (Flag::Flagged, &self.flagged),
+            (Flag::Deleted, &self.deleted),
+            (Flag::Draft, &self.draft),
+        ]
+        .into_iter()
+    }
+
+    /// Gets the tag associated to the given flag.
+    pub fn get_tag(&self, flag: &Flag) -> NotmuchTag {
+        match flag {
+            Flag::Seen => self.seen.clone(),
+            Flag::Answered => self.answered.clone(),
+            Flag::Flagged => self.flagged.clone(),
+            Flag::Deleted => self.deleted.clone(),
+            Flag::Draft => self.draft.clone(),
+            Flag::Custom(keyword) => self
+                .keywords
+                .iter()
+                .find(|(_, k)| *k == keyword)
+                .map(|(tag, _)| NotmuchTag::new(tag))
+                .unwrap_or_else(|| NotmuchTag::new(keyword)),
+        }
+    }
+
+    /// Builds flags from the given Notmuch tags.
+    pub fn to_flags(&self, tags: impl IntoIterator<Item = String>) -> Flags {
+        let tags = HashSet::<String>::from_iter(tags);
+        let mut flags = Flags::default();
+
+        for (flag, tag) in self.iter() {
+            if tag.is_set(&tags) {
+                flags.insert(flag);
+            }
+        }
+
+        for tag in &tags {
+            if self.iter().any(|(_, t)| &t.name == tag) {
+                continue;
+            }
+
+            match self.keywords.get(tag) {
+                Some(keyword) => flags.insert(Flag::custom(keyword)),
+                None => flags.insert(Flag::custom(tag)),
+            };
+        }
+
+        flags
+    }
+}
+
+impl Default for NotmuchTagsConfig {
+    fn default() -> Self {
+        Self {
+            seen: Self::default_seen(),
+            answered: Self::default_answered(),
+            flagged: Self::default_flagged(),
+            deleted: Self::default_deleted(),
+            draft: Self::default_draft(),
+            keywords: Default::default(),
+        }
+    }
+}
+
+/// The Notmuch tag associated to a flag.
+///
+/// The flag is set when the tag is present, or when the tag is
+/// absent if the tag is negated. A negated tag is represented by a
+/// leading `!`, for example `!unread` for the seen flag.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "String", into = "String")
+)]
+pub struct NotmuchTag {
+    /// The name of the tag.
+    pub name: String,
+
+    /// Whether the flag is set when the tag is absent.
+    pub negated: bool,
+}
+
+impl NotmuchTag {
+    pub fn new(name: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            negated: false,
+        }
+    }
+
+    pub fn negated(name: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            negated: true,
+        }
+    }
+
+    /// Returns `true` if the flag is set for the given tags.
+    pub fn is_set(&self, tags: &HashSet<String>) -> bool {
+        tags.contains(&self.name) != self.negated
+    }
+
+    /// Adds or removes the tag from the given message so that the
+    /// flag is set.
+    pub fn set(&self, msg: &Message) -> Result<()> {
+        if self.negated {
+            msg.remove_tag(&self.name)
+        } else {
+            msg.add_tag(&self.name)
+        }
+        .map_err(|err| Error::UpdateTagError(err, self.name.clone()))
+    }
+
+    /// Adds or removes the tag from the given message so that the
+    /// flag is unset.
+    pub fn unset(&self, msg: &Message) -> Result<()> {
+        if self.negated {
+            msg.add_tag(&self.name)
+        } else {
+            msg.remove_tag(&self.name)
+        }
+        .map_err(|err| Error::UpdateTagError(err, self.name.clone()))
+    }
+}
+
+impl From<String> for NotmuchTag {
+    fn from(tag: String) -> Self {
+        match tag.strip_prefix('!') {
+            Some(name) => Self::negated(name),
+            None => Self::new(tag),
+        }
+    }
+}
+
+impl From<NotmuchTag> for String {
+    fn from(tag: NotmuchTag) -> Self {
+        tag.to_string()
+    }
+}
+
+impl fmt::Display for NotmuchTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negated {
+            write!(f, "!")?;
+        }
+
+        write!(f, "{}", self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NotmuchTag, NotmuchTagsConfig};
+    use crate::flag::{Flag, Flags};
+
+    #[test]
+    fn default_tags_to_flags() {
+        let config = NotmuchTagsConfig::default();
+
+        assert_eq!(
+            config.to_flags(["replied".into(), "custom".into()]),
+            Flags::from_iter([Flag::Seen, Flag::Answered, Flag::custom("custom")]),
+        );
+
+        assert_eq!(
+            config.to_flags(["unread".into(), "deleted".into()]),
+            Flags::from_iter([Flag::Deleted]),
+        );
+    }
+
+    #[test]
+    fn custom_tags_to_flags() {
+        let config = NotmuchTagsConfig {
+            deleted: NotmuchTag::from(String::from("!inbox")),
+            keywords: [("todo".into(), "$Todo".into())].into_iter().collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            config.to_flags(["inbox".into(), "unread".into(), "todo".into()]),
+            Flags::from_iter([Flag::custom("$Todo")]),
+        );
+
+        assert_eq!(
+            config.to_flags([]),
+            Flags::from_iter([Flag::Seen, Flag::Deleted]),
+        );
+
+        assert_eq!(
+            config.get_tag(&Flag::custom("$Todo")),
+            NotmuchTag::new("todo")
+        );
+        assert_eq!(config.get_tag(&Flag::Deleted).to_string(), "!inbox");
+    }
+}
@@ -2,7 +2,7 @@
 
 use thiserror::Error;
 
-use crate::{AnyBoxedError, AnyError};
+use crate::{AnyBoxedError, AnyError, ErrorKind};
 
 /// The global `Result` alias of the module.
 pub type Result<T> = result::Result<T, Error>;
@@ -18,12 +18,39 @@ pub enum Error {
     ExecuteQueryError(#[source] notmuch::Error),
     #[error("cannot close notmuch database")]
     CloseDatabaseError(#[source] notmuch::Error),
+    #[error("cannot update notmuch tag {1}")]
+    UpdateTagError(#[source] notmuch::Error, String),
+    #[error("cannot re-index notmuch database")]
+    IndexDatabaseError(#[source] process::Error),
+    #[error("cannot write messages to virtual folder {0}: saved searches are read-only")]
+    WriteVirtualFolderError(String),
+}
+
+impl Error {
+    /// Returns the kind of the error.
+    ///
+    /// See [`ErrorKind`] for the available kinds.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::OpenDatabaseError(_) => ErrorKind::Config,
+            Self::CreateQueryError(_) => ErrorKind::Protocol,
+            Self::ExecuteQueryError(_)
+            | Self::CloseDatabaseError(_)
+            | Self::UpdateTagError(..)
+            | Self::IndexDatabaseError(_)
+            | Self::WriteVirtualFolderError(_) => ErrorKind::Other,
+        }
+    }
 }
 
 impl AnyError for Error {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn kind(&self) -> ErrorKind {
+        Error::kind(self)
+    }
 }
 
 impl From<Error> for AnyBoxedError {
@@ -6,6 +6,8 @@
 use async_trait::async_trait;
 use maildirs::Maildirs;
 use notmuch::{Database, DatabaseMode};
+use once_cell::sync::Lazy;
+use regex::Regex;
 use shellexpand_utils::shellexpand_path;
 use tokio::sync::Mutex;
 use tracing::info;
@@ -25,12 +27,16 @@
     },
     flag::{
         add::{notmuch::AddNotmuchFlags, AddFlags},
+        list::{notmuch::ListNotmuchFlags, ListFlags},
         remove::{notmuch::RemoveNotmuchFlags, RemoveFlags},
         set::{notmuch::SetNotmuchFlags, SetFlags},
     },
     folder::{
         add::{notmuch::AddNotmuchFolder, AddFolder},
+        delete::{notmuch::DeleteNotmuchFolder, DeleteFolder},
+        expunge::{notmuch::ExpungeNotmuchFolder, ExpungeFolder},
         list::{notmuch::ListNotmuchFolders, ListFolders},
+        Folder, FolderKind, Folders,
     },
     maildir::{config::MaildirConfig, MaildirContext},
     message::{
@@ -45,6 +51,10 @@
     AnyResult,
 };
 
+/// Regex extracting the Maildir folder from a Notmuch query.
+static EXTRACT_FOLDER_FROM_QUERY: Lazy<Regex> =
+    Lazy::new(|| Regex::new("folder:\"?([^\"]*)\"?").unwrap());
+
 /// The Notmuch backend context.
 ///
 /// The Notmuch database internally uses `Rc` which prevents it to be
@@ -83,6 +93,97 @@ pub fn open_db(&self) -> Result<Database> {
     pub fn maildirpp(&self) -> bool {
         self.notmuch_config.maildirpp
     }
+
+    /// Build the Notmuch query matching the messages of the given
+    /// saved search, if the given folder is one.
+    ///
+    /// Saved searches are matched by their raw name, the same way
+    /// they are listed by [`NotmuchContext::list_saved_searches`].
+    pub fn saved_search_query(&self, folder: &str) -> Option<String> {
+        let query = self.notmuch_config.find_saved_search(folder)?;
+        Some(format!("({query})"))
+    }
+
+    /// Build the Notmuch query matching the messages of the given
+    /// folder.
+    ///
+    /// Saved searches are expanded to their query, other folders are
+    /// matched using the `folder:` prefix.
+    pub fn folder_query(&self, folder: &str) -> String {
+        if let Some(query) = self.saved_search_query(folder) {
+            return query;
+        }
+
+        let folder = self.account_config.get_folder_alias(folder);
+
+        if self.maildirpp() && FolderKind::matches_inbox(&folder) {
+            String::from("folder:\"\"")
+        } else {
+            format!("folder:{folder:?}")
+        }
+    }
+
+    /// List the saved searches as virtual folders.
+    ///
+    /// Saved searches are not listed by [`ListFolders`], so that they
+    /// are not synchronized as real folders. The description of each
+    /// folder is its Notmuch query.
+    pub fn list_saved_searches(&self) -> Folders {
+        self.notmuch_config
+            .saved_searches
+            .iter()
+            .map(|(name, query)| Folder {
+                kind: self
+                    .account_config
+                    .find_folder_kind_from_alias(name)
+                    .or_else(|| name.parse().ok()),
+                name: name.clone(),
+                desc: query.clone(),
+            })
+            .collect()
+    }
+
+    /// Ensure that messages can be written to the given folder.
+    ///
+    /// Saved searches are virtual folders: messages cannot be added,
+    /// copied nor moved into them.
+    pub fn check_writable_folder(&self, folder: &str) -> Result<()> {
+        if self.notmuch_config.find_saved_search(folder).is_some() {
+            return Err(Error::WriteVirtualFolderError(folder.to_owned()));
+        }
+
+        Ok(())
+    }
+
+    /// Find the Maildir folder matching the given folder.
+    ///
+    /// Folder aliases defined as Notmuch queries are supported as
+    /// long as they contain a `folder:` term.
+    pub fn find_maildir_folder(&self, folder: &str) -> String {
+        match self.account_config.find_folder_alias(folder) {
+            Some(alias) => EXTRACT_FOLDER_FROM_QUERY
+                .captures(&alias)
+                .map(|m| m[1].to_owned())
+                .unwrap_or(folder.to_owned()),
+            None => folder.to_owned(),
+        }
+    }
+
+    /// Re-index the Notmuch database, so that changes applied to the
+    /// underlying Maildir tree are taken into account.
+    ///
+    /// See [`NotmuchConfig::find_index_cmd`].
+    pub async fn reindex(&self) -> Result<()> {
+        info!("re-indexing notmuch database");
+
+        self.notmuch_config
+            .find_index_cmd()
+            .run()
+            .await
+            .map_err(Error::IndexDatabaseError)?;
+
+        Ok(())
+    }
 }
 
 /// The sync version of the Notmuch backend context.
@@ -155,20 +256,26 @@ fn list_folders(&self) -> Option<BackendFeature<Self::Context, dyn ListFolders>>
         Some(Arc::new(ListNotmuchFolders::some_new_boxed))
     }
 
-    // TODO
-    // fn expunge_folder(&self) -> Option<BackendFeature<Self::Context, dyn ExpungeFolder>> {
-    //     Some(Arc::new(ExpungeNotmuchFolder::some_new_boxed))
-    // }
+    fn expunge_folder(&self) -> Option<BackendFeature<Self::Context, dyn ExpungeFolder>> {
+        if !self.notmuch_config.manage_folders {
+            return None;
+        }
+
+        Some(Arc::new(ExpungeNotmuchFolder::some_new_boxed))
+    }
 
     // TODO
     // fn purge_folder(&self) -> Option<BackendFeature<Self::Context, dyn PurgeFolder>> {
     //     Some(Arc::new(PurgeNotmuchFolder::some_new_boxed))
     // }
 
-    // TODO
-    // fn delete_folder(&self) -> Option<BackendFeature<Self::Context, dyn DeleteFolder>> {
-    //     Some(Arc::new(DeleteNotmuchFolder::some_new_boxed))
-    // }
+    fn delete_folder(&self) -> Option<BackendFeature<Self::Context, dyn DeleteFolder>> {
+        if !self.notmuch_config.manage_folders {
+            return None;
+        }
+
+        Some(Arc::new(DeleteNotmuchFolder::some_new_boxed))
+    }
 
     fn get_envelope(&self) -> Option<BackendFeature<Self::Context, dyn GetEnvelope>> {
         Some(Arc::new(GetNotmuchEnvelope::some_new_boxed))
@@ -195,6 +302,10 @@ fn remove_flags(&self) -> Option<BackendFeature<Self::Context, dyn RemoveFlags>>
         Some(Arc::new(RemoveNotmuchFlags::some_new_boxed))
     }
 
+    fn list_flags(&self) -> Option<BackendFeature<Self::Context, dyn ListFlags>> {
+        Some(Arc::new(ListNotmuchFlags::some_new_boxed))
+    }
+
     fn add_message(&self) -> Option<BackendFeature<Self::Context, dyn AddMessage>> {
         Some(Arc::new(AddNotmuchMessage::some_new_boxed))
     }
@@ -231,6 +342,9 @@ async fn build(self) -> AnyResult<Self::Context> {
         let maildir_config = Arc::new(MaildirConfig {
             root_dir: root.path().to_owned(),
             maildirpp: self.notmuch_config.maildirpp,
+            layout: None,
+            uid_db: false,
+            envelopes: Default::default(),
         });
 
         let mdir_ctx = MaildirContext {
@@ -287,3 +401,106 @@ async fn check_up(&self) -> AnyResult<()> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, HashMap},
+        sync::Arc,
+    };
+
+    use maildirs::Maildirs;
+    use tempfile::{tempdir, TempDir};
+    use tokio::sync::Mutex;
+
+    use super::{config::NotmuchConfig, Error, NotmuchContext, NotmuchContextSync};
+    use crate::{
+        account::config::AccountConfig,
+        folder::{
+            config::FolderConfig,
+            list::{notmuch::ListNotmuchFolders, ListFolders},
+        },
+        maildir::{config::MaildirConfig, MaildirContext},
+    };
+
+    fn context(root: &TempDir) -> NotmuchContextSync {
+        let account_config = Arc::new(AccountConfig {
+            folder: Some(FolderConfig {
+                aliases: Some(HashMap::from_iter([
+                    (String::from("unread"), String::from("Unread")),
+                    (String::from("sent"), String::from("Sent")),
+                ])),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+
+        let notmuch_config = Arc::new(NotmuchConfig {
+            saved_searches: BTreeMap::from_iter([(
+                String::from("unread"),
+                String::from("tag:unread"),
+            )]),
+            ..Default::default()
+        });
+
+        let mdir_ctx = MaildirContext {
+            account_config: account_config.clone(),
+            maildir_config: Arc::new(MaildirConfig {
+                root_dir: root.path().to_owned(),
+                ..Default::default()
+            }),
+            root: Maildirs::new(root.path()),
+        };
+
+        let ctx = NotmuchContext {
+            account_config: account_config.clone(),
+            notmuch_config: notmuch_config.clone(),
+            mdir_ctx,
+        };
+
+        NotmuchContextSync {
+            account_config,
+            notmuch_config,
+            inner: Arc::new(Mutex::new(ctx)),
+        }
+    }
+
+    #[tokio::test]
+    async fn saved_search_query_uses_raw_name() {
+        let root = tempdir().unwrap();
+        let ctx = context(&root);
+        let ctx = ctx.lock().await;
+
+        assert_eq!(ctx.folder_query("unread"), "(tag:unread)");
+        assert_eq!(ctx.folder_query("sent"), "folder:\"Sent\"");
+        assert_eq!(ctx.saved_search_query("sent"), None);
+    }
+
+    #[tokio::test]
+    async fn saved_searches_listed_apart() {
+        let root = tempdir().unwrap();
+        let ctx = context(&root);
+
+        let folders = ListNotmuchFolders::new(&ctx).list_folders().await.unwrap();
+        assert!(!folders.iter().any(|folder| folder.name == "unread"));
+
+        let ctx = ctx.lock().await;
+        let searches = ctx.list_saved_searches();
+        assert_eq!(searches.len(), 1);
+        assert_eq!(searches[0].name, "unread");
+        assert_eq!(searches[0].desc, "tag:unread");
+    }
+
+    #[tokio::test]
+    async fn saved_searches_are_read_only() {
+        let root = tempdir().unwrap();
+        let ctx = context(&root);
+        let ctx = ctx.lock().await;
+
+        assert!(matches!(
+            ctx.check_writable_folder("unread"),
+            Err(Error::WriteVirtualFolderError(folder)) if folder == "unread",
+        ));
+        assert!(ctx.check_writable_folder("sent").is_ok());
+    }
+}
@@ -1,6 +1,18 @@
-use std::{future::IntoFuture, time::Duration};
+use std::{
+    fmt,
+    future::{Future, IntoFuture},
+    hash::{BuildHasher, RandomState},
+    sync::Arc,
+    time::Duration,
+};
 
-use tokio::time::{error::Elapsed, timeout, Timeout};
+use tokio::time::{error::Elapsed, sleep, timeout, Timeout};
+use tracing::debug;
+
+use crate::{
+    metrics::{self, Metric},
+    AnyResult, ErrorKind,
+};
 
 pub type Result<T> = std::result::Result<T, Elapsed>;
 
@@ -32,6 +44,9 @@ pub fn next<T>(&mut self, res: Result<T>) -> RetryState<T> {
             }
             None if self.attempts < 3 => {
                 self.attempts += 1;
+                metrics::emit(Metric::Retry {
+                    attempt: self.attempts,
+                });
                 return RetryState::Retry;
             }
             None => {
@@ -40,3 +55,269 @@ pub fn next<T>(&mut self, res: Result<T>) -> RetryState<T> {
         }
     }
 }
+
+/// The class of a backend operation.
+///
+/// Each class has its own [`RetryPolicy`], see [`RetryPolicies`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum OperationClass {
+    /// Idempotent operations reading data, like listing folders or
+    /// envelopes, getting or peeking messages.
+    Read,
+
+    /// Operations changing data, like adding folders or messages,
+    /// changing flags, copying, moving or deleting messages.
+    Write,
+
+    /// Operations sending messages.
+    Send,
+}
+
+/// The retry predicate.
+///
+/// Wrapper around a function deciding, from the kind of the error,
+/// whether a failed operation should be retried or not.
+#[derive(Clone)]
+pub struct RetryPredicate(Arc<dyn Fn(ErrorKind) -> bool + Send + Sync>);
+
+impl RetryPredicate {
+    /// Creates a new retry predicate from the given function.
+    pub fn new(f: impl Fn(ErrorKind) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    /// Returns `true` if an error of the given kind should be
+    /// retried.
+    pub fn should_retry(&self, kind: ErrorKind) -> bool {
+        (self.0)(kind)
+    }
+}
+
+impl Default for RetryPredicate {
+    /// Only network errors are retried by default.
+    fn default() -> Self {
+        Self::new(|kind| kind == ErrorKind::Network)
+    }
+}
+
+impl fmt::Debug for RetryPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RetryPredicate()")
+    }
+}
+
+impl PartialEq for RetryPredicate {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for RetryPredicate {}
+
+/// The retry policy of a backend operation class.
+///
+/// The default policy does not retry anything.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RetryPolicy {
+    /// The maximum number of retries after the first attempt.
+    pub max_retries: u8,
+
+    /// The delay before the first retry. The delay doubles at each
+    /// retry.
+    pub initial_backoff: Duration,
+
+    /// The maximum delay between two attempts.
+    pub max_backoff: Duration,
+
+    /// Whether a random jitter is applied to the delay, so that
+    /// clients do not retry all at the same time.
+    pub jitter: bool,
+
+    /// Whether the error of a failed attempt should be retried.
+    pub predicate: RetryPredicate,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a new retry policy retrying network errors up to the
+    /// given number of times, with a jittered exponential backoff.
+    pub fn new(max_retries: u8) -> Self {
+        Self {
+            max_retries,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+            jitter: true,
+            predicate: RetryPredicate::default(),
+        }
+    }
+
+    /// Creates a new retry policy that never retries.
+    pub fn never() -> Self {
+        Self::new(0)
+    }
+
+    /// Defines the initial and maximum delays between two attempts,
+    /// using the builder pattern.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Enables or disables the jitter, using the builder pattern.
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Defines the retry predicate from the given function, using
+    /// the builder pattern.
+    pub fn with_predicate(mut self, f: impl Fn(ErrorKind) -> bool + Send + Sync + 'static) -> Self {
+        self.predicate = RetryPredicate::new(f);
+        self
+    }
+
+    /// Returns the delay to wait before the given retry attempt,
+    /// starting from 1.
+    pub fn backoff(&self, attempt: u8) -> Duration {
+        let exp = u32::from(attempt.saturating_sub(1)).min(16);
+        let delay = self
+            .initial_backoff
+            .saturating_mul(1 << exp)
+            .min(self.max_backoff);
+
+        if !self.jitter {
+            return delay;
+        }
+
+        // keeps between half and the whole delay
+        let half = delay / 2;
+        let random = RandomState::new().hash_one(attempt);
+        let jitter = half.mul_f64((random % 1000) as f64 / 1000.0);
+        half + jitter
+    }
+
+    /// Runs the given operation, retrying it according to the
+    /// policy.
+    ///
+    /// The operation is called again for every attempt.
+    pub async fn retry<T, F, Fut>(&self, name: &'static str, mut f: F) -> AnyResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = AnyResult<T>>,
+    {
+        let mut attempt = 0;
+
+        loop {
+            match f().await {
+                Ok(res) => return Ok(res),
+                Err(err)
+                    if attempt < self.max_retries && self.predicate.should_retry(err.kind()) =>
+                {
+                    attempt += 1;
+                    let delay = self.backoff(attempt);
+                    debug!(name, attempt, ?delay, kind = %err.kind(), "retrying backend operation");
+                    debug!("{err:?}");
+                    metrics::emit(Metric::Retry { attempt });
+                    sleep(delay).await;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// The retry policies of a backend, by operation class.
+///
+/// By default, no operation is retried.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RetryPolicies {
+    /// The retry policy of [`OperationClass::Read`] operations.
+    pub read: RetryPolicy,
+
+    /// The retry policy of [`OperationClass::Write`] operations.
+    pub write: RetryPolicy,
+
+    /// The retry policy of [`OperationClass::Send`] operations.
+    pub send: RetryPolicy,
+}
+
+impl RetryPolicies {
+    /// Returns the retry policy of the given operation class.
+    pub fn get(&self, class: OperationClass) -> &RetryPolicy {
+        match class {
+            OperationClass::Read => &self.read,
+            OperationClass::Write => &self.write,
+            OperationClass::Send => &self.send,
+        }
+    }
+
+    /// Defines the retry policy of the given operation class.
+    pub fn set(&mut self, class: OperationClass, policy: RetryPolicy) {
+        match class {
+            OperationClass::Read => self.read = policy,
+            OperationClass::Write => self.write = policy,
+            OperationClass::Send => self.send = policy,
+        }
+    }
+
+    /// Defines the retry policy of the given operation class, using
+    /// the builder pattern.
+    pub fn with(mut self, class: OperationClass, policy: RetryPolicy) -> Self {
+        self.set(class, policy);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicU8, Ordering},
+        time::Duration,
+    };
+
+    use super::RetryPolicy;
+    use crate::{backend::Error, ErrorKind};
+
+    #[test]
+    fn backoff() {
+        let policy = RetryPolicy::new(5)
+            .with_backoff(Duration::from_secs(1), Duration::from_secs(5))
+            .with_jitter(false);
+
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(4), Duration::from_secs(5));
+
+        let policy = policy.with_jitter(true);
+        let delay = policy.backoff(2);
+        assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn retry() {
+        let attempts = AtomicU8::new(0);
+        let f = || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(Error::ListFoldersNotAvailableError.into())
+        };
+
+        // retryable errors are retried until the maximum is reached
+        let policy = RetryPolicy::new(2)
+            .with_backoff(Duration::ZERO, Duration::ZERO)
+            .with_predicate(|kind| kind == ErrorKind::Config);
+        assert!(policy.retry("test", f).await.is_err());
+        assert_eq!(attempts.swap(0, Ordering::SeqCst), 3);
+
+        // other errors are not retried
+        let policy = RetryPolicy::new(2).with_backoff(Duration::ZERO, Duration::ZERO);
+        assert!(policy.retry("test", f).await.is_err());
+        assert_eq!(attempts.swap(0, Ordering::SeqCst), 1);
+    }
+}
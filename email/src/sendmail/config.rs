@@ -3,9 +3,16 @@
 //! This module contains the configuration specific to the sendmail
 //! sender.
 
+use std::path::Path;
+
 use once_cell::sync::Lazy;
 use process::Command;
 
+use crate::{
+    doctor::{DoctorReport, Problem},
+    ErrorKind,
+};
+
 pub static SENDMAIL_DEFAULT_COMMAND: Lazy<Command> =
     Lazy::new(|| Command::new("/usr/bin/sendmail"));
 
@@ -25,4 +32,25 @@ impl SendmailConfig {
     pub fn cmd(&self) -> &Command {
         self.cmd.as_ref().unwrap_or(&*SENDMAIL_DEFAULT_COMMAND)
     }
+
+    /// Checks that the sendmail command is defined and, when given as
+    /// an absolute path, that the program exists.
+    pub fn check_up(&self) -> DoctorReport {
+        let mut report = DoctorReport::new();
+        let cmd = self.cmd().to_string();
+
+        match cmd.split_whitespace().next() {
+            None => {
+                report.push(Problem::error("sendmail.cmd", "sendmail command is empty"));
+            }
+            Some(program) if Path::new(program).is_absolute() && !Path::new(program).exists() => {
+                let msg = format!("sendmail program {program} does not exist");
+                let problem = Problem::error("sendmail.cmd", msg).with_kind(ErrorKind::NotFound);
+                report.push(problem);
+            }
+            Some(_) => (),
+        }
+
+        report
+    }
 }
@@ -5,14 +5,20 @@
 
 use std::io;
 
-use mail_send::Credentials;
+use mail_send::{Credentials, SmtpClientBuilder};
+use secret::Secret;
 use tracing::debug;
 
+use super::build_client;
 #[doc(inline)]
 pub use super::{Error, Result};
 #[cfg(feature = "oauth2")]
 use crate::account::config::oauth2::{OAuth2Config, OAuth2Method};
-use crate::{account::config::passwd::PasswordConfig, tls::Encryption};
+use crate::{
+    account::config::passwd::PasswordConfig,
+    doctor::{DoctorReport, Problem},
+    tls::Encryption,
+};
 
 /// The SMTP sender configuration.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -43,10 +49,44 @@ pub struct SmtpConfig {
     ///
     /// Authentication can be done using password or OAuth 2.0.
     /// See [SmtpAuthConfig].
+    #[cfg_attr(feature = "derive", serde(default))]
     pub auth: SmtpAuthConfig,
 }
 
 impl SmtpConfig {
+    /// Checks that the SMTP server is reachable and that the
+    /// credentials are valid.
+    pub async fn check_up(&self) -> DoctorReport {
+        let mut report = DoctorReport::new();
+
+        if self.host.trim().is_empty() {
+            report.push(Problem::error("smtp.host", "SMTP host is empty"));
+            return report;
+        }
+
+        let credentials = match self.credentials().await {
+            Ok(credentials) => credentials,
+            Err(err) => {
+                report.push(Problem::from_error("smtp.auth", &err));
+                return report;
+            }
+        };
+
+        let mut client_builder = SmtpClientBuilder::new(self.host.clone(), self.port)
+            .credentials(credentials)
+            .implicit_tls(!self.is_start_tls_encryption_enabled());
+
+        if self.is_encryption_disabled() {
+            client_builder = client_builder.allow_invalid_certs();
+        }
+
+        if let Err(err) = build_client(self, client_builder).await {
+            report.push(Problem::from_error("smtp", &err));
+        }
+
+        report
+    }
+
     /// Return `true` if TLS or StartTLS is enabled.
     pub fn is_encryption_enabled(&self) -> bool {
         matches!(
@@ -182,6 +222,31 @@ pub fn replace_empty_secrets(&mut self, name: impl AsRef<str>) -> Result<()> {
 
         Ok(())
     }
+
+    /// Returns the secrets whose keyring entry is derived from the
+    /// account name, along with the suffix of their entry key (for
+    /// example `smtp-passwd` for the `{name}-smtp-passwd` entry).
+    ///
+    /// See [`AccountConfig::rename`].
+    ///
+    /// [`AccountConfig::rename`]: crate::account::config::AccountConfig::rename
+    pub fn account_secrets_mut(&mut self) -> Vec<(&'static str, &mut Secret)> {
+        match self {
+            Self::Password(passwd) => vec![("smtp-passwd", &mut passwd.0)],
+            #[cfg(feature = "oauth2")]
+            Self::OAuth2(config) => {
+                let mut secrets = Vec::with_capacity(3);
+
+                if let Some(secret) = config.client_secret.as_mut() {
+                    secrets.push(("smtp-oauth2-client-secret", secret));
+                }
+
+                secrets.push(("smtp-oauth2-access-token", &mut config.access_token));
+                secrets.push(("smtp-oauth2-refresh-token", &mut config.refresh_token));
+                secrets
+            }
+        }
+    }
 }
 
 impl Default for SmtpAuthConfig {
@@ -2,7 +2,7 @@
 
 use thiserror::Error;
 
-use crate::{AnyBoxedError, AnyError};
+use crate::{AnyBoxedError, AnyError, ErrorKind};
 
 /// The global `Result` alias of the module.
 pub type Result<T> = result::Result<T, Error>;
@@ -40,10 +40,50 @@ pub enum Error {
     MailSendNoOpFailed(#[source] mail_send::Error),
 }
 
+impl Error {
+    /// Returns the kind of the error.
+    ///
+    /// See [`ErrorKind`] for the available kinds.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::GetPasswdSmtpError(_)
+            | Self::GetPasswdEmptySmtpError
+            | Self::AccessTokenWasNotAvailable
+            | Self::RefreshingAccessTokenFailed
+            | Self::ResettingOAuthFailed
+            | Self::ConfiguringOAuthFailed
+            | Self::ReplacingKeyringFailed(_) => ErrorKind::Auth,
+            Self::SendMessageTimedOutError
+            | Self::ConnectTcpSmtpError(_)
+            | Self::ConnectTlsSmtpError(_)
+            | Self::MailSendNoOpFailed(_) => ErrorKind::Network,
+            Self::SendMessageMissingSenderError | Self::SendMessageMissingRecipientError => {
+                ErrorKind::Protocol
+            }
+            Self::SendMessageError(err) => match err {
+                mail_send::Error::Auth(_)
+                | mail_send::Error::AuthenticationFailed(_)
+                | mail_send::Error::MissingCredentials
+                | mail_send::Error::UnsupportedAuthMechanism => ErrorKind::Auth,
+                mail_send::Error::Io(_)
+                | mail_send::Error::Tls(_)
+                | mail_send::Error::Timeout
+                | mail_send::Error::MissingStartTls => ErrorKind::Network,
+                mail_send::Error::InvalidTLSName => ErrorKind::Config,
+                _ => ErrorKind::Protocol,
+            },
+        }
+    }
+}
+
 impl AnyError for Error {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn kind(&self) -> ErrorKind {
+        Error::kind(self)
+    }
 }
 
 impl From<Error> for AnyBoxedError {
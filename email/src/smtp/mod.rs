@@ -264,6 +264,9 @@ pub async fn build_client(
         }
         #[cfg(feature = "oauth2")]
         (SmtpAuthConfig::OAuth2(oauth2_config), false) => {
+            // the access token may have been refreshed since the
+            // client builder was created
+            client_builder = client_builder.credentials(smtp_config.credentials().await?);
             match Ok(build_tcp_client(&client_builder).await?) {
                 Ok(client) => Ok((client_builder, client)),
                 Err(Error::ConnectTcpSmtpError(mail_send::Error::AuthenticationFailed(_))) => {
@@ -281,6 +284,9 @@ pub async fn build_client(
         }
         #[cfg(feature = "oauth2")]
         (SmtpAuthConfig::OAuth2(oauth2_config), true) => {
+            // the access token may have been refreshed since the
+            // client builder was created
+            client_builder = client_builder.credentials(smtp_config.credentials().await?);
             match Ok(build_tls_client(&client_builder).await?) {
                 Ok(client) => Ok((client_builder, client)),
                 Err(Error::ConnectTlsSmtpError(mail_send::Error::AuthenticationFailed(_))) => {
@@ -3,7 +3,7 @@
 use advisory_lock::FileLockError;
 use thiserror::Error;
 
-use crate::{email, folder, AnyBoxedError};
+use crate::{account, email, folder, AnyBoxedError, ErrorKind};
 
 /// The global `Result` alias of the module.
 pub type Result<T> = result::Result<T, Error>;
@@ -17,14 +17,26 @@ pub enum Error {
     LockFileError(#[source] FileLockError, PathBuf),
     #[error("cannot unlock sync file at {1}")]
     UnlockFileError(#[source] FileLockError, PathBuf),
+    #[error("cannot get sync runtime directory")]
+    GetRuntimeDirError(#[source] account::Error),
     #[error("cannot get sync cache directory")]
     GetCacheDirectorySyncError,
+    #[error("cannot read sync cache version at {1}")]
+    ReadCacheVersionError(#[source] io::Error, PathBuf),
+    #[error("cannot parse sync cache version {0} at {1}")]
+    ParseCacheVersionError(String, PathBuf),
+    #[error("cannot write sync cache version at {1}")]
+    WriteCacheVersionError(#[source] io::Error, PathBuf),
+    #[error("sync cache at {2} has version {0}, which is more recent than the supported version {1}: upgrade the library, or remove the cache directory to rebuild it from scratch")]
+    CacheVersionTooRecentError(u32, u32, PathBuf),
     #[error("cannot sync folders")]
     SyncFoldersError(#[source] folder::Error),
     #[error("cannot expunge folders after sync")]
     ExpungeFoldersError(#[source] folder::Error),
     #[error("cannot sync emails")]
     SyncEmailsError(#[source] email::Error),
+    #[error("cannot read sync id mapping")]
+    ReadIdMappingError(#[source] email::Error),
     #[error("cannot configure left sync context")]
     ConfigureLeftContextError(#[source] AnyBoxedError),
     #[error("cannot configure right sync context")]
@@ -36,3 +48,29 @@ pub enum Error {
     #[error("cannot build sync pool context")]
     BuildSyncPoolContextError(#[source] AnyBoxedError),
 }
+
+impl Error {
+    /// Returns the kind of the error.
+    ///
+    /// See [`ErrorKind`] for the available kinds.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::LockFileError(..) => ErrorKind::Conflict,
+            Self::CacheVersionTooRecentError(..) => ErrorKind::Conflict,
+            Self::GetRuntimeDirError(err) => err.kind(),
+            Self::SyncFoldersError(err) | Self::ExpungeFoldersError(err) => err.kind(),
+            Self::SyncEmailsError(err) | Self::ReadIdMappingError(err) => err.kind(),
+            Self::ConfigureLeftContextError(err)
+            | Self::ConfigureRightContextError(err)
+            | Self::LeftContextNotConfiguredError(err)
+            | Self::RightContextNotConfiguredError(err)
+            | Self::BuildSyncPoolContextError(err) => err.kind(),
+            Self::GetCacheDirectorySyncError => ErrorKind::Config,
+            Self::ParseCacheVersionError(..) => ErrorKind::Protocol,
+            Self::OpenLockFileError(..)
+            | Self::UnlockFileError(..)
+            | Self::ReadCacheVersionError(..)
+            | Self::WriteCacheVersionError(..) => ErrorKind::Other,
+        }
+    }
+}
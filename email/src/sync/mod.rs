@@ -3,16 +3,32 @@
 //! Module dedicated to synchronization of folders and emails between
 //! two backends. The main structure of this module is
 //! [`SyncBuilder`].
+//!
+//! ## Custom synchronization engines
+//!
+//! The [`SyncBuilder`] synchronizes two backends using Maildir
+//! caches. Projects willing to synchronize different stores can
+//! build their own engine on top of the same hunk model, using the
+//! low-level primitives re-exported by this module:
+//!
+//! - [`build_folder_patch`] and [`build_email_patch`] compute the
+//!   changes (hunks) needed to synchronize both sides, from pure
+//!   data inputs (folder names and envelopes),
+//! - [`apply_folder_permissions`] and [`apply_email_permissions`]
+//!   remove the hunks not allowed by the given permissions,
+//! - [`FolderSyncHunk`] and [`EmailSyncHunk`] describe the changes
+//!   to apply, which is left to the engine.
 
 mod error;
 pub mod hash;
 pub mod pool;
 pub mod report;
+pub mod version;
 
 use std::{
     collections::{BTreeMap, BTreeSet},
-    env, fmt,
-    fs::{self, OpenOptions},
+    fmt,
+    fs::OpenOptions,
     future::Future,
     hash::{DefaultHasher, Hash, Hasher},
     path::PathBuf,
@@ -21,40 +37,48 @@
 };
 
 use advisory_lock::{AdvisoryFileLock, FileLockMode};
-use dirs::{cache_dir, runtime_dir};
-use once_cell::sync::Lazy;
-use tracing::debug;
+use dirs::cache_dir;
+use tracing::{debug, info_span, Instrument};
 
 #[doc(inline)]
 pub use self::error::{Error, Result};
 use self::{hash::SyncHash, report::SyncReport};
 use crate::{
     backend::{context::BackendContextBuilder, BackendBuilder},
-    email::{self, sync::hunk::EmailSyncHunk},
+    email,
     envelope::sync::config::EnvelopeSyncFilters,
     flag::sync::config::FlagSyncPermissions,
     folder::{
         self,
-        sync::{
-            config::{FolderSyncPermissions, FolderSyncStrategy},
-            hunk::{FolderName, FolderSyncHunk},
-            patch::FolderSyncPatch,
-        },
+        retention::ApplyFolderRetention,
+        sync::config::{FolderSyncPermissions, FolderSyncStrategy},
     },
     maildir::{config::MaildirConfig, MaildirContextBuilder},
     message::sync::config::MessageSyncPermissions,
+    metrics::measure_sync_phase,
     sync::pool::{SyncPoolConfig, SyncPoolContextBuilder},
 };
-
-static RUNTIME_DIR: Lazy<PathBuf> = Lazy::new(|| {
-    let dir = runtime_dir()
-        .unwrap_or_else(env::temp_dir)
-        .join("pimalaya")
-        .join("email")
-        .join("sync");
-    fs::create_dir_all(&dir).expect(&format!("should create runtime directory {dir:?}"));
-    dir
-});
+#[doc(inline)]
+pub use crate::{
+    email::sync::{
+        conflict::EmailSyncConflict,
+        hunk::EmailSyncHunk,
+        id_mapping::EmailSyncIdMapping,
+        patch::{
+            apply_permissions as apply_email_permissions, build as build_email_patch,
+            into_sync_entry, EmailSyncPatch,
+        },
+    },
+    folder::sync::{
+        hunk::{FolderName, FolderSyncHunk, FoldersName},
+        patch::{
+            apply_permissions as apply_folder_permissions, build as build_folder_patch,
+            build_with_tombstones as build_folder_patch_with_tombstones, FolderSyncPatch,
+            FolderSyncPatches,
+        },
+        tombstone::FolderSyncTombstones,
+    },
+};
 
 /// The synchronization builder.
 #[derive(Clone)]
@@ -192,6 +216,26 @@ pub fn with_folder_filters(mut self, f: impl Into<FolderSyncStrategy>) -> Self {
         self
     }
 
+    // folder deletions propagation setters
+
+    pub fn set_some_propagate_folder_deletions(&mut self, propagate: Option<bool>) {
+        self.config.propagate_folder_deletions = propagate;
+    }
+
+    pub fn set_propagate_folder_deletions(&mut self, propagate: bool) {
+        self.set_some_propagate_folder_deletions(Some(propagate));
+    }
+
+    pub fn with_some_propagate_folder_deletions(mut self, propagate: Option<bool>) -> Self {
+        self.set_some_propagate_folder_deletions(propagate);
+        self
+    }
+
+    pub fn with_propagate_folder_deletions(mut self, propagate: bool) -> Self {
+        self.set_propagate_folder_deletions(propagate);
+        self
+    }
+
     // left folder permissions setters
 
     pub fn set_some_left_folder_permissions(
@@ -376,6 +420,26 @@ pub fn get_cache_dir(&self) -> Result<PathBuf> {
             .ok_or(Error::GetCacheDirectorySyncError.into())
     }
 
+    /// Reads the id mapping persisted by the last synchronization.
+    pub fn get_id_mapping(&self) -> Result<EmailSyncIdMapping> {
+        let root_dir = self.get_cache_dir()?.join(&self.left_hash);
+        EmailSyncIdMapping::read(root_dir).map_err(Error::ReadIdMappingError)
+    }
+
+    /// Finds the right (remote) identifier of the given left (local)
+    /// email identifier, as mapped by the last synchronization.
+    pub fn map_local_to_remote(&self, folder: &str, id: &str) -> Result<Option<String>> {
+        let mapping = self.get_id_mapping()?;
+        Ok(mapping.left_to_right(folder, id).map(ToOwned::to_owned))
+    }
+
+    /// Finds the left (local) identifier of the given right (remote)
+    /// email identifier, as mapped by the last synchronization.
+    pub fn map_remote_to_local(&self, folder: &str, id: &str) -> Result<Option<String>> {
+        let mapping = self.get_id_mapping()?;
+        Ok(mapping.right_to_left(folder, id).map(ToOwned::to_owned))
+    }
+
     pub fn get_left_cache_builder(&self) -> Result<BackendBuilder<MaildirContextBuilder>> {
         let left_config = self.left_builder.account_config.clone();
         let root_dir = self.get_cache_dir()?.join(&self.left_hash);
@@ -384,6 +448,9 @@ pub fn get_left_cache_builder(&self) -> Result<BackendBuilder<MaildirContextBuil
             Arc::new(MaildirConfig {
                 root_dir,
                 maildirpp: false,
+                layout: None,
+                uid_db: false,
+                envelopes: Default::default(),
             }),
         );
         let left_cache_builder = BackendBuilder::new(left_config, ctx);
@@ -398,16 +465,42 @@ pub fn get_right_cache_builder(&self) -> Result<BackendBuilder<MaildirContextBui
             Arc::new(MaildirConfig {
                 root_dir,
                 maildirpp: false,
+                layout: None,
+                uid_db: false,
+                envelopes: Default::default(),
             }),
         );
         let right_cache_builder = BackendBuilder::new(right_config, ctx);
         Ok(right_cache_builder)
     }
 
+    /// Migrates the left and right caches to the current cache
+    /// version.
+    ///
+    /// This function is automatically called before synchronizing.
+    pub fn migrate(&self) -> Result<()> {
+        let cache_dir = self.get_cache_dir()?;
+        version::migrate(cache_dir.join(&self.left_hash))?;
+        version::migrate(cache_dir.join(&self.right_hash))?;
+        Ok(())
+    }
+
     // build
 
     pub async fn sync(self) -> Result<SyncReport> {
-        let left_lock_file_path = RUNTIME_DIR.join(format!("{}.lock", self.left_hash));
+        let span = info_span!("sync", left = %self.left_hash, right = %self.right_hash);
+        self.sync_inner().instrument(span).await
+    }
+
+    async fn sync_inner(self) -> Result<SyncReport> {
+        let runtime_dir = self
+            .left_builder
+            .account_config
+            .get_global_runtime_dir()
+            .and_then(|dir| dir.subdir("sync"))
+            .map_err(Error::GetRuntimeDirError)?;
+
+        let left_lock_file_path = runtime_dir.join(format!("{}.lock", self.left_hash));
         debug!("locking left sync file {left_lock_file_path:?}");
         let left_lock_file = OpenOptions::new()
             .create(true)
@@ -419,7 +512,7 @@ pub async fn sync(self) -> Result<SyncReport> {
             .try_lock(FileLockMode::Exclusive)
             .map_err(|err| Error::LockFileError(err, left_lock_file_path.clone()))?;
 
-        let right_lock_file_path = RUNTIME_DIR.join(format!("{}.lock", self.right_hash));
+        let right_lock_file_path = runtime_dir.join(format!("{}.lock", self.right_hash));
         debug!("locking right sync file {right_lock_file_path:?}");
         let right_lock_file = OpenOptions::new()
             .create(true)
@@ -431,6 +524,8 @@ pub async fn sync(self) -> Result<SyncReport> {
             .try_lock(FileLockMode::Exclusive)
             .map_err(|err| Error::LockFileError(err, right_lock_file_path.clone()))?;
 
+        self.migrate()?;
+
         let mut left_cache_builder = self.get_left_cache_builder()?;
         let left_cache_check = left_cache_builder.ctx_builder.check_configuration();
 
@@ -495,29 +590,51 @@ pub async fn sync(self) -> Result<SyncReport> {
             }
         }?;
 
-        let ctx = Arc::new(
-            SyncPoolContextBuilder::new(
-                self.config,
-                left_cache_builder,
-                left_builder,
-                right_cache_builder,
-                right_builder,
-            )
-            .build()
+        let ctx = SyncPoolContextBuilder::new(
+            self.config,
+            left_cache_builder,
+            left_builder,
+            right_cache_builder,
+            right_builder,
+        )
+        .build();
+        let ctx = measure_sync_phase("context", ctx)
             .await
-            .map_err(Error::BuildSyncPoolContextError)?,
-        );
+            .map_err(Error::BuildSyncPoolContextError)?;
+        let ctx = Arc::new(ctx);
 
         let mut report = SyncReport::default();
 
-        report.folder = folder::sync::<L, R>(ctx.clone())
+        report.folder = measure_sync_phase("folders", folder::sync::<L, R>(ctx.clone()))
             .await
             .map_err(Error::SyncFoldersError)?;
-        report.email = email::sync::<L, R>(ctx.clone(), &report.folder.names)
+
+        let emails = email::sync::<L, R>(ctx.clone(), &report.folder.names);
+        report.email = measure_sync_phase("emails", emails)
             .await
             .map_err(Error::SyncEmailsError)?;
 
-        folder::sync::expunge::<L, R>(ctx.clone(), &report.folder.names).await;
+        let expunge = folder::sync::expunge::<L, R>(ctx.clone(), &report.folder.names);
+        measure_sync_phase("expunge", async {
+            expunge.await;
+            Ok::<_, Error>(())
+        })
+        .await?;
+
+        let retention_enabled = ctx
+            .right
+            .account_config
+            .folder
+            .as_ref()
+            .and_then(|c| c.retention.as_ref())
+            .is_some_and(|c| c.after_sync);
+
+        if retention_enabled && !ctx.dry_run {
+            report.retention = measure_sync_phase("retention", async {
+                Ok::<_, Error>(ctx.right.apply_folder_retention().await)
+            })
+            .await?;
+        }
 
         debug!("unlocking sync files");
         left_lock_file
@@ -1,20 +1,19 @@
 use std::{collections::BTreeSet, sync::Arc};
 
+use super::SyncEventHandler;
 #[doc(inline)]
 pub use super::{Error, Result};
-use super::{SyncDestination, SyncEventHandler};
 use crate::{
     backend::{
         context::{BackendContext, BackendContextBuilder},
         Backend, BackendBuilder,
     },
-    email::sync::hunk::EmailSyncHunk,
+    email::sync::{hunk::EmailSyncHunk, patch as email_patch},
     envelope::sync::config::EnvelopeSyncFilters,
     flag::sync::config::FlagSyncPermissions,
     folder::sync::{
         config::{FolderSyncPermissions, FolderSyncStrategy},
-        hunk::FolderSyncHunk,
-        patch::FolderSyncPatches,
+        patch::{self as folder_patch, FolderSyncPatches},
     },
     maildir::{MaildirContextBuilder, MaildirContextSync},
     message::sync::config::MessageSyncPermissions,
@@ -31,6 +30,7 @@ pub struct SyncPoolConfig {
     pub right_message_permissions: Option<MessageSyncPermissions>,
     pub pool_size: Option<usize>,
     pub folder_filters: Option<FolderSyncStrategy>,
+    pub propagate_folder_deletions: Option<bool>,
     pub envelope_filters: Option<EnvelopeSyncFilters>,
     pub handler: Option<Arc<SyncEventHandler>>,
     pub dry_run: Option<bool>,
@@ -51,8 +51,8 @@ pub struct SyncPoolContextBuilder<L, R>
 
 impl<L, R> SyncPoolContextBuilder<L, R>
 where
-    L: BackendContextBuilder,
-    R: BackendContextBuilder,
+    L: BackendContextBuilder + 'static,
+    R: BackendContextBuilder + 'static,
 {
     pub fn new(
         config: SyncPoolConfig,
@@ -169,6 +169,23 @@ pub async fn build(self) -> AnyResult<SyncPoolContext<L::Context, R::Context>> {
             })
             .unwrap_or_default();
 
+        let propagate_folder_deletions =
+            self.config.propagate_folder_deletions.unwrap_or_else(|| {
+                [
+                    &self.left_builder.account_config,
+                    &self.right_builder.account_config,
+                ]
+                .into_iter()
+                .all(|config| {
+                    config
+                        .folder
+                        .as_ref()
+                        .and_then(|c| c.sync.as_ref())
+                        .map(|c| c.propagate_deletions)
+                        .unwrap_or(true)
+                })
+            });
+
         let envelope_filters = self
             .config
             .envelope_filters
@@ -202,6 +219,7 @@ pub async fn build(self) -> AnyResult<SyncPoolContext<L::Context, R::Context>> {
             right_flag_permissions,
             right_message_permissions,
             folder_filters,
+            propagate_folder_deletions,
             envelope_filters,
             handler: self.config.handler,
             dry_run: self.config.dry_run.unwrap_or_default(),
@@ -221,6 +239,7 @@ pub struct SyncPoolContext<L: BackendContext, R: BackendContext> {
     pub right_flag_permissions: FlagSyncPermissions,
     pub right_message_permissions: MessageSyncPermissions,
     pub folder_filters: FolderSyncStrategy,
+    pub propagate_folder_deletions: bool,
     pub envelope_filters: EnvelopeSyncFilters,
     pub handler: Option<Arc<SyncEventHandler>>,
     pub dry_run: bool,
@@ -228,34 +247,20 @@ pub struct SyncPoolContext<L: BackendContext, R: BackendContext> {
 
 impl<L: BackendContext, R: BackendContext> SyncPoolContext<L, R> {
     pub fn apply_folder_permissions(&self, patch: &mut FolderSyncPatches) {
-        use FolderSyncHunk::*;
-        use SyncDestination::*;
-
-        for (_, patch) in patch.iter_mut() {
-            patch.retain(|hunk| match hunk {
-                Create(_, Left) | Cache(_, Left) => self.left_folder_permissions.create,
-                Create(_, Right) | Cache(_, Right) => self.right_folder_permissions.create,
-                Delete(_, Left) | Uncache(_, Left) => self.left_folder_permissions.delete,
-                Delete(_, Right) | Uncache(_, Right) => self.right_folder_permissions.delete,
-            });
-        }
+        folder_patch::apply_permissions(
+            patch,
+            &self.left_folder_permissions,
+            &self.right_folder_permissions,
+        )
     }
 
     pub fn apply_flag_and_message_permissions(&self, patch: &mut BTreeSet<EmailSyncHunk>) {
-        use EmailSyncHunk::*;
-        use SyncDestination::*;
-
-        patch.retain(|hunk| match hunk {
-            GetThenCache(_, _, Left) => self.left_message_permissions.create,
-            GetThenCache(_, _, Right) => self.right_message_permissions.create,
-            CopyThenCache(_, _, _, Left, _) => self.left_message_permissions.create,
-            CopyThenCache(_, _, _, Right, _) => self.right_message_permissions.create,
-            UpdateCachedFlags(_, _, Left) => self.left_flag_permissions.update,
-            UpdateCachedFlags(_, _, Right) => self.right_flag_permissions.update,
-            UpdateFlags(_, _, Left) => self.left_flag_permissions.update,
-            UpdateFlags(_, _, Right) => self.right_flag_permissions.update,
-            Uncache(_, _, Left) | Delete(_, _, Left) => self.left_message_permissions.delete,
-            Uncache(_, _, Right) | Delete(_, _, Right) => self.right_message_permissions.delete,
-        });
+        email_patch::apply_permissions(
+            patch,
+            &self.left_flag_permissions,
+            &self.left_message_permissions,
+            &self.right_flag_permissions,
+            &self.right_message_permissions,
+        )
     }
 }
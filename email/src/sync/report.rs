@@ -3,7 +3,10 @@
 //! Module dedicated to synchronization reporting. The main structure
 //! of thi module is [`SyncReport`].
 
-use crate::{email::sync::report::EmailSyncReport, folder::sync::report::FolderSyncReport};
+use crate::{
+    email::sync::report::EmailSyncReport,
+    folder::{retention::FolderRetentionReport, sync::report::FolderSyncReport},
+};
 
 /// The synchronization report.
 ///
@@ -16,4 +19,8 @@ pub struct SyncReport {
 
     /// The report of email synchronization.
     pub email: EmailSyncReport,
+
+    /// The report of folder retention applied after
+    /// synchronization, if enabled.
+    pub retention: FolderRetentionReport,
 }
@@ -0,0 +1,138 @@
+//! # Sync cache version
+//!
+//! Module dedicated to synchronization cache versioning.
+//!
+//! The version of a synchronization cache is stored in a file at the
+//! root of its directory. When the cache format changes, the version
+//! is increased and a migration is added, so that existing caches
+//! are upgraded instead of being silently broken or wiped.
+
+use std::{fs, io, path::Path};
+
+use tracing::debug;
+
+use super::{Error, Result};
+
+/// The current version of the synchronization cache.
+pub const SYNC_CACHE_VERSION: u32 = 1;
+
+/// The name of the file containing the cache version, at the root of
+/// the cache directory.
+pub const SYNC_CACHE_VERSION_FILE_NAME: &str = ".version";
+
+/// The cache migration, from one version to the next one.
+type Migration = fn(&Path) -> Result<()>;
+
+/// The cache migrations, indexed by the version they migrate from.
+const MIGRATIONS: [Migration; SYNC_CACHE_VERSION as usize] = [migrate_v0_to_v1];
+
+/// Reads the version of the cache at the given root directory.
+///
+/// Returns `None` for new caches. Caches created before versioning
+/// are considered at version 0.
+pub fn read_version(root_dir: impl AsRef<Path>) -> Result<Option<u32>> {
+    let root_dir = root_dir.as_ref();
+    let path = root_dir.join(SYNC_CACHE_VERSION_FILE_NAME);
+
+    match fs::read_to_string(&path) {
+        Ok(version) => {
+            let version = version.trim();
+            let version = version
+                .parse()
+                .map_err(|_| Error::ParseCacheVersionError(version.to_owned(), path))?;
+            Ok(Some(version))
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let is_empty = match fs::read_dir(root_dir) {
+                Ok(mut entries) => entries.next().is_none(),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => true,
+                Err(err) => return Err(Error::ReadCacheVersionError(err, root_dir.to_owned())),
+            };
+
+            Ok(if is_empty { None } else { Some(0) })
+        }
+        Err(err) => Err(Error::ReadCacheVersionError(err, path)),
+    }
+}
+
+/// Migrates the cache at the given root directory to the current
+/// version, then returns the version the cache was migrated from.
+///
+/// New caches are created at the current version. Caches with a
+/// version more recent than the current one are rejected, since they
+/// have been written by a more recent version of the library.
+pub fn migrate(root_dir: impl AsRef<Path>) -> Result<u32> {
+    let root_dir = root_dir.as_ref();
+
+    let prev_version = match read_version(root_dir)? {
+        None => SYNC_CACHE_VERSION,
+        Some(version) if version > SYNC_CACHE_VERSION => {
+            let path = root_dir.to_owned();
+            return Err(Error::CacheVersionTooRecentError(
+                version,
+                SYNC_CACHE_VERSION,
+                path,
+            ));
+        }
+        Some(version) => version,
+    };
+
+    for version in prev_version..SYNC_CACHE_VERSION {
+        debug!(?root_dir, "migrating sync cache from v{version}");
+        MIGRATIONS[version as usize](root_dir)?;
+    }
+
+    fs::create_dir_all(root_dir)
+        .map_err(|err| Error::WriteCacheVersionError(err, root_dir.to_owned()))?;
+
+    let path = root_dir.join(SYNC_CACHE_VERSION_FILE_NAME);
+    fs::write(&path, SYNC_CACHE_VERSION.to_string())
+        .map_err(|err| Error::WriteCacheVersionError(err, path))?;
+
+    Ok(prev_version)
+}
+
+/// Migrates the cache from version 0 to version 1.
+///
+/// Caches created before versioning are plain Maildirs, which remain
+/// compatible: synchronization state files introduced with version 1
+/// are considered empty when missing.
+fn migrate_v0_to_v1(_root_dir: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::{migrate, read_version, SYNC_CACHE_VERSION, SYNC_CACHE_VERSION_FILE_NAME};
+    use crate::sync::Error;
+
+    #[test]
+    fn migrate_sync_cache() {
+        let tmp = tempdir().unwrap();
+        let dir = tmp.path().join("sync-cache");
+
+        // new cache
+        assert_eq!(read_version(&dir).unwrap(), None);
+        assert_eq!(migrate(&dir).unwrap(), SYNC_CACHE_VERSION);
+        assert_eq!(read_version(&dir).unwrap(), Some(SYNC_CACHE_VERSION));
+
+        // legacy cache
+        fs::remove_file(dir.join(SYNC_CACHE_VERSION_FILE_NAME)).unwrap();
+        fs::create_dir_all(dir.join("INBOX")).unwrap();
+        assert_eq!(read_version(&dir).unwrap(), Some(0));
+        assert_eq!(migrate(&dir).unwrap(), 0);
+        assert_eq!(read_version(&dir).unwrap(), Some(SYNC_CACHE_VERSION));
+
+        // cache from the future
+        let version = SYNC_CACHE_VERSION + 1;
+        fs::write(dir.join(SYNC_CACHE_VERSION_FILE_NAME), version.to_string()).unwrap();
+        assert!(matches!(
+            migrate(&dir),
+            Err(Error::CacheVersionTooRecentError(v, SYNC_CACHE_VERSION, _)) if v == version
+        ));
+    }
+}
@@ -0,0 +1,121 @@
+use chrono::{DateTime, FixedOffset};
+
+use crate::{
+    envelope::{Address, Envelope},
+    flag::Flags,
+    message::Message,
+};
+
+/// The backend-independent part of an envelope.
+///
+/// Identifiers, sizes and previews depend on the backend the
+/// envelope comes from, so they are left out of comparisons.
+#[derive(Debug, Eq, PartialEq)]
+struct ComparableEnvelope<'a> {
+    message_id: &'a str,
+    in_reply_to: Option<&'a str>,
+    flags: &'a Flags,
+    from: &'a Address,
+    to: &'a Address,
+    to_addrs: &'a [Address],
+    cc_addrs: &'a [Address],
+    subject: &'a str,
+    date: DateTime<FixedOffset>,
+    has_attachment: bool,
+}
+
+impl<'a> From<&'a Envelope> for ComparableEnvelope<'a> {
+    fn from(envelope: &'a Envelope) -> Self {
+        Self {
+            message_id: &envelope.message_id,
+            in_reply_to: envelope.in_reply_to.as_deref(),
+            flags: &envelope.flags,
+            from: &envelope.from,
+            to: &envelope.to,
+            to_addrs: &envelope.to_addrs,
+            cc_addrs: &envelope.cc_addrs,
+            subject: &envelope.subject,
+            date: envelope.date,
+            has_attachment: envelope.has_attachment,
+        }
+    }
+}
+
+/// Assert that two envelopes are equal, regardless of the backend
+/// they come from.
+///
+/// Identifiers, sizes and previews are not compared.
+///
+/// # Panics
+///
+/// Panics if the envelopes differ.
+#[track_caller]
+pub fn assert_envelope_eq(left: &Envelope, right: &Envelope) {
+    assert_eq!(
+        ComparableEnvelope::from(left),
+        ComparableEnvelope::from(right)
+    );
+}
+
+/// Assert that two lists of envelopes are equal, regardless of the
+/// backend they come from.
+///
+/// Envelopes are compared in order, see [`assert_envelope_eq`].
+///
+/// # Panics
+///
+/// Panics if the lists differ.
+#[track_caller]
+pub fn assert_envelopes_eq(left: &[Envelope], right: &[Envelope]) {
+    let left: Vec<_> = left.iter().map(ComparableEnvelope::from).collect();
+    let right: Vec<_> = right.iter().map(ComparableEnvelope::from).collect();
+    assert_eq!(left, right);
+}
+
+/// Assert that two raw messages are equal.
+///
+/// Messages are compared using [`Message::diff`]: headers are
+/// compared by name using their raw values, and bodies using their
+/// decoded text parts. Line endings are not significant.
+///
+/// # Panics
+///
+/// Panics with a unified diff if the messages differ.
+#[track_caller]
+pub fn assert_messages_eq(left: impl AsRef<[u8]>, right: impl AsRef<[u8]>) {
+    assert_messages_eq_ignoring(left, right, [])
+}
+
+/// Assert that two raw messages are equal, ignoring the given
+/// headers.
+///
+/// Header names are case-insensitive. This is useful to ignore
+/// headers added by backends, like `Received` or `X-Keywords`. See
+/// [`assert_messages_eq`].
+///
+/// # Panics
+///
+/// Panics with a unified diff if the messages differ.
+#[track_caller]
+pub fn assert_messages_eq_ignoring<'a>(
+    left: impl AsRef<[u8]>,
+    right: impl AsRef<[u8]>,
+    ignored_headers: impl IntoIterator<Item = &'a str>,
+) {
+    let ignored_headers: Vec<_> = ignored_headers.into_iter().collect();
+
+    let left = Message::from(left.as_ref());
+    let right = Message::from(right.as_ref());
+
+    let mut diff = left
+        .diff(&right)
+        .unwrap_or_else(|err| panic!("cannot diff messages: {err}"));
+
+    diff.headers.retain(|header| {
+        !ignored_headers
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(header.name()))
+    });
+
+    assert!(diff.is_empty(), "messages differ:\n{diff}");
+}
@@ -0,0 +1,59 @@
+use std::{any::Any, io, path::PathBuf, result};
+
+use thiserror::Error;
+
+use crate::{AnyBoxedError, AnyError, ErrorKind};
+
+/// The global `Result` alias of the module.
+pub type Result<T> = result::Result<T, Error>;
+
+/// The global `Error` enum of the module.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot create corpus maildir folder {1}")]
+    CreateCorpusMaildirError(#[source] maildirs::Error, String),
+    #[error("cannot write corpus message {1} to maildir {2}")]
+    WriteCorpusMessageError(#[source] maildirs::Error, String, PathBuf),
+    #[error("cannot read fixtures directory {1}")]
+    ReadFixturesDirError(#[source] io::Error, PathBuf),
+    #[error("cannot read fixture {1}")]
+    ReadFixtureError(#[source] io::Error, PathBuf),
+    #[error("cannot create temporary maildirs at {1}")]
+    CreateTempMaildirsError(#[source] io::Error, PathBuf),
+    #[error("cannot create temporary maildir folder {1}")]
+    CreateTempMaildirError(#[source] maildirs::Error, String),
+    #[error("cannot write message to temporary maildir {1}")]
+    WriteTempMaildirMessageError(#[source] maildirs::Error, PathBuf),
+}
+
+impl Error {
+    /// Returns the kind of the error.
+    ///
+    /// See [`ErrorKind`] for the available kinds.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::ReadFixturesDirError(..) | Self::ReadFixtureError(..) => ErrorKind::NotFound,
+            Self::CreateCorpusMaildirError(..)
+            | Self::WriteCorpusMessageError(..)
+            | Self::CreateTempMaildirsError(..)
+            | Self::CreateTempMaildirError(..)
+            | Self::WriteTempMaildirMessageError(..) => ErrorKind::Other,
+        }
+    }
+}
+
+impl AnyError for Error {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn kind(&self) -> ErrorKind {
+        Error::kind(self)
+    }
+}
+
+impl From<Error> for AnyBoxedError {
+    fn from(err: Error) -> Self {
+        Box::new(err)
+    }
+}
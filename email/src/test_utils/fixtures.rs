@@ -0,0 +1,115 @@
+use std::{env, fs, path::Path};
+
+use super::{Error, Result};
+
+/// The environment variable used to (re)generate golden files
+/// instead of comparing against them, see [`assert_golden`].
+pub const UPDATE_GOLDEN_ENV: &str = "EMAIL_UPDATE_GOLDEN";
+
+/// The MIME fixtures shipped with the crate, as `(name, raw)`
+/// tuples.
+///
+/// Fixtures are hand-written messages covering common shapes: plain
+/// text, replies, encoded headers, HTML alternatives and
+/// attachments. Names are file names without the `.eml` extension.
+pub const FIXTURES: [(&str, &[u8]); 5] = [
+    ("plain", include_bytes!("fixtures/plain.eml")),
+    ("reply", include_bytes!("fixtures/reply.eml")),
+    (
+        "encoded-headers",
+        include_bytes!("fixtures/encoded-headers.eml"),
+    ),
+    (
+        "html-alternative",
+        include_bytes!("fixtures/html-alternative.eml"),
+    ),
+    ("attachment", include_bytes!("fixtures/attachment.eml")),
+];
+
+/// Get the raw MIME fixture matching the given name.
+pub fn fixture(name: impl AsRef<str>) -> Option<&'static [u8]> {
+    let name = name.as_ref();
+
+    FIXTURES
+        .iter()
+        .find(|(fixture, _)| *fixture == name)
+        .map(|(_, raw)| *raw)
+}
+
+/// Load the MIME fixtures from the given directory, as `(name, raw)`
+/// tuples sorted by name.
+///
+/// Only `.eml` files are loaded, names are file names without the
+/// extension.
+pub fn load_fixtures(dir: impl AsRef<Path>) -> Result<Vec<(String, Vec<u8>)>> {
+    let dir = dir.as_ref();
+
+    let entries =
+        fs::read_dir(dir).map_err(|err| Error::ReadFixturesDirError(err, dir.to_owned()))?;
+
+    let mut fixtures = Vec::new();
+
+    for entry in entries {
+        let path = entry
+            .map_err(|err| Error::ReadFixturesDirError(err, dir.to_owned()))?
+            .path();
+
+        if path.extension().and_then(|ext| ext.to_str()) != Some("eml") {
+            continue;
+        }
+
+        let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        let raw = fs::read(&path).map_err(|err| Error::ReadFixtureError(err, path.clone()))?;
+        fixtures.push((name.to_owned(), raw));
+    }
+
+    fixtures.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    Ok(fixtures)
+}
+
+/// Assert that the given content matches the golden file at the
+/// given path.
+///
+/// When the [`UPDATE_GOLDEN_ENV`] environment variable is set, the
+/// golden file is (re)written with the given content instead, so
+/// that golden files can be regenerated with
+/// `EMAIL_UPDATE_GOLDEN=1 cargo test`.
+///
+/// # Panics
+///
+/// Panics if the golden file cannot be read or written, or if its
+/// content differs from the given one.
+pub fn assert_golden(path: impl AsRef<Path>, actual: impl AsRef<str>) {
+    let path = path.as_ref();
+    let actual = actual.as_ref();
+
+    if env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap_or_else(|err| {
+                panic!("cannot create golden dir {}: {err}", parent.display())
+            });
+        }
+
+        fs::write(path, actual)
+            .unwrap_or_else(|err| panic!("cannot write golden file {}: {err}", path.display()));
+
+        return;
+    }
+
+    let expected = fs::read_to_string(path).unwrap_or_else(|err| {
+        panic!(
+            "cannot read golden file {} (set {UPDATE_GOLDEN_ENV} to create it): {err}",
+            path.display()
+        )
+    });
+
+    assert!(
+        expected.replace("\r\n", "\n") == actual.replace("\r\n", "\n"),
+        "golden file {} differs (set {UPDATE_GOLDEN_ENV} to update it)\n--- expected\n{expected}\n+++ actual\n{actual}",
+        path.display(),
+    );
+}
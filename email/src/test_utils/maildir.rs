@@ -0,0 +1,108 @@
+use std::{collections::HashSet, env, fs, path::Path};
+
+use maildirs::Maildirs;
+use uuid::Uuid;
+
+use super::{Corpus, Error, Result, FIXTURES};
+use crate::{flag::Flags, maildir::config::MaildirConfig};
+
+/// A throwaway Maildir tree.
+///
+/// The tree lives in a unique directory inside the system temporary
+/// directory, and is removed when dropped. Each folder is a Maildir
+/// inside the root directory, as expected by the Maildir backend
+/// when Maildir++ is disabled.
+#[derive(Debug)]
+pub struct TempMaildirs {
+    mdirs: Maildirs,
+}
+
+impl TempMaildirs {
+    /// Create a new, empty throwaway Maildir tree.
+    pub fn new() -> Result<Self> {
+        let root = env::temp_dir().join(format!("email-lib-{}", Uuid::new_v4()));
+        fs::create_dir_all(&root)
+            .map_err(|err| Error::CreateTempMaildirsError(err, root.clone()))?;
+
+        Ok(Self {
+            mdirs: Maildirs::new(root),
+        })
+    }
+
+    /// Create a new throwaway Maildir tree containing the MIME
+    /// fixtures shipped with the crate, in the given folder.
+    pub fn from_fixtures(folder: impl AsRef<str>) -> Result<Self> {
+        let mdirs = Self::new()?;
+
+        for (_, raw) in FIXTURES {
+            mdirs.add_message(folder.as_ref(), raw, &Flags::default())?;
+        }
+
+        Ok(mdirs)
+    }
+
+    /// Create a new throwaway Maildir tree containing the messages
+    /// of the given corpus.
+    pub fn from_corpus(corpus: &Corpus) -> Result<Self> {
+        let mdirs = Self::new()?;
+        corpus.write_maildirs(mdirs.root())?;
+        Ok(mdirs)
+    }
+
+    /// Return the root directory of the tree.
+    pub fn root(&self) -> &Path {
+        self.mdirs.path()
+    }
+
+    /// Return the Maildirs of the tree.
+    pub fn maildirs(&self) -> &Maildirs {
+        &self.mdirs
+    }
+
+    /// Build a Maildir backend configuration pointing to the tree.
+    pub fn config(&self) -> MaildirConfig {
+        MaildirConfig {
+            root_dir: self.root().to_owned(),
+            maildirpp: false,
+            layout: None,
+            uid_db: false,
+            envelopes: Default::default(),
+        }
+    }
+
+    /// Add the given raw message with the given flags to the given
+    /// folder, creating the folder if needed.
+    ///
+    /// Returns the Maildir identifier of the added message.
+    pub fn add_message(
+        &self,
+        folder: impl AsRef<str>,
+        raw: impl AsRef<[u8]>,
+        flags: &Flags,
+    ) -> Result<String> {
+        let folder = folder.as_ref();
+
+        let mdir = self
+            .mdirs
+            .create(folder)
+            .map_err(|err| Error::CreateTempMaildirError(err, folder.to_owned()))?;
+
+        let flags: HashSet<maildirs::Flag> = flags.into();
+
+        let entry = mdir
+            .write_cur(raw, flags)
+            .map_err(|err| Error::WriteTempMaildirMessageError(err, mdir.path().to_owned()))?;
+
+        let id = entry
+            .id()
+            .map_err(|err| Error::WriteTempMaildirMessageError(err, mdir.path().to_owned()))?;
+
+        Ok(id.to_owned())
+    }
+}
+
+impl Drop for TempMaildirs {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(self.root());
+    }
+}
@@ -0,0 +1,753 @@
+//! # Test utils
+//!
+//! Module dedicated to testing helpers, for integration tests and
+//! downstream crates.
+//!
+//! The main structure is the [`Corpus`], a deterministic generator of
+//! realistic synthetic messages: varied charsets and transfer
+//! encodings, HTML alternatives, attachments, threads and malformed
+//! edge cases. The same seed always produces the same messages, so
+//! the corpus can be shared between tests and benchmarks. The corpus
+//! can also be written to pre-populated Maildirs, see
+//! [`Corpus::write_maildirs`].
+//!
+//! Downstream crates can also write integration tests against the
+//! hand-written MIME [`FIXTURES`] shipped with the crate, load their
+//! own fixtures with [`load_fixtures`] and compare outputs against
+//! golden files with [`assert_golden`]. Throwaway Maildir trees are
+//! built with [`TempMaildirs`], and envelopes and messages coming
+//! from different backends are compared with [`assert_envelopes_eq`]
+//! and [`assert_messages_eq`].
+
+mod assert;
+mod error;
+mod fixtures;
+mod maildir;
+
+use std::{collections::HashSet, path::PathBuf};
+
+use chrono::DateTime;
+use mail_builder::encoders::{
+    base64::{base64_encode, base64_encode_mime},
+    quoted_printable::quoted_printable_encode,
+};
+use maildirs::Maildirs;
+
+#[doc(inline)]
+pub use self::{
+    assert::{
+        assert_envelope_eq, assert_envelopes_eq, assert_messages_eq, assert_messages_eq_ignoring,
+    },
+    error::{Error, Result},
+    fixtures::{assert_golden, fixture, load_fixtures, FIXTURES, UPDATE_GOLDEN_ENV},
+    maildir::TempMaildirs,
+};
+use crate::flag::{Flag, Flags};
+
+/// The timestamp of the first message of the corpus
+/// (2024-01-01T00:00:00Z).
+const BASE_TIMESTAMP: i64 = 1704067200;
+
+/// The default number of messages of the corpus.
+pub const DEFAULT_CORPUS_SIZE: usize = 100;
+
+/// The default folders of the corpus.
+pub const DEFAULT_CORPUS_FOLDERS: [&str; 3] = ["INBOX", "Sent", "Archives"];
+
+const NAMES: [(&str, &str); 8] = [
+    ("Alice Martin", "alice"),
+    ("Bob Smith", "bob"),
+    ("Zoë Ångström", "zoe"),
+    ("José Núñez", "jose"),
+    ("Jürgen Groß", "juergen"),
+    ("Émilie Dubois", "emilie"),
+    ("李雷", "li"),
+    ("Иван Петров", "ivan"),
+];
+
+const SUBJECTS: [&str; 8] = [
+    "Meeting notes",
+    "Quarterly report",
+    "Café à midi ?",
+    "Sprint planning",
+    "Größenänderung der Bilder",
+    "Weekend plans",
+    "Invoice for March",
+    "Привет из Москвы",
+];
+
+const ASCII_SENTENCES: [&str; 6] = [
+    "Hello, I hope you are doing well.",
+    "Please find the details below.",
+    "Let me know if anything is unclear.",
+    "The meeting is moved to Thursday at 10am.",
+    "I will send the updated figures tomorrow.",
+    "Thanks again for your help.",
+];
+
+const LATIN1_SENTENCES: [&str; 4] = [
+    "Déjà vu: the café is closed on Sundays.",
+    "Die Straße ist wegen Bauarbeiten gesperrt.",
+    "El niño comió piñata en la mañana.",
+    "Smörgåsbord på fredag, alla är välkomna.",
+];
+
+const UTF8_SENTENCES: [&str; 4] = [
+    "Встреча перенесена на завтра.",
+    "会议改到明天上午。",
+    "Ceci coûte 42 €, c’est raisonnable.",
+    "Ship it! 🚀",
+];
+
+const ATTACHMENTS: [(&str, &str); 4] = [
+    ("report.csv", "text/csv"),
+    ("photo.png", "image/png"),
+    ("notes.pdf", "application/pdf"),
+    ("résumé.txt", "text/plain"),
+];
+
+/// The malformation of a message of the corpus.
+///
+/// Malformed messages are meant to test how parsers and backends
+/// deal with real-world, non-compliant messages.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Malformation {
+    /// The `Date` header is missing.
+    MissingDate,
+
+    /// The `Date` header cannot be parsed.
+    InvalidDate,
+
+    /// The `From` header is missing.
+    MissingFrom,
+
+    /// Lines end with `\n` instead of `\r\n`.
+    BareLineFeeds,
+
+    /// The header section contains a line which is not a header.
+    InvalidHeader,
+
+    /// A header value contains non UTF-8 bytes.
+    InvalidUtf8Header,
+
+    /// The `Subject` header is a very long, unfolded line.
+    LongHeader,
+
+    /// The closing boundary of the multipart body is missing.
+    UnterminatedMultipart,
+}
+
+impl Malformation {
+    /// All the malformations.
+    pub const ALL: [Self; 8] = [
+        Self::MissingDate,
+        Self::InvalidDate,
+        Self::MissingFrom,
+        Self::BareLineFeeds,
+        Self::InvalidHeader,
+        Self::InvalidUtf8Header,
+        Self::LongHeader,
+        Self::UnterminatedMultipart,
+    ];
+}
+
+/// A synthetic message of the corpus.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SampleMessage {
+    /// The index of the message in the corpus.
+    pub index: usize,
+
+    /// The folder the message belongs to.
+    pub folder: String,
+
+    /// The Message-ID of the message, without angle brackets.
+    pub message_id: String,
+
+    /// The Message-ID of the parent message, if the message is a
+    /// reply.
+    pub in_reply_to: Option<String>,
+
+    /// The Message-IDs of the ancestors of the message, from the
+    /// root of the thread to the parent.
+    pub references: Vec<String>,
+
+    /// The decoded subject of the message.
+    pub subject: String,
+
+    /// The charset of the text body.
+    pub charset: &'static str,
+
+    /// Whether the message has an HTML alternative.
+    pub has_html: bool,
+
+    /// Whether the message has an attachment.
+    pub has_attachment: bool,
+
+    /// The malformation of the message, if any.
+    pub malformation: Option<Malformation>,
+
+    /// The flags of the message.
+    pub flags: Flags,
+
+    /// The raw message.
+    pub raw: Vec<u8>,
+}
+
+/// The deterministic sample message corpus generator.
+///
+/// The generator follows the builder pattern. Messages are generated
+/// sequentially, so the first messages of a corpus do not depend on
+/// its size.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Corpus {
+    seed: u64,
+    size: usize,
+    folders: Vec<String>,
+    malformed: bool,
+}
+
+impl Default for Corpus {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            size: DEFAULT_CORPUS_SIZE,
+            folders: DEFAULT_CORPUS_FOLDERS.map(String::from).to_vec(),
+            malformed: true,
+        }
+    }
+}
+
+impl Corpus {
+    /// Create a new corpus generator from the given seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            ..Default::default()
+        }
+    }
+
+    /// Set the number of messages of the corpus.
+    pub fn with_size(mut self, size: usize) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Set the folders messages are spread across.
+    ///
+    /// An empty list of folders falls back to `INBOX`.
+    pub fn with_folders(mut self, folders: impl IntoIterator<Item = impl ToString>) -> Self {
+        self.folders = folders.into_iter().map(|f| f.to_string()).collect();
+        self
+    }
+
+    /// Include or exclude malformed messages.
+    ///
+    /// Malformed messages are included by default.
+    pub fn with_malformed(mut self, malformed: bool) -> Self {
+        self.malformed = malformed;
+        self
+    }
+
+    /// Generate the messages of the corpus.
+    pub fn messages(&self) -> Vec<SampleMessage> {
+        let mut rng = Rng::new(self.seed);
+        let mut timestamp = BASE_TIMESTAMP;
+        let mut messages = Vec::with_capacity(self.size);
+
+        for index in 0..self.size {
+            timestamp += 60 + rng.below(7200) as i64;
+            let msg = self.generate(&mut rng, index, timestamp, &messages);
+            messages.push(msg);
+        }
+
+        messages
+    }
+
+    /// Write the messages of the corpus to Maildirs at the given
+    /// root directory.
+    ///
+    /// Each folder is a Maildir inside the root directory, as
+    /// expected by the Maildir backend when Maildir++ is disabled.
+    /// Message flags are preserved.
+    pub fn write_maildirs(&self, root: impl Into<PathBuf>) -> Result<Maildirs> {
+        let mdirs = Maildirs::new(root);
+
+        for msg in self.messages() {
+            let mdir = mdirs
+                .create(&msg.folder)
+                .map_err(|err| Error::CreateCorpusMaildirError(err, msg.folder.clone()))?;
+
+            let flags: HashSet<maildirs::Flag> = (&msg.flags).into();
+
+            mdir.write_cur(&msg.raw, flags).map_err(|err| {
+                Error::WriteCorpusMessageError(err, msg.message_id, mdir.path().to_owned())
+            })?;
+        }
+
+        Ok(mdirs)
+    }
+
+    fn generate(
+        &self,
+        rng: &mut Rng,
+        index: usize,
+        timestamp: i64,
+        messages: &[SampleMessage],
+    ) -> SampleMessage {
+        let folder = match self.folders.as_slice() {
+            [] => String::from("INBOX"),
+            folders => rng.pick(folders).clone(),
+        };
+
+        let message_id = format!("{index}.{:x}@corpus.localhost", self.seed);
+        let (from_name, from_user) = *rng.pick(&NAMES);
+        let (to_name, to_user) = *rng.pick(&NAMES);
+
+        let parent = if index > 0 && rng.chance(3) {
+            Some(&messages[rng.below(index)])
+        } else {
+            None
+        };
+
+        let (subject, in_reply_to, references) = match parent {
+            Some(parent) => {
+                let subject = if parent.subject.starts_with("Re: ") {
+                    parent.subject.clone()
+                } else {
+                    format!("Re: {}", parent.subject)
+                };
+                let mut references = parent.references.clone();
+                references.push(parent.message_id.clone());
+                (subject, Some(parent.message_id.clone()), references)
+            }
+            None => (rng.pick(&SUBJECTS).to_string(), None, Vec::new()),
+        };
+
+        let malformation = if self.malformed && rng.chance(10) {
+            Some(*rng.pick(&Malformation::ALL))
+        } else {
+            None
+        };
+
+        let charset = *rng.pick(&["utf-8", "iso-8859-1", "us-ascii"]);
+        let text = sentences(rng, charset);
+        let has_html = rng.chance(4);
+        let has_attachment =
+            rng.chance(5) || malformation == Some(Malformation::UnterminatedMultipart);
+
+        let mut flags = Flags::default();
+        if rng.below(3) > 0 {
+            flags.insert(Flag::Seen);
+        }
+        if rng.chance(10) {
+            flags.insert(Flag::Flagged);
+        }
+        if parent.is_none() && rng.chance(8) {
+            flags.insert(Flag::Answered);
+        }
+
+        // message headers
+
+        let mut raw = Vec::new();
+
+        push_header(&mut raw, format!("Message-ID: <{message_id}>"));
+
+        match malformation {
+            Some(Malformation::MissingDate) => (),
+            Some(Malformation::InvalidDate) => push_header(&mut raw, "Date: not a date"),
+            _ => {
+                let date = DateTime::from_timestamp(timestamp, 0).unwrap_or_default();
+                push_header(&mut raw, format!("Date: {}", date.to_rfc2822()));
+            }
+        }
+
+        if malformation != Some(Malformation::MissingFrom) {
+            let from = encode_word(from_name);
+            push_header(&mut raw, format!("From: {from} <{from_user}@example.org>"));
+        }
+
+        let to = encode_word(to_name);
+        push_header(&mut raw, format!("To: {to} <{to_user}@example.org>"));
+
+        if malformation == Some(Malformation::LongHeader) {
+            let subject = subject.repeat(2000 / subject.len().max(1) + 1);
+            push_header(&mut raw, format!("Subject: {}", encode_word(&subject)));
+        } else {
+            push_header(&mut raw, format!("Subject: {}", encode_word(&subject)));
+        }
+
+        if let Some(in_reply_to) = &in_reply_to {
+            push_header(&mut raw, format!("In-Reply-To: <{in_reply_to}>"));
+            let references: Vec<_> = references.iter().map(|id| format!("<{id}>")).collect();
+            push_header(&mut raw, format!("References: {}", references.join(" ")));
+        }
+
+        if malformation == Some(Malformation::InvalidHeader) {
+            push_header(&mut raw, "This line is not a header");
+        }
+
+        if malformation == Some(Malformation::InvalidUtf8Header) {
+            raw.extend_from_slice(b"X-Invalid: caf\xe9 \xff\xfe\r\n");
+        }
+
+        push_header(&mut raw, "MIME-Version: 1.0");
+
+        // message body
+
+        let text_part = text_part(rng, "plain", charset, &text.join("\r\n\r\n"));
+        let body = if has_html {
+            let html = format!("<html><body><p>{}</p></body></html>", text.join("</p><p>"));
+            let html_part = text_part(rng, "html", charset, &html);
+            multipart(
+                "alternative",
+                &format!("=_{index}_alt"),
+                vec![text_part, html_part],
+                true,
+            )
+        } else {
+            text_part
+        };
+
+        let body = if has_attachment {
+            let terminated = malformation != Some(Malformation::UnterminatedMultipart);
+            let attachment = attachment_part(rng);
+            multipart(
+                "mixed",
+                &format!("=_{index}_mixed"),
+                vec![body, attachment],
+                terminated,
+            )
+        } else {
+            body
+        };
+
+        raw.extend(body);
+
+        if malformation == Some(Malformation::BareLineFeeds) {
+            raw = replace_crlf(&raw);
+        }
+
+        SampleMessage {
+            index,
+            folder,
+            message_id,
+            in_reply_to,
+            references,
+            subject,
+            charset,
+            has_html,
+            has_attachment,
+            malformation,
+            flags,
+            raw,
+        }
+    }
+}
+
+/// Tiny deterministic pseudo-random number generator (SplitMix64).
+///
+/// It avoids depending on a random crate whose algorithms could
+/// change between versions, which would change the corpus.
+#[derive(Clone, Debug)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Return a number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+
+    /// Return `true` once every `n` times, on average.
+    fn chance(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+/// Generate a few paragraphs compatible with the given charset.
+fn sentences(rng: &mut Rng, charset: &str) -> Vec<String> {
+    let count = 1 + rng.below(5);
+
+    (0..count)
+        .map(|_| {
+            let sentence = match (charset, rng.below(3)) {
+                ("us-ascii", _) | (_, 0) => *rng.pick(&ASCII_SENTENCES),
+                ("utf-8", 1) => *rng.pick(&UTF8_SENTENCES),
+                _ => *rng.pick(&LATIN1_SENTENCES),
+            };
+            sentence.to_owned()
+        })
+        .collect()
+}
+
+fn push_header(raw: &mut Vec<u8>, header: impl AsRef<str>) {
+    raw.extend_from_slice(header.as_ref().as_bytes());
+    raw.extend_from_slice(b"\r\n");
+}
+
+/// Encode the given header value as a RFC 2047 encoded word, if it
+/// contains non-ASCII characters.
+fn encode_word(value: &str) -> String {
+    if value.is_ascii() {
+        return value.to_owned();
+    }
+
+    let encoded = base64_encode(value.as_bytes()).unwrap_or_default();
+    format!("=?utf-8?B?{}?=", String::from_utf8_lossy(&encoded))
+}
+
+/// Encode the given text to the given charset.
+///
+/// The text is expected to be representable in the charset.
+fn encode_text(charset: &str, text: &str) -> Vec<u8> {
+    match charset {
+        "iso-8859-1" => text
+            .chars()
+            .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+            .collect(),
+        _ => text.as_bytes().to_vec(),
+    }
+}
+
+fn text_part(rng: &mut Rng, subtype: &str, charset: &str, text: &str) -> Vec<u8> {
+    let bytes = encode_text(charset, text);
+    let mut part = Vec::new();
+
+    push_header(
+        &mut part,
+        format!("Content-Type: text/{subtype}; charset=\"{charset}\""),
+    );
+
+    if charset == "us-ascii" {
+        push_header(&mut part, "Content-Transfer-Encoding: 7bit");
+        part.extend_from_slice(b"\r\n");
+        part.extend(bytes);
+    } else if rng.chance(2) {
+        push_header(&mut part, "Content-Transfer-Encoding: quoted-printable");
+        part.extend_from_slice(b"\r\n");
+        let _ = quoted_printable_encode(&bytes, &mut part, false, true);
+    } else {
+        push_header(&mut part, "Content-Transfer-Encoding: 8bit");
+        part.extend_from_slice(b"\r\n");
+        part.extend(bytes);
+    }
+
+    part.extend_from_slice(b"\r\n");
+    part
+}
+
+fn attachment_part(rng: &mut Rng) -> Vec<u8> {
+    let (name, ctype) = *rng.pick(&ATTACHMENTS);
+
+    let contents: Vec<u8> = match ctype {
+        "text/csv" => (0..1 + rng.below(20))
+            .map(|n| format!("{n},{},{}\r\n", rng.below(1000), rng.below(1000)))
+            .collect::<String>()
+            .into_bytes(),
+        "text/plain" => rng.pick(&LATIN1_SENTENCES).as_bytes().to_vec(),
+        _ => (0..64 + rng.below(960))
+            .map(|_| rng.next_u64() as u8)
+            .collect(),
+    };
+
+    let mut part = Vec::new();
+
+    if name.is_ascii() {
+        push_header(&mut part, format!("Content-Type: {ctype}; name=\"{name}\""));
+        push_header(
+            &mut part,
+            format!("Content-Disposition: attachment; filename=\"{name}\""),
+        );
+    } else {
+        let name = urlencoding::encode(name);
+        push_header(&mut part, format!("Content-Type: {ctype}"));
+        push_header(
+            &mut part,
+            format!("Content-Disposition: attachment; filename*=utf-8''{name}"),
+        );
+    }
+
+    push_header(&mut part, "Content-Transfer-Encoding: base64");
+    part.extend_from_slice(b"\r\n");
+    let _ = base64_encode_mime(&contents, &mut part, false);
+    part.extend_from_slice(b"\r\n");
+    part
+}
+
+fn multipart(subtype: &str, boundary: &str, parts: Vec<Vec<u8>>, terminated: bool) -> Vec<u8> {
+    let mut multipart = Vec::new();
+
+    push_header(
+        &mut multipart,
+        format!("Content-Type: multipart/{subtype}; boundary=\"{boundary}\""),
+    );
+    multipart.extend_from_slice(b"\r\n");
+
+    for part in parts {
+        push_header(&mut multipart, format!("--{boundary}"));
+        multipart.extend(part);
+    }
+
+    if terminated {
+        push_header(&mut multipart, format!("--{boundary}--"));
+    }
+
+    multipart
+}
+
+fn replace_crlf(raw: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(raw.len());
+    let mut bytes = raw.iter().peekable();
+
+    while let Some(&b) = bytes.next() {
+        if b == b'\r' && bytes.peek() == Some(&&b'\n') {
+            continue;
+        }
+        out.push(b);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use mail_parser::MessageParser;
+
+    use super::{
+        assert_messages_eq, assert_messages_eq_ignoring, fixture, Corpus, Malformation,
+        TempMaildirs, FIXTURES,
+    };
+    use crate::flag::{Flag, Flags};
+
+    #[test]
+    fn deterministic() {
+        let corpus = Corpus::new(42).with_size(50);
+        assert_eq!(corpus.messages(), corpus.messages());
+
+        let prefix = Corpus::new(42).with_size(10).messages();
+        assert_eq!(prefix, corpus.messages()[..10]);
+
+        assert_ne!(corpus.messages(), Corpus::new(43).with_size(50).messages());
+    }
+
+    #[test]
+    fn variety() {
+        let msgs = Corpus::new(0).with_size(200).messages();
+
+        assert!(msgs.iter().any(|msg| msg.has_html));
+        assert!(msgs.iter().any(|msg| msg.has_attachment));
+        assert!(msgs.iter().any(|msg| msg.in_reply_to.is_some()));
+        assert!(msgs.iter().any(|msg| msg.malformation.is_some()));
+        assert!(msgs.iter().any(|msg| msg.charset == "iso-8859-1"));
+
+        for msg in msgs.iter().filter(|msg| msg.malformation.is_none()) {
+            let parsed = MessageParser::new().parse(&msg.raw).unwrap();
+            assert_eq!(parsed.message_id(), Some(msg.message_id.as_str()));
+            assert_eq!(parsed.subject(), Some(msg.subject.as_str()));
+            assert!(parsed.date().is_some());
+
+            if let Some(in_reply_to) = &msg.in_reply_to {
+                assert_eq!(parsed.in_reply_to().as_text(), Some(in_reply_to.as_str()));
+                assert!(msg.references.contains(in_reply_to));
+            }
+        }
+
+        let msgs = Corpus::new(0).with_malformed(false).messages();
+        assert!(msgs.iter().all(|msg| msg.malformation.is_none()));
+    }
+
+    #[test]
+    fn malformations() {
+        let msgs = Corpus::new(0).with_size(1000).messages();
+
+        for malformation in Malformation::ALL {
+            assert!(msgs
+                .iter()
+                .any(|msg| msg.malformation == Some(malformation)));
+        }
+
+        let msg = msgs
+            .iter()
+            .find(|msg| msg.malformation == Some(Malformation::BareLineFeeds))
+            .unwrap();
+        assert!(!msg.raw.windows(2).any(|w| w == b"\r\n"));
+    }
+
+    #[test]
+    fn maildirs() {
+        let root = tempfile::tempdir().unwrap();
+        let corpus = Corpus::new(7).with_size(30).with_folders(["INBOX", "Sent"]);
+        let mdirs = corpus.write_maildirs(root.path()).unwrap();
+
+        let mut count = 0;
+        for folder in ["INBOX", "Sent"] {
+            let mdir = mdirs.get(folder).unwrap();
+            count += mdir.read().unwrap().count();
+        }
+
+        assert_eq!(count, 30);
+    }
+
+    #[test]
+    fn fixtures() {
+        for (name, raw) in FIXTURES {
+            let parsed = MessageParser::new().parse(raw).unwrap();
+            assert!(parsed.message_id().is_some(), "fixture {name}");
+            assert_messages_eq(raw, raw);
+        }
+
+        assert!(fixture("plain").is_some());
+        assert!(fixture("unknown").is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "messages differ")]
+    fn messages_differ() {
+        assert_messages_eq(fixture("plain").unwrap(), fixture("reply").unwrap());
+    }
+
+    #[test]
+    fn messages_eq_ignoring() {
+        let raw = fixture("plain").unwrap();
+        let mut received = b"Received: from localhost\r\n".to_vec();
+        received.extend_from_slice(raw);
+
+        assert_messages_eq_ignoring(raw, &received, ["received"]);
+        assert_messages_eq(raw, String::from_utf8_lossy(raw).replace("\r\n", "\n"));
+    }
+
+    #[test]
+    fn temp_maildirs() {
+        let mdirs = TempMaildirs::from_fixtures("INBOX").unwrap();
+        let root = mdirs.root().to_owned();
+
+        let flags = Flags::from_iter([Flag::Seen]);
+        let id = mdirs
+            .add_message("Archives", fixture("plain").unwrap(), &flags)
+            .unwrap();
+
+        let inbox = mdirs.maildirs().get("INBOX").unwrap();
+        assert_eq!(inbox.read().unwrap().count(), FIXTURES.len());
+
+        let archives = mdirs.maildirs().get("Archives").unwrap();
+        assert!(archives.find(&id).unwrap().is_some());
+
+        drop(mdirs);
+        assert!(!root.exists());
+    }
+}
@@ -113,6 +113,7 @@ pub struct WatchNotifyConfig {
     ///  - "{recipient}" either the recipient name or the address
     ///  - "{recipient.name}" the recipient name or "unknown"
     ///  - "{recipient.address}" the recipient address
+    ///  - "{priority}" the priority: "high", "normal" or "low"
     pub summary: String,
 
     /// The body of the notification.
@@ -126,5 +127,6 @@ pub struct WatchNotifyConfig {
     ///  - "{recipient}" either the recipient name or the address
     ///  - "{recipient.name}" the recipient name or "unknown"
     ///  - "{recipient.address}" the recipient address
+    ///  - "{priority}" the priority: "high", "normal" or "low"
     pub body: String,
 }
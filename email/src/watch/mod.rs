@@ -1 +1,3 @@
 pub mod config;
+#[cfg(feature = "watch")]
+pub mod service;
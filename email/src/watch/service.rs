@@ -0,0 +1,410 @@
+//! # Watch service
+//!
+//! Module dedicated to the watch service. The [`WatchService`] wraps
+//! a backend implementing [`WatchEnvelopes`] into a long-running
+//! service: each folder is watched in its own task, connection
+//! errors are retried with an exponential backoff, and envelopes
+//! changes trigger both the configured watch hooks and
+//! [`WatchEvent`]s that can be consumed programmatically.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    select,
+    sync::{broadcast, oneshot, watch},
+    task::JoinHandle,
+    time::{sleep, timeout},
+};
+use tracing::{debug, info, warn};
+
+use crate::envelope::{watch::WatchEnvelopes, Envelope};
+
+/// The default delay before reconnecting a folder watcher.
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The default maximum delay before reconnecting a folder watcher.
+pub const DEFAULT_MAX_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// The delay given to folder watchers to shut down gracefully.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The capacity of the events channel. Subscribers lagging behind
+/// lose the oldest events.
+const EVENTS_CAPACITY: usize = 256;
+
+tokio::task_local! {
+    /// The events emitter of the current folder watcher, set by the
+    /// watch service.
+    static EMITTER: WatchEventEmitter;
+}
+
+/// The watch event.
+///
+/// Events emitted by the [`WatchService`], see
+/// [`WatchService::subscribe`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WatchEvent {
+    /// The watcher of the given folder started (or restarted after
+    /// a reconnection).
+    Started(String),
+
+    /// A new envelope has been received in the given folder.
+    Received(String, Envelope),
+
+    /// The watcher of the given folder failed with the given error,
+    /// and is going to reconnect.
+    Disconnected(String, String),
+
+    /// The watcher of the given folder stopped.
+    Stopped(String),
+}
+
+/// The watch status of a folder.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum WatchStatus {
+    /// The folder is being watched.
+    Running,
+
+    /// The folder watcher failed, and is waiting before reconnecting
+    /// for the given attempt.
+    Reconnecting(usize),
+
+    /// The folder is not watched.
+    #[default]
+    Stopped,
+}
+
+/// The events emitter of a folder watcher.
+#[derive(Clone, Debug)]
+struct WatchEventEmitter {
+    folder: String,
+    events: broadcast::Sender<WatchEvent>,
+}
+
+/// Emit a received envelope event, when running inside a folder
+/// watcher of a [`WatchService`].
+pub(crate) fn emit_received(envelope: &Envelope) {
+    let _ = EMITTER.try_with(|emitter| {
+        let event = WatchEvent::Received(emitter.folder.clone(), envelope.clone());
+        // an error only means that nobody listens
+        let _ = emitter.events.send(event);
+    });
+}
+
+/// The watch service.
+///
+/// Watches multiple folders of the same backend. The service needs
+/// to be started with [`WatchService::start`], and should be stopped
+/// with [`WatchService::stop`].
+pub struct WatchService<B: WatchEnvelopes + ?Sized + 'static> {
+    backend: Arc<B>,
+    folders: Vec<String>,
+    retry_delay: Duration,
+    max_retry_delay: Duration,
+    events: broadcast::Sender<WatchEvent>,
+    statuses: Arc<Mutex<HashMap<String, WatchStatus>>>,
+    stop: Option<watch::Sender<bool>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl<B: WatchEnvelopes + ?Sized + 'static> WatchService<B> {
+    /// Create a new watch service for the given backend and folders.
+    pub fn new(backend: Arc<B>, folders: impl IntoIterator<Item = impl ToString>) -> Self {
+        let (events, _) = broadcast::channel(EVENTS_CAPACITY);
+
+        Self {
+            backend,
+            folders: folders.into_iter().map(|f| f.to_string()).collect(),
+            retry_delay: DEFAULT_RETRY_DELAY,
+            max_retry_delay: DEFAULT_MAX_RETRY_DELAY,
+            events,
+            statuses: Default::default(),
+            stop: None,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Set the initial delay before reconnecting a folder watcher.
+    ///
+    /// The delay doubles after each consecutive failure.
+    pub fn with_retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Set the maximum delay before reconnecting a folder watcher.
+    pub fn with_max_retry_delay(mut self, delay: Duration) -> Self {
+        self.max_retry_delay = delay;
+        self
+    }
+
+    /// Subscribe to the events of the service.
+    pub fn subscribe(&self) -> broadcast::Receiver<WatchEvent> {
+        self.events.subscribe()
+    }
+
+    /// Return `true` if the service is started.
+    pub fn is_running(&self) -> bool {
+        self.stop.is_some()
+    }
+
+    /// Return the watch status of each folder.
+    pub fn status(&self) -> HashMap<String, WatchStatus> {
+        let statuses = self.statuses.lock().unwrap();
+
+        self.folders
+            .iter()
+            .map(|folder| {
+                let status = statuses.get(folder).cloned().unwrap_or_default();
+                (folder.clone(), status)
+            })
+            .collect()
+    }
+
+    /// Start watching all folders, each one in a dedicated task.
+    ///
+    /// Has no effect if the service is already started.
+    pub fn start(&mut self) {
+        if self.is_running() {
+            return;
+        }
+
+        info!(folders = ?self.folders, "starting watch service");
+
+        let (stop, stop_rx) = watch::channel(false);
+
+        for folder in self.folders.clone() {
+            let watcher = FolderWatcher {
+                backend: self.backend.clone(),
+                folder,
+                retry_delay: self.retry_delay,
+                max_retry_delay: self.max_retry_delay,
+                events: self.events.clone(),
+                statuses: self.statuses.clone(),
+                stop: stop_rx.clone(),
+            };
+
+            self.tasks.push(tokio::spawn(watcher.run()));
+        }
+
+        self.stop = Some(stop);
+    }
+
+    /// Stop watching all folders, then wait for the tasks to end.
+    ///
+    /// Has no effect if the service is not started.
+    pub async fn stop(&mut self) {
+        let Some(stop) = self.stop.take() else {
+            return;
+        };
+
+        info!("stopping watch service");
+
+        let _ = stop.send(true);
+
+        for task in self.tasks.drain(..) {
+            if let Err(err) = task.await {
+                debug!("cannot join folder watcher task, skipping it");
+                debug!("{err:?}");
+            }
+        }
+    }
+}
+
+impl<B: WatchEnvelopes + ?Sized + 'static> Drop for WatchService<B> {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// The watcher of a single folder, running in its own task.
+struct FolderWatcher<B: WatchEnvelopes + ?Sized + 'static> {
+    backend: Arc<B>,
+    folder: String,
+    retry_delay: Duration,
+    max_retry_delay: Duration,
+    events: broadcast::Sender<WatchEvent>,
+    statuses: Arc<Mutex<HashMap<String, WatchStatus>>>,
+    stop: watch::Receiver<bool>,
+}
+
+impl<B: WatchEnvelopes + ?Sized + 'static> FolderWatcher<B> {
+    fn set_status(&self, status: WatchStatus) {
+        let mut statuses = self.statuses.lock().unwrap();
+        statuses.insert(self.folder.clone(), status);
+    }
+
+    fn emit(&self, event: WatchEvent) {
+        let _ = self.events.send(event);
+    }
+
+    async fn run(mut self) {
+        let emitter = WatchEventEmitter {
+            folder: self.folder.clone(),
+            events: self.events.clone(),
+        };
+
+        let mut attempt = 0;
+
+        loop {
+            let (shutdown_request, wait_for_shutdown_request) = oneshot::channel();
+            let (shutdown, _) = oneshot::channel();
+
+            self.set_status(WatchStatus::Running);
+            self.emit(WatchEvent::Started(self.folder.clone()));
+
+            let started_at = Instant::now();
+            let backend = self.backend.clone();
+            let folder = self.folder.clone();
+            let watch = EMITTER.scope(emitter.clone(), async move {
+                backend
+                    .watch_envelopes(&folder, wait_for_shutdown_request, shutdown)
+                    .await
+            });
+            tokio::pin!(watch);
+
+            let res = select! {
+                res = &mut watch => Some(res),
+                _ = self.stop.changed() => None,
+            };
+
+            match res {
+                None => {
+                    let _ = shutdown_request.send(());
+                    wait(&self.folder, watch).await;
+                    break;
+                }
+                Some(Ok(())) => {
+                    debug!(folder = self.folder, "folder watcher ended");
+                    break;
+                }
+                Some(Err(err)) => {
+                    warn!(folder = self.folder, "folder watcher failed: {err}");
+                    debug!("{err:?}");
+
+                    // consider the connection stable again if the
+                    // watcher ran longer than the maximum delay
+                    if started_at.elapsed() > self.max_retry_delay {
+                        attempt = 0;
+                    }
+
+                    attempt += 1;
+
+                    let delay = self
+                        .retry_delay
+                        .saturating_mul(1u32 << (attempt - 1).min(16))
+                        .min(self.max_retry_delay);
+
+                    self.set_status(WatchStatus::Reconnecting(attempt));
+                    self.emit(WatchEvent::Disconnected(
+                        self.folder.clone(),
+                        err.to_string(),
+                    ));
+
+                    info!(folder = self.folder, attempt, "reconnecting in {delay:?}");
+
+                    select! {
+                        _ = sleep(delay) => continue,
+                        _ = self.stop.changed() => break,
+                    }
+                }
+            }
+        }
+
+        self.set_status(WatchStatus::Stopped);
+        self.emit(WatchEvent::Stopped(self.folder.clone()));
+    }
+}
+
+/// Wait for the given folder watcher to shut down, giving up after
+/// [`SHUTDOWN_TIMEOUT`].
+async fn wait(folder: &str, watch: impl Future) {
+    if timeout(SHUTDOWN_TIMEOUT, watch).await.is_err() {
+        debug!(
+            folder,
+            "folder watcher did not shut down in time, dropping it"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use tokio::sync::oneshot::{Receiver, Sender};
+
+    use super::{WatchEvent, WatchService, WatchStatus};
+    use crate::{envelope::watch::WatchEnvelopes, AnyResult};
+
+    /// Backend failing on first connection, then waiting for the
+    /// shutdown request.
+    #[derive(Default)]
+    struct FlakyBackend(AtomicUsize);
+
+    #[async_trait]
+    impl WatchEnvelopes for FlakyBackend {
+        async fn watch_envelopes(
+            &self,
+            _folder: &str,
+            wait_for_shutdown_request: Receiver<()>,
+            shutdown: Sender<()>,
+        ) -> AnyResult<()> {
+            if self.0.fetch_add(1, Ordering::SeqCst) == 0 {
+                let err = std::io::Error::other("connection reset");
+                return Err(crate::email::error::Error::IoError(err).into());
+            }
+
+            let _ = wait_for_shutdown_request.await;
+            let _ = shutdown.send(());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn reconnect_then_stop() {
+        let backend = Arc::new(FlakyBackend::default());
+        let mut service =
+            WatchService::new(backend.clone(), ["INBOX"]).with_retry_delay(Duration::ZERO);
+        let mut events = service.subscribe();
+
+        service.start();
+        assert!(service.is_running());
+
+        assert_eq!(
+            events.recv().await.unwrap(),
+            WatchEvent::Started("INBOX".into())
+        );
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            WatchEvent::Disconnected(folder, _) if folder == "INBOX"
+        ));
+        assert_eq!(
+            events.recv().await.unwrap(),
+            WatchEvent::Started("INBOX".into())
+        );
+
+        service.stop().await;
+        assert!(!service.is_running());
+
+        assert_eq!(
+            events.recv().await.unwrap(),
+            WatchEvent::Stopped("INBOX".into())
+        );
+        assert_eq!(service.status()["INBOX"], WatchStatus::Stopped);
+        assert_eq!(backend.0.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+}
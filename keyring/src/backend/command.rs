@@ -0,0 +1,182 @@
+//! # Command backend
+//!
+//! Module dedicated to the command keyring backend, which bridges
+//! password managers exposing a `pass`-like command-line interface,
+//! like [pass](https://www.passwordstore.org/) and
+//! [gopass](https://www.gopass.pw/).
+
+use std::{
+    any::Any,
+    io::{self, Write},
+    process::{Command, Output, Stdio},
+};
+
+use crate::native::{self, credential::CredentialApi};
+
+/// The password manager program.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum PassProgram {
+    /// The standard unix password manager.
+    #[default]
+    Pass,
+
+    /// The slightly more awesome standard unix password manager for
+    /// teams.
+    Gopass,
+}
+
+impl PassProgram {
+    /// Returns the name of the program binary.
+    pub fn bin(&self) -> &'static str {
+        match self {
+            Self::Pass => "pass",
+            Self::Gopass => "gopass",
+        }
+    }
+
+    /// Returns the arguments used to print the secret of the given
+    /// key.
+    fn show_args<'a>(&self, key: &'a str) -> Vec<&'a str> {
+        match self {
+            Self::Pass => vec!["show", key],
+            Self::Gopass => vec!["show", "--password", key],
+        }
+    }
+}
+
+/// The command keyring backend configuration.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct CommandBackend {
+    /// The password manager program.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub program: PassProgram,
+
+    /// The prefix prepended to keys, in order to store secrets in a
+    /// dedicated folder of the password store.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub prefix: Option<String>,
+}
+
+impl CommandBackend {
+    /// Creates a new command backend for the given program.
+    pub fn new(program: PassProgram) -> Self {
+        Self {
+            program,
+            prefix: None,
+        }
+    }
+
+    /// Returns the path of the given key in the password store.
+    pub fn path(&self, key: &str) -> String {
+        match &self.prefix {
+            Some(prefix) => format!("{}/{key}", prefix.trim_end_matches('/')),
+            None => key.to_owned(),
+        }
+    }
+}
+
+/// The command credential.
+#[derive(Debug)]
+pub struct CommandCredential {
+    program: PassProgram,
+    path: String,
+}
+
+impl CommandCredential {
+    /// Creates a new command credential from a backend
+    /// configuration and a key.
+    pub fn new(config: &CommandBackend, key: &str) -> Self {
+        Self {
+            program: config.program.clone(),
+            path: config.path(key),
+        }
+    }
+
+    fn run(&self, args: &[&str], stdin: Option<&[u8]>) -> native::Result<Output> {
+        let mut cmd = Command::new(self.program.bin());
+        cmd.args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let mut child = cmd.spawn().map_err(platform_failure)?;
+
+        if let Some(input) = stdin {
+            if let Some(mut child_stdin) = child.stdin.take() {
+                child_stdin.write_all(input).map_err(platform_failure)?;
+            }
+        }
+
+        let output = child.wait_with_output().map_err(platform_failure)?;
+
+        if output.status.success() {
+            return Ok(output);
+        }
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        if stderr.contains("not in the password store") || stderr.contains("not found") {
+            return Err(native::Error::NoEntry);
+        }
+
+        let err = format!(
+            "{} exited with {}: {}",
+            self.program.bin(),
+            output.status,
+            stderr.trim()
+        );
+
+        Err(platform_failure(io::Error::other(err)))
+    }
+}
+
+impl CredentialApi for CommandCredential {
+    fn set_password(&self, password: &str) -> native::Result<()> {
+        self.set_secret(password.as_bytes())
+    }
+
+    fn set_secret(&self, secret: &[u8]) -> native::Result<()> {
+        let args = ["insert", "--multiline", "--force", self.path.as_str()];
+        self.run(&args, Some(secret))?;
+        Ok(())
+    }
+
+    fn get_password(&self) -> native::Result<String> {
+        let secret = native::error::decode_password(self.get_secret()?)?;
+
+        // the secret is the first line of the entry, following the
+        // password store conventions
+        let secret = secret.lines().next().unwrap_or_default().to_owned();
+
+        Ok(secret)
+    }
+
+    fn get_secret(&self) -> native::Result<Vec<u8>> {
+        let args = self.program.show_args(&self.path);
+        Ok(self.run(&args, None)?.stdout)
+    }
+
+    fn delete_credential(&self) -> native::Result<()> {
+        let args = ["rm", "--force", self.path.as_str()];
+        self.run(&args, None)?;
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+fn platform_failure(err: io::Error) -> native::Error {
+    native::Error::PlatformFailure(err.into())
+}
@@ -0,0 +1,97 @@
+//! # Environment backend
+//!
+//! Module dedicated to the environment variable keyring backend.
+//! Secrets are read from environment variables, which makes this
+//! backend read-only.
+
+use std::{any::Any, env, io};
+
+use crate::native::{self, credential::CredentialApi};
+
+/// The environment variable keyring backend configuration.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct EnvBackend {
+    /// The prefix prepended to environment variable names.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub prefix: Option<String>,
+}
+
+impl EnvBackend {
+    /// Returns the name of the environment variable holding the
+    /// secret of the given key.
+    ///
+    /// The key is uppercased, and characters that are neither ASCII
+    /// alphanumeric nor underscores are replaced by underscores.
+    pub fn var_name(&self, key: &str) -> String {
+        let key: String = key
+            .chars()
+            .map(|c| match c {
+                c if c.is_ascii_alphanumeric() => c.to_ascii_uppercase(),
+                _ => '_',
+            })
+            .collect();
+
+        match &self.prefix {
+            Some(prefix) => format!("{prefix}{key}"),
+            None => key,
+        }
+    }
+}
+
+/// The environment variable credential.
+#[derive(Debug)]
+pub struct EnvCredential {
+    var: String,
+}
+
+impl EnvCredential {
+    /// Creates a new environment variable credential from a backend
+    /// configuration and a key.
+    pub fn new(config: &EnvBackend, key: &str) -> Self {
+        Self {
+            var: config.var_name(key),
+        }
+    }
+
+    fn read_only_error(&self) -> native::Error {
+        let err = format!("environment variable {} is read-only", self.var);
+        native::Error::NoStorageAccess(io::Error::new(io::ErrorKind::Unsupported, err).into())
+    }
+}
+
+impl CredentialApi for EnvCredential {
+    fn set_password(&self, _password: &str) -> native::Result<()> {
+        Err(self.read_only_error())
+    }
+
+    fn set_secret(&self, _secret: &[u8]) -> native::Result<()> {
+        Err(self.read_only_error())
+    }
+
+    fn get_password(&self) -> native::Result<String> {
+        match env::var(&self.var) {
+            Ok(secret) => Ok(secret),
+            Err(env::VarError::NotPresent) => Err(native::Error::NoEntry),
+            Err(env::VarError::NotUnicode(secret)) => {
+                Err(native::Error::BadEncoding(secret.into_encoded_bytes()))
+            }
+        }
+    }
+
+    fn get_secret(&self) -> native::Result<Vec<u8>> {
+        self.get_password().map(String::into_bytes)
+    }
+
+    fn delete_credential(&self) -> native::Result<()> {
+        Err(self.read_only_error())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
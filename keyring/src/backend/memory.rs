@@ -0,0 +1,72 @@
+//! # Memory backend
+//!
+//! Module dedicated to the in-memory keyring backend, mostly useful
+//! for tests. Secrets are shared across the whole process and are
+//! lost when it exits.
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+};
+
+use once_cell::sync::Lazy;
+
+use crate::native::{self, credential::CredentialApi};
+
+/// The identifier of in-memory credentials, made of a service name
+/// and a key.
+type MemoryId = (String, String);
+
+/// The in-memory secrets, indexed by service name and key.
+static SECRETS: Lazy<Mutex<HashMap<MemoryId, Vec<u8>>>> = Lazy::new(Default::default);
+
+/// The in-memory credential, identified by its service name and its
+/// key.
+#[derive(Debug)]
+pub struct MemoryCredential {
+    id: MemoryId,
+}
+
+impl MemoryCredential {
+    /// Creates a new in-memory credential from a service name and a
+    /// key.
+    pub fn new(service: impl ToString, key: impl ToString) -> Self {
+        Self {
+            id: (service.to_string(), key.to_string()),
+        }
+    }
+}
+
+impl CredentialApi for MemoryCredential {
+    fn set_password(&self, password: &str) -> native::Result<()> {
+        self.set_secret(password.as_bytes())
+    }
+
+    fn set_secret(&self, secret: &[u8]) -> native::Result<()> {
+        let mut secrets = SECRETS.lock().unwrap_or_else(PoisonError::into_inner);
+        secrets.insert(self.id.clone(), secret.to_vec());
+        Ok(())
+    }
+
+    fn get_password(&self) -> native::Result<String> {
+        native::error::decode_password(self.get_secret()?)
+    }
+
+    fn get_secret(&self) -> native::Result<Vec<u8>> {
+        let secrets = SECRETS.lock().unwrap_or_else(PoisonError::into_inner);
+        secrets.get(&self.id).cloned().ok_or(native::Error::NoEntry)
+    }
+
+    fn delete_credential(&self) -> native::Result<()> {
+        let mut secrets = SECRETS.lock().unwrap_or_else(PoisonError::into_inner);
+        secrets
+            .remove(&self.id)
+            .map(|_| ())
+            .ok_or(native::Error::NoEntry)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,161 @@
+//! # Keyring backends
+//!
+//! Module dedicated to keyring backends. By default, secrets are
+//! stored in the native keyring of the platform. Other backends can
+//! be selected globally using [`set_global_backend`], or per entry
+//! using [`KeyringEntry::try_new_with_backend`] or a backend prefix
+//! in the entry key (see [`KeyringBackend::parse_key`]).
+//!
+//! [`KeyringEntry::try_new_with_backend`]: crate::KeyringEntry::try_new_with_backend
+
+mod command;
+mod env;
+mod memory;
+#[cfg(feature = "vault")]
+mod vault;
+
+use once_cell::sync::OnceCell;
+use tracing::debug;
+
+#[cfg(feature = "vault")]
+use self::vault::VaultBatch;
+#[cfg(feature = "vault")]
+#[doc(inline)]
+pub use self::vault::{VaultBackend, VaultCredential};
+#[doc(inline)]
+pub use self::{
+    command::{CommandBackend, CommandCredential, PassProgram},
+    env::{EnvBackend, EnvCredential},
+    memory::MemoryCredential,
+};
+use crate::native;
+
+/// The global backend, wrapped in a once cell.
+static BACKEND: OnceCell<KeyringBackend> = OnceCell::new();
+
+/// Gets the global keyring backend.
+///
+/// If the backend is not defined, returns the native backend.
+pub fn get_global_backend() -> &'static KeyringBackend {
+    static NATIVE: KeyringBackend = KeyringBackend::Native;
+    BACKEND.get().unwrap_or(&NATIVE)
+}
+
+/// Replaces the global keyring backend.
+///
+/// This function has no effect if a global backend has already been
+/// defined.
+pub fn set_global_backend(backend: KeyringBackend) {
+    debug!(?backend, "define global backend");
+
+    if let Err((prev, _)) = BACKEND.try_insert(backend) {
+        debug!(backend = ?prev, "backend already defined, skipping it");
+    }
+}
+
+/// The keyring backend, where secrets are stored.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "kebab-case")
+)]
+pub enum KeyringBackend {
+    /// The native keyring of the platform.
+    #[default]
+    Native,
+
+    /// An encrypted file vault.
+    #[cfg(feature = "vault")]
+    Vault(VaultBackend),
+
+    /// A `pass`-like password manager command.
+    Command(CommandBackend),
+
+    /// Environment variables (read-only).
+    Env(EnvBackend),
+
+    /// An in-memory store, shared across the process.
+    Memory,
+}
+
+impl KeyringBackend {
+    /// Splits the given entry key into a backend and the actual key,
+    /// if the key starts with a known backend prefix.
+    ///
+    /// Known prefixes are `native:`, `memory:`, `env:`, `pass:`,
+    /// `gopass:` and `vault:`. The `vault:` prefix requires the
+    /// global backend to be a vault, since vaults need to be
+    /// configured.
+    pub fn parse_key(key: &str) -> Option<(KeyringBackend, &str)> {
+        let (prefix, key) = key.split_once(':')?;
+
+        let backend = match prefix {
+            "native" => Self::Native,
+            "memory" => Self::Memory,
+            "env" => Self::Env(EnvBackend::default()),
+            "pass" => Self::Command(CommandBackend::new(PassProgram::Pass)),
+            "gopass" => Self::Command(CommandBackend::new(PassProgram::Gopass)),
+            #[cfg(feature = "vault")]
+            "vault" => match get_global_backend() {
+                backend @ Self::Vault(_) => backend.clone(),
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        Some((backend, key))
+    }
+
+    /// Builds the native entry matching the given service name and
+    /// key.
+    pub fn build_entry(&self, service: &str, key: &str) -> native::Result<native::Entry> {
+        let entry = match self {
+            Self::Native => return native::Entry::new(service, key),
+            #[cfg(feature = "vault")]
+            Self::Vault(config) => {
+                native::Entry::new_with_credential(Box::new(VaultCredential::new(config, key)))
+            }
+            Self::Command(config) => {
+                native::Entry::new_with_credential(Box::new(CommandCredential::new(config, key)))
+            }
+            Self::Env(config) => {
+                native::Entry::new_with_credential(Box::new(EnvCredential::new(config, key)))
+            }
+            Self::Memory => {
+                native::Entry::new_with_credential(Box::new(MemoryCredential::new(service, key)))
+            }
+        };
+
+        Ok(entry)
+    }
+}
+
+/// The reader of a batch of entries.
+///
+/// Backends that need to load all their secrets at once, like
+/// vaults, load them only once per batch.
+#[derive(Debug, Default)]
+pub(crate) struct BatchReader {
+    #[cfg(feature = "vault")]
+    vaults: VaultBatch,
+}
+
+impl BatchReader {
+    /// Gets the password of the given native entry, built from the
+    /// given backend and key.
+    #[cfg_attr(not(feature = "vault"), allow(unused_variables))]
+    pub fn get_password(
+        &mut self,
+        backend: &KeyringBackend,
+        key: &str,
+        entry: &native::Entry,
+    ) -> native::Result<String> {
+        #[cfg(feature = "vault")]
+        if let KeyringBackend::Vault(config) = backend {
+            return self.vaults.get_password(config, key);
+        }
+
+        entry.get_password()
+    }
+}
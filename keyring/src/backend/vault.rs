@@ -0,0 +1,301 @@
+//! # Vault backend
+//!
+//! Module dedicated to the encrypted file keyring backend. Secrets
+//! are stored together in a single file, encrypted with
+//! [age](https://age-encryption.org/) using a passphrase.
+
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Read, Write},
+    iter,
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+};
+
+use age::{scrypt, secrecy::SecretString};
+use once_cell::sync::Lazy;
+
+use crate::native::{self, credential::CredentialApi};
+
+/// The lock preventing concurrent writes to vaults within the same
+/// process.
+static LOCK: Lazy<Mutex<()>> = Lazy::new(Default::default);
+
+/// The vault keyring backend configuration.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct VaultBackend {
+    /// The path to the vault file.
+    pub path: PathBuf,
+
+    /// The name of the environment variable holding the passphrase
+    /// of the vault.
+    ///
+    /// Defaults to [`VaultBackend::DEFAULT_PASSPHRASE_VAR`].
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub passphrase_var: Option<String>,
+
+    /// The passphrase of the vault.
+    ///
+    /// Takes precedence over the environment variable. It is never
+    /// (de)serialized.
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub passphrase: Option<String>,
+}
+
+impl VaultBackend {
+    /// The default name of the environment variable holding the
+    /// passphrase of the vault.
+    pub const DEFAULT_PASSPHRASE_VAR: &'static str = "KEYRING_VAULT_PASSPHRASE";
+
+    /// Creates a new vault backend from a path and a passphrase.
+    pub fn new(path: impl Into<PathBuf>, passphrase: impl ToString) -> Self {
+        Self {
+            path: path.into(),
+            passphrase_var: None,
+            passphrase: Some(passphrase.to_string()),
+        }
+    }
+
+    /// Returns the passphrase of the vault.
+    fn passphrase(&self) -> native::Result<SecretString> {
+        if let Some(passphrase) = &self.passphrase {
+            return Ok(SecretString::from(passphrase.clone()));
+        }
+
+        let var = match &self.passphrase_var {
+            Some(var) => var.as_str(),
+            None => Self::DEFAULT_PASSPHRASE_VAR,
+        };
+
+        match env::var(var) {
+            Ok(passphrase) => Ok(SecretString::from(passphrase)),
+            Err(err) => {
+                let err = format!("cannot get vault passphrase from {var}: {err}");
+                let err = io::Error::new(io::ErrorKind::NotFound, err);
+                Err(native::Error::NoStorageAccess(err.into()))
+            }
+        }
+    }
+
+    /// Reads and decrypts the secrets of the vault.
+    ///
+    /// An unexisting vault is considered empty.
+    fn read(&self) -> native::Result<BTreeMap<String, Vec<u8>>> {
+        let encrypted = match fs::read(&self.path) {
+            Ok(encrypted) => encrypted,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(err) => return Err(platform_failure(err)),
+        };
+
+        let decryptor = age::Decryptor::new(&encrypted[..]).map_err(platform_failure)?;
+
+        if !decryptor.is_scrypt() {
+            let err = "vault is not encrypted with a passphrase";
+            return Err(platform_failure(io::Error::new(
+                io::ErrorKind::InvalidData,
+                err,
+            )));
+        }
+
+        let identity = scrypt::Identity::new(self.passphrase()?);
+
+        let mut decrypted = Vec::new();
+        decryptor
+            .decrypt(iter::once(&identity as &dyn age::Identity))
+            .map_err(|err| native::Error::NoStorageAccess(err.into()))?
+            .read_to_end(&mut decrypted)
+            .map_err(platform_failure)?;
+
+        decode(&decrypted)
+    }
+
+    /// Encrypts and writes the given secrets to the vault.
+    fn write(&self, secrets: &BTreeMap<String, Vec<u8>>) -> native::Result<()> {
+        let encryptor = age::Encryptor::with_user_passphrase(self.passphrase()?);
+
+        let mut encrypted = Vec::new();
+        let mut writer = encryptor
+            .wrap_output(&mut encrypted)
+            .map_err(platform_failure)?;
+        writer
+            .write_all(encode(secrets).as_bytes())
+            .map_err(platform_failure)?;
+        writer.finish().map_err(platform_failure)?;
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(platform_failure)?;
+        }
+
+        // write to a temporary file first, so that the vault is never
+        // left half-written
+        let tmp = self.path.with_extension("tmp");
+
+        // a temporary file left by an interrupted write may have
+        // other permissions, so it is replaced rather than reused
+        match fs::remove_file(&tmp) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(platform_failure(err));
+            }
+            _ => (),
+        }
+
+        let mut opts = OpenOptions::new();
+        opts.write(true).create_new(true);
+
+        // the file is only readable by its owner before anything is
+        // written into it
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            opts.mode(0o600);
+        }
+
+        let mut file = opts.open(&tmp).map_err(platform_failure)?;
+        file.write_all(&encrypted).map_err(platform_failure)?;
+        file.sync_all().map_err(platform_failure)?;
+        fs::rename(&tmp, &self.path).map_err(platform_failure)?;
+
+        Ok(())
+    }
+}
+
+/// The secrets of the vaults read during a batch of operations.
+///
+/// Decrypting a vault is expensive by design, so each vault is read
+/// and decrypted only once per batch (see
+/// [`KeyringEntry::get_many`]).
+///
+/// [`KeyringEntry::get_many`]: crate::KeyringEntry::get_many
+#[derive(Debug, Default)]
+pub(crate) struct VaultBatch {
+    vaults: Vec<(VaultBackend, BTreeMap<String, Vec<u8>>)>,
+}
+
+impl VaultBatch {
+    /// Gets the password of the given key from the given vault.
+    pub fn get_password(&mut self, vault: &VaultBackend, key: &str) -> native::Result<String> {
+        let secrets = match self.vaults.iter().position(|(v, _)| v == vault) {
+            Some(i) => &self.vaults[i].1,
+            None => {
+                let secrets = {
+                    let _lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+                    vault.read()?
+                };
+                self.vaults.push((vault.clone(), secrets));
+                &self.vaults[self.vaults.len() - 1].1
+            }
+        };
+
+        let secret = secrets.get(key).cloned().ok_or(native::Error::NoEntry)?;
+        native::error::decode_password(secret)
+    }
+}
+
+/// The vault credential.
+#[derive(Debug)]
+pub struct VaultCredential {
+    vault: VaultBackend,
+    key: String,
+}
+
+impl VaultCredential {
+    /// Creates a new vault credential from a backend configuration
+    /// and a key.
+    pub fn new(config: &VaultBackend, key: &str) -> Self {
+        Self {
+            vault: config.clone(),
+            key: key.to_owned(),
+        }
+    }
+}
+
+impl CredentialApi for VaultCredential {
+    fn set_password(&self, password: &str) -> native::Result<()> {
+        self.set_secret(password.as_bytes())
+    }
+
+    fn set_secret(&self, secret: &[u8]) -> native::Result<()> {
+        let _lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut secrets = self.vault.read()?;
+        secrets.insert(self.key.clone(), secret.to_vec());
+        self.vault.write(&secrets)
+    }
+
+    fn get_password(&self) -> native::Result<String> {
+        native::error::decode_password(self.get_secret()?)
+    }
+
+    fn get_secret(&self) -> native::Result<Vec<u8>> {
+        let _lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut secrets = self.vault.read()?;
+        secrets.remove(&self.key).ok_or(native::Error::NoEntry)
+    }
+
+    fn delete_credential(&self) -> native::Result<()> {
+        let _lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut secrets = self.vault.read()?;
+        secrets.remove(&self.key).ok_or(native::Error::NoEntry)?;
+        self.vault.write(&secrets)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Encodes secrets, one per line, as a pair of hexadecimal strings
+/// separated by a space.
+fn encode(secrets: &BTreeMap<String, Vec<u8>>) -> String {
+    secrets
+        .iter()
+        .map(|(key, secret)| format!("{} {}\n", hex(key.as_bytes()), hex(secret)))
+        .collect()
+}
+
+/// Decodes secrets encoded with [`encode`].
+fn decode(bytes: &[u8]) -> native::Result<BTreeMap<String, Vec<u8>>> {
+    let invalid = || {
+        let err = io::Error::new(io::ErrorKind::InvalidData, "invalid vault content");
+        platform_failure(err)
+    };
+
+    let content = std::str::from_utf8(bytes).map_err(|_| invalid())?;
+    let mut secrets = BTreeMap::new();
+
+    for line in content.lines().filter(|line| !line.is_empty()) {
+        let (key, secret) = line.split_once(' ').ok_or_else(invalid)?;
+        let key = unhex(key).ok_or_else(invalid)?;
+        let key = String::from_utf8(key).map_err(|_| invalid())?;
+        let secret = unhex(secret).ok_or_else(invalid)?;
+        secrets.insert(key, secret);
+    }
+
+    Ok(secrets)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn unhex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn platform_failure(err: impl std::error::Error + Send + Sync + 'static) -> native::Error {
+    native::Error::PlatformFailure(Box::new(err))
+}
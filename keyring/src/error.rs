@@ -23,6 +23,8 @@ pub enum Error {
     SetSecretError(#[source] native::Error, String),
     #[error("cannot delete secret from keyring matching `{1}`")]
     DeleteSecretError(#[source] native::Error, String),
+    #[error("cannot list keyring entries of namespace `{1}`")]
+    ListEntriesError(#[source] native::Error, String),
 
     #[cfg(feature = "tokio")]
     #[error(transparent)]
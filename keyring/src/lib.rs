@@ -1,7 +1,9 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 #![doc = include_str!("../README.md")]
 
+pub mod backend;
 mod error;
+mod namespace;
 mod service;
 
 use std::sync::Arc;
@@ -9,9 +11,13 @@
 pub use keyring_native as native;
 use tracing::debug;
 
+use crate::backend::BatchReader;
+
 #[doc(inline)]
 pub use crate::{
+    backend::{get_global_backend, set_global_backend, KeyringBackend},
     error::{Error, Result},
+    namespace::KeyringNamespace,
     service::{get_global_service_name, set_global_service_name},
 };
 
@@ -41,8 +47,18 @@ pub struct KeyringEntry {
     /// The key used to identify the current keyring entry.
     pub key: String,
 
+    /// The key used by the backend, which is the key stripped from
+    /// its backend prefix if any.
+    backend_key: String,
+
+    /// The namespace of the entry.
+    namespace: KeyringNamespace,
+
     /// The native keyring entry.
     entry: Arc<native::Entry>,
+
+    /// The native keyring entry holding the index of the namespace.
+    index: Arc<native::Entry>,
 }
 
 impl Eq for KeyringEntry {}
@@ -61,6 +77,65 @@ pub fn try_new(key: impl ToString) -> Result<Self> {
         Self::try_from(key.to_string())
     }
 
+    /// Creates a new keyring entry from a key, stored in the given
+    /// backend.
+    ///
+    /// The backend is not part of the (de)serialized form of the
+    /// entry. Use a backend prefix in the key instead if needed (see
+    /// [`KeyringBackend::parse_key`]).
+    pub fn try_new_with_backend(key: impl ToString, backend: &KeyringBackend) -> Result<Self> {
+        KeyringNamespace::global()
+            .with_backend(backend.clone())
+            .entry(key)
+    }
+
+    /// Creates a new keyring entry from a key, within the given
+    /// namespace.
+    ///
+    /// The namespace is not part of the (de)serialized form of the
+    /// entry.
+    pub fn try_new_in(namespace: &KeyringNamespace, key: impl ToString) -> Result<Self> {
+        namespace.entry(key)
+    }
+
+    /// Builds a keyring entry and the index of its namespace.
+    pub(crate) fn build(
+        namespace: KeyringNamespace,
+        key: String,
+        backend_key: String,
+    ) -> Result<Self> {
+        let entry = match namespace
+            .backend()
+            .build_entry(namespace.name(), &backend_key)
+        {
+            Ok(entry) => Ok(Arc::new(entry)),
+            Err(err) => Err(Error::BuildEntryError(err, key.clone())),
+        }?;
+
+        let index = Arc::new(namespace.index()?);
+
+        Ok(Self {
+            key,
+            backend_key,
+            namespace,
+            entry,
+            index,
+        })
+    }
+
+    /// Returns the namespace of the keyring entry.
+    pub fn namespace(&self) -> &KeyringNamespace {
+        &self.namespace
+    }
+
+    /// Renames the keyring entry within its namespace, then returns
+    /// the renamed entry.
+    ///
+    /// See [`KeyringNamespace::rename`].
+    pub async fn rename(&self, key: impl AsRef<str>) -> Result<Self> {
+        self.namespace.rename(&self.backend_key, key.as_ref()).await
+    }
+
     /// Gets the secret of the keyring entry.
     pub async fn get_secret(&self) -> Result<String> {
         let key = &self.key;
@@ -92,6 +167,72 @@ pub async fn find_secret(&self) -> Result<Option<String>> {
         }
     }
 
+    /// Gets the secrets of the given keyring entries, in order.
+    ///
+    /// Secrets are all fetched within the same blocking task, which
+    /// is cheaper than calling [`KeyringEntry::get_secret`] for each
+    /// entry, for example when loading all the credentials of an
+    /// account. Vaults are decrypted only once per call.
+    pub async fn get_many<'a>(
+        entries: impl IntoIterator<Item = &'a KeyringEntry>,
+    ) -> Result<Vec<String>> {
+        let entries: Vec<_> = entries
+            .into_iter()
+            .map(|entry| {
+                let backend = entry.namespace.backend().clone();
+                let backend_key = entry.backend_key.clone();
+                (entry.key.clone(), backend, backend_key, entry.entry.clone())
+            })
+            .collect();
+        debug!(count = entries.len(), "get keyring secrets");
+
+        spawn_blocking(move || {
+            let mut reader = BatchReader::default();
+            entries
+                .into_iter()
+                .map(|(key, backend, backend_key, entry)| {
+                    reader
+                        .get_password(&backend, &backend_key, &entry)
+                        .map_err(|err| Error::GetSecretError(err, key))
+                })
+                .collect()
+        })
+        .await?
+    }
+
+    /// Finds the secrets of the given keyring entries, in order.
+    ///
+    /// This function is like [`KeyringEntry::get_many`], except
+    /// that secrets that cannot be found are returned as `None`.
+    pub async fn find_many<'a>(
+        entries: impl IntoIterator<Item = &'a KeyringEntry>,
+    ) -> Result<Vec<Option<String>>> {
+        let entries: Vec<_> = entries
+            .into_iter()
+            .map(|entry| {
+                let backend = entry.namespace.backend().clone();
+                let backend_key = entry.backend_key.clone();
+                (entry.key.clone(), backend, backend_key, entry.entry.clone())
+            })
+            .collect();
+        debug!(count = entries.len(), "find keyring secrets");
+
+        spawn_blocking(move || {
+            let mut reader = BatchReader::default();
+            entries
+                .into_iter()
+                .map(|(key, backend, backend_key, entry)| {
+                    match reader.get_password(&backend, &backend_key, &entry) {
+                        Err(native::Error::NoEntry) => Ok(None),
+                        Err(err) => Err(Error::FindSecretError(err, key)),
+                        Ok(secret) => Ok(Some(secret)),
+                    }
+                })
+                .collect()
+        })
+        .await?
+    }
+
     /// (Re)sets the secret of the keyring entry.
     pub async fn set_secret(&self, secret: impl ToString) -> Result<()> {
         let key = &self.key;
@@ -99,9 +240,15 @@ pub async fn set_secret(&self, secret: impl ToString) -> Result<()> {
 
         let secret = secret.to_string();
         let entry = self.entry.clone();
-        spawn_blocking(move || entry.set_password(&secret))
-            .await?
-            .map_err(|err| Error::SetSecretError(err, key.clone()))?;
+        let index = self.index.clone();
+        let backend_key = self.backend_key.clone();
+        spawn_blocking(move || {
+            entry.set_password(&secret)?;
+            namespace::update_index(&index, &backend_key, true);
+            Ok(())
+        })
+        .await?
+        .map_err(|err| Error::SetSecretError(err, key.clone()))?;
 
         Ok(())
     }
@@ -122,9 +269,19 @@ pub async fn delete_secret(&self) -> Result<()> {
         debug!(key, "delete keyring secret");
 
         let entry = self.entry.clone();
-        spawn_blocking(move || entry.delete_credential())
-            .await?
-            .map_err(|err| Error::DeleteSecretError(err, key.clone()))?;
+        let index = self.index.clone();
+        let backend_key = self.backend_key.clone();
+        spawn_blocking(move || {
+            let res = entry.delete_credential();
+
+            if matches!(res, Ok(()) | Err(native::Error::NoEntry)) {
+                namespace::update_index(&index, &backend_key, false);
+            }
+
+            res
+        })
+        .await?
+        .map_err(|err| Error::DeleteSecretError(err, key.clone()))?;
 
         Ok(())
     }
@@ -138,15 +295,19 @@ impl TryFrom<String> for KeyringEntry {
     /// This implementation is a wrapper around
     /// [`native::Entry::new`], where the service name is taken
     /// globally from [`get_global_service_name`].
+    ///
+    /// The entry is stored in the backend matching the prefix of the
+    /// key if any, otherwise in the global backend (see
+    /// [`get_global_backend`]).
     fn try_from(key: String) -> Result<Self> {
-        let service = get_global_service_name();
-
-        let entry = match native::Entry::new(service, &key) {
-            Ok(entry) => Ok(Arc::new(entry)),
-            Err(err) => Err(Error::BuildEntryError(err, key.clone())),
-        }?;
-
-        Ok(Self { key, entry })
+        match KeyringBackend::parse_key(&key) {
+            Some((backend, backend_key)) => {
+                let namespace = KeyringNamespace::global().with_backend(backend);
+                let backend_key = backend_key.to_owned();
+                Self::build(namespace, key, backend_key)
+            }
+            None => Self::build(KeyringNamespace::global(), key.clone(), key),
+        }
     }
 }
 
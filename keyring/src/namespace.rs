@@ -0,0 +1,223 @@
+//! # Namespace
+//!
+//! Module dedicated to keyring namespaces. A namespace groups the
+//! entries of an application (the service name of native keyrings)
+//! stored in the same backend.
+//!
+//! Since keyrings do not provide a way to enumerate their secrets,
+//! each namespace keeps an index of the keys of its entries, updated
+//! whenever a secret is set or deleted. This index allows entries to
+//! be listed, renamed and migrated.
+
+use std::sync::{Arc, Mutex, PoisonError};
+
+use once_cell::sync::Lazy;
+use tracing::{debug, warn};
+
+use crate::{
+    get_global_backend, get_global_service_name, native, spawn_blocking, Error, KeyringBackend,
+    KeyringEntry, Result,
+};
+
+/// The lock preventing concurrent updates of namespace indexes
+/// within the same process.
+static INDEX_LOCK: Lazy<Mutex<()>> = Lazy::new(Default::default);
+
+/// The keyring namespace.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KeyringNamespace {
+    /// The name of the namespace, used as keyring service name.
+    name: String,
+
+    /// The backend entries of the namespace are stored in.
+    backend: KeyringBackend,
+}
+
+impl KeyringNamespace {
+    /// The prefix of the key of the entry holding the index of the
+    /// namespace.
+    pub const INDEX_KEY_PREFIX: &'static str = "keyring-lib-index:";
+
+    /// Creates a new namespace from a name, using the global backend
+    /// (see [`get_global_backend`]).
+    pub fn new(name: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            backend: get_global_backend().clone(),
+        }
+    }
+
+    /// Creates the global namespace, named after the global service
+    /// name (see [`get_global_service_name`]).
+    pub fn global() -> Self {
+        Self::new(get_global_service_name())
+    }
+
+    /// Replaces the backend of the namespace, using the builder
+    /// pattern.
+    pub fn with_backend(mut self, backend: KeyringBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Returns the name of the namespace.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the backend of the namespace.
+    pub fn backend(&self) -> &KeyringBackend {
+        &self.backend
+    }
+
+    /// Creates a new keyring entry from a key, within the namespace.
+    pub fn entry(&self, key: impl ToString) -> Result<KeyringEntry> {
+        let key = key.to_string();
+        KeyringEntry::build(self.clone(), key.clone(), key)
+    }
+
+    /// Returns the key of the entry holding the index of the
+    /// namespace.
+    ///
+    /// The key contains the name of the namespace, since some
+    /// backends do not take service names into account.
+    pub fn index_key(&self) -> String {
+        format!("{}{}", Self::INDEX_KEY_PREFIX, self.name)
+    }
+
+    /// Builds the native entry holding the index of the namespace.
+    pub(crate) fn index(&self) -> Result<native::Entry> {
+        let key = self.index_key();
+        self.backend
+            .build_entry(&self.name, &key)
+            .map_err(|err| Error::BuildEntryError(err, key))
+    }
+
+    /// Lists the entries of the namespace whose key starts with the
+    /// given prefix.
+    ///
+    /// Only entries whose secret has been set through this library
+    /// are listed.
+    pub async fn list_entries(&self, prefix: &str) -> Result<Vec<KeyringEntry>> {
+        debug!(namespace = self.name, prefix, "list keyring entries");
+
+        let index = self.index()?;
+        let keys = spawn_blocking(move || read_index(&index))
+            .await?
+            .map_err(|err| Error::ListEntriesError(err, self.name.clone()))?;
+
+        keys.into_iter()
+            .filter(|key| key.starts_with(prefix))
+            .map(|key| self.entry(key))
+            .collect()
+    }
+
+    /// Renames the entry matching the given key within the
+    /// namespace, then returns the renamed entry.
+    ///
+    /// The secret is moved to the new entry, then the old entry is
+    /// deleted.
+    pub async fn rename(&self, from: &str, to: &str) -> Result<KeyringEntry> {
+        debug!(namespace = self.name, from, to, "rename keyring entry");
+
+        let entry = self.entry(from)?;
+        let secret = entry.get_secret().await?;
+        let renamed_entry = self.entry(to)?.try_with_secret(secret).await?;
+        entry.delete_secret().await?;
+
+        Ok(renamed_entry)
+    }
+
+    /// Moves the entries of the namespace to the given namespace.
+    ///
+    /// The given function maps keys of this namespace to keys of the
+    /// target namespace. Entries mapped to `None` are left
+    /// untouched. Returns the pairs of keys of the moved entries.
+    ///
+    /// This helper can be used to migrate entries from one naming
+    /// scheme to another, by migrating a namespace to itself, or from
+    /// one backend to another.
+    pub async fn migrate(
+        &self,
+        target: &KeyringNamespace,
+        rename: impl Fn(&str) -> Option<String>,
+    ) -> Result<Vec<(String, String)>> {
+        debug!(
+            from = self.name,
+            to = target.name,
+            "migrate keyring entries"
+        );
+
+        let mut moved = Vec::new();
+
+        for entry in self.list_entries("").await? {
+            let from = entry.key.clone();
+
+            let Some(to) = rename(&from) else {
+                continue;
+            };
+
+            if self == target && from == to {
+                continue;
+            }
+
+            let secret = entry.get_secret().await?;
+            target.entry(&to)?.set_secret(secret).await?;
+            entry.delete_secret().await?;
+
+            moved.push((from, to));
+        }
+
+        Ok(moved)
+    }
+}
+
+impl Default for KeyringNamespace {
+    fn default() -> Self {
+        Self::global()
+    }
+}
+
+/// Reads the keys of the given index entry.
+fn read_index(index: &native::Entry) -> native::Result<Vec<String>> {
+    match index.get_password() {
+        Ok(keys) => Ok(keys.lines().map(ToOwned::to_owned).collect()),
+        Err(native::Error::NoEntry) => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Adds the given key to the given index entry, or removes it from
+/// it.
+///
+/// Errors are logged rather than returned, since the index is not
+/// the source of truth of secrets.
+pub(crate) fn update_index(index: &Arc<native::Entry>, key: &str, add: bool) {
+    if key.contains('\n') {
+        warn!(key, "cannot index keyring entry key containing new lines");
+        return;
+    }
+
+    let _lock = INDEX_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let res = read_index(index).and_then(|mut keys| {
+        let exists = keys.iter().any(|k| k == key);
+
+        match (add, exists) {
+            (true, false) => keys.push(key.to_owned()),
+            (false, true) => keys.retain(|k| k != key),
+            _ => return Ok(()),
+        }
+
+        if keys.is_empty() {
+            index.delete_credential()
+        } else {
+            index.set_password(&keys.join("\n"))
+        }
+    });
+
+    if let Err(err) = res {
+        warn!(key, "cannot update keyring entries index: {err}");
+        debug!("{err:?}");
+    }
+}
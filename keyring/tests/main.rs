@@ -1,6 +1,9 @@
 #[cfg(feature = "async-std")]
 use async_std::test;
-use keyring::{get_global_service_name, set_global_service_name, KeyringEntry};
+use keyring::{
+    backend::EnvBackend, get_global_service_name, set_global_service_name, KeyringBackend,
+    KeyringEntry, KeyringNamespace,
+};
 #[cfg(feature = "tokio")]
 use tokio::test;
 
@@ -25,3 +28,137 @@ async fn main() {
     entry.delete_secret().await.unwrap();
     assert_eq!(entry.find_secret().await.unwrap(), None);
 }
+
+#[test_log::test(test)]
+async fn backends() {
+    // test memory backend, selected per entry using a key prefix
+    let entry = KeyringEntry::try_new("memory:key").unwrap();
+    assert_eq!(entry.key, "memory:key");
+    assert_eq!(entry.find_secret().await.unwrap(), None);
+
+    entry.set_secret("secret").await.unwrap();
+    let same_entry = KeyringEntry::try_new_with_backend("key", &KeyringBackend::Memory).unwrap();
+    assert_eq!(same_entry.get_secret().await.unwrap(), "secret");
+
+    entry.delete_secret().await.unwrap();
+    assert_eq!(same_entry.find_secret().await.unwrap(), None);
+
+    // test batch operations
+    let entries = [
+        KeyringEntry::try_new("memory:key1").unwrap(),
+        KeyringEntry::try_new("memory:key2").unwrap(),
+    ];
+    entries[0].set_secret("secret1").await.unwrap();
+    assert_eq!(
+        KeyringEntry::find_many(&entries).await.unwrap(),
+        vec![Some(String::from("secret1")), None],
+    );
+    assert!(KeyringEntry::get_many(&entries).await.is_err());
+
+    entries[1].set_secret("secret2").await.unwrap();
+    assert_eq!(
+        KeyringEntry::get_many(&entries).await.unwrap(),
+        vec!["secret1", "secret2"],
+    );
+
+    // test environment backend
+    std::env::set_var("KEYRING_TEST_KEY", "secret");
+    let entry = KeyringEntry::try_new_with_backend(
+        "key",
+        &KeyringBackend::Env(EnvBackend {
+            prefix: Some(String::from("KEYRING_TEST_")),
+        }),
+    )
+    .unwrap();
+    assert_eq!(entry.get_secret().await.unwrap(), "secret");
+    assert!(entry.set_secret("new-secret").await.is_err());
+}
+
+#[test_log::test(test)]
+async fn namespaces() {
+    let namespace = KeyringNamespace::new("namespaces").with_backend(KeyringBackend::Memory);
+
+    // test list entries
+    for (key, secret) in [("a-imap", "1"), ("a-smtp", "2"), ("b-imap", "3")] {
+        let entry = KeyringEntry::try_new_in(&namespace, key).unwrap();
+        entry.set_secret(secret).await.unwrap();
+    }
+
+    let keys = |entries: Vec<KeyringEntry>| -> Vec<String> {
+        entries.into_iter().map(|entry| entry.key).collect()
+    };
+
+    let entries = namespace.list_entries("a-").await.unwrap();
+    assert_eq!(keys(entries), vec!["a-imap", "a-smtp"]);
+
+    // test rename
+    let entry = namespace.entry("b-imap").unwrap();
+    let entry = entry.rename("c-imap").await.unwrap();
+    assert_eq!(entry.get_secret().await.unwrap(), "3");
+    assert_eq!(
+        namespace
+            .entry("b-imap")
+            .unwrap()
+            .find_secret()
+            .await
+            .unwrap(),
+        None
+    );
+
+    // test migrate
+    let moved = namespace
+        .migrate(&namespace, |key| {
+            let key = key.strip_prefix("a-")?;
+            Some(format!("d-{key}"))
+        })
+        .await
+        .unwrap();
+    assert_eq!(moved.len(), 2);
+
+    let entries = namespace.list_entries("").await.unwrap();
+    assert_eq!(keys(entries), vec!["c-imap", "d-imap", "d-smtp"]);
+}
+
+#[cfg(feature = "vault")]
+#[test_log::test(test)]
+async fn vault() {
+    use keyring::backend::VaultBackend;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("vault.age");
+    let backend = KeyringBackend::Vault(VaultBackend::new(&path, "passphrase"));
+
+    let entries = [
+        KeyringEntry::try_new_with_backend("key1", &backend).unwrap(),
+        KeyringEntry::try_new_with_backend("key2", &backend).unwrap(),
+    ];
+
+    // test set/get secret
+    entries[0].set_secret("secret1").await.unwrap();
+    assert_eq!(entries[0].get_secret().await.unwrap(), "secret1");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    // test batch operations
+    assert_eq!(
+        KeyringEntry::find_many(&entries).await.unwrap(),
+        vec![Some(String::from("secret1")), None],
+    );
+    assert!(KeyringEntry::get_many(&entries).await.is_err());
+
+    entries[1].set_secret("secret2").await.unwrap();
+    assert_eq!(
+        KeyringEntry::get_many(&entries).await.unwrap(),
+        vec!["secret1", "secret2"],
+    );
+
+    // test wrong passphrase
+    let backend = KeyringBackend::Vault(VaultBackend::new(&path, "wrong"));
+    let entry = KeyringEntry::try_new_with_backend("key1", &backend).unwrap();
+    assert!(entry.get_secret().await.is_err());
+}
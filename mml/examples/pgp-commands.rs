@@ -14,10 +14,15 @@ async fn main() {
                 "gpg --homedir ./tests/gpg-home -eqa <recipients>",
             )),
             encrypt_recipient_fmt: Some(PgpCommands::default_encrypt_recipient_fmt()),
+            encrypt_hidden_recipient_fmt: Some(PgpCommands::default_encrypt_hidden_recipient_fmt()),
             encrypt_recipients_sep: Some(PgpCommands::default_encrypt_recipients_sep()),
+            list_keys_cmd: Some(Command::new(
+                "gpg --homedir ./tests/gpg-home --list-keys --with-colons <recipient>",
+            )),
             decrypt_cmd: Some(Command::new("gpg --homedir ./tests/gpg-home -dq")),
             sign_cmd: Some(Command::new("gpg --homedir ./tests/gpg-home -saq")),
             verify_cmd: Some(Command::new("gpg --homedir ./tests/gpg-home --verify -q")),
+            key_selection: Default::default(),
         }))
         .build(mml)
         .unwrap();
@@ -17,6 +17,7 @@ async fn main() {
     let mml_compiler = MmlCompilerBuilder::new()
         .with_pgp(Pgp::Gpg(PgpGpg {
             home_dir: Some(PathBuf::from("./tests/gpg-home")),
+            key_selection: Default::default(),
         }))
         .build(mml)
         .unwrap();
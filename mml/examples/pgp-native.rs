@@ -38,6 +38,7 @@ async fn main() {
                 "bob@localhost".into(),
                 bob_pkey.clone(),
             )],
+            ..Default::default()
         }))
         .build(mml)
         .unwrap();
@@ -20,10 +20,77 @@ pub enum Error {
     #[cfg(feature = "compiler")]
     #[error("cannot read attachment at {1:?}")]
     ReadAttachmentError(#[source] io::Error, PathBuf),
+    #[cfg(feature = "compiler")]
+    #[error("cannot parse calendar part: invalid utf-8")]
+    ParseCalendarPartUtf8Error(#[source] std::str::Utf8Error),
+    #[cfg(feature = "compiler")]
+    #[error("cannot compile invalid calendar part: {0}")]
+    InvalidCalendarPartError(String),
+    #[cfg(feature = "compiler")]
+    #[error("cannot compile attachment {0}: size of {1} bytes exceeds the limit of {2} bytes")]
+    AttachmentTooLargeError(String, usize, usize),
+    #[cfg(feature = "compiler")]
+    #[error("cannot compile message: size of {0} bytes exceeds the limit of {1} bytes")]
+    MessageTooLargeError(usize, usize),
+    #[cfg(feature = "compiler")]
+    #[error("cannot compile message: invalid {0} header value {1}")]
+    InvalidPriorityHeaderError(&'static str, String),
+    #[cfg(feature = "compiler")]
+    #[error("cannot encode text part using {1}")]
+    EncodeTextPartError(#[source] io::Error, &'static str),
+    #[cfg(feature = "compiler")]
+    #[error("cannot decode part data using {0}")]
+    DecodePartDataError(String),
+    #[cfg(feature = "compiler")]
+    #[error("cannot externalize attachment {1}")]
+    ExternalizeAttachmentError(#[source] Box<dyn std::error::Error + Send + Sync>, String),
+    #[cfg(feature = "markdown")]
+    #[error("cannot parse markdown part: invalid utf-8")]
+    ParseMarkdownPartUtf8Error(#[source] std::string::FromUtf8Error),
+    #[cfg(feature = "remote")]
+    #[error("cannot parse remote part url {1}")]
+    ParseRemotePartUrlError(#[source] http::ureq::http::uri::InvalidUri, String),
+    #[cfg(feature = "remote")]
+    #[error("cannot download remote part at {1}")]
+    DownloadRemotePartError(#[source] http::Error, String),
+    #[cfg(feature = "remote")]
+    #[error("cannot read remote part at {1}")]
+    ReadRemotePartError(#[source] http::ureq::Error, String),
+
+    #[cfg(feature = "template")]
+    #[error("cannot parse template: unclosed tag at offset {0}")]
+    ParseTemplateUnclosedTagError(usize),
+    #[cfg(feature = "template")]
+    #[error("cannot parse template: invalid tag {0}")]
+    ParseTemplateInvalidTagError(String),
+    #[cfg(feature = "template")]
+    #[error("cannot parse template: unexpected tag {0}")]
+    ParseTemplateUnexpectedTagError(String),
+    #[cfg(feature = "template")]
+    #[error("cannot parse template: unclosed block {0}")]
+    ParseTemplateUnclosedBlockError(String),
+    #[cfg(feature = "template")]
+    #[error("cannot render template: undefined variable {0}")]
+    RenderTemplateUndefinedVariableError(String),
+    #[cfg(feature = "template")]
+    #[error("cannot render template: variable {0} is not a text")]
+    RenderTemplateNonTextVariableError(String),
+    #[cfg(feature = "template")]
+    #[error("cannot render template: variable {0} is not a list")]
+    RenderTemplateNonListVariableError(String),
+    #[cfg(feature = "template")]
+    #[error("cannot render template: invalid UTF-8 MML message")]
+    RenderTemplateUtf8Error(#[source] std::str::Utf8Error),
 
     #[cfg(feature = "pgp")]
     #[error("cannot sign part using pgp: missing sender")]
     PgpSignMissingSenderError,
+    #[cfg(feature = "pgp")]
+    #[error("cannot verify pgp signature: missing sender")]
+    PgpVerifyMissingSenderError,
+    #[cfg(feature = "pgp")]
+    #[error("cannot select pgp public key of {0}")]
+    SelectPgpPublicKeyError(String),
 
     #[cfg(all(feature = "pgp-native", feature = "keyring"))]
     #[error("cannot get pgp secret key from keyring")]
@@ -41,6 +108,10 @@ pub enum Error {
     #[error("cannot get pgp secret key passphrase from keyring")]
     GetSecretKeyPassphraseFromKeyringError(#[source] secret::Error),
 
+    #[cfg(feature = "pgp-native")]
+    #[error("cannot prompt for pgp secret key passphrase")]
+    PromptPgpSecretKeyPassphraseError(#[source] io::Error),
+
     #[cfg(all(feature = "pgp-native", feature = "keyring"))]
     #[error("cannot get pgp secret key from keyring")]
     GetPgpSecretKeyFromKeyringError(#[source] secret::keyring::Error),
@@ -74,10 +145,62 @@ pub enum Error {
     #[error("cannot read native pgp secret key")]
     ReadNativePgpSecretKeyError(#[source] pgp::Error),
 
+    #[cfg(feature = "autocrypt")]
+    #[error("cannot open autocrypt peer state database at {1}")]
+    OpenAutocryptPeersError(#[source] rusqlite::Error, PathBuf),
+
+    #[cfg(feature = "autocrypt")]
+    #[error("cannot get autocrypt peer state of {1}")]
+    GetAutocryptPeerError(#[source] rusqlite::Error, String),
+
+    #[cfg(feature = "autocrypt")]
+    #[error("cannot update autocrypt peer state of {1}")]
+    UpdateAutocryptPeerError(#[source] rusqlite::Error, String),
+
+    #[cfg(feature = "autocrypt")]
+    #[error("cannot parse autocrypt public key of {1}")]
+    ParseAutocryptKeyError(#[source] pgp::native::errors::Error, String),
+
+    #[cfg(all(feature = "pgp-agent", unix))]
+    #[error("cannot read gpg-agent pgp public key at {1}")]
+    ReadAgentPgpPublicKeyError(#[source] pgp::Error, PathBuf),
+    #[cfg(all(feature = "pgp-agent", unix))]
+    #[error("cannot decrypt data using gpg-agent")]
+    DecryptAgentPgpError(#[source] pgp::Error),
+    #[cfg(all(feature = "pgp-agent", unix))]
+    #[error("cannot sign data using gpg-agent")]
+    SignAgentPgpError(#[source] pgp::Error),
+
+    #[cfg(feature = "pgp-card")]
+    #[error("cannot read OpenPGP card pgp public key at {1}")]
+    ReadCardPgpPublicKeyError(#[source] pgp::Error, PathBuf),
+    #[cfg(feature = "pgp-card")]
+    #[error("cannot decrypt data using OpenPGP card")]
+    DecryptCardPgpError(#[source] pgp::Error),
+    #[cfg(feature = "pgp-card")]
+    #[error("cannot sign data using OpenPGP card")]
+    SignCardPgpError(#[source] pgp::Error),
+
+    #[cfg(feature = "autocrypt")]
+    #[error("cannot encode autocrypt public key")]
+    EncodeAutocryptKeyError(#[source] pgp::native::errors::Error),
+
+    #[cfg(feature = "autocrypt")]
+    #[error("cannot sign autocrypt public key")]
+    SignAutocryptKeyError(#[source] pgp::native::errors::Error),
+
     #[error("cannot parse MIME message")]
     ParseMimeMessageError,
     #[error("cannot save attachment at {1}")]
     WriteAttachmentError(#[source] io::Error, PathBuf),
+    #[error("cannot encode part data using base64")]
+    EncodePartDataError(#[source] io::Error),
+    #[error("cannot find charset {0}")]
+    FindCharsetError(String),
+    #[error("cannot decode text using charset {0}")]
+    DecodeCharsetError(String),
+    #[error("cannot encode text using charset {0}")]
+    EncodeCharsetError(String),
     #[error("cannot build email")]
     WriteMessageError(#[source] io::Error),
     #[error("cannot parse pgp decrypted part")]
@@ -8,12 +8,14 @@
 
 #[doc(inline)]
 pub use crate::error::{Error, Result};
+#[cfg(feature = "compiler")]
+#[doc(inline)]
+pub use crate::message::{
+    ComposerAttachment, MessageComposer, MmlCompileResult, MmlCompiler, MmlCompilerBuilder,
+};
 #[cfg(feature = "interpreter")]
 #[doc(inline)]
 pub use crate::message::{MimeInterpreter, MimeInterpreterBuilder};
-#[cfg(feature = "compiler")]
-#[doc(inline)]
-pub use crate::message::{MmlCompileResult, MmlCompiler, MmlCompilerBuilder};
 
 #[cfg(any(feature = "pgp-commands", feature = "pgp-native"))]
 #[cfg(any(
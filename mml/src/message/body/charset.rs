@@ -0,0 +1,162 @@
+//! # Charset
+//!
+//! Module dedicated to the charset handling of MML bodies. The
+//! interpreter decodes texts from the charset declared by their part,
+//! and the compiler can encode texts to a given charset.
+
+use std::borrow::Cow;
+
+use encoding_rs::{DecoderResult, Encoding};
+
+use crate::{Error, Result};
+
+/// The policy applied to byte sequences that cannot be decoded using
+/// the charset of a text part.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CharsetReplacement {
+    /// Replace invalid sequences with the replacement character
+    /// `U+FFFD`.
+    #[default]
+    Replace,
+
+    /// Skip invalid sequences.
+    Skip,
+
+    /// Fail the interpretation with an error.
+    Error,
+}
+
+/// The transfer encoding of text parts generated by the compiler.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TransferEncoding {
+    /// Use the most suitable encoding, depending on the contents:
+    /// 7bit, quoted-printable or base64.
+    #[default]
+    Auto,
+
+    /// Always use the quoted-printable encoding.
+    QuotedPrintable,
+
+    /// Always use the base64 encoding.
+    Base64,
+}
+
+/// Find the encoding matching the given charset label.
+pub(crate) fn find_encoding(charset: &str) -> Option<&'static Encoding> {
+    Encoding::for_label_no_replacement(charset.trim().as_bytes())
+}
+
+/// Return `true` if the given charset label designates UTF-8 or one
+/// of its subsets.
+pub(crate) fn is_utf8(charset: &str) -> bool {
+    let charset = charset.trim();
+    charset.eq_ignore_ascii_case("utf-8")
+        || charset.eq_ignore_ascii_case("utf8")
+        || charset.eq_ignore_ascii_case("us-ascii")
+        || charset.eq_ignore_ascii_case("ascii")
+}
+
+/// Decode the given bytes using the given encoding, applying the
+/// given replacement policy to invalid sequences.
+pub(crate) fn decode(
+    encoding: &'static Encoding,
+    bytes: &[u8],
+    replacement: CharsetReplacement,
+) -> Result<String> {
+    match replacement {
+        CharsetReplacement::Replace => {
+            Ok(encoding.decode_without_bom_handling(bytes).0.into_owned())
+        }
+        CharsetReplacement::Error => encoding
+            .decode_without_bom_handling_and_without_replacement(bytes)
+            .map(Cow::into_owned)
+            .ok_or_else(|| Error::DecodeCharsetError(encoding.name().to_owned())),
+        CharsetReplacement::Skip => {
+            let mut decoder = encoding.new_decoder_without_bom_handling();
+            let mut text = String::new();
+            let mut bytes = bytes;
+
+            loop {
+                if let Some(len) = decoder.max_utf8_buffer_length_without_replacement(bytes.len()) {
+                    text.reserve(len);
+                }
+
+                let (res, read) =
+                    decoder.decode_to_string_without_replacement(bytes, &mut text, true);
+                bytes = &bytes[read..];
+
+                match res {
+                    DecoderResult::InputEmpty => break Ok(text),
+                    DecoderResult::OutputFull => continue,
+                    DecoderResult::Malformed(..) => continue,
+                }
+            }
+        }
+    }
+}
+
+/// Encode the given text using the given encoding.
+///
+/// Characters that cannot be represented in the given encoding make
+/// the encoding fail, instead of being replaced by HTML numeric
+/// character references.
+pub(crate) fn encode(encoding: &'static Encoding, text: &str) -> Result<Vec<u8>> {
+    if encoding.output_encoding() != encoding {
+        return Err(Error::EncodeCharsetError(encoding.name().to_owned()));
+    }
+
+    match encoding.encode(text) {
+        (_, _, true) => Err(Error::EncodeCharsetError(encoding.name().to_owned())),
+        (bytes, _, false) => Ok(bytes.into_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, find_encoding, CharsetReplacement};
+
+    #[test]
+    fn decode_charsets() {
+        let latin1 = find_encoding("ISO-8859-1").unwrap();
+        let text = decode(latin1, b"caf\xe9", CharsetReplacement::Error).unwrap();
+        assert_eq!(text, "café");
+
+        let koi8 = find_encoding("koi8-r").unwrap();
+        let text = decode(koi8, b"\xf0\xd2\xc9\xd7\xc5\xd4", CharsetReplacement::Error).unwrap();
+        assert_eq!(text, "Привет");
+
+        let sjis = find_encoding("Shift_JIS").unwrap();
+        let text = decode(sjis, b"\x82\xb1\x82\xf1", CharsetReplacement::Error).unwrap();
+        assert_eq!(text, "こん");
+
+        let gbk = find_encoding("gbk").unwrap();
+        let text = decode(gbk, b"\xc4\xe3\xba\xc3", CharsetReplacement::Error).unwrap();
+        assert_eq!(text, "你好");
+    }
+
+    #[test]
+    fn decode_replacement() {
+        let utf8 = find_encoding("utf-8").unwrap();
+        let bytes = b"caf\xff!";
+
+        let text = decode(utf8, bytes, CharsetReplacement::Replace).unwrap();
+        assert_eq!(text, "caf\u{FFFD}!");
+
+        let text = decode(utf8, bytes, CharsetReplacement::Skip).unwrap();
+        assert_eq!(text, "caf!");
+
+        assert!(decode(utf8, bytes, CharsetReplacement::Error).is_err());
+    }
+
+    #[test]
+    fn encode_charsets() {
+        let sjis = find_encoding("shift_jis").unwrap();
+        assert_eq!(encode(sjis, "こん").unwrap(), b"\x82\xb1\x82\xf1");
+
+        let latin2 = find_encoding("iso-8859-2").unwrap();
+        assert!(encode(latin2, "こん").is_err());
+
+        let utf16 = find_encoding("utf-16le").unwrap();
+        assert!(encode(utf16, "hello").is_err());
+    }
+}
@@ -0,0 +1,683 @@
+//! # MML checks
+//!
+//! Module dedicated to MML body checks. Checks validate a MML body
+//! without compiling it, and return structured [Diagnostic]s with
+//! line/column spans, so that editors can surface errors live.
+
+use std::{collections::HashMap, fmt, path::PathBuf};
+
+use shellexpand_utils::shellexpand_path;
+
+#[cfg(feature = "remote")]
+use crate::message::body::URL;
+use crate::message::body::{
+    ATTACHMENT, BACKSLASH, CHARSET, CID, CREATION_DATE, DATA_ENCODING, DESCRIPTION, DISPOSITION,
+    DOUBLE_QUOTE, ENCODING, ENCODING_7BIT, ENCODING_8BIT, ENCODING_BASE64,
+    ENCODING_QUOTED_PRINTABLE, FILENAME, GREATER_THAN, INLINE, METHOD, MODIFICATION_DATE,
+    MULTIPART_BEGIN, MULTIPART_END, NAME, PART_BEGIN, READ_DATE, RECIPIENT_FILENAME, SIGNATURE,
+    SIGNATURE_ABOVE, SIGNATURE_BELOW, SIGNATURE_NONE, TYPE,
+};
+#[cfg(feature = "pgp")]
+use crate::message::body::{ENCRYPT, PGP_INLINE, PGP_MIME, SIGN};
+
+use super::parsers::{self, prelude::*};
+
+/// The properties allowed in `<#part>` tags.
+const PART_PROPS: &[&str] = &[
+    TYPE,
+    FILENAME,
+    #[cfg(feature = "remote")]
+    URL,
+    RECIPIENT_FILENAME,
+    NAME,
+    CHARSET,
+    ENCODING,
+    DATA_ENCODING,
+    CREATION_DATE,
+    MODIFICATION_DATE,
+    READ_DATE,
+    DESCRIPTION,
+    DISPOSITION,
+    CID,
+    METHOD,
+    SIGNATURE,
+    #[cfg(feature = "pgp")]
+    ENCRYPT,
+    #[cfg(feature = "pgp")]
+    SIGN,
+];
+
+/// The properties allowed in `<#multipart>` tags.
+const MULTIPART_PROPS: &[&str] = &[
+    TYPE,
+    DESCRIPTION,
+    #[cfg(feature = "pgp")]
+    ENCRYPT,
+    #[cfg(feature = "pgp")]
+    SIGN,
+];
+
+/// The severity of a [Diagnostic].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Severity {
+    /// The MML body cannot be compiled.
+    Error,
+
+    /// The MML body can be compiled, but probably not as expected.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// The kind of a [Diagnostic].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DiagnosticKind {
+    /// The property is not supported by the tag.
+    UnknownProperty(String),
+
+    /// The property is missing its `=value`.
+    MissingValue(String),
+
+    /// The property value is not supported.
+    InvalidValue(String, String),
+
+    /// The property is defined multiple times with different values.
+    ConflictingValues(String),
+
+    /// The property is defined multiple times with the same value.
+    DuplicateProperty(String),
+
+    /// The PGP property (`encrypt` or `sign`) is already defined by an
+    /// enclosing multipart.
+    NestedPgpProperty(String),
+
+    /// The PGP property (`encrypt` or `sign`) uses PGP inline on a
+    /// multipart, whereas PGP inline only applies to plain text
+    /// parts.
+    PgpInlineMultipart(String),
+
+    /// The file referenced by the `filename` property does not exist.
+    MissingFile(PathBuf),
+
+    /// The tag is not closed by `>`.
+    UnclosedTag,
+
+    /// The `<#multipart>` tag is not closed by `<#/multipart>`.
+    UnclosedMultipart,
+
+    /// The `<#/multipart>` tag does not close any `<#multipart>`.
+    UnexpectedMultipartEnd,
+
+    /// Any other syntax error, reported by the MML parser.
+    Syntax(String),
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownProperty(key) => write!(f, "unknown property {key}"),
+            Self::MissingValue(key) => write!(f, "missing value for property {key}"),
+            Self::InvalidValue(key, val) => write!(f, "invalid value {val} for property {key}"),
+            Self::ConflictingValues(key) => write!(f, "conflicting values for property {key}"),
+            Self::DuplicateProperty(key) => write!(f, "duplicate property {key}"),
+            Self::NestedPgpProperty(key) => {
+                write!(
+                    f,
+                    "property {key} already defined by an enclosing multipart"
+                )
+            }
+            Self::PgpInlineMultipart(key) => {
+                write!(f, "property {key} cannot use pgpinline on a multipart")
+            }
+            Self::MissingFile(path) => write!(f, "cannot find file {}", path.display()),
+            Self::UnclosedTag => write!(f, "unclosed tag, missing {GREATER_THAN}"),
+            Self::UnclosedMultipart => write!(f, "unclosed multipart, missing {MULTIPART_END}"),
+            Self::UnexpectedMultipartEnd => write!(f, "unexpected {MULTIPART_END}"),
+            Self::Syntax(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+/// A position in a MML message.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Position {
+    /// The byte offset, starting at 0.
+    pub offset: usize,
+
+    /// The line number, starting at 1.
+    pub line: usize,
+
+    /// The column number (in characters), starting at 1.
+    pub column: usize,
+}
+
+impl Position {
+    /// Compute the position of the given byte offset in the given
+    /// text.
+    fn new(text: &str, offset: usize) -> Self {
+        let offset = offset.min(text.len());
+        let before = &text[..offset];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or_default();
+
+        Self {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+/// A span in a MML message.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Span {
+    /// The start position (inclusive).
+    pub start: Position,
+
+    /// The end position (exclusive).
+    pub end: Position,
+}
+
+/// A diagnostic returned by MML checks.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// The severity of the diagnostic.
+    pub severity: Severity,
+
+    /// The kind of the diagnostic.
+    pub kind: DiagnosticKind,
+
+    /// The span the diagnostic applies to.
+    pub span: Span,
+}
+
+impl Diagnostic {
+    /// Return `true` if the diagnostic is an error.
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Position { line, column, .. } = self.span.start;
+        write!(f, "{line}:{column}: {}: {}", self.severity, self.kind)
+    }
+}
+
+/// A property parsed from a tag.
+struct Prop<'a> {
+    key: &'a str,
+    val: Option<String>,
+    start: usize,
+    end: usize,
+}
+
+/// The MML checker.
+struct Checker<'a> {
+    /// The whole source text, used to compute positions.
+    src: &'a str,
+
+    /// The collected diagnostics.
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+    fn push(&mut self, severity: Severity, kind: DiagnosticKind, start: usize, end: usize) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            kind,
+            span: Span {
+                start: Position::new(self.src, start),
+                end: Position::new(self.src, end),
+            },
+        });
+    }
+
+    fn error(&mut self, kind: DiagnosticKind, start: usize, end: usize) {
+        self.push(Severity::Error, kind, start, end)
+    }
+
+    fn warning(&mut self, kind: DiagnosticKind, start: usize, end: usize) {
+        self.push(Severity::Warning, kind, start, end)
+    }
+
+    /// Parse the properties of the tag starting at the given offset,
+    /// right after the tag name.
+    ///
+    /// Returns the properties and the offset right after the closing
+    /// `>`, or `None` if the tag is not closed.
+    fn parse_props(&mut self, mut offset: usize) -> Option<(Vec<Prop<'a>>, usize)> {
+        let src = self.src;
+        let mut props = Vec::new();
+
+        loop {
+            offset += src[offset..].len() - src[offset..].trim_start().len();
+
+            let rest = &src[offset..];
+
+            if rest.is_empty() {
+                return None;
+            }
+
+            if rest.starts_with(GREATER_THAN) {
+                return Some((props, offset + 1));
+            }
+
+            let key_len = rest
+                .find(|c: char| c == '=' || c == GREATER_THAN || c.is_whitespace())
+                .unwrap_or(rest.len());
+            let key = &rest[..key_len];
+            let start = offset;
+            offset += key_len;
+
+            let after_key = src[offset..].trim_start();
+
+            if !after_key.starts_with('=') {
+                props.push(Prop {
+                    key,
+                    val: None,
+                    start,
+                    end: offset,
+                });
+                continue;
+            }
+
+            offset = src.len() - after_key.len() + 1;
+            offset += src[offset..].len() - src[offset..].trim_start().len();
+
+            let (val, len) = parse_val(&src[offset..]);
+            offset += len;
+
+            props.push(Prop {
+                key,
+                val: Some(val),
+                start,
+                end: offset,
+            });
+        }
+    }
+
+    /// Check the given properties against the given allowed keys.
+    ///
+    /// Returns the valid properties, indexed by key.
+    fn check_props(
+        &mut self,
+        props: Vec<Prop<'a>>,
+        allowed: &[&str],
+    ) -> HashMap<&'a str, (String, usize, usize)> {
+        let mut valid: HashMap<&str, (String, usize, usize)> = HashMap::new();
+
+        for Prop {
+            key,
+            val,
+            start,
+            end,
+        } in props
+        {
+            if !allowed.contains(&key) {
+                self.error(DiagnosticKind::UnknownProperty(key.to_owned()), start, end);
+                continue;
+            }
+
+            let Some(val) = val else {
+                self.error(DiagnosticKind::MissingValue(key.to_owned()), start, end);
+                continue;
+            };
+
+            if let Some(allowed_vals) = allowed_vals(key) {
+                if !allowed_vals.contains(&val.as_str()) {
+                    let kind = DiagnosticKind::InvalidValue(key.to_owned(), val.clone());
+                    self.error(kind, start, end);
+                }
+            }
+
+            match valid.get(key) {
+                Some((prev, _, _)) if *prev == val => {
+                    self.warning(
+                        DiagnosticKind::DuplicateProperty(key.to_owned()),
+                        start,
+                        end,
+                    );
+                }
+                Some(_) => {
+                    self.error(
+                        DiagnosticKind::ConflictingValues(key.to_owned()),
+                        start,
+                        end,
+                    );
+                }
+                None => {
+                    valid.insert(key, (val, start, end));
+                }
+            }
+        }
+
+        valid
+    }
+
+    /// Check PGP properties against the ones of enclosing multiparts.
+    #[allow(unused_variables)]
+    fn check_pgp_props(
+        &mut self,
+        props: &HashMap<&str, (String, usize, usize)>,
+        multiparts: &[Multipart],
+        is_multipart: bool,
+    ) {
+        #[cfg(feature = "pgp")]
+        for key in [ENCRYPT, SIGN] {
+            if let Some((val, start, end)) = props.get(key) {
+                if is_multipart && val == PGP_INLINE {
+                    let kind = DiagnosticKind::PgpInlineMultipart(key.to_owned());
+                    self.warning(kind, *start, *end);
+                }
+
+                if multiparts.iter().any(|m| m.pgp_props.contains(&key)) {
+                    let kind = DiagnosticKind::NestedPgpProperty(key.to_owned());
+                    self.warning(kind, *start, *end);
+                }
+            }
+        }
+    }
+
+    /// Check the given MML body, starting at the given offset of the
+    /// source text.
+    fn check(&mut self, body_offset: usize) {
+        let src = self.src;
+        let mut multiparts: Vec<Multipart> = Vec::new();
+        let mut offset = body_offset;
+
+        while let Some(pos) = src[offset..].find("<#") {
+            let start = offset + pos;
+            let rest = &src[start..];
+
+            if rest.starts_with(MULTIPART_END) {
+                offset = start + MULTIPART_END.len();
+
+                if multiparts.pop().is_none() {
+                    self.error(DiagnosticKind::UnexpectedMultipartEnd, start, offset);
+                }
+
+                continue;
+            }
+
+            let tag = [(MULTIPART_BEGIN, true), (PART_BEGIN, false)]
+                .into_iter()
+                .find(|(tag, _)| {
+                    rest.strip_prefix(tag).is_some_and(|rest| {
+                        rest.starts_with(|c: char| c == GREATER_THAN || c.is_whitespace())
+                    })
+                });
+
+            let Some((tag, is_multipart)) = tag else {
+                offset = start + 2;
+                continue;
+            };
+
+            let Some((props, end)) = self.parse_props(start + tag.len()) else {
+                self.error(DiagnosticKind::UnclosedTag, start, start + tag.len());
+                break;
+            };
+
+            offset = end;
+
+            if is_multipart {
+                let props = self.check_props(props, MULTIPART_PROPS);
+                self.check_pgp_props(&props, &multiparts, true);
+
+                multiparts.push(Multipart {
+                    start,
+                    end,
+                    #[cfg(feature = "pgp")]
+                    pgp_props: [ENCRYPT, SIGN]
+                        .into_iter()
+                        .filter(|key| props.contains_key(key))
+                        .collect(),
+                });
+            } else {
+                let props = self.check_props(props, PART_PROPS);
+                self.check_pgp_props(&props, &multiparts, false);
+
+                if let Some((fpath, start, end)) = props.get(FILENAME) {
+                    let fpath = shellexpand_path(fpath);
+                    if !fpath.is_file() {
+                        self.error(DiagnosticKind::MissingFile(fpath), *start, *end);
+                    }
+                }
+            }
+        }
+
+        for Multipart { start, end, .. } in multiparts {
+            self.error(DiagnosticKind::UnclosedMultipart, start, end);
+        }
+    }
+}
+
+/// A multipart being checked.
+struct Multipart {
+    start: usize,
+    end: usize,
+    #[cfg(feature = "pgp")]
+    pgp_props: Vec<&'static str>,
+}
+
+/// Return the values allowed for the given property key, if the
+/// property is restricted.
+fn allowed_vals(key: &str) -> Option<&'static [&'static str]> {
+    match key {
+        DISPOSITION => Some(&[INLINE, ATTACHMENT]),
+        ENCODING => Some(&[
+            ENCODING_7BIT,
+            ENCODING_8BIT,
+            ENCODING_QUOTED_PRINTABLE,
+            ENCODING_BASE64,
+        ]),
+        DATA_ENCODING => Some(&[ENCODING_QUOTED_PRINTABLE, ENCODING_BASE64]),
+        SIGNATURE => Some(&[SIGNATURE_ABOVE, SIGNATURE_BELOW, SIGNATURE_NONE]),
+        #[cfg(feature = "pgp")]
+        ENCRYPT | SIGN => Some(&[PGP_MIME, PGP_INLINE]),
+        _ => None,
+    }
+}
+
+/// Parse a property value, either quoted or not.
+///
+/// Returns the value and its length in the given text.
+fn parse_val(text: &str) -> (String, usize) {
+    let mut val = String::new();
+    let mut chars = text.char_indices();
+
+    if text.starts_with(DOUBLE_QUOTE) {
+        chars.next();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                BACKSLASH => {
+                    if let Some((_, c)) = chars.next() {
+                        val.push(c);
+                    }
+                }
+                DOUBLE_QUOTE => return (val, i + 1),
+                c => val.push(c),
+            }
+        }
+
+        return (val, text.len());
+    }
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            BACKSLASH => {
+                if let Some((_, c)) = chars.next() {
+                    val.push(c);
+                }
+            }
+            c if c == GREATER_THAN || c.is_whitespace() => return (val, i),
+            c => val.push(c),
+        }
+    }
+
+    (val, text.len())
+}
+
+/// Check the MML body starting at the given offset of the given
+/// source text.
+///
+/// Spans of the returned diagnostics are relative to the whole source
+/// text, so that they match the MML message when the body is part of
+/// it. Syntax errors reported by the MML parser are only added when
+/// no other error has been found, to avoid duplicates.
+pub(crate) fn check(src: &str, body_offset: usize) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        src,
+        diagnostics: Vec::new(),
+    };
+
+    checker.check(body_offset);
+
+    if !checker.diagnostics.iter().any(Diagnostic::is_error) {
+        let res = parsers::parts().parse(&src[body_offset..]);
+
+        for err in res.errors() {
+            let span = err.span();
+            let kind = DiagnosticKind::Syntax(err.to_string());
+            checker.error(kind, body_offset + span.start, body_offset + span.end);
+        }
+    }
+
+    checker.diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use concat_with::concat_line;
+
+    use super::{check, DiagnosticKind, Position, Severity};
+
+    #[test]
+    fn valid() {
+        let mml = concat_line!(
+            "<#multipart type=alternative>",
+            "<#part type=text/plain>Hello<#/part>",
+            "<#part type=\"text/html\" description=\"Hello world\">Hello",
+            "<#/multipart>",
+        );
+
+        assert_eq!(check(mml, 0), vec![]);
+    }
+
+    #[test]
+    fn unknown_property() {
+        let mml = concat_line!("Hello", "<#part type=text/plain foo=bar>", "world");
+
+        let diagnostics = check(mml, 0);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(
+            diagnostics[0].kind,
+            DiagnosticKind::UnknownProperty("foo".into())
+        );
+        assert_eq!(
+            diagnostics[0].span.start,
+            Position {
+                offset: 29,
+                line: 2,
+                column: 24,
+            }
+        );
+        assert_eq!(diagnostics[0].span.end.column, 31);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "2:24: error: unknown property foo"
+        );
+    }
+
+    #[test]
+    fn invalid_values() {
+        let mml = "<#part disposition=attached encoding=base64 encoding=7bit>";
+
+        let kinds: Vec<_> = check(mml, 0).into_iter().map(|d| d.kind).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                DiagnosticKind::InvalidValue("disposition".into(), "attached".into()),
+                DiagnosticKind::ConflictingValues("encoding".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn multiparts() {
+        let mml = concat_line!("<#/multipart>", "<#multipart type=mixed>", "<#part>Hello",);
+
+        let diagnostics = check(mml, 0);
+        let kinds: Vec<_> = diagnostics.iter().map(|d| d.kind.clone()).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                DiagnosticKind::UnexpectedMultipartEnd,
+                DiagnosticKind::UnclosedMultipart,
+            ]
+        );
+        assert_eq!(diagnostics[1].span.start.line, 2);
+
+        let kinds: Vec<_> = check("<#part type=text/plain", 0)
+            .into_iter()
+            .map(|d| d.kind)
+            .collect();
+
+        assert_eq!(kinds, vec![DiagnosticKind::UnclosedTag]);
+    }
+
+    #[test]
+    fn missing_file() {
+        let mml = "<#part filename=/tmp/mml-check-missing-file.txt><#/part>";
+
+        let kinds: Vec<_> = check(mml, 0).into_iter().map(|d| d.kind).collect();
+
+        assert_eq!(
+            kinds,
+            vec![DiagnosticKind::MissingFile(
+                "/tmp/mml-check-missing-file.txt".into()
+            )]
+        );
+    }
+
+    #[cfg(feature = "pgp")]
+    #[test]
+    fn pgp() {
+        let mml = concat_line!(
+            "<#multipart type=mixed sign=pgpmime>",
+            "<#part sign=pgpmime encrypt=pgpmime encrypt=smime>Hello",
+            "<#/multipart>",
+        );
+
+        let diagnostics = check(mml, 0);
+        let kinds: Vec<_> = diagnostics.iter().map(|d| d.kind.clone()).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                DiagnosticKind::InvalidValue("encrypt".into(), "smime".into()),
+                DiagnosticKind::ConflictingValues("encrypt".into()),
+                DiagnosticKind::NestedPgpProperty("sign".into()),
+            ]
+        );
+        assert_eq!(diagnostics[2].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn escaped() {
+        let mml = "<#!part foo=bar>Hello<#!/multipart>";
+        assert_eq!(check(mml, 0), vec![]);
+    }
+}
@@ -0,0 +1,179 @@
+//! # Image downscaling
+//!
+//! Module dedicated to the image downscaling of the MML compiler.
+//! Image attachments above a configurable size are downscaled and
+//! recompressed, so that messages stay under provider limits.
+
+use std::io::Cursor;
+
+use image::{
+    codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, GenericImageView, ImageFormat,
+};
+use tracing::{debug, warn};
+
+/// The default size above which images are downscaled, in bytes (1
+/// MiB).
+pub const DEFAULT_IMAGE_MIN_SIZE: usize = 1024 * 1024;
+
+/// The default maximum width and height of downscaled images, in
+/// pixels.
+pub const DEFAULT_IMAGE_MAX_DIMENSION: u32 = 1920;
+
+/// The default quality of recompressed JPEG images, from 1 to 100.
+pub const DEFAULT_IMAGE_JPEG_QUALITY: u8 = 80;
+
+/// The image downscaling options of the MML compiler.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ImageDownscaling {
+    /// The size above which images are downscaled, in bytes.
+    pub min_size: usize,
+
+    /// The maximum width and height of downscaled images, in pixels.
+    ///
+    /// The aspect ratio is preserved.
+    pub max_dimension: u32,
+
+    /// The quality of recompressed JPEG images, from 1 to 100.
+    pub jpeg_quality: u8,
+}
+
+impl Default for ImageDownscaling {
+    fn default() -> Self {
+        Self {
+            min_size: DEFAULT_IMAGE_MIN_SIZE,
+            max_dimension: DEFAULT_IMAGE_MAX_DIMENSION,
+            jpeg_quality: DEFAULT_IMAGE_JPEG_QUALITY,
+        }
+    }
+}
+
+impl ImageDownscaling {
+    /// Downscale and recompress the given image contents.
+    ///
+    /// Returns [`None`] if the image is below the minimum size, if
+    /// its format is not supported, or if the processed image is not
+    /// smaller than the original one. The image format is preserved,
+    /// so that the attachment name and type remain valid.
+    pub(crate) fn downscale(&self, contents: &[u8]) -> Option<Vec<u8>> {
+        if contents.len() <= self.min_size {
+            return None;
+        }
+
+        let format = match image::guess_format(contents) {
+            Ok(format) => format,
+            Err(err) => {
+                debug!("cannot guess image format, skipping downscaling: {err}");
+                return None;
+            }
+        };
+
+        let img = match image::load_from_memory_with_format(contents, format) {
+            Ok(img) => img,
+            Err(err) => {
+                warn!("cannot decode {format:?} image, skipping downscaling: {err}");
+                debug!("{err:?}");
+                return None;
+            }
+        };
+
+        let (width, height) = img.dimensions();
+        let img = if width > self.max_dimension || height > self.max_dimension {
+            debug!("downscaling {format:?} image of {width}x{height} pixels");
+            img.resize(self.max_dimension, self.max_dimension, FilterType::Lanczos3)
+        } else {
+            img
+        };
+
+        let mut downscaled = Vec::new();
+
+        let encoded = match format {
+            ImageFormat::Jpeg => {
+                let encoder = JpegEncoder::new_with_quality(&mut downscaled, self.jpeg_quality);
+                DynamicImage::ImageRgb8(img.to_rgb8()).write_with_encoder(encoder)
+            }
+            format => img.write_to(&mut Cursor::new(&mut downscaled), format),
+        };
+
+        if let Err(err) = encoded {
+            warn!("cannot encode {format:?} image, skipping downscaling: {err}");
+            debug!("{err:?}");
+            return None;
+        }
+
+        if downscaled.len() >= contents.len() {
+            debug!("downscaled image is not smaller than the original one, skipping it");
+            return None;
+        }
+
+        debug!(
+            "downscaled {format:?} image from {} to {} bytes",
+            contents.len(),
+            downscaled.len()
+        );
+
+        Some(downscaled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{GenericImageView, ImageFormat, Rgb, RgbImage};
+
+    use super::ImageDownscaling;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbImage::from_fn(width, height, |x, y| {
+            Rgb([
+                (x * 7 % 256) as u8,
+                (y * 13 % 256) as u8,
+                ((x ^ y) % 256) as u8,
+            ])
+        });
+
+        let mut contents = Vec::new();
+        img.write_to(&mut Cursor::new(&mut contents), ImageFormat::Png)
+            .unwrap();
+        contents
+    }
+
+    #[test]
+    fn downscale() {
+        let opts = ImageDownscaling {
+            min_size: 0,
+            max_dimension: 40,
+            ..Default::default()
+        };
+
+        let contents = png(200, 100);
+        let downscaled = opts.downscale(&contents).unwrap();
+        assert!(downscaled.len() < contents.len());
+
+        let img = image::load_from_memory(&downscaled).unwrap();
+        assert_eq!(image::guess_format(&downscaled).unwrap(), ImageFormat::Png);
+        assert_eq!(img.dimensions(), (40, 20));
+    }
+
+    #[test]
+    fn downscale_skipped() {
+        let opts = ImageDownscaling {
+            max_dimension: 40,
+            ..Default::default()
+        };
+
+        // below the minimum size
+        assert_eq!(opts.downscale(&png(200, 100)), None);
+
+        let opts = ImageDownscaling {
+            min_size: 0,
+            ..opts
+        };
+
+        // not an image
+        assert_eq!(opts.downscale(b"Hello, world!"), None);
+
+        // already small enough
+        assert_eq!(opts.downscale(&png(20, 10)), None);
+    }
+}
@@ -2,34 +2,87 @@
 //!
 //! Module dedicated to MML → MIME message body compilation.
 
+pub(crate) mod check;
+#[cfg(feature = "image")]
+mod downscale;
 mod parsers;
-mod tokens;
+mod signature;
+mod size;
+pub(crate) mod tokens;
 
-use std::{ffi::OsStr, fs, ops::Deref};
+#[cfg(feature = "remote")]
+use std::time::Duration;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    ffi::OsStr,
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    ops::Deref,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use async_recursion::async_recursion;
+#[cfg(feature = "pgp")]
+use mail_builder::headers::HeaderType;
 use mail_builder::{
+    encoders::{base64::base64_encode_mime, quoted_printable::quoted_printable_encode},
+    headers::{content_type::ContentType, text::Text},
     mime::{BodyPart, MimePart},
     MessageBuilder,
 };
+use mail_parser::decoders::{base64::base64_decode, quoted_printable::quoted_printable_decode};
 use shellexpand_utils::shellexpand_path;
 #[allow(unused_imports)]
 use tracing::{debug, warn};
 
 #[cfg(feature = "pgp")]
-use crate::pgp::Pgp;
+use crate::pgp::{inline, Pgp};
+#[cfg(feature = "autocrypt")]
+use crate::pgp::{AutocryptHeader, PreferEncrypt};
 use crate::{Error, Result};
 
+#[cfg(feature = "markdown")]
+use super::MARKDOWN;
+#[cfg(feature = "remote")]
+use super::URL;
 use super::{
-    ALTERNATIVE, ATTACHMENT, DISPOSITION, ENCODING, ENCODING_7BIT, ENCODING_8BIT, ENCODING_BASE64,
-    ENCODING_QUOTED_PRINTABLE, FILENAME, INLINE, MIXED, MULTIPART_BEGIN, MULTIPART_BEGIN_ESCAPED,
-    MULTIPART_END, MULTIPART_END_ESCAPED, NAME, PART_BEGIN, PART_BEGIN_ESCAPED, PART_END,
-    PART_END_ESCAPED, RECIPIENT_FILENAME, RELATED, TYPE,
+    charset::{self, TransferEncoding},
+    ALTERNATIVE, ATTACHMENT, CALENDAR, CHARSET, CID, DATA_ENCODING, DESCRIPTION, DISPOSITION,
+    ENCODING, ENCODING_7BIT, ENCODING_8BIT, ENCODING_BASE64, ENCODING_QUOTED_PRINTABLE, FILENAME,
+    INLINE, METHOD, MIXED, MULTIPART_BEGIN, MULTIPART_BEGIN_ESCAPED, MULTIPART_END,
+    MULTIPART_END_ESCAPED, NAME, PART_BEGIN, PART_BEGIN_ESCAPED, PART_END, PART_END_ESCAPED,
+    RECIPIENT_FILENAME, RELATED, TYPE,
 };
 #[cfg(feature = "pgp")]
-use super::{ENCRYPT, PGP_MIME, SIGN};
+use super::{ENCRYPT, PGP_INLINE, PGP_MIME, SIGN};
+
+#[cfg(feature = "image")]
+#[doc(inline)]
+pub use self::downscale::{
+    ImageDownscaling, DEFAULT_IMAGE_JPEG_QUALITY, DEFAULT_IMAGE_MAX_DIMENSION,
+    DEFAULT_IMAGE_MIN_SIZE,
+};
+#[doc(inline)]
+pub use self::{
+    check::{Diagnostic, DiagnosticKind, Position, Severity, Span},
+    signature::{Signature, SignaturePlacement},
+    size::{ExternalAttachment, ExternalizeFn, ExternalizeResult, OversizeStrategy, SizeLimits},
+};
+use self::{
+    parsers::prelude::*,
+    signature::SignatureState,
+    tokens::{Part, Props},
+};
 
-use self::{parsers::prelude::*, tokens::Part};
+/// The default maximum size of remote parts, in bytes (10 MiB).
+#[cfg(feature = "remote")]
+pub const DEFAULT_REMOTE_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// The default timeout of remote parts downloads.
+#[cfg(feature = "remote")]
+pub const DEFAULT_REMOTE_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// MML → MIME message body compiler.
 ///
@@ -43,6 +96,22 @@ pub struct MmlBodyCompiler {
     pgp_sender: Option<String>,
     #[cfg(feature = "pgp")]
     pgp_recipients: Vec<String>,
+    #[cfg(feature = "pgp")]
+    pgp_hidden_recipients: Vec<String>,
+    #[cfg(feature = "pgp")]
+    pgp_encrypt_to_self: bool,
+    #[cfg(feature = "autocrypt")]
+    autocrypt: Option<PreferEncrypt>,
+    #[cfg(feature = "remote")]
+    remote_max_size: Option<u64>,
+    #[cfg(feature = "remote")]
+    remote_timeout: Option<Duration>,
+    size_limits: SizeLimits,
+    #[cfg(feature = "image")]
+    image_downscaling: Option<ImageDownscaling>,
+    charset: Option<String>,
+    transfer_encoding: TransferEncoding,
+    signature: Option<Signature>,
 }
 
 impl<'a> MmlBodyCompiler {
@@ -85,6 +154,294 @@ pub fn with_pgp_recipients(mut self, recipients: Vec<String>) -> Self {
         self
     }
 
+    #[cfg(feature = "pgp")]
+    pub fn with_pgp_hidden_recipients(mut self, recipients: Vec<String>) -> Self {
+        self.pgp_hidden_recipients = recipients;
+        self
+    }
+
+    /// Encrypt parts to the sender as well, so that sent copies of
+    /// encrypted messages remain readable by the sender.
+    #[cfg(feature = "pgp")]
+    pub fn set_pgp_encrypt_to_self(&mut self, encrypt_to_self: bool) {
+        self.pgp_encrypt_to_self = encrypt_to_self;
+    }
+
+    /// Encrypt parts to the sender as well, so that sent copies of
+    /// encrypted messages remain readable by the sender.
+    #[cfg(feature = "pgp")]
+    pub fn with_pgp_encrypt_to_self(mut self, encrypt_to_self: bool) -> Self {
+        self.set_pgp_encrypt_to_self(encrypt_to_self);
+        self
+    }
+
+    /// Build the recipients and the hidden recipients to encrypt
+    /// parts for.
+    ///
+    /// The sender is added to the recipients when encryption to self
+    /// is enabled. Hidden recipients that are also regular recipients
+    /// are not hidden.
+    #[cfg(feature = "pgp")]
+    fn pgp_encrypt_recipients(&self) -> (Vec<String>, Vec<String>) {
+        let mut recipients = self.pgp_recipients.clone();
+
+        if self.pgp_encrypt_to_self {
+            match &self.pgp_sender {
+                Some(sender) if !recipients.contains(sender) => recipients.push(sender.clone()),
+                Some(_) => (),
+                None => debug!("cannot encrypt part to self: missing sender"),
+            }
+        }
+
+        let hidden_recipients = self
+            .pgp_hidden_recipients
+            .iter()
+            .filter(|recipient| !recipients.contains(recipient))
+            .cloned()
+            .collect();
+
+        (recipients, hidden_recipients)
+    }
+
+    /// Enable the generation of the Autocrypt header, advertising the
+    /// given encryption preference.
+    ///
+    /// The header is generated from the secret key of the sender, and
+    /// requires the native PGP backend.
+    #[cfg(feature = "autocrypt")]
+    pub fn set_autocrypt(&mut self, prefer_encrypt: PreferEncrypt) {
+        self.autocrypt = Some(prefer_encrypt);
+    }
+
+    /// Enable the generation of the Autocrypt header.
+    ///
+    /// See [`MmlBodyCompiler::set_autocrypt`].
+    #[cfg(feature = "autocrypt")]
+    pub fn with_autocrypt(mut self, prefer_encrypt: PreferEncrypt) -> Self {
+        self.set_autocrypt(prefer_encrypt);
+        self
+    }
+
+    /// Build the Autocrypt header of the sender, if enabled.
+    #[cfg(feature = "autocrypt")]
+    pub(crate) async fn autocrypt_header(&self) -> Result<Option<AutocryptHeader>> {
+        let Some(prefer_encrypt) = self.autocrypt else {
+            return Ok(None);
+        };
+
+        let Some(sender) = &self.pgp_sender else {
+            debug!("skipping autocrypt header: missing sender");
+            return Ok(None);
+        };
+
+        match &self.pgp {
+            Some(Pgp::Native(native)) => {
+                let header = native.autocrypt_header(sender, prefer_encrypt).await?;
+                Ok(Some(header))
+            }
+            _ => {
+                debug!("skipping autocrypt header: native pgp backend required");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Set the size limits of attachments and of the whole message.
+    pub fn set_size_limits(&mut self, limits: SizeLimits) {
+        self.size_limits = limits;
+    }
+
+    /// Set the size limits of attachments and of the whole message.
+    pub fn with_size_limits(mut self, limits: SizeLimits) -> Self {
+        self.set_size_limits(limits);
+        self
+    }
+
+    /// Set the charset of inline text parts.
+    ///
+    /// Texts are encoded to the given charset, unless their part
+    /// defines its own `charset` property. Defaults to UTF-8.
+    pub fn set_charset(&mut self, charset: impl ToString) {
+        self.charset = Some(charset.to_string());
+    }
+
+    /// Set the charset of inline text parts.
+    ///
+    /// See [`MmlBodyCompiler::set_charset`].
+    pub fn with_charset(mut self, charset: impl ToString) -> Self {
+        self.set_charset(charset);
+        self
+    }
+
+    /// Set the transfer encoding of inline text parts.
+    ///
+    /// Parts defining their own `encoding` property are considered
+    /// already encoded, and are not affected by this option.
+    pub fn set_transfer_encoding(&mut self, encoding: TransferEncoding) {
+        self.transfer_encoding = encoding;
+    }
+
+    /// Set the transfer encoding of inline text parts.
+    ///
+    /// See [`MmlBodyCompiler::set_transfer_encoding`].
+    pub fn with_transfer_encoding(mut self, encoding: TransferEncoding) -> Self {
+        self.set_transfer_encoding(encoding);
+        self
+    }
+
+    /// Set the signature inserted into plain text and HTML bodies.
+    ///
+    /// The signature is inserted once per variant, into the first
+    /// body of each type. It can be moved or disabled per message
+    /// using the `signature` part property (`above`, `below` or
+    /// `none`).
+    pub fn set_signature(&mut self, signature: Signature) {
+        self.signature = Some(signature);
+    }
+
+    /// Set the signature inserted into plain text and HTML bodies.
+    ///
+    /// See [`MmlBodyCompiler::set_signature`].
+    pub fn with_signature(mut self, signature: Signature) -> Self {
+        self.set_signature(signature);
+        self
+    }
+
+    /// Insert the signature into the given body text, if the part
+    /// is a plain text or HTML body of the message.
+    fn sign_body(
+        &self,
+        props: &Props,
+        ctype: &ContentType,
+        body: impl Into<Cow<'a, str>>,
+        state: &SignatureState,
+    ) -> Cow<'a, str> {
+        let body = body.into();
+
+        let Some(signature) = &self.signature else {
+            return body;
+        };
+
+        let is_body = !props.contains_key(ENCODING)
+            && !props.contains_key(CID)
+            && props.get(DISPOSITION) != Some(&ATTACHMENT);
+
+        if !is_body {
+            return body;
+        }
+
+        if Self::is_html(ctype) {
+            match state.sign_html() {
+                Some(placement) => Cow::Owned(signature.sign_html(&body, placement)),
+                None => body,
+            }
+        } else if ctype.c_type.eq_ignore_ascii_case("text/plain") {
+            match state.sign_plain() {
+                Some(placement) => Cow::Owned(signature.sign_plain(&body, placement)),
+                None => body,
+            }
+        } else {
+            body
+        }
+    }
+
+    /// Enable the downscaling of image attachments.
+    ///
+    /// Images above the minimum size are downscaled and recompressed
+    /// before being attached. The original name and size are
+    /// recorded in the part description.
+    #[cfg(feature = "image")]
+    pub fn set_image_downscaling(&mut self, downscaling: ImageDownscaling) {
+        self.image_downscaling = Some(downscaling);
+    }
+
+    /// Enable the downscaling of image attachments.
+    ///
+    /// See [`MmlBodyCompiler::set_image_downscaling`].
+    #[cfg(feature = "image")]
+    pub fn with_image_downscaling(mut self, downscaling: ImageDownscaling) -> Self {
+        self.set_image_downscaling(downscaling);
+        self
+    }
+
+    /// Set the maximum size, in bytes, of remote parts.
+    ///
+    /// Defaults to [`DEFAULT_REMOTE_MAX_SIZE`].
+    #[cfg(feature = "remote")]
+    pub fn set_remote_max_size(&mut self, max_size: u64) {
+        self.remote_max_size = Some(max_size);
+    }
+
+    /// Set the maximum size, in bytes, of remote parts.
+    ///
+    /// Defaults to [`DEFAULT_REMOTE_MAX_SIZE`].
+    #[cfg(feature = "remote")]
+    pub fn with_remote_max_size(mut self, max_size: u64) -> Self {
+        self.set_remote_max_size(max_size);
+        self
+    }
+
+    /// Set the timeout of remote parts downloads.
+    ///
+    /// Defaults to [`DEFAULT_REMOTE_TIMEOUT`].
+    #[cfg(feature = "remote")]
+    pub fn set_remote_timeout(&mut self, timeout: Duration) {
+        self.remote_timeout = Some(timeout);
+    }
+
+    /// Set the timeout of remote parts downloads.
+    ///
+    /// Defaults to [`DEFAULT_REMOTE_TIMEOUT`].
+    #[cfg(feature = "remote")]
+    pub fn with_remote_timeout(mut self, timeout: Duration) -> Self {
+        self.set_remote_timeout(timeout);
+        self
+    }
+
+    /// Download the remote part at the given URL.
+    ///
+    /// The download fails if it takes longer than the remote timeout
+    /// or if the resource is bigger than the remote maximum size.
+    #[cfg(feature = "remote")]
+    async fn download_remote_part(&self, url: &str) -> Result<Vec<u8>> {
+        let uri: http::ureq::http::Uri = url
+            .parse()
+            .map_err(|err| Error::ParseRemotePartUrlError(err, url.to_owned()))?;
+        let timeout = self.remote_timeout.unwrap_or(DEFAULT_REMOTE_TIMEOUT);
+        let max_size = self.remote_max_size.unwrap_or(DEFAULT_REMOTE_MAX_SIZE);
+
+        debug!("downloading remote part at {url}");
+
+        let res = http::Client::new()
+            .send(move |agent| {
+                agent
+                    .get(uri)
+                    .config()
+                    .timeout_global(Some(timeout))
+                    .build()
+                    .call()
+            })
+            .await
+            .map_err(|err| Error::DownloadRemotePartError(err, url.to_owned()))?;
+
+        res.into_body()
+            .into_with_config()
+            .limit(max_size)
+            .read_to_vec()
+            .map_err(|err| Error::ReadRemotePartError(err, url.to_owned()))
+    }
+
+    /// Extract the file name of the given remote part URL.
+    ///
+    /// The file name is the last non-empty segment of the URL path.
+    #[cfg(feature = "remote")]
+    fn remote_file_name(url: &str) -> Option<&str> {
+        let url = url.split(['?', '#']).next()?;
+        let url = url.split_once("://").map_or(url, |(_, url)| url);
+        let (_, path) = url.split_once('/')?;
+        path.rsplit('/').next().filter(|name| !name.is_empty())
+    }
+
     /// Encrypt the given MIME part using PGP.
     #[cfg(feature = "pgp")]
     async fn encrypt_part(&self, clear_part: &MimePart<'a>) -> Result<MimePart<'a>> {
@@ -94,7 +451,7 @@ async fn encrypt_part(&self, clear_part: &MimePart<'a>) -> Result<MimePart<'a>>
                 Ok(clear_part.clone())
             }
             Some(pgp) => {
-                let recipients = self.pgp_recipients.clone();
+                let (recipients, hidden_recipients) = self.pgp_encrypt_recipients();
 
                 let mut clear_part_bytes = Vec::new();
                 clear_part
@@ -102,7 +459,9 @@ async fn encrypt_part(&self, clear_part: &MimePart<'a>) -> Result<MimePart<'a>>
                     .write_part(&mut clear_part_bytes)
                     .map_err(Error::WriteCompiledPartToVecError)?;
 
-                let encrypted_part_bytes = pgp.encrypt(recipients, clear_part_bytes).await?;
+                let encrypted_part_bytes = pgp
+                    .encrypt_with_hidden_recipients(recipients, hidden_recipients, clear_part_bytes)
+                    .await?;
                 let encrypted_part_bytes =
                     encrypted_part_bytes
                         .into_iter()
@@ -207,6 +566,148 @@ async fn try_sign_part(&self, clear_part: MimePart<'a>) -> MimePart<'a> {
         }
     }
 
+    /// Return the text contents of the given `text/plain` MIME part.
+    ///
+    /// Return `None` if the part is not a UTF-8 plain text part.
+    #[cfg(feature = "pgp")]
+    fn get_plain_text_contents(part: &MimePart<'a>) -> Option<String> {
+        let ctype = part.headers.iter().find_map(|(key, val)| match val {
+            HeaderType::ContentType(ctype) if key.eq_ignore_ascii_case("Content-Type") => {
+                Some(ctype)
+            }
+            _ => None,
+        })?;
+
+        if !ctype.c_type.eq_ignore_ascii_case("text/plain") {
+            return None;
+        }
+
+        let charset = ctype
+            .attributes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(CHARSET));
+
+        if charset.is_some_and(|(_, charset)| !charset::is_utf8(charset)) {
+            return None;
+        }
+
+        let text = match &part.contents {
+            BodyPart::Text(text) => text.to_string(),
+            BodyPart::Binary(bytes) => String::from_utf8(bytes.to_vec()).ok()?,
+            BodyPart::Multipart(_) => return None,
+        };
+
+        Some(text.replace('\r', ""))
+    }
+
+    /// Replace the text contents of the given `text/plain` MIME part.
+    #[cfg(feature = "pgp")]
+    fn set_plain_text_contents(mut part: MimePart<'a>, text: String) -> MimePart<'a> {
+        part.contents = match part.contents {
+            BodyPart::Binary(_) => BodyPart::Binary(text.into_bytes().into()),
+            _ => BodyPart::Text(text.into()),
+        };
+        part
+    }
+
+    /// Encrypt the given `text/plain` MIME part using PGP inline.
+    ///
+    /// The text is replaced by the ASCII-armored encrypted text. Other
+    /// parts are returned as they are.
+    #[cfg(feature = "pgp")]
+    async fn encrypt_part_inline(&self, clear_part: MimePart<'a>) -> Result<MimePart<'a>> {
+        let Some(pgp) = &self.pgp else {
+            debug!("cannot encrypt part: pgp not configured");
+            return Ok(clear_part);
+        };
+
+        let Some(text) = Self::get_plain_text_contents(&clear_part) else {
+            debug!("cannot encrypt non plain text part using pgp inline");
+            return Ok(clear_part);
+        };
+
+        let (recipients, hidden_recipients) = self.pgp_encrypt_recipients();
+        let encrypted_bytes = pgp
+            .encrypt_with_hidden_recipients(recipients, hidden_recipients, text.into_bytes())
+            .await?;
+        let encrypted_text = String::from_utf8_lossy(&encrypted_bytes).replace('\r', "");
+
+        Ok(Self::set_plain_text_contents(clear_part, encrypted_text))
+    }
+
+    /// Try to encrypt the given `text/plain` MIME part using PGP
+    /// inline.
+    ///
+    /// If the operation fails, log a warning and return the original
+    /// MIME part.
+    #[cfg(feature = "pgp")]
+    async fn try_encrypt_part_inline(&self, clear_part: MimePart<'a>) -> MimePart<'a> {
+        match self.encrypt_part_inline(clear_part.clone()).await {
+            Ok(encrypted_part) => encrypted_part,
+            Err(err) => {
+                debug!("cannot encrypt email part using pgp inline: {err}");
+                debug!("{err:?}");
+                clear_part
+            }
+        }
+    }
+
+    /// Sign the given `text/plain` MIME part using PGP inline.
+    ///
+    /// The text is replaced by a cleartext signed message, as defined
+    /// in [RFC 4880]. Other parts are returned as they are.
+    ///
+    /// [RFC 4880]: https://www.rfc-editor.org/rfc/rfc4880#section-7
+    #[cfg(feature = "pgp")]
+    async fn sign_part_inline(&self, clear_part: MimePart<'a>) -> Result<MimePart<'a>> {
+        let Some(pgp) = &self.pgp else {
+            debug!("cannot sign part: pgp not configured");
+            return Ok(clear_part);
+        };
+
+        let Some(text) = Self::get_plain_text_contents(&clear_part) else {
+            debug!("cannot sign non plain text part using pgp inline");
+            return Ok(clear_part);
+        };
+
+        let sender = self
+            .pgp_sender
+            .as_ref()
+            .ok_or(Error::PgpSignMissingSenderError)?;
+
+        let data = inline::cleartext_signed_data(&text);
+        let signature_bytes = pgp.sign(sender, data).await?;
+        let signature = String::from_utf8_lossy(&signature_bytes).replace('\r', "");
+
+        // some backends directly produce cleartext signed messages
+        let signed_text = if signature
+            .trim_start()
+            .starts_with(inline::SIGNED_MESSAGE_BEGIN)
+        {
+            signature
+        } else {
+            inline::build_signed_message(&text, &signature)
+        };
+
+        Ok(Self::set_plain_text_contents(clear_part, signed_text))
+    }
+
+    /// Try to sign the given `text/plain` MIME part using PGP inline.
+    ///
+    /// If the operation fails, log a warning and return the original
+    /// MIME part.
+    #[cfg(feature = "pgp")]
+    async fn try_sign_part_inline(&self, clear_part: MimePart<'a>) -> MimePart<'a> {
+        match self.sign_part_inline(clear_part.clone()).await {
+            Ok(signed_part) => signed_part,
+            Err(err) => {
+                debug!("cannot sign email part using pgp inline: {err}");
+                debug!("{err:?}");
+                clear_part
+            }
+        }
+    }
+
     /// Replace escaped opening and closing tags by normal opening and
     /// closing tags.
     fn unescape_mml_markup(text: impl AsRef<str>) -> String {
@@ -217,85 +718,698 @@ fn unescape_mml_markup(text: impl AsRef<str>) -> String {
             .replace(MULTIPART_END_ESCAPED, MULTIPART_END)
     }
 
-    /// Compile given parts parsed from a MML body to a
-    /// [MessageBuilder].
-    async fn compile_parts(&'a self, parts: Vec<Part<'a>>) -> Result<MessageBuilder> {
-        let mut builder = MessageBuilder::new();
+    /// Return `true` if the given content type is a calendar one.
+    fn is_calendar(ctype: &ContentType) -> bool {
+        ctype.c_type.eq_ignore_ascii_case(CALENDAR)
+    }
 
-        builder = match parts.len() {
-            0 => builder.text_body(String::new()),
-            1 => builder.body(self.compile_part(parts.into_iter().next().unwrap()).await?),
-            _ => {
-                let mut compiled_parts = Vec::new();
+    /// Compile the given calendar part contents to a [MimePart].
+    ///
+    /// The contents need to be a valid iCalendar object containing
+    /// at least one component. The iTIP method is taken from the
+    /// `method` property, or from the iCalendar object itself. When
+    /// both are given, they need to match. The method and the charset
+    /// are added as parameters of the Content-Type, and line endings
+    /// are normalized to CRLF as required by [RFC 5545].
+    ///
+    /// [RFC 5545]: https://www.rfc-editor.org/rfc/rfc5545#section-3.1
+    fn compile_calendar_part(
+        props: &Props<'a>,
+        mut ctype: ContentType<'a>,
+        contents: &[u8],
+    ) -> Result<MimePart<'a>> {
+        let ics = std::str::from_utf8(contents).map_err(Error::ParseCalendarPartUtf8Error)?;
+        let lines: Vec<&str> = ics
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .filter(|line| !line.trim().is_empty())
+            .collect();
 
-                for part in parts {
-                    let part = self.compile_part(part).await?;
-                    compiled_parts.push(part);
-                }
+        let is_first_vcalendar = lines
+            .first()
+            .is_some_and(|line| line.eq_ignore_ascii_case("BEGIN:VCALENDAR"));
+        let is_last_vcalendar = lines
+            .last()
+            .is_some_and(|line| line.eq_ignore_ascii_case("END:VCALENDAR"));
+
+        if !is_first_vcalendar || !is_last_vcalendar {
+            let reason = "content should start with BEGIN:VCALENDAR and end with END:VCALENDAR";
+            return Err(Error::InvalidCalendarPartError(reason.to_owned()));
+        }
+
+        let has_component = lines.iter().any(|line| {
+            let line = line.to_ascii_uppercase();
+            line.starts_with("BEGIN:V") && line != "BEGIN:VCALENDAR" && line != "BEGIN:VTIMEZONE"
+        });
 
-                builder.body(MimePart::new("multipart/mixed", compiled_parts))
+        if !has_component {
+            let reason = "content should contain at least one component";
+            return Err(Error::InvalidCalendarPartError(reason.to_owned()));
+        }
+
+        // only top-level properties are considered, the ones placed
+        // before the first component
+        let ics_method = lines
+            .iter()
+            .skip(1)
+            .take_while(|line| !line.to_ascii_uppercase().starts_with("BEGIN:"))
+            .find_map(|line| {
+                let (key, val) = line.split_once(':')?;
+                key.eq_ignore_ascii_case(METHOD).then_some(val.trim())
+            });
+
+        let method = match (props.get(METHOD), ics_method) {
+            (Some(method), Some(ics_method)) if !method.eq_ignore_ascii_case(ics_method) => {
+                let reason = format!("method {method} does not match calendar method {ics_method}");
+                return Err(Error::InvalidCalendarPartError(reason));
             }
+            (Some(method), _) => Some(method.to_uppercase()),
+            (None, ics_method) => ics_method.map(str::to_uppercase),
         };
 
-        Ok(builder)
+        let mut ics = String::new();
+
+        for (i, line) in lines.iter().enumerate() {
+            ics.push_str(line);
+            ics.push_str("\r\n");
+
+            // iTIP requires the METHOD property to be set when the
+            // Content-Type has a method parameter
+            if i == 0 && ics_method.is_none() {
+                if let Some(method) = &method {
+                    ics.push_str(&format!("METHOD:{method}\r\n"));
+                }
+            }
+        }
+
+        if let Some(method) = method {
+            ctype = ctype.attribute(METHOD, method);
+        }
+
+        if !ctype
+            .attributes
+            .iter()
+            .any(|(key, _)| key.eq_ignore_ascii_case("charset"))
+        {
+            ctype = ctype.attribute("charset", "utf-8");
+        }
+
+        Ok(MimePart::new(ctype, ics))
     }
 
-    /// Compile the given part parsed from MML body to a [MimePart].
-    #[async_recursion]
-    async fn compile_part(&'a self, part: Part<'a>) -> Result<MimePart> {
-        match part {
-            Part::Multi(props, parts) => {
-                let no_parts = BodyPart::Multipart(Vec::new());
+    /// Return `true` if the given part properties describe a Markdown
+    /// part that should be rendered.
+    ///
+    /// Markdown parts sent as attachment are kept as it is.
+    #[cfg(feature = "markdown")]
+    fn is_markdown(props: &Props) -> bool {
+        let is_markdown = props
+            .get(TYPE)
+            .is_some_and(|ctype| ctype.eq_ignore_ascii_case(MARKDOWN));
+        let is_attachment = props.get(DISPOSITION) == Some(&ATTACHMENT);
+        is_markdown && !is_attachment
+    }
 
-                let mut multi_part = match props.get(TYPE) {
-                    Some(&MIXED) | None => MimePart::new("multipart/mixed", no_parts),
-                    Some(&ALTERNATIVE) => MimePart::new("multipart/alternative", no_parts),
-                    Some(&RELATED) => MimePart::new("multipart/related", no_parts),
-                    Some(unknown) => {
-                        debug!("unknown multipart type {unknown}, falling back to mixed");
-                        MimePart::new("multipart/mixed", no_parts)
-                    }
-                };
+    /// Compile the given Markdown to a [MimePart].
+    ///
+    /// The Markdown is rendered to HTML, then both the original
+    /// Markdown (as plain text) and the HTML are wrapped into a
+    /// `multipart/alternative` part, so that recipients can read the
+    /// message whatever their mail client.
+    #[cfg(feature = "markdown")]
+    fn compile_markdown_part(
+        markdown: String,
+        content_ids: &HashMap<String, String>,
+    ) -> MimePart<'a> {
+        use pulldown_cmark::{html, Options, Parser};
 
-                for part in parts {
-                    multi_part.add_part(self.compile_part(part).await?)
-                }
+        let opts = Options::ENABLE_TABLES
+            | Options::ENABLE_FOOTNOTES
+            | Options::ENABLE_STRIKETHROUGH
+            | Options::ENABLE_TASKLISTS;
 
-                #[cfg(feature = "pgp")]
-                {
-                    multi_part = match props.get(SIGN) {
-                        Some(&PGP_MIME) => self.try_sign_part(multi_part).await,
-                        _ => multi_part,
-                    };
+        let mut html = String::new();
+        html::push_html(&mut html, Parser::new_ext(&markdown, opts));
+        let html = Self::rewrite_content_ids(&html, content_ids);
 
-                    multi_part = match props.get(ENCRYPT) {
-                        Some(&PGP_MIME) => self.try_encrypt_part(multi_part).await,
-                        _ => multi_part,
-                    };
+        MimePart::new(
+            "multipart/alternative",
+            vec![
+                MimePart::new("text/plain", markdown),
+                MimePart::new("text/html", html),
+            ],
+        )
+    }
+
+    /// Return `true` if the given content type is an HTML one.
+    fn is_html(ctype: &ContentType) -> bool {
+        ctype.c_type.eq_ignore_ascii_case("text/html")
+    }
+
+    /// Return `true` if the given content type is a text one.
+    fn is_text(ctype: &ContentType) -> bool {
+        ctype.c_type.starts_with("text/")
+    }
+
+    /// Return `true` if the given part has a `cid` property.
+    fn has_cid(part: &Part) -> bool {
+        matches!(part, Part::Single(props, _) if props.contains_key(CID))
+    }
+
+    /// Generate the Content-ID of the given `cid` property.
+    ///
+    /// A `cid` containing `@` is used as it is. Otherwise a suffix is
+    /// generated from the given source (the part filename or body),
+    /// so that the Content-ID is unique.
+    fn generate_content_id(cid: &str, source: &str) -> String {
+        if cid.contains('@') {
+            return cid.to_owned();
+        }
+
+        let mut hasher = DefaultHasher::new();
+        cid.hash(&mut hasher);
+        source.hash(&mut hasher);
+
+        format!("{cid}.{:016x}@mml", hasher.finish())
+    }
+
+    /// Collect Content-IDs of the given sibling parts having a `cid`
+    /// property.
+    ///
+    /// Content-IDs are indexed by `cid` and by filename (both the
+    /// full path and the file name only), so that HTML parts can
+    /// refer to inline parts either with `cid:logo` or `image.png`.
+    fn collect_content_ids(parts: &[Part], content_ids: &mut HashMap<String, String>) {
+        for part in parts {
+            let Part::Single(props, body) = part else {
+                continue;
+            };
+
+            let Some(cid) = props.get(CID) else {
+                continue;
+            };
+
+            let fpath = props.get(FILENAME);
+            let content_id = Self::generate_content_id(cid, fpath.unwrap_or(body));
+
+            if let Some(fpath) = fpath {
+                let fname = Path::new(fpath).file_name().and_then(OsStr::to_str);
+                if let Some(fname) = fname {
+                    content_ids.insert(fname.to_owned(), content_id.clone());
                 }
+                content_ids.insert(fpath.to_string(), content_id.clone());
+            }
 
-                Ok(multi_part)
+            content_ids.insert(cid.to_string(), content_id);
+        }
+    }
+
+    /// Rewrite references to inline parts of the given HTML.
+    ///
+    /// Both `cid:` URLs using the `cid` property and `src` attributes
+    /// using the filename of inline parts are rewritten to `cid:`
+    /// URLs using the generated Content-ID.
+    fn rewrite_content_ids(html: &str, content_ids: &HashMap<String, String>) -> String {
+        let mut html = html.to_owned();
+
+        for (key, content_id) in content_ids {
+            for quote in ['"', '\''] {
+                html = html
+                    .replace(
+                        &format!("{quote}cid:{key}{quote}"),
+                        &format!("{quote}cid:{content_id}{quote}"),
+                    )
+                    .replace(
+                        &format!("src={quote}{key}{quote}"),
+                        &format!("src={quote}cid:{content_id}{quote}"),
+                    );
             }
-            Part::Single(ref props, body) => {
-                let fpath = props.get(FILENAME).map(shellexpand_path);
+        }
 
-                let mut part = match &fpath {
-                    Some(fpath) => {
+        html
+    }
+
+    /// Compile the given HTML contents to a [MimePart], rewriting
+    /// references to inline parts.
+    fn compile_html_part(
+        ctype: ContentType<'a>,
+        contents: Vec<u8>,
+        content_ids: &HashMap<String, String>,
+    ) -> MimePart<'a> {
+        if content_ids.is_empty() {
+            return MimePart::new(ctype, contents);
+        }
+
+        match String::from_utf8(contents) {
+            Ok(html) => MimePart::new(ctype, Self::rewrite_content_ids(&html, content_ids)),
+            Err(err) => {
+                debug!("cannot rewrite content ids of non UTF-8 HTML part: {err}");
+                MimePart::new(ctype, err.into_bytes())
+            }
+        }
+    }
+
+    /// Compile the given text to a [MimePart], using the charset and
+    /// the transfer encoding of the compiler.
+    ///
+    /// The charset of the given content type, when defined, takes
+    /// precedence over the charset of the compiler.
+    fn compile_text_part(
+        &self,
+        mut ctype: ContentType<'a>,
+        text: impl Into<Cow<'a, str>>,
+    ) -> Result<MimePart<'a>> {
+        let text = text.into();
+
+        let charset = ctype
+            .attributes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(CHARSET))
+            .map(|(_, charset)| charset.to_string());
+
+        let charset = match (charset, &self.charset) {
+            (Some(charset), _) => charset,
+            (None, Some(charset)) => {
+                ctype = ctype.attribute(CHARSET, charset.clone());
+                charset.clone()
+            }
+            (None, None) if self.transfer_encoding == TransferEncoding::Auto => {
+                // mail-builder adds the UTF-8 charset by itself
+                return Ok(MimePart::new(ctype, BodyPart::Text(text)));
+            }
+            (None, None) => {
+                ctype = ctype.attribute(CHARSET, "utf-8");
+                String::from("utf-8")
+            }
+        };
+
+        let body = if charset::is_utf8(&charset) {
+            match text {
+                Cow::Borrowed(text) => Cow::Borrowed(text.as_bytes()),
+                Cow::Owned(text) => Cow::Owned(text.into_bytes()),
+            }
+        } else {
+            let encoding = charset::find_encoding(&charset)
+                .ok_or_else(|| Error::FindCharsetError(charset.clone()))?;
+            Cow::Owned(charset::encode(encoding, &text)?)
+        };
+
+        let mut encoded = Vec::new();
+
+        let part = match self.transfer_encoding {
+            TransferEncoding::Auto => MimePart::new(ctype, BodyPart::Binary(body)),
+            TransferEncoding::QuotedPrintable => {
+                quoted_printable_encode(&body, &mut encoded, false, true)
+                    .map_err(|err| Error::EncodeTextPartError(err, ENCODING_QUOTED_PRINTABLE))?;
+                MimePart::new(ctype, encoded).transfer_encoding(ENCODING_QUOTED_PRINTABLE)
+            }
+            TransferEncoding::Base64 => {
+                base64_encode_mime(&body, &mut encoded, false)
+                    .map_err(|err| Error::EncodeTextPartError(err, ENCODING_BASE64))?;
+                MimePart::new(ctype, encoded).transfer_encoding(ENCODING_BASE64)
+            }
+        };
+
+        Ok(part)
+    }
+
+    /// Decode the given part data using the given `data-encoding`
+    /// property.
+    fn decode_part_data(encoding: &str, data: &str) -> Result<Vec<u8>> {
+        let data = match encoding {
+            ENCODING_BASE64 => base64_decode(data.trim().as_bytes()),
+            ENCODING_QUOTED_PRINTABLE => quoted_printable_decode(data.as_bytes()),
+            _ => None,
+        };
+
+        data.ok_or_else(|| Error::DecodePartDataError(encoding.to_owned()))
+    }
+
+    /// Add the given body size to the total message size, and check
+    /// it against the message size limit.
+    fn check_body_size(&self, size: usize, total_size: &AtomicUsize) -> Result<()> {
+        let total = total_size.fetch_add(size, Ordering::Relaxed) + size;
+
+        match self.size_limits.message_max_size {
+            Some(max) if total > max => Err(Error::MessageTooLargeError(total, max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the size of the given attachment against the size
+    /// limits.
+    ///
+    /// If the attachment fits, its size is added to the total message
+    /// size and [`None`] is returned. Otherwise, depending on the
+    /// [`OversizeStrategy`], either an error is returned or the
+    /// attachment is externalized and the [`Some`] URL is returned.
+    async fn check_attachment_size(
+        &self,
+        filename: &str,
+        content_type: &str,
+        contents: &[u8],
+        total_size: &AtomicUsize,
+    ) -> Result<Option<String>> {
+        let limits = &self.size_limits;
+        let size = contents.len();
+        let total = total_size.load(Ordering::Relaxed) + size::base64_size(size);
+
+        let attachment_max_size = limits.attachment_max_size.filter(|max| size > *max);
+        let message_max_size = limits.message_max_size.filter(|max| total > *max);
+
+        match (&limits.strategy, attachment_max_size, message_max_size) {
+            (_, None, None) => {
+                total_size.store(total, Ordering::Relaxed);
+                Ok(None)
+            }
+            (OversizeStrategy::Error, Some(max), _) => Err(Error::AttachmentTooLargeError(
+                filename.to_owned(),
+                size,
+                max,
+            )),
+            (OversizeStrategy::Error, None, Some(max)) => {
+                Err(Error::MessageTooLargeError(total, max))
+            }
+            (OversizeStrategy::Externalize(externalize), _, _) => {
+                debug!("externalizing attachment {filename} of {size} bytes");
+
+                let attachment = ExternalAttachment {
+                    filename: filename.to_owned(),
+                    content_type: content_type.to_owned(),
+                    contents: contents.to_owned(),
+                };
+
+                let url = externalize(attachment)
+                    .await
+                    .map_err(|err| Error::ExternalizeAttachmentError(err, filename.to_owned()))?;
+
+                Ok(Some(url))
+            }
+        }
+    }
+
+    /// Compile the link part replacing an externalized attachment.
+    fn compile_external_part(filename: &str, size: usize, url: &str) -> MimePart<'a> {
+        let text = format!(
+            "The attachment {filename} ({size} bytes) was too large to be sent by email.\r\nIt can be downloaded at: {url}\r\n"
+        );
+
+        MimePart::new("text/plain", text).inline()
+    }
+
+    /// Compile the given parts into a `multipart/mixed` [MimePart].
+    ///
+    /// Inline parts having a `cid` property are grouped together
+    /// with the first other part (usually the HTML body) into a
+    /// `multipart/related` part, so that mail clients can resolve
+    /// `cid:` references.
+    async fn compile_mixed_parts(
+        &'a self,
+        parts: Vec<Part<'a>>,
+        content_ids: &HashMap<String, String>,
+        total_size: &AtomicUsize,
+        signature: &SignatureState,
+    ) -> Result<MimePart> {
+        let mut related_parts = Vec::new();
+        let mut other_parts = Vec::new();
+
+        for part in parts {
+            if Self::has_cid(&part) {
+                let part = self
+                    .compile_part(part, content_ids, total_size, signature)
+                    .await?;
+                related_parts.push(part);
+            } else {
+                let part = self
+                    .compile_part(part, content_ids, total_size, signature)
+                    .await?;
+                other_parts.push(part);
+            }
+        }
+
+        if related_parts.is_empty() {
+            return Ok(MimePart::new("multipart/mixed", other_parts));
+        }
+
+        let mut other_parts = other_parts.into_iter();
+
+        if let Some(root_part) = other_parts.next() {
+            related_parts.insert(0, root_part);
+        }
+
+        let related_part = MimePart::new("multipart/related", related_parts);
+        let mut other_parts: Vec<_> = other_parts.collect();
+
+        if other_parts.is_empty() {
+            Ok(related_part)
+        } else {
+            other_parts.insert(0, related_part);
+            Ok(MimePart::new("multipart/mixed", other_parts))
+        }
+    }
+
+    /// Compile given parts parsed from a MML body to a
+    /// [MessageBuilder].
+    pub(crate) async fn compile_parts(&'a self, parts: Vec<Part<'a>>) -> Result<MessageBuilder> {
+        let mut builder = MessageBuilder::new();
+
+        let mut content_ids = HashMap::new();
+        Self::collect_content_ids(&parts, &mut content_ids);
+
+        let total_size = AtomicUsize::new(0);
+        let signature = SignatureState::new(self.signature.as_ref(), &parts);
+
+        builder = match parts.len() {
+            0 => {
+                let part = self
+                    .compile_part(Part::PlainText(""), &content_ids, &total_size, &signature)
+                    .await?;
+                builder.body(part)
+            }
+            1 => {
+                let part = parts.into_iter().next().unwrap();
+                let part = self
+                    .compile_part(part, &content_ids, &total_size, &signature)
+                    .await?;
+                builder.body(part)
+            }
+            _ => {
+                let part = self
+                    .compile_mixed_parts(parts, &content_ids, &total_size, &signature)
+                    .await?;
+                builder.body(part)
+            }
+        };
+
+        Ok(builder)
+    }
+
+    /// Compile the given part parsed from MML body to a [MimePart].
+    #[async_recursion]
+    async fn compile_part(
+        &'a self,
+        part: Part<'a>,
+        content_ids: &HashMap<String, String>,
+        total_size: &AtomicUsize,
+        signature: &SignatureState,
+    ) -> Result<MimePart> {
+        match part {
+            Part::Multi(props, parts) => {
+                let no_parts = BodyPart::Multipart(Vec::new());
+
+                let mut content_ids = content_ids.clone();
+                Self::collect_content_ids(&parts, &mut content_ids);
+
+                let multi_part = match props.get(TYPE) {
+                    Some(&MIXED) | None => None,
+                    Some(&ALTERNATIVE) => Some(MimePart::new("multipart/alternative", no_parts)),
+                    Some(&RELATED) => Some(MimePart::new("multipart/related", no_parts)),
+                    Some(stype) => Some(MimePart::new(format!("multipart/{stype}"), no_parts)),
+                };
+
+                let mut multi_part = match multi_part {
+                    Some(mut multi_part) => {
+                        for part in parts {
+                            let part = self
+                                .compile_part(part, &content_ids, total_size, signature)
+                                .await?;
+                            multi_part.add_part(part)
+                        }
+                        multi_part
+                    }
+                    None => {
+                        self.compile_mixed_parts(parts, &content_ids, total_size, signature)
+                            .await?
+                    }
+                };
+
+                #[cfg(feature = "pgp")]
+                {
+                    multi_part = match props.get(SIGN) {
+                        Some(&PGP_MIME) => self.try_sign_part(multi_part).await,
+                        _ => multi_part,
+                    };
+
+                    multi_part = match props.get(ENCRYPT) {
+                        Some(&PGP_MIME) => self.try_encrypt_part(multi_part).await,
+                        _ => multi_part,
+                    };
+                }
+
+                Ok(multi_part)
+            }
+            #[cfg(feature = "markdown")]
+            Part::Single(ref props, body) if Self::is_markdown(props) => {
+                let fpath = props.get(FILENAME).map(shellexpand_path);
+
+                let markdown = match &fpath {
+                    Some(fpath) => {
                         let contents = fs::read(fpath)
                             .map_err(|err| Error::ReadAttachmentError(err, fpath.clone()))?;
+                        String::from_utf8(contents).map_err(Error::ParseMarkdownPartUtf8Error)?
+                    }
+                    None => body.to_owned(),
+                };
+
+                self.check_body_size(markdown.len(), total_size)?;
+
+                #[allow(unused_mut)]
+                let mut part = Self::compile_markdown_part(markdown, content_ids);
+
+                #[cfg(feature = "pgp")]
+                {
+                    part = match props.get(SIGN) {
+                        Some(&PGP_MIME) => self.try_sign_part(part).await,
+                        _ => part,
+                    };
+
+                    part = match props.get(ENCRYPT) {
+                        Some(&PGP_MIME) => self.try_encrypt_part(part).await,
+                        _ => part,
+                    };
+                };
+
+                Ok(part)
+            }
+            Part::Single(ref props, body) => {
+                let fpath = props.get(FILENAME).map(shellexpand_path);
+
+                let contents = match &fpath {
+                    Some(fpath) => Some(
+                        fs::read(fpath)
+                            .map_err(|err| Error::ReadAttachmentError(err, fpath.clone()))?,
+                    ),
+                    None => None,
+                };
+
+                #[cfg(feature = "remote")]
+                let contents = match (contents, props.get(URL)) {
+                    (None, Some(url)) => Some(self.download_remote_part(url).await?),
+                    (contents, _) => contents,
+                };
+
+                let contents = match (contents, props.get(DATA_ENCODING)) {
+                    (None, Some(encoding)) => Some(Self::decode_part_data(encoding, body)?),
+                    (contents, _) => contents,
+                };
+
+                #[allow(unused_mut)]
+                let mut description = props.get(DESCRIPTION).map(ToString::to_string);
+
+                let contents = match contents {
+                    Some(contents) => {
+                        let filename = props
+                            .get(RECIPIENT_FILENAME)
+                            .copied()
+                            .or_else(|| match &fpath {
+                                Some(fpath) => fpath.file_name().and_then(OsStr::to_str),
+                                #[cfg(feature = "remote")]
+                                None => props.get(URL).and_then(|url| Self::remote_file_name(url)),
+                                #[cfg(not(feature = "remote"))]
+                                None => None,
+                            })
+                            .unwrap_or("noname");
+                        let ctype: ContentType =
+                            Part::get_or_guess_content_type(props, &contents).into();
+                        #[cfg(feature = "image")]
+                        let contents = match &self.image_downscaling {
+                            Some(opts) if ctype.c_type.starts_with("image/") => {
+                                match opts.downscale(&contents) {
+                                    Some(downscaled) => {
+                                        let size = contents.len();
+                                        let note = format!("original: {filename}, {size} bytes");
+                                        description = Some(match description {
+                                            Some(description) => format!("{description} ({note})"),
+                                            None => note,
+                                        });
+                                        downscaled
+                                    }
+                                    None => contents,
+                                }
+                            }
+                            _ => contents,
+                        };
+                        let external_url = self
+                            .check_attachment_size(filename, &ctype.c_type, &contents, total_size)
+                            .await?;
+                        if let Some(url) = external_url {
+                            return Ok(Self::compile_external_part(filename, contents.len(), &url));
+                        }
+                        Some(contents)
+                    }
+                    None => {
+                        self.check_body_size(body.len(), total_size)?;
+                        None
+                    }
+                };
+
+                let mut part = match contents {
+                    Some(contents) => {
                         let mut ctype = Part::get_or_guess_content_type(props, &contents).into();
+                        if let Some(charset) = props.get(CHARSET) {
+                            ctype = ctype.attribute("charset", *charset);
+                        }
                         if let Some(name) = props.get(NAME) {
                             ctype = ctype.attribute("name", *name);
                         }
-                        MimePart::new(ctype, contents)
+                        if Self::is_calendar(&ctype) {
+                            Self::compile_calendar_part(props, ctype, &contents)?
+                        } else if Self::is_html(&ctype) {
+                            Self::compile_html_part(ctype, contents, content_ids)
+                        } else {
+                            MimePart::new(ctype, contents)
+                        }
                     }
                     None => {
                         let mut ctype =
                             Part::get_or_guess_content_type(props, body.as_bytes()).into();
+                        if let Some(charset) = props.get(CHARSET) {
+                            ctype = ctype.attribute("charset", *charset);
+                        }
                         if let Some(name) = props.get(NAME) {
                             ctype = ctype.attribute("name", *name);
                         }
-                        MimePart::new(ctype, body)
+                        if Self::is_calendar(&ctype) {
+                            Self::compile_calendar_part(props, ctype, body.as_bytes())?
+                        } else if Self::is_html(&ctype) && !content_ids.is_empty() {
+                            let html = Self::rewrite_content_ids(body, content_ids);
+                            if props.contains_key(ENCODING) {
+                                MimePart::new(ctype, html)
+                            } else {
+                                let html = self.sign_body(props, &ctype, html, signature);
+                                self.compile_text_part(ctype, html)?
+                            }
+                        } else if props.contains_key(ENCODING) && !Self::is_text(&ctype) {
+                            // already encoded bodies are written as is,
+                            // the binary body prevents mail-builder
+                            // from adding a charset to non-text parts
+                            MimePart::new(ctype, body.as_bytes())
+                        } else if props.contains_key(ENCODING) || !Self::is_text(&ctype) {
+                            MimePart::new(ctype, body)
+                        } else {
+                            let body = self.sign_body(props, &ctype, body, signature);
+                            self.compile_text_part(ctype, body)?
+                        }
                     }
                 };
 
@@ -310,18 +1424,28 @@ async fn compile_part(&'a self, part: Part<'a>) -> Result<MimePart> {
                 };
 
                 part = match props.get(DISPOSITION) {
-                    Some(&INLINE) => part.inline(),
+                    Some(&INLINE) => match props.get(RECIPIENT_FILENAME) {
+                        Some(filename) => part.header(
+                            "Content-Disposition",
+                            ContentType::new(INLINE).attribute("filename", *filename),
+                        ),
+                        None => part.inline(),
+                    },
                     Some(&ATTACHMENT) => part.attachment(
                         props
                             .get(RECIPIENT_FILENAME)
                             .map(Deref::deref)
                             .or_else(|| match &fpath {
                                 Some(fpath) => fpath.file_name().and_then(OsStr::to_str),
+                                #[cfg(feature = "remote")]
+                                None => props.get(URL).and_then(|url| Self::remote_file_name(url)),
+                                #[cfg(not(feature = "remote"))]
                                 None => None,
                             })
                             .unwrap_or("noname")
                             .to_owned(),
                     ),
+                    _ if props.contains_key(CID) => part.inline(),
                     _ if fpath.is_some() => part.attachment(
                         props
                             .get(RECIPIENT_FILENAME)
@@ -335,18 +1459,45 @@ async fn compile_part(&'a self, part: Part<'a>) -> Result<MimePart> {
                             })
                             .unwrap_or_else(|| "noname".to_string()),
                     ),
+                    #[cfg(feature = "remote")]
+                    _ if props.contains_key(URL) => part.attachment(
+                        props
+                            .get(RECIPIENT_FILENAME)
+                            .copied()
+                            .or_else(|| Self::remote_file_name(props[URL]))
+                            .unwrap_or("noname")
+                            .to_owned(),
+                    ),
                     _ => part,
                 };
 
+                if let Some(cid) = props.get(CID) {
+                    let content_id = match content_ids.get(*cid) {
+                        Some(content_id) => content_id.clone(),
+                        None => {
+                            let source = props.get(FILENAME).unwrap_or(&body);
+                            Self::generate_content_id(cid, source)
+                        }
+                    };
+
+                    part = part.cid(content_id);
+                }
+
+                if let Some(description) = description {
+                    part = part.header("Content-Description", Text::new(description));
+                }
+
                 #[cfg(feature = "pgp")]
                 {
                     part = match props.get(SIGN) {
                         Some(&PGP_MIME) => self.try_sign_part(part).await,
+                        Some(&PGP_INLINE) => self.try_sign_part_inline(part).await,
                         _ => part,
                     };
 
                     part = match props.get(ENCRYPT) {
                         Some(&PGP_MIME) => self.try_encrypt_part(part).await,
+                        Some(&PGP_INLINE) => self.try_encrypt_part_inline(part).await,
                         _ => part,
                     };
                 };
@@ -354,13 +1505,28 @@ async fn compile_part(&'a self, part: Part<'a>) -> Result<MimePart> {
                 Ok(part)
             }
             Part::PlainText(body) => {
-                let body = Self::unescape_mml_markup(body);
-                let part = MimePart::new("text/plain", body);
+                let mut body = Self::unescape_mml_markup(body);
+                if let (Some(sig), Some(placement)) =
+                    (&self.signature, signature.sign_plain_text(&body))
+                {
+                    body = sig.sign_plain(&body, placement);
+                }
+                self.check_body_size(body.len(), total_size)?;
+                let part = self.compile_text_part(ContentType::new("text/plain"), body)?;
                 Ok(part)
             }
         }
     }
 
+    /// Check the given raw MML body without compiling it.
+    ///
+    /// Returns the diagnostics found (unknown properties, unclosed
+    /// multiparts, missing files etc), with spans relative to the
+    /// given body. An empty list means that the body is valid.
+    pub fn check(&self, mml_body: &str) -> Vec<Diagnostic> {
+        check::check(mml_body, 0)
+    }
+
     /// Compile the given raw MML body to MIME body.
     pub async fn compile(&'a self, mml_body: &'a str) -> Result<MessageBuilder> {
         let res = parsers::parts().parse(mml_body);
@@ -376,10 +1542,15 @@ pub async fn compile(&'a self, mml_body: &'a str) -> Result<MessageBuilder> {
 #[cfg(test)]
 mod tests {
     use concat_with::concat_line;
+    use mail_parser::MessageParser;
     use std::io::prelude::*;
     use tempfile::Builder;
 
-    use super::MmlBodyCompiler;
+    use super::{
+        ExternalAttachment, ExternalizeFn, ExternalizeResult, MmlBodyCompiler, OversizeStrategy,
+        Signature, SignaturePlacement, SizeLimits, TransferEncoding,
+    };
+    use crate::Error;
 
     #[tokio::test]
     async fn plain() {
@@ -408,6 +1579,167 @@ async fn plain() {
         assert_eq!(msg, expected_msg);
     }
 
+    #[tokio::test]
+    async fn charset() {
+        let msg = MmlBodyCompiler::new()
+            .with_charset("iso-8859-1")
+            .with_transfer_encoding(TransferEncoding::Base64)
+            .compile("Café\n")
+            .await
+            .unwrap()
+            .message_id("id@localhost")
+            .date(0_u64)
+            .write_to_string()
+            .unwrap();
+
+        let expected_msg = concat_line!(
+            "Message-ID: <id@localhost>\r",
+            "Date: Thu, 1 Jan 1970 00:00:00 +0000\r",
+            "MIME-Version: 1.0\r",
+            "Content-Type: text/plain; charset=\"iso-8859-1\"\r",
+            "Content-Transfer-Encoding: base64\r",
+            "\r",
+            "Q2Fm6Qo=\r",
+            "",
+        );
+
+        assert_eq!(msg, expected_msg);
+
+        let msg = MmlBodyCompiler::new()
+            .with_charset("iso-8859-1")
+            .with_transfer_encoding(TransferEncoding::Base64)
+            .compile("<#part type=text/plain charset=shift_jis>こん<#/part>")
+            .await
+            .unwrap()
+            .message_id("id@localhost")
+            .date(0_u64)
+            .write_to_string()
+            .unwrap();
+
+        let expected_msg = concat_line!(
+            "Message-ID: <id@localhost>\r",
+            "Date: Thu, 1 Jan 1970 00:00:00 +0000\r",
+            "MIME-Version: 1.0\r",
+            "Content-Type: text/plain; charset=\"shift_jis\"\r",
+            "Content-Transfer-Encoding: base64\r",
+            "\r",
+            "grGC8Q==\r",
+            "",
+        );
+
+        assert_eq!(msg, expected_msg);
+
+        let compiler = MmlBodyCompiler::new().with_charset("iso-8859-1");
+        let res = compiler.compile("こんにちは").await;
+        assert!(matches!(res, Err(Error::EncodeCharsetError(_))));
+    }
+
+    #[tokio::test]
+    async fn transfer_encoding() {
+        let mml_body = concat_line!("Hello, world!", "");
+
+        let msg = MmlBodyCompiler::new()
+            .with_transfer_encoding(TransferEncoding::Base64)
+            .compile(mml_body)
+            .await
+            .unwrap()
+            .message_id("id@localhost")
+            .date(0_u64)
+            .write_to_string()
+            .unwrap();
+
+        let expected_msg = concat_line!(
+            "Message-ID: <id@localhost>\r",
+            "Date: Thu, 1 Jan 1970 00:00:00 +0000\r",
+            "MIME-Version: 1.0\r",
+            "Content-Type: text/plain; charset=\"utf-8\"\r",
+            "Content-Transfer-Encoding: base64\r",
+            "\r",
+            "SGVsbG8sIHdvcmxkIQo=\r",
+            "",
+        );
+
+        assert_eq!(msg, expected_msg);
+    }
+
+    #[tokio::test]
+    async fn signature() {
+        let compiler = MmlBodyCompiler::new()
+            .with_signature(Signature::new("Alice").with_html("<b>Alice</b>"));
+
+        let mml_body = concat_line!(
+            "<#multipart type=alternative>",
+            "<#part type=text/plain>",
+            "Hello!",
+            "<#part type=text/html>",
+            "<p>Hello!</p>",
+            "<#/multipart>",
+            "<#part type=text/plain disposition=attachment>Attached<#/part>",
+        );
+
+        let msg = compiler.compile(mml_body).await.unwrap();
+        let msg = msg.write_to_vec().unwrap();
+        let msg = MessageParser::new().parse(&msg).unwrap();
+
+        let text = msg.body_text(0).unwrap().replace('\r', "");
+        assert_eq!(text, "Hello!\n\n-- \nAlice\n");
+
+        let html = msg.body_html(0).unwrap().replace('\r', "");
+        let expected_html = "<p>Hello!</p>\n<div class=\"signature\">-- <br>\n<b>Alice</b></div>\n";
+        assert_eq!(html, expected_html);
+
+        let attachment = msg.attachment(0).unwrap();
+        assert_eq!(attachment.contents(), b"Attached");
+    }
+
+    #[tokio::test]
+    async fn signature_placement() {
+        let below = MmlBodyCompiler::new().with_signature(Signature::new("Alice"));
+        let above = MmlBodyCompiler::new()
+            .with_signature(Signature::new("Alice").with_placement(SignaturePlacement::AboveQuote));
+
+        let compile = |compiler: &MmlBodyCompiler, mml_body: &str| {
+            let compiler = compiler.clone();
+            let mml_body = mml_body.to_owned();
+            async move {
+                let msg = compiler.compile(&mml_body).await.unwrap();
+                let msg = msg.write_to_vec().unwrap();
+                let msg = MessageParser::new().parse(&msg).unwrap();
+                msg.body_text(0).unwrap().replace('\r', "")
+            }
+        };
+
+        let mml_body = concat_line!("Hello!", "", "Bob wrote:", "> Hi");
+
+        let text = compile(&below, mml_body).await;
+        let expected_text =
+            concat_line!("Hello!", "", "Bob wrote:", "> Hi", "", "-- ", "Alice", "");
+        assert_eq!(text, expected_text);
+
+        let text = compile(&above, mml_body).await;
+        let expected_text = concat_line!("Hello!", "", "-- ", "Alice", "", "Bob wrote:", "> Hi");
+        assert_eq!(text, expected_text);
+
+        let mml_body = concat_line!(
+            "<#part type=text/plain signature=below>",
+            "Hello!",
+            "",
+            "Bob wrote:",
+            "> Hi",
+            "<#/part>",
+        );
+
+        let text = compile(&above, mml_body).await;
+        let expected_text =
+            concat_line!("Hello!", "", "Bob wrote:", "> Hi", "", "-- ", "Alice", "");
+        assert_eq!(text, expected_text);
+
+        let mml_body = concat_line!("<#part type=text/plain signature=none>", "Hello!<#/part>");
+
+        let text = compile(&below, mml_body).await;
+        assert_eq!(text, "Hello!");
+    }
+
     #[tokio::test]
     async fn html() {
         let mml_body = concat_line!(
@@ -476,4 +1808,367 @@ async fn attachment() {
 
         assert_eq!(msg, expected_msg);
     }
+
+    #[tokio::test]
+    async fn calendar() {
+        let mml_body = concat_line!(
+            "<#part type=text/calendar method=request>",
+            "BEGIN:VCALENDAR",
+            "VERSION:2.0",
+            "BEGIN:VEVENT",
+            "UID:event-1@localhost",
+            "END:VEVENT",
+            "END:VCALENDAR",
+            "<#/part>",
+        );
+
+        let msg = MmlBodyCompiler::new()
+            .compile(mml_body)
+            .await
+            .unwrap()
+            .message_id("id@localhost")
+            .date(0_u64)
+            .write_to_string()
+            .unwrap();
+
+        let expected_msg = concat_line!(
+            "Message-ID: <id@localhost>\r",
+            "Date: Thu, 1 Jan 1970 00:00:00 +0000\r",
+            "MIME-Version: 1.0\r",
+            "Content-Type: text/calendar; method=\"REQUEST\"; charset=\"utf-8\"\r",
+            "Content-Transfer-Encoding: 7bit\r",
+            "\r",
+            "BEGIN:VCALENDAR\r",
+            "METHOD:REQUEST\r",
+            "VERSION:2.0\r",
+            "BEGIN:VEVENT\r",
+            "UID:event-1@localhost\r",
+            "END:VEVENT\r",
+            "END:VCALENDAR\r",
+            "",
+        );
+
+        assert_eq!(msg, expected_msg);
+    }
+
+    #[tokio::test]
+    async fn invalid_calendar() {
+        let mml_body = concat_line!(
+            "<#part type=text/calendar method=REPLY>",
+            "BEGIN:VCALENDAR",
+            "METHOD:REQUEST",
+            "BEGIN:VEVENT",
+            "UID:event-1@localhost",
+            "END:VEVENT",
+            "END:VCALENDAR",
+            "<#/part>",
+        );
+
+        let compiler = MmlBodyCompiler::new();
+        let res = compiler.compile(mml_body).await;
+        assert!(matches!(res, Err(Error::InvalidCalendarPartError(_))));
+
+        let mml_body = "<#part type=text/calendar>BEGIN:VEVENT<#/part>";
+        let res = compiler.compile(mml_body).await;
+        assert!(matches!(res, Err(Error::InvalidCalendarPartError(_))));
+    }
+
+    #[cfg(feature = "markdown")]
+    #[tokio::test]
+    async fn markdown() {
+        let mml_body = concat_line!(
+            "<#part type=text/markdown>",
+            "# Hello",
+            "",
+            "This is **Markdown**.",
+            "<#/part>",
+        );
+
+        let msg = MmlBodyCompiler::new()
+            .compile(mml_body)
+            .await
+            .unwrap()
+            .message_id("id@localhost")
+            .date(0_u64)
+            .write_to_string()
+            .unwrap();
+
+        assert!(msg.contains("Content-Type: multipart/alternative;"));
+        assert!(msg.contains(concat_line!(
+            "Content-Type: text/plain; charset=\"utf-8\"\r",
+            "Content-Transfer-Encoding: 7bit\r",
+            "\r",
+            "# Hello\r",
+            "\r",
+            "This is **Markdown**.\r",
+        )));
+        assert!(msg.contains(concat_line!(
+            "Content-Type: text/html; charset=\"utf-8\"\r",
+            "Content-Transfer-Encoding: 7bit\r",
+            "\r",
+            "<h1>Hello</h1>\r",
+            "<p>This is <strong>Markdown</strong>.</p>\r",
+        )));
+    }
+
+    #[tokio::test]
+    async fn inline_cid() {
+        let mut image = Builder::new()
+            .prefix("image")
+            .suffix(".png")
+            .rand_bytes(0)
+            .tempfile()
+            .unwrap();
+        write!(image, "image").unwrap();
+        let image_path = image.path().to_string_lossy();
+
+        let mml_body = format!(
+            "<#part type=text/html>\n<img src=\"cid:logo\"><img src=\"image.png\">\n<#/part>\n<#part filename={image_path} type=image/png disposition=inline cid=logo><#/part>\n"
+        );
+
+        let msg = MmlBodyCompiler::new()
+            .compile(&mml_body)
+            .await
+            .unwrap()
+            .message_id("id@localhost")
+            .date(0_u64)
+            .write_to_string()
+            .unwrap();
+
+        let content_id = MmlBodyCompiler::generate_content_id("logo", &image_path);
+
+        assert!(msg.contains("Content-Type: multipart/related;"));
+        assert!(!msg.contains("multipart/mixed"));
+        assert!(msg.contains(&format!("Content-ID: <{content_id}>")));
+        assert!(msg.contains("Content-Disposition: inline"));
+        assert!(msg.contains(&format!(
+            "<img src=\"cid:{content_id}\"><img src=\"cid:{content_id}\">"
+        )));
+    }
+
+    #[tokio::test]
+    async fn explicit_cid() {
+        let mml_body = concat_line!(
+            "<#part type=text/html>",
+            "<img src='cid:logo@localhost'>",
+            "<#/part>",
+            "<#part type=text/plain cid=logo@localhost>logo<#/part>",
+            "<#part type=text/plain disposition=attachment>attachment<#/part>",
+        );
+
+        let msg = MmlBodyCompiler::new()
+            .compile(mml_body)
+            .await
+            .unwrap()
+            .message_id("id@localhost")
+            .date(0_u64)
+            .write_to_string()
+            .unwrap();
+
+        assert!(msg.contains("Content-Type: multipart/mixed;"));
+        assert!(msg.contains("Content-Type: multipart/related;"));
+        assert!(msg.contains("Content-ID: <logo@localhost>"));
+        assert!(msg.contains("<img src='cid:logo@localhost'>"));
+    }
+
+    #[cfg(feature = "remote")]
+    #[test]
+    fn remote_file_name() {
+        let name = MmlBodyCompiler::remote_file_name("https://localhost/assets/logo.png?v=1#top");
+        assert_eq!(name, Some("logo.png"));
+
+        let name = MmlBodyCompiler::remote_file_name("https://localhost/assets/");
+        assert_eq!(name, None);
+
+        let name = MmlBodyCompiler::remote_file_name("https://localhost");
+        assert_eq!(name, None);
+    }
+
+    #[cfg(feature = "remote")]
+    #[tokio::test]
+    async fn remote() {
+        use std::{io::BufReader, net::TcpListener, thread};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let res = "HTTP/1.1 200 OK\r\nContent-Length: 13\r\nConnection: close\r\n\r\nHello, world!";
+                stream.write_all(res.as_bytes()).unwrap();
+            }
+        });
+
+        let mml_body = format!("<#part type=text/plain url=http://{addr}/hello.txt><#/part>");
+
+        let msg = MmlBodyCompiler::new()
+            .compile(&mml_body)
+            .await
+            .unwrap()
+            .message_id("id@localhost")
+            .date(0_u64)
+            .write_to_string()
+            .unwrap();
+
+        assert!(msg.contains("Content-Disposition: attachment; filename=\"hello.txt\""));
+        assert!(msg.contains("Hello, world!"));
+
+        let res = MmlBodyCompiler::new()
+            .with_remote_max_size(5)
+            .compile(&mml_body)
+            .await;
+
+        assert!(matches!(res, Err(Error::ReadRemotePartError(_, _))));
+    }
+
+    #[tokio::test]
+    async fn size_limits() {
+        let mut attachment = Builder::new()
+            .prefix("big")
+            .suffix(".bin")
+            .rand_bytes(0)
+            .tempfile()
+            .unwrap();
+        attachment.write_all(&[0; 1024]).unwrap();
+        let attachment_path = attachment.path().to_string_lossy();
+
+        let mml_body = format!(
+            "Hello, world!\n<#part filename={attachment_path} type=application/octet-stream><#/part>\n"
+        );
+
+        let compiler = MmlBodyCompiler::new().with_size_limits(SizeLimits {
+            attachment_max_size: Some(512),
+            ..Default::default()
+        });
+        let res = compiler.compile(&mml_body).await;
+
+        assert!(matches!(
+            res,
+            Err(Error::AttachmentTooLargeError(name, 1024, 512)) if name == "big.bin"
+        ));
+
+        let compiler = MmlBodyCompiler::new().with_size_limits(SizeLimits {
+            message_max_size: Some(1024),
+            ..Default::default()
+        });
+        let res = compiler.compile(&mml_body).await;
+
+        assert!(matches!(res, Err(Error::MessageTooLargeError(_, 1024))));
+
+        let compiler = MmlBodyCompiler::new().with_size_limits(SizeLimits {
+            attachment_max_size: Some(2048),
+            message_max_size: Some(4096),
+            ..Default::default()
+        });
+        let res = compiler.compile(&mml_body).await;
+
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn size_limits_externalize() {
+        let mut attachment = Builder::new()
+            .prefix("huge")
+            .suffix(".bin")
+            .rand_bytes(0)
+            .tempfile()
+            .unwrap();
+        attachment.write_all(&[0; 1024]).unwrap();
+        let attachment_path = attachment.path().to_string_lossy();
+
+        let mml_body = format!(
+            "Hello, world!\n<#part filename={attachment_path} type=application/octet-stream><#/part>\n"
+        );
+
+        let externalize = ExternalizeFn::new(|attachment: ExternalAttachment| async move {
+            assert_eq!(attachment.filename, "huge.bin");
+            assert_eq!(attachment.content_type, "application/octet-stream");
+            assert_eq!(attachment.contents.len(), 1024);
+            ExternalizeResult::Ok(String::from("https://localhost/huge.bin"))
+        });
+
+        let msg = MmlBodyCompiler::new()
+            .with_size_limits(SizeLimits {
+                attachment_max_size: Some(512),
+                message_max_size: None,
+                strategy: OversizeStrategy::Externalize(externalize),
+            })
+            .compile(&mml_body)
+            .await
+            .unwrap()
+            .message_id("id@localhost")
+            .date(0_u64)
+            .write_to_string()
+            .unwrap();
+
+        assert!(msg.contains("The attachment huge.bin (1024 bytes) was too large"));
+        assert!(msg.contains("https://localhost/huge.bin"));
+        assert!(!msg.contains("application/octet-stream"));
+    }
+
+    #[cfg(feature = "image")]
+    #[tokio::test]
+    async fn image_downscaling() {
+        use image::{ImageFormat, Rgb, RgbImage};
+
+        use super::ImageDownscaling;
+
+        let mut attachment = Builder::new()
+            .prefix("photo")
+            .suffix(".png")
+            .rand_bytes(0)
+            .tempfile()
+            .unwrap();
+        let img = RgbImage::from_fn(200, 100, |x, y| Rgb([x as u8, y as u8, (x ^ y) as u8]));
+        img.write_to(attachment.as_file_mut(), ImageFormat::Png)
+            .unwrap();
+        let attachment_path = attachment.path().to_string_lossy();
+        let size = attachment.as_file().metadata().unwrap().len();
+
+        let mml_body = format!(
+            "<#part filename={attachment_path} type=image/png description=Holidays><#/part>\n"
+        );
+
+        let msg = MmlBodyCompiler::new()
+            .with_image_downscaling(ImageDownscaling {
+                min_size: 0,
+                max_dimension: 40,
+                ..Default::default()
+            })
+            .compile(&mml_body)
+            .await
+            .unwrap()
+            .message_id("id@localhost")
+            .date(0_u64)
+            .write_to_string()
+            .unwrap();
+
+        let expected = format!("Content-Description: Holidays (original: photo.png, {size} bytes)");
+        assert!(msg.contains(&expected));
+    }
+
+    #[cfg(feature = "pgp")]
+    #[test]
+    fn pgp_encrypt_recipients() {
+        let compiler = MmlBodyCompiler::new()
+            .with_pgp_sender(Some("alice@localhost".into()))
+            .with_pgp_recipients(vec!["bob@localhost".into()])
+            .with_pgp_hidden_recipients(vec!["bob@localhost".into(), "carl@localhost".into()]);
+
+        let (recipients, hidden_recipients) = compiler.pgp_encrypt_recipients();
+        assert_eq!(recipients, vec!["bob@localhost"]);
+        assert_eq!(hidden_recipients, vec!["carl@localhost"]);
+
+        let compiler = compiler.with_pgp_encrypt_to_self(true);
+
+        let (recipients, hidden_recipients) = compiler.pgp_encrypt_recipients();
+        assert_eq!(recipients, vec!["bob@localhost", "alice@localhost"]);
+        assert_eq!(hidden_recipients, vec!["carl@localhost"]);
+    }
 }
@@ -3,13 +3,13 @@
 mod vals;
 
 pub(crate) mod prelude {
-    #[cfg(feature = "pgp")]
-    use crate::message::body::PGP_MIME;
     use crate::message::body::{
         ATTACHMENT, BACKSLASH, DOUBLE_QUOTE, ENCODING_7BIT, ENCODING_8BIT, ENCODING_BASE64,
         ENCODING_QUOTED_PRINTABLE, INLINE, MULTIPART_BEGIN, MULTIPART_END, NEW_LINE, PART_BEGIN,
-        PART_END,
+        PART_END, SIGNATURE_ABOVE, SIGNATURE_BELOW, SIGNATURE_NONE,
     };
+    #[cfg(feature = "pgp")]
+    use crate::message::body::{PGP_INLINE, PGP_MIME};
 
     pub(crate) use chumsky::prelude::*;
 
@@ -75,10 +75,30 @@ pub(crate) fn encoding_base64<'a>() -> impl Parser<'a, &'a str, &'a str, ParserE
         maybe_quoted_const_val(ENCODING_BASE64).labelled(ENCODING_BASE64)
     }
 
+    pub(crate) fn signature_above<'a>() -> impl Parser<'a, &'a str, &'a str, ParserError<'a>> + Clone
+    {
+        maybe_quoted_const_val(SIGNATURE_ABOVE).labelled(SIGNATURE_ABOVE)
+    }
+
+    pub(crate) fn signature_below<'a>() -> impl Parser<'a, &'a str, &'a str, ParserError<'a>> + Clone
+    {
+        maybe_quoted_const_val(SIGNATURE_BELOW).labelled(SIGNATURE_BELOW)
+    }
+
+    pub(crate) fn signature_none<'a>() -> impl Parser<'a, &'a str, &'a str, ParserError<'a>> + Clone
+    {
+        maybe_quoted_const_val(SIGNATURE_NONE).labelled(SIGNATURE_NONE)
+    }
+
     #[cfg(feature = "pgp")]
     pub(crate) fn pgp_mime<'a>() -> impl Parser<'a, &'a str, &'a str, ParserError<'a>> + Clone {
         maybe_quoted_const_val(PGP_MIME).labelled(PGP_MIME)
     }
+
+    #[cfg(feature = "pgp")]
+    pub(crate) fn pgp_inline<'a>() -> impl Parser<'a, &'a str, &'a str, ParserError<'a>> + Clone {
+        maybe_quoted_const_val(PGP_INLINE).labelled(PGP_INLINE)
+    }
 }
 
 pub(crate) use parts::*;
@@ -5,9 +5,12 @@
     GREATER_THAN, MULTIPART_BEGIN, MULTIPART_END,
 };
 
+#[cfg(feature = "remote")]
+use super::url;
 use super::{
-    creation_date, data_encoding, description, disposition, encoding, filename, modification_date,
-    multipart_type, name, part_type, prelude::*, read_date, recipient_filename,
+    charset, cid, creation_date, data_encoding, description, disposition, encoding, filename,
+    method, modification_date, multipart_type, name, part_type, prelude::*, read_date,
+    recipient_filename, signature,
 };
 #[cfg(feature = "pgp")]
 use super::{encrypt, sign};
@@ -68,8 +71,11 @@ pub(crate) fn part<'a>() -> impl Parser<'a, &'a str, Part<'a>, ParserError<'a>>
             choice((
                 part_type(),
                 filename(),
+                #[cfg(feature = "remote")]
+                url(),
                 recipient_filename(),
                 name(),
+                charset(),
                 encoding(),
                 data_encoding(),
                 creation_date(),
@@ -77,6 +83,9 @@ pub(crate) fn part<'a>() -> impl Parser<'a, &'a str, Part<'a>, ParserError<'a>>
                 read_date(),
                 description(),
                 disposition(),
+                cid(),
+                method(),
+                signature(),
                 #[cfg(feature = "pgp")]
                 encrypt(),
                 #[cfg(feature = "pgp")]
@@ -5,28 +5,27 @@
 //!
 //! [Emacs MML definition]: https://www.gnu.org/software/emacs/manual/html_node/emacs-mime/MML-Definition.html
 
+#[cfg(feature = "remote")]
+use crate::message::body::URL;
 use crate::message::body::{
-    compiler::tokens::Prop, ALTERNATIVE, CHARSET, CREATION_DATE, DATA_ENCODING, DESCRIPTION,
-    DISPOSITION, ENCODING, FILENAME, MIXED, MODIFICATION_DATE, NAME, READ_DATE, RECIPIENT_FILENAME,
-    RELATED, SIZE, TYPE,
+    compiler::tokens::Prop, CHARSET, CID, CREATION_DATE, DATA_ENCODING, DESCRIPTION, DISPOSITION,
+    ENCODING, FILENAME, METHOD, MODIFICATION_DATE, NAME, READ_DATE, RECIPIENT_FILENAME, SIGNATURE,
+    SIZE, TYPE,
 };
 #[cfg(feature = "pgp")]
 use crate::message::body::{ENCRYPT, RECIPIENTS, SENDER, SIGN};
 
-use super::{maybe_quoted_const_val, prelude::*, quoted_val, val};
+use super::{prelude::*, quoted_val, val};
 
 /// The multipart type property.
 ///
-/// > The MIME type of the part (Content-Type).
+/// > The MIME subtype of the multipart (Content-Type): `mixed`,
+/// > `alternative`, `related` or any other subtype.
 pub(crate) fn multipart_type<'a>() -> impl Parser<'a, &'a str, Prop<'a>, ParserError<'a>> + Clone {
     just(TYPE)
         .labelled(TYPE)
         .then_ignore(just('=').padded())
-        .then(choice((
-            maybe_quoted_const_val(MIXED).labelled(MIXED),
-            maybe_quoted_const_val(ALTERNATIVE).labelled(ALTERNATIVE),
-            maybe_quoted_const_val(RELATED).labelled(RELATED),
-        )))
+        .then(choice((quoted_val(), val().to_slice())))
         .padded()
 }
 
@@ -92,6 +91,46 @@ pub(crate) fn name<'a>() -> impl Parser<'a, &'a str, Prop<'a>, ParserError<'a>>
         .padded()
 }
 
+/// The method property parser.
+///
+/// The iTIP method of a calendar part (REQUEST, REPLY, CANCEL…), as
+/// defined in [RFC 5546]. It is added as parameter of the
+/// Content-Type of `text/calendar` parts.
+///
+/// [RFC 5546]: https://www.rfc-editor.org/rfc/rfc5546
+pub(crate) fn method<'a>() -> impl Parser<'a, &'a str, Prop<'a>, ParserError<'a>> + Clone {
+    just(METHOD)
+        .labelled(METHOD)
+        .then_ignore(just('=').padded())
+        .then(choice((quoted_val(), val().to_slice())))
+        .padded()
+}
+
+/// The Content-ID property parser.
+///
+/// Identifies an inline part, so that sibling HTML parts can refer
+/// to it using `cid:` URLs (Content-ID).
+pub(crate) fn cid<'a>() -> impl Parser<'a, &'a str, Prop<'a>, ParserError<'a>> + Clone {
+    just(CID)
+        .labelled(CID)
+        .then_ignore(just('=').padded())
+        .then(choice((quoted_val(), val().to_slice())))
+        .padded()
+}
+
+/// The URL property parser.
+///
+/// Download the resource at the given URL and use it as the body of
+/// the part (Content-Disposition).
+#[cfg(feature = "remote")]
+pub(crate) fn url<'a>() -> impl Parser<'a, &'a str, Prop<'a>, ParserError<'a>> + Clone {
+    just(URL)
+        .labelled(URL)
+        .then_ignore(just('=').padded())
+        .then(choice((quoted_val(), val().to_slice())))
+        .padded()
+}
+
 /// The disposition property parser.
 ///
 /// > Valid values are ‘inline’ and ‘attachment’
@@ -223,16 +262,32 @@ pub(crate) fn size<'a>() -> impl Parser<'a, &'a str, Prop<'a>, ParserError<'a>>
         .padded()
 }
 
+/// The signature property parser.
+///
+/// Override the placement of the configured signature (`above` or
+/// `below` the quote), or disable it for the whole message (`none`).
+pub(crate) fn signature<'a>() -> impl Parser<'a, &'a str, Prop<'a>, ParserError<'a>> + Clone {
+    just(SIGNATURE)
+        .labelled(SIGNATURE)
+        .then_ignore(just('=').padded())
+        .then(choice((
+            signature_above(),
+            signature_below(),
+            signature_none(),
+        )))
+        .padded()
+}
+
 /// The sign property parser.
 ///
 /// What technology to sign this MML part with (smime, pgp or
-/// pgpmime).
+/// pgpmime). Only pgpmime and pgpinline are supported.
 #[cfg(feature = "pgp")]
 pub(crate) fn sign<'a>() -> impl Parser<'a, &'a str, Prop<'a>, ParserError<'a>> + Clone {
     just(SIGN)
         .labelled(SIGN)
         .then_ignore(just('=').padded())
-        .then(pgp_mime())
+        .then(choice((pgp_mime(), pgp_inline())))
         .padded()
 }
 
@@ -240,11 +295,13 @@ pub(crate) fn sign<'a>() -> impl Parser<'a, &'a str, Prop<'a>, ParserError<'a>>
 ///
 /// > What technology to encrypt this MML part with (smime, pgp or
 /// pgpmime)
+///
+/// Only pgpmime and pgpinline are supported.
 #[cfg(feature = "pgp")]
 pub(crate) fn encrypt<'a>() -> impl Parser<'a, &'a str, Prop<'a>, ParserError<'a>> + Clone {
     just(ENCRYPT)
         .labelled(ENCRYPT)
         .then_ignore(just('=').padded())
-        .then(pgp_mime())
+        .then(choice((pgp_mime(), pgp_inline())))
         .padded()
 }
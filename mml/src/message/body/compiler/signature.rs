@@ -0,0 +1,294 @@
+//! # Signature
+//!
+//! Module dedicated to the signature of the MML compiler. A
+//! configured signature is inserted into the plain text and HTML
+//! bodies of compiled messages, after the standard `-- ` delimiter.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::message::body::{
+    compiler::tokens::Part, SIGNATURE, SIGNATURE_ABOVE, SIGNATURE_BELOW, SIGNATURE_NONE,
+};
+
+/// The signature delimiter, as defined by [RFC 3676].
+///
+/// [RFC 3676]: https://www.rfc-editor.org/rfc/rfc3676#section-4.3
+pub(crate) const SIGNATURE_DELIM: &str = "-- ";
+
+/// The placement of the signature in bodies quoting another message.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SignaturePlacement {
+    /// Insert the signature at the end of the body, below the quote.
+    #[default]
+    BelowQuote,
+
+    /// Insert the signature above the quote, including its
+    /// attribution line (`On …, … wrote:`).
+    AboveQuote,
+}
+
+/// The signature of the MML compiler.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Signature {
+    /// The plain text variant of the signature, without delimiter.
+    pub plain: String,
+
+    /// The HTML variant of the signature, without delimiter.
+    ///
+    /// When not defined, the plain text variant is escaped and used
+    /// in HTML bodies.
+    pub html: Option<String>,
+
+    /// The placement of the signature.
+    pub placement: SignaturePlacement,
+}
+
+impl Signature {
+    /// Create a new signature from the given plain text variant.
+    pub fn new(plain: impl ToString) -> Self {
+        Self {
+            plain: plain.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Set the HTML variant of the signature.
+    pub fn with_html(mut self, html: impl ToString) -> Self {
+        self.html = Some(html.to_string());
+        self
+    }
+
+    /// Set the placement of the signature.
+    pub fn with_placement(mut self, placement: SignaturePlacement) -> Self {
+        self.placement = placement;
+        self
+    }
+
+    /// Return the plain text variant, without any leading delimiter
+    /// and trailing whitespaces.
+    fn plain_text(&self) -> &str {
+        let plain = self.plain.trim_end();
+
+        match plain.split_once('\n') {
+            Some((delim, plain)) if delim.trim_end() == "--" => plain,
+            _ if plain.trim_end() == "--" => "",
+            _ => plain,
+        }
+    }
+
+    /// Insert the signature into the given plain text body.
+    pub(crate) fn sign_plain(&self, body: &str, placement: SignaturePlacement) -> String {
+        let signature = format!("{SIGNATURE_DELIM}\n{}\n", self.plain_text());
+
+        let quote = match placement {
+            SignaturePlacement::AboveQuote => find_plain_quote(body),
+            SignaturePlacement::BelowQuote => None,
+        };
+
+        match quote {
+            Some(0) => format!("{signature}\n{body}"),
+            Some(i) => {
+                let (head, quote) = body.split_at(i);
+                format!("{}\n\n{signature}\n{quote}", head.trim_end())
+            }
+            None if body.trim().is_empty() => signature,
+            None => format!("{}\n\n{signature}", body.trim_end()),
+        }
+    }
+
+    /// Insert the signature into the given HTML body.
+    pub(crate) fn sign_html(&self, body: &str, placement: SignaturePlacement) -> String {
+        let html = match &self.html {
+            Some(html) => html.trim_end().to_owned(),
+            None => escape_html(self.plain_text()).replace('\n', "<br>\n"),
+        };
+
+        let signature = format!("<div class=\"signature\">{SIGNATURE_DELIM}<br>\n{html}</div>\n");
+
+        // ASCII lowercase keeps byte offsets unchanged
+        let lowercase = body.to_ascii_lowercase();
+
+        let i = match placement {
+            SignaturePlacement::AboveQuote => lowercase.find("<blockquote"),
+            SignaturePlacement::BelowQuote => None,
+        };
+
+        match i.or_else(|| lowercase.rfind("</body>")) {
+            Some(i) => {
+                let (head, tail) = body.split_at(i);
+                format!("{head}{signature}{tail}")
+            }
+            None => format!("{body}{signature}"),
+        }
+    }
+}
+
+/// The signature state of a compilation.
+///
+/// The signature is inserted only once per variant, into the first
+/// plain text body and the first HTML body.
+#[derive(Debug, Default)]
+pub(crate) struct SignatureState {
+    placement: Option<SignaturePlacement>,
+    sign_blank_texts: bool,
+    plain_signed: AtomicBool,
+    html_signed: AtomicBool,
+}
+
+impl SignatureState {
+    /// Create a new signature state from the given signature and the
+    /// parts of the message.
+    ///
+    /// The `signature` property of parts overrides the placement of
+    /// the signature, or disables it for the whole message.
+    pub(crate) fn new(signature: Option<&Signature>, parts: &[Part]) -> Self {
+        let placement = signature.and_then(|signature| match find_signature_prop(parts) {
+            Some(SIGNATURE_NONE) => None,
+            Some(SIGNATURE_ABOVE) => Some(SignaturePlacement::AboveQuote),
+            Some(SIGNATURE_BELOW) => Some(SignaturePlacement::BelowQuote),
+            _ => Some(signature.placement),
+        });
+
+        Self {
+            placement,
+            // blank texts are usually separators between parts, they
+            // are signed only when they are the whole body
+            sign_blank_texts: parts.len() <= 1,
+            ..Default::default()
+        }
+    }
+
+    /// Return the placement of the signature if it should be
+    /// inserted into the given plain text.
+    pub(crate) fn sign_plain_text(&self, text: &str) -> Option<SignaturePlacement> {
+        if text.trim().is_empty() && !self.sign_blank_texts {
+            return None;
+        }
+
+        self.sign_plain()
+    }
+
+    /// Return the placement of the signature if it should be
+    /// inserted into the next plain text body.
+    pub(crate) fn sign_plain(&self) -> Option<SignaturePlacement> {
+        let placement = self.placement?;
+        let signed = self.plain_signed.swap(true, Ordering::Relaxed);
+        (!signed).then_some(placement)
+    }
+
+    /// Return the placement of the signature if it should be
+    /// inserted into the next HTML body.
+    pub(crate) fn sign_html(&self) -> Option<SignaturePlacement> {
+        let placement = self.placement?;
+        let signed = self.html_signed.swap(true, Ordering::Relaxed);
+        (!signed).then_some(placement)
+    }
+}
+
+/// Find the first `signature` property of the given parts.
+fn find_signature_prop<'a>(parts: &[Part<'a>]) -> Option<&'a str> {
+    parts.iter().find_map(|part| match part {
+        Part::Single(props, _) => props.get(SIGNATURE).copied(),
+        Part::Multi(_, parts) => find_signature_prop(parts),
+        Part::PlainText(_) => None,
+    })
+}
+
+/// Find the byte offset of the first quote of the given plain text,
+/// including its attribution line.
+fn find_plain_quote(text: &str) -> Option<usize> {
+    let mut offset = 0;
+    let mut prev_line: Option<(usize, &str)> = None;
+
+    for line in text.split_inclusive('\n') {
+        if line.starts_with('>') {
+            return Some(match prev_line {
+                Some((i, prev_line)) if prev_line.trim_end().ends_with(':') => i,
+                _ => offset,
+            });
+        }
+
+        if !line.trim().is_empty() {
+            prev_line = Some((offset, line));
+        }
+
+        offset += line.len();
+    }
+
+    None
+}
+
+/// Escape the given text so that it can be inserted into HTML.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use concat_with::concat_line;
+
+    use super::{Signature, SignaturePlacement};
+
+    #[test]
+    fn sign_plain() {
+        let signature = Signature::new("-- \nAlice\n");
+        let body = concat_line!("Hello!", "", "On Mon, 1 Jan 2024, Bob wrote:", "> Hi", "",);
+
+        assert_eq!(
+            signature.sign_plain(body, SignaturePlacement::BelowQuote),
+            concat_line!(
+                "Hello!",
+                "",
+                "On Mon, 1 Jan 2024, Bob wrote:",
+                "> Hi",
+                "",
+                "-- ",
+                "Alice",
+                "",
+            ),
+        );
+
+        assert_eq!(
+            signature.sign_plain(body, SignaturePlacement::AboveQuote),
+            concat_line!(
+                "Hello!",
+                "",
+                "-- ",
+                "Alice",
+                "",
+                "On Mon, 1 Jan 2024, Bob wrote:",
+                "> Hi",
+                "",
+            ),
+        );
+    }
+
+    #[test]
+    fn sign_html() {
+        let signature = Signature::new("Alice <alice@localhost>");
+        let body = "<html><body><p>Hello!</p><blockquote>Hi</blockquote></body></html>";
+
+        assert_eq!(
+            signature.sign_html(body, SignaturePlacement::BelowQuote),
+            concat!(
+                "<html><body><p>Hello!</p><blockquote>Hi</blockquote>",
+                "<div class=\"signature\">-- <br>\nAlice &lt;alice@localhost&gt;</div>\n",
+                "</body></html>",
+            ),
+        );
+
+        let signature = signature.with_html("<b>Alice</b>");
+
+        assert_eq!(
+            signature.sign_html(body, SignaturePlacement::AboveQuote),
+            concat!(
+                "<html><body><p>Hello!</p>",
+                "<div class=\"signature\">-- <br>\n<b>Alice</b></div>\n",
+                "<blockquote>Hi</blockquote></body></html>",
+            ),
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! # Size limits
+//!
+//! Module dedicated to the size limits of the MML compiler. Limits
+//! prevent users from accidentally generating huge messages that
+//! relays would reject anyway.
+
+use std::{error, fmt, future::Future, ops::Deref, pin::Pin, sync::Arc};
+
+/// The result returned by an [`ExternalizeFn`]: the URL the
+/// attachment has been uploaded to.
+pub type ExternalizeResult = Result<String, Box<dyn error::Error + Send + Sync>>;
+
+/// The size limits of the MML compiler.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SizeLimits {
+    /// The maximum size of an attachment, in bytes.
+    pub attachment_max_size: Option<usize>,
+
+    /// The maximum size of the whole message, in bytes.
+    ///
+    /// The size is estimated from the size of parts, once encoded
+    /// (attachments are considered encoded in base64).
+    pub message_max_size: Option<usize>,
+
+    /// The strategy applied to attachments exceeding the limits.
+    pub strategy: OversizeStrategy,
+}
+
+/// The strategy applied to attachments exceeding the size limits.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum OversizeStrategy {
+    /// Abort the compilation with an error.
+    #[default]
+    Error,
+
+    /// Upload the attachment using the given function, and replace
+    /// it with a link part.
+    Externalize(ExternalizeFn),
+}
+
+/// An attachment exceeding the size limits, given to the
+/// [`ExternalizeFn`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExternalAttachment {
+    /// The file name of the attachment.
+    pub filename: String,
+
+    /// The content type of the attachment.
+    pub content_type: String,
+
+    /// The raw contents of the attachment.
+    pub contents: Vec<u8>,
+}
+
+/// Externalize function.
+///
+/// This is just a wrapper around an async function that uploads the
+/// given attachment somewhere and returns its URL.
+#[derive(Clone)]
+pub struct ExternalizeFn(
+    #[allow(clippy::type_complexity)]
+    Arc<
+        dyn Fn(ExternalAttachment) -> Pin<Box<dyn Future<Output = ExternalizeResult> + Send>>
+            + Send
+            + Sync,
+    >,
+);
+
+impl ExternalizeFn {
+    /// Create a new externalize function.
+    pub fn new<F: Future<Output = ExternalizeResult> + Send + 'static>(
+        f: impl Fn(ExternalAttachment) -> F + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(move |attachment| Box::pin(f(attachment))))
+    }
+}
+
+impl Deref for ExternalizeFn {
+    type Target = Arc<
+        dyn Fn(ExternalAttachment) -> Pin<Box<dyn Future<Output = ExternalizeResult> + Send>>
+            + Send
+            + Sync,
+    >;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl PartialEq for ExternalizeFn {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for ExternalizeFn {}
+
+impl fmt::Debug for ExternalizeFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ExternalizeFn()")
+    }
+}
+
+/// Estimate the size of the given contents once encoded in base64,
+/// including line breaks (76 chars per line).
+pub(crate) fn base64_size(size: usize) -> usize {
+    size.div_ceil(57) * 78
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn base64_size() {
+        assert_eq!(super::base64_size(0), 0);
+        assert_eq!(super::base64_size(1), 78);
+        assert_eq!(super::base64_size(57), 78);
+        assert_eq!(super::base64_size(58), 156);
+    }
+}
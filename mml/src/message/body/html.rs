@@ -0,0 +1,281 @@
+//! # HTML to plain text conversion module
+//!
+//! Module dedicated to the conversion of `text/html` parts into plain
+//! text. The HTML is first rewritten according to the interpreter
+//! options (links, tables, images), then converted using
+//! [`nanohtml2text`] and finally wrapped to the maximum line width.
+
+use nanohtml2text::html2text;
+
+/// Strategy to render links of `text/html` parts.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum HtmlLinks {
+    /// Render links inline, right after their content: `content
+    /// (https://example.com)`.
+    #[default]
+    Inline,
+
+    /// Render links as numbered references after their content:
+    /// `content [1]`. The list of references is appended at the end
+    /// of the text.
+    Footnotes,
+
+    /// Render only the content of links, without their URL.
+    Strip,
+}
+
+/// Options of the HTML to plain text conversion.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct HtmlToText {
+    pub links: HtmlLinks,
+    pub tables: bool,
+    pub images_alt: bool,
+    pub max_width: Option<usize>,
+}
+
+impl HtmlToText {
+    /// Convert the given HTML into plain text.
+    pub fn convert(&self, html: &str) -> String {
+        let html = self.rewrite(html);
+        let text = html2text(&html);
+
+        match self.max_width {
+            Some(width) if width > 0 => wrap(&text, width),
+            _ => text,
+        }
+    }
+
+    /// Rewrite the given HTML so that the conversion to plain text
+    /// follows the current options.
+    fn rewrite(&self, html: &str) -> String {
+        let mut out = String::with_capacity(html.len());
+        let mut footnotes = Vec::new();
+        let mut footnote = None;
+        let mut first_cell = true;
+
+        let mut i = 0;
+        while let Some(start) = html[i..].find('<').map(|n| i + n) {
+            out.push_str(&html[i..start]);
+
+            // HTML comments are kept as it is, since they may contain
+            // tags that should not be rewritten
+            if html[start..].starts_with("<!--") {
+                let end = html[start..]
+                    .find("-->")
+                    .map_or(html.len(), |n| start + n + 3);
+                out.push_str(&html[start..end]);
+                i = end;
+                continue;
+            }
+
+            let Some(end) = html[start..].find('>').map(|n| start + n) else {
+                i = start;
+                break;
+            };
+
+            let tag = &html[start + 1..end];
+            let (name, attrs) = tag
+                .split_once(char::is_whitespace)
+                .unwrap_or((tag, Default::default()));
+            let name = name.trim_end_matches('/').to_ascii_lowercase();
+
+            match name.as_str() {
+                "a" if self.links != HtmlLinks::Inline => {
+                    if self.links == HtmlLinks::Footnotes {
+                        footnote = attr(attrs, "href")
+                            .filter(|href| !href.is_empty() && !href.starts_with("javascript:"));
+                    }
+                }
+                "/a" if self.links != HtmlLinks::Inline => {
+                    if let Some(href) = footnote.take() {
+                        footnotes.push(href);
+                        out.push_str(&format!(" [{}]", footnotes.len()));
+                    }
+                }
+                "img" if self.images_alt => {
+                    if let Some(alt) = attr(attrs, "alt").filter(|alt| !alt.trim().is_empty()) {
+                        out.push_str(&format!("[{}]", alt.trim()));
+                    }
+                }
+                "table" | "/table" if self.tables => {
+                    out.push_str("<br>");
+                }
+                "tr" if self.tables => {
+                    out.push_str("<br>");
+                    first_cell = true;
+                }
+                "td" | "th" if self.tables => {
+                    if !first_cell {
+                        out.push_str(" | ");
+                    }
+                    first_cell = false;
+                }
+                _ => {
+                    out.push_str(&html[start..=end]);
+                }
+            }
+
+            i = end + 1;
+        }
+
+        out.push_str(&html[i..]);
+
+        if !footnotes.is_empty() {
+            out.push_str("<p>");
+            for (n, href) in footnotes.iter().enumerate() {
+                out.push_str(&format!("[{}] {href}<br>", n + 1));
+            }
+        }
+
+        out
+    }
+}
+
+/// Extract the value of the given attribute from the given tag
+/// attributes.
+fn attr(attrs: &str, name: &str) -> Option<String> {
+    let mut rest = attrs;
+
+    loop {
+        rest = rest.trim_start();
+
+        if rest.is_empty() {
+            return None;
+        }
+
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        rest = rest[key_end..].trim_start();
+
+        let value = match rest.strip_prefix('=') {
+            None => None,
+            Some(value) => {
+                let value = value.trim_start();
+                let (value, next) = match value.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let value = &value[1..];
+                        let end = value.find(quote).unwrap_or(value.len());
+                        (&value[..end], &value[(end + 1).min(value.len())..])
+                    }
+                    _ => {
+                        let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                        (&value[..end], &value[end..])
+                    }
+                };
+                rest = next;
+                Some(value)
+            }
+        };
+
+        if key.eq_ignore_ascii_case(name) {
+            return Some(value.unwrap_or_default().to_owned());
+        }
+    }
+}
+
+/// Wrap lines of the given text to the given maximum width.
+///
+/// Words longer than the width are kept on their own line. Line
+/// endings produced by [`html2text`] (CRLF) are preserved.
+fn wrap(text: &str, width: usize) -> String {
+    let mut out = String::with_capacity(text.len());
+
+    for (n, line) in text.split("\r\n").enumerate() {
+        if n > 0 {
+            out.push_str("\r\n");
+        }
+
+        let mut len = 0;
+
+        for word in line.split(' ').filter(|word| !word.is_empty()) {
+            let word_len = word.chars().count();
+
+            if len > 0 && len + 1 + word_len > width {
+                out.push_str("\r\n");
+                len = 0;
+            }
+
+            if len > 0 {
+                out.push(' ');
+                len += 1;
+            }
+
+            out.push_str(word);
+            len += word_len;
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{attr, wrap, HtmlLinks, HtmlToText};
+
+    #[test]
+    fn attributes() {
+        let attrs = r#"class=link HREF="https://a.b/?c=d" title='e f' hidden"#;
+
+        assert_eq!(attr(attrs, "href"), Some("https://a.b/?c=d".into()));
+        assert_eq!(attr(attrs, "class"), Some("link".into()));
+        assert_eq!(attr(attrs, "title"), Some("e f".into()));
+        assert_eq!(attr(attrs, "hidden"), Some("".into()));
+        assert_eq!(attr(attrs, "alt"), None);
+    }
+
+    #[test]
+    fn links() {
+        let html =
+            r#"<p>See <a href="https://a.b">this</a> and <a href="https://c.d">that</a>.</p>"#;
+
+        let text = HtmlToText::default().convert(html);
+        assert_eq!(text, "See this (https://a.b) and that (https://c.d).");
+
+        let text = HtmlToText {
+            links: HtmlLinks::Footnotes,
+            ..Default::default()
+        }
+        .convert(html);
+        assert_eq!(
+            text,
+            "See this [1] and that [2].\r\n\r\n[1] https://a.b\r\n[2] https://c.d\r\n"
+        );
+
+        let text = HtmlToText {
+            links: HtmlLinks::Strip,
+            ..Default::default()
+        }
+        .convert(html);
+        assert_eq!(text, "See this and that.");
+    }
+
+    #[test]
+    fn tables_and_images() {
+        let html = concat!(
+            "<table><tr><th>Name</th><th>Age</th></tr>",
+            "<tr><td>Alice</td><td>42</td></tr></table>",
+            r#"<img src="logo.png" alt="Logo">"#,
+        );
+
+        let text = HtmlToText::default().convert(html);
+        assert_eq!(text, "NameAgeAlice42");
+
+        let text = HtmlToText {
+            tables: true,
+            images_alt: true,
+            ..Default::default()
+        }
+        .convert(html);
+        assert_eq!(text, "Name | Age\r\nAlice | 42\r\n[Logo]");
+    }
+
+    #[test]
+    fn max_width() {
+        assert_eq!(
+            wrap("aaa bbb ccc\r\n\r\ndddddddd e", 7),
+            "aaa bbb\r\nccc\r\n\r\ndddddddd\r\ne"
+        );
+    }
+}
@@ -2,22 +2,33 @@
 //!
 //! Module dedicated to MIME → MML message body interpretation.
 
-use std::{env, fs, path::PathBuf};
+use std::{borrow::Cow, env, fs, path::PathBuf};
 
 use async_recursion::async_recursion;
-use mail_builder::MessageBuilder;
-use mail_parser::{Message, MessageParser, MessagePart, MimeHeaders, PartType};
-use nanohtml2text::html2text;
+use encoding_rs::UTF_8;
+use mail_builder::{encoders::base64::base64_encode, MessageBuilder};
+use mail_parser::{
+    decoders::{base64::base64_decode, quoted_printable::quoted_printable_decode},
+    Message, MessageParser, MessagePart, MimeHeaders, PartType,
+};
 #[allow(unused_imports)]
 use tracing::{debug, trace, warn};
 
 #[cfg(feature = "pgp")]
-use crate::pgp::Pgp;
+use crate::pgp::{
+    inline::{self, InlineBlock},
+    PartVerificationReport, Pgp, PgpVerificationReport,
+};
 use crate::{Error, Result};
 
 use super::{
-    MULTIPART_BEGIN, MULTIPART_BEGIN_ESCAPED, MULTIPART_END, MULTIPART_END_ESCAPED, PART_BEGIN,
-    PART_BEGIN_ESCAPED, PART_END, PART_END_ESCAPED,
+    charset::{self, CharsetReplacement},
+    html::{HtmlLinks, HtmlToText},
+    ATTACHMENT, CHARSET, CID, DATA_ENCODING, DESCRIPTION, DISPOSITION, ENCODING, ENCODING_7BIT,
+    ENCODING_8BIT, ENCODING_BASE64, ENCODING_QUOTED_PRINTABLE, INLINE, MIXED, MULTIPART_BEGIN,
+    MULTIPART_BEGIN_ESCAPED, MULTIPART_END, MULTIPART_END_ESCAPED, NAME, PART_BEGIN,
+    PART_BEGIN_ESCAPED, PART_END, PART_END_ESCAPED, QUOTE_BEGIN, QUOTE_END, RECIPIENT_FILENAME,
+    TYPE,
 };
 
 /// Filters parts to show by MIME type.
@@ -131,6 +142,19 @@ pub struct MimeBodyInterpreter {
     /// plain text parts starting by the standard delimiter `-- \n`.
     show_plain_texts_signature: bool,
 
+    /// Defines visibility of quote markers in `text/plain` parts.
+    ///
+    /// When `true`, quoted regions of plain text parts (lines
+    /// starting by `>`) are wrapped into `<#quote depth=N>` and
+    /// `<#/quote>` markers, so that interfaces can fold them. Nested
+    /// quotes lead to nested markers. The attribution line preceding
+    /// a top-level quote (`On …, … wrote:`) is added to its marker as
+    /// `attribution` property. Quoted lines are kept as they are.
+    ///
+    /// Markers are not part of the MML specification: they are meant
+    /// to be consumed by interfaces, not to be compiled back.
+    show_quote_markers: bool,
+
     /// Defines the saving strategy of attachments content.
     ///
     /// An attachment is interpreted this way: `<#part
@@ -149,6 +173,35 @@ pub struct MimeBodyInterpreter {
     /// [`std::env::temp_dir()`].
     save_attachments_dir: PathBuf,
 
+    /// Defines how `text/html` parts are converted into plain text.
+    ///
+    /// This option only applies when `text/html` parts are not
+    /// shown as raw HTML, see [`FilterParts::Only`].
+    html_to_text: HtmlToText,
+
+    /// Defines the policy applied to invalid byte sequences when
+    /// decoding text parts from their charset.
+    charset_replacement: CharsetReplacement,
+
+    /// Defines the charset of text parts that do not declare one.
+    ///
+    /// When `None`, text parts without charset are decoded as UTF-8.
+    default_charset: Option<String>,
+
+    /// Defines the lossless mode.
+    ///
+    /// When `true`, the interpreted MML can be compiled back to an
+    /// equivalent MIME message: multiparts are always shown with
+    /// their subtype, all alternative parts are kept, and parts are
+    /// annotated with their charset, transfer encoding, disposition,
+    /// Content-ID and description. Contents are kept transfer-encoded
+    /// as they are, or are encoded in base64 (see the `data-encoding`
+    /// property) when they cannot be represented in MML.
+    ///
+    /// This mode overrides all other visibility and filtering options,
+    /// and does not decrypt nor verify PGP parts.
+    lossless: bool,
+
     #[cfg(feature = "pgp")]
     pgp: Option<Pgp>,
     #[cfg(feature = "pgp")]
@@ -166,8 +219,13 @@ fn default() -> Self {
             show_inline_attachments: true,
             filter_parts: Default::default(),
             show_plain_texts_signature: true,
+            show_quote_markers: false,
             save_attachments: Default::default(),
             save_attachments_dir: Self::default_save_attachments_dir(),
+            html_to_text: Default::default(),
+            charset_replacement: Default::default(),
+            default_charset: None,
+            lossless: false,
             #[cfg(feature = "pgp")]
             pgp: Default::default(),
             #[cfg(feature = "pgp")]
@@ -207,6 +265,11 @@ pub fn with_show_plain_texts_signature(mut self, visibility: bool) -> Self {
         self
     }
 
+    pub fn with_show_quote_markers(mut self, visibility: bool) -> Self {
+        self.show_quote_markers = visibility;
+        self
+    }
+
     pub fn with_show_attachments(mut self, visibility: bool) -> Self {
         self.show_attachments = visibility;
         self
@@ -227,6 +290,53 @@ pub fn with_save_attachments_dir(mut self, dir: impl Into<PathBuf>) -> Self {
         self
     }
 
+    /// Customize the rendering of links when converting `text/html`
+    /// parts into plain text.
+    pub fn with_html_links(mut self, links: HtmlLinks) -> Self {
+        self.html_to_text.links = links;
+        self
+    }
+
+    /// Render HTML tables row by row, with cells separated by ` | `,
+    /// when converting `text/html` parts into plain text.
+    pub fn with_show_html_tables(mut self, visibility: bool) -> Self {
+        self.html_to_text.tables = visibility;
+        self
+    }
+
+    /// Render HTML images as their alternative text `[alt]` when
+    /// converting `text/html` parts into plain text.
+    pub fn with_show_html_images_alt(mut self, visibility: bool) -> Self {
+        self.html_to_text.images_alt = visibility;
+        self
+    }
+
+    /// Wrap lines to the given maximum width when converting
+    /// `text/html` parts into plain text.
+    pub fn with_html_max_width(mut self, width: Option<usize>) -> Self {
+        self.html_to_text.max_width = width;
+        self
+    }
+
+    /// Customize the policy applied to invalid byte sequences when
+    /// decoding text parts from their charset.
+    pub fn with_charset_replacement(mut self, replacement: CharsetReplacement) -> Self {
+        self.charset_replacement = replacement;
+        self
+    }
+
+    /// Decode text parts that do not declare a charset using the
+    /// given one, instead of UTF-8.
+    pub fn with_default_charset(mut self, charset: Option<String>) -> Self {
+        self.default_charset = charset;
+        self
+    }
+
+    pub fn with_lossless(mut self, lossless: bool) -> Self {
+        self.lossless = lossless;
+        self
+    }
+
     #[cfg(feature = "pgp")]
     pub fn set_pgp(&mut self, pgp: impl Into<Pgp>) {
         self.pgp = Some(pgp.into());
@@ -270,6 +380,68 @@ fn escape_mml_markup(text: String) -> String {
             .replace(MULTIPART_END, MULTIPART_END_ESCAPED)
     }
 
+    /// Decode the text contents of the given part.
+    ///
+    /// The text already decoded by the MIME parser is kept for UTF-8
+    /// parts when invalid sequences are replaced. Otherwise the text
+    /// is decoded again from the raw part, using the charset of the
+    /// part or the default one.
+    fn decode_text<'a>(
+        &self,
+        msg: &Message<'_>,
+        part: &MessagePart<'_>,
+        text: &'a str,
+    ) -> Result<Cow<'a, str>> {
+        let charset = part
+            .content_type()
+            .and_then(|ctype| ctype.attribute("charset"))
+            .or(self.default_charset.as_deref());
+
+        let encoding = match charset {
+            Some(charset) if !charset::is_utf8(charset) => match charset::find_encoding(charset) {
+                Some(encoding) => encoding,
+                None if self.charset_replacement == CharsetReplacement::Error => {
+                    return Err(Error::FindCharsetError(charset.to_owned()));
+                }
+                None => {
+                    debug!("cannot find charset {charset}, keeping parsed text");
+                    return Ok(Cow::Borrowed(text));
+                }
+            },
+            _ if self.charset_replacement == CharsetReplacement::Replace => {
+                return Ok(Cow::Borrowed(text));
+            }
+            _ => UTF_8,
+        };
+
+        let raw = msg
+            .raw_message
+            .get(part.raw_body_offset()..part.raw_end_offset())
+            .unwrap_or_default();
+
+        let bytes = match part.content_transfer_encoding() {
+            Some(encoding) if encoding.eq_ignore_ascii_case(ENCODING_BASE64) => {
+                base64_decode(raw).map(Cow::Owned)
+            }
+            Some(encoding) if encoding.eq_ignore_ascii_case(ENCODING_QUOTED_PRINTABLE) => {
+                quoted_printable_decode(raw).map(Cow::Owned)
+            }
+            _ => Some(Cow::Borrowed(raw)),
+        };
+
+        match bytes {
+            Some(bytes) => Ok(Cow::Owned(charset::decode(
+                encoding,
+                &bytes,
+                self.charset_replacement,
+            )?)),
+            None => {
+                debug!("cannot decode text part transfer encoding, keeping parsed text");
+                Ok(Cow::Borrowed(text))
+            }
+        }
+    }
+
     /// Decrypt the given [MessagePart] using PGP.
     #[cfg(feature = "pgp")]
     async fn decrypt_part(&self, encrypted_part: &MessagePart<'_>) -> Result<String> {
@@ -322,6 +494,264 @@ async fn verify_msg(&self, msg: &Message<'_>, ids: &[usize]) -> Result<()> {
         Ok(())
     }
 
+    /// Decrypt the given ASCII-armored PGP inline message.
+    #[cfg(feature = "pgp")]
+    async fn decrypt_inline(&self, encrypted: &str) -> Result<String> {
+        let Some(pgp) = &self.pgp else {
+            debug!("cannot decrypt inline message: pgp not configured");
+            return Ok(encrypted.to_owned());
+        };
+
+        let recipient = self
+            .pgp_recipient
+            .as_ref()
+            .ok_or(Error::PgpDecryptMissingRecipientError)?;
+        let encrypted_bytes = encrypted.as_bytes().to_owned();
+        let decrypted_bytes = pgp.decrypt(recipient, encrypted_bytes).await?;
+
+        Ok(String::from_utf8_lossy(&decrypted_bytes).replace('\r', ""))
+    }
+
+    /// Verify the given PGP inline cleartext signed message.
+    #[cfg(feature = "pgp")]
+    async fn verify_inline(&self, text: &str, signature: &str) -> Result<()> {
+        let Some(pgp) = &self.pgp else {
+            debug!("cannot verify inline message: pgp not configured");
+            return Ok(());
+        };
+
+        let recipient = self
+            .pgp_recipient
+            .as_ref()
+            .ok_or(Error::PgpDecryptMissingRecipientError)?;
+        let signature_bytes = signature.as_bytes().to_owned();
+        let signed_bytes = inline::cleartext_signed_data(text);
+        pgp.verify(recipient, signature_bytes, signed_bytes).await?;
+
+        Ok(())
+    }
+
+    /// Interpret PGP inline blocks of the given plain text.
+    ///
+    /// Encrypted messages are replaced by their decrypted text, and
+    /// cleartext signed messages are verified then replaced by their
+    /// cleartext. Blocks that cannot be decrypted are kept as they
+    /// are.
+    #[cfg(feature = "pgp")]
+    #[async_recursion]
+    async fn interpret_inline_pgp(&self, text: &str) -> String {
+        let text = text.replace('\r', "");
+        let mut plain = String::with_capacity(text.len());
+
+        for block in inline::split_inline_blocks(&text) {
+            match block {
+                InlineBlock::Text(text) => {
+                    plain.push_str(text);
+                }
+                InlineBlock::Encrypted(encrypted) => match self.decrypt_inline(encrypted).await {
+                    Ok(decrypted) if decrypted != encrypted => {
+                        // decrypted texts may contain signed messages
+                        let decrypted = self.interpret_inline_pgp(&decrypted).await;
+                        plain.push_str(&decrypted);
+                    }
+                    Ok(_) => {
+                        plain.push_str(encrypted);
+                    }
+                    Err(err) => {
+                        debug!("cannot decrypt inline message using pgp: {err}");
+                        trace!("{err:?}");
+                        plain.push_str(encrypted);
+                    }
+                },
+                InlineBlock::Signed { text, signature } => {
+                    match self.verify_inline(&text, signature).await {
+                        Ok(()) => {
+                            debug!("inline message successfully verified using pgp");
+                        }
+                        Err(err) => {
+                            debug!("cannot verify inline message using pgp: {err}");
+                            trace!("{err:?}");
+                        }
+                    }
+
+                    plain.push_str(&text);
+                }
+            }
+        }
+
+        plain
+    }
+
+    /// Verify the given signature bytes and signed bytes using PGP,
+    /// and report details about the signature.
+    ///
+    /// Errors are reported rather than returned.
+    #[cfg(feature = "pgp")]
+    async fn verify_with_report(
+        &self,
+        signature_bytes: Vec<u8>,
+        signed_bytes: Vec<u8>,
+    ) -> PgpVerificationReport {
+        let Some(sender) = &self.pgp_sender else {
+            let err = Error::PgpVerifyMissingSenderError;
+            return PgpVerificationReport::from_error("", &err);
+        };
+
+        let report = match &self.pgp {
+            None => Err(Error::PgpMissingConfigurationError),
+            Some(pgp) => {
+                pgp.verify_with_report(sender, signature_bytes, signed_bytes)
+                    .await
+            }
+        };
+
+        report.unwrap_or_else(|err| PgpVerificationReport::from_error(sender, &err))
+    }
+
+    /// Report the verification of PGP inline blocks of the given
+    /// plain text.
+    ///
+    /// Encrypted blocks are decrypted, then their content is
+    /// reported as nested parts.
+    #[cfg(feature = "pgp")]
+    #[async_recursion]
+    async fn interpret_inline_pgp_verification(&self, text: &str) -> Vec<PartVerificationReport> {
+        let text = text.replace('\r', "");
+        let mut reports = Vec::new();
+
+        for block in inline::split_inline_blocks(&text) {
+            match block {
+                InlineBlock::Text(_) => {
+                    // nothing to verify
+                }
+                InlineBlock::Encrypted(encrypted) => match self.decrypt_inline(encrypted).await {
+                    Ok(decrypted) if decrypted != encrypted => {
+                        let parts = self.interpret_inline_pgp_verification(&decrypted).await;
+                        reports.push(PartVerificationReport {
+                            ctype: String::from("text/plain"),
+                            signature: None,
+                            parts,
+                        });
+                    }
+                    Ok(_) => {
+                        // pgp not configured
+                    }
+                    Err(err) => {
+                        debug!("cannot decrypt inline message using pgp: {err}");
+                        trace!("{err:?}");
+                    }
+                },
+                InlineBlock::Signed { text, signature } => {
+                    let signature_bytes = signature.as_bytes().to_owned();
+                    let signed_bytes = inline::cleartext_signed_data(&text);
+                    let report = self.verify_with_report(signature_bytes, signed_bytes).await;
+                    reports.push(PartVerificationReport {
+                        ctype: String::from("text/plain"),
+                        signature: Some(report),
+                        parts: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        reports
+    }
+
+    /// Report the verification of the given [MessagePart] and its
+    /// nested parts.
+    #[cfg(feature = "pgp")]
+    #[async_recursion]
+    async fn interpret_part_verification(
+        &self,
+        msg: &Message<'_>,
+        part: &MessagePart<'_>,
+    ) -> Vec<PartVerificationReport> {
+        let ctype = get_ctype(part);
+
+        match &part.body {
+            PartType::Text(plain) if ctype == "text/plain" => {
+                match self.decode_text(msg, part, plain) {
+                    Ok(plain) if inline::has_inline_blocks(&plain) => {
+                        self.interpret_inline_pgp_verification(&plain).await
+                    }
+                    Ok(_) => Vec::new(),
+                    Err(err) => {
+                        debug!("cannot decode text part: {err}");
+                        trace!("{err:?}");
+                        Vec::new()
+                    }
+                }
+            }
+            PartType::Multipart(ids) if ctype == "multipart/signed" && ids.len() == 2 => {
+                let signed_part = msg.part(ids[0]).unwrap();
+                let signed_part_bytes = msg.raw_message
+                    [signed_part.raw_header_offset()..signed_part.raw_end_offset()]
+                    .to_owned();
+
+                let signature_part = msg.part(ids[1]).unwrap();
+                let signature_bytes = signature_part.contents().to_owned();
+
+                let report = self
+                    .verify_with_report(signature_bytes, signed_part_bytes)
+                    .await;
+
+                vec![PartVerificationReport {
+                    ctype,
+                    signature: Some(report),
+                    parts: self.interpret_part_verification(msg, signed_part).await,
+                }]
+            }
+            PartType::Multipart(ids) if ctype == "multipart/encrypted" && ids.len() == 2 => {
+                let Some(pgp) = &self.pgp else {
+                    debug!("cannot decrypt part: pgp not configured");
+                    return Vec::new();
+                };
+
+                let Some(recipient) = &self.pgp_recipient else {
+                    debug!("cannot decrypt part: missing recipient");
+                    return Vec::new();
+                };
+
+                let encrypted_bytes = msg.part(ids[1]).unwrap().contents().to_owned();
+                let decrypted_bytes = match pgp.decrypt(recipient, encrypted_bytes).await {
+                    Ok(bytes) => bytes,
+                    Err(err) => {
+                        debug!("cannot decrypt email part using pgp: {err}");
+                        trace!("{err:?}");
+                        return Vec::new();
+                    }
+                };
+
+                let Some(clear_msg) = MessageParser::new().parse(&decrypted_bytes) else {
+                    debug!("cannot parse decrypted email part");
+                    return Vec::new();
+                };
+
+                let parts = self
+                    .interpret_part_verification(&clear_msg, clear_msg.root_part())
+                    .await;
+
+                vec![PartVerificationReport {
+                    ctype,
+                    signature: None,
+                    parts,
+                }]
+            }
+            PartType::Multipart(ids) => {
+                let mut reports = Vec::new();
+
+                for id in ids {
+                    if let Some(part) = msg.part(*id) {
+                        reports.extend(self.interpret_part_verification(msg, part).await);
+                    }
+                }
+
+                reports
+            }
+            _ => Vec::new(),
+        }
+    }
+
     fn interpret_attachment(&self, ctype: &str, part: &MessagePart, data: &[u8]) -> Result<String> {
         let mut tpl = String::new();
 
@@ -403,6 +833,10 @@ fn interpret_text_plain(&self, plain: &str) -> String {
                     .unwrap_or(plain);
             }
 
+            if self.show_quote_markers {
+                plain = mark_quotes(&plain);
+            }
+
             tpl.push_str(&plain);
         }
 
@@ -418,7 +852,7 @@ fn interpret_text_html(&self, html: &str) -> String {
                 let html = Self::escape_mml_markup(html);
                 tpl.push_str(&html);
             } else {
-                let html = html2text(&html);
+                let html = self.html_to_text.convert(html);
                 let html = Self::escape_mml_markup(html);
 
                 if self.show_parts {
@@ -436,20 +870,164 @@ fn interpret_text_html(&self, html: &str) -> String {
         tpl
     }
 
+    /// Interpret the given part losslessly.
+    ///
+    /// See [`MimeBodyInterpreter::lossless`].
+    fn interpret_part_lossless(&self, msg: &Message<'_>, part: &MessagePart<'_>) -> Result<String> {
+        let mut tpl = String::new();
+
+        if let PartType::Multipart(ids) = &part.body {
+            let stype = part
+                .content_type()
+                .and_then(|ctype| ctype.subtype())
+                .unwrap_or(MIXED);
+
+            tpl.push_str(&format!("{MULTIPART_BEGIN} {TYPE}={stype}"));
+            if let Some(description) = part.content_description() {
+                tpl.push_str(&format!(" {DESCRIPTION}={}", quote_prop_val(description)));
+            }
+            tpl.push_str(">\n");
+
+            for id in ids {
+                if let Some(part) = msg.part(*id) {
+                    tpl.push_str(&self.interpret_part_lossless(msg, part)?);
+                } else {
+                    debug!("cannot find part {id}, skipping it");
+                }
+            }
+
+            tpl.push_str(MULTIPART_END);
+            tpl.push('\n');
+
+            return Ok(tpl);
+        }
+
+        let ctype = part.content_type();
+        let raw = msg
+            .raw_message
+            .get(part.raw_body_offset()..part.raw_end_offset())
+            .unwrap_or_default();
+
+        let encoding = match part.content_transfer_encoding() {
+            Some(encoding) => encoding.to_ascii_lowercase(),
+            None if raw.is_ascii() => ENCODING_7BIT.to_owned(),
+            None => ENCODING_8BIT.to_owned(),
+        };
+
+        let supported_encodings = [
+            ENCODING_7BIT,
+            ENCODING_8BIT,
+            ENCODING_QUOTED_PRINTABLE,
+            ENCODING_BASE64,
+        ];
+
+        // contents are kept transfer-encoded, unless the encoding is
+        // not supported by MML, in which case decoded contents are
+        // used instead
+        let (encoding, contents) = if supported_encodings.contains(&encoding.as_str()) {
+            (Some(encoding), raw)
+        } else {
+            debug!("unsupported transfer encoding {encoding}, using decoded contents");
+            (None, part.contents())
+        };
+
+        let mut props = vec![(TYPE, get_ctype(part))];
+
+        let charset = match (&part.body, &encoding) {
+            // decoded texts are always UTF-8
+            (PartType::Text(_) | PartType::Html(_), None) => Some("utf-8"),
+            _ => ctype.and_then(|ctype| ctype.attribute("charset")),
+        };
+
+        if let Some(charset) = charset {
+            props.push((CHARSET, quote_prop_val(charset)));
+        }
+
+        if let Some(name) = ctype.and_then(|ctype| ctype.attribute("name")) {
+            props.push((NAME, quote_prop_val(name)));
+        }
+
+        if let Some(encoding) = encoding {
+            props.push((ENCODING, encoding));
+        }
+
+        if let Some(disposition) = part.content_disposition() {
+            if disposition.is_attachment() {
+                props.push((DISPOSITION, ATTACHMENT.to_owned()));
+            } else if disposition.is_inline() {
+                props.push((DISPOSITION, INLINE.to_owned()));
+            }
+
+            if let Some(filename) = disposition.attribute("filename") {
+                props.push((RECIPIENT_FILENAME, quote_prop_val(filename)));
+            }
+        }
+
+        if let Some(cid) = part.content_id() {
+            props.push((CID, quote_prop_val(cid)));
+        }
+
+        if let Some(description) = part.content_description() {
+            props.push((DESCRIPTION, quote_prop_val(description)));
+        }
+
+        let text = std::str::from_utf8(contents)
+            .ok()
+            .filter(|text| !has_mml_markup(text));
+
+        let contents = match text {
+            Some(text) => text.to_owned(),
+            None => {
+                props.push((DATA_ENCODING, ENCODING_BASE64.to_owned()));
+                let data = base64_encode(contents).map_err(Error::EncodePartDataError)?;
+                let mut lines = data.chunks(76).fold(String::new(), |mut lines, line| {
+                    lines.push_str(&String::from_utf8_lossy(line));
+                    lines.push('\n');
+                    lines
+                });
+                lines.pop();
+                lines
+            }
+        };
+
+        tpl.push_str(PART_BEGIN);
+        for (key, val) in props {
+            tpl.push_str(&format!(" {key}={val}"));
+        }
+        tpl.push_str(">\n");
+        tpl.push_str(&contents);
+        tpl.push_str(PART_END);
+        tpl.push('\n');
+
+        Ok(tpl)
+    }
+
     #[async_recursion]
     async fn interpret_part(&self, msg: &Message<'_>, part: &MessagePart<'_>) -> Result<String> {
+        if self.lossless {
+            return self.interpret_part_lossless(msg, part);
+        }
+
         let mut tpl = String::new();
         let ctype = get_ctype(part);
 
         match &part.body {
             PartType::Text(plain) if ctype == "text/plain" => {
-                tpl.push_str(&self.interpret_text_plain(plain));
+                let plain = self.decode_text(msg, part, plain)?;
+                #[cfg(feature = "pgp")]
+                let plain = match inline::has_inline_blocks(&plain) {
+                    true => Cow::Owned(self.interpret_inline_pgp(&plain).await),
+                    false => plain,
+                };
+                tpl.push_str(&self.interpret_text_plain(&plain));
             }
             PartType::Text(text) => {
-                tpl.push_str(&self.interpret_text(&ctype, text));
+                let text = self.decode_text(msg, part, text)?;
+                tpl.push_str(&self.interpret_text(&ctype, &text));
             }
             PartType::Html(html) => {
-                tpl.push_str(&self.interpret_text_html(html));
+                let html = self.decode_text(msg, part, html)?;
+                tpl.push_str(&self.interpret_text_html(&html));
             }
             PartType::Binary(data) => {
                 tpl.push_str(&self.interpret_attachment(&ctype, part, data)?);
@@ -471,15 +1049,19 @@ async fn interpret_part(&self, msg: &Message<'_>, part: &MessagePart<'_>) -> Res
                                 PartType::Text(plain)
                                     if is_plain(part) && !plain.trim().is_empty() =>
                                 {
-                                    Some(Ok(self.interpret_text_plain(plain)))
+                                    Some(
+                                        self.decode_text(msg, part, plain)
+                                            .map(|plain| self.interpret_text_plain(&plain)),
+                                    )
                                 }
                                 _ => None,
                             })
                             .or_else(|| {
                                 parts.clone().find_map(|part| match &part.body {
-                                    PartType::Html(html) if !html.trim().is_empty() => {
-                                        Some(Ok(self.interpret_text_html(html)))
-                                    }
+                                    PartType::Html(html) if !html.trim().is_empty() => Some(
+                                        self.decode_text(msg, part, html)
+                                            .map(|html| self.interpret_text_html(&html)),
+                                    ),
                                     _ => None,
                                 })
                             })
@@ -487,9 +1069,10 @@ async fn interpret_part(&self, msg: &Message<'_>, part: &MessagePart<'_>) -> Res
                                 parts.clone().find_map(|part| {
                                     let ctype = get_ctype(part);
                                     match &part.body {
-                                        PartType::Text(text) if !text.trim().is_empty() => {
-                                            Some(Ok(self.interpret_text(&ctype, text)))
-                                        }
+                                        PartType::Text(text) if !text.trim().is_empty() => Some(
+                                            self.decode_text(msg, part, text)
+                                                .map(|text| self.interpret_text(&ctype, &text)),
+                                        ),
                                         _ => None,
                                     }
                                 })
@@ -596,6 +1179,20 @@ pub async fn interpret_msg<'a>(&self, msg: &Message<'a>) -> Result<String> {
         self.interpret_part(msg, msg.root_part()).await
     }
 
+    /// Report the PGP verification of the given MIME [Message].
+    ///
+    /// Signatures are verified using the PGP sender, and encrypted
+    /// parts are decrypted using the PGP recipient so that their
+    /// content can be verified too. The returned reports follow the
+    /// nested structure of the message.
+    #[cfg(feature = "pgp")]
+    pub async fn interpret_msg_verification<'a>(
+        &self,
+        msg: &Message<'a>,
+    ) -> Vec<PartVerificationReport> {
+        self.interpret_part_verification(msg, msg.root_part()).await
+    }
+
     /// Interpret the given MIME message bytes as a MML message
     /// string.
     pub async fn interpret_bytes<'a>(&self, bytes: impl AsRef<[u8]> + 'a) -> Result<String> {
@@ -627,12 +1224,93 @@ fn is_plain(part: &MessagePart) -> bool {
     get_ctype(part) == "text/plain"
 }
 
+/// Return `true` if the given text contains MML markup.
+fn has_mml_markup(text: &str) -> bool {
+    [PART_BEGIN, PART_END, MULTIPART_BEGIN, MULTIPART_END]
+        .iter()
+        .any(|markup| text.contains(markup))
+}
+
+/// Return the quote depth of the given plain text line.
+///
+/// The depth is the number of leading `>`, optionally separated by
+/// spaces (`> > text`).
+fn quote_depth(line: &str) -> usize {
+    let mut depth = 0;
+
+    for c in line.chars() {
+        match c {
+            '>' => depth += 1,
+            ' ' if depth > 0 => continue,
+            _ => break,
+        }
+    }
+
+    depth
+}
+
+/// Wrap quoted regions of the given plain text into quote markers.
+///
+/// See [`MimeBodyInterpreter::show_quote_markers`].
+fn mark_quotes(text: &str) -> String {
+    let mut marked = String::with_capacity(text.len());
+    let mut depth = 0;
+    let mut prev_line: Option<&str> = None;
+
+    for line in text.split_inclusive('\n') {
+        let line_depth = quote_depth(line);
+
+        for _ in line_depth..depth {
+            marked.push_str(QUOTE_END);
+            marked.push('\n');
+        }
+
+        for d in depth + 1..=line_depth {
+            marked.push_str(&format!("{QUOTE_BEGIN} depth={d}"));
+
+            let attribution = prev_line
+                .map(str::trim)
+                .filter(|line| d == 1 && line.ends_with(':'));
+
+            if let Some(attribution) = attribution {
+                marked.push_str(&format!(" attribution={}", quote_prop_val(attribution)));
+            }
+
+            marked.push_str(">\n");
+        }
+
+        if !line.trim().is_empty() {
+            prev_line = Some(line);
+        }
+
+        depth = line_depth;
+        marked.push_str(line);
+    }
+
+    if depth > 0 && !marked.ends_with('\n') {
+        marked.push('\n');
+    }
+
+    for _ in 0..depth {
+        marked.push_str(QUOTE_END);
+        marked.push('\n');
+    }
+
+    marked
+}
+
+/// Quote the given MML property value.
+fn quote_prop_val(val: &str) -> String {
+    let val = val.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{val}\"")
+}
+
 #[cfg(test)]
 mod tests {
     use concat_with::concat_line;
     use mail_builder::{mime::MimePart, MessageBuilder};
 
-    use super::{FilterParts, MimeBodyInterpreter};
+    use super::{CharsetReplacement, FilterParts, MimeBodyInterpreter};
 
     #[tokio::test]
     async fn nested_multiparts() {
@@ -1012,4 +1690,214 @@ async fn hide_parts_multipart_mixed() {
 
         assert_eq!(tpl, expected_tpl);
     }
+
+    #[tokio::test]
+    async fn lossless() {
+        let builder = MessageBuilder::new()
+            .text_body("Hello")
+            .html_body("<p>Hello</p>");
+
+        let tpl = MimeBodyInterpreter::new()
+            .with_lossless(true)
+            .interpret_msg_builder(builder)
+            .await
+            .unwrap();
+
+        let expected_tpl = concat_line!(
+            "<#multipart type=alternative>",
+            "<#part type=text/plain charset=\"utf-8\" encoding=7bit>",
+            "Hello<#/part>",
+            "<#part type=text/html charset=\"utf-8\" encoding=7bit>",
+            "<p>Hello</p><#/part>",
+            "<#/multipart>",
+            "",
+        );
+
+        assert_eq!(tpl, expected_tpl);
+    }
+
+    #[tokio::test]
+    async fn charsets() {
+        let msg = [
+            b"Content-Type: multipart/mixed; boundary=\"b\"\r\n".as_slice(),
+            b"\r\n",
+            b"--b\r\n",
+            b"Content-Type: text/plain; charset=koi8-r\r\n",
+            b"Content-Transfer-Encoding: 8bit\r\n",
+            b"\r\n",
+            b"\xf0\xd2\xc9\xd7\xc5\xd4\r\n",
+            b"--b\r\n",
+            b"Content-Type: text/plain; charset=shift_jis\r\n",
+            b"Content-Transfer-Encoding: base64\r\n",
+            b"\r\n",
+            b"grGC8Q==\r\n",
+            b"--b--\r\n",
+        ]
+        .concat();
+
+        let tpl = MimeBodyInterpreter::new()
+            .interpret_bytes(&msg)
+            .await
+            .unwrap();
+
+        assert_eq!(tpl, "Приветこん");
+    }
+
+    #[tokio::test]
+    async fn charset_replacement() {
+        let msg = b"Content-Type: text/plain\r\n\r\ncaf\xe9!";
+
+        let tpl = MimeBodyInterpreter::new()
+            .interpret_bytes(msg)
+            .await
+            .unwrap();
+        assert_eq!(tpl, "caf\u{FFFD}!");
+
+        let tpl = MimeBodyInterpreter::new()
+            .with_default_charset(Some("iso-8859-1".into()))
+            .interpret_bytes(msg)
+            .await
+            .unwrap();
+        assert_eq!(tpl, "café!");
+
+        let tpl = MimeBodyInterpreter::new()
+            .with_charset_replacement(CharsetReplacement::Skip)
+            .interpret_bytes(msg)
+            .await
+            .unwrap();
+        assert_eq!(tpl, "caf!");
+
+        let res = MimeBodyInterpreter::new()
+            .with_charset_replacement(CharsetReplacement::Error)
+            .interpret_bytes(msg)
+            .await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn quote_markers() {
+        let builder = MessageBuilder::new().body(MimePart::new(
+            "text/plain",
+            concat_line!(
+                "Hello!",
+                "",
+                "On Mon, 1 Jan 2024, Bob wrote:",
+                "> Hi,",
+                "> > How are you?",
+                "> Fine.",
+                "",
+                "Bye",
+            ),
+        ));
+
+        let tpl = MimeBodyInterpreter::new()
+            .interpret_msg_builder(builder.clone())
+            .await
+            .unwrap();
+
+        assert!(!tpl.contains("<#quote"));
+
+        let tpl = MimeBodyInterpreter::new()
+            .with_show_quote_markers(true)
+            .interpret_msg_builder(builder)
+            .await
+            .unwrap();
+
+        let expected_tpl = concat_line!(
+            "Hello!",
+            "",
+            "On Mon, 1 Jan 2024, Bob wrote:",
+            "<#quote depth=1 attribution=\"On Mon, 1 Jan 2024, Bob wrote:\">",
+            "> Hi,",
+            "<#quote depth=2>",
+            "> > How are you?",
+            "<#/quote>",
+            "> Fine.",
+            "<#/quote>",
+            "",
+            "Bye",
+        );
+
+        assert_eq!(tpl, expected_tpl);
+    }
+
+    #[cfg(feature = "pgp")]
+    #[tokio::test]
+    async fn pgp_inline_signed() {
+        let builder = MessageBuilder::new().body(MimePart::new(
+            "text/plain",
+            concat_line!(
+                "-----BEGIN PGP SIGNED MESSAGE-----",
+                "Hash: SHA256",
+                "",
+                "Hello!",
+                "- -- ",
+                "Alice",
+                "-----BEGIN PGP SIGNATURE-----",
+                "",
+                "iHUEARYIAB0WIQQ=",
+                "-----END PGP SIGNATURE-----",
+                "",
+            ),
+        ));
+
+        let tpl = MimeBodyInterpreter::new()
+            .interpret_msg_builder(builder)
+            .await
+            .unwrap();
+
+        assert_eq!(tpl, concat_line!("Hello!", "-- ", "Alice", ""));
+    }
+
+    #[cfg(feature = "pgp")]
+    #[tokio::test]
+    async fn pgp_verification_report() {
+        use mail_parser::MessageParser;
+
+        let signed = concat_line!(
+            "-----BEGIN PGP SIGNED MESSAGE-----",
+            "Hash: SHA256",
+            "",
+            "Hello!",
+            "-----BEGIN PGP SIGNATURE-----",
+            "",
+            "iHUEARYIAB0WIQQ=",
+            "-----END PGP SIGNATURE-----",
+            "",
+        );
+
+        let builder = MessageBuilder::new().body(MimePart::new(
+            "multipart/mixed",
+            vec![
+                MimePart::new("text/plain", "Not signed.\n"),
+                MimePart::new(
+                    "multipart/signed",
+                    vec![
+                        MimePart::new("text/plain", signed),
+                        MimePart::new("application/pgp-signature", "signature"),
+                    ],
+                ),
+            ],
+        ));
+        let bytes = builder.write_to_vec().unwrap();
+        let msg = MessageParser::new().parse(&bytes).unwrap();
+
+        let reports = MimeBodyInterpreter::new()
+            .with_pgp_sender(Some(String::from("alice@localhost")))
+            .interpret_msg_verification(&msg)
+            .await;
+
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].ctype, "multipart/signed");
+        assert!(!reports[0].is_valid());
+
+        let signature = reports[0].signature.as_ref().unwrap();
+        assert_eq!(signature.signer, "alice@localhost");
+        assert!(signature.error.is_some());
+
+        let parts = &reports[0].parts;
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].ctype, "text/plain");
+        assert!(parts[0].signature.is_some());
+    }
 }
@@ -6,17 +6,37 @@
 
 #![allow(dead_code)]
 
+mod charset;
 #[cfg(feature = "compiler")]
 pub mod compiler;
 #[cfg(feature = "interpreter")]
+mod html;
+#[cfg(feature = "interpreter")]
 pub mod interpreter;
 
 #[cfg(feature = "compiler")]
 #[doc(inline)]
-pub use self::compiler::MmlBodyCompiler;
+pub use self::charset::TransferEncoding;
+#[cfg(feature = "compiler")]
+#[doc(inline)]
+pub use self::compiler::{
+    Diagnostic, DiagnosticKind, ExternalAttachment, ExternalizeFn, ExternalizeResult,
+    MmlBodyCompiler, OversizeStrategy, Position, Severity, Signature, SignaturePlacement,
+    SizeLimits, Span,
+};
+#[cfg(feature = "image")]
+#[doc(inline)]
+pub use self::compiler::{
+    ImageDownscaling, DEFAULT_IMAGE_JPEG_QUALITY, DEFAULT_IMAGE_MAX_DIMENSION,
+    DEFAULT_IMAGE_MIN_SIZE,
+};
 #[cfg(feature = "interpreter")]
 #[doc(inline)]
-pub use self::interpreter::{FilterParts, MimeBodyInterpreter};
+pub use self::{
+    charset::CharsetReplacement,
+    html::HtmlLinks,
+    interpreter::{FilterParts, MimeBodyInterpreter},
+};
 
 pub(crate) const PART_BEGIN: &str = "<#part";
 pub(crate) const PART_BEGIN_ESCAPED: &str = "<#!part";
@@ -28,9 +48,14 @@
 pub(crate) const MULTIPART_END: &str = "<#/multipart>";
 pub(crate) const MULTIPART_END_ESCAPED: &str = "<#!/multipart>";
 
+pub(crate) const QUOTE_BEGIN: &str = "<#quote";
+pub(crate) const QUOTE_END: &str = "<#/quote>";
+
 pub(crate) const ALTERNATIVE: &str = "alternative";
 pub(crate) const ATTACHMENT: &str = "attachment";
+pub(crate) const CALENDAR: &str = "text/calendar";
 pub(crate) const CHARSET: &str = "charset";
+pub(crate) const CID: &str = "cid";
 pub(crate) const CREATION_DATE: &str = "creation-date";
 pub(crate) const DATA_ENCODING: &str = "data-encoding";
 pub(crate) const DESCRIPTION: &str = "description";
@@ -44,10 +69,15 @@
 pub(crate) const ENCRYPT: &str = "encrypt";
 pub(crate) const FILENAME: &str = "filename";
 pub(crate) const INLINE: &str = "inline";
+#[cfg(feature = "markdown")]
+pub(crate) const MARKDOWN: &str = "text/markdown";
+pub(crate) const METHOD: &str = "method";
 pub(crate) const MIXED: &str = "mixed";
 pub(crate) const MODIFICATION_DATE: &str = "modification-date";
 pub(crate) const NAME: &str = "name";
 #[cfg(feature = "pgp")]
+pub(crate) const PGP_INLINE: &str = "pgpinline";
+#[cfg(feature = "pgp")]
 pub(crate) const PGP_MIME: &str = "pgpmime";
 pub(crate) const READ_DATE: &str = "read-date";
 #[cfg(feature = "pgp")]
@@ -58,8 +88,14 @@
 pub(crate) const SENDER: &str = "sender";
 #[cfg(feature = "pgp")]
 pub(crate) const SIGN: &str = "sign";
+pub(crate) const SIGNATURE: &str = "signature";
+pub(crate) const SIGNATURE_ABOVE: &str = "above";
+pub(crate) const SIGNATURE_BELOW: &str = "below";
+pub(crate) const SIGNATURE_NONE: &str = "none";
 pub(crate) const SIZE: &str = "size";
 pub(crate) const TYPE: &str = "type";
+#[cfg(feature = "remote")]
+pub(crate) const URL: &str = "url";
 
 pub(crate) const BACKSLASH: char = '\\';
 pub(crate) const DOUBLE_QUOTE: char = '"';
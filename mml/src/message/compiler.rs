@@ -2,12 +2,30 @@
 //!
 //! Module dedicated to MML → MIME message compilation.
 
-use mail_builder::{headers::text::Text, MessageBuilder};
-use mail_parser::{Message, MessageParser};
-
+#[cfg(feature = "remote")]
+use std::time::Duration;
+
+use mail_builder::{
+    headers::{raw::Raw, text::Text},
+    MessageBuilder,
+};
+use mail_parser::{HeaderValue, Message, MessageParser};
+
+#[cfg(feature = "image")]
+use crate::message::ImageDownscaling;
+#[cfg(feature = "template")]
+use crate::message::{template, TemplateVars};
 #[cfg(feature = "pgp")]
-use crate::{message::header, pgp::Pgp};
-use crate::{message::MmlBodyCompiler, Error, Result};
+use crate::pgp::Pgp;
+#[cfg(feature = "autocrypt")]
+use crate::pgp::{autocrypt::AUTOCRYPT, PreferEncrypt};
+use crate::{
+    message::{
+        body::compiler::check, header, Diagnostic, MmlBodyCompiler, Signature, SizeLimits,
+        TransferEncoding,
+    },
+    Error, Result,
+};
 
 /// MML → MIME message compiler builder.
 ///
@@ -17,6 +35,13 @@
 pub struct MmlCompilerBuilder {
     /// The internal MML to MIME message body compiler.
     mml_body_compiler: MmlBodyCompiler,
+
+    /// The template variables.
+    ///
+    /// When defined, the MML message is evaluated as a template
+    /// before being compiled.
+    #[cfg(feature = "template")]
+    vars: Option<TemplateVars>,
 }
 
 impl MmlCompilerBuilder {
@@ -51,25 +76,159 @@ pub fn with_some_pgp(mut self, pgp: Option<impl Into<Pgp>>) -> Self {
         self
     }
 
+    /// Encrypt parts to the sender as well.
+    ///
+    /// See [`MmlBodyCompiler::set_pgp_encrypt_to_self`].
+    #[cfg(feature = "pgp")]
+    pub fn set_pgp_encrypt_to_self(&mut self, encrypt_to_self: bool) {
+        self.mml_body_compiler
+            .set_pgp_encrypt_to_self(encrypt_to_self);
+    }
+
+    /// Encrypt parts to the sender as well.
+    ///
+    /// See [`MmlBodyCompiler::set_pgp_encrypt_to_self`].
+    #[cfg(feature = "pgp")]
+    pub fn with_pgp_encrypt_to_self(mut self, encrypt_to_self: bool) -> Self {
+        self.mml_body_compiler
+            .set_pgp_encrypt_to_self(encrypt_to_self);
+        self
+    }
+
+    /// Enable the generation of the Autocrypt header.
+    ///
+    /// See [`MmlBodyCompiler::set_autocrypt`].
+    #[cfg(feature = "autocrypt")]
+    pub fn set_autocrypt(&mut self, prefer_encrypt: PreferEncrypt) {
+        self.mml_body_compiler.set_autocrypt(prefer_encrypt);
+    }
+
+    /// Enable the generation of the Autocrypt header.
+    ///
+    /// See [`MmlBodyCompiler::set_autocrypt`].
+    #[cfg(feature = "autocrypt")]
+    pub fn with_autocrypt(mut self, prefer_encrypt: PreferEncrypt) -> Self {
+        self.mml_body_compiler.set_autocrypt(prefer_encrypt);
+        self
+    }
+
+    /// Customize the size limits.
+    pub fn set_size_limits(&mut self, limits: SizeLimits) {
+        self.mml_body_compiler.set_size_limits(limits);
+    }
+
+    /// Customize the size limits.
+    pub fn with_size_limits(mut self, limits: SizeLimits) -> Self {
+        self.mml_body_compiler.set_size_limits(limits);
+        self
+    }
+
+    /// Customize the charset of inline text parts.
+    pub fn set_charset(&mut self, charset: impl ToString) {
+        self.mml_body_compiler.set_charset(charset);
+    }
+
+    /// Customize the charset of inline text parts.
+    pub fn with_charset(mut self, charset: impl ToString) -> Self {
+        self.mml_body_compiler.set_charset(charset);
+        self
+    }
+
+    /// Customize the transfer encoding of inline text parts.
+    pub fn set_transfer_encoding(&mut self, encoding: TransferEncoding) {
+        self.mml_body_compiler.set_transfer_encoding(encoding);
+    }
+
+    /// Customize the transfer encoding of inline text parts.
+    pub fn with_transfer_encoding(mut self, encoding: TransferEncoding) -> Self {
+        self.mml_body_compiler.set_transfer_encoding(encoding);
+        self
+    }
+
+    /// Customize the signature inserted into bodies.
+    pub fn set_signature(&mut self, signature: Signature) {
+        self.mml_body_compiler.set_signature(signature);
+    }
+
+    /// Customize the signature inserted into bodies.
+    pub fn with_signature(mut self, signature: Signature) -> Self {
+        self.mml_body_compiler.set_signature(signature);
+        self
+    }
+
+    /// Customize the downscaling of image attachments.
+    #[cfg(feature = "image")]
+    pub fn set_image_downscaling(&mut self, downscaling: ImageDownscaling) {
+        self.mml_body_compiler.set_image_downscaling(downscaling);
+    }
+
+    /// Customize the downscaling of image attachments.
+    #[cfg(feature = "image")]
+    pub fn with_image_downscaling(mut self, downscaling: ImageDownscaling) -> Self {
+        self.mml_body_compiler.set_image_downscaling(downscaling);
+        self
+    }
+
+    /// Customize the maximum size of remote parts.
+    #[cfg(feature = "remote")]
+    pub fn set_remote_max_size(&mut self, max_size: u64) {
+        self.mml_body_compiler.set_remote_max_size(max_size);
+    }
+
+    /// Customize the maximum size of remote parts.
+    #[cfg(feature = "remote")]
+    pub fn with_remote_max_size(mut self, max_size: u64) -> Self {
+        self.mml_body_compiler.set_remote_max_size(max_size);
+        self
+    }
+
+    /// Customize the timeout of remote parts downloads.
+    #[cfg(feature = "remote")]
+    pub fn set_remote_timeout(&mut self, timeout: Duration) {
+        self.mml_body_compiler.set_remote_timeout(timeout);
+    }
+
+    /// Customize the timeout of remote parts downloads.
+    #[cfg(feature = "remote")]
+    pub fn with_remote_timeout(mut self, timeout: Duration) -> Self {
+        self.mml_body_compiler.set_remote_timeout(timeout);
+        self
+    }
+
+    /// Customize the template variables.
+    #[cfg(feature = "template")]
+    pub fn set_vars(&mut self, vars: TemplateVars) {
+        self.vars = Some(vars);
+    }
+
+    /// Customize the template variables.
+    #[cfg(feature = "template")]
+    pub fn with_vars(mut self, vars: TemplateVars) -> Self {
+        self.set_vars(vars);
+        self
+    }
+
     /// Build the final [MmlCompiler] based on the defined options.
     pub fn build(self, mml_msg: &str) -> Result<MmlCompiler<'_>> {
-        let mml_msg = MessageParser::new()
-            .parse(mml_msg.as_bytes())
-            .ok_or(Error::ParseMessageError)?;
-        let mml_body_compiler = self.mml_body_compiler;
-
-        #[cfg(feature = "pgp")]
-        let mml_body_compiler = mml_body_compiler
-            .with_pgp_recipients(header::extract_emails(mml_msg.to()))
-            .with_pgp_sender(header::extract_first_email(mml_msg.from()));
+        #[cfg(feature = "template")]
+        if let Some(vars) = self.vars.as_ref() {
+            let mml_msg = template::render(mml_msg, vars)?;
+            let mml_msg = parse_message(&mml_msg)?.into_owned();
+            return Ok(MmlCompiler::new(mml_msg, self.mml_body_compiler));
+        }
 
-        Ok(MmlCompiler {
-            mml_msg,
-            mml_body_compiler,
-        })
+        let mml_msg = parse_message(mml_msg)?;
+        Ok(MmlCompiler::new(mml_msg, self.mml_body_compiler))
     }
 }
 
+/// Parse the given raw MML message.
+fn parse_message(mml_msg: &str) -> Result<Message<'_>> {
+    MessageParser::new()
+        .parse(mml_msg.as_bytes())
+        .ok_or(Error::ParseMessageError)
+}
+
 /// MML → MIME message compiler.
 ///
 /// This structure allows users to choose the final form of the
@@ -80,7 +239,52 @@ pub struct MmlCompiler<'a> {
     mml_body_compiler: MmlBodyCompiler,
 }
 
-impl MmlCompiler<'_> {
+impl<'a> MmlCompiler<'a> {
+    fn new(mml_msg: Message<'a>, mml_body_compiler: MmlBodyCompiler) -> Self {
+        #[cfg(feature = "pgp")]
+        let mml_body_compiler = mml_body_compiler
+            .with_pgp_recipients(header::extract_emails(mml_msg.to()))
+            .with_pgp_hidden_recipients(header::extract_emails(mml_msg.bcc()))
+            .with_pgp_sender(header::extract_first_email(mml_msg.from()));
+
+        Self {
+            mml_msg,
+            mml_body_compiler,
+        }
+    }
+
+    /// Evaluate the inner MML message as a template, using the given
+    /// variables.
+    ///
+    /// Both headers and body are evaluated, see the [template]
+    /// module for the supported syntax. This is useful for mail-merge
+    /// style generation of messages.
+    #[cfg(feature = "template")]
+    pub fn with_vars(self, vars: TemplateVars) -> Result<MmlCompiler<'static>> {
+        let mml_msg = std::str::from_utf8(self.mml_msg.raw_message())
+            .map_err(Error::RenderTemplateUtf8Error)?;
+        let mml_msg = template::render(mml_msg, &vars)?;
+        let mml_msg = parse_message(&mml_msg)?.into_owned();
+        Ok(MmlCompiler::new(mml_msg, self.mml_body_compiler))
+    }
+
+    /// Check the inner MML message without compiling it.
+    ///
+    /// Returns the diagnostics found in the MML body (unknown
+    /// properties, unclosed multiparts, missing files, conflicting
+    /// PGP properties etc), with spans relative to the whole MML
+    /// message. An empty list means that the message is valid.
+    pub fn check(&self) -> Vec<Diagnostic> {
+        let Some(part) = self.mml_msg.parts.first() else {
+            return Vec::new();
+        };
+
+        let raw = String::from_utf8_lossy(&self.mml_msg.raw_message);
+        let body_offset = part.offset_body.min(raw.len());
+
+        check::check(&raw, body_offset)
+    }
+
     /// Compile the inner MML message into a [MmlCompileResult].
     ///
     /// The fact to return a intermediate structure allows users to
@@ -100,12 +304,60 @@ pub async fn compile(&self) -> Result<MmlCompileResult<'_>> {
 
         mime_msg_builder = mime_msg_builder.header("MIME-Version", Text::new("1.0"));
 
+        let mut importance = None;
+        let mut x_priority = None;
+
         for header in self.mml_msg.headers() {
             let key = header.name.as_str();
-            let val = super::header::to_builder_val(header);
+
+            // priority headers are validated, so that they can be
+            // understood by receivers
+            if key.eq_ignore_ascii_case(header::IMPORTANCE) {
+                let val = header::display_raw_value(&self.mml_msg.raw_message, header);
+                let level = header::parse_importance(&val)
+                    .ok_or(Error::InvalidPriorityHeaderError(header::IMPORTANCE, val))?;
+                importance = Some(level);
+            } else if key.eq_ignore_ascii_case(header::X_PRIORITY) {
+                let val = header::display_raw_value(&self.mml_msg.raw_message, header);
+                let level = header::parse_x_priority(&val)
+                    .ok_or(Error::InvalidPriorityHeaderError(header::X_PRIORITY, val))?;
+                x_priority = Some(level);
+            }
+
+            let val = match header.value {
+                // trace headers cannot be built back from their
+                // parsed value, so their raw value is kept instead
+                HeaderValue::Received(_) => {
+                    let val = header::display_raw_value(&self.mml_msg.raw_message, header);
+                    Raw::new(val).into()
+                }
+                _ => header::to_builder_val(header),
+            };
             mime_msg_builder = mime_msg_builder.header(key, val);
         }
 
+        // clients understand either one or the other priority
+        // header, so the missing one is added
+        match (importance, x_priority) {
+            (Some(level), None) => {
+                let val = Text::new(header::to_x_priority(level));
+                mime_msg_builder = mime_msg_builder.header(header::X_PRIORITY, val);
+            }
+            (None, Some(level)) => {
+                mime_msg_builder = mime_msg_builder.header(header::IMPORTANCE, Text::new(level));
+            }
+            _ => (),
+        }
+
+        // headers explicitly set in the MML message take precedence
+        #[cfg(feature = "autocrypt")]
+        if self.mml_msg.header(AUTOCRYPT).is_none() {
+            if let Some(header) = mml_body_compiler.autocrypt_header().await? {
+                let val = Raw::new(header.to_string());
+                mime_msg_builder = mime_msg_builder.header(AUTOCRYPT, val);
+            }
+        }
+
         Ok(MmlCompileResult { mime_msg_builder })
     }
 }
@@ -116,7 +368,7 @@ pub async fn compile(&self) -> Result<MmlCompileResult<'_>> {
 /// desired MIME message: [MessageBuilder], [Vec], [String] etc.
 #[derive(Clone, Debug, Default)]
 pub struct MmlCompileResult<'a> {
-    mime_msg_builder: MessageBuilder<'a>,
+    pub(crate) mime_msg_builder: MessageBuilder<'a>,
 }
 
 impl<'a> MmlCompileResult<'a> {
@@ -191,6 +443,27 @@ async fn non_ascii_headers() {
         assert_eq!(mml_msg, expected_mml_msg);
     }
 
+    #[tokio::test]
+    async fn priority_headers() {
+        let compile = |importance: &'static str| async move {
+            let mml = format!("Importance: {importance}\nSubject: test\n\nHello\n");
+            let compiled = MmlCompilerBuilder::new()
+                .build(&mml)
+                .unwrap()
+                .compile()
+                .await?
+                .into_string()
+                .unwrap();
+            crate::Result::Ok(compiled)
+        };
+
+        let compiled = compile("High").await.unwrap();
+        assert!(compiled.contains("Importance: High"));
+        assert!(compiled.contains("X-Priority: 1 (Highest)"));
+
+        assert!(compile("urgent").await.is_err());
+    }
+
     #[tokio::test]
     async fn message_id_with_angles() {
         let mml = concat_line!(
@@ -257,6 +530,108 @@ async fn message_id_without_angles() {
         assert_eq!(mml_msg, expected_mml_msg);
     }
 
+    #[cfg(feature = "template")]
+    #[tokio::test]
+    async fn template() {
+        use crate::message::TemplateVars;
+
+        let mml = concat_line!(
+            "Message-ID: <id@localhost>",
+            "Date: Thu, 1 Jan 1970 00:00:00 +0000",
+            "From: from@localhost",
+            "To: {{ email }}",
+            "Subject: Hello {{ name }}",
+            "",
+            "Hello {{ name }}!",
+            "{{#each items as item}}",
+            "- {{ item }}",
+            "{{/each}}",
+            "{{#if vip}}See you soon.{{else}}Bye.{{/if}}",
+            "",
+        );
+
+        let mut vars = TemplateVars::new();
+        vars.insert("email".into(), "alice@localhost".into());
+        vars.insert("name".into(), "Alice".into());
+        vars.insert("items".into(), vec!["apples", "pears"].into());
+        vars.insert("vip".into(), true.into());
+
+        let expected_mml_msg = concat_line!(
+            "To: alice@localhost",
+            "Subject: Hello Alice",
+            "",
+            "Hello Alice!",
+            "",
+            "- apples",
+            "",
+            "- pears",
+            "",
+            "See you soon.",
+            "",
+        );
+
+        let mml_compiler = MmlCompilerBuilder::new().build(mml).unwrap();
+        let mml_compiler = mml_compiler.with_vars(vars.clone()).unwrap();
+        let mime_msg_builder = mml_compiler.compile().await.unwrap().into_msg_builder();
+
+        let mml_msg = MimeInterpreterBuilder::new()
+            .with_show_only_headers(["To", "Subject"])
+            .build()
+            .from_msg_builder(mime_msg_builder)
+            .await
+            .unwrap();
+
+        assert_eq!(mml_msg, expected_mml_msg);
+
+        let mml_compiler = MmlCompilerBuilder::new()
+            .with_vars(vars)
+            .build(mml)
+            .unwrap();
+        let mime_msg_builder = mml_compiler.compile().await.unwrap().into_msg_builder();
+
+        let mml_msg = MimeInterpreterBuilder::new()
+            .with_show_only_headers(["To", "Subject"])
+            .build()
+            .from_msg_builder(mime_msg_builder)
+            .await
+            .unwrap();
+
+        assert_eq!(mml_msg, expected_mml_msg);
+    }
+
+    #[test]
+    fn check() {
+        use crate::message::DiagnosticKind;
+
+        let mml = concat_line!(
+            "From: from@localhost",
+            "To: to@localhost",
+            "Subject: subject",
+            "",
+            "Hello!",
+            "<#multipart type=mixed>",
+            "<#part type=text/plain foo=bar>world",
+            "",
+        );
+
+        let mml_compiler = MmlCompilerBuilder::new().build(mml).unwrap();
+        let diagnostics = mml_compiler.check();
+        let kinds: Vec<_> = diagnostics.iter().map(|d| d.kind.clone()).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                DiagnosticKind::UnknownProperty("foo".into()),
+                DiagnosticKind::UnclosedMultipart,
+            ]
+        );
+
+        assert_eq!(diagnostics[0].span.start.line, 7);
+        assert_eq!(diagnostics[0].span.start.column, 24);
+        assert_eq!(diagnostics[1].span.start.line, 6);
+        assert_eq!(diagnostics[1].span.start.column, 1);
+    }
+
     #[tokio::test]
     async fn mml_markup_unescaped() {
         let mml = concat_line!(
@@ -299,4 +674,119 @@ async fn mml_markup_unescaped() {
 
         assert_eq!(mml_msg, expected_mml_msg);
     }
+
+    #[tokio::test]
+    async fn lossless_round_trip() {
+        use mail_parser::{MessageParser, MimeHeaders};
+
+        let msg = concat!(
+            "Received: from localhost\r\n",
+            "\tby localhost; Thu, 1 Jan 1970 00:00:00 +0000\r\n",
+            "Message-ID: <id@localhost>\r\n",
+            "Date: Thu, 1 Jan 1970 00:00:00 +0000\r\n",
+            "From: from@localhost\r\n",
+            "To: to@localhost\r\n",
+            "Subject: Round trip\r\n",
+            "X-Custom: custom\r\n",
+            "MIME-Version: 1.0\r\n",
+            "Content-Type: multipart/mixed; boundary=\"mixed\"\r\n",
+            "\r\n",
+            "--mixed\r\n",
+            "Content-Type: multipart/alternative; boundary=\"alt\"\r\n",
+            "\r\n",
+            "--alt\r\n",
+            "Content-Type: text/plain; charset=\"iso-8859-1\"\r\n",
+            "Content-Transfer-Encoding: quoted-printable\r\n",
+            "\r\n",
+            "caf=E9\r\n",
+            "--alt\r\n",
+            "Content-Type: text/html; charset=\"utf-8\"\r\n",
+            "Content-Transfer-Encoding: quoted-printable\r\n",
+            "\r\n",
+            "<p>caf=C3=A9</p>\r\n",
+            "--alt--\r\n",
+            "\r\n",
+            "--mixed\r\n",
+            "Content-Type: multipart/related; boundary=\"rel\"\r\n",
+            "\r\n",
+            "--rel\r\n",
+            "Content-Type: image/gif\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "Content-Disposition: inline\r\n",
+            "Content-ID: <logo@localhost>\r\n",
+            "Content-Description: Logo\r\n",
+            "\r\n",
+            "R0lGODlhAQABAAAAACw=\r\n",
+            "--rel--\r\n",
+            "\r\n",
+            "--mixed\r\n",
+            "Content-Type: text/plain; charset=\"utf-8\"\r\n",
+            "Content-Transfer-Encoding: 7bit\r\n",
+            "\r\n",
+            "<#part>not markup<#/part>\r\n",
+            "--mixed\r\n",
+            "Content-Type: application/octet-stream; name=\"data.bin\"\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "Content-Disposition: attachment; filename=\"data.bin\"\r\n",
+            "\r\n",
+            "AAECAwQF\r\n",
+            "--mixed--\r\n",
+        );
+
+        let mml = MimeInterpreterBuilder::new()
+            .with_lossless(true)
+            .build()
+            .from_bytes(msg)
+            .await
+            .unwrap();
+
+        let compiled = MmlCompilerBuilder::new()
+            .build(&mml)
+            .unwrap()
+            .compile()
+            .await
+            .unwrap()
+            .into_vec()
+            .unwrap();
+
+        let expected = MessageParser::new().parse(msg).unwrap();
+        let got = MessageParser::new().parse(&compiled).unwrap();
+
+        for header in ["Message-ID", "Date", "From", "To", "Subject", "X-Custom"] {
+            assert_eq!(got.header(header), expected.header(header), "{header}");
+        }
+
+        assert_eq!(
+            got.header_raw("Received").map(str::trim),
+            Some("from localhost\tby localhost; Thu, 1 Jan 1970 00:00:00 +0000"),
+        );
+
+        assert_eq!(got.parts.len(), expected.parts.len());
+
+        for (got, expected) in got.parts.iter().zip(expected.parts.iter()) {
+            let ctype = |part: &mail_parser::MessagePart| {
+                part.content_type().map(|ctype| {
+                    (
+                        ctype.ctype().to_owned(),
+                        ctype.subtype().map(ToOwned::to_owned),
+                        ctype.attribute("charset").map(ToOwned::to_owned),
+                        ctype.attribute("name").map(ToOwned::to_owned),
+                    )
+                })
+            };
+
+            assert_eq!(ctype(got), ctype(expected));
+            assert_eq!(
+                got.content_transfer_encoding(),
+                expected.content_transfer_encoding()
+            );
+            assert_eq!(
+                got.content_disposition().map(|d| d.ctype()),
+                expected.content_disposition().map(|d| d.ctype())
+            );
+            assert_eq!(got.content_id(), expected.content_id());
+            assert_eq!(got.content_description(), expected.content_description());
+            assert_eq!(got.contents(), expected.contents());
+        }
+    }
 }
@@ -0,0 +1,469 @@
+//! # Message composition module
+//!
+//! Module dedicated to the composition of MIME messages without MML.
+//! The [MessageComposer] builds the same parts as the ones parsed
+//! from a MML body, so that both produce the same MIME message.
+
+use std::{borrow::Cow, collections::HashMap, path::PathBuf};
+
+use mail_builder::{
+    encoders::base64::base64_encode,
+    headers::{address::Address, text::Text, HeaderType},
+};
+
+#[cfg(feature = "pgp")]
+use crate::{
+    message::body::{ENCRYPT, PGP_MIME, SIGN},
+    pgp::Pgp,
+};
+use crate::{
+    message::{
+        body::{
+            compiler::tokens::{Part, Props},
+            ALTERNATIVE, ATTACHMENT, CID, DATA_ENCODING, DESCRIPTION, DISPOSITION, ENCODING_BASE64,
+            FILENAME, INLINE, RECIPIENT_FILENAME, TYPE,
+        },
+        MmlBodyCompiler, MmlCompileResult,
+    },
+    Error, Result,
+};
+
+/// The source of the contents of a [ComposerAttachment].
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum AttachmentSource {
+    /// The contents are read from the given file path at composition
+    /// time.
+    Path(String),
+
+    /// The contents are given in memory, encoded in base64.
+    Data(String),
+}
+
+/// An attachment of a [MessageComposer].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ComposerAttachment {
+    source: AttachmentSource,
+    content_type: Option<String>,
+    filename: Option<String>,
+    cid: Option<String>,
+    description: Option<String>,
+    inline: bool,
+}
+
+impl ComposerAttachment {
+    /// Create a new attachment from the file at the given path.
+    ///
+    /// The file is read at composition time, and its name is used as
+    /// attachment file name unless another one is given.
+    pub fn from_path(path: impl Into<PathBuf>) -> Self {
+        let path = path.into().to_string_lossy().into_owned();
+        Self::new(AttachmentSource::Path(path))
+    }
+
+    /// Create a new attachment from the given file name and
+    /// contents.
+    pub fn from_data(filename: impl ToString, data: impl AsRef<[u8]>) -> Result<Self> {
+        let data = base64_encode(data.as_ref()).map_err(Error::EncodePartDataError)?;
+        let data = String::from_utf8_lossy(&data).into_owned();
+        Ok(Self::new(AttachmentSource::Data(data)).with_filename(filename))
+    }
+
+    fn new(source: AttachmentSource) -> Self {
+        Self {
+            source,
+            content_type: None,
+            filename: None,
+            cid: None,
+            description: None,
+            inline: false,
+        }
+    }
+
+    /// Set the content type of the attachment.
+    ///
+    /// When not defined, the content type is guessed from the
+    /// contents.
+    pub fn with_content_type(mut self, ctype: impl ToString) -> Self {
+        self.content_type = Some(ctype.to_string());
+        self
+    }
+
+    /// Set the file name of the attachment, as seen by recipients.
+    pub fn with_filename(mut self, filename: impl ToString) -> Self {
+        self.filename = Some(filename.to_string());
+        self
+    }
+
+    /// Set the description of the attachment.
+    pub fn with_description(mut self, description: impl ToString) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Attach the contents inline, for example to display an image
+    /// in the HTML body.
+    ///
+    /// The given Content-ID can be referenced from the HTML body
+    /// with `cid:{cid}`, see the `cid` MML part property.
+    pub fn with_inline(mut self, cid: Option<impl ToString>) -> Self {
+        self.inline = true;
+        self.cid = cid.map(|cid| cid.to_string());
+        self
+    }
+
+    /// Build the MML part matching the attachment.
+    fn to_part(&self) -> Part<'_> {
+        let mut props = Props::new();
+
+        let body = match &self.source {
+            AttachmentSource::Path(path) => {
+                props.insert(FILENAME, path.as_str());
+                ""
+            }
+            AttachmentSource::Data(data) => {
+                props.insert(DATA_ENCODING, ENCODING_BASE64);
+                data.as_str()
+            }
+        };
+
+        props.insert(DISPOSITION, if self.inline { INLINE } else { ATTACHMENT });
+
+        if let Some(ctype) = &self.content_type {
+            props.insert(TYPE, ctype);
+        }
+
+        if let Some(filename) = &self.filename {
+            props.insert(RECIPIENT_FILENAME, filename);
+        }
+
+        if let Some(cid) = &self.cid {
+            props.insert(CID, cid);
+        }
+
+        if let Some(description) = &self.description {
+            props.insert(DESCRIPTION, description);
+        }
+
+        Part::Single(props, body)
+    }
+}
+
+/// Typed MIME message composer.
+///
+/// The composer is an alternative to MML for programmatic senders:
+/// headers, bodies and attachments are given using typed functions,
+/// and the resulting MIME message is the same as the one compiled
+/// from the equivalent MML message.
+#[derive(Clone, Debug, Default)]
+pub struct MessageComposer<'x> {
+    headers: Vec<(Cow<'x, str>, HeaderType<'x>)>,
+    text_body: Option<Cow<'x, str>>,
+    html_body: Option<Cow<'x, str>>,
+    attachments: Vec<ComposerAttachment>,
+    mml_body_compiler: MmlBodyCompiler,
+    #[cfg(feature = "pgp")]
+    pgp_sign: bool,
+    #[cfg(feature = "pgp")]
+    pgp_encrypt: bool,
+    #[cfg(feature = "pgp")]
+    pgp_sender: Option<String>,
+    #[cfg(feature = "pgp")]
+    pgp_recipients: Vec<String>,
+}
+
+impl<'x> MessageComposer<'x> {
+    /// Create a new message composer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Customize the internal MML body compiler.
+    ///
+    /// This allows to reuse compiler options, like size limits or
+    /// charsets, for composed messages.
+    pub fn with_mml_body_compiler(mut self, compiler: MmlBodyCompiler) -> Self {
+        self.mml_body_compiler = compiler;
+        #[cfg(feature = "pgp")]
+        {
+            self.mml_body_compiler = self
+                .mml_body_compiler
+                .with_pgp_sender(self.pgp_sender.clone())
+                .with_pgp_recipients(self.pgp_recipients.clone());
+        }
+        self
+    }
+
+    /// Add the given header.
+    pub fn with_header(
+        mut self,
+        key: impl Into<Cow<'x, str>>,
+        val: impl Into<HeaderType<'x>>,
+    ) -> Self {
+        self.headers.push((key.into(), val.into()));
+        self
+    }
+
+    /// Set the `From` header.
+    pub fn with_from(self, addr: impl Into<Address<'x>>) -> Self {
+        let addr = addr.into();
+
+        #[cfg(feature = "pgp")]
+        let self_ = {
+            let mut self_ = self;
+            self_.pgp_sender = emails(&addr).into_iter().next();
+            self_.mml_body_compiler = self_
+                .mml_body_compiler
+                .with_pgp_sender(self_.pgp_sender.clone());
+            self_
+        };
+        #[cfg(not(feature = "pgp"))]
+        let self_ = self;
+
+        self_.with_header("From", addr)
+    }
+
+    /// Set the `To` header.
+    pub fn with_to(self, addr: impl Into<Address<'x>>) -> Self {
+        self.with_recipients_header("To", addr.into())
+    }
+
+    /// Set the `Cc` header.
+    pub fn with_cc(self, addr: impl Into<Address<'x>>) -> Self {
+        self.with_recipients_header("Cc", addr.into())
+    }
+
+    /// Set the `Bcc` header.
+    pub fn with_bcc(self, addr: impl Into<Address<'x>>) -> Self {
+        self.with_recipients_header("Bcc", addr.into())
+    }
+
+    /// Set the `Reply-To` header.
+    pub fn with_reply_to(self, addr: impl Into<Address<'x>>) -> Self {
+        self.with_header("Reply-To", addr.into())
+    }
+
+    /// Set the `Subject` header.
+    pub fn with_subject(self, subject: impl Into<Cow<'x, str>>) -> Self {
+        self.with_header("Subject", Text::new(subject))
+    }
+
+    /// Set the plain text body.
+    pub fn with_text_body(mut self, text: impl Into<Cow<'x, str>>) -> Self {
+        self.text_body = Some(text.into());
+        self
+    }
+
+    /// Set the HTML body.
+    ///
+    /// When both plain text and HTML bodies are defined, they are
+    /// compiled into a `multipart/alternative` part.
+    pub fn with_html_body(mut self, html: impl Into<Cow<'x, str>>) -> Self {
+        self.html_body = Some(html.into());
+        self
+    }
+
+    /// Add the given attachment.
+    pub fn with_attachment(mut self, attachment: ComposerAttachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    /// Customize PGP.
+    #[cfg(feature = "pgp")]
+    pub fn with_pgp(mut self, pgp: impl Into<Pgp>) -> Self {
+        self.mml_body_compiler.set_pgp(pgp);
+        self
+    }
+
+    /// Sign the message using PGP/MIME.
+    #[cfg(feature = "pgp")]
+    pub fn with_pgp_sign(mut self, sign: bool) -> Self {
+        self.pgp_sign = sign;
+        self
+    }
+
+    /// Encrypt the message using PGP/MIME, for all the recipients
+    /// found in the `To`, `Cc` and `Bcc` headers.
+    #[cfg(feature = "pgp")]
+    pub fn with_pgp_encrypt(mut self, encrypt: bool) -> Self {
+        self.pgp_encrypt = encrypt;
+        self
+    }
+
+    /// Add a header containing recipients.
+    fn with_recipients_header(self, key: &'static str, addr: Address<'x>) -> Self {
+        #[cfg(feature = "pgp")]
+        let self_ = {
+            let mut self_ = self;
+            self_.pgp_recipients.extend(emails(&addr));
+            self_.mml_body_compiler = self_
+                .mml_body_compiler
+                .with_pgp_recipients(self_.pgp_recipients.clone());
+            self_
+        };
+        #[cfg(not(feature = "pgp"))]
+        let self_ = self;
+
+        self_.with_header(key, addr)
+    }
+
+    /// Build the MML parts matching the composed message.
+    fn to_parts(&self) -> Vec<Part<'_>> {
+        let text_part = |ctype, body| {
+            let mut props = Props::new();
+            props.insert(TYPE, ctype);
+            Part::Single(props, body)
+        };
+
+        let body = match (&self.text_body, &self.html_body) {
+            (None, None) => None,
+            (Some(text), None) => Some(text_part("text/plain", text.as_ref())),
+            (None, Some(html)) => Some(text_part("text/html", html.as_ref())),
+            (Some(text), Some(html)) => Some(Part::Multi(
+                HashMap::from_iter([(TYPE, ALTERNATIVE)]),
+                vec![
+                    text_part("text/plain", text.as_ref()),
+                    text_part("text/html", html.as_ref()),
+                ],
+            )),
+        };
+
+        let mut parts: Vec<Part> = body.into_iter().collect();
+        parts.extend(self.attachments.iter().map(ComposerAttachment::to_part));
+
+        #[cfg(feature = "pgp")]
+        if self.pgp_sign || self.pgp_encrypt {
+            // PGP properties apply to a single root part, so that the
+            // whole message gets signed or encrypted
+            let mut root = match parts.len() {
+                1 => parts.remove(0),
+                _ => Part::Multi(Props::new(), parts),
+            };
+
+            if let Part::Multi(props, _) | Part::Single(props, _) = &mut root {
+                if self.pgp_sign {
+                    props.insert(SIGN, PGP_MIME);
+                }
+                if self.pgp_encrypt {
+                    props.insert(ENCRYPT, PGP_MIME);
+                }
+            }
+
+            return vec![root];
+        }
+
+        parts
+    }
+
+    /// Compose the message into a [MmlCompileResult].
+    pub async fn compose(&self) -> Result<MmlCompileResult<'_>> {
+        let parts = self.to_parts();
+
+        let mut mime_msg_builder = self.mml_body_compiler.compile_parts(parts).await?;
+
+        mime_msg_builder = mime_msg_builder.header("MIME-Version", Text::new("1.0"));
+        mime_msg_builder
+            .headers
+            .extend(self.headers.iter().cloned());
+
+        Ok(MmlCompileResult { mime_msg_builder })
+    }
+}
+
+/// Extract email addresses from the given address header value.
+#[cfg(feature = "pgp")]
+fn emails(addr: &Address) -> Vec<String> {
+    match addr {
+        Address::Address(addr) => vec![addr.email.to_string()],
+        Address::Group(group) => group.addresses.iter().flat_map(emails).collect(),
+        Address::List(addrs) => addrs.iter().flat_map(emails).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use concat_with::concat_line;
+    use mail_builder::headers::raw::Raw;
+
+    use super::{ComposerAttachment, MessageComposer};
+    use crate::MmlCompilerBuilder;
+
+    async fn assert_same_as_mml(composer: MessageComposer<'_>, mml: &str) {
+        let composed = composer
+            .with_header("Message-ID", Raw::new("<id@localhost>"))
+            .with_header("Date", Raw::new("Thu, 1 Jan 1970 00:00:00 +0000"));
+        let composed = composed.compose().await.unwrap().into_string().unwrap();
+
+        let mml = mml.replacen(
+            "\n\n",
+            "\nMessage-ID: <id@localhost>\nDate: Thu, 1 Jan 1970 00:00:00 +0000\n\n",
+            1,
+        );
+        let compiler = MmlCompilerBuilder::new().build(&mml).unwrap();
+        let compiled = compiler.compile().await.unwrap().into_string().unwrap();
+
+        // multipart boundaries are random
+        let normalize = |msg: String| {
+            msg.lines()
+                .map(|line| match line.find("boundary=") {
+                    Some(i) => &line[..i],
+                    None if line.starts_with("--") => "--",
+                    None => line,
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        assert_eq!(normalize(composed), normalize(compiled));
+    }
+
+    #[tokio::test]
+    async fn text() {
+        let composer = MessageComposer::new()
+            .with_from(("Alice", "alice@localhost"))
+            .with_to("bob@localhost")
+            .with_subject("Hello")
+            .with_text_body("Hello, world!\n");
+
+        let mml = concat_line!(
+            "From: Alice <alice@localhost>",
+            "To: bob@localhost",
+            "Subject: Hello",
+            "",
+            "Hello, world!",
+        );
+
+        assert_same_as_mml(composer, mml).await;
+    }
+
+    #[tokio::test]
+    async fn alternative_with_attachments() {
+        let attachment = ComposerAttachment::from_data("hello.txt", "Hello, world!")
+            .unwrap()
+            .with_content_type("text/plain");
+
+        let composer = MessageComposer::new()
+            .with_from("alice@localhost")
+            .with_to(vec![("Bob", "bob@localhost"), ("Carol", "carol@localhost")])
+            .with_subject("Hello")
+            .with_text_body("Hello, world!\n")
+            .with_html_body("<p>Hello, world!</p>\n")
+            .with_attachment(attachment);
+
+        let mml = concat_line!(
+            "From: alice@localhost",
+            "To: Bob <bob@localhost>, Carol <carol@localhost>",
+            "Subject: Hello",
+            "",
+            "<#multipart type=alternative>",
+            "<#part type=text/plain>",
+            "Hello, world!",
+            "<#part type=text/html>",
+            "<p>Hello, world!</p>",
+            "<#/multipart>",
+            "<#part type=text/plain disposition=attachment recipient-filename=hello.txt data-encoding=base64>SGVsbG8sIHdvcmxkIQ==<#/part>",
+        );
+
+        assert_same_as_mml(composer, mml).await;
+    }
+}
@@ -9,6 +9,46 @@
 use mail_parser::{Addr, Address, ContentType, Group, Header, HeaderName, HeaderValue};
 use std::borrow::Cow;
 
+/// The name of the `Importance` header.
+pub(crate) const IMPORTANCE: &str = "Importance";
+
+/// The name of the non-standard but widespread `X-Priority` header.
+pub(crate) const X_PRIORITY: &str = "X-Priority";
+
+/// Parse the given `Importance` header value into a priority level:
+/// `high`, `normal` or `low`.
+pub(crate) fn parse_importance(val: &str) -> Option<&'static str> {
+    match val.trim().to_ascii_lowercase().as_str() {
+        "high" => Some("high"),
+        "normal" => Some("normal"),
+        "low" => Some("low"),
+        _ => None,
+    }
+}
+
+/// Parse the given `X-Priority` header value into a priority level.
+///
+/// The value starts with a digit from 1 (highest) to 5 (lowest),
+/// optionally followed by a comment like `1 (Highest)`.
+pub(crate) fn parse_x_priority(val: &str) -> Option<&'static str> {
+    match val.split_whitespace().next()? {
+        "1" | "2" => Some("high"),
+        "3" => Some("normal"),
+        "4" | "5" => Some("low"),
+        _ => None,
+    }
+}
+
+/// Build the `X-Priority` header value matching the given priority
+/// level.
+pub(crate) fn to_x_priority(level: &str) -> &'static str {
+    match level {
+        "high" => "1 (Highest)",
+        "low" => "5 (Lowest)",
+        _ => "3 (Normal)",
+    }
+}
+
 pub(super) fn display_value(key: &str, val: &HeaderValue) -> String {
     match val {
         HeaderValue::Address(Address::List(addrs)) => display_addrs(addrs),
@@ -28,6 +68,15 @@ pub(super) fn display_value(key: &str, val: &HeaderValue) -> String {
     }
 }
 
+/// Display the raw value of the given header, unfolded.
+pub(super) fn display_raw_value(raw: &[u8], header: &Header) -> String {
+    let val = raw
+        .get(header.offset_start..header.offset_end)
+        .unwrap_or_default();
+    let val = String::from_utf8_lossy(val);
+    val.replace("\r\n", "").replace('\n', "").trim().to_owned()
+}
+
 fn display_addr(addr: &Addr) -> String {
     let email = match &addr.address {
         Some(addr) => addr.to_string(),
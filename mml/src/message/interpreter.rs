@@ -7,9 +7,9 @@
 use std::path::PathBuf;
 
 #[cfg(feature = "pgp")]
-use crate::pgp::Pgp;
+use crate::pgp::{PartVerificationReport, Pgp};
 use crate::{
-    message::{FilterParts, MimeBodyInterpreter},
+    message::{CharsetReplacement, FilterParts, HtmlLinks, MimeBodyInterpreter},
     Error, Result,
 };
 
@@ -39,12 +39,28 @@ pub fn contains(&self, header: &String) -> bool {
     }
 }
 
+/// MIME headers describing the structure of the message.
+///
+/// In lossless mode, these headers are not shown since the MML
+/// compiler generates them back from the MML body.
+const MIME_HEADERS: [&str; 6] = [
+    "MIME-Version",
+    "Content-Type",
+    "Content-Transfer-Encoding",
+    "Content-Disposition",
+    "Content-ID",
+    "Content-Description",
+];
+
 /// MIME → MML message interpreter builder.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct MimeInterpreterBuilder {
     /// The strategy to display headers.
     show_headers: FilterHeaders,
 
+    /// Whether the interpretation is lossless.
+    lossless: bool,
+
     /// The internal MIME to MML message body interpreter.
     mime_body_interpreter: MimeBodyInterpreter,
 }
@@ -147,6 +163,12 @@ pub fn with_show_plain_texts_signature(mut self, b: bool) -> Self {
         self
     }
 
+    /// Show quote markers around quoted regions of plain texts.
+    pub fn with_show_quote_markers(mut self, b: bool) -> Self {
+        self.mime_body_interpreter = self.mime_body_interpreter.with_show_quote_markers(b);
+        self
+    }
+
     /// Show MML attachments tags.
     pub fn with_show_attachments(mut self, b: bool) -> Self {
         self.mime_body_interpreter = self.mime_body_interpreter.with_show_attachments(b);
@@ -188,6 +210,59 @@ pub fn with_save_some_attachments_dir(self, dir: Option<impl Into<PathBuf>>) ->
         }
     }
 
+    /// Customize the rendering of links of HTML parts.
+    pub fn with_html_links(mut self, links: HtmlLinks) -> Self {
+        self.mime_body_interpreter = self.mime_body_interpreter.with_html_links(links);
+        self
+    }
+
+    /// Show HTML tables row by row.
+    pub fn with_show_html_tables(mut self, b: bool) -> Self {
+        self.mime_body_interpreter = self.mime_body_interpreter.with_show_html_tables(b);
+        self
+    }
+
+    /// Show HTML images alternative text.
+    pub fn with_show_html_images_alt(mut self, b: bool) -> Self {
+        self.mime_body_interpreter = self.mime_body_interpreter.with_show_html_images_alt(b);
+        self
+    }
+
+    /// Wrap lines of HTML parts to the given maximum width.
+    pub fn with_html_max_width(mut self, width: Option<usize>) -> Self {
+        self.mime_body_interpreter = self.mime_body_interpreter.with_html_max_width(width);
+        self
+    }
+
+    /// Customize the policy applied to invalid byte sequences when
+    /// decoding text parts from their charset.
+    pub fn with_charset_replacement(mut self, replacement: CharsetReplacement) -> Self {
+        self.mime_body_interpreter = self
+            .mime_body_interpreter
+            .with_charset_replacement(replacement);
+        self
+    }
+
+    /// Decode text parts that do not declare a charset using the
+    /// given one, instead of UTF-8.
+    pub fn with_default_charset(mut self, charset: Option<String>) -> Self {
+        self.mime_body_interpreter = self.mime_body_interpreter.with_default_charset(charset);
+        self
+    }
+
+    /// Interpret the message losslessly.
+    ///
+    /// The resulting MML can be compiled back to an equivalent MIME
+    /// message: all headers are shown with their raw value, and the
+    /// MML body keeps the structure, the part parameters and the
+    /// transfer encodings of the original message. This mode
+    /// overrides headers and parts visibility options.
+    pub fn with_lossless(mut self, lossless: bool) -> Self {
+        self.lossless = lossless;
+        self.mime_body_interpreter = self.mime_body_interpreter.with_lossless(lossless);
+        self
+    }
+
     /// Customize PGP.
     #[cfg(feature = "pgp")]
     pub fn set_pgp(&mut self, pgp: impl Into<Pgp>) {
@@ -221,6 +296,7 @@ pub fn with_some_pgp(mut self, pgp: Option<impl Into<Pgp>>) -> Self {
     pub fn build(self) -> MimeInterpreter {
         MimeInterpreter {
             show_headers: self.show_headers,
+            lossless: self.lossless,
             mime_body_interpreter: self.mime_body_interpreter,
         }
     }
@@ -230,6 +306,7 @@ pub fn build(self) -> MimeInterpreter {
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct MimeInterpreter {
     show_headers: FilterHeaders,
+    lossless: bool,
     mime_body_interpreter: MimeBodyInterpreter,
 }
 
@@ -239,6 +316,18 @@ pub async fn from_msg(self, msg: &Message<'_>) -> Result<String> {
         let mut mml = String::new();
 
         match self.show_headers {
+            _ if self.lossless => msg
+                .headers()
+                .iter()
+                .filter(|header| {
+                    let key = header.name.as_str();
+                    !MIME_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(key))
+                })
+                .for_each(|header| {
+                    let key = header.name.as_str();
+                    let val = header::display_raw_value(&msg.raw_message, header);
+                    mml.push_str(&format!("{key}: {val}\n"));
+                }),
             FilterHeaders::All => msg.headers().iter().for_each(|header| {
                 let key = header.name.as_str();
                 let val = header::display_value(key, &header.value);
@@ -293,6 +382,41 @@ pub async fn from_msg_builder(self, builder: MessageBuilder<'_>) -> Result<Strin
         let bytes = builder.write_to_vec().map_err(Error::BuildEmailError)?;
         self.from_bytes(&bytes).await
     }
+
+    /// Report the PGP verification of the given MIME [Message].
+    ///
+    /// See [`MimeBodyInterpreter::interpret_msg_verification`].
+    #[cfg(feature = "pgp")]
+    pub async fn verify_msg(self, msg: &Message<'_>) -> Vec<PartVerificationReport> {
+        self.mime_body_interpreter
+            .with_pgp_sender(header::extract_first_email(msg.from()))
+            .with_pgp_recipient(header::extract_first_email(msg.to()))
+            .interpret_msg_verification(msg)
+            .await
+    }
+
+    /// Report the PGP verification of the given MIME message bytes.
+    #[cfg(feature = "pgp")]
+    pub async fn verify_bytes(
+        self,
+        bytes: impl AsRef<[u8]>,
+    ) -> Result<Vec<PartVerificationReport>> {
+        let msg = MessageParser::new()
+            .parse(bytes.as_ref())
+            .ok_or(Error::ParseRawEmailError)?;
+        Ok(self.verify_msg(&msg).await)
+    }
+
+    /// Report the PGP verification of the given MIME
+    /// [MessageBuilder].
+    #[cfg(feature = "pgp")]
+    pub async fn verify_msg_builder(
+        self,
+        builder: MessageBuilder<'_>,
+    ) -> Result<Vec<PartVerificationReport>> {
+        let bytes = builder.write_to_vec().map_err(Error::BuildEmailError)?;
+        self.verify_bytes(&bytes).await
+    }
 }
 
 #[cfg(test)]
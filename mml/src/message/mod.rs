@@ -5,7 +5,9 @@
 //! ## Compilation
 //!
 //! A MML message/body can be compiled into a MIME message/body using
-//! the [MmlCompilerBuilder]/[MmlBodyCompiler] builders.
+//! the [MmlCompilerBuilder]/[MmlBodyCompiler] builders. MIME
+//! messages can also be built without MML using the
+//! [MessageComposer].
 //!
 //! ## Interpretation
 //!
@@ -15,19 +17,37 @@
 pub mod body;
 #[cfg(feature = "compiler")]
 pub mod compiler;
+#[cfg(feature = "compiler")]
+pub mod composer;
 pub(crate) mod header;
 #[cfg(feature = "interpreter")]
 pub mod interpreter;
+#[cfg(feature = "template")]
+pub mod template;
 
-#[cfg(feature = "compiler")]
+#[cfg(feature = "image")]
 #[doc(inline)]
-pub use self::{
-    body::MmlBodyCompiler,
-    compiler::{MmlCompileResult, MmlCompiler, MmlCompilerBuilder},
+pub use self::body::{
+    ImageDownscaling, DEFAULT_IMAGE_JPEG_QUALITY, DEFAULT_IMAGE_MAX_DIMENSION,
+    DEFAULT_IMAGE_MIN_SIZE,
 };
+#[cfg(feature = "template")]
+#[doc(inline)]
+pub use self::template::{TemplateValue, TemplateVars};
 #[cfg(feature = "interpreter")]
 #[doc(inline)]
 pub use self::{
-    body::{FilterParts, MimeBodyInterpreter},
+    body::{CharsetReplacement, FilterParts, HtmlLinks, MimeBodyInterpreter},
     interpreter::{FilterHeaders, MimeInterpreter, MimeInterpreterBuilder},
 };
+#[cfg(feature = "compiler")]
+#[doc(inline)]
+pub use self::{
+    body::{
+        Diagnostic, DiagnosticKind, ExternalAttachment, ExternalizeFn, ExternalizeResult,
+        MmlBodyCompiler, OversizeStrategy, Position, Severity, Signature, SignaturePlacement,
+        SizeLimits, Span, TransferEncoding,
+    },
+    compiler::{MmlCompileResult, MmlCompiler, MmlCompilerBuilder},
+    composer::{ComposerAttachment, MessageComposer},
+};
@@ -0,0 +1,411 @@
+//! # Template module
+//!
+//! Module dedicated to MML templates. Templates are evaluated before
+//! the compilation, which allows mail-merge style generation of
+//! messages from a single MML message and a context map of
+//! [TemplateVars].
+//!
+//! The following syntax is supported, both in headers and body:
+//!
+//! - `{{ name }}` is replaced by the value of the variable `name`.
+//!   Nested values can be accessed using dots: `{{ user.name }}`.
+//! - `{{#if name}}…{{else}}…{{/if}}` renders the first block if the
+//!   variable `name` is truthy (a non-empty text or list, or `true`),
+//!   otherwise the optional `else` block.
+//! - `{{#each items as item}}…{{/each}}` renders the block for each
+//!   element of the list `items`, which can be referred to as `item`
+//!   inside the block.
+//! - `\{{` is rendered as a literal `{{`.
+
+use std::collections::HashMap;
+
+use crate::{Error, Result};
+
+/// The variables given to a template, indexed by name.
+pub type TemplateVars = HashMap<String, TemplateValue>;
+
+/// The value of a template variable.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TemplateValue {
+    /// A text value, rendered as it is.
+    Text(String),
+
+    /// A boolean value, mostly useful for conditionals.
+    Bool(bool),
+
+    /// A list of values, mostly useful for loops.
+    List(Vec<TemplateValue>),
+
+    /// A map of values, accessible using dots.
+    Map(TemplateVars),
+}
+
+impl TemplateValue {
+    /// Return `true` if the value is considered as true by
+    /// conditionals.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Self::Text(text) => !text.is_empty(),
+            Self::Bool(b) => *b,
+            Self::List(list) => !list.is_empty(),
+            Self::Map(map) => !map.is_empty(),
+        }
+    }
+}
+
+impl From<&str> for TemplateValue {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_owned())
+    }
+}
+
+impl From<String> for TemplateValue {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<bool> for TemplateValue {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl<T: Into<TemplateValue>> From<Vec<T>> for TemplateValue {
+    fn from(list: Vec<T>) -> Self {
+        Self::List(list.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<TemplateVars> for TemplateValue {
+    fn from(map: TemplateVars) -> Self {
+        Self::Map(map)
+    }
+}
+
+/// A token of a template: either raw text or the trimmed content of
+/// a `{{ … }}` tag.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Text(String),
+    Tag(String),
+}
+
+/// A node of a parsed template.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Node {
+    Text(String),
+    Var(String),
+    If(String, Vec<Node>, Vec<Node>),
+    Each(String, String, Vec<Node>),
+}
+
+/// Render the given template using the given variables.
+pub fn render(tpl: &str, vars: &TemplateVars) -> Result<String> {
+    let mut tokens = tokenize(tpl)?.into_iter();
+    let (nodes, end) = parse_block(&mut tokens)?;
+
+    if let Some(tag) = end {
+        return Err(Error::ParseTemplateUnexpectedTagError(tag));
+    }
+
+    let mut output = String::with_capacity(tpl.len());
+    render_nodes(&nodes, vars, &mut Vec::new(), &mut output)?;
+    Ok(output)
+}
+
+/// Split the given template into text and tag tokens.
+fn tokenize(tpl: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut offset = 0;
+
+    while let Some(pos) = tpl[offset..].find("{{") {
+        let start = offset + pos;
+
+        if tpl[..start].ends_with('\\') {
+            text.push_str(&tpl[offset..start - 1]);
+            text.push_str("{{");
+            offset = start + 2;
+            continue;
+        }
+
+        let Some(len) = tpl[start + 2..].find("}}") else {
+            return Err(Error::ParseTemplateUnclosedTagError(start));
+        };
+
+        text.push_str(&tpl[offset..start]);
+
+        if !text.is_empty() {
+            tokens.push(Token::Text(std::mem::take(&mut text)));
+        }
+
+        let tag = tpl[start + 2..start + 2 + len].trim();
+        tokens.push(Token::Tag(tag.to_owned()));
+        offset = start + 2 + len + 2;
+    }
+
+    text.push_str(&tpl[offset..]);
+
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+
+    Ok(tokens)
+}
+
+/// Parse tokens until the end of the current block.
+///
+/// Returns the parsed nodes, and the tag that ended the block
+/// (`else`, `/if` or `/each`) if any.
+fn parse_block(tokens: &mut impl Iterator<Item = Token>) -> Result<(Vec<Node>, Option<String>)> {
+    let mut nodes = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        let tag = match token {
+            Token::Text(text) => {
+                nodes.push(Node::Text(text));
+                continue;
+            }
+            Token::Tag(tag) => tag,
+        };
+
+        if matches!(tag.as_str(), "else" | "/if" | "/each") {
+            return Ok((nodes, Some(tag)));
+        }
+
+        if let Some(path) = tag.strip_prefix("#if ") {
+            let path = parse_path(path, &tag)?;
+            let (then_nodes, end) = parse_block(tokens)?;
+
+            let else_nodes = match end.as_deref() {
+                Some("/if") => Vec::new(),
+                Some("else") => match parse_block(tokens)? {
+                    (else_nodes, Some(end)) if end == "/if" => else_nodes,
+                    (_, Some(end)) => return Err(Error::ParseTemplateUnexpectedTagError(end)),
+                    (_, None) => return Err(Error::ParseTemplateUnclosedBlockError(tag)),
+                },
+                Some(end) => return Err(Error::ParseTemplateUnexpectedTagError(end.to_owned())),
+                None => return Err(Error::ParseTemplateUnclosedBlockError(tag)),
+            };
+
+            nodes.push(Node::If(path, then_nodes, else_nodes));
+            continue;
+        }
+
+        if let Some(args) = tag.strip_prefix("#each ") {
+            let (path, alias) = match args.split_whitespace().collect::<Vec<_>>()[..] {
+                [path, "as", alias] => (parse_path(path, &tag)?, parse_path(alias, &tag)?),
+                _ => return Err(Error::ParseTemplateInvalidTagError(tag.clone())),
+            };
+
+            if alias.contains('.') {
+                return Err(Error::ParseTemplateInvalidTagError(tag));
+            }
+
+            let body = match parse_block(tokens)? {
+                (body, Some(end)) if end == "/each" => body,
+                (_, Some(end)) => return Err(Error::ParseTemplateUnexpectedTagError(end)),
+                (_, None) => return Err(Error::ParseTemplateUnclosedBlockError(tag)),
+            };
+
+            nodes.push(Node::Each(path, alias, body));
+            continue;
+        }
+
+        let path = parse_path(&tag, &tag)?;
+        nodes.push(Node::Var(path));
+    }
+
+    Ok((nodes, None))
+}
+
+/// Parse the given variable path, belonging to the given tag.
+fn parse_path(path: &str, tag: &str) -> Result<String> {
+    let path = path.trim();
+
+    let valid = !path.is_empty()
+        && path.split('.').all(|key| {
+            !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        });
+
+    if valid {
+        Ok(path.to_owned())
+    } else {
+        Err(Error::ParseTemplateInvalidTagError(tag.to_owned()))
+    }
+}
+
+/// Find the value of the given variable path.
+///
+/// The first key is searched in loop scopes first (innermost first),
+/// then in the root variables.
+fn lookup<'a>(
+    path: &str,
+    vars: &'a TemplateVars,
+    scopes: &[(&str, &'a TemplateValue)],
+) -> Option<&'a TemplateValue> {
+    let mut keys = path.split('.');
+    let first = keys.next()?;
+
+    let mut value = scopes
+        .iter()
+        .rev()
+        .find(|(alias, _)| *alias == first)
+        .map(|(_, value)| *value)
+        .or_else(|| vars.get(first))?;
+
+    for key in keys {
+        value = match value {
+            TemplateValue::Map(map) => map.get(key)?,
+            _ => return None,
+        };
+    }
+
+    Some(value)
+}
+
+/// Render the given nodes into the given output.
+fn render_nodes<'a>(
+    nodes: &'a [Node],
+    vars: &'a TemplateVars,
+    scopes: &mut Vec<(&'a str, &'a TemplateValue)>,
+    output: &mut String,
+) -> Result<()> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => {
+                output.push_str(text);
+            }
+            Node::Var(path) => match lookup(path, vars, scopes) {
+                Some(TemplateValue::Text(text)) => output.push_str(text),
+                Some(TemplateValue::Bool(b)) => output.push_str(&b.to_string()),
+                Some(_) => {
+                    return Err(Error::RenderTemplateNonTextVariableError(path.clone()));
+                }
+                None => {
+                    return Err(Error::RenderTemplateUndefinedVariableError(path.clone()));
+                }
+            },
+            Node::If(path, then_nodes, else_nodes) => {
+                let truthy = lookup(path, vars, scopes)
+                    .map(TemplateValue::is_truthy)
+                    .unwrap_or_default();
+
+                if truthy {
+                    render_nodes(then_nodes, vars, scopes, output)?;
+                } else {
+                    render_nodes(else_nodes, vars, scopes, output)?;
+                }
+            }
+            Node::Each(path, alias, body) => match lookup(path, vars, scopes) {
+                Some(TemplateValue::List(list)) => {
+                    for item in list {
+                        scopes.push((alias.as_str(), item));
+                        let res = render_nodes(body, vars, scopes, output);
+                        scopes.pop();
+                        res?;
+                    }
+                }
+                Some(_) => {
+                    return Err(Error::RenderTemplateNonListVariableError(path.clone()));
+                }
+                None => {
+                    return Err(Error::RenderTemplateUndefinedVariableError(path.clone()));
+                }
+            },
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use concat_with::concat_line;
+
+    use super::{render, TemplateValue, TemplateVars};
+    use crate::Error;
+
+    fn vars() -> TemplateVars {
+        let mut user = TemplateVars::new();
+        user.insert("name".into(), "Alice".into());
+        user.insert("vip".into(), true.into());
+
+        let mut vars = TemplateVars::new();
+        vars.insert("user".into(), user.into());
+        vars.insert("items".into(), vec!["apples", "pears"].into());
+        vars.insert("empty".into(), TemplateValue::List(Vec::new()));
+        vars
+    }
+
+    #[test]
+    fn variables() {
+        let tpl = "Hello {{ user.name }}, vip={{user.vip}}!";
+        let rendered = render(tpl, &vars()).unwrap();
+        assert_eq!(rendered, "Hello Alice, vip=true!");
+    }
+
+    #[test]
+    fn conditionals() {
+        let tpl = "{{#if user.vip}}VIP{{else}}regular{{/if}} {{#if empty}}items{{else}}none{{/if}}";
+        let rendered = render(tpl, &vars()).unwrap();
+        assert_eq!(rendered, "VIP none");
+
+        let tpl = "{{#if undefined}}defined{{/if}}";
+        let rendered = render(tpl, &vars()).unwrap();
+        assert_eq!(rendered, "");
+    }
+
+    #[test]
+    fn loops() {
+        let tpl = concat_line!(
+            "{{#each items as item}}",
+            "- {{ item }} for {{ user.name }}",
+            "{{/each}}",
+        );
+
+        let rendered = render(tpl, &vars()).unwrap();
+
+        let expected = concat_line!("", "- apples for Alice", "", "- pears for Alice", "",);
+
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn escaped() {
+        let rendered = render(r"\{{ user.name }}", &vars()).unwrap();
+        assert_eq!(rendered, "{{ user.name }}");
+    }
+
+    #[test]
+    fn errors() {
+        let vars = vars();
+
+        let err = render("{{ undefined }}", &vars).unwrap_err();
+        assert!(
+            matches!(err, Error::RenderTemplateUndefinedVariableError(path) if path == "undefined")
+        );
+
+        let err = render("{{ items }}", &vars).unwrap_err();
+        assert!(matches!(err, Error::RenderTemplateNonTextVariableError(path) if path == "items"));
+
+        let err = render("{{#each user as u}}{{/each}}", &vars).unwrap_err();
+        assert!(matches!(err, Error::RenderTemplateNonListVariableError(path) if path == "user"));
+
+        let err = render("{{ user.name", &vars).unwrap_err();
+        assert!(matches!(err, Error::ParseTemplateUnclosedTagError(0)));
+
+        let err = render("{{#if user.vip}}VIP", &vars).unwrap_err();
+        assert!(matches!(err, Error::ParseTemplateUnclosedBlockError(_)));
+
+        let err = render("{{#if user.vip}}VIP{{/each}}", &vars).unwrap_err();
+        assert!(matches!(err, Error::ParseTemplateUnexpectedTagError(tag) if tag == "/each"));
+
+        let err = render("{{#each items}}{{/each}}", &vars).unwrap_err();
+        assert!(matches!(err, Error::ParseTemplateInvalidTagError(_)));
+    }
+}
@@ -0,0 +1,424 @@
+//! # Autocrypt
+//!
+//! Module dedicated to [Autocrypt] Level 1. It contains the
+//! `Autocrypt` header used to advertise the public key of the sender
+//! on outgoing messages, and the peer state database used to store
+//! the public keys advertised by incoming messages.
+//!
+//! Peer states are stored in a SQLite database, and are used by the
+//! native PGP backend to discover public keys of recipients, see
+//! [`NativePgpPublicKeysResolver::Autocrypt`].
+//!
+//! [Autocrypt]: https://autocrypt.org/level1.html
+//! [`NativePgpPublicKeysResolver::Autocrypt`]: super::NativePgpPublicKeysResolver::Autocrypt
+
+use std::{
+    fmt,
+    io::Cursor,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use mail_builder::encoders::base64::base64_encode;
+use mail_parser::{decoders::base64::base64_decode, Message};
+use pgp::native::{ser::Serialize, Deserializable, SignedPublicKey};
+use rusqlite::{params, Connection, OptionalExtension};
+use tracing::debug;
+
+use crate::{Error, Result};
+
+/// The name of the Autocrypt header.
+pub const AUTOCRYPT: &str = "Autocrypt";
+
+/// The maximum length of a folded line of key data.
+const KEYDATA_LINE_LEN: usize = 76;
+
+/// The encryption preference of a peer.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PreferEncrypt {
+    /// The peer did not express any preference.
+    #[default]
+    NoPreference,
+
+    /// The peer prefers to receive encrypted messages when the other
+    /// party prefers it too.
+    Mutual,
+}
+
+impl PreferEncrypt {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::NoPreference => "nopreference",
+            Self::Mutual => "mutual",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s.trim() {
+            "mutual" => Self::Mutual,
+            _ => Self::NoPreference,
+        }
+    }
+}
+
+/// The Autocrypt header.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AutocryptHeader {
+    /// The email address of the key owner.
+    pub addr: String,
+
+    /// The encryption preference of the key owner.
+    pub prefer_encrypt: PreferEncrypt,
+
+    /// The binary public key.
+    pub keydata: Vec<u8>,
+}
+
+impl AutocryptHeader {
+    /// Create a new Autocrypt header from the given address and public
+    /// key.
+    pub fn new(addr: impl ToString, pkey: &SignedPublicKey) -> Result<Self> {
+        let keydata = pkey.to_bytes().map_err(Error::EncodeAutocryptKeyError)?;
+
+        Ok(Self {
+            addr: addr.to_string(),
+            prefer_encrypt: PreferEncrypt::default(),
+            keydata,
+        })
+    }
+
+    /// Customize the encryption preference.
+    pub fn with_prefer_encrypt(mut self, prefer_encrypt: PreferEncrypt) -> Self {
+        self.prefer_encrypt = prefer_encrypt;
+        self
+    }
+
+    /// Parse the given raw Autocrypt header value.
+    ///
+    /// Returns `None` if the header is invalid: missing `addr` or
+    /// `keydata` attributes, or unknown critical attributes (the ones
+    /// not starting with an underscore).
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut addr = None;
+        let mut prefer_encrypt = PreferEncrypt::default();
+        let mut keydata = None;
+
+        for attr in value.split(';') {
+            let attr = attr.trim();
+
+            if attr.is_empty() {
+                continue;
+            }
+
+            let (key, val) = attr.split_once('=')?;
+
+            match key.trim() {
+                "addr" => addr = Some(val.trim().to_owned()),
+                "prefer-encrypt" => prefer_encrypt = PreferEncrypt::parse(val),
+                "keydata" => {
+                    let data: Vec<u8> = val.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+                    keydata = Some(base64_decode(&data)?);
+                }
+                "type" if val.trim() == "1" => (),
+                key if key.starts_with('_') => (),
+                _ => return None,
+            }
+        }
+
+        Some(Self {
+            addr: addr?,
+            prefer_encrypt,
+            keydata: keydata?,
+        })
+    }
+
+    /// Parse the public key contained in the key data.
+    pub fn public_key(&self) -> Result<SignedPublicKey> {
+        read_pkey(&self.addr, &self.keydata)
+    }
+}
+
+impl fmt::Display for AutocryptHeader {
+    /// Display the header value, with key data folded over multiple
+    /// lines.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "addr={}", self.addr)?;
+
+        if self.prefer_encrypt == PreferEncrypt::Mutual {
+            write!(f, "; prefer-encrypt={}", self.prefer_encrypt.as_str())?;
+        }
+
+        write!(f, "; keydata=")?;
+
+        let keydata = base64_encode(&self.keydata).map_err(|_| fmt::Error)?;
+        for line in keydata.chunks(KEYDATA_LINE_LEN) {
+            write!(f, "\r\n {}", String::from_utf8_lossy(line))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The Autocrypt state of a peer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AutocryptPeer {
+    /// The email address of the peer.
+    pub addr: String,
+
+    /// The date of the most recent message seen from the peer, as a
+    /// UNIX timestamp.
+    pub last_seen: i64,
+
+    /// The date of the most recent message seen from the peer
+    /// containing an Autocrypt header, as a UNIX timestamp.
+    pub autocrypt_timestamp: i64,
+
+    /// The binary public key of the peer.
+    pub public_key: Vec<u8>,
+
+    /// The encryption preference of the peer.
+    pub prefer_encrypt: PreferEncrypt,
+}
+
+impl AutocryptPeer {
+    /// Parse the public key of the peer.
+    pub fn public_key(&self) -> Result<SignedPublicKey> {
+        read_pkey(&self.addr, &self.public_key)
+    }
+}
+
+/// The Autocrypt peer state database.
+#[derive(Debug)]
+pub struct AutocryptPeers {
+    conn: Connection,
+}
+
+impl AutocryptPeers {
+    /// Open the peer state database at the given path.
+    ///
+    /// The database is created if it does not exist yet.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        debug!(?path, "opening autocrypt peer state database");
+
+        let conn = Connection::open(path)
+            .map_err(|err| Error::OpenAutocryptPeersError(err, path.to_owned()))?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS peers (
+                addr TEXT PRIMARY KEY NOT NULL,
+                last_seen INTEGER NOT NULL,
+                autocrypt_timestamp INTEGER NOT NULL,
+                public_key BLOB NOT NULL,
+                prefer_encrypt TEXT NOT NULL
+            )",
+            (),
+        )
+        .map_err(|err| Error::OpenAutocryptPeersError(err, path.to_owned()))?;
+
+        Ok(Self { conn })
+    }
+
+    /// Get the state of the peer matching the given email address.
+    pub fn get(&self, addr: &str) -> Result<Option<AutocryptPeer>> {
+        let addr = addr.trim().to_lowercase();
+
+        self.conn
+            .query_row(
+                "SELECT last_seen, autocrypt_timestamp, public_key, prefer_encrypt
+                 FROM peers WHERE addr = ?1",
+                params![addr],
+                |row| {
+                    Ok(AutocryptPeer {
+                        addr: addr.clone(),
+                        last_seen: row.get(0)?,
+                        autocrypt_timestamp: row.get(1)?,
+                        public_key: row.get(2)?,
+                        prefer_encrypt: PreferEncrypt::parse(&row.get::<_, String>(3)?),
+                    })
+                },
+            )
+            .optional()
+            .map_err(|err| Error::GetAutocryptPeerError(err, addr.clone()))
+    }
+
+    /// Get the public key of the peer matching the given email
+    /// address.
+    pub fn get_public_key(&self, addr: &str) -> Result<Option<SignedPublicKey>> {
+        match self.get(addr)? {
+            Some(peer) => Ok(Some(peer.public_key()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Update the state of the given peer, following the Autocrypt
+    /// Level 1 update process.
+    ///
+    /// The given date is the date of the message, as a UNIX
+    /// timestamp. Dates in the future are replaced by the current
+    /// date. Headers that do not match the given address or that
+    /// contain an invalid key are ignored.
+    pub fn update(&self, addr: &str, date: i64, header: Option<&AutocryptHeader>) -> Result<()> {
+        let addr = addr.trim().to_lowercase();
+        let date = date.min(now());
+
+        let header = header
+            .filter(|header| header.addr.trim().to_lowercase() == addr)
+            .filter(|header| match header.public_key() {
+                Ok(_) => true,
+                Err(err) => {
+                    debug!(?err, "ignoring invalid autocrypt header of {addr}");
+                    false
+                }
+            });
+        let peer = self.get(&addr)?;
+
+        if let Some(peer) = &peer {
+            if date < peer.autocrypt_timestamp {
+                debug!("skipping outdated autocrypt update for {addr}");
+                return Ok(());
+            }
+        }
+
+        let res = match (header, peer) {
+            (Some(header), _) => {
+                debug!("updating autocrypt public key of {addr}");
+                self.conn.execute(
+                    "INSERT INTO peers (addr, last_seen, autocrypt_timestamp, public_key, prefer_encrypt)
+                     VALUES (?1, ?2, ?2, ?3, ?4)
+                     ON CONFLICT(addr) DO UPDATE SET
+                        last_seen = MAX(last_seen, ?2),
+                        autocrypt_timestamp = ?2,
+                        public_key = ?3,
+                        prefer_encrypt = ?4",
+                    params![
+                        addr,
+                        date,
+                        header.keydata,
+                        header.prefer_encrypt.as_str()
+                    ],
+                )
+            }
+            (None, Some(peer)) if date > peer.last_seen => {
+                debug!("updating autocrypt last seen date of {addr}");
+                self.conn.execute(
+                    "UPDATE peers SET last_seen = ?2 WHERE addr = ?1",
+                    params![addr, date],
+                )
+            }
+            (None, _) => return Ok(()),
+        };
+
+        res.map_err(|err| Error::UpdateAutocryptPeerError(err, addr.clone()))?;
+        Ok(())
+    }
+
+    /// Update the state of the sender of the given message.
+    ///
+    /// Messages without sender or date are ignored, as well as
+    /// messages containing more than one Autocrypt header.
+    pub fn update_from_message(&self, msg: &Message) -> Result<()> {
+        let Some(addr) = msg
+            .from()
+            .and_then(|from| from.first())
+            .and_then(|from| from.address())
+        else {
+            return Ok(());
+        };
+
+        let Some(date) = msg.date() else {
+            return Ok(());
+        };
+
+        let mut headers = msg
+            .headers_raw()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(AUTOCRYPT))
+            .filter_map(|(_, val)| AutocryptHeader::parse(val));
+
+        let header = match (headers.next(), headers.next()) {
+            (Some(header), None) => Some(header),
+            _ => None,
+        };
+
+        self.update(addr, date.to_timestamp(), header.as_ref())
+    }
+}
+
+/// Read the given binary public key.
+fn read_pkey(addr: &str, data: &[u8]) -> Result<SignedPublicKey> {
+    SignedPublicKey::from_bytes(Cursor::new(data))
+        .map_err(|err| Error::ParseAutocryptKeyError(err, addr.to_owned()))
+}
+
+/// Return the current date, as a UNIX timestamp.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use mail_parser::MessageParser;
+    use tempfile::tempdir;
+
+    use super::{AutocryptHeader, AutocryptPeers, PreferEncrypt};
+
+    #[test]
+    fn header() {
+        let header = AutocryptHeader {
+            addr: String::from("alice@localhost"),
+            prefer_encrypt: PreferEncrypt::Mutual,
+            keydata: vec![0; 100],
+        };
+
+        let value = header.to_string();
+        assert!(value.starts_with("addr=alice@localhost; prefer-encrypt=mutual; keydata=\r\n "));
+        assert!(value.lines().all(|line| line.trim_end().len() <= 78));
+        assert_eq!(AutocryptHeader::parse(&value), Some(header));
+
+        assert_eq!(AutocryptHeader::parse("addr=alice@localhost"), None);
+        assert_eq!(
+            AutocryptHeader::parse("addr=a; keydata=AAAA; unknown=1"),
+            None
+        );
+        assert!(AutocryptHeader::parse("addr=a; keydata=AAAA; _unknown=1").is_some());
+    }
+
+    #[tokio::test]
+    async fn peers() {
+        let dir = tempdir().unwrap();
+        let peers = AutocryptPeers::open(dir.path().join("peers.sqlite")).unwrap();
+
+        let (_, pkey) = pgp::gen_key_pair("alice@localhost", "").await.unwrap();
+        let header = AutocryptHeader::new("alice@localhost", &pkey)
+            .unwrap()
+            .with_prefer_encrypt(PreferEncrypt::Mutual);
+
+        let msg = format!(
+            "From: Alice <Alice@localhost>\r\nDate: Thu, 1 Jan 2015 00:00:00 +0000\r\nAutocrypt: {header}\r\n\r\nHello!\r\n"
+        );
+        let msg = MessageParser::new().parse(msg.as_bytes()).unwrap();
+        peers.update_from_message(&msg).unwrap();
+
+        let peer = peers.get("alice@localhost").unwrap().unwrap();
+        assert_eq!(peer.last_seen, 1420070400);
+        assert_eq!(peer.autocrypt_timestamp, 1420070400);
+        assert_eq!(peer.prefer_encrypt, PreferEncrypt::Mutual);
+        assert_eq!(peer.public_key().unwrap(), pkey);
+
+        // older messages do not override the peer state
+        peers.update("alice@localhost", 0, None).unwrap();
+        let outdated = peers.get("alice@localhost").unwrap().unwrap();
+        assert_eq!(outdated, peer);
+
+        // messages without header only update the last seen date
+        peers.update("alice@localhost", 1420070401, None).unwrap();
+        let peer = peers.get("alice@localhost").unwrap().unwrap();
+        assert_eq!(peer.last_seen, 1420070401);
+        assert_eq!(peer.autocrypt_timestamp, 1420070400);
+
+        assert_eq!(peers.get("bob@localhost").unwrap(), None);
+    }
+}
@@ -0,0 +1,155 @@
+//! # PGP card module
+//!
+//! This module contains the PGP backend based on OpenPGP smartcards
+//! (YubiKey, Nitrokey…). Secret key operations (signing and
+//! decryption) are delegated to the card, so that secret keys never
+//! leave it. Public key operations (encryption and verification) are
+//! performed natively.
+
+use std::path::PathBuf;
+
+pub use pgp::card::{CardKeySlot, CardPrompts};
+use shellexpand_utils::shellexpand_path;
+use tracing::debug;
+
+use super::{
+    native::{user_emails, NativePgpPublicKeysResolver, PgpNative, SignedPublicKey},
+    KeySelection, PgpVerificationReport,
+};
+use crate::{Error, Result};
+
+/// The OpenPGP card PGP backend.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct PgpCard {
+    /// The name (or part of the name) of the PC/SC reader the card
+    /// is plugged into.
+    ///
+    /// Defaults to the first reader containing an OpenPGP card.
+    pub reader: Option<String>,
+
+    /// The path to the armored public key of the sender.
+    ///
+    /// It needs to match the secret keys stored on the card.
+    pub public_key: PathBuf,
+
+    /// The list of public key resolvers, used for encryption and
+    /// verification.
+    pub public_keys_resolvers: Vec<NativePgpPublicKeysResolver>,
+
+    /// The selection of public keys, used when several keys match
+    /// the same recipient.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub key_selection: KeySelection,
+
+    /// The callbacks used to prompt the user for the card PIN and
+    /// touch.
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub prompts: CardPrompts,
+}
+
+impl PgpCard {
+    async fn public_key(&self) -> Result<SignedPublicKey> {
+        let path = shellexpand_path(&self.public_key);
+        let pkey = pgp::read_pkey_from_path(path.clone())
+            .await
+            .map_err(|err| Error::ReadCardPgpPublicKeyError(err, path))?;
+        Ok(pkey)
+    }
+
+    fn native(&self) -> PgpNative {
+        PgpNative {
+            public_keys_resolvers: self.public_keys_resolvers.clone(),
+            key_selection: self.key_selection.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Builds the native backend used for encryption, which can also
+    /// resolve the public key of the sender, so that messages can be
+    /// encrypted to self.
+    async fn native_for_encryption(&self) -> PgpNative {
+        let mut native = self.native();
+
+        match self.public_key().await {
+            Ok(pkey) => {
+                for email in user_emails(&pkey) {
+                    let resolver = NativePgpPublicKeysResolver::Raw(email, pkey.clone());
+                    native.public_keys_resolvers.push(resolver);
+                }
+            }
+            Err(err) => {
+                debug!(?err, "cannot read pgp public key of the sender");
+            }
+        }
+
+        native
+    }
+
+    /// Encrypts the given plain bytes using the given recipients.
+    pub async fn encrypt(
+        &self,
+        emails: impl IntoIterator<Item = String>,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        self.encrypt_with_hidden_recipients(emails, [], data).await
+    }
+
+    /// Encrypts the given plain bytes using the given recipients and
+    /// hidden recipients.
+    pub async fn encrypt_with_hidden_recipients(
+        &self,
+        emails: impl IntoIterator<Item = String>,
+        hidden_emails: impl IntoIterator<Item = String>,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        self.native_for_encryption()
+            .await
+            .encrypt_with_hidden_recipients(emails, hidden_emails, data)
+            .await
+    }
+
+    /// Decrypts the given encrypted bytes using the card.
+    pub async fn decrypt(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        let pkey = self.public_key().await?;
+        let reader = self.reader.clone();
+        let prompts = self.prompts.clone();
+        let data = pgp::card::decrypt(reader, prompts, pkey, data)
+            .await
+            .map_err(Error::DecryptCardPgpError)?;
+        Ok(data)
+    }
+
+    /// Signs the given plain bytes using the card.
+    pub async fn sign(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        let pkey = self.public_key().await?;
+        let reader = self.reader.clone();
+        let prompts = self.prompts.clone();
+        let data = pgp::card::sign(reader, prompts, pkey, data)
+            .await
+            .map_err(Error::SignCardPgpError)?;
+        Ok(data)
+    }
+
+    /// Verifies the given signed bytes as well as the signature bytes
+    /// using the given recipient.
+    pub async fn verify(&self, email: impl AsRef<str>, sig: Vec<u8>, data: Vec<u8>) -> Result<()> {
+        self.native().verify(email, sig, data).await
+    }
+
+    /// Verifies the given signed bytes as well as the signature bytes
+    /// using the given signer, and reports details about the
+    /// signature.
+    pub async fn verify_with_report(
+        &self,
+        email: impl AsRef<str>,
+        sig: Vec<u8>,
+        data: Vec<u8>,
+    ) -> Result<PgpVerificationReport> {
+        self.native().verify_with_report(email, sig, data).await
+    }
+}
@@ -2,8 +2,12 @@
 //!
 //! This module contains the PGP backend based on shell commands.
 
+use std::time::{Duration, SystemTime};
+
 use process::Command;
+use tracing::debug;
 
+use super::{KeySelection, PgpKeyCandidate, PgpKeyValidity};
 use crate::{Error, Result};
 
 /// The shell commands PGP backend.
@@ -30,6 +34,16 @@ pub struct PgpCommands {
     /// Default to `--recipient <recipient>`.
     pub encrypt_recipient_fmt: Option<String>,
 
+    /// The PGP encrypt hidden recipient format.
+    ///
+    /// A special placeholder `<recipient>` is available to represent
+    /// one hidden recipient of the encrypt command. Hidden recipients
+    /// (like Bcc recipients) should not appear in the encrypted
+    /// message.
+    ///
+    /// Default to `--hidden-recipient <recipient>`.
+    pub encrypt_hidden_recipient_fmt: Option<String>,
+
     /// The PGP encrypt recipients separator.
     ///
     /// Separator used between recipient formats.
@@ -37,6 +51,24 @@ pub struct PgpCommands {
     /// Defaults to space.
     pub encrypt_recipients_sep: Option<String>,
 
+    /// The PGP list keys command.
+    ///
+    /// A special placeholder `<recipient>` is available to represent
+    /// the recipient keys need to be listed for. The command should
+    /// output keys using the GnuPG colon listing format. Listed keys
+    /// are selected using [`PgpCommands::key_selection`], then passed
+    /// to the encrypt command by fingerprint. When the command fails
+    /// or does not list any key, the recipient is passed as it is.
+    ///
+    /// Defaults to `gpg --list-keys --with-colons --fixed-list-mode
+    /// <recipient>`.
+    pub list_keys_cmd: Option<Command>,
+
+    /// The selection of public keys, used when several keys match
+    /// the same recipient.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub key_selection: KeySelection,
+
     /// The PGP decrypt command.
     ///
     /// Defaults to `gpg --decrypt --quiet`.
@@ -62,10 +94,18 @@ pub fn default_encrypt_recipient_fmt() -> String {
         String::from("--recipient <recipient>")
     }
 
+    pub fn default_encrypt_hidden_recipient_fmt() -> String {
+        String::from("--hidden-recipient <recipient>")
+    }
+
     pub fn default_encrypt_recipients_sep() -> String {
         String::from(" ")
     }
 
+    pub fn default_list_keys_cmd() -> Command {
+        Command::new("gpg --list-keys --with-colons --fixed-list-mode <recipient>")
+    }
+
     pub fn default_decrypt_cmd() -> Command {
         Command::new("gpg --decrypt --quiet")
     }
@@ -83,25 +123,58 @@ pub async fn encrypt(
         &self,
         recipients: impl IntoIterator<Item = String>,
         plain_bytes: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        self.encrypt_with_hidden_recipients(recipients, [], plain_bytes)
+            .await
+    }
+
+    /// Encrypts the given plain bytes using the given recipients and
+    /// hidden recipients.
+    pub async fn encrypt_with_hidden_recipients(
+        &self,
+        recipients: impl IntoIterator<Item = String>,
+        hidden_recipients: impl IntoIterator<Item = String>,
+        plain_bytes: Vec<u8>,
     ) -> Result<Vec<u8>> {
         let recipient_fmt = self
             .encrypt_recipient_fmt
             .clone()
             .unwrap_or_else(Self::default_encrypt_recipient_fmt);
+        let hidden_recipient_fmt = self
+            .encrypt_hidden_recipient_fmt
+            .clone()
+            .unwrap_or_else(Self::default_encrypt_hidden_recipient_fmt);
         let recipients_sep = self
             .encrypt_recipients_sep
             .clone()
             .unwrap_or_else(Self::default_encrypt_recipients_sep);
-        let recipients_str =
-            recipients
-                .into_iter()
-                .fold(String::new(), |mut recipients_str, recipient| {
-                    if !recipients_str.is_empty() {
-                        recipients_str.push_str(&recipients_sep);
-                    }
-                    recipients_str.push_str(&recipient_fmt.replace("<recipient>", &recipient));
-                    recipients_str
-                });
+
+        let mut resolved_recipients = Vec::new();
+        for recipient in recipients {
+            resolved_recipients.extend(self.resolve_recipient(recipient).await?);
+        }
+
+        let mut resolved_hidden_recipients = Vec::new();
+        for recipient in hidden_recipients {
+            resolved_hidden_recipients.extend(self.resolve_recipient(recipient).await?);
+        }
+
+        let recipients = resolved_recipients
+            .into_iter()
+            .map(|recipient| recipient_fmt.replace("<recipient>", &recipient));
+        let hidden_recipients = resolved_hidden_recipients
+            .into_iter()
+            .map(|recipient| hidden_recipient_fmt.replace("<recipient>", &recipient));
+        let recipients_str = recipients.chain(hidden_recipients).fold(
+            String::new(),
+            |mut recipients_str, recipient| {
+                if !recipients_str.is_empty() {
+                    recipients_str.push_str(&recipients_sep);
+                }
+                recipients_str.push_str(&recipient);
+                recipients_str
+            },
+        );
 
         let res = self
             .encrypt_cmd
@@ -115,6 +188,47 @@ pub async fn encrypt(
         Ok(res.into())
     }
 
+    /// Resolves the given recipient into the recipients passed to
+    /// the encrypt command.
+    ///
+    /// Keys matching the recipient are listed using the list keys
+    /// command, then selected using the key selection configuration.
+    /// When no key can be listed, pinned keys are used as they are,
+    /// or the recipient itself when there is no pinned key.
+    async fn resolve_recipient(&self, recipient: String) -> Result<Vec<String>> {
+        let candidates = match self
+            .list_keys_cmd
+            .clone()
+            .unwrap_or_else(Self::default_list_keys_cmd)
+            .replace("<recipient>", &recipient)
+            .run()
+            .await
+        {
+            Ok(output) => parse_colon_listing(&output.to_string_lossy()),
+            Err(err) => {
+                debug!(?err, "cannot list pgp keys of {recipient}");
+                Vec::new()
+            }
+        };
+
+        if candidates.is_empty() {
+            let recipients = match self.key_selection.pinned(&recipient) {
+                Some(pins) => pins.to_vec(),
+                None => vec![recipient],
+            };
+            return Ok(recipients);
+        }
+
+        let fingerprints = self
+            .key_selection
+            .select(&recipient, &candidates)?
+            .into_iter()
+            .map(|i| candidates[i].fingerprint.clone())
+            .collect();
+
+        Ok(fingerprints)
+    }
+
     /// Decrypts the given encrypted bytes.
     pub async fn decrypt(&self, encrypted_bytes: Vec<u8>) -> Result<Vec<u8>> {
         let res = self
@@ -153,3 +267,112 @@ pub async fn verify(&self, signature_bytes: Vec<u8>, _signed_bytes: Vec<u8>) ->
         Ok(())
     }
 }
+
+/// Parses the keys of the given GnuPG colon listing.
+///
+/// See the `DETAILS` file of the GnuPG documentation for the format.
+/// Keys that cannot be used for encryption are skipped.
+fn parse_colon_listing(listing: &str) -> Vec<PgpKeyCandidate> {
+    let mut candidates = Vec::new();
+    // whether the current primary key is kept, and whether its
+    // fingerprint is expected on the next `fpr` record
+    let mut current = false;
+    let mut expect_fpr = false;
+
+    for line in listing.lines() {
+        let fields: Vec<&str> = line.split(':').collect();
+        let field = |i: usize| fields.get(i).copied().unwrap_or_default();
+
+        match field(0) {
+            "pub" => {
+                let caps = field(11);
+                current = caps.is_empty() || caps.contains('E');
+                expect_fpr = current;
+
+                if current {
+                    let validity = match field(1) {
+                        "r" => PgpKeyValidity::Revoked,
+                        "e" => PgpKeyValidity::Expired,
+                        _ => PgpKeyValidity::Valid,
+                    };
+                    let created_at = field(5)
+                        .parse()
+                        .ok()
+                        .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+
+                    candidates.push(PgpKeyCandidate {
+                        created_at,
+                        validity,
+                        ..Default::default()
+                    });
+                }
+            }
+            "fpr" if expect_fpr => {
+                expect_fpr = false;
+                if let Some(candidate) = candidates.last_mut() {
+                    candidate.fingerprint = field(9).to_uppercase();
+                }
+            }
+            "uid" if current => {
+                if let Some(candidate) = candidates.last_mut() {
+                    let uid = field(9).replace("\\x3a", ":");
+                    candidate.user_ids.push(uid);
+                }
+            }
+            "sub" => {
+                expect_fpr = false;
+            }
+            _ => (),
+        }
+    }
+
+    candidates.retain(|candidate| !candidate.fingerprint.is_empty());
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::parse_colon_listing;
+    use crate::pgp::PgpKeyValidity;
+
+    #[test]
+    fn parse_colon_listing_keys() {
+        let listing = concat!(
+            "tru::1:1700000000:0:3:1:5\n",
+            "pub:u:255:22:1111111111111111:1700000000:::u:::scESC::::::ed25519:::0:\n",
+            "fpr:::::::::AAAAAAAAAAAAAAAAAAAAAAAA1111111111111111:\n",
+            "uid:u::::1700000000::HASH::Alice <alice@localhost>::::::::::0:\n",
+            "sub:u:255:18:2222222222222222:1700000000::::::e:::::cv25519::\n",
+            "fpr:::::::::BBBBBBBBBBBBBBBBBBBBBBBB2222222222222222:\n",
+            "pub:r:255:22:3333333333333333:1600000000:::-:::sc::::::ed25519:::0:\n",
+            "fpr:::::::::CCCCCCCCCCCCCCCCCCCCCCCC3333333333333333:\n",
+            "uid:r::::1600000000::HASH::Alice <alice@localhost>::::::::::0:\n",
+            "pub:e:255:22:4444444444444444:1500000000:1600000000::-:::scE::::::ed25519:::0:\n",
+            "fpr:::::::::dddddddddddddddddddddddd4444444444444444:\n",
+            "uid:e::::1500000000::HASH::Alice\\x3a old <alice@localhost>::::::::::0:\n",
+        );
+
+        let candidates = parse_colon_listing(listing);
+        assert_eq!(candidates.len(), 2);
+
+        assert_eq!(
+            candidates[0].fingerprint,
+            "AAAAAAAAAAAAAAAAAAAAAAAA1111111111111111"
+        );
+        assert_eq!(candidates[0].user_ids, vec!["Alice <alice@localhost>"]);
+        assert_eq!(
+            candidates[0].created_at,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1700000000))
+        );
+        assert_eq!(candidates[0].validity, PgpKeyValidity::Valid);
+
+        assert_eq!(
+            candidates[1].fingerprint,
+            "DDDDDDDDDDDDDDDDDDDDDDDD4444444444444444"
+        );
+        assert_eq!(candidates[1].user_ids, vec!["Alice: old <alice@localhost>"]);
+        assert_eq!(candidates[1].validity, PgpKeyValidity::Expired);
+    }
+}
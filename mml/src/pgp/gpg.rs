@@ -4,9 +4,10 @@
 
 use std::path::PathBuf;
 
-use gpgme::{Context, Protocol};
+use gpgme::{Context, EncryptFlags, Key, Protocol, SignatureSummary};
 use tracing::{debug, trace};
 
+use super::{KeySelection, PgpKeyCandidate, PgpKeyValidity, PgpVerificationReport};
 use crate::{Error, Result};
 
 /// The GPG PGP backend.
@@ -21,6 +22,11 @@ pub struct PgpGpg {
     ///
     /// Defaults to GPG default home directory (~/.gpg).
     pub home_dir: Option<PathBuf>,
+
+    /// The selection of public keys, used when several keys match
+    /// the same recipient.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub key_selection: KeySelection,
 }
 
 impl PgpGpg {
@@ -47,28 +53,40 @@ pub async fn encrypt(
         &self,
         emails: impl IntoIterator<Item = String>,
         plain_bytes: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        self.encrypt_with_hidden_recipients(emails, [], plain_bytes)
+            .await
+    }
+
+    /// Encrypts the given plain bytes using the given recipients and
+    /// hidden recipients.
+    ///
+    /// GPG cannot hide only some of the recipients: as soon as there
+    /// is one hidden recipient, all recipients are hidden.
+    pub async fn encrypt_with_hidden_recipients(
+        &self,
+        emails: impl IntoIterator<Item = String>,
+        hidden_emails: impl IntoIterator<Item = String>,
+        plain_bytes: Vec<u8>,
     ) -> Result<Vec<u8>> {
         let mut ctx = self.get_context()?;
 
         // TODO: make it really async
         let mut keys = Vec::new();
-        for ref email in emails {
-            match ctx.locate_key(email) {
-                Ok(key) => {
-                    debug!("found public key for {email} for encryption");
-                    trace!("{key:#?}");
-                    keys.push(key);
-                }
-                Err(err) => {
-                    debug!("cannot locate gpg key for {email}: {err}");
-                    debug!("cannot locate gpg key for {email}: {err}");
-                }
-            }
-        }
+        self.locate_keys(&mut ctx, emails, &mut keys)?;
+
+        let visible_keys_count = keys.len();
+        self.locate_keys(&mut ctx, hidden_emails, &mut keys)?;
+
+        let flags = if keys.len() > visible_keys_count {
+            EncryptFlags::THROW_KEYIDS
+        } else {
+            EncryptFlags::empty()
+        };
 
         let mut encrypted_bytes = Vec::new();
         let res = ctx
-            .encrypt(keys.iter(), plain_bytes, &mut encrypted_bytes)
+            .encrypt_with_flags(keys.iter(), plain_bytes, &mut encrypted_bytes, flags)
             .map_err(Error::EncryptGpgError)?;
         trace!("encrypt result: {res:#?}");
 
@@ -81,6 +99,53 @@ pub async fn encrypt(
         Ok(encrypted_bytes)
     }
 
+    /// Locates the public keys of the given email addresses, then
+    /// selects keys using the key selection configuration.
+    ///
+    /// Keys are first searched in the local keyring. When none
+    /// matches, GPG is asked to locate one, which may retrieve it
+    /// using the `auto-key-locate` mechanisms.
+    fn locate_keys(
+        &self,
+        ctx: &mut Context,
+        emails: impl IntoIterator<Item = String>,
+        keys: &mut Vec<Key>,
+    ) -> Result<()> {
+        for ref email in emails {
+            let mut candidates: Vec<Key> = match ctx.find_keys([email.as_str()]) {
+                Ok(found) => found
+                    .filter_map(|key| key.ok())
+                    .filter(|key| !key.is_disabled() && !key.is_invalid())
+                    .collect(),
+                Err(err) => {
+                    debug!("cannot find gpg keys for {email}: {err}");
+                    Vec::new()
+                }
+            };
+
+            if candidates.is_empty() {
+                match ctx.locate_key(email) {
+                    Ok(key) => candidates.push(key),
+                    Err(err) => {
+                        debug!("cannot locate gpg key for {email}: {err}");
+                        continue;
+                    }
+                }
+            }
+
+            for key in self
+                .key_selection
+                .select_keys(email, candidates, key_candidate)?
+            {
+                debug!("found public key for {email} for encryption");
+                trace!("{key:#?}");
+                keys.push(key);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Decrypts the given encrypted bytes.
     pub async fn decrypt(&self, mut encrypted_bytes: Vec<u8>) -> Result<Vec<u8>> {
         let mut ctx = self.get_context()?;
@@ -118,4 +183,75 @@ pub async fn verify(&self, signature_bytes: Vec<u8>, signed_bytes: Vec<u8>) -> R
 
         Ok(())
     }
+
+    /// Verifies the given signed bytes as well as the signature
+    /// bytes, and reports details about the signature.
+    pub async fn verify_with_report(
+        &self,
+        signer: impl AsRef<str>,
+        signature_bytes: Vec<u8>,
+        signed_bytes: Vec<u8>,
+    ) -> Result<PgpVerificationReport> {
+        let mut ctx = self.get_context()?;
+
+        let res = ctx
+            .verify_opaque(signature_bytes, signed_bytes)
+            .map_err(Error::VerifyGpgError)?;
+        trace!("verify result: {res:#?}");
+
+        let mut report = PgpVerificationReport::new(signer.as_ref());
+
+        let Some(sig) = res.signatures().next() else {
+            report.error = Some(String::from("missing signature"));
+            return Ok(report);
+        };
+
+        if let Err(err) = sig.status() {
+            report.error = Some(err.to_string());
+        }
+
+        // gpg reports the key id in place of the fingerprint when
+        // the key is missing
+        if let Ok(fpr) = sig.fingerprint() {
+            let fpr = fpr.to_uppercase();
+            report.key_id = Some(fpr[fpr.len().saturating_sub(16)..].to_owned());
+            report.fingerprint = Some(fpr).filter(|fpr| fpr.len() > 16);
+        }
+
+        report.created_at = sig.creation_time();
+
+        let summary = sig.summary();
+        report.key_validity = if summary.contains(SignatureSummary::KEY_REVOKED) {
+            PgpKeyValidity::Revoked
+        } else if summary.contains(SignatureSummary::KEY_EXPIRED) {
+            PgpKeyValidity::Expired
+        } else if summary.contains(SignatureSummary::KEY_MISSING) {
+            PgpKeyValidity::Unknown
+        } else {
+            PgpKeyValidity::Valid
+        };
+
+        Ok(report)
+    }
+}
+
+/// Describes the given GPG key as a key selection candidate.
+fn key_candidate(key: &Key) -> PgpKeyCandidate {
+    let validity = if key.is_revoked() {
+        PgpKeyValidity::Revoked
+    } else if key.is_expired() {
+        PgpKeyValidity::Expired
+    } else {
+        PgpKeyValidity::Valid
+    };
+
+    PgpKeyCandidate {
+        user_ids: key
+            .user_ids()
+            .filter_map(|uid| uid.id().ok().map(ToOwned::to_owned))
+            .collect(),
+        created_at: key.primary_key().and_then(|key| key.creation_time()),
+        validity,
+        ..PgpKeyCandidate::new(key.fingerprint().unwrap_or_default())
+    }
 }
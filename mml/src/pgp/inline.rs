@@ -0,0 +1,305 @@
+//! # PGP inline
+//!
+//! Module dedicated to PGP inline, as opposed to PGP/MIME. Encrypted
+//! texts are ASCII-armored messages, and signed texts use the
+//! cleartext signature framework defined in [RFC 4880].
+//!
+//! [RFC 4880]: https://www.rfc-editor.org/rfc/rfc4880#section-7
+
+use mail_parser::decoders::base64::base64_decode;
+
+pub(crate) const MESSAGE_BEGIN: &str = "-----BEGIN PGP MESSAGE-----";
+pub(crate) const MESSAGE_END: &str = "-----END PGP MESSAGE-----";
+pub(crate) const SIGNED_MESSAGE_BEGIN: &str = "-----BEGIN PGP SIGNED MESSAGE-----";
+pub(crate) const SIGNATURE_BEGIN: &str = "-----BEGIN PGP SIGNATURE-----";
+pub(crate) const SIGNATURE_END: &str = "-----END PGP SIGNATURE-----";
+
+/// A block of a plain text containing PGP inline data.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum InlineBlock<'a> {
+    /// A text outside of any armored block.
+    Text(&'a str),
+
+    /// An ASCII-armored encrypted message.
+    Encrypted(&'a str),
+
+    /// A cleartext signed message.
+    Signed {
+        /// The dash-unescaped cleartext.
+        text: String,
+
+        /// The ASCII-armored signature.
+        signature: &'a str,
+    },
+}
+
+/// Return `true` if the given text contains PGP inline data.
+pub(crate) fn has_inline_blocks(text: &str) -> bool {
+    text.contains(MESSAGE_BEGIN) || text.contains(SIGNED_MESSAGE_BEGIN)
+}
+
+/// Split the given plain text into text and PGP inline blocks.
+///
+/// The text is expected to use `\n` line endings. Armored blocks that are not terminated are kept as text.
+pub(crate) fn split_inline_blocks(text: &str) -> Vec<InlineBlock<'_>> {
+    let mut blocks = Vec::new();
+    let mut rest = text;
+
+    loop {
+        let next = [MESSAGE_BEGIN, SIGNED_MESSAGE_BEGIN]
+            .into_iter()
+            .filter_map(|begin| find_line(rest, begin).map(|i| (i, begin)))
+            .min_by_key(|(i, _)| *i);
+
+        let Some((i, begin)) = next else {
+            break;
+        };
+
+        let block = match begin {
+            MESSAGE_BEGIN => parse_encrypted(&rest[i..]),
+            _ => parse_signed(&rest[i..]),
+        };
+
+        let Some((block, len)) = block else {
+            break;
+        };
+
+        if i > 0 {
+            blocks.push(InlineBlock::Text(&rest[..i]));
+        }
+
+        blocks.push(block);
+        rest = &rest[i + len..];
+    }
+
+    if !rest.is_empty() {
+        blocks.push(InlineBlock::Text(rest));
+    }
+
+    blocks
+}
+
+/// Build the data to sign or to verify from the given cleartext.
+///
+/// As defined by the cleartext signature framework, trailing
+/// whitespaces are removed from every line, line endings are
+/// converted to `\r\n` and the last line ending is excluded.
+pub(crate) fn cleartext_signed_data(text: &str) -> Vec<u8> {
+    let text = text.strip_suffix('\n').unwrap_or(text);
+
+    text.split('\n')
+        .map(|line| line.trim_end_matches([' ', '\t', '\r']))
+        .collect::<Vec<_>>()
+        .join("\r\n")
+        .into_bytes()
+}
+
+/// Build a cleartext signed message from the given text and the
+/// given ASCII-armored signature.
+pub(crate) fn build_signed_message(text: &str, signature: &str) -> String {
+    let mut msg = String::from(SIGNED_MESSAGE_BEGIN);
+    msg.push('\n');
+
+    if let Some(hash) = find_signature_hash(signature) {
+        msg.push_str(&format!("Hash: {hash}\n"));
+    }
+
+    msg.push('\n');
+
+    // trailing whitespaces are ignored by signatures, they are
+    // kept so that the signature delimiter `-- ` is preserved
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    for line in text.split('\n') {
+        if line.starts_with('-') {
+            msg.push_str("- ");
+        }
+        msg.push_str(line);
+        msg.push('\n');
+    }
+
+    msg.push_str(signature.trim());
+    msg.push('\n');
+    msg
+}
+
+/// Find the byte offset of the first line starting by the given
+/// armor header line.
+fn find_line(text: &str, header: &str) -> Option<usize> {
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        if line.trim_end() == header {
+            return Some(offset);
+        }
+        offset += line.len();
+    }
+
+    None
+}
+
+/// Find the byte offset of the end of the line containing the given
+/// armor tail line, starting from the given offset.
+fn find_line_end(text: &str, tail: &str) -> Option<usize> {
+    let i = find_line(text, tail)?;
+    let end = text[i..].find('\n').map_or(text.len(), |j| i + j + 1);
+    Some(end)
+}
+
+/// Parse the encrypted message at the beginning of the given text.
+fn parse_encrypted(text: &str) -> Option<(InlineBlock<'_>, usize)> {
+    let len = find_line_end(text, MESSAGE_END)?;
+    Some((InlineBlock::Encrypted(&text[..len]), len))
+}
+
+/// Parse the cleartext signed message at the beginning of the given
+/// text.
+fn parse_signed(text: &str) -> Option<(InlineBlock<'_>, usize)> {
+    // armor headers (like `Hash`) end at the first empty line
+    let text_begin = text.find("\n\n")? + 2;
+
+    let signature_begin = text_begin + find_line(&text[text_begin..], SIGNATURE_BEGIN)?;
+    let len = signature_begin + find_line_end(&text[signature_begin..], SIGNATURE_END)?;
+
+    let cleartext = text[text_begin..signature_begin]
+        .split_inclusive('\n')
+        .map(|line| line.strip_prefix("- ").unwrap_or(line))
+        .collect();
+
+    let block = InlineBlock::Signed {
+        text: cleartext,
+        signature: &text[signature_begin..len],
+    };
+
+    Some((block, len))
+}
+
+/// Find the name of the hash algorithm of the given ASCII-armored
+/// signature, as expected by the `Hash` armor header.
+fn find_signature_hash(signature: &str) -> Option<&'static str> {
+    let data: String = signature
+        .lines()
+        .skip_while(|line| line.trim() != SIGNATURE_BEGIN)
+        .skip(1)
+        .skip_while(|line| !line.trim().is_empty())
+        .skip(1)
+        .take_while(|line| !line.starts_with('=') && !line.starts_with("-----"))
+        .collect();
+
+    let packet = base64_decode(data.as_bytes())?;
+    let (&tag, packet) = packet.split_first()?;
+
+    // skip the packet length, see RFC 4880 section 4.2
+    let len_size = if tag & 0x40 == 0 {
+        match tag & 0x03 {
+            0 => 1,
+            1 => 2,
+            2 => 4,
+            _ => 0,
+        }
+    } else {
+        match packet.first()? {
+            0..=191 => 1,
+            192..=223 => 2,
+            255 => 5,
+            _ => 1,
+        }
+    };
+
+    let body = packet.get(len_size..)?;
+
+    // see RFC 4880 section 5.2
+    let hash = match body.first()? {
+        3 => body.get(16)?,
+        _ => body.get(3)?,
+    };
+
+    // see RFC 4880 section 9.4
+    match hash {
+        1 => Some("MD5"),
+        2 => Some("SHA1"),
+        3 => Some("RIPEMD160"),
+        8 => Some("SHA256"),
+        9 => Some("SHA384"),
+        10 => Some("SHA512"),
+        11 => Some("SHA224"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use concat_with::concat_line;
+
+    use super::{build_signed_message, cleartext_signed_data, split_inline_blocks, InlineBlock};
+
+    const SIGNATURE: &str = concat_line!(
+        "-----BEGIN PGP SIGNATURE-----",
+        "",
+        "iHUEARYIAB0WIQQ=",
+        "=abcd",
+        "-----END PGP SIGNATURE-----",
+        "",
+    );
+
+    #[test]
+    fn signed_message() {
+        let text = concat_line!("Hello  ", "-- ", "Alice");
+
+        assert_eq!(cleartext_signed_data(text), b"Hello\r\n--\r\nAlice");
+
+        let msg = build_signed_message(text, SIGNATURE);
+
+        assert_eq!(
+            msg,
+            concat_line!(
+                "-----BEGIN PGP SIGNED MESSAGE-----",
+                "Hash: SHA256",
+                "",
+                "Hello  ",
+                "- -- ",
+                "Alice",
+                "-----BEGIN PGP SIGNATURE-----",
+                "",
+                "iHUEARYIAB0WIQQ=",
+                "=abcd",
+                "-----END PGP SIGNATURE-----",
+                "",
+            ),
+        );
+
+        let text = format!("Before\n{msg}After\n");
+
+        assert_eq!(
+            split_inline_blocks(&text),
+            vec![
+                InlineBlock::Text("Before\n"),
+                InlineBlock::Signed {
+                    text: String::from("Hello  \n-- \nAlice\n"),
+                    signature: SIGNATURE,
+                },
+                InlineBlock::Text("After\n"),
+            ],
+        );
+    }
+
+    #[test]
+    fn encrypted_message() {
+        let encrypted = concat_line!(
+            "-----BEGIN PGP MESSAGE-----",
+            "",
+            "hQEMA=",
+            "-----END PGP MESSAGE-----",
+            "",
+        );
+
+        let text = format!("{encrypted}Unterminated\n-----BEGIN PGP MESSAGE-----\n");
+
+        assert_eq!(
+            split_inline_blocks(&text),
+            vec![
+                InlineBlock::Encrypted(encrypted),
+                InlineBlock::Text("Unterminated\n-----BEGIN PGP MESSAGE-----\n"),
+            ],
+        );
+    }
+}
@@ -1,19 +1,39 @@
 //! # PGP
 //!
-//! This module contains available PGP backends: shell commands, GPG
-//! and native.
+//! This module contains available PGP backends: shell commands, GPG,
+//! gpg-agent, OpenPGP cards and native.
 
+#[cfg(all(feature = "pgp-agent", unix))]
+pub mod agent;
+#[cfg(feature = "autocrypt")]
+pub mod autocrypt;
+#[cfg(feature = "pgp-card")]
+pub mod card;
 #[cfg(feature = "pgp-commands")]
 pub mod commands;
 #[cfg(feature = "pgp-gpg")]
 pub mod gpg;
+pub(crate) mod inline;
 #[cfg(feature = "pgp-native")]
 pub mod native;
+#[cfg(feature = "pgp-native")]
+pub mod passphrase;
+pub mod report;
+pub mod selection;
 
 use tracing::{debug, trace};
 
 use crate::{Error, Result};
 
+#[cfg(all(feature = "pgp-agent", unix))]
+#[doc(inline)]
+pub use self::agent::PgpAgent;
+#[cfg(feature = "autocrypt")]
+#[doc(inline)]
+pub use self::autocrypt::{AutocryptHeader, AutocryptPeer, AutocryptPeers, PreferEncrypt};
+#[cfg(feature = "pgp-card")]
+#[doc(inline)]
+pub use self::card::{CardKeySlot, CardPrompts, PgpCard};
 #[cfg(feature = "pgp-commands")]
 #[doc(inline)]
 pub use self::commands::PgpCommands;
@@ -25,6 +45,13 @@
 pub use self::native::{
     NativePgpPublicKeysResolver, NativePgpSecretKey, PgpNative, SignedPublicKey, SignedSecretKey,
 };
+#[cfg(feature = "pgp-native")]
+#[doc(inline)]
+pub use self::passphrase::{PassphraseCache, PassphrasePrompt};
+#[doc(inline)]
+pub use self::report::{PartVerificationReport, PgpKeyValidity, PgpVerificationReport};
+#[doc(inline)]
+pub use self::selection::{KeySelection, KeySelectionPolicy, KeySelector, PgpKeyCandidate};
 
 /// The PGP backends.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -46,6 +73,23 @@ pub enum Pgp {
     /// Use native Rust implementation of PGP to perform PGP actions.
     #[cfg(feature = "pgp-native")]
     Native(PgpNative),
+
+    /// Use gpg-agent to perform secret key PGP actions, and native
+    /// Rust implementation of PGP to perform public key PGP actions.
+    ///
+    /// Secret keys never leave the agent, which takes care of
+    /// unlocking them.
+    #[cfg(all(feature = "pgp-agent", unix))]
+    Agent(PgpAgent),
+
+    /// Use OpenPGP cards to perform secret key PGP actions, and
+    /// native Rust implementation of PGP to perform public key PGP
+    /// actions.
+    ///
+    /// Secret keys never leave the card. PIN and touch prompts are
+    /// performed through [`CardPrompts`].
+    #[cfg(feature = "pgp-card")]
+    Card(PgpCard),
 }
 
 impl Pgp {
@@ -54,6 +98,21 @@ pub async fn encrypt(
         &self,
         recipients: impl IntoIterator<Item = String>,
         plain_bytes: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        self.encrypt_with_hidden_recipients(recipients, [], plain_bytes)
+            .await
+    }
+
+    /// Encrypts the given plain bytes using the given recipients and
+    /// hidden recipients.
+    ///
+    /// Hidden recipients (like Bcc recipients) can decrypt the
+    /// message, but their key ids do not appear in it.
+    pub async fn encrypt_with_hidden_recipients(
+        &self,
+        recipients: impl IntoIterator<Item = String>,
+        hidden_recipients: impl IntoIterator<Item = String>,
+        plain_bytes: Vec<u8>,
     ) -> Result<Vec<u8>> {
         debug!("encrypting bytes using pgp");
         let plain_str = String::from_utf8_lossy(&plain_bytes);
@@ -62,11 +121,32 @@ pub async fn encrypt(
         match self {
             Self::None => Err(Error::PgpMissingConfigurationError),
             #[cfg(feature = "pgp-commands")]
-            Self::Commands(cmds) => cmds.encrypt(recipients, plain_bytes).await,
+            Self::Commands(cmds) => {
+                cmds.encrypt_with_hidden_recipients(recipients, hidden_recipients, plain_bytes)
+                    .await
+            }
             #[cfg(feature = "pgp-native")]
-            Self::Native(native) => native.encrypt(recipients, plain_bytes).await,
+            Self::Native(native) => {
+                native
+                    .encrypt_with_hidden_recipients(recipients, hidden_recipients, plain_bytes)
+                    .await
+            }
             #[cfg(feature = "pgp-gpg")]
-            Self::Gpg(gpg) => gpg.encrypt(recipients, plain_bytes).await,
+            Self::Gpg(gpg) => {
+                gpg.encrypt_with_hidden_recipients(recipients, hidden_recipients, plain_bytes)
+                    .await
+            }
+            #[cfg(all(feature = "pgp-agent", unix))]
+            Self::Agent(agent) => {
+                agent
+                    .encrypt_with_hidden_recipients(recipients, hidden_recipients, plain_bytes)
+                    .await
+            }
+            #[cfg(feature = "pgp-card")]
+            Self::Card(card) => {
+                card.encrypt_with_hidden_recipients(recipients, hidden_recipients, plain_bytes)
+                    .await
+            }
         }
     }
 
@@ -89,6 +169,10 @@ pub async fn decrypt(
             Self::Native(native) => native.decrypt(recipient, encrypted_bytes).await,
             #[cfg(feature = "pgp-gpg")]
             Self::Gpg(gpg) => gpg.decrypt(encrypted_bytes).await,
+            #[cfg(all(feature = "pgp-agent", unix))]
+            Self::Agent(agent) => agent.decrypt(encrypted_bytes).await,
+            #[cfg(feature = "pgp-card")]
+            Self::Card(card) => card.decrypt(encrypted_bytes).await,
         }
     }
 
@@ -107,6 +191,10 @@ pub async fn sign(&self, recipient: impl ToString, plain_bytes: Vec<u8>) -> Resu
             Self::Native(native) => native.sign(recipient, plain_bytes).await,
             #[cfg(feature = "pgp-gpg")]
             Self::Gpg(gpg) => gpg.sign(plain_bytes).await,
+            #[cfg(all(feature = "pgp-agent", unix))]
+            Self::Agent(agent) => agent.sign(plain_bytes).await,
+            #[cfg(feature = "pgp-card")]
+            Self::Card(card) => card.sign(plain_bytes).await,
         }
     }
 
@@ -137,6 +225,60 @@ pub async fn verify(
             }
             #[cfg(feature = "pgp-gpg")]
             Self::Gpg(gpg) => gpg.verify(signature_bytes, signed_bytes).await,
+            #[cfg(all(feature = "pgp-agent", unix))]
+            Self::Agent(agent) => agent.verify(recipient, signature_bytes, signed_bytes).await,
+            #[cfg(feature = "pgp-card")]
+            Self::Card(card) => card.verify(recipient, signature_bytes, signed_bytes).await,
+        }
+    }
+
+    /// Verifies the given signed bytes as well as the given signature
+    /// bytes using the given signer, and reports details about the
+    /// signature.
+    ///
+    /// An invalid signature does not fail: the reason is stored in
+    /// [`PgpVerificationReport::error`].
+    pub async fn verify_with_report(
+        &self,
+        signer: impl AsRef<str>,
+        signature_bytes: Vec<u8>,
+        signed_bytes: Vec<u8>,
+    ) -> Result<PgpVerificationReport> {
+        let signer = signer.as_ref();
+        debug!("verifying signature of {signer} with report using pgp");
+
+        match self {
+            Self::None => Err(Error::PgpMissingConfigurationError),
+            #[cfg(feature = "pgp-commands")]
+            Self::Commands(cmds) => {
+                let report = match cmds.verify(signature_bytes, signed_bytes).await {
+                    Ok(()) => PgpVerificationReport::new(signer),
+                    Err(err) => PgpVerificationReport::from_error(signer, &err),
+                };
+                Ok(report)
+            }
+            #[cfg(feature = "pgp-native")]
+            Self::Native(native) => {
+                native
+                    .verify_with_report(signer, signature_bytes, signed_bytes)
+                    .await
+            }
+            #[cfg(feature = "pgp-gpg")]
+            Self::Gpg(gpg) => {
+                gpg.verify_with_report(signer, signature_bytes, signed_bytes)
+                    .await
+            }
+            #[cfg(all(feature = "pgp-agent", unix))]
+            Self::Agent(agent) => {
+                agent
+                    .verify_with_report(signer, signature_bytes, signed_bytes)
+                    .await
+            }
+            #[cfg(feature = "pgp-card")]
+            Self::Card(card) => {
+                card.verify_with_report(signer, signature_bytes, signed_bytes)
+                    .await
+            }
         }
     }
 }
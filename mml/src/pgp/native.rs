@@ -2,13 +2,22 @@
 //!
 //! This module contains the native PGP backend.
 
-use std::{collections::HashSet, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
 
 pub use pgp::native::{SignedPublicKey, SignedSecretKey};
 use secret::Secret;
 use shellexpand_utils::shellexpand_path;
 use tracing::debug;
 
+#[cfg(feature = "autocrypt")]
+use super::autocrypt::{AutocryptHeader, AutocryptPeers, PreferEncrypt};
+use super::{
+    KeySelection, PassphraseCache, PassphrasePrompt, PgpKeyCandidate, PgpVerificationReport,
+};
 use crate::{Error, Result};
 
 /// The native PGP secret key source.
@@ -89,6 +98,11 @@ pub enum NativePgpPublicKeysResolver {
     ///
     /// Supported protocols: `http(s)://`, `hkp(s)://`.
     KeyServers(Vec<String>),
+
+    /// The public key is resolved using the Autocrypt peer state
+    /// database located at the given path.
+    #[cfg(feature = "autocrypt")]
+    Autocrypt(PathBuf),
 }
 
 /// The native PGP backend.
@@ -105,25 +119,94 @@ pub struct PgpNative {
     /// The passphrase associated to the secret key.
     pub secret_key_passphrase: Secret,
 
+    /// The callback used to prompt the user for the passphrase of
+    /// the secret key, when [`PgpNative::secret_key_passphrase`] is
+    /// empty.
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub secret_key_passphrase_prompt: Option<PassphrasePrompt>,
+
+    /// The time to live of cached passphrases, in seconds.
+    ///
+    /// Secret keys and their passphrases are kept in memory during
+    /// this time once they successfully decrypted or signed a
+    /// message, so that the user is not prompted (and the keyring is
+    /// not hit) for every message. `0` disables the cache.
+    ///
+    /// Defaults to 600 (10 minutes).
+    pub passphrase_cache_ttl: Option<u64>,
+
+    /// The in-memory cache of secret keys and their passphrases.
+    ///
+    /// Clones of the backend share the same cache.
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub passphrase_cache: PassphraseCache,
+
     /// The list of public key resolvers.
     pub public_keys_resolvers: Vec<NativePgpPublicKeysResolver>,
+
+    /// The selection of public keys, used when several keys match
+    /// the same recipient.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub key_selection: KeySelection,
 }
 
 impl PgpNative {
+    pub fn default_passphrase_cache_ttl() -> u64 {
+        600
+    }
+
     /// Encrypts the given plain bytes using the given recipients.
     pub async fn encrypt(
         &self,
         emails: impl IntoIterator<Item = String>,
         data: Vec<u8>,
     ) -> Result<Vec<u8>> {
-        let mut pkeys = Vec::new();
+        self.encrypt_with_hidden_recipients(emails, [], data).await
+    }
+
+    /// Encrypts the given plain bytes using the given recipients and
+    /// hidden recipients.
+    ///
+    /// The key ids of hidden recipients do not appear in the
+    /// encrypted message.
+    pub async fn encrypt_with_hidden_recipients(
+        &self,
+        emails: impl IntoIterator<Item = String>,
+        hidden_emails: impl IntoIterator<Item = String>,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        let pkeys = self.find_public_keys(emails).await?;
+        let hidden_pkeys = self.find_public_keys(hidden_emails).await?;
+
+        let data = pgp::encrypt_with_hidden_recipients(pkeys, hidden_pkeys, data)
+            .await
+            .map_err(Error::EncryptNativePgpError)?;
+
+        Ok(data)
+    }
+
+    /// Finds the public keys of the given email addresses using the
+    /// public key resolvers, in order, then selects keys using the
+    /// key selection configuration.
+    ///
+    /// All raw pairs matching a recipient are taken into account,
+    /// whereas other resolvers are only used for recipients that are
+    /// not resolved yet. Email addresses that cannot be resolved are
+    /// matched against the user ids of the secret key, so that
+    /// messages can be encrypted to self.
+    async fn find_public_keys(
+        &self,
+        emails: impl IntoIterator<Item = String>,
+    ) -> Result<Vec<SignedPublicKey>> {
+        let mut candidates: HashMap<String, Vec<SignedPublicKey>> = HashMap::new();
         let mut recipients: HashSet<String> = HashSet::from_iter(emails.into_iter());
 
         for resolver in &self.public_keys_resolvers {
             match resolver {
                 NativePgpPublicKeysResolver::Raw(recipient, pkey) => {
-                    if recipients.remove(recipient) {
+                    if recipients.remove(recipient) || candidates.contains_key(recipient) {
                         debug!("found pgp public key for {recipient} using raw pair");
+                        let pkeys = candidates.entry(recipient.clone()).or_default();
                         pkeys.push(pkey.clone())
                     }
                 }
@@ -131,98 +214,231 @@ pub async fn encrypt(
                     let recipients_clone = recipients.clone().into_iter().collect();
                     let wkd_pkeys = pgp::http::wkd::get_all(recipients_clone).await;
 
-                    pkeys.extend(wkd_pkeys.into_iter().fold(
-                        Vec::new(),
-                        |mut pkeys, (ref recipient, res)| {
-                            match res {
-                                Ok(pkey) => {
-                                    if recipients.remove(recipient) {
-                                        debug!("found pgp public key for {recipient} using wkd");
-                                        pkeys.push(pkey);
-                                    }
-                                }
-                                Err(err) => {
-                                    let msg = format!("cannot find pgp public key for {recipient}");
-                                    debug!("{msg} using wkd: {err}");
-                                    debug!("{err:?}");
+                    for (recipient, res) in wkd_pkeys {
+                        match res {
+                            Ok(pkey) => {
+                                if recipients.remove(&recipient) {
+                                    debug!("found pgp public key for {recipient} using wkd");
+                                    candidates.entry(recipient).or_default().push(pkey);
                                 }
                             }
-                            pkeys
-                        },
-                    ));
+                            Err(err) => {
+                                let msg = format!("cannot find pgp public key for {recipient}");
+                                debug!("{msg} using wkd: {err}");
+                                debug!("{err:?}");
+                            }
+                        }
+                    }
                 }
                 NativePgpPublicKeysResolver::KeyServers(key_servers) => {
                     let recipients_clone = recipients.clone().into_iter().collect();
                     let http_pkeys =
                         pgp::http::get_all(recipients_clone, key_servers.to_owned()).await;
 
-                    pkeys.extend(http_pkeys.into_iter().fold(
-                        Vec::default(),
-                        |mut pkeys, (ref recipient, res)| {
-                            match res {
-                                Ok(pkey) => {
-                                    if recipients.remove(recipient) {
-                                        let msg = format!("found pgp public key for {recipient}");
-                                        debug!("{msg} using key servers");
-                                        pkeys.push(pkey);
-                                    }
-                                }
-                                Err(err) => {
-                                    let msg = format!("cannot find pgp public key for {recipient}");
-                                    debug!("{msg} using key servers: {err}");
-                                    debug!("{err:?}");
+                    for (recipient, res) in http_pkeys {
+                        match res {
+                            Ok(pkey) => {
+                                if recipients.remove(&recipient) {
+                                    let msg = format!("found pgp public key for {recipient}");
+                                    debug!("{msg} using key servers");
+                                    candidates.entry(recipient).or_default().push(pkey);
                                 }
                             }
-                            pkeys
-                        },
-                    ));
+                            Err(err) => {
+                                let msg = format!("cannot find pgp public key for {recipient}");
+                                debug!("{msg} using key servers: {err}");
+                                debug!("{err:?}");
+                            }
+                        }
+                    }
+                }
+                #[cfg(feature = "autocrypt")]
+                NativePgpPublicKeysResolver::Autocrypt(path) => {
+                    if recipients.is_empty() {
+                        continue;
+                    }
+
+                    let peers = match AutocryptPeers::open(shellexpand_path(path)) {
+                        Ok(peers) => peers,
+                        Err(err) => {
+                            debug!(?err, "cannot open autocrypt peer state database");
+                            continue;
+                        }
+                    };
+
+                    for recipient in recipients.clone() {
+                        match peers.get_public_key(&recipient) {
+                            Ok(Some(pkey)) => {
+                                debug!("found pgp public key for {recipient} using autocrypt");
+                                recipients.remove(&recipient);
+                                candidates.entry(recipient).or_default().push(pkey);
+                            }
+                            Ok(None) => {
+                                debug!(
+                                    "cannot find pgp public key for {recipient} using autocrypt"
+                                );
+                            }
+                            Err(err) => {
+                                let msg = format!("cannot find pgp public key for {recipient}");
+                                debug!(?err, "{msg} using autocrypt");
+                            }
+                        }
+                    }
                 }
             }
+        }
+
+        let own_recipient = match self.secret_key {
+            NativePgpSecretKey::None => None,
+            _ => recipients.iter().next().cloned(),
+        };
 
-            if recipients.is_empty() {
-                break;
+        if let Some(own_recipient) = own_recipient {
+            match self.secret_key.get(own_recipient).await {
+                Ok(skey) => {
+                    let pkey = pgp::to_signed_public_key(&skey);
+                    let emails = user_emails(&pkey);
+                    recipients.retain(|recipient| {
+                        if emails
+                            .iter()
+                            .any(|email| email.eq_ignore_ascii_case(recipient))
+                        {
+                            debug!("found pgp public key for {recipient} using secret key");
+                            let pkeys = candidates.entry(recipient.clone()).or_default();
+                            pkeys.push(pkey.clone());
+                            false
+                        } else {
+                            true
+                        }
+                    });
+                }
+                Err(err) => {
+                    debug!(?err, "cannot get pgp secret key");
+                }
             }
         }
 
-        let data = pgp::encrypt(pkeys, data)
-            .await
-            .map_err(Error::EncryptNativePgpError)?;
+        let mut pkeys = Vec::new();
 
-        Ok(data)
+        for (recipient, candidates) in candidates {
+            let selected = self
+                .key_selection
+                .select_keys(&recipient, candidates, key_candidate)?;
+            pkeys.extend(selected);
+        }
+
+        Ok(pkeys)
+    }
+
+    /// Gets the secret key of the given email address, together
+    /// with its passphrase.
+    ///
+    /// They are taken from the passphrase cache when available.
+    /// Otherwise the passphrase is taken from the configuration, or
+    /// prompted when the configuration does not provide any. The
+    /// returned boolean tells if they come from the cache.
+    async fn unlock_secret_key(&self, email: &str) -> Result<(SignedSecretKey, String, bool)> {
+        if let Some((skey, passphrase)) = self.passphrase_cache.get(email) {
+            return Ok((skey, passphrase, true));
+        }
+
+        let skey = self.secret_key.get(email).await?;
+
+        let passphrase = match &self.secret_key_passphrase_prompt {
+            Some(prompt) if self.secret_key_passphrase.is_empty() => prompt
+                .prompt(email)
+                .map_err(Error::PromptPgpSecretKeyPassphraseError)?,
+            _ => self
+                .secret_key_passphrase
+                .get()
+                .await
+                .map_err(Error::GetSecretKeyPassphraseFromKeyringError)?,
+        };
+
+        Ok((skey, passphrase, false))
+    }
+
+    /// Caches the given secret key and passphrase of the given email
+    /// address, unless the cache is disabled.
+    fn cache_secret_key(&self, email: &str, skey: SignedSecretKey, passphrase: String) {
+        let ttl = self
+            .passphrase_cache_ttl
+            .unwrap_or_else(Self::default_passphrase_cache_ttl);
+
+        if ttl > 0 {
+            let ttl = Duration::from_secs(ttl);
+            self.passphrase_cache.insert(email, skey, passphrase, ttl);
+        }
     }
 
     /// Decrypts the given encrypted bytes using the given recipient.
     pub async fn decrypt(&self, email: impl ToString, data: Vec<u8>) -> Result<Vec<u8>> {
-        let skey = self.secret_key.get(email).await?;
-        let passphrase = self
-            .secret_key_passphrase
-            .get()
-            .await
-            .map_err(Error::GetSecretKeyPassphraseFromKeyringError)?;
-        let data = pgp::decrypt(skey, passphrase, data)
+        let email = email.to_string();
+        let (skey, passphrase, cached) = self.unlock_secret_key(&email).await?;
+        let data = pgp::decrypt(skey.clone(), &passphrase, data)
             .await
             .map_err(Error::DecryptNativePgpError)?;
+
+        if !cached {
+            self.cache_secret_key(&email, skey, passphrase);
+        }
+
         Ok(data)
     }
 
     /// Signs the given plain bytes using the given recipient.
     pub async fn sign(&self, email: impl ToString, data: Vec<u8>) -> Result<Vec<u8>> {
-        let skey = self.secret_key.get(email).await?;
-        let passphrase = self
-            .secret_key_passphrase
-            .get()
-            .await
-            .map_err(Error::GetSecretKeyPassphraseFromKeyringError)?;
-        let data = pgp::sign(skey, passphrase, data)
+        let email = email.to_string();
+        let (skey, passphrase, cached) = self.unlock_secret_key(&email).await?;
+        let data = pgp::sign(skey.clone(), &passphrase, data)
             .await
             .map_err(Error::SignNativePgpError)?;
+
+        if !cached {
+            self.cache_secret_key(&email, skey, passphrase);
+        }
+
         Ok(data)
     }
 
     /// Verifies the given signed bytes as well as the signature bytes
     /// using the given recipient.
     pub async fn verify(&self, email: impl AsRef<str>, sig: Vec<u8>, data: Vec<u8>) -> Result<()> {
+        let pkey = self.find_public_key(email.as_ref()).await?;
+        let sig = pgp::read_sig_from_bytes(sig)
+            .await
+            .map_err(Error::ReadNativePgpSignatureError)?;
+        pgp::verify(pkey, sig, data)
+            .await
+            .map_err(Error::VerifyNativePgpSignatureError)?;
+
+        Ok(())
+    }
+
+    /// Verifies the given signed bytes as well as the signature bytes
+    /// using the given signer, and reports details about the
+    /// signature.
+    pub async fn verify_with_report(
+        &self,
+        email: impl AsRef<str>,
+        sig: Vec<u8>,
+        data: Vec<u8>,
+    ) -> Result<PgpVerificationReport> {
         let email = email.as_ref();
+        let pkey = self.find_public_key(email).await?;
+        let sig = pgp::read_sig_from_bytes(sig)
+            .await
+            .map_err(Error::ReadNativePgpSignatureError)?;
+        let report = pgp::verify_with_report(pkey, sig, data)
+            .await
+            .map_err(Error::VerifyNativePgpSignatureError)?;
+
+        Ok(PgpVerificationReport::from_native(email, report))
+    }
+
+    /// Finds the public key of the given email address using the
+    /// public key resolvers, in order.
+    async fn find_public_key(&self, email: &str) -> Result<SignedPublicKey> {
         let mut pkey_found = None;
 
         for resolver in &self.public_keys_resolvers {
@@ -264,17 +480,93 @@ pub async fn verify(&self, email: impl AsRef<str>, sig: Vec<u8>, data: Vec<u8>)
                         }
                     }
                 }
+                #[cfg(feature = "autocrypt")]
+                NativePgpPublicKeysResolver::Autocrypt(path) => {
+                    let pkey = AutocryptPeers::open(shellexpand_path(path))
+                        .and_then(|peers| peers.get_public_key(email));
+                    match pkey {
+                        Ok(Some(pkey)) => {
+                            debug!("found pgp public key for {email} using autocrypt");
+                            pkey_found = Some(pkey);
+                            break;
+                        }
+                        Ok(None) => {
+                            debug!("cannot find pgp public key for {email} using autocrypt");
+                            continue;
+                        }
+                        Err(err) => {
+                            let msg = format!("cannot find pgp public key for {email}");
+                            debug!(?err, "{msg} using autocrypt");
+                            continue;
+                        }
+                    }
+                }
             }
         }
 
-        let pkey = pkey_found.ok_or(Error::FindPgpPublicKeyError(email.to_owned()))?;
-        let sig = pgp::read_sig_from_bytes(sig)
-            .await
-            .map_err(Error::ReadNativePgpSignatureError)?;
-        pgp::verify(pkey, sig, data)
-            .await
-            .map_err(Error::VerifyNativePgpSignatureError)?;
+        pkey_found.ok_or(Error::FindPgpPublicKeyError(email.to_owned()))
+    }
 
-        Ok(())
+    /// Builds the Autocrypt header of the given sender, using the
+    /// public key derived from the secret key.
+    #[cfg(feature = "autocrypt")]
+    pub async fn autocrypt_header(
+        &self,
+        email: impl ToString,
+        prefer_encrypt: PreferEncrypt,
+    ) -> Result<AutocryptHeader> {
+        use pgp::native::types::SecretKeyTrait;
+
+        let email = email.to_string();
+        let (skey, passphrase, _) = self.unlock_secret_key(&email).await?;
+        let pkey = skey
+            .public_key()
+            .sign(&skey, || passphrase)
+            .map_err(Error::SignAutocryptKeyError)?;
+        let header = AutocryptHeader::new(email, &pkey)?.with_prefer_encrypt(prefer_encrypt);
+        Ok(header)
+    }
+}
+
+/// Returns the email addresses of the user ids of the given public
+/// key.
+///
+/// User ids are usually made of a name and an email address between
+/// angle brackets, for example `Alice <alice@localhost>`.
+pub(crate) fn user_emails(pkey: &SignedPublicKey) -> Vec<String> {
+    pkey.details
+        .users
+        .iter()
+        .map(|user| {
+            let id = user.id.id().to_string();
+            let email = match (id.rfind('<'), id.rfind('>')) {
+                (Some(start), Some(end)) if start < end => &id[start + 1..end],
+                _ => id.trim(),
+            };
+            email.to_owned()
+        })
+        .collect()
+}
+
+/// Describes the given public key as a key selection candidate.
+fn key_candidate(pkey: &SignedPublicKey) -> PgpKeyCandidate {
+    use pgp::native::types::KeyTrait;
+
+    let fingerprint: String = pkey
+        .fingerprint()
+        .iter()
+        .map(|b| format!("{b:02X}"))
+        .collect();
+
+    PgpKeyCandidate {
+        user_ids: pkey
+            .details
+            .users
+            .iter()
+            .map(|user| user.id.id().to_string())
+            .collect(),
+        created_at: Some(SystemTime::from(*pkey.primary_key.created_at())),
+        validity: pgp::key_status(pkey).into(),
+        ..PgpKeyCandidate::new(fingerprint)
     }
 }
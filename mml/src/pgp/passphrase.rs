@@ -0,0 +1,133 @@
+//! # PGP passphrase module
+//!
+//! This module contains the pinentry-style [`PassphrasePrompt`] used
+//! to ask users for secret key passphrases, as well as the
+//! [`PassphraseCache`] used by the native backend to keep unlocked
+//! secret keys in memory for a while, so that users are not prompted
+//! (and the keyring is not hit) for every single message.
+
+use std::{
+    collections::HashMap,
+    fmt, io,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use pgp::native::SignedSecretKey;
+use tracing::debug;
+
+/// The callback used to prompt the user for the passphrase of a
+/// secret key.
+///
+/// The callback receives the email address the secret key belongs
+/// to.
+#[derive(Clone)]
+pub struct PassphrasePrompt(Arc<dyn Fn(&str) -> io::Result<String> + Send + Sync>);
+
+impl PassphrasePrompt {
+    /// Creates a new prompt from the given callback.
+    pub fn new(prompt: impl Fn(&str) -> io::Result<String> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(prompt))
+    }
+
+    /// Prompts the user for the passphrase of the secret key of the
+    /// given email address.
+    pub fn prompt(&self, email: &str) -> io::Result<String> {
+        (self.0)(email)
+    }
+}
+
+impl PartialEq for PassphrasePrompt {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for PassphrasePrompt {}
+
+impl fmt::Debug for PassphrasePrompt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PassphrasePrompt()")
+    }
+}
+
+/// A secret key cached together with its passphrase.
+struct CachedSecretKey {
+    skey: SignedSecretKey,
+    passphrase: String,
+    expires_at: Instant,
+}
+
+/// The in-memory cache of secret keys and their passphrases, indexed
+/// by email address.
+///
+/// Clones share the same cache, so that the cache survives clones of
+/// the backend owning it.
+#[derive(Clone, Default)]
+pub struct PassphraseCache(Arc<Mutex<HashMap<String, CachedSecretKey>>>);
+
+impl PassphraseCache {
+    /// Returns the secret key and the passphrase cached for the given
+    /// email address, unless they expired.
+    pub(crate) fn get(&self, email: &str) -> Option<(SignedSecretKey, String)> {
+        let mut cache = self.0.lock().unwrap();
+
+        match cache.get(email) {
+            Some(entry) if entry.expires_at > Instant::now() => {
+                debug!("using cached pgp secret key passphrase for {email}");
+                Some((entry.skey.clone(), entry.passphrase.clone()))
+            }
+            Some(_) => {
+                debug!("cached pgp secret key passphrase for {email} expired");
+                cache.remove(email);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Caches the given secret key and passphrase for the given email
+    /// address, during the given time to live.
+    pub(crate) fn insert(
+        &self,
+        email: impl ToString,
+        skey: SignedSecretKey,
+        passphrase: impl ToString,
+        ttl: Duration,
+    ) {
+        let entry = CachedSecretKey {
+            skey,
+            passphrase: passphrase.to_string(),
+            expires_at: Instant::now() + ttl,
+        };
+
+        self.0.lock().unwrap().insert(email.to_string(), entry);
+    }
+
+    /// Removes the secret key and the passphrase cached for the given
+    /// email address.
+    pub fn remove(&self, email: &str) {
+        self.0.lock().unwrap().remove(email);
+    }
+
+    /// Removes all cached secret keys and passphrases.
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+// The cache is a runtime state rather than a configuration, so it
+// does not take part in the equality of backends.
+impl PartialEq for PassphraseCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for PassphraseCache {}
+
+impl fmt::Debug for PassphraseCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PassphraseCache()")
+    }
+}
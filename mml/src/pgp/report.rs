@@ -0,0 +1,142 @@
+//! # PGP verification report module
+//!
+//! This module contains the detailed results of PGP signature
+//! verifications, for single signatures ([`PgpVerificationReport`])
+//! as well as for whole messages ([`PartVerificationReport`]).
+
+use std::time::SystemTime;
+
+use crate::Error;
+
+/// The validity of a signer public key.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum PgpKeyValidity {
+    /// The key can be used.
+    Valid,
+
+    /// The key expired.
+    Expired,
+
+    /// The key has been revoked.
+    Revoked,
+
+    /// The validity of the key could not be determined, for example
+    /// because the key could not be found or because the backend
+    /// does not expose it.
+    #[default]
+    Unknown,
+}
+
+#[cfg(feature = "pgp-native")]
+impl From<pgp::KeyStatus> for PgpKeyValidity {
+    fn from(status: pgp::KeyStatus) -> Self {
+        match status {
+            pgp::KeyStatus::Valid => Self::Valid,
+            pgp::KeyStatus::Expired(_) => Self::Expired,
+            pgp::KeyStatus::Revoked(_) => Self::Revoked,
+        }
+    }
+}
+
+/// The detailed result of a PGP signature verification.
+///
+/// Details depend on the backend: the commands backend for example
+/// only reports whether the signature is valid.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PgpVerificationReport {
+    /// The email address of the expected signer.
+    pub signer: String,
+
+    /// The reason why the signature could not be verified, if any.
+    ///
+    /// When `None`, the signature is valid.
+    pub error: Option<String>,
+
+    /// The id of the key that issued the signature, as uppercase
+    /// hexadecimal.
+    pub key_id: Option<String>,
+
+    /// The fingerprint of the key that issued the signature, as
+    /// uppercase hexadecimal.
+    pub fingerprint: Option<String>,
+
+    /// The creation time of the signature.
+    pub created_at: Option<SystemTime>,
+
+    /// The validity of the signer public key.
+    pub key_validity: PgpKeyValidity,
+}
+
+impl PgpVerificationReport {
+    /// Creates a new report for the given signer.
+    pub fn new(signer: impl ToString) -> Self {
+        Self {
+            signer: signer.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a new report for the given signer whose verification
+    /// failed with the given error.
+    pub fn from_error(signer: impl ToString, err: &Error) -> Self {
+        Self {
+            error: Some(err.to_string()),
+            ..Self::new(signer)
+        }
+    }
+
+    /// Returns `true` if the signature is valid.
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[cfg(feature = "pgp-native")]
+impl PgpVerificationReport {
+    /// Creates a new report for the given signer from the given
+    /// native report.
+    pub(crate) fn from_native(signer: impl ToString, report: pgp::VerificationReport) -> Self {
+        Self {
+            signer: signer.to_string(),
+            error: report.error,
+            key_id: Some(report.key_id),
+            fingerprint: Some(report.fingerprint),
+            created_at: report.created_at.map(SystemTime::from),
+            key_validity: report.key_status.into(),
+        }
+    }
+}
+
+/// The verification report of a message part.
+///
+/// Only parts involved in PGP are reported: `multipart/signed` and
+/// `multipart/encrypted` parts, as well as PGP inline signed blocks
+/// of `text/plain` parts.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PartVerificationReport {
+    /// The MIME type of the part.
+    pub ctype: String,
+
+    /// The report of the part signature, if the part is signed.
+    pub signature: Option<PgpVerificationReport>,
+
+    /// The reports of the nested parts.
+    ///
+    /// For signed parts, it contains the reports of the signed
+    /// content. For encrypted parts, it contains the reports of the
+    /// decrypted content.
+    pub parts: Vec<PartVerificationReport>,
+}
+
+impl PartVerificationReport {
+    /// Returns `true` if the part and all its nested parts have
+    /// valid signatures.
+    pub fn is_valid(&self) -> bool {
+        let valid = match &self.signature {
+            Some(report) => report.is_valid(),
+            None => true,
+        };
+
+        valid && self.parts.iter().all(Self::is_valid)
+    }
+}
@@ -0,0 +1,359 @@
+//! # PGP key selection module
+//!
+//! This module contains the logic used to select public keys for
+//! encryption when several keys match the same recipient: the global
+//! [`KeySelectionPolicy`] and the per-recipient key pins, both
+//! grouped in [`KeySelection`].
+
+use std::{collections::HashMap, fmt, sync::Arc, time::SystemTime};
+
+use super::PgpKeyValidity;
+use crate::{Error, Result};
+
+/// A public key matching a recipient.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PgpKeyCandidate {
+    /// The fingerprint of the key, as uppercase hexadecimal.
+    pub fingerprint: String,
+
+    /// The user ids of the key.
+    pub user_ids: Vec<String>,
+
+    /// The creation time of the key.
+    pub created_at: Option<SystemTime>,
+
+    /// The validity of the key.
+    pub validity: PgpKeyValidity,
+}
+
+impl PgpKeyCandidate {
+    /// Creates a new candidate for the given fingerprint.
+    pub fn new(fingerprint: impl ToString) -> Self {
+        Self {
+            fingerprint: fingerprint.to_string().to_uppercase(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns `true` if the key can be used for encryption, which
+    /// means it is neither expired nor revoked.
+    pub fn is_usable(&self) -> bool {
+        !matches!(
+            self.validity,
+            PgpKeyValidity::Expired | PgpKeyValidity::Revoked
+        )
+    }
+
+    /// Returns `true` if the key matches the given pin.
+    ///
+    /// A pin is either a fingerprint or a key id (the last 16 or 8
+    /// hexadecimal characters of a fingerprint). Spaces and `0x`
+    /// prefixes are ignored, as well as the case.
+    pub fn matches_pin(&self, pin: impl AsRef<str>) -> bool {
+        let pin = pin.as_ref().trim();
+        let pin = pin
+            .strip_prefix("0x")
+            .or_else(|| pin.strip_prefix("0X"))
+            .unwrap_or(pin);
+        let pin = pin
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_uppercase();
+
+        !pin.is_empty() && self.fingerprint.ends_with(&pin)
+    }
+}
+
+/// The callback used to interactively select keys.
+///
+/// The callback receives the email address of the recipient as well
+/// as the candidates matching it, and returns the indexes of the
+/// selected candidates.
+#[derive(Clone)]
+pub struct KeySelector(
+    #[allow(clippy::type_complexity)]
+    Arc<dyn Fn(&str, &[PgpKeyCandidate]) -> Vec<usize> + Send + Sync>,
+);
+
+impl KeySelector {
+    /// Creates a new selector from the given callback.
+    pub fn new(
+        select: impl Fn(&str, &[PgpKeyCandidate]) -> Vec<usize> + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(select))
+    }
+
+    /// Calls the selector for the given recipient and candidates.
+    pub fn select(&self, email: &str, candidates: &[PgpKeyCandidate]) -> Vec<usize> {
+        (self.0)(email, candidates)
+    }
+}
+
+impl PartialEq for KeySelector {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for KeySelector {}
+
+impl fmt::Debug for KeySelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "KeySelector()")
+    }
+}
+
+/// The policy used to select keys when several keys match the same
+/// recipient.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum KeySelectionPolicy {
+    /// Select the most recently created usable key.
+    #[default]
+    Newest,
+
+    /// Select all usable keys.
+    AllValid,
+
+    /// Let the given callback select keys.
+    ///
+    /// The callback receives all the candidates, including expired
+    /// and revoked ones, so that interfaces can show them. It is only
+    /// called when there is more than one candidate, or when the
+    /// only candidate is not usable.
+    #[cfg_attr(feature = "derive", serde(skip))]
+    Interactive(KeySelector),
+}
+
+/// The key selection configuration.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct KeySelection {
+    /// The policy used for recipients without pinned keys.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub policy: KeySelectionPolicy,
+
+    /// The keys pinned by recipient email address.
+    ///
+    /// Keys are identified by fingerprint or key id. When a recipient
+    /// has pinned keys, only usable keys matching them are selected,
+    /// whatever the policy.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub pins: HashMap<String, Vec<String>>,
+}
+
+impl KeySelection {
+    /// Creates a new key selection using the given policy.
+    pub fn new(policy: KeySelectionPolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    /// Pins the given keys for the given recipient.
+    pub fn pin(&mut self, email: impl ToString, keys: impl IntoIterator<Item = impl ToString>) {
+        self.pins
+            .entry(email.to_string())
+            .or_default()
+            .extend(keys.into_iter().map(|key| key.to_string()));
+    }
+
+    /// Pins the given keys for the given recipient, using the
+    /// builder pattern.
+    pub fn with_pin(
+        mut self,
+        email: impl ToString,
+        keys: impl IntoIterator<Item = impl ToString>,
+    ) -> Self {
+        self.pin(email, keys);
+        self
+    }
+
+    /// Returns the keys pinned for the given recipient, if any.
+    ///
+    /// Email addresses are compared case-insensitively.
+    pub fn pinned(&self, email: &str) -> Option<&[String]> {
+        self.pins
+            .iter()
+            .find(|(pinned_email, _)| pinned_email.eq_ignore_ascii_case(email))
+            .map(|(_, keys)| keys.as_slice())
+            .filter(|keys| !keys.is_empty())
+    }
+
+    /// Selects keys among the given candidates of the given
+    /// recipient, and returns their indexes.
+    ///
+    /// Fails if no key could be selected.
+    pub fn select(&self, email: &str, candidates: &[PgpKeyCandidate]) -> Result<Vec<usize>> {
+        if candidates.is_empty() {
+            return Err(Error::SelectPgpPublicKeyError(email.to_owned()));
+        }
+
+        let usable = || {
+            candidates
+                .iter()
+                .enumerate()
+                .filter(|(_, candidate)| candidate.is_usable())
+        };
+
+        let mut indexes: Vec<usize> = if let Some(pins) = self.pinned(email) {
+            usable()
+                .filter(|(_, candidate)| pins.iter().any(|pin| candidate.matches_pin(pin)))
+                .map(|(i, _)| i)
+                .collect()
+        } else {
+            match &self.policy {
+                KeySelectionPolicy::Newest => usable()
+                    .max_by_key(|(_, candidate)| candidate.created_at)
+                    .map(|(i, _)| i)
+                    .into_iter()
+                    .collect(),
+                KeySelectionPolicy::AllValid => usable().map(|(i, _)| i).collect(),
+                KeySelectionPolicy::Interactive(_)
+                    if candidates.len() == 1 && candidates[0].is_usable() =>
+                {
+                    vec![0]
+                }
+                KeySelectionPolicy::Interactive(selector) => selector
+                    .select(email, candidates)
+                    .into_iter()
+                    .filter(|i| *i < candidates.len())
+                    .collect(),
+            }
+        };
+
+        indexes.sort_unstable();
+        indexes.dedup();
+
+        if indexes.is_empty() {
+            return Err(Error::SelectPgpPublicKeyError(email.to_owned()));
+        }
+
+        Ok(indexes)
+    }
+
+    /// Selects keys among the given keys of the given recipient.
+    ///
+    /// The given function is used to describe keys as candidates.
+    pub fn select_keys<K>(
+        &self,
+        email: &str,
+        keys: Vec<K>,
+        to_candidate: impl Fn(&K) -> PgpKeyCandidate,
+    ) -> Result<Vec<K>> {
+        let candidates: Vec<_> = keys.iter().map(to_candidate).collect();
+        let indexes = self.select(email, &candidates)?;
+
+        let keys = keys
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| indexes.contains(i))
+            .map(|(_, key)| key)
+            .collect();
+
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{KeySelection, KeySelectionPolicy, KeySelector, PgpKeyCandidate};
+    use crate::pgp::PgpKeyValidity;
+
+    fn candidates() -> Vec<PgpKeyCandidate> {
+        let key = |fpr: &str, secs: u64, validity: PgpKeyValidity| PgpKeyCandidate {
+            created_at: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
+            validity,
+            ..PgpKeyCandidate::new(fpr)
+        };
+
+        vec![
+            key(
+                "AAAA0000AAAA0000AAAA0000AAAA0000AAAA0001",
+                1,
+                PgpKeyValidity::Valid,
+            ),
+            key(
+                "AAAA0000AAAA0000AAAA0000AAAA0000AAAA0002",
+                3,
+                PgpKeyValidity::Revoked,
+            ),
+            key(
+                "aaaa0000aaaa0000aaaa0000aaaa0000aaaa0003",
+                2,
+                PgpKeyValidity::Unknown,
+            ),
+        ]
+    }
+
+    #[test]
+    fn newest() {
+        let selection = KeySelection::default();
+        let indexes = selection.select("alice@localhost", &candidates()).unwrap();
+        assert_eq!(indexes, vec![2]);
+    }
+
+    #[test]
+    fn all_valid() {
+        let selection = KeySelection::new(KeySelectionPolicy::AllValid);
+        let indexes = selection.select("alice@localhost", &candidates()).unwrap();
+        assert_eq!(indexes, vec![0, 2]);
+
+        let keys = selection
+            .select_keys("alice@localhost", vec!["a", "b", "c"], |key| {
+                candidates()[(key.as_bytes()[0] - b'a') as usize].clone()
+            })
+            .unwrap();
+        assert_eq!(keys, vec!["a", "c"]);
+    }
+
+    #[test]
+    fn interactive() {
+        let selector = KeySelector::new(|email, candidates| {
+            assert_eq!(email, "alice@localhost");
+            assert_eq!(candidates.len(), 3);
+            vec![1, 1, 7]
+        });
+        let selection = KeySelection::new(KeySelectionPolicy::Interactive(selector));
+        let indexes = selection.select("alice@localhost", &candidates()).unwrap();
+        assert_eq!(indexes, vec![1]);
+
+        let selector = KeySelector::new(|_, _| unreachable!());
+        let selection = KeySelection::new(KeySelectionPolicy::Interactive(selector));
+        let indexes = selection
+            .select("alice@localhost", &candidates()[..1])
+            .unwrap();
+        assert_eq!(indexes, vec![0]);
+    }
+
+    #[test]
+    fn pins() {
+        let selection = KeySelection::new(KeySelectionPolicy::Newest)
+            .with_pin("Alice@Localhost", ["0xaaaa0000 aaaa0001"])
+            .with_pin("bob@localhost", ["AAAA0002"]);
+
+        let indexes = selection.select("alice@localhost", &candidates()).unwrap();
+        assert_eq!(indexes, vec![0]);
+
+        // pinned keys need to be usable
+        assert!(selection.select("bob@localhost", &candidates()).is_err());
+
+        // recipients without pins use the policy
+        let indexes = selection.select("carl@localhost", &candidates()).unwrap();
+        assert_eq!(indexes, vec![2]);
+
+        assert!(selection.select("carl@localhost", &[]).is_err());
+    }
+}
@@ -16,6 +16,7 @@
 async fn pgp_gpg() {
     let pgp = Pgp::Gpg(PgpGpg {
         home_dir: Some(PathBuf::from("./tests/gpg-home")),
+        key_selection: Default::default(),
     });
 
     let mml = concat_line!(
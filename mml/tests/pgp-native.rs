@@ -1,12 +1,21 @@
 #![cfg(feature = "pgp-native")]
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 #[cfg(feature = "async-std")]
 use async_std::test;
 use concat_with::concat_line;
 use mml::{
-    pgp::{NativePgpPublicKeysResolver, NativePgpSecretKey, Pgp, PgpNative},
+    pgp::{
+        NativePgpPublicKeysResolver, NativePgpSecretKey, PartVerificationReport, PassphrasePrompt,
+        Pgp, PgpKeyValidity, PgpNative,
+    },
     MimeInterpreterBuilder, MmlCompilerBuilder,
 };
 use pgp::gen_key_pair;
@@ -101,12 +110,13 @@ async fn spawn_fake_key_server(pkeys: HashMap<String, String>) -> String {
             public_keys_resolvers: vec![NativePgpPublicKeysResolver::KeyServers(vec![
                 key_server_addr,
             ])],
+            ..Default::default()
         }))
         .build(mml)
         .unwrap();
     let msg_builder = mml_compiler.compile().await.unwrap().into_msg_builder();
 
-    let mml = MimeInterpreterBuilder::new()
+    let interpreter = MimeInterpreterBuilder::new()
         .with_show_only_headers(["From", "To", "Subject"])
         .with_pgp(Pgp::Native(PgpNative {
             secret_key: NativePgpSecretKey::Raw(bob_skey.clone()),
@@ -115,12 +125,37 @@ async fn spawn_fake_key_server(pkeys: HashMap<String, String>) -> String {
                 "alice@localhost".into(),
                 alice_pkey.clone(),
             )],
+            ..Default::default()
         }))
-        .build()
-        .from_msg_builder(msg_builder)
+        .build();
+
+    let reports = interpreter
+        .clone()
+        .verify_msg_builder(msg_builder.clone())
         .await
         .unwrap();
 
+    fn signatures(reports: &[PartVerificationReport]) -> usize {
+        reports
+            .iter()
+            .map(|report| report.signature.iter().count() + signatures(&report.parts))
+            .sum()
+    }
+
+    assert!(reports.iter().all(PartVerificationReport::is_valid));
+    assert_eq!(signatures(&reports), 1);
+
+    let signature = reports
+        .iter()
+        .flat_map(|report| [report].into_iter().chain(&report.parts))
+        .find_map(|report| report.signature.as_ref())
+        .unwrap();
+    assert_eq!(signature.signer, "alice@localhost");
+    assert_eq!(signature.key_validity, PgpKeyValidity::Valid);
+    assert!(signature.fingerprint.is_some());
+
+    let mml = interpreter.from_msg_builder(msg_builder).await.unwrap();
+
     let expected_mml = concat_line!(
         "From: alice@localhost",
         "To: bob@localhost",
@@ -132,3 +167,64 @@ async fn spawn_fake_key_server(pkeys: HashMap<String, String>) -> String {
 
     assert_eq!(mml, expected_mml);
 }
+
+#[test_log::test(test)]
+async fn pgp_native_passphrase_cache() {
+    let (bob_skey, bob_pkey) = gen_key_pair("bob@localhost", "passphrase").await.unwrap();
+
+    let prompts = Arc::new(AtomicUsize::new(0));
+    let native = PgpNative {
+        secret_key: NativePgpSecretKey::Raw(bob_skey),
+        secret_key_passphrase_prompt: Some(PassphrasePrompt::new({
+            let prompts = prompts.clone();
+            move |email| {
+                assert_eq!(email, "bob@localhost");
+                prompts.fetch_add(1, Ordering::SeqCst);
+                Ok(String::from("passphrase"))
+            }
+        })),
+        public_keys_resolvers: vec![NativePgpPublicKeysResolver::Raw(
+            "bob@localhost".into(),
+            bob_pkey,
+        )],
+        ..Default::default()
+    };
+
+    let recipients = || [String::from("bob@localhost")];
+    let encrypted = native
+        .encrypt(recipients(), b"message".to_vec())
+        .await
+        .unwrap();
+
+    // the passphrase is prompted once, then taken from the cache,
+    // including from clones of the backend
+    for native in [native.clone(), native.clone(), native.clone()] {
+        let plain = native
+            .decrypt("bob@localhost", encrypted.clone())
+            .await
+            .unwrap();
+        assert_eq!(plain, b"message");
+    }
+    assert_eq!(prompts.load(Ordering::SeqCst), 1);
+
+    native.passphrase_cache.clear();
+    native
+        .decrypt("bob@localhost", encrypted.clone())
+        .await
+        .unwrap();
+    assert_eq!(prompts.load(Ordering::SeqCst), 2);
+
+    // a null time to live disables the cache
+    let native = PgpNative {
+        passphrase_cache_ttl: Some(0),
+        passphrase_cache: Default::default(),
+        ..native
+    };
+    for _ in 0..2 {
+        native
+            .decrypt("bob@localhost", encrypted.clone())
+            .await
+            .unwrap();
+    }
+    assert_eq!(prompts.load(Ordering::SeqCst), 4);
+}
@@ -1,22 +1,31 @@
 //! Authorization Grant Code flow helper, as defined in the
 //! [RFC6749](https://datatracker.ietf.org/doc/html/rfc6749#section-1.3.1)
 
+use std::{
+    borrow::Cow,
+    future::{self, Future},
+    pin::pin,
+    task::Poll,
+    time::Duration,
+};
+
 #[cfg(feature = "async-std")]
 use async_std::{
-    io::{BufReadExt, BufReader, WriteExt},
+    io::{BufReadExt, BufReader, Write as AsyncWrite, WriteExt},
     net::TcpListener,
 };
 use oauth2::{
-    url::Url, AuthorizationCode, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, RequestTokenError,
-    Scope, TokenResponse,
+    url::Url, AuthorizationCode, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl,
+    RequestTokenError, Scope,
 };
 #[cfg(feature = "tokio")]
 use tokio::{
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader},
     net::TcpListener,
 };
+use tracing::debug;
 
-use super::{Client, Error, Result};
+use super::{Client, Error, Result, Tokens};
 
 /// OAuth 2.0 Authorization Code Grant flow builder.
 ///
@@ -30,10 +39,44 @@
 /// to click on the redirect URL in order to extract the access token
 /// and the refresh token by calling
 /// [`AuthorizationCodeGrant::wait_for_redirection`].
+///
+/// The redirect server can be customized: the address it binds to,
+/// the path it expects, the pages it answers with and the time it
+/// waits for the redirection.
 #[derive(Debug, Default)]
 pub struct AuthorizationCodeGrant {
     pub scopes: Vec<Scope>,
     pub pkce: Option<(PkceCodeChallenge, PkceCodeVerifier)>,
+
+    /// Host the redirect server binds to.
+    ///
+    /// Defaults to the redirect host of the client. Useful when the
+    /// redirect server runs behind a proxy or inside a container,
+    /// for example by binding to `0.0.0.0`.
+    pub redirect_bind_host: Option<String>,
+
+    /// Port the redirect server binds to.
+    ///
+    /// Defaults to the redirect port of the client.
+    pub redirect_bind_port: Option<u16>,
+
+    /// Path of the redirect URL.
+    ///
+    /// Defaults to `/`.
+    pub redirect_path: Option<String>,
+
+    /// HTML page sent back to the browser when the authentication
+    /// succeeds.
+    pub success_html: Option<String>,
+
+    /// HTML page sent back to the browser when the authentication
+    /// fails.
+    pub error_html: Option<String>,
+
+    /// Maximum duration to wait for the redirection.
+    ///
+    /// Waits forever by default.
+    pub timeout: Option<Duration>,
 }
 
 impl AuthorizationCodeGrant {
@@ -54,6 +97,41 @@ pub fn with_pkce(mut self) -> Self {
         self
     }
 
+    pub fn with_redirect_bind_host(mut self, host: impl ToString) -> Self {
+        self.redirect_bind_host = Some(host.to_string());
+        self
+    }
+
+    pub fn with_redirect_bind_port(mut self, port: u16) -> Self {
+        self.redirect_bind_port = Some(port);
+        self
+    }
+
+    pub fn with_redirect_path(mut self, path: impl ToString) -> Self {
+        let path = path.to_string();
+        self.redirect_path = Some(if path.starts_with('/') {
+            path
+        } else {
+            format!("/{path}")
+        });
+        self
+    }
+
+    pub fn with_success_html(mut self, html: impl ToString) -> Self {
+        self.success_html = Some(html.to_string());
+        self
+    }
+
+    pub fn with_error_html(mut self, html: impl ToString) -> Self {
+        self.error_html = Some(html.to_string());
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
     /// Generate the redirect URL used to complete the OAuth 2.0
     /// Authorization Code Grant flow.
     pub fn get_redirect_url(&self, client: &Client) -> (Url, CsrfToken) {
@@ -61,6 +139,10 @@ pub fn get_redirect_url(&self, client: &Client) -> (Url, CsrfToken) {
             .authorize_url(CsrfToken::new_random)
             .add_scopes(self.scopes.clone());
 
+        if let Some(redirect_url) = self.redirect_url(client) {
+            redirect = redirect.set_redirect_uri(Cow::Owned(redirect_url));
+        }
+
         if let Some((pkce_challenge, _)) = &self.pkce {
             redirect = redirect.set_pkce_challenge(pkce_challenge.clone());
         }
@@ -77,69 +159,53 @@ pub async fn wait_for_redirection(
         client: &Client,
         csrf_state: CsrfToken,
     ) -> Result<(String, Option<String>)> {
-        // listen for one single connection
-        let (mut stream, _) =
-            TcpListener::bind((client.redirect_host.as_str(), client.redirect_port))
-                .await
-                .map_err(|err| {
-                    Error::BindRedirectServerError(
-                        client.redirect_host.clone(),
-                        client.redirect_port,
-                        err,
-                    )
-                })?
-                .accept()
-                .await
-                .map_err(Error::AcceptRedirectServerError)?;
-
-        // extract the code from the url
-        let code = {
-            let mut reader = BufReader::new(&mut stream);
+        let tokens = self.wait_for_tokens(client, csrf_state).await?;
+        Ok(tokens.into())
+    }
 
-            let mut request_line = String::new();
-            reader.read_line(&mut request_line).await?;
+    /// Same as [`AuthorizationCodeGrant::wait_for_redirection`], but
+    /// returns all the tokens issued by the authorization server,
+    /// including the lifetime of the access token.
+    pub async fn wait_for_tokens(self, client: &Client, csrf_state: CsrfToken) -> Result<Tokens> {
+        self.wait_for_tokens_until(client, csrf_state, future::pending())
+            .await
+    }
 
-            let redirect_url = request_line
-                .split_whitespace()
-                .nth(1)
-                .ok_or_else(|| Error::MissingRedirectUrlError(request_line.clone()))?;
-            let redirect_url = format!("http://localhost{redirect_url}");
-            let redirect_url = Url::parse(&redirect_url)
-                .map_err(|err| Error::ParseRedirectUrlError(err, redirect_url.clone()))?;
+    /// Same as [`AuthorizationCodeGrant::wait_for_tokens`], but stops
+    /// waiting as soon as the given `cancel` future resolves.
+    pub async fn wait_for_tokens_until(
+        self,
+        client: &Client,
+        csrf_state: CsrfToken,
+        cancel: impl Future<Output = ()>,
+    ) -> Result<Tokens> {
+        let code = {
+            let wait = self.wait_for_code(client, &csrf_state);
+            let cancel = async {
+                cancel.await;
+                Err(Error::RedirectServerCancelledError)
+            };
 
-            let (_, state) = redirect_url
-                .query_pairs()
-                .find(|(key, _)| key == "state")
-                .ok_or_else(|| Error::FindStateInRedirectUrlError(redirect_url.clone()))?;
-            let state = CsrfToken::new(state.into_owned());
-
-            if state.secret() != csrf_state.secret() {
-                return Err(Error::InvalidStateError(
-                    state.secret().to_owned(),
-                    csrf_state.secret().to_owned(),
-                ));
+            match self.timeout {
+                Some(timeout) => {
+                    let timeout = async {
+                        sleep(timeout).await;
+                        Err(Error::RedirectServerTimeoutError(timeout))
+                    };
+                    race(wait, race(cancel, timeout)).await?
+                }
+                None => race(wait, cancel).await?,
             }
-
-            let (_, code) = redirect_url
-                .query_pairs()
-                .find(|(key, _)| key == "code")
-                .ok_or_else(|| Error::FindCodeInRedirectUrlError(redirect_url.clone()))?;
-
-            AuthorizationCode::new(code.into_owned())
         };
 
-        // write a basic http response in plain text
-        let res = "Authentication successful!";
-        let res = format!(
-            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
-            res.len(),
-            res
-        );
-        stream.write_all(res.as_bytes()).await?;
-
         // exchange the code for an access token and a refresh token
+        let redirect_url = self.redirect_url(client);
         let mut res = client.exchange_code(code);
 
+        if let Some(redirect_url) = &redirect_url {
+            res = res.set_redirect_uri(Cow::Borrowed(redirect_url));
+        }
+
         if let Some((_, pkce_verifier)) = self.pkce {
             res = res.set_pkce_verifier(pkce_verifier);
         }
@@ -154,9 +220,157 @@ pub async fn wait_for_redirection(
                 RequestTokenError::Other(err) => Error::ExchangeCodeError(err),
             })?;
 
-        let access_token = res.access_token().secret().to_owned();
-        let refresh_token = res.refresh_token().map(|t| t.secret().clone());
+        Ok(Tokens::from_response(&res))
+    }
 
-        Ok((access_token, refresh_token))
+    /// Returns the redirect URL of the client, including the custom
+    /// redirect path if any.
+    fn redirect_url(&self, client: &Client) -> Option<RedirectUrl> {
+        let path = self.redirect_path.as_ref()?;
+        let mut url = client.redirect_uri()?.url().clone();
+        url.set_path(path);
+        Some(RedirectUrl::from_url(url))
     }
+
+    /// Spawns the redirect server, then waits for the redirection
+    /// and extracts the code from it.
+    ///
+    /// Requests made to other paths than the redirect path (like
+    /// `/favicon.ico`) are answered with a 404 and ignored.
+    async fn wait_for_code(
+        &self,
+        client: &Client,
+        csrf_state: &CsrfToken,
+    ) -> Result<AuthorizationCode> {
+        let host = match &self.redirect_bind_host {
+            Some(host) => host.clone(),
+            None => client.redirect_host.clone(),
+        };
+        let port = self.redirect_bind_port.unwrap_or(client.redirect_port);
+
+        let listener = TcpListener::bind((host.as_str(), port))
+            .await
+            .map_err(|err| Error::BindRedirectServerError(host.clone(), port, err))?;
+
+        loop {
+            let (mut stream, _) = listener
+                .accept()
+                .await
+                .map_err(Error::AcceptRedirectServerError)?;
+
+            let mut reader = BufReader::new(&mut stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).await?;
+
+            let redirect_url = request_line
+                .split_whitespace()
+                .nth(1)
+                .ok_or_else(|| Error::MissingRedirectUrlError(request_line.clone()))?;
+            let redirect_url = format!("http://localhost{redirect_url}");
+            let redirect_url = Url::parse(&redirect_url)
+                .map_err(|err| Error::ParseRedirectUrlError(err, redirect_url.clone()))?;
+
+            let path = self.redirect_path.as_deref().unwrap_or("/");
+            if redirect_url.path() != path {
+                debug!(
+                    "ignoring request to unexpected path {}",
+                    redirect_url.path()
+                );
+                write_response(&mut stream, "404 Not Found", None, "Not found").await?;
+                continue;
+            }
+
+            return match extract_code(&redirect_url, csrf_state) {
+                Ok(code) => {
+                    let html = self.success_html.as_deref();
+                    let text = "Authentication successful!";
+                    write_response(&mut stream, "200 OK", html, text).await?;
+                    Ok(code)
+                }
+                Err(err) => {
+                    let html = self.error_html.as_deref();
+                    let text = "Authentication failed!";
+                    write_response(&mut stream, "400 Bad Request", html, text).await?;
+                    Err(err)
+                }
+            };
+        }
+    }
+}
+
+/// Extracts the code from the given redirect URL, after checking
+/// its state.
+fn extract_code(redirect_url: &Url, csrf_state: &CsrfToken) -> Result<AuthorizationCode> {
+    if let Some((_, err)) = redirect_url.query_pairs().find(|(key, _)| key == "error") {
+        return Err(Error::AuthorizationError(err.into_owned()));
+    }
+
+    let (_, state) = redirect_url
+        .query_pairs()
+        .find(|(key, _)| key == "state")
+        .ok_or_else(|| Error::FindStateInRedirectUrlError(redirect_url.clone()))?;
+    let state = CsrfToken::new(state.into_owned());
+
+    if state.secret() != csrf_state.secret() {
+        return Err(Error::InvalidStateError(
+            state.secret().to_owned(),
+            csrf_state.secret().to_owned(),
+        ));
+    }
+
+    let (_, code) = redirect_url
+        .query_pairs()
+        .find(|(key, _)| key == "code")
+        .ok_or_else(|| Error::FindCodeInRedirectUrlError(redirect_url.clone()))?;
+
+    Ok(AuthorizationCode::new(code.into_owned()))
+}
+
+/// Writes a basic HTTP response, in HTML if given, otherwise in
+/// plain text.
+async fn write_response<S>(
+    stream: &mut S,
+    status: &str,
+    html: Option<&str>,
+    text: &str,
+) -> Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    let (content_type, body) = match html {
+        Some(html) => ("text/html; charset=utf-8", html),
+        None => ("text/plain; charset=utf-8", text),
+    };
+
+    let res = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len(),
+    );
+
+    stream.write_all(res.as_bytes()).await?;
+    stream.flush().await?;
+
+    Ok(())
+}
+
+/// Waits for the first of the given futures to resolve.
+async fn race<T>(a: impl Future<Output = T>, b: impl Future<Output = T>) -> T {
+    let mut a = pin!(a);
+    let mut b = pin!(b);
+
+    future::poll_fn(|cx| match a.as_mut().poll(cx) {
+        Poll::Ready(output) => Poll::Ready(output),
+        Poll::Pending => b.as_mut().poll(cx),
+    })
+    .await
+}
+
+#[cfg(feature = "async-std")]
+async fn sleep(duration: Duration) {
+    async_std::task::sleep(duration).await
+}
+
+#[cfg(feature = "tokio")]
+async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await
 }
@@ -39,6 +39,14 @@ pub enum Error {
     FindStateInRedirectUrlError(Url),
     #[error("cannot exchange code for access and refresh tokens: {0}")]
     ExchangeCodeError(String),
+    #[error("authorization server returned error {0}")]
+    AuthorizationError(String),
+    #[error("redirection not received after {0:?}")]
+    RedirectServerTimeoutError(std::time::Duration),
+    #[error("redirection cancelled")]
+    RedirectServerCancelledError,
+    #[error("unknown oauth 2.0 provider {0}")]
+    UnknownProviderError(String),
 
     #[error(transparent)]
     IoError(#[from] std::io::Error),
@@ -8,12 +8,16 @@
 mod authorization_code_grant;
 mod client;
 mod error;
+mod provider;
 mod refresh_access_token;
+mod tokens;
 
 #[doc(inline)]
 pub use self::{
     authorization_code_grant::AuthorizationCodeGrant,
     client::Client,
     error::{Error, Result},
+    provider::Provider,
     refresh_access_token::RefreshAccessToken,
+    tokens::Tokens,
 };
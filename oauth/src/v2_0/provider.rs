@@ -0,0 +1,115 @@
+//! Built-in OAuth 2.0 provider presets, carrying the endpoints and
+//! the scopes needed to access mailboxes over IMAP and SMTP.
+
+use std::{fmt, str::FromStr};
+
+use super::{Client, Error, Result};
+
+/// OAuth 2.0 provider preset.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Provider {
+    /// Google Mail.
+    Gmail,
+
+    /// Microsoft Outlook, including Office 365.
+    #[cfg_attr(feature = "derive", serde(alias = "office365"))]
+    Outlook,
+
+    /// Yahoo Mail.
+    Yahoo,
+
+    /// Fastmail.
+    Fastmail,
+}
+
+impl Provider {
+    /// Returns the URL of the authorization endpoint of the
+    /// provider.
+    pub fn auth_url(&self) -> &'static str {
+        match self {
+            Self::Gmail => "https://accounts.google.com/o/oauth2/v2/auth",
+            Self::Outlook => "https://login.microsoftonline.com/common/oauth2/v2.0/authorize",
+            Self::Yahoo => "https://api.login.yahoo.com/oauth2/request_auth",
+            Self::Fastmail => "https://api.fastmail.com/oauth/authorize",
+        }
+    }
+
+    /// Returns the URL of the token endpoint of the provider.
+    pub fn token_url(&self) -> &'static str {
+        match self {
+            Self::Gmail => "https://www.googleapis.com/oauth2/v3/token",
+            Self::Outlook => "https://login.microsoftonline.com/common/oauth2/v2.0/token",
+            Self::Yahoo => "https://api.login.yahoo.com/oauth2/get_token",
+            Self::Fastmail => "https://api.fastmail.com/oauth/refresh",
+        }
+    }
+
+    /// Returns the default scopes needed to access mailboxes of the
+    /// provider over IMAP and SMTP.
+    pub fn scopes(&self) -> &'static [&'static str] {
+        match self {
+            Self::Gmail => &["https://mail.google.com/"],
+            Self::Outlook => &[
+                "https://outlook.office.com/IMAP.AccessAsUser.All",
+                "https://outlook.office.com/SMTP.Send",
+                "offline_access",
+            ],
+            Self::Yahoo => &["mail-w"],
+            Self::Fastmail => &[
+                "https://www.fastmail.com/dev/protocol-imap",
+                "https://www.fastmail.com/dev/protocol-smtp",
+                "offline_access",
+            ],
+        }
+    }
+
+    /// Builds a [`Client`] using the endpoints of the provider.
+    pub fn client(
+        &self,
+        client_id: impl ToString,
+        client_secret: Option<impl ToString>,
+        redirect_scheme: impl ToString,
+        redirect_host: impl ToString,
+        redirect_port: impl Into<u16>,
+    ) -> Result<Client> {
+        Client::new(
+            client_id,
+            client_secret,
+            self.auth_url(),
+            self.token_url(),
+            redirect_scheme,
+            redirect_host,
+            redirect_port,
+        )
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gmail => write!(f, "gmail"),
+            Self::Outlook => write!(f, "outlook"),
+            Self::Yahoo => write!(f, "yahoo"),
+            Self::Fastmail => write!(f, "fastmail"),
+        }
+    }
+}
+
+impl FromStr for Provider {
+    type Err = Error;
+
+    fn from_str(provider: &str) -> Result<Self> {
+        match provider.trim().to_lowercase().as_str() {
+            "gmail" | "google" => Ok(Self::Gmail),
+            "outlook" | "office365" => Ok(Self::Outlook),
+            "yahoo" => Ok(Self::Yahoo),
+            "fastmail" => Ok(Self::Fastmail),
+            _ => Err(Error::UnknownProviderError(provider.to_owned())),
+        }
+    }
+}
@@ -1,9 +1,9 @@
 //! Refresh Access Token flow helper, as defined in the
 //! [RFC6749](https://datatracker.ietf.org/doc/html/rfc6749#section-6)
 
-use oauth2::{RefreshToken, TokenResponse};
+use oauth2::RefreshToken;
 
-use super::{Client, Error, Result};
+use super::{Client, Error, Result, Tokens};
 
 /// OAuth 2.0 Refresh Access Token flow builder. The builder is empty
 /// for now but scopes will be added in the future. This flow exchange
@@ -22,6 +22,17 @@ pub async fn refresh_access_token(
         client: &Client,
         refresh_token: impl ToString,
     ) -> Result<(String, Option<String>)> {
+        let tokens = self.refresh_tokens(client, refresh_token).await?;
+        Ok(tokens.into())
+    }
+
+    /// Exchanges the given refresh token for new tokens, including
+    /// the lifetime of the new access token.
+    pub async fn refresh_tokens(
+        &self,
+        client: &Client,
+        refresh_token: impl ToString,
+    ) -> Result<Tokens> {
         let res = client
             .exchange_refresh_token(&RefreshToken::new(refresh_token.to_string()))
             .request_async(&Client::send_oauth2_request)
@@ -29,9 +40,6 @@ pub async fn refresh_access_token(
             .map_err(Box::new)
             .map_err(Error::RefreshAccessTokenError)?;
 
-        let access_token = res.access_token().secret().to_owned();
-        let refresh_token = res.refresh_token().map(|t| t.secret().clone());
-
-        Ok((access_token, refresh_token))
+        Ok(Tokens::from_response(&res))
     }
 }
@@ -0,0 +1,38 @@
+//! Tokens issued by the authorization server, as defined in the
+//! [RFC6749](https://datatracker.ietf.org/doc/html/rfc6749#section-5.1)
+
+use std::time::Duration;
+
+use oauth2::TokenResponse;
+
+/// The tokens issued by the token endpoint of the authorization
+/// server.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Tokens {
+    /// The access token used to access protected resources.
+    pub access_token: String,
+
+    /// The refresh token used to obtain new access tokens, if issued
+    /// by the authorization server.
+    pub refresh_token: Option<String>,
+
+    /// The lifetime of the access token, if given by the
+    /// authorization server.
+    pub expires_in: Option<Duration>,
+}
+
+impl Tokens {
+    pub(crate) fn from_response(res: &impl TokenResponse) -> Self {
+        Self {
+            access_token: res.access_token().secret().to_owned(),
+            refresh_token: res.refresh_token().map(|t| t.secret().clone()),
+            expires_in: res.expires_in(),
+        }
+    }
+}
+
+impl From<Tokens> for (String, Option<String>) {
+    fn from(tokens: Tokens) -> Self {
+        (tokens.access_token, tokens.refresh_token)
+    }
+}
@@ -0,0 +1,409 @@
+//! # Agent
+//!
+//! Module dedicated to gpg-agent. It exposes functions to sign and
+//! decrypt using secret keys held by gpg-agent, which is contacted
+//! through its Assuan socket. Secret key material never enters the
+//! process: only hashes and encrypted session keys are sent to the
+//! agent, which takes care of unlocking keys (using pinentry).
+//!
+//! Secret keys are identified by their keygrip, which can be listed
+//! using `gpg --list-secret-keys --with-keygrip`.
+
+use std::{
+    env,
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    external::{self, ecdh_wrapped_key},
+    native::{
+        crypto::public_key::PublicKeyAlgorithm,
+        types::{Mpi, PublicParams},
+        SignedPublicKey,
+    },
+    utils::spawn_blocking,
+    Error, Result,
+};
+
+/// The libgcrypt identifier of the SHA-256 hash algorithm.
+const GCRY_MD_SHA256: u8 = 8;
+
+/// The maximum length of an Assuan line, without the trailing new
+/// line.
+const MAX_LINE_LEN: usize = 1000;
+
+/// Returns the default path of the gpg-agent socket.
+///
+/// The socket is searched in `$GNUPGHOME`, then in the runtime
+/// directory used by GnuPG 2.1+, then in `~/.gnupg`.
+pub fn default_socket_path() -> PathBuf {
+    if let Some(dir) = env::var_os("GNUPGHOME") {
+        return PathBuf::from(dir).join("S.gpg-agent");
+    }
+
+    if let Some(dir) = env::var_os("XDG_RUNTIME_DIR") {
+        let path = PathBuf::from(dir).join("gnupg").join("S.gpg-agent");
+        if path.exists() {
+            return path;
+        }
+    }
+
+    let home = env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+    home.join(".gnupg").join("S.gpg-agent")
+}
+
+/// Signs the given bytes using the secret key held by gpg-agent
+/// matching the given keygrip.
+///
+/// The given public key is used to build the signature packet: it
+/// needs to be the public counterpart of the agent secret key.
+pub async fn sign(
+    socket: PathBuf,
+    keygrip: impl ToString,
+    pkey: SignedPublicKey,
+    plain_bytes: Vec<u8>,
+) -> Result<Vec<u8>> {
+    let keygrip = keygrip.to_string();
+
+    spawn_blocking(move || {
+        external::sign(&pkey, &plain_bytes, |algo, hash| {
+            let mut agent = AssuanClient::connect(&socket)?;
+            agent.send(&format!("SIGKEY {keygrip}"), None)?;
+            agent.send(
+                &format!("SETHASH {GCRY_MD_SHA256} {}", encode_hex(hash)),
+                None,
+            )?;
+            let sig_val = Sexp::parse(&agent.send("PKSIGN", None)?)?;
+
+            match algo {
+                PublicKeyAlgorithm::RSA | PublicKeyAlgorithm::RSASign => {
+                    Ok(vec![Mpi::from_raw_slice(sig_val.find_atom(b"s")?)])
+                }
+                PublicKeyAlgorithm::EdDSA | PublicKeyAlgorithm::ECDSA => Ok(vec![
+                    Mpi::from_raw_slice(sig_val.find_atom(b"r")?),
+                    Mpi::from_raw_slice(sig_val.find_atom(b"s")?),
+                ]),
+                algo => Err(Error::UnsupportedExternalAlgorithmError(algo)),
+            }
+        })
+    })
+    .await?
+}
+
+/// Decrypts the given bytes using the secret key held by gpg-agent
+/// matching the given keygrip.
+///
+/// The given public key is used to find the session key packet
+/// addressed to the agent secret key: it needs to be the public
+/// counterpart of the agent secret key.
+pub async fn decrypt(
+    socket: PathBuf,
+    keygrip: impl ToString,
+    pkey: SignedPublicKey,
+    encrypted_bytes: Vec<u8>,
+) -> Result<Vec<u8>> {
+    let keygrip = keygrip.to_string();
+
+    spawn_blocking(move || {
+        external::decrypt(&pkey, &encrypted_bytes, |params, mpis| {
+            let mut agent = AssuanClient::connect(&socket)?;
+            agent.send(&format!("SETKEY {keygrip}"), None)?;
+            let enc_val = build_enc_val(params, mpis)?;
+            let value = agent.send("PKDECRYPT", Some(&enc_val))?;
+            let value = Sexp::parse(&value)?.find_atom(b"value")?.to_vec();
+            Ok(value)
+        })
+    })
+    .await?
+}
+
+/// Builds the `enc-val` S-expression of the given encrypted session
+/// key, as expected by the `PKDECRYPT` command.
+fn build_enc_val(params: &PublicParams, mpis: &[Mpi]) -> Result<Vec<u8>> {
+    let sexp = match (params, mpis) {
+        (PublicParams::RSA { .. }, [a]) => Sexp::list([
+            Sexp::atom(b"enc-val"),
+            Sexp::list([Sexp::atom(b"rsa"), Sexp::pair(b"a", &to_unsigned(a))]),
+        ]),
+        (PublicParams::ECDH { .. }, [e, ..]) => {
+            let wrapped = ecdh_wrapped_key(mpis)?;
+            let s = [&[wrapped.len() as u8], wrapped.as_slice()].concat();
+            Sexp::list([
+                Sexp::atom(b"enc-val"),
+                Sexp::list([
+                    Sexp::atom(b"ecdh"),
+                    Sexp::pair(b"s", &s),
+                    Sexp::pair(b"e", e.as_bytes()),
+                ]),
+            ])
+        }
+        _ => return Err(Error::InvalidExternalSessionKeyError),
+    };
+
+    Ok(sexp.to_bytes())
+}
+
+/// Returns the unsigned representation of the given MPI, as expected
+/// by libgcrypt.
+fn to_unsigned(mpi: &Mpi) -> Vec<u8> {
+    let bytes = mpi.as_bytes();
+    match bytes.first() {
+        Some(byte) if byte & 0x80 != 0 => [&[0], bytes].concat(),
+        _ => bytes.to_vec(),
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02X}")).collect()
+}
+
+/// Escapes the given bytes as Assuan data.
+fn escape(bytes: &[u8]) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(bytes.len());
+
+    for byte in bytes {
+        match byte {
+            b'%' | b'\r' | b'\n' => escaped.extend(format!("%{byte:02X}").as_bytes()),
+            byte => escaped.push(*byte),
+        }
+    }
+
+    escaped
+}
+
+/// Unescapes the given Assuan data.
+fn unescape(bytes: &[u8]) -> Vec<u8> {
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let byte = match bytes.get(i..i + 3) {
+            Some([b'%', hi, lo]) => {
+                let hex = [*hi, *lo];
+                let hex = std::str::from_utf8(&hex).unwrap_or_default();
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        i += 2;
+                        byte
+                    }
+                    Err(_) => b'%',
+                }
+            }
+            _ => bytes[i],
+        };
+
+        unescaped.push(byte);
+        i += 1;
+    }
+
+    unescaped
+}
+
+/// A minimal client of the Assuan protocol, used to talk to
+/// gpg-agent.
+struct AssuanClient {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl AssuanClient {
+    /// Connects to the given socket and reads the greeting.
+    fn connect(path: &Path) -> Result<Self> {
+        let writer = UnixStream::connect(path)
+            .map_err(|err| Error::ConnectAgentError(err, path.to_owned()))?;
+        let reader = writer
+            .try_clone()
+            .map_err(|err| Error::ConnectAgentError(err, path.to_owned()))?;
+
+        let mut client = Self {
+            reader: BufReader::new(reader),
+            writer,
+        };
+
+        client.read_response("", None)?;
+        Ok(client)
+    }
+
+    /// Sends the given command and returns the data of its response.
+    ///
+    /// The given inquire data is sent back when the agent inquires
+    /// for it, otherwise inquiries are answered with no data.
+    fn send(&mut self, cmd: &str, inquire: Option<&[u8]>) -> Result<Vec<u8>> {
+        self.write_line(cmd.as_bytes())?;
+        self.read_response(cmd, inquire)
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> Result<()> {
+        self.writer
+            .write_all(&[line, b"\n"].concat())
+            .map_err(Error::WriteAgentError)
+    }
+
+    fn read_response(&mut self, cmd: &str, inquire: Option<&[u8]>) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+
+        loop {
+            let mut line = Vec::new();
+            self.reader
+                .read_until(b'\n', &mut line)
+                .map_err(Error::ReadAgentError)?;
+
+            if line.is_empty() {
+                return Err(Error::AgentClosedError);
+            }
+
+            if line.ends_with(b"\n") {
+                line.pop();
+            }
+
+            if line == b"OK" || line.starts_with(b"OK ") {
+                return Ok(data);
+            }
+
+            if let Some(err) = line.strip_prefix(b"ERR ") {
+                let err = String::from_utf8_lossy(err).to_string();
+                return Err(Error::AgentCommandError(cmd.to_owned(), err));
+            }
+
+            // other lines are status and comment lines
+            if let Some(bytes) = line.strip_prefix(b"D ") {
+                data.extend(unescape(bytes));
+            } else if line.starts_with(b"INQUIRE ") {
+                for chunk in inquire.unwrap_or_default().chunks(MAX_LINE_LEN / 3 - 2) {
+                    self.write_line(&[b"D ", escape(chunk).as_slice()].concat())?;
+                }
+                self.write_line(b"END")?;
+            }
+        }
+    }
+}
+
+/// A canonical S-expression, as used by gpg-agent.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Sexp {
+    Atom(Vec<u8>),
+    List(Vec<Sexp>),
+}
+
+impl Sexp {
+    fn atom(bytes: &[u8]) -> Self {
+        Self::Atom(bytes.to_vec())
+    }
+
+    fn list(items: impl IntoIterator<Item = Sexp>) -> Self {
+        Self::List(items.into_iter().collect())
+    }
+
+    fn pair(name: &[u8], value: &[u8]) -> Self {
+        Self::list([Self::atom(name), Self::atom(value)])
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        let (sexp, _) = Self::parse_next(bytes)?;
+        Ok(sexp)
+    }
+
+    fn parse_next(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        match bytes.split_first() {
+            Some((b'(', mut rest)) => {
+                let mut items = Vec::new();
+
+                loop {
+                    match rest.split_first() {
+                        Some((b')', tail)) => return Ok((Self::List(items), tail)),
+                        Some(_) => {
+                            let (item, tail) = Self::parse_next(rest)?;
+                            items.push(item);
+                            rest = tail;
+                        }
+                        None => return Err(Error::ParseAgentSexpError),
+                    }
+                }
+            }
+            Some(_) => {
+                let colon = bytes
+                    .iter()
+                    .position(|byte| *byte == b':')
+                    .ok_or(Error::ParseAgentSexpError)?;
+                let len: usize = std::str::from_utf8(&bytes[..colon])
+                    .ok()
+                    .and_then(|len| len.parse().ok())
+                    .ok_or(Error::ParseAgentSexpError)?;
+                let rest = &bytes[colon + 1..];
+
+                if rest.len() < len {
+                    return Err(Error::ParseAgentSexpError);
+                }
+
+                Ok((Self::Atom(rest[..len].to_vec()), &rest[len..]))
+            }
+            None => Err(Error::ParseAgentSexpError),
+        }
+    }
+
+    /// Finds the value of the first list named after the given name.
+    fn find_atom(&self, name: &[u8]) -> Result<&[u8]> {
+        self.find(name).ok_or(Error::ParseAgentSexpError)
+    }
+
+    fn find(&self, name: &[u8]) -> Option<&[u8]> {
+        match self {
+            Self::Atom(_) => None,
+            Self::List(items) => match items.as_slice() {
+                [Self::Atom(key), Self::Atom(value), ..] if key == name => Some(value),
+                items => items.iter().find_map(|item| item.find(name)),
+            },
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Atom(bytes) => [format!("{}:", bytes.len()).as_bytes(), bytes].concat(),
+            Self::List(items) => {
+                let mut bytes = vec![b'('];
+                for item in items {
+                    bytes.extend(item.to_bytes());
+                }
+                bytes.push(b')');
+                bytes
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, unescape, Sexp};
+
+    #[test]
+    fn sexp() {
+        let sexp = Sexp::list([
+            Sexp::atom(b"sig-val"),
+            Sexp::list([
+                Sexp::atom(b"eddsa"),
+                Sexp::pair(b"r", b"\x01\x02"),
+                Sexp::pair(b"s", b"\x03)"),
+            ]),
+        ]);
+
+        let bytes = sexp.to_bytes();
+        assert_eq!(
+            bytes,
+            b"(7:sig-val(5:eddsa(1:r2:\x01\x02)(1:s2:\x03))))".to_vec()
+        );
+
+        let sexp = Sexp::parse(&bytes).unwrap();
+        assert_eq!(sexp.find(b"r"), Some(b"\x01\x02".as_slice()));
+        assert_eq!(sexp.find(b"s"), Some(b"\x03)".as_slice()));
+        assert_eq!(sexp.find(b"a"), None);
+    }
+
+    #[test]
+    fn escaping() {
+        let data = b"100%\r\nok".to_vec();
+        assert_eq!(escape(&data), b"100%25%0D%0Aok".to_vec());
+        assert_eq!(unescape(&escape(&data)), data);
+    }
+}
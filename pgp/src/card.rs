@@ -0,0 +1,362 @@
+//! # Card
+//!
+//! Module dedicated to OpenPGP smartcards (YubiKey, Nitrokey…). It
+//! exposes functions to sign and decrypt using secret keys stored on
+//! a card, which is contacted through PC/SC. Secret key material
+//! never leaves the card.
+//!
+//! Cards may require the user PIN and a physical touch: both are
+//! requested through [`CardPrompts`].
+
+use std::{fmt, io, sync::Arc};
+
+use tracing::debug;
+
+pub use crate::pcsc::PcscError;
+use crate::{
+    external,
+    native::{
+        crypto::{ecc_curve::ECCCurve, public_key::PublicKeyAlgorithm},
+        types::{Mpi, PublicParams},
+        SignedPublicKey,
+    },
+    pcsc::{Card, Context, MAX_BUFFER_SIZE_EXTENDED},
+    utils::spawn_blocking,
+    Error, Result,
+};
+
+/// The application identifier of the OpenPGP card application.
+const OPENPGP_AID: [u8; 6] = [0xD2, 0x76, 0x00, 0x01, 0x24, 0x01];
+
+/// The DER prefix of SHA-256 digest infos, used by RSA signatures.
+const SHA256_DIGEST_INFO: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0D, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05,
+    0x00, 0x04, 0x20,
+];
+
+/// The status word of successful commands.
+const SW_OK: u16 = 0x9000;
+
+/// The secret key slot of an OpenPGP card.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CardKeySlot {
+    /// The slot of the signing secret key.
+    Signing,
+
+    /// The slot of the decryption secret key.
+    Decryption,
+}
+
+impl CardKeySlot {
+    /// Returns the reference of the PIN unlocking the slot, as
+    /// expected by the `VERIFY` command.
+    fn pin_ref(&self) -> u8 {
+        match self {
+            Self::Signing => 0x81,
+            Self::Decryption => 0x82,
+        }
+    }
+}
+
+/// The callbacks used to interact with the user during OpenPGP card
+/// operations.
+#[derive(Clone)]
+pub struct CardPrompts {
+    #[allow(clippy::type_complexity)]
+    pin: Arc<dyn Fn(CardKeySlot) -> io::Result<String> + Send + Sync>,
+    touch: Arc<dyn Fn(CardKeySlot) + Send + Sync>,
+}
+
+impl CardPrompts {
+    /// Creates new prompts using the given PIN callback.
+    ///
+    /// The callback is called every time the card requires the user
+    /// PIN to unlock the given slot.
+    pub fn new(pin: impl Fn(CardKeySlot) -> io::Result<String> + Send + Sync + 'static) -> Self {
+        Self {
+            pin: Arc::new(pin),
+            touch: Arc::new(|_| ()),
+        }
+    }
+
+    /// Sets the touch callback.
+    ///
+    /// The callback is called right before operations that may
+    /// require the user to touch the card, so that interfaces can
+    /// notify the user. The operation blocks until the card is
+    /// touched.
+    pub fn with_touch(mut self, touch: impl Fn(CardKeySlot) + Send + Sync + 'static) -> Self {
+        self.touch = Arc::new(touch);
+        self
+    }
+}
+
+impl Default for CardPrompts {
+    fn default() -> Self {
+        Self::new(|_| {
+            let err = "missing OpenPGP card PIN prompt";
+            Err(io::Error::new(io::ErrorKind::NotFound, err))
+        })
+    }
+}
+
+impl PartialEq for CardPrompts {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.pin, &other.pin) && Arc::ptr_eq(&self.touch, &other.touch)
+    }
+}
+
+impl Eq for CardPrompts {}
+
+impl fmt::Debug for CardPrompts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CardPrompts()")
+    }
+}
+
+/// Signs the given bytes using the signing secret key of the OpenPGP
+/// card plugged into the given reader.
+///
+/// When no reader is given, the first card found is used. The given
+/// public key is used to build the signature packet: it needs to be
+/// the public counterpart of the card secret keys.
+pub async fn sign(
+    reader: Option<String>,
+    prompts: CardPrompts,
+    pkey: SignedPublicKey,
+    plain_bytes: Vec<u8>,
+) -> Result<Vec<u8>> {
+    spawn_blocking(move || {
+        external::sign(&pkey, &plain_bytes, |algo, hash| {
+            let card = OpenPgpCard::connect(reader.as_deref())?;
+            card.verify_pin(CardKeySlot::Signing, &prompts)?;
+
+            let data = match algo {
+                PublicKeyAlgorithm::RSA | PublicKeyAlgorithm::RSASign => {
+                    [SHA256_DIGEST_INFO.as_slice(), hash].concat()
+                }
+                PublicKeyAlgorithm::EdDSA | PublicKeyAlgorithm::ECDSA => hash.to_vec(),
+                algo => return Err(Error::UnsupportedExternalAlgorithmError(algo)),
+            };
+
+            (prompts.touch)(CardKeySlot::Signing);
+            let sig = card.transmit([0x00, 0x2A, 0x9E, 0x9A], &data, true)?;
+
+            match algo {
+                PublicKeyAlgorithm::RSA | PublicKeyAlgorithm::RSASign => {
+                    Ok(vec![Mpi::from_raw_slice(&sig)])
+                }
+                _ => {
+                    let (r, s) = sig.split_at(sig.len() / 2);
+                    Ok(vec![Mpi::from_raw_slice(r), Mpi::from_raw_slice(s)])
+                }
+            }
+        })
+    })
+    .await?
+}
+
+/// Decrypts the given bytes using the decryption secret key of the
+/// OpenPGP card plugged into the given reader.
+///
+/// When no reader is given, the first card found is used. The given
+/// public key is used to find the session key packet addressed to
+/// the card: it needs to be the public counterpart of the card
+/// secret keys.
+pub async fn decrypt(
+    reader: Option<String>,
+    prompts: CardPrompts,
+    pkey: SignedPublicKey,
+    encrypted_bytes: Vec<u8>,
+) -> Result<Vec<u8>> {
+    spawn_blocking(move || {
+        // the card is kept connected and unlocked between attempts
+        let mut card = None;
+
+        external::decrypt(&pkey, &encrypted_bytes, |params, mpis| {
+            let card: &OpenPgpCard = match &mut card {
+                Some(card) => card,
+                None => {
+                    let new_card = OpenPgpCard::connect(reader.as_deref())?;
+                    new_card.verify_pin(CardKeySlot::Decryption, &prompts)?;
+                    card.insert(new_card)
+                }
+            };
+
+            let data = match (params, mpis) {
+                (PublicParams::RSA { n, .. }, [c]) => {
+                    // the cryptogram needs to be as long as the
+                    // modulus, prefixed by the padding indicator
+                    let c = c.as_bytes();
+                    let mut data = vec![0; 1 + n.len().saturating_sub(c.len())];
+                    data.extend(c);
+                    data
+                }
+                (PublicParams::ECDH { curve, .. }, [point, ..]) => {
+                    let point = match (curve, point.as_bytes().split_first()) {
+                        (ECCCurve::Curve25519, Some((0x40, point))) => point,
+                        _ => point.as_bytes(),
+                    };
+                    encode_tlv(
+                        &[0xA6],
+                        &encode_tlv(&[0x7F, 0x49], &encode_tlv(&[0x86], point)),
+                    )
+                }
+                _ => return Err(Error::InvalidExternalSessionKeyError),
+            };
+
+            (prompts.touch)(CardKeySlot::Decryption);
+            card.transmit([0x00, 0x2A, 0x80, 0x86], &data, true)
+        })
+    })
+    .await?
+}
+
+/// Encodes the given value as a BER-TLV data object.
+fn encode_tlv(tag: &[u8], value: &[u8]) -> Vec<u8> {
+    let len = value.len();
+    let len = match len {
+        0..=0x7F => vec![len as u8],
+        0x80..=0xFF => vec![0x81, len as u8],
+        _ => vec![0x82, (len >> 8) as u8, len as u8],
+    };
+
+    [tag, &len, value].concat()
+}
+
+/// A connection to the OpenPGP application of a card.
+struct OpenPgpCard {
+    card: Card,
+}
+
+impl OpenPgpCard {
+    /// Connects to the first card matching the given reader name and
+    /// selects its OpenPGP application.
+    fn connect(reader: Option<&str>) -> Result<Self> {
+        let ctx = Context::establish().map_err(Error::EstablishCardContextError)?;
+        let readers = ctx.list_readers().map_err(Error::ListCardReadersError)?;
+
+        for name in readers {
+            let name_str = name.to_string_lossy();
+
+            if let Some(reader) = reader {
+                if !name_str.contains(reader) {
+                    continue;
+                }
+            }
+
+            let card = match ctx.connect(&name) {
+                Ok(card) => Self { card },
+                Err(err) => {
+                    debug!(?err, "cannot connect to card reader {name_str}");
+                    continue;
+                }
+            };
+
+            match card.transmit([0x00, 0xA4, 0x04, 0x00], &OPENPGP_AID, false) {
+                Ok(_) => {
+                    debug!("found OpenPGP card in reader {name_str}");
+                    return Ok(card);
+                }
+                Err(err) => {
+                    debug!(?err, "cannot select OpenPGP application in {name_str}");
+                    continue;
+                }
+            }
+        }
+
+        Err(Error::FindCardError)
+    }
+
+    /// Verifies the user PIN unlocking the given slot.
+    fn verify_pin(&self, slot: CardKeySlot, prompts: &CardPrompts) -> Result<()> {
+        let pin = (prompts.pin)(slot).map_err(Error::GetCardPinError)?;
+
+        match self.transmit([0x00, 0x20, 0x00, slot.pin_ref()], pin.as_bytes(), false) {
+            Err(Error::CardStatusError(sw)) if sw & 0xFFF0 == 0x63C0 => {
+                Err(Error::VerifyCardPinError((sw & 0x000F) as u8))
+            }
+            res => res.map(|_| ()),
+        }
+    }
+
+    /// Transmits the given command with the given data, and returns
+    /// the data of the response when expected.
+    ///
+    /// Data longer than a short APDU is sent using command chaining,
+    /// and long responses are retrieved using `GET RESPONSE`.
+    fn transmit(&self, header: [u8; 4], data: &[u8], expect_data: bool) -> Result<Vec<u8>> {
+        let [cla, ins, p1, p2] = header;
+        let chunks: Vec<&[u8]> = if data.is_empty() {
+            vec![&[]]
+        } else {
+            data.chunks(0xFF).collect()
+        };
+
+        let mut res = Vec::new();
+        let mut buf = [0; MAX_BUFFER_SIZE_EXTENDED];
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            let last = i == chunks.len() - 1;
+            let cla = if last { cla } else { cla | 0x10 };
+
+            let mut apdu = vec![cla, ins, p1, p2];
+            if !chunk.is_empty() {
+                apdu.push(chunk.len() as u8);
+                apdu.extend(*chunk);
+            }
+            if last && expect_data {
+                apdu.push(0x00);
+            }
+
+            let mut sw = self.transmit_apdu(&apdu, &mut buf, &mut res)?;
+
+            while sw >> 8 == 0x61 {
+                let apdu = [0x00, 0xC0, 0x00, 0x00, sw as u8];
+                sw = self.transmit_apdu(&apdu, &mut buf, &mut res)?;
+            }
+
+            if sw != SW_OK {
+                return Err(Error::CardStatusError(sw));
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Transmits the given APDU, appends the data of the response to
+    /// the given buffer and returns the status word.
+    fn transmit_apdu(&self, apdu: &[u8], buf: &mut [u8], res: &mut Vec<u8>) -> Result<u16> {
+        let rapdu = self
+            .card
+            .transmit(apdu, buf)
+            .map_err(Error::TransmitCardError)?;
+
+        match rapdu {
+            [data @ .., sw1, sw2] => {
+                res.extend(data);
+                Ok(u16::from_be_bytes([*sw1, *sw2]))
+            }
+            _ => Err(Error::CardStatusError(0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::encode_tlv;
+
+    #[test]
+    fn tlv() {
+        let point = [0x42; 32];
+        let tlv = encode_tlv(
+            &[0xA6],
+            &encode_tlv(&[0x7F, 0x49], &encode_tlv(&[0x86], &point)),
+        );
+        assert_eq!(&tlv[..7], &[0xA6, 37, 0x7F, 0x49, 34, 0x86, 32]);
+        assert_eq!(&tlv[7..], &point);
+
+        let value = [0x00; 200];
+        assert_eq!(&encode_tlv(&[0x86], &value)[..3], &[0x86, 0x81, 200]);
+    }
+}
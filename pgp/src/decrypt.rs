@@ -3,15 +3,28 @@
 //! Module dedicated to PGP decryption. This module exposes a simple
 //! function [`decrypt`] and its associated [`Error`]s.
 
-use std::io::Cursor;
+use std::{io::Cursor, iter};
+
+use tracing::debug;
 
 use crate::{
-    native::{self, Deserializable, Message, SignedSecretKey},
+    encrypt::is_wildcard_key_id,
+    native::{
+        self,
+        packet::PublicKeyEncryptedSessionKey,
+        ser::Serialize,
+        types::{KeyId, KeyTrait},
+        Deserializable, Esk, Message, SignedSecretKey,
+    },
     utils::spawn_blocking,
     Error, Result,
 };
 
 /// Decrypts bytes using the given secret key and its passphrase.
+///
+/// Session keys addressed to the wildcard key id (hidden recipients)
+/// are tried against the primary key and every subkey of the given
+/// secret key.
 pub async fn decrypt(
     skey: SignedSecretKey,
     passphrase: impl ToString,
@@ -21,25 +34,84 @@ pub async fn decrypt(
     spawn_blocking(move || {
         let (msg, _) = Message::from_armor_single(Cursor::new(&encrypted_bytes))
             .map_err(Error::ImportMessageFromArmorError)?;
-        let (decryptor, _) = msg
-            .decrypt(|| passphrase, &[&skey])
-            .map_err(Error::DecryptMessageError)?;
-        let msgs = decryptor
-            .collect::<native::errors::Result<Vec<_>>>()
-            .map_err(Error::DecryptMessageError)?;
-        let msg = msgs.into_iter().next().ok_or(Error::GetMessageEmptyError)?;
-        let msg = msg.decompress().map_err(Error::DecompressMessageError)?;
-
-        let plain_bytes = msg
-            .get_content()
-            .map_err(Error::GetMessageContentError)?
-            .ok_or(Error::GetMessageContentEmptyError)?;
-
-        Ok(plain_bytes)
+
+        match decrypt_msg(&msg, &skey, &passphrase) {
+            Err(Error::DecryptMessageError(native::errors::Error::MissingKey)) => {
+                for msg in readdress_wildcard_esks(&msg, &skey)? {
+                    match decrypt_msg(&msg, &skey, &passphrase) {
+                        Ok(plain_bytes) => return Ok(plain_bytes),
+                        Err(err) => debug!(?err, "cannot decrypt hidden recipient session key"),
+                    }
+                }
+
+                Err(Error::DecryptMessageError(
+                    native::errors::Error::MissingKey,
+                ))
+            }
+            res => res,
+        }
     })
     .await?
 }
 
+fn decrypt_msg(msg: &Message, skey: &SignedSecretKey, passphrase: &str) -> Result<Vec<u8>> {
+    let (decryptor, _) = msg
+        .decrypt(|| passphrase.to_owned(), &[skey])
+        .map_err(Error::DecryptMessageError)?;
+    let msgs = decryptor
+        .collect::<native::errors::Result<Vec<_>>>()
+        .map_err(Error::DecryptMessageError)?;
+    let msg = msgs.into_iter().next().ok_or(Error::GetMessageEmptyError)?;
+    let msg = msg.decompress().map_err(Error::DecompressMessageError)?;
+
+    let plain_bytes = msg
+        .get_content()
+        .map_err(Error::GetMessageContentError)?
+        .ok_or(Error::GetMessageContentEmptyError)?;
+
+    Ok(plain_bytes)
+}
+
+/// Builds one encrypted message per wildcard session key packet and
+/// per key id of the given secret key, so that the session key can be
+/// matched against the right (sub)key.
+fn readdress_wildcard_esks(msg: &Message, skey: &SignedSecretKey) -> Result<Vec<Message>> {
+    let Message::Encrypted { esk, edata } = msg else {
+        return Ok(Vec::new());
+    };
+
+    let key_ids: Vec<KeyId> = iter::once(skey.key_id())
+        .chain(skey.secret_subkeys.iter().map(KeyTrait::key_id))
+        .collect();
+
+    let mut msgs = Vec::new();
+
+    for esk in esk {
+        let Esk::PublicKeyEncryptedSessionKey(esk) = esk else {
+            continue;
+        };
+
+        if !is_wildcard_key_id(esk.id()) {
+            continue;
+        }
+
+        // the key id follows the version of the packet
+        let mut bytes = esk.to_bytes().map_err(Error::DecryptMessageError)?;
+
+        for key_id in &key_ids {
+            bytes[1..9].copy_from_slice(key_id.as_ref());
+            let esk = PublicKeyEncryptedSessionKey::from_slice(esk.packet_version(), &bytes)
+                .map_err(Error::DecryptMessageError)?;
+            msgs.push(Message::Encrypted {
+                esk: vec![Esk::PublicKeyEncryptedSessionKey(esk)],
+                edata: edata.clone(),
+            });
+        }
+    }
+
+    Ok(msgs)
+}
+
 #[cfg(test)]
 mod tests {
     #[cfg(feature = "async-std")]
@@ -47,7 +119,7 @@ mod tests {
     #[cfg(feature = "tokio")]
     use tokio::test;
 
-    use crate::{decrypt, encrypt, gen_key_pair, native};
+    use crate::{decrypt, encrypt, encrypt_with_hidden_recipients, gen_key_pair, native};
 
     #[test_log::test(test)]
     async fn encrypt_then_decrypt() {
@@ -76,4 +148,40 @@ async fn encrypt_then_decrypt() {
             super::Error::DecryptMessageError(native::errors::Error::MissingKey),
         ));
     }
+
+    #[test_log::test(test)]
+    async fn encrypt_with_hidden_recipients_then_decrypt() {
+        let (alice_skey, alice_pkey) = gen_key_pair("alice@localhost", "").await.unwrap();
+        let (bob_skey, bob_pkey) = gen_key_pair("bob@localhost", "").await.unwrap();
+        let (carl_skey, carl_pkey) = gen_key_pair("carl@localhost", "").await.unwrap();
+        let (dave_skey, _dave_pkey) = gen_key_pair("dave@localhost", "").await.unwrap();
+
+        let msg = b"encrypted message".to_vec();
+        let encrypted_msg = encrypt_with_hidden_recipients(
+            vec![alice_pkey],
+            vec![bob_pkey, carl_pkey],
+            msg.clone(),
+        )
+        .await
+        .unwrap();
+
+        let alice_msg = decrypt(alice_skey, "", encrypted_msg.clone())
+            .await
+            .unwrap();
+        assert_eq!(alice_msg, msg);
+
+        let bob_msg = decrypt(bob_skey, "", encrypted_msg.clone()).await.unwrap();
+        assert_eq!(bob_msg, msg);
+
+        let carl_msg = decrypt(carl_skey, "", encrypted_msg.clone()).await.unwrap();
+        assert_eq!(carl_msg, msg);
+
+        let dave_msg = decrypt(dave_skey, "", encrypted_msg.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            dave_msg,
+            super::Error::DecryptMessageError(native::errors::Error::MissingKey),
+        ));
+    }
 }
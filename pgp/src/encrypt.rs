@@ -1,7 +1,9 @@
 //! # Encrypt
 //!
 //! Module dedicated to PGP encryption. This module exposes a simple
-//! function [`encrypt`] and its associated [`Error`]s.
+//! function [`encrypt`], a variant supporting hidden recipients
+//! [`encrypt_with_hidden_recipients`] and their associated
+//! [`Error`]s.
 
 use std::io;
 
@@ -83,6 +85,68 @@ fn to_writer_old(&self, writer: &mut impl io::Write) -> native::errors::Result<(
     }
 }
 
+/// The wildcard key id, used in place of the key id of hidden
+/// recipients.
+///
+/// See <https://www.rfc-editor.org/rfc/rfc4880#section-5.1>.
+const WILDCARD_KEY_ID: [u8; 8] = [0; 8];
+
+/// Returns `true` if the given key id is the wildcard key id.
+pub(crate) fn is_wildcard_key_id(key_id: &KeyId) -> bool {
+    key_id.as_ref() == WILDCARD_KEY_ID
+}
+
+/// Encryption-capable public (sub)key of a recipient.
+///
+/// The key id of hidden recipients is replaced by the wildcard key
+/// id, so that encrypted messages do not leak their identity.
+#[derive(Debug)]
+struct RecipientKey<'a> {
+    key: SignedPublicKeyOrSubkey<'a>,
+    hidden: bool,
+}
+
+impl KeyTrait for RecipientKey<'_> {
+    fn fingerprint(&self) -> Vec<u8> {
+        self.key.fingerprint()
+    }
+
+    fn key_id(&self) -> KeyId {
+        if self.hidden {
+            KeyId::from_slice(&WILDCARD_KEY_ID).expect("wildcard key id should be valid")
+        } else {
+            self.key.key_id()
+        }
+    }
+
+    fn algorithm(&self) -> PublicKeyAlgorithm {
+        self.key.algorithm()
+    }
+}
+
+impl PublicKeyTrait for RecipientKey<'_> {
+    fn verify_signature(
+        &self,
+        hash: HashAlgorithm,
+        data: &[u8],
+        sig: &[Mpi],
+    ) -> native::errors::Result<()> {
+        self.key.verify_signature(hash, data, sig)
+    }
+
+    fn encrypt<R: Rng + CryptoRng>(
+        &self,
+        rng: &mut R,
+        plain: &[u8],
+    ) -> native::errors::Result<Vec<Mpi>> {
+        self.key.encrypt(rng, plain)
+    }
+
+    fn to_writer_old(&self, writer: &mut impl io::Write) -> native::errors::Result<()> {
+        self.key.to_writer_old(writer)
+    }
+}
+
 /// Find primary key or subkey to use for encryption.
 ///
 /// First, tries to use subkeys. If none of the subkeys are suitable
@@ -101,14 +165,35 @@ fn find_pkey_for_encryption(key: &SignedPublicKey) -> Option<SignedPublicKeyOrSu
 
 /// Encrypts given bytes using the given list of public keys.
 pub async fn encrypt(pkeys: Vec<SignedPublicKey>, plain_bytes: Vec<u8>) -> Result<Vec<u8>> {
+    encrypt_with_hidden_recipients(pkeys, Vec::new(), plain_bytes).await
+}
+
+/// Encrypts given bytes using the given lists of public keys.
+///
+/// Session keys encrypted for hidden public keys are addressed to
+/// the wildcard key id instead of the recipient key id, like the
+/// `--hidden-recipient` option of GnuPG. This is typically used for
+/// Bcc recipients.
+pub async fn encrypt_with_hidden_recipients(
+    pkeys: Vec<SignedPublicKey>,
+    hidden_pkeys: Vec<SignedPublicKey>,
+    plain_bytes: Vec<u8>,
+) -> Result<Vec<u8>> {
     spawn_blocking(move || {
         let mut rng = thread_rng();
 
         let msg = Message::new_literal_bytes("", &plain_bytes);
 
-        let pkeys: Vec<SignedPublicKeyOrSubkey> =
-            pkeys.iter().filter_map(find_pkey_for_encryption).collect();
-        let pkeys_refs: Vec<&SignedPublicKeyOrSubkey> = pkeys.iter().collect();
+        let visible_pkeys = pkeys.iter().map(|pkey| (pkey, false));
+        let hidden_pkeys = hidden_pkeys.iter().map(|pkey| (pkey, true));
+        let pkeys: Vec<RecipientKey> = visible_pkeys
+            .chain(hidden_pkeys)
+            .filter_map(|(pkey, hidden)| {
+                let key = find_pkey_for_encryption(pkey)?;
+                Some(RecipientKey { key, hidden })
+            })
+            .collect();
+        let pkeys_refs: Vec<&RecipientKey> = pkeys.iter().collect();
 
         let encrypted_bytes = msg
             .compress(CompressionAlgorithm::ZLIB)
@@ -62,6 +62,18 @@ pub enum Error {
     #[cfg(feature = "key-discovery")]
     #[error("cannot find pgp public key for email {0}")]
     FindPublicKeyError(String),
+    #[cfg(feature = "key-discovery")]
+    #[error("cannot publish public key at {1}: {2}: {0}")]
+    PublishPublicKeyError(String, http::ureq::http::Uri, http::ureq::http::StatusCode),
+    #[cfg(feature = "key-discovery")]
+    #[error("cannot publish public key to {0}: only hkp(s) key servers are supported")]
+    PublishPublicKeyUnsupportedError(String),
+    #[cfg(feature = "key-discovery")]
+    #[error("cannot publish public key: no key server accepted it")]
+    PublishPublicKeyNoKeyServerError,
+    #[cfg(feature = "key-discovery")]
+    #[error("cannot refresh pgp public key {0}")]
+    RefreshPublicKeyError(String),
     #[error("cannot build pgp secret key params")]
     BuildSecretKeyParamsError(#[source] SecretKeyParamsBuilderError),
     #[error("cannot generate pgp secret key")]
@@ -78,6 +90,23 @@ pub enum Error {
     #[error("cannot verify pgp public subkey")]
     VerifyPublicKeyError(#[source] native::errors::Error),
 
+    #[error("cannot set pgp key expiry")]
+    SetKeyExpiryError(#[source] native::errors::Error),
+    #[error("cannot export pgp public key as armored string")]
+    ExportPublicKeyError(#[source] native::errors::Error),
+    #[error("cannot export pgp secret key as armored string")]
+    ExportSecretKeyError(#[source] native::errors::Error),
+    #[error("cannot import pgp keys from armored string")]
+    ImportKeysError(#[source] native::errors::Error),
+    #[error("cannot revoke pgp key")]
+    RevokeKeyError(#[source] native::errors::Error),
+    #[cfg(feature = "keyring")]
+    #[error("cannot get pgp secret key from keyring")]
+    GetSecretKeyFromKeyringError(#[source] keyring::Error),
+    #[cfg(feature = "keyring")]
+    #[error("cannot set pgp secret key into keyring")]
+    SetSecretKeyIntoKeyringError(#[source] keyring::Error),
+
     #[error("cannot read armored public key at {1}")]
     ReadArmoredPublicKeyError(#[source] std::io::Error, PathBuf),
     #[error("cannot parse armored public key from {1}")]
@@ -111,6 +140,69 @@ pub enum Error {
     #[error("cannot parse certificate")]
     ParseCertError(#[source] native::errors::Error),
 
+    #[cfg(all(feature = "agent", unix))]
+    #[error("cannot connect to gpg-agent at {1}")]
+    ConnectAgentError(#[source] std::io::Error, PathBuf),
+    #[cfg(all(feature = "agent", unix))]
+    #[error("cannot send command to gpg-agent")]
+    WriteAgentError(#[source] std::io::Error),
+    #[cfg(all(feature = "agent", unix))]
+    #[error("cannot read gpg-agent response")]
+    ReadAgentError(#[source] std::io::Error),
+    #[cfg(all(feature = "agent", unix))]
+    #[error("cannot read gpg-agent response: connection closed")]
+    AgentClosedError,
+    #[cfg(all(feature = "agent", unix))]
+    #[error("cannot execute gpg-agent command {0}: {1}")]
+    AgentCommandError(String, String),
+    #[cfg(all(feature = "agent", unix))]
+    #[error("cannot parse gpg-agent S-expression")]
+    ParseAgentSexpError,
+
+    #[cfg(feature = "card")]
+    #[error("cannot establish PC/SC context")]
+    EstablishCardContextError(#[source] crate::card::PcscError),
+    #[cfg(feature = "card")]
+    #[error("cannot list PC/SC readers")]
+    ListCardReadersError(#[source] crate::card::PcscError),
+    #[cfg(feature = "card")]
+    #[error("cannot find OpenPGP card")]
+    FindCardError,
+    #[cfg(feature = "card")]
+    #[error("cannot transmit APDU to OpenPGP card")]
+    TransmitCardError(#[source] crate::card::PcscError),
+    #[cfg(feature = "card")]
+    #[error("cannot execute OpenPGP card command: status {0:04X}")]
+    CardStatusError(u16),
+    #[cfg(feature = "card")]
+    #[error("cannot verify OpenPGP card PIN: {0} tries left")]
+    VerifyCardPinError(u8),
+    #[cfg(feature = "card")]
+    #[error("cannot get OpenPGP card PIN")]
+    GetCardPinError(#[source] std::io::Error),
+
+    #[cfg(any(all(feature = "agent", unix), feature = "card"))]
+    #[error("cannot find pgp public key for signing")]
+    FindPublicKeyForSigningError,
+    #[cfg(any(all(feature = "agent", unix), feature = "card"))]
+    #[error("cannot find pgp public key matching encrypted session keys")]
+    FindPublicKeyForDecryptionError,
+    #[cfg(any(all(feature = "agent", unix), feature = "card"))]
+    #[error("cannot use external secret key with pgp algorithm {0:?}")]
+    UnsupportedExternalAlgorithmError(native::crypto::public_key::PublicKeyAlgorithm),
+    #[cfg(any(all(feature = "agent", unix), feature = "card"))]
+    #[error("cannot build pgp signature from external secret key")]
+    BuildExternalSignatureError(#[source] native::errors::Error),
+    #[cfg(any(all(feature = "agent", unix), feature = "card"))]
+    #[error("cannot decrypt pgp session key using external secret key")]
+    DecryptExternalSessionKeyError(#[source] native::errors::Error),
+    #[cfg(any(all(feature = "agent", unix), feature = "card"))]
+    #[error("cannot decrypt pgp session key using external secret key: invalid session key")]
+    InvalidExternalSessionKeyError,
+    #[cfg(any(all(feature = "agent", unix), feature = "card"))]
+    #[error("cannot decrypt pgp message: message is not encrypted")]
+    GetMessageNotEncryptedError,
+
     #[cfg(feature = "tokio")]
     #[error(transparent)]
     JoinError(#[from] tokio::task::JoinError),
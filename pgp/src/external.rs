@@ -0,0 +1,312 @@
+//! # External
+//!
+//! Module dedicated to secret keys held by external devices, like
+//! gpg-agent or smartcards. These devices only expose low-level
+//! operations (signing hashes and decrypting session keys): this
+//! module builds and parses the surrounding PGP packets, using the
+//! public counterpart of the external secret keys.
+
+use std::io::Cursor;
+
+use chrono::{SubsecRound, Utc};
+use num_traits::FromPrimitive;
+use smallvec::SmallVec;
+use tracing::debug;
+
+use crate::{
+    encrypt::is_wildcard_key_id,
+    native::{
+        self,
+        crypto::{
+            aes_kw, checksum, ecdh::build_ecdh_param, hash::HashAlgorithm,
+            public_key::PublicKeyAlgorithm, sym::SymmetricKeyAlgorithm,
+        },
+        packet::{
+            PublicKeyEncryptedSessionKey, Signature, SignatureConfig, SignatureConfigBuilder,
+            SignatureType, Subpacket, SubpacketData,
+        },
+        types::{KeyTrait, Mpi, PublicParams, Tag},
+        Deserializable, Esk, Message, SignedPublicKey, StandaloneSignature,
+    },
+    Error, Result,
+};
+
+/// Signs the given bytes, delegating the signature of the hash to the
+/// given function.
+///
+/// The function receives the algorithm of the signing key and the
+/// SHA-256 hash to sign, and returns the signature MPIs.
+pub(crate) fn sign(
+    pkey: &SignedPublicKey,
+    plain_bytes: &[u8],
+    sign_hash: impl FnOnce(PublicKeyAlgorithm, &[u8]) -> Result<Vec<Mpi>>,
+) -> Result<Vec<u8>> {
+    let (algo, key_id, fingerprint) =
+        find_pkey_for_signing(pkey).ok_or(Error::FindPublicKeyForSigningError)?;
+
+    let config = SignatureConfigBuilder::default()
+        .typ(SignatureType::Binary)
+        .pub_alg(algo)
+        .hash_alg(HashAlgorithm::SHA2_256)
+        .hashed_subpackets(vec![
+            Subpacket::regular(SubpacketData::SignatureCreationTime(
+                Utc::now().trunc_subsecs(0),
+            )),
+            Subpacket::regular(SubpacketData::IssuerFingerprint(
+                Default::default(),
+                SmallVec::from_slice(&fingerprint),
+            )),
+        ])
+        .unhashed_subpackets(vec![Subpacket::regular(SubpacketData::Issuer(key_id))])
+        .build()
+        .map_err(Error::BuildExternalSignatureError)?;
+
+    let hash = hash_signature(&config, plain_bytes).map_err(Error::BuildExternalSignatureError)?;
+    let mpis = sign_hash(algo, &hash)?;
+
+    let sig = Signature::from_config(config, [hash[0], hash[1]], mpis);
+    let sig_bytes = StandaloneSignature::new(sig)
+        .to_armored_bytes(None)
+        .map_err(Error::ExportSignedMessageToArmoredBytesError)?;
+
+    Ok(sig_bytes)
+}
+
+/// Decrypts the given bytes, delegating the decryption of the session
+/// key to the given function.
+///
+/// The function receives the public parameters of the decryption key
+/// and the encrypted session key MPIs, and returns the decrypted
+/// value (see [`decode_session_key`]).
+pub(crate) fn decrypt(
+    pkey: &SignedPublicKey,
+    encrypted_bytes: &[u8],
+    mut decrypt_session_key: impl FnMut(&PublicParams, &[Mpi]) -> Result<Vec<u8>>,
+) -> Result<Vec<u8>> {
+    let (msg, _) = Message::from_armor_single(Cursor::new(encrypted_bytes))
+        .map_err(Error::ImportMessageFromArmorError)?;
+
+    let Message::Encrypted { esk, edata } = msg else {
+        return Err(Error::GetMessageNotEncryptedError);
+    };
+
+    let esks: Vec<_> = esk
+        .iter()
+        .filter_map(|esk| match esk {
+            Esk::PublicKeyEncryptedSessionKey(esk) => Some(esk),
+            Esk::SymKeyEncryptedSessionKey(_) => None,
+        })
+        .collect();
+
+    // session keys addressed to the key come first, then session
+    // keys of hidden recipients, which need to be tried one by one
+    let addressed = esks.iter().filter_map(|esk| {
+        let (params, fingerprint) = find_pkey_for_decryption(pkey, esk)?;
+        Some((esk, params, fingerprint))
+    });
+    let hidden = esks
+        .iter()
+        .filter(|esk| is_wildcard_key_id(esk.id()))
+        .filter_map(|esk| {
+            let (params, fingerprint) = find_pkey_for_encryption(pkey)?;
+            Some((esk, params, fingerprint))
+        });
+    let mut candidates = addressed.chain(hidden).peekable();
+
+    if candidates.peek().is_none() {
+        return Err(Error::FindPublicKeyForDecryptionError);
+    }
+
+    let mut session_key = Err(Error::FindPublicKeyForDecryptionError);
+
+    for (esk, params, fingerprint) in candidates {
+        session_key = decrypt_session_key(params, esk.mpis())
+            .and_then(|value| decode_session_key(params, &fingerprint, esk.mpis(), value));
+
+        match &session_key {
+            Ok(_) => break,
+            Err(err) => debug!(?err, "cannot decrypt session key, trying next one"),
+        }
+    }
+
+    let (key, alg) = session_key?;
+
+    let edata = edata.first().ok_or(Error::GetMessageEmptyError)?;
+    let mut data = edata.data().to_vec();
+    let data = if edata.tag() == Tag::SymEncryptedProtectedData {
+        alg.decrypt_protected(&key, &mut data)
+    } else {
+        alg.decrypt(&key, &mut data)
+    }
+    .map_err(Error::DecryptMessageError)?;
+
+    let msg = Message::from_bytes_many(Cursor::new(data.to_vec()))
+        .next()
+        .ok_or(Error::GetMessageEmptyError)?
+        .map_err(Error::DecryptMessageError)?;
+    let msg = msg.decompress().map_err(Error::DecompressMessageError)?;
+
+    let plain_bytes = msg
+        .get_content()
+        .map_err(Error::GetMessageContentError)?
+        .ok_or(Error::GetMessageContentEmptyError)?;
+
+    Ok(plain_bytes)
+}
+
+/// Finds the algorithm, the key id and the fingerprint of the primary
+/// key or subkey to use for signing.
+///
+/// First tries the primary key, then subkeys.
+fn find_pkey_for_signing(
+    pkey: &SignedPublicKey,
+) -> Option<(PublicKeyAlgorithm, native::types::KeyId, Vec<u8>)> {
+    if pkey.is_signing_key() {
+        Some((pkey.algorithm(), pkey.key_id(), pkey.fingerprint()))
+    } else {
+        pkey.public_subkeys
+            .iter()
+            .find(|subkey| subkey.is_signing_key())
+            .map(|subkey| (subkey.algorithm(), subkey.key_id(), subkey.fingerprint()))
+    }
+}
+
+/// Finds the public parameters and the fingerprint of the primary key
+/// or subkey the given session key packet is addressed to.
+fn find_pkey_for_decryption<'a>(
+    pkey: &'a SignedPublicKey,
+    esk: &PublicKeyEncryptedSessionKey,
+) -> Option<(&'a PublicParams, Vec<u8>)> {
+    if &pkey.key_id() == esk.id() {
+        return Some((pkey.primary_key.public_params(), pkey.fingerprint()));
+    }
+
+    pkey.public_subkeys
+        .iter()
+        .find(|subkey| &subkey.key_id() == esk.id())
+        .map(|subkey| (subkey.key.public_params(), subkey.fingerprint()))
+}
+
+/// Finds the public parameters and the fingerprint of the primary key
+/// or subkey to use for encryption, used to decrypt session keys of
+/// hidden recipients.
+fn find_pkey_for_encryption(pkey: &SignedPublicKey) -> Option<(&PublicParams, Vec<u8>)> {
+    if pkey.is_encryption_key() {
+        Some((pkey.primary_key.public_params(), pkey.fingerprint()))
+    } else {
+        pkey.public_subkeys
+            .iter()
+            .find(|subkey| subkey.is_encryption_key())
+            .map(|subkey| (subkey.key.public_params(), subkey.fingerprint()))
+    }
+}
+
+/// Computes the hash of the given data to sign, including the
+/// signature trailer.
+fn hash_signature(config: &SignatureConfig, data: &[u8]) -> native::errors::Result<Vec<u8>> {
+    let mut hasher = config.hash_alg.new_hasher()?;
+    hasher.update(data);
+    let len = config.hash_signature_data(&mut *hasher)?;
+    hasher.update(&config.trailer(len));
+    Ok(hasher.finish())
+}
+
+/// Decodes the session key from the given decrypted value.
+///
+/// For RSA keys, the value is the session key, optionally framed
+/// with its PKCS#1 padding. For ECDH keys, the value is the shared
+/// secret, which needs to be derived then used to unwrap the session
+/// key (see RFC 6637).
+fn decode_session_key(
+    params: &PublicParams,
+    fingerprint: &[u8],
+    mpis: &[Mpi],
+    value: Vec<u8>,
+) -> Result<(Vec<u8>, SymmetricKeyAlgorithm)> {
+    let dek = match params {
+        PublicParams::RSA { .. } => {
+            let mut frame = value.as_slice();
+
+            // strip the PKCS#1 padding when the device did not
+            if frame.len() > 64 {
+                if frame.first() == Some(&0) {
+                    frame = &frame[1..];
+                }
+                let pos = frame
+                    .iter()
+                    .skip(1)
+                    .position(|byte| *byte == 0)
+                    .ok_or(Error::InvalidExternalSessionKeyError)?;
+                frame = &frame[pos + 2..];
+            }
+
+            frame.to_vec()
+        }
+        PublicParams::ECDH {
+            curve,
+            hash,
+            alg_sym,
+            ..
+        } => {
+            // the shared secret may be given as a point, prefixed by
+            // 0x40 for Curve25519 and by 0x04 for NIST curves
+            let x = match value.split_first() {
+                Some((0x40, x)) if x.len() == 32 => x,
+                Some((0x04, xy)) if xy.len() % 2 == 0 => &xy[..xy.len() / 2],
+                _ => &value[..],
+            };
+
+            let param = build_ecdh_param(&curve.oid(), *alg_sym, *hash, fingerprint);
+            let mut z = hash
+                .digest(&[&[0, 0, 0, 1], x, &param].concat())
+                .map_err(Error::DecryptExternalSessionKeyError)?;
+            z.truncate(alg_sym.key_size());
+
+            let wrapped = ecdh_wrapped_key(mpis)?;
+            let mut dek =
+                aes_kw::unwrap(&z, &wrapped).map_err(Error::DecryptExternalSessionKeyError)?;
+
+            // PKCS#5 unpadding
+            let pad = *dek.last().ok_or(Error::InvalidExternalSessionKeyError)? as usize;
+            if pad == 0 || pad > dek.len() {
+                return Err(Error::InvalidExternalSessionKeyError);
+            }
+            dek.truncate(dek.len() - pad);
+            dek
+        }
+        _ => return Err(Error::InvalidExternalSessionKeyError),
+    };
+
+    // the session key is made of the algorithm identifier, the key
+    // itself and a two-octet checksum
+    if dek.len() < 4 {
+        return Err(Error::InvalidExternalSessionKeyError);
+    }
+
+    let alg =
+        SymmetricKeyAlgorithm::from_u8(dek[0]).ok_or(Error::InvalidExternalSessionKeyError)?;
+    let (key, sum) = dek[1..].split_at(dek.len() - 3);
+    checksum::simple(sum, key).map_err(Error::DecryptExternalSessionKeyError)?;
+
+    Ok((key.to_vec(), alg))
+}
+
+/// Returns the wrapped session key of the given ECDH encrypted
+/// session key, restoring its leading zeros.
+pub(crate) fn ecdh_wrapped_key(mpis: &[Mpi]) -> Result<Vec<u8>> {
+    match mpis {
+        [_, len, wrapped] => {
+            let len = len.first().copied().unwrap_or_default() as usize;
+            let wrapped = wrapped.as_bytes();
+
+            if wrapped.len() > len {
+                return Err(Error::InvalidExternalSessionKeyError);
+            }
+
+            let mut padded = vec![0; len - wrapped.len()];
+            padded.extend(wrapped);
+            Ok(padded)
+        }
+        _ => Err(Error::InvalidExternalSessionKeyError),
+    }
+}
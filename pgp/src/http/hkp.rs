@@ -1,8 +1,8 @@
 //! # HKP key discovery
 //!
 //! Module dedicated to HTTP Keyserver Protocol. Since HKP is just
-//! HTTP, this module only contains a function that formats a given
-//! URI to match [HKP specs].
+//! HTTP, this module only contains functions that format a given URI
+//! to match [HKP specs], for both key lookup and key submission.
 //!
 //! [HKP specs]: https://datatracker.ietf.org/doc/html/draft-shaw-openpgp-hkp-00
 
@@ -12,19 +12,32 @@
 
 /// Formats the given URI to match the HKP specs.
 ///
-/// It basically adds `/pks` plus few query params.
+/// It basically adds `/pks` plus few query params. The search can be
+/// an email address or a fingerprint prefixed by `0x`.
 pub(crate) fn format_key_server_uri(uri: Uri, email: &str) -> Result<Uri> {
+    format_pks_uri(uri, &format!("pks/lookup?op=get&search={email}"))
+}
+
+/// Formats the given URI to match the HKP key submission specs.
+///
+/// It basically adds `/pks/add`. Keys need to be posted as a form,
+/// using the `keytext` field.
+pub(crate) fn format_key_server_add_uri(uri: Uri) -> Result<Uri> {
+    format_pks_uri(uri, "pks/add")
+}
+
+/// Builds the HTTP URI of the given `pks` path.
+fn format_pks_uri(uri: Uri, pks_path: &str) -> Result<Uri> {
     let authority = uri.host().unwrap_or("localhost");
     let scheme = match uri.scheme_str() {
         Some("hkps") => "https",
         _ => "http",
     };
 
-    let pks_path = format!("pks/lookup?op=get&search={email}");
     let path = if uri.path().is_empty() {
-        String::from("/") + &pks_path
+        String::from("/") + pks_path
     } else {
-        uri.path().to_owned() + &pks_path
+        uri.path().to_owned() + pks_path
     };
 
     let uri = Uri::builder()
@@ -2,7 +2,9 @@
 //!
 //! Module dedicated to HTTP public key discovery. The main purpose of
 //! this module is to get public keys belonging to given emails by
-//! contacting key servers.
+//! contacting key servers. It can also publish public keys to HKP
+//! key servers, and refresh known public keys in order to detect
+//! revocations and expirations.
 
 pub mod hkp;
 pub mod wkd;
@@ -17,21 +19,33 @@
 use tracing::{debug, warn};
 
 use crate::{
-    native::{Deserializable, SignedPublicKey},
+    key::{key_status, KeyStatus},
+    native::{types::KeyTrait, Deserializable, SignedPublicKey},
     utils::spawn,
     Error, Result,
 };
 
-/// Calls the given key server in order to get the public key
-/// belonging to the given email address.
-async fn fetch(client: &http::Client, email: &str, key_server: &str) -> Result<SignedPublicKey> {
+/// A public key refreshed from key servers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RefreshedKey {
+    /// The public key as returned by the key server.
+    pub pkey: SignedPublicKey,
+
+    /// The status of the refreshed public key.
+    pub status: KeyStatus,
+}
+
+/// Calls the given key server in order to get the public key matching
+/// the given search, which can be either an email address or a
+/// fingerprint prefixed by `0x`.
+async fn fetch(client: &http::Client, search: &str, key_server: &str) -> Result<SignedPublicKey> {
     let uri: Uri = key_server
-        .replace("<email>", email)
+        .replace("<email>", search)
         .parse()
         .map_err(http::Error::from)?;
 
     let uri = match uri.scheme_str() {
-        Some("hkp") | Some("hkps") => hkp::format_key_server_uri(uri, email).unwrap(),
+        Some("hkp") | Some("hkps") => hkp::format_key_server_uri(uri, search).unwrap(),
         // TODO: manage file scheme
         _ => uri,
     };
@@ -90,6 +104,138 @@ async fn get(
     Err(Error::FindPublicKeyError(email.to_owned()))
 }
 
+/// Sends the given armored public key to the given HKP key server.
+async fn upload(client: &http::Client, armored: &str, key_server: &str) -> Result<()> {
+    let uri: Uri = key_server.parse().map_err(http::Error::from)?;
+
+    let uri = match uri.scheme_str() {
+        Some("hkp") | Some("hkps") => hkp::format_key_server_add_uri(uri)?,
+        _ => {
+            return Err(Error::PublishPublicKeyUnsupportedError(
+                key_server.to_owned(),
+            ))
+        }
+    };
+
+    let uri_clone = uri.clone();
+    let form = [("keytext", armored.to_owned())];
+    let res = client
+        .send(move |agent| agent.post(uri_clone).send_form(form))
+        .await?;
+
+    let status = res.status();
+
+    if !status.is_success() {
+        let mut err = String::new();
+        res.into_body()
+            .as_reader()
+            .read_to_string(&mut err)
+            .map_err(|err| Error::ReadHttpError(err, uri.clone(), status))?;
+        return Err(Error::PublishPublicKeyError(err, uri, status));
+    }
+
+    Ok(())
+}
+
+/// Publishes the given public key to the first HKP key server
+/// accepting it.
+///
+/// Key servers are tried in order, which allows to use a list of
+/// servers as a pool with fallbacks. Returns the key server the
+/// public key has been published to.
+pub async fn publish(pkey: SignedPublicKey, key_servers: Vec<String>) -> Result<String> {
+    let armored = crate::export_public(pkey).await?;
+    let client = http::Client::new();
+
+    for key_server in key_servers {
+        match upload(&client, &armored, &key_server).await {
+            Ok(()) => {
+                debug!("published pgp public key to {key_server}");
+                return Ok(key_server);
+            }
+            Err(err) => {
+                let msg = format!("cannot publish pgp public key to {key_server}");
+                warn!("{msg}: {err}");
+                debug!("{msg}: {err:?}");
+                continue;
+            }
+        }
+    }
+
+    Err(Error::PublishPublicKeyNoKeyServerError)
+}
+
+/// Re-fetches the given public key from the given key servers, using
+/// its fingerprint.
+async fn refresh(client: &http::Client, fpr: &str, key_servers: &[String]) -> Result<RefreshedKey> {
+    let search = format!("0x{fpr}");
+
+    for key_server in key_servers {
+        match fetch(client, &search, key_server).await {
+            Ok(pkey) if format_fingerprint(&pkey) == fpr => {
+                debug!("refreshed pgp public key {fpr} from {key_server}");
+                let status = key_status(&pkey);
+                return Ok(RefreshedKey { pkey, status });
+            }
+            Ok(_) => {
+                debug!("skipping pgp public key from {key_server}: fingerprint mismatch");
+                continue;
+            }
+            Err(err) => {
+                let msg = format!("cannot refresh pgp public key {fpr} from {key_server}");
+                warn!("{msg}: {err}");
+                debug!("{msg}: {err:?}");
+                continue;
+            }
+        }
+    }
+
+    Err(Error::RefreshPublicKeyError(fpr.to_owned()))
+}
+
+/// Re-fetches the given known public keys from the given key servers,
+/// in order to record their revocations and expirations.
+///
+/// Results are indexed by the hexadecimal fingerprint of the known
+/// public keys.
+pub async fn refresh_keys(
+    pkeys: Vec<SignedPublicKey>,
+    key_servers: Vec<String>,
+) -> Vec<(String, Result<RefreshedKey>)> {
+    let key_servers = Arc::new(key_servers);
+    let client = http::Client::new();
+
+    FuturesUnordered::from_iter(pkeys.into_iter().map(|pkey| {
+        let key_servers = key_servers.clone();
+        let client = client.clone();
+        let fpr = format_fingerprint(&pkey);
+        spawn(async move {
+            let res = self::refresh(&client, &fpr, &key_servers).await;
+            (fpr, res)
+        })
+    }))
+    .filter_map(|res| async {
+        match res {
+            Ok(res) => Some(res),
+            Err(err) => {
+                debug!(?err, "skipping failed task");
+                None
+            }
+        }
+    })
+    .collect()
+    .await
+}
+
+/// Formats the fingerprint of the given public key as an uppercase
+/// hexadecimal string.
+fn format_fingerprint(pkey: &SignedPublicKey) -> String {
+    pkey.fingerprint()
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect()
+}
+
 /// Gets public key associated to the given email.
 pub async fn get_one(email: String, key_servers: Vec<String>) -> Result<SignedPublicKey> {
     let client = http::Client::new();
@@ -0,0 +1,393 @@
+//! # Key
+//!
+//! Module dedicated to PGP key management. It exposes functions to
+//! generate, export, import and revoke keys, so that keys can be
+//! provisioned without relying on external tools like GnuPG.
+//!
+//! Secret keys can also be stored in and read from the user's global
+//! keyring (requires the `keyring` cargo feature).
+
+use std::{io::Cursor, time::Duration};
+
+use bstr::BString;
+use chrono::{DateTime, SubsecRound, Utc};
+use smallvec::{smallvec, SmallVec};
+
+use crate::{
+    native::{
+        self,
+        crypto::{hash::HashAlgorithm, sym::SymmetricKeyAlgorithm},
+        from_armor_many,
+        packet::{
+            PacketTrait, RevocationCode, Signature, SignatureConfigBuilder, SignatureType,
+            Subpacket, SubpacketData,
+        },
+        types::{CompressionAlgorithm, KeyTrait, PublicKeyTrait, SecretKeyTrait},
+        KeyType, PublicOrSecret, SecretKeyParamsBuilder, SignedPublicKey, SignedPublicSubKey,
+        SignedSecretKey, SubkeyParamsBuilder,
+    },
+    utils::spawn_blocking,
+    Error, Result,
+};
+
+/// The algorithm of generated keys.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum KeyAlgorithm {
+    /// EdDSA primary key for signing and ECDH subkey for encrypting,
+    /// both using Curve25519.
+    #[default]
+    Ed25519,
+
+    /// RSA primary key and subkey of the given bit size.
+    Rsa(u32),
+}
+
+impl KeyAlgorithm {
+    fn primary_key_type(&self) -> KeyType {
+        match self {
+            Self::Ed25519 => KeyType::EdDSA,
+            Self::Rsa(bits) => KeyType::Rsa(*bits),
+        }
+    }
+
+    fn subkey_type(&self) -> KeyType {
+        match self {
+            Self::Ed25519 => KeyType::ECDH,
+            Self::Rsa(bits) => KeyType::Rsa(*bits),
+        }
+    }
+}
+
+/// A key imported from an armored string.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ImportedKey {
+    /// The imported key is a public key.
+    Public(SignedPublicKey),
+
+    /// The imported key is a secret key.
+    Secret(SignedSecretKey),
+}
+
+/// The status of a public key.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum KeyStatus {
+    /// The key can be used.
+    Valid,
+
+    /// The key expired at the given date.
+    Expired(DateTime<Utc>),
+
+    /// The key has been revoked, with an optional reason.
+    Revoked(Option<String>),
+}
+
+/// Returns the status of the given public key.
+///
+/// Only revocation signatures issued by the key itself are taken
+/// into account.
+pub fn key_status(pkey: &SignedPublicKey) -> KeyStatus {
+    let revocation = pkey
+        .details
+        .revocation_signatures
+        .iter()
+        .find(|sig| sig.verify_key(&pkey.primary_key).is_ok());
+
+    if let Some(sig) = revocation {
+        let reason = sig.revocation_reason_string().map(ToString::to_string);
+        return KeyStatus::Revoked(reason);
+    }
+
+    match pkey.expires_at() {
+        Some(date) if date <= Utc::now() => KeyStatus::Expired(date),
+        _ => KeyStatus::Valid,
+    }
+}
+
+/// Generates a new pair of secret and public keys for the given user
+/// id, using the given algorithm and passphrase.
+///
+/// The user id is usually made of a name and an email address, for
+/// example `Alice <alice@localhost>`. When an expiry is given, the
+/// key expires after the given duration starting from now.
+pub async fn generate_key(
+    user_id: impl ToString,
+    algo: KeyAlgorithm,
+    expiry: Option<Duration>,
+    passphrase: impl ToString,
+) -> Result<(SignedSecretKey, SignedPublicKey)> {
+    let user_id = user_id.to_string();
+    let passphrase = passphrase.to_string();
+    let passphrase = if passphrase.trim().is_empty() {
+        None
+    } else {
+        Some(passphrase)
+    };
+
+    spawn_blocking(move || {
+        let key_params = SecretKeyParamsBuilder::default()
+            .key_type(algo.primary_key_type())
+            .can_create_certificates(true)
+            .can_sign(true)
+            .primary_user_id(user_id)
+            .passphrase(passphrase.clone())
+            .preferred_symmetric_algorithms(smallvec![SymmetricKeyAlgorithm::AES256])
+            .preferred_hash_algorithms(smallvec![HashAlgorithm::SHA2_256])
+            .preferred_compression_algorithms(smallvec![CompressionAlgorithm::ZLIB])
+            .subkey(
+                SubkeyParamsBuilder::default()
+                    .key_type(algo.subkey_type())
+                    .can_encrypt(true)
+                    .passphrase(passphrase.clone())
+                    .build()
+                    .map_err(Error::BuildPublicKeyParamsError)?,
+            )
+            .build()
+            .map_err(Error::BuildSecretKeyParamsError)?;
+
+        let passphrase = passphrase.unwrap_or_default();
+
+        let skey = key_params
+            .generate()
+            .map_err(Error::GenerateSecretKeyError)?;
+        let mut skey = skey
+            .sign(|| passphrase.clone())
+            .map_err(Error::SignSecretKeyError)?;
+
+        if let Some(expiry) = expiry {
+            set_expiry(&mut skey, expiry, passphrase).map_err(Error::SetKeyExpiryError)?;
+        }
+
+        skey.verify().map_err(Error::VerifySecretKeyError)?;
+
+        let pkey = to_signed_public_key(&skey);
+        pkey.verify().map_err(Error::VerifyPublicKeyError)?;
+
+        Ok((skey, pkey))
+    })
+    .await?
+}
+
+/// Exports the given public key as an armored string.
+pub async fn export_public(pkey: SignedPublicKey) -> Result<String> {
+    spawn_blocking(move || {
+        pkey.to_armored_string(None)
+            .map_err(Error::ExportPublicKeyError)
+    })
+    .await?
+}
+
+/// Exports the given secret key as an armored string.
+///
+/// The secret key material stays protected by its passphrase, if
+/// any.
+pub async fn export_secret(skey: SignedSecretKey) -> Result<String> {
+    spawn_blocking(move || {
+        skey.to_armored_string(None)
+            .map_err(Error::ExportSecretKeyError)
+    })
+    .await?
+}
+
+/// Imports all the public and secret keys contained in the given
+/// armored string.
+pub async fn import(armored: impl ToString) -> Result<Vec<ImportedKey>> {
+    let armored = armored.to_string();
+
+    spawn_blocking(move || {
+        let (keys, _) = from_armor_many(Cursor::new(armored)).map_err(Error::ImportKeysError)?;
+
+        keys.map(|key| match key.map_err(Error::ImportKeysError)? {
+            PublicOrSecret::Public(pkey) => Ok(ImportedKey::Public(pkey)),
+            PublicOrSecret::Secret(skey) => Ok(ImportedKey::Secret(skey)),
+        })
+        .collect()
+    })
+    .await?
+}
+
+/// Revokes the given secret key.
+///
+/// Returns the public key containing the revocation signature, which
+/// needs to be published (for example on key servers) so that peers
+/// stop using the key.
+pub async fn revoke(
+    skey: SignedSecretKey,
+    passphrase: impl ToString,
+    code: RevocationCode,
+    reason: impl ToString,
+) -> Result<SignedPublicKey> {
+    let passphrase = passphrase.to_string();
+    let reason = reason.to_string();
+
+    spawn_blocking(move || {
+        let sig =
+            sign_revocation(&skey, passphrase, code, reason).map_err(Error::RevokeKeyError)?;
+        let mut pkey = to_signed_public_key(&skey);
+        pkey.details.revocation_signatures.push(sig);
+        Ok(pkey)
+    })
+    .await?
+}
+
+/// Stores the given secret key as an armored string in the given
+/// keyring entry.
+#[cfg(feature = "keyring")]
+pub async fn write_skey_to_keyring(
+    entry: &keyring::KeyringEntry,
+    skey: SignedSecretKey,
+) -> Result<()> {
+    let armored = export_secret(skey).await?;
+    entry
+        .set_secret(armored)
+        .await
+        .map_err(Error::SetSecretKeyIntoKeyringError)?;
+    Ok(())
+}
+
+/// Reads the armored secret key stored in the given keyring entry.
+#[cfg(feature = "keyring")]
+pub async fn read_skey_from_keyring(entry: &keyring::KeyringEntry) -> Result<SignedSecretKey> {
+    let armored = entry
+        .get_secret()
+        .await
+        .map_err(Error::GetSecretKeyFromKeyringError)?;
+    crate::read_skey_from_string(armored).await
+}
+
+/// Builds the signed public key of the given signed secret key,
+/// reusing its existing signatures.
+pub fn to_signed_public_key(skey: &SignedSecretKey) -> SignedPublicKey {
+    let mut subkeys = skey.public_subkeys.clone();
+
+    subkeys.extend(
+        skey.secret_subkeys.iter().map(|subkey| {
+            SignedPublicSubKey::new(subkey.key.public_key(), subkey.signatures.clone())
+        }),
+    );
+
+    SignedPublicKey::new(skey.primary_key.public_key(), skey.details.clone(), subkeys)
+}
+
+/// Re-signs the user ids of the given secret key with the given
+/// expiry.
+///
+/// Version 4 keys store their expiry in the self-signatures of their
+/// user ids, as an offset from the creation date of the key.
+fn set_expiry(
+    skey: &mut SignedSecretKey,
+    expiry: Duration,
+    passphrase: String,
+) -> native::errors::Result<()> {
+    let created_at = skey.primary_key.created_at().timestamp();
+    let offset = Utc::now().timestamp() - created_at + expiry.as_secs() as i64;
+    let expiry = DateTime::from_timestamp(offset, 0).unwrap_or_default();
+
+    let mut users = std::mem::take(&mut skey.details.users);
+
+    for user in &mut users {
+        for sig in &mut user.signatures {
+            let mut config = sig.config.clone();
+            config
+                .hashed_subpackets
+                .retain(|p| !matches!(p.data, SubpacketData::KeyExpirationTime(_)));
+            config
+                .hashed_subpackets
+                .push(Subpacket::regular(SubpacketData::KeyExpirationTime(expiry)));
+
+            *sig =
+                config.sign_certificate(&*skey, || passphrase.clone(), user.id.tag(), &user.id)?;
+        }
+    }
+
+    skey.details.users = users;
+    Ok(())
+}
+
+/// Builds the key revocation signature of the given secret key.
+fn sign_revocation(
+    skey: &SignedSecretKey,
+    passphrase: String,
+    code: RevocationCode,
+    reason: String,
+) -> native::errors::Result<Signature> {
+    let config = SignatureConfigBuilder::default()
+        .typ(SignatureType::KeyRevocation)
+        .pub_alg(skey.algorithm())
+        .hash_alg(HashAlgorithm::SHA2_256)
+        .hashed_subpackets(vec![
+            Subpacket::regular(SubpacketData::SignatureCreationTime(
+                Utc::now().trunc_subsecs(0),
+            )),
+            Subpacket::regular(SubpacketData::IssuerFingerprint(
+                Default::default(),
+                SmallVec::from_slice(&skey.fingerprint()),
+            )),
+            Subpacket::regular(SubpacketData::RevocationReason(code, BString::from(reason))),
+        ])
+        .unhashed_subpackets(vec![Subpacket::regular(SubpacketData::Issuer(
+            skey.key_id(),
+        ))])
+        .build()?;
+
+    // key revocations are computed over the primary key only, see
+    // RFC 4880 section 5.2.4
+    let mut hasher = config.hash_alg.new_hasher()?;
+    let mut key_buf = Vec::new();
+    skey.to_writer_old(&mut key_buf)?;
+    hasher.update(&key_buf);
+    let len = config.hash_signature_data(&mut *hasher)?;
+    hasher.update(&config.trailer(len));
+    let hash = hasher.finish();
+
+    let signed_hash_value = [hash[0], hash[1]];
+    let sig = skey.create_signature(|| passphrase, config.hash_alg, &hash)?;
+
+    Ok(Signature::from_config(config, signed_hash_value, sig))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::Utc;
+
+    use crate::native::packet::RevocationCode;
+
+    use super::{
+        export_public, export_secret, generate_key, import, key_status, revoke, ImportedKey,
+        KeyAlgorithm, KeyStatus,
+    };
+
+    #[tokio::test]
+    async fn generate_export_import_revoke() {
+        let (skey, pkey) = generate_key(
+            "Alice <alice@localhost>",
+            KeyAlgorithm::Ed25519,
+            Some(Duration::from_secs(3600)),
+            "",
+        )
+        .await
+        .unwrap();
+
+        assert!(pkey.expires_at().unwrap() > Utc::now());
+        assert_eq!(key_status(&pkey), KeyStatus::Valid);
+
+        let armored = export_public(pkey.clone()).await.unwrap();
+        let keys = import(armored).await.unwrap();
+        assert_eq!(keys, vec![ImportedKey::Public(pkey)]);
+
+        let armored = export_secret(skey.clone()).await.unwrap();
+        let keys = import(armored).await.unwrap();
+        assert_eq!(keys, vec![ImportedKey::Secret(skey.clone())]);
+
+        let revoked = revoke(skey, "", RevocationCode::KeyRetired, "retired")
+            .await
+            .unwrap();
+        let sig = &revoked.details.revocation_signatures[0];
+        sig.verify_key(&revoked.primary_key).unwrap();
+        assert_eq!(
+            key_status(&revoked),
+            KeyStatus::Revoked(Some(String::from("retired"))),
+        );
+    }
+}
@@ -1,28 +1,44 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 #![doc = include_str!("../README.md")]
 
+#[cfg(all(feature = "agent", unix))]
+pub mod agent;
+#[cfg(feature = "card")]
+pub mod card;
 pub mod decrypt;
 pub mod encrypt;
 mod error;
+#[cfg(any(all(feature = "agent", unix), feature = "card"))]
+mod external;
 #[cfg(feature = "key-discovery")]
 pub mod http;
+pub mod key;
+#[cfg(feature = "card")]
+mod pcsc;
 pub mod sign;
 pub mod utils;
 pub mod verify;
 
 pub use pgp_native as native;
 
+#[cfg(feature = "keyring")]
+#[doc(inline)]
+pub use crate::key::{read_skey_from_keyring, write_skey_to_keyring};
 #[doc(inline)]
 pub use crate::{
     decrypt::decrypt,
-    encrypt::encrypt,
+    encrypt::{encrypt, encrypt_with_hidden_recipients},
     error::{Error, Result},
+    key::{
+        export_public, export_secret, generate_key, import, key_status, revoke,
+        to_signed_public_key, ImportedKey, KeyAlgorithm, KeyStatus,
+    },
     sign::sign,
     utils::{
         gen_key_pair, read_pkey_from_path, read_sig_from_bytes, read_skey_from_file,
         read_skey_from_string,
     },
-    verify::verify,
+    verify::{verify, verify_with_report, VerificationReport},
 };
 
 #[cfg(feature = "key-discovery")]
@@ -0,0 +1,252 @@
+//! # PC/SC
+//!
+//! Module dedicated to the minimal PC/SC bindings used to talk to
+//! OpenPGP cards. Bindings link against the system PC/SC
+//! implementation: pcsc-lite on Unix, the PCSC framework on macOS
+//! and WinSCard on Windows.
+
+use std::{
+    ffi::{c_char, c_void, CStr, CString},
+    fmt, mem, ptr,
+    sync::Arc,
+};
+
+#[cfg(not(any(windows, target_os = "macos")))]
+mod sys {
+    use std::ffi::{c_long, c_ulong};
+
+    pub type Dword = c_ulong;
+    pub type Long = c_long;
+    pub type Handle = c_long;
+}
+
+#[cfg(target_os = "macos")]
+mod sys {
+    pub type Dword = u32;
+    pub type Long = i32;
+    pub type Handle = i32;
+}
+
+#[cfg(windows)]
+mod sys {
+    pub type Dword = u32;
+    pub type Long = i32;
+    pub type Handle = usize;
+}
+
+use sys::{Dword, Handle, Long};
+
+/// The maximum size of an extended APDU response.
+pub const MAX_BUFFER_SIZE_EXTENDED: usize = 4 + 3 + (1 << 16) + 3 + 2;
+
+const SCARD_S_SUCCESS: Long = 0;
+const SCARD_E_NO_READERS_AVAILABLE: u32 = 0x8010002E;
+const SCARD_SCOPE_USER: Dword = 0;
+const SCARD_SHARE_SHARED: Dword = 2;
+const SCARD_PROTOCOL_T0: Dword = 1;
+const SCARD_PROTOCOL_T1: Dword = 2;
+const SCARD_LEAVE_CARD: Dword = 0;
+
+#[repr(C)]
+struct IoRequest {
+    protocol: Dword,
+    pci_len: Dword,
+}
+
+#[cfg_attr(not(any(windows, target_os = "macos")), link(name = "pcsclite"))]
+#[cfg_attr(target_os = "macos", link(name = "PCSC", kind = "framework"))]
+#[cfg_attr(windows, link(name = "winscard"))]
+extern "system" {
+    fn SCardEstablishContext(
+        scope: Dword,
+        reserved1: *const c_void,
+        reserved2: *const c_void,
+        ctx: *mut Handle,
+    ) -> Long;
+
+    fn SCardReleaseContext(ctx: Handle) -> Long;
+
+    #[cfg_attr(windows, link_name = "SCardListReadersA")]
+    fn SCardListReaders(
+        ctx: Handle,
+        groups: *const c_char,
+        readers: *mut c_char,
+        readers_len: *mut Dword,
+    ) -> Long;
+
+    #[cfg_attr(windows, link_name = "SCardConnectA")]
+    fn SCardConnect(
+        ctx: Handle,
+        reader: *const c_char,
+        share_mode: Dword,
+        preferred_protocols: Dword,
+        card: *mut Handle,
+        active_protocol: *mut Dword,
+    ) -> Long;
+
+    fn SCardDisconnect(card: Handle, disposition: Dword) -> Long;
+
+    fn SCardTransmit(
+        card: Handle,
+        send_pci: *const IoRequest,
+        send_buf: *const u8,
+        send_len: Dword,
+        recv_pci: *mut IoRequest,
+        recv_buf: *mut u8,
+        recv_len: *mut Dword,
+    ) -> Long;
+}
+
+/// The PC/SC error, wrapping the status code returned by the PC/SC
+/// implementation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PcscError(pub u32);
+
+impl fmt::Display for PcscError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.0 {
+            0x80100002 => "the action was cancelled",
+            0x80100009 => "the reader is unknown",
+            0x8010000B => "the card is used by another application",
+            0x8010000C => "no card in the reader",
+            0x8010000F => "the card protocol is not supported",
+            0x8010001D => "the PC/SC service is not available",
+            SCARD_E_NO_READERS_AVAILABLE => "no reader available",
+            0x80100069 => "the card has been removed",
+            _ => "unknown error",
+        };
+
+        write!(f, "{reason} (PC/SC status 0x{:08X})", self.0)
+    }
+}
+
+impl std::error::Error for PcscError {}
+
+/// Turns the given PC/SC status code into a result.
+fn check(code: Long) -> Result<(), PcscError> {
+    if code == SCARD_S_SUCCESS {
+        Ok(())
+    } else {
+        Err(PcscError(code as u32))
+    }
+}
+
+/// The PC/SC context handle, released when dropped.
+struct ContextHandle(Handle);
+
+impl Drop for ContextHandle {
+    fn drop(&mut self) {
+        unsafe { SCardReleaseContext(self.0) };
+    }
+}
+
+/// The PC/SC context.
+///
+/// The context is shared with the cards it connects to, since
+/// releasing it invalidates their handles.
+pub struct Context(Arc<ContextHandle>);
+
+impl Context {
+    /// Establishes a new PC/SC context in the user scope.
+    pub fn establish() -> Result<Self, PcscError> {
+        let mut ctx: Handle = 0;
+        check(unsafe {
+            SCardEstablishContext(SCARD_SCOPE_USER, ptr::null(), ptr::null(), &mut ctx)
+        })?;
+        Ok(Self(Arc::new(ContextHandle(ctx))))
+    }
+
+    /// Lists the names of the readers connected to the system.
+    pub fn list_readers(&self) -> Result<Vec<CString>, PcscError> {
+        let ctx = self.0 .0;
+        let mut len: Dword = 0;
+
+        match check(unsafe { SCardListReaders(ctx, ptr::null(), ptr::null_mut(), &mut len) }) {
+            Err(PcscError(SCARD_E_NO_READERS_AVAILABLE)) => return Ok(Vec::new()),
+            res => res?,
+        }
+
+        let mut buf = vec![0u8; len as usize];
+
+        match check(unsafe {
+            SCardListReaders(ctx, ptr::null(), buf.as_mut_ptr().cast(), &mut len)
+        }) {
+            Err(PcscError(SCARD_E_NO_READERS_AVAILABLE)) => return Ok(Vec::new()),
+            res => res?,
+        }
+
+        // readers are returned as a multi-string: NUL-separated
+        // names, terminated by an empty name
+        buf.truncate(len as usize);
+        let readers = buf
+            .split(|byte| *byte == 0)
+            .filter(|name| !name.is_empty())
+            .filter_map(|name| CString::new(name).ok())
+            .collect();
+
+        Ok(readers)
+    }
+
+    /// Connects to the card inserted in the given reader, in shared
+    /// mode.
+    pub fn connect(&self, reader: &CStr) -> Result<Card, PcscError> {
+        let mut card: Handle = 0;
+        let mut protocol: Dword = 0;
+
+        check(unsafe {
+            SCardConnect(
+                self.0 .0,
+                reader.as_ptr(),
+                SCARD_SHARE_SHARED,
+                SCARD_PROTOCOL_T0 | SCARD_PROTOCOL_T1,
+                &mut card,
+                &mut protocol,
+            )
+        })?;
+
+        Ok(Card {
+            card,
+            protocol,
+            _ctx: self.0.clone(),
+        })
+    }
+}
+
+/// The connection to a card, disconnected when dropped.
+pub struct Card {
+    card: Handle,
+    protocol: Dword,
+    _ctx: Arc<ContextHandle>,
+}
+
+impl Card {
+    /// Transmits the given APDU, and returns the response written in
+    /// the given buffer.
+    pub fn transmit<'a>(&self, apdu: &[u8], buf: &'a mut [u8]) -> Result<&'a [u8], PcscError> {
+        let pci = IoRequest {
+            protocol: self.protocol,
+            pci_len: mem::size_of::<IoRequest>() as Dword,
+        };
+        let mut len = buf.len() as Dword;
+
+        check(unsafe {
+            SCardTransmit(
+                self.card,
+                &pci,
+                apdu.as_ptr(),
+                apdu.len() as Dword,
+                ptr::null_mut(),
+                buf.as_mut_ptr(),
+                &mut len,
+            )
+        })?;
+
+        Ok(&buf[..len as usize])
+    }
+}
+
+impl Drop for Card {
+    fn drop(&mut self) {
+        unsafe { SCardDisconnect(self.card, SCARD_LEAVE_CARD) };
+    }
+}
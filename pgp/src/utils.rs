@@ -4,68 +4,21 @@
 
 use std::{fs, io::Cursor, path::PathBuf};
 
-use smallvec::smallvec;
-
 use crate::{
-    native::{
-        crypto::{hash::HashAlgorithm, sym::SymmetricKeyAlgorithm},
-        types::{CompressionAlgorithm, SecretKeyTrait},
-        Deserializable, KeyType, SecretKeyParamsBuilder, SignedPublicKey, SignedSecretKey,
-        StandaloneSignature, SubkeyParamsBuilder,
-    },
+    key::{generate_key, KeyAlgorithm},
+    native::{Deserializable, SignedPublicKey, SignedSecretKey, StandaloneSignature},
     Error, Result,
 };
 
 /// Generates a new pair of secret and public keys for the given email
 /// address and passphrase.
+///
+/// See [`generate_key`] for more options.
 pub async fn gen_key_pair(
     email: impl ToString,
     passphrase: impl ToString,
 ) -> Result<(SignedSecretKey, SignedPublicKey)> {
-    let email = email.to_string();
-    let passphrase = passphrase.to_string();
-    let passphrase = if passphrase.trim().is_empty() {
-        None
-    } else {
-        Some(passphrase)
-    };
-
-    spawn_blocking(move || {
-        let key_params = SecretKeyParamsBuilder::default()
-            .key_type(KeyType::EdDSA)
-            .can_create_certificates(true)
-            .can_sign(true)
-            .primary_user_id(email)
-            .passphrase(passphrase.clone())
-            .preferred_symmetric_algorithms(smallvec![SymmetricKeyAlgorithm::AES256])
-            .preferred_hash_algorithms(smallvec![HashAlgorithm::SHA2_256])
-            .preferred_compression_algorithms(smallvec![CompressionAlgorithm::ZLIB])
-            .subkey(
-                SubkeyParamsBuilder::default()
-                    .key_type(KeyType::ECDH)
-                    .can_encrypt(true)
-                    .passphrase(passphrase)
-                    .build()
-                    .map_err(Error::BuildPublicKeyParamsError)?,
-            )
-            .build()
-            .map_err(Error::BuildSecretKeyParamsError)?;
-
-        let skey = key_params
-            .generate()
-            .map_err(Error::GenerateSecretKeyError)?;
-        let skey = skey.sign(String::new).map_err(Error::SignSecretKeyError)?;
-        skey.verify().map_err(Error::VerifySecretKeyError)?;
-
-        let pkey = skey.public_key();
-        let pkey = pkey
-            .sign(&skey, String::new)
-            .map_err(Error::SignPublicKeyError)?;
-        pkey.verify().map_err(Error::VerifyPublicKeyError)?;
-
-        Ok((skey, pkey))
-    })
-    .await?
+    generate_key(email, KeyAlgorithm::default(), None, passphrase).await
 }
 
 /// Reads a signed public key from the given path.
@@ -1,14 +1,48 @@
 //! # Verify
 //!
 //! Module dedicated to PGP verification. This module exposes a simple
-//! function [`verify`] and its associated [`Error`]s.
+//! function [`verify`], a detailed variant [`verify_with_report`] and
+//! their associated [`Error`]s.
+
+use chrono::{DateTime, Utc};
 
 use crate::{
-    native::{SignedPublicKey, StandaloneSignature},
+    key::{key_status, KeyStatus},
+    native::{types::KeyTrait, SignedPublicKey, StandaloneSignature},
     utils::spawn_blocking,
     Error, Result,
 };
 
+/// The detailed result of a signature verification.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VerificationReport {
+    /// The reason why the signature could not be verified, if any.
+    ///
+    /// When `None`, the signature is valid.
+    pub error: Option<String>,
+
+    /// The id of the key that issued the signature, as uppercase
+    /// hexadecimal.
+    pub key_id: String,
+
+    /// The fingerprint of the key that issued the signature, as
+    /// uppercase hexadecimal.
+    pub fingerprint: String,
+
+    /// The creation date of the signature.
+    pub created_at: Option<DateTime<Utc>>,
+
+    /// The status of the signer public key.
+    pub key_status: KeyStatus,
+}
+
+impl VerificationReport {
+    /// Returns `true` if the signature is valid.
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
 /// Verifies given standalone signature using the given public key.
 pub async fn verify(
     pkey: SignedPublicKey,
@@ -24,6 +58,50 @@ pub async fn verify(
     .await?
 }
 
+/// Verifies given standalone signature using the given public key,
+/// and reports details about the signature and its signer.
+///
+/// Unlike [`verify`], an invalid signature does not fail: the reason
+/// is stored in [`VerificationReport::error`]. When the signature was
+/// issued by a subkey, the subkey is used for verification.
+pub async fn verify_with_report(
+    pkey: SignedPublicKey,
+    signature: StandaloneSignature,
+    signed_bytes: Vec<u8>,
+) -> Result<VerificationReport> {
+    spawn_blocking(move || {
+        let sig = &signature.signature;
+        let issuer = sig.issuer();
+
+        let subkey = pkey
+            .public_subkeys
+            .iter()
+            .find(|subkey| Some(&subkey.key_id()) == issuer);
+
+        let (key_id, fingerprint, res) = match subkey {
+            Some(subkey) => (
+                subkey.key_id(),
+                subkey.fingerprint(),
+                signature.verify(subkey, &signed_bytes),
+            ),
+            None => (
+                pkey.key_id(),
+                pkey.fingerprint(),
+                signature.verify(&pkey, &signed_bytes),
+            ),
+        };
+
+        Ok(VerificationReport {
+            error: res.err().map(|err| err.to_string()),
+            key_id: format!("{key_id:X}"),
+            fingerprint: fingerprint.iter().map(|b| format!("{b:02X}")).collect(),
+            created_at: sig.created().cloned(),
+            key_status: key_status(&pkey),
+        })
+    })
+    .await?
+}
+
 #[cfg(test)]
 mod tests {
     #[cfg(feature = "async-std")]
@@ -31,7 +109,10 @@ mod tests {
     #[cfg(feature = "tokio")]
     use tokio::test;
 
-    use crate::{gen_key_pair, read_sig_from_bytes, sign, verify};
+    use crate::{
+        gen_key_pair, native::types::KeyTrait, read_sig_from_bytes, sign, verify,
+        verify_with_report, KeyStatus,
+    };
 
     #[test_log::test(test)]
     async fn sign_then_verify() {
@@ -42,4 +123,26 @@ async fn sign_then_verify() {
 
         verify(pkey, sig, msg).await.unwrap();
     }
+
+    #[test_log::test(test)]
+    async fn sign_then_verify_with_report() {
+        let (skey, pkey) = gen_key_pair("test@localhost", "").await.unwrap();
+        let msg = b"signed message".to_vec();
+        let raw_sig = sign(skey, "", msg.clone()).await.unwrap();
+        let sig = read_sig_from_bytes(raw_sig).await.unwrap();
+
+        let report = verify_with_report(pkey.clone(), sig.clone(), msg)
+            .await
+            .unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.key_id, format!("{:X}", pkey.key_id()));
+        assert_eq!(report.fingerprint.len(), 40);
+        assert!(report.created_at.is_some());
+        assert_eq!(report.key_status, KeyStatus::Valid);
+
+        let report = verify_with_report(pkey, sig, b"tampered message".to_vec())
+            .await
+            .unwrap();
+        assert!(!report.is_valid());
+    }
 }
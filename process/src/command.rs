@@ -4,8 +4,11 @@
 //! struct, and various implementations of transformation.
 
 use std::{
+    collections::HashMap,
     ops::{Deref, DerefMut},
+    path::PathBuf,
     process::Stdio,
+    time::Duration,
 };
 
 #[cfg(feature = "async-std")]
@@ -14,16 +17,54 @@
 use tokio::{io::AsyncWriteExt, process::Command as AsyncCommand};
 use tracing::{debug, info};
 
-use crate::{Error, Output, Result};
+use crate::{CommandStream, Error, Output, Result};
+
+/// The shell used to interpret commands.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Shell {
+    /// Uses `cmd /C` on Windows (except MinGW), `sh -c` otherwise.
+    #[default]
+    Default,
+
+    /// Uses `sh -c`.
+    Sh,
+
+    /// Uses `cmd /C`.
+    Cmd,
+
+    /// Uses `powershell -NoProfile -Command`.
+    #[cfg_attr(feature = "derive", serde(alias = "pwsh"))]
+    PowerShell,
+
+    /// Executes the command directly, without shell.
+    ///
+    /// The command is split into arguments following basic shell
+    /// quoting rules: single quotes, double quotes and backslash
+    /// escapes. Pipes, redirections and variable expansions are not
+    /// supported.
+    None,
+}
 
 /// The command structure.
 ///
-/// The structure is just a simple `String` wrapper.
+/// The structure is a `String` wrapper, plus few options to control
+/// the way the command is spawned: timeout, environment variables,
+/// working directory and shell.
+///
+/// When (de)serialized, a command without options is represented
+/// as a simple string. Otherwise it is represented as a map
+/// containing a `cmd` key plus optional `timeout` (a human-readable
+/// duration like `30s` or `1m 30s`), `env`, `cwd` and `shell` keys.
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(
     feature = "derive",
     derive(serde::Serialize, serde::Deserialize),
-    serde(from = "String", into = "String")
+    serde(from = "derive::Command", into = "derive::Command")
 )]
 pub struct Command {
     /// The inner command.
@@ -32,8 +73,28 @@ pub struct Command {
     /// Whenever the output should be piped or not.
     ///
     /// Defaults to `true`.
-    #[cfg_attr(feature = "derive", serde(skip))]
     piped: bool,
+
+    /// The maximum duration the command can run.
+    ///
+    /// When the timeout expires, the process is killed. Defaults to
+    /// `None`, which means no timeout.
+    timeout: Option<Duration>,
+
+    /// The environment variables injected into the process.
+    ///
+    /// Variables are added on top of the ones inherited from the
+    /// parent process.
+    envs: HashMap<String, String>,
+
+    /// The working directory of the process.
+    ///
+    /// Defaults to `None`, which means the working directory of the
+    /// parent process.
+    current_dir: Option<PathBuf>,
+
+    /// The shell used to interpret the command.
+    shell: Shell,
 }
 
 impl Command {
@@ -45,6 +106,10 @@ pub fn new(cmd: impl ToString) -> Self {
         Self {
             inner: cmd.to_string(),
             piped: true,
+            timeout: None,
+            envs: HashMap::new(),
+            current_dir: None,
+            shell: Shell::default(),
         }
     }
 
@@ -65,6 +130,115 @@ pub fn with_output_piped(mut self, piped: bool) -> Self {
         self
     }
 
+    /// Returns the timeout of the command, if any.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Defines the maximum duration the command can run.
+    ///
+    /// See [`Command::with_timeout`] for the builder pattern
+    /// alternative.
+    pub fn set_timeout(&mut self, timeout: impl Into<Option<Duration>>) {
+        self.timeout = timeout.into();
+    }
+
+    /// Defines the maximum duration the command can run, using the
+    /// builder pattern.
+    ///
+    /// See [`Command::set_timeout`] for the setter alternative.
+    pub fn with_timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.set_timeout(timeout);
+        self
+    }
+
+    /// Returns the environment variables injected into the process.
+    pub fn envs(&self) -> &HashMap<String, String> {
+        &self.envs
+    }
+
+    /// Injects an environment variable into the process.
+    ///
+    /// See [`Command::with_env`] for the builder pattern alternative.
+    pub fn set_env(&mut self, key: impl ToString, val: impl ToString) {
+        self.envs.insert(key.to_string(), val.to_string());
+    }
+
+    /// Injects an environment variable into the process, using the
+    /// builder pattern.
+    ///
+    /// See [`Command::set_env`] for the setter alternative.
+    pub fn with_env(mut self, key: impl ToString, val: impl ToString) -> Self {
+        self.set_env(key, val);
+        self
+    }
+
+    /// Injects multiple environment variables into the process.
+    ///
+    /// See [`Command::with_envs`] for the builder pattern
+    /// alternative.
+    pub fn set_envs(&mut self, envs: impl IntoIterator<Item = (impl ToString, impl ToString)>) {
+        for (key, val) in envs {
+            self.set_env(key, val);
+        }
+    }
+
+    /// Injects multiple environment variables into the process,
+    /// using the builder pattern.
+    ///
+    /// See [`Command::set_envs`] for the setter alternative.
+    pub fn with_envs(
+        mut self,
+        envs: impl IntoIterator<Item = (impl ToString, impl ToString)>,
+    ) -> Self {
+        self.set_envs(envs);
+        self
+    }
+
+    /// Returns the working directory of the process, if any.
+    pub fn current_dir(&self) -> Option<&PathBuf> {
+        self.current_dir.as_ref()
+    }
+
+    /// Defines the working directory of the process.
+    ///
+    /// See [`Command::with_current_dir`] for the builder pattern
+    /// alternative.
+    pub fn set_current_dir(&mut self, dir: impl Into<PathBuf>) {
+        self.current_dir = Some(dir.into());
+    }
+
+    /// Defines the working directory of the process, using the
+    /// builder pattern.
+    ///
+    /// See [`Command::set_current_dir`] for the setter alternative.
+    pub fn with_current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.set_current_dir(dir);
+        self
+    }
+
+    /// Returns the shell used to interpret the command.
+    pub fn shell(&self) -> Shell {
+        self.shell
+    }
+
+    /// Defines the shell used to interpret the command.
+    ///
+    /// See [`Command::with_shell`] for the builder pattern
+    /// alternative.
+    pub fn set_shell(&mut self, shell: Shell) {
+        self.shell = shell;
+    }
+
+    /// Defines the shell used to interpret the command, using the
+    /// builder pattern.
+    ///
+    /// See [`Command::set_shell`] for the setter alternative.
+    pub fn with_shell(mut self, shell: Shell) -> Self {
+        self.set_shell(shell);
+        self
+    }
+
     /// Wrapper around [`alloc::str::replace`].
     ///
     /// This function is particularly useful when you need to replace
@@ -87,6 +261,10 @@ pub async fn run(&self) -> Result<Output> {
     /// output. Otherwise the commands pipes this input to the
     /// standard input channel then waits for the output on the
     /// standard output channel.
+    ///
+    /// If a timeout is defined and expires before the command exits,
+    /// the process is killed and [`Error::TimeoutError`] is
+    /// returned.
     pub async fn run_with(&self, input: impl AsRef<[u8]>) -> Result<Output> {
         info!(cmd = self.inner, "run shell command");
 
@@ -100,8 +278,8 @@ pub async fn run_with(&self, input: impl AsRef<[u8]>) -> Result<Output> {
             Stdio::piped()
         };
 
-        let mut cmd = new_async_command()
-            .arg(&self.inner)
+        let mut cmd = self
+            .to_async_command()?
             .stdin(stdin)
             .stdout(if self.piped {
                 debug!("stdout piped");
@@ -119,23 +297,49 @@ pub async fn run_with(&self, input: impl AsRef<[u8]>) -> Result<Output> {
             })
             .spawn()?;
 
-        if !input.is_empty() {
-            cmd.stdin
-                .as_mut()
-                .ok_or(Error::GetStdinError)?
-                .write_all(input)
-                .await?;
-        }
+        let output = async {
+            if !input.is_empty() {
+                cmd.stdin
+                    .as_mut()
+                    .ok_or(Error::GetStdinError)?
+                    .write_all(input)
+                    .await?;
+            }
+
+            #[cfg(feature = "async-std")]
+            let output = cmd.output().await?;
+            #[cfg(feature = "tokio")]
+            let output = cmd.wait_with_output().await?;
+
+            Result::Ok(output)
+        };
+
+        // NOTE: when the timeout expires, the output future is
+        // dropped, which drops the child and kills the process
+        // thanks to `kill_on_drop`.
+        let output = match self.timeout {
+            None => output.await?,
+            Some(timeout) => {
+                #[cfg(feature = "async-std")]
+                let output = async_std::future::timeout(timeout, output).await;
+                #[cfg(feature = "tokio")]
+                let output = tokio::time::timeout(timeout, output).await;
 
-        #[cfg(feature = "async-std")]
-        let output = cmd.output().await?;
-        #[cfg(feature = "tokio")]
-        let output = cmd.wait_with_output().await?;
+                match output {
+                    Ok(output) => output?,
+                    Err(_) => {
+                        debug!(?timeout, "shell command timed out, process killed");
+                        return Err(Error::TimeoutError(self.to_string(), timeout));
+                    }
+                }
+            }
+        };
 
-        let code = output
-            .status
-            .code()
-            .ok_or_else(|| Error::GetExitStatusCodeNotAvailableError(self.to_string()))?;
+        let code = output.status.code().ok_or_else(|| {
+            let err = String::from_utf8_lossy(&output.stderr);
+            debug!(%err, "cannot get exit status code of shell command");
+            Error::GetExitStatusCodeNotAvailableError(self.to_string())
+        })?;
 
         if code == 0 {
             debug!(code, "shell command gracefully exited");
@@ -148,6 +352,48 @@ pub async fn run_with(&self, input: impl AsRef<[u8]>) -> Result<Output> {
 
         Ok(Output::from(output.stdout))
     }
+
+    /// Runs the current command and streams its output.
+    ///
+    /// Standard output and standard error channels are always piped,
+    /// standard input is inherited from the parent. Output lines are
+    /// pulled from the returned [`CommandStream`] as soon as the
+    /// command emits them, then the final exit status code can be
+    /// retrieved with [`CommandStream::wait`].
+    ///
+    /// If a timeout is defined, it applies to the whole lifetime of
+    /// the stream.
+    pub async fn run_streaming(&self) -> Result<CommandStream> {
+        info!(cmd = self.inner, "run shell command in streaming mode");
+
+        let child = self
+            .to_async_command()?
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        Ok(CommandStream::new(self.to_string(), child, self.timeout))
+    }
+
+    /// Prepares the async command, without standard channels.
+    fn to_async_command(&self) -> Result<AsyncCommand> {
+        let mut cmd = new_async_command(self.shell, &self.inner)?;
+
+        if !self.envs.is_empty() {
+            debug!("inject {} environment variable(s)", self.envs.len());
+            cmd.envs(&self.envs);
+        }
+
+        if let Some(dir) = &self.current_dir {
+            debug!(?dir, "set working directory");
+            cmd.current_dir(dir);
+        }
+
+        cmd.kill_on_drop(self.timeout.is_some());
+
+        Ok(cmd)
+    }
 }
 
 impl Deref for Command {
@@ -182,18 +428,174 @@ fn to_string(&self) -> String {
     }
 }
 
-/// Prepares a new async command.
-fn new_async_command() -> AsyncCommand {
-    #[cfg(not(windows))]
-    let windows = false;
-    #[cfg(windows)]
-    let windows = !std::env::var("MSYSTEM")
-        .map(|env| env.starts_with("MINGW"))
-        .unwrap_or_default();
+/// Prepares a new async command for the given shell.
+fn new_async_command(shell: Shell, inner: &str) -> Result<AsyncCommand> {
+    let (program, args) = match shell {
+        Shell::Default => {
+            #[cfg(not(windows))]
+            let windows = false;
+            #[cfg(windows)]
+            let windows = !std::env::var("MSYSTEM")
+                .map(|env| env.starts_with("MINGW"))
+                .unwrap_or_default();
+
+            if windows {
+                ("cmd", vec!["/C"])
+            } else {
+                ("sh", vec!["-c"])
+            }
+        }
+        Shell::Sh => ("sh", vec!["-c"]),
+        Shell::Cmd => ("cmd", vec!["/C"]),
+        Shell::PowerShell => ("powershell", vec!["-NoProfile", "-Command"]),
+        Shell::None => {
+            let mut args = split_args(inner)?.into_iter();
+            let program = args
+                .next()
+                .ok_or_else(|| Error::ParseCommandError(inner.to_owned()))?;
+            let mut cmd = AsyncCommand::new(program);
+            cmd.args(args);
+            return Ok(cmd);
+        }
+    };
+
+    let mut cmd = AsyncCommand::new(program);
+    cmd.args(args).arg(inner);
+    Ok(cmd)
+}
+
+/// Splits the given command into arguments.
+///
+/// Arguments are separated by whitespaces, unless they are quoted
+/// (simple or double) or escaped with a backslash.
+fn split_args(cmd: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut arg = None::<String>;
+    let mut chars = cmd.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(arg) = arg.take() {
+                    args.push(arg);
+                }
+            }
+            '\\' => {
+                let c = chars
+                    .next()
+                    .ok_or_else(|| Error::ParseCommandError(cmd.to_owned()))?;
+                arg.get_or_insert_with(String::new).push(c);
+            }
+            '\'' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err(Error::ParseCommandError(cmd.to_owned())),
+                    }
+                }
+            }
+            '"' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => arg.push(c),
+                            Some(c) => {
+                                arg.push('\\');
+                                arg.push(c);
+                            }
+                            None => return Err(Error::ParseCommandError(cmd.to_owned())),
+                        },
+                        Some(c) => arg.push(c),
+                        None => return Err(Error::ParseCommandError(cmd.to_owned())),
+                    }
+                }
+            }
+            c => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if let Some(arg) = arg {
+        args.push(arg);
+    }
+
+    Ok(args)
+}
+
+#[cfg(feature = "derive")]
+mod derive {
+    use std::{collections::HashMap, path::PathBuf, time::Duration};
 
-    let (shell, arg) = if windows { ("cmd", "/C") } else { ("sh", "-c") };
+    use super::Shell;
+
+    /// Serde representation of a [`super::Command`].
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(untagged)]
+    pub(super) enum Command {
+        Inline(String),
+        Table {
+            cmd: String,
+            #[serde(
+                default,
+                with = "humantime_serde",
+                skip_serializing_if = "Option::is_none"
+            )]
+            timeout: Option<Duration>,
+            #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+            env: HashMap<String, String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            cwd: Option<PathBuf>,
+            #[serde(default, skip_serializing_if = "is_default_shell")]
+            shell: Shell,
+        },
+    }
+
+    fn is_default_shell(shell: &Shell) -> bool {
+        *shell == Shell::Default
+    }
 
-    let mut cmd = AsyncCommand::new(shell);
-    cmd.arg(arg);
-    cmd
+    impl From<Command> for super::Command {
+        fn from(cmd: Command) -> Self {
+            match cmd {
+                Command::Inline(cmd) => Self::new(cmd),
+                Command::Table {
+                    cmd,
+                    timeout,
+                    env,
+                    cwd,
+                    shell,
+                } => Self {
+                    timeout,
+                    envs: env,
+                    current_dir: cwd,
+                    shell,
+                    ..Self::new(cmd)
+                },
+            }
+        }
+    }
+
+    impl From<super::Command> for Command {
+        fn from(cmd: super::Command) -> Self {
+            let has_options = cmd.timeout.is_some()
+                || !cmd.envs.is_empty()
+                || cmd.current_dir.is_some()
+                || cmd.shell != Shell::Default;
+
+            if !has_options {
+                return Self::Inline(cmd.inner);
+            }
+
+            Self::Table {
+                cmd: cmd.inner,
+                timeout: cmd.timeout,
+                env: cmd.envs,
+                cwd: cmd.current_dir,
+                shell: cmd.shell,
+            }
+        }
+    }
 }
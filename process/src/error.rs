@@ -3,7 +3,7 @@
 //! Module dedicated to process errors. It contains an [`Error`] enum
 //! based on [`thiserror::Error`] and a type alias [`Result`].
 
-use std::string::FromUtf8Error;
+use std::{string::FromUtf8Error, time::Duration};
 
 use thiserror::Error;
 
@@ -21,6 +21,10 @@ pub enum Error {
     GetExitStatusCodeNonZeroError(String, i32, String),
     #[error("cannot parse command output as string")]
     ParseOutputAsUtf8StringError(#[source] FromUtf8Error),
+    #[error("command {0} timed out after {1:?}")]
+    TimeoutError(String, Duration),
+    #[error("cannot parse command arguments: {0}")]
+    ParseCommandError(String),
 
     #[error(transparent)]
     IoError(#[from] std::io::Error),
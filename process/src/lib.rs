@@ -5,13 +5,15 @@
 mod error;
 mod output;
 mod pipeline;
+mod stream;
 
 #[doc(inline)]
 pub use crate::{
-    command::Command,
+    command::{Command, Shell},
     error::{Error, Result},
     output::Output,
     pipeline::Pipeline,
+    stream::{CommandStream, OutputLine},
 };
 
 #[cfg(any(
@@ -20,7 +20,7 @@
 #[cfg_attr(
     feature = "derive",
     derive(serde::Serialize, serde::Deserialize),
-    serde(from = "Vec<String>", into = "Vec<String>")
+    serde(transparent)
 )]
 pub struct Pipeline(Vec<Command>);
 
@@ -0,0 +1,303 @@
+//! # Command stream
+//!
+//! Module dedicated to command output streaming. It exposes the
+//! [`CommandStream`] struct, returned by
+//! [`Command::run_streaming`](crate::Command::run_streaming), and
+//! the [`OutputLine`] enum.
+
+use std::{
+    future::{poll_fn, Future},
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "async-std")]
+use async_std::{
+    io::Read as AsyncRead,
+    process::{Child, ChildStderr, ChildStdout},
+};
+#[cfg(feature = "tokio")]
+use tokio::{
+    io::{AsyncRead, ReadBuf},
+    process::{Child, ChildStderr, ChildStdout},
+};
+use tracing::debug;
+
+use crate::{Error, Result};
+
+/// A line emitted by a streamed command.
+///
+/// Lines are raw bytes, stripped from their trailing line ending
+/// (`\n` or `\r\n`).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OutputLine {
+    /// A line emitted on the standard output channel.
+    Stdout(Vec<u8>),
+
+    /// A line emitted on the standard error channel.
+    Stderr(Vec<u8>),
+}
+
+impl OutputLine {
+    /// Returns the raw bytes of the line.
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Stdout(line) => line,
+            Self::Stderr(line) => line,
+        }
+    }
+
+    /// Reads the line as string lossy.
+    pub fn to_string_lossy(&self) -> String {
+        String::from_utf8_lossy(self.as_bytes()).to_string()
+    }
+}
+
+/// The command stream structure.
+///
+/// Gives access to the output of a running command, line by line, as
+/// soon as the command emits them. See [`CommandStream::next_line`]
+/// and [`CommandStream::wait`].
+#[derive(Debug)]
+pub struct CommandStream {
+    /// The command, as string.
+    cmd: String,
+
+    /// The running child process.
+    child: Child,
+
+    /// The standard output line reader.
+    ///
+    /// Set to `None` once the channel reached the end of file.
+    stdout: Option<LineReader<ChildStdout>>,
+
+    /// The standard error line reader.
+    ///
+    /// Set to `None` once the channel reached the end of file.
+    stderr: Option<LineReader<ChildStderr>>,
+
+    /// The standard error lines emitted so far, used to build error
+    /// values.
+    captured_stderr: Vec<u8>,
+
+    /// The instant after which the command is killed, if any.
+    deadline: Option<(Instant, Duration)>,
+}
+
+impl CommandStream {
+    pub(crate) fn new(cmd: String, mut child: Child, timeout: Option<Duration>) -> Self {
+        let stdout = child.stdout.take().map(LineReader::new);
+        let stderr = child.stderr.take().map(LineReader::new);
+
+        Self {
+            cmd,
+            child,
+            stdout,
+            stderr,
+            captured_stderr: Vec::new(),
+            deadline: timeout.map(|timeout| (Instant::now() + timeout, timeout)),
+        }
+    }
+
+    /// Waits for the next line emitted by the command, on either
+    /// standard output or standard error channels.
+    ///
+    /// Returns `None` once both channels are closed.
+    pub async fn next_line(&mut self) -> Option<Result<OutputLine>> {
+        let Some((instant, timeout)) = self.deadline else {
+            return poll_fn(|cx| self.poll_next_line(cx)).await;
+        };
+
+        let remaining = instant.saturating_duration_since(Instant::now());
+        let line = poll_fn(|cx| self.poll_next_line(cx));
+
+        match timeout_after(remaining, line).await {
+            Some(line) => line,
+            None => {
+                self.kill();
+                Some(Err(Error::TimeoutError(self.cmd.clone(), timeout)))
+            }
+        }
+    }
+
+    /// Waits for the command to exit, then returns its exit status
+    /// code.
+    ///
+    /// Remaining standard output lines are discarded. Contrary to
+    /// [`Command::run`](crate::Command::run), a non-zero exit status
+    /// code is not considered as an error.
+    pub async fn wait(mut self) -> Result<i32> {
+        while let Some(line) = self.next_line().await {
+            line?;
+        }
+
+        #[cfg(feature = "async-std")]
+        let status = self.child.status();
+        #[cfg(feature = "tokio")]
+        let status = self.child.wait();
+
+        let status = match self.deadline {
+            None => status.await?,
+            Some((instant, timeout)) => {
+                let remaining = instant.saturating_duration_since(Instant::now());
+                match timeout_after(remaining, status).await {
+                    Some(status) => status?,
+                    None => {
+                        self.kill();
+                        return Err(Error::TimeoutError(self.cmd, timeout));
+                    }
+                }
+            }
+        };
+
+        let code = status.code().ok_or_else(|| {
+            let err = String::from_utf8_lossy(&self.captured_stderr);
+            debug!(%err, "cannot get exit status code of streamed shell command");
+            Error::GetExitStatusCodeNotAvailableError(self.cmd.clone())
+        })?;
+
+        debug!(code, "streamed shell command exited");
+
+        Ok(code)
+    }
+
+    /// Returns the standard error lines emitted so far.
+    pub fn captured_stderr(&self) -> &[u8] {
+        &self.captured_stderr
+    }
+
+    fn poll_next_line(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<OutputLine>>> {
+        if let Some(stdout) = &mut self.stdout {
+            match stdout.poll_next_line(cx) {
+                Poll::Ready(Ok(Some(line))) => {
+                    return Poll::Ready(Some(Ok(OutputLine::Stdout(line))));
+                }
+                Poll::Ready(Ok(None)) => {
+                    debug!("stdout closed");
+                    self.stdout = None;
+                }
+                Poll::Ready(Err(err)) => {
+                    self.stdout = None;
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+                Poll::Pending => (),
+            }
+        }
+
+        if let Some(stderr) = &mut self.stderr {
+            match stderr.poll_next_line(cx) {
+                Poll::Ready(Ok(Some(line))) => {
+                    self.captured_stderr.extend(&line);
+                    self.captured_stderr.push(b'\n');
+                    return Poll::Ready(Some(Ok(OutputLine::Stderr(line))));
+                }
+                Poll::Ready(Ok(None)) => {
+                    debug!("stderr closed");
+                    self.stderr = None;
+                }
+                Poll::Ready(Err(err)) => {
+                    self.stderr = None;
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+                Poll::Pending => (),
+            }
+        }
+
+        if self.stdout.is_none() && self.stderr.is_none() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn kill(&mut self) {
+        debug!("streamed shell command timed out, kill process");
+
+        #[cfg(feature = "async-std")]
+        let res = self.child.kill();
+        #[cfg(feature = "tokio")]
+        let res = self.child.start_kill();
+
+        if let Err(err) = res {
+            debug!(?err, "cannot kill process");
+        }
+    }
+}
+
+/// Runs the given future, returning `None` if it does not complete
+/// before the given duration.
+async fn timeout_after<T>(duration: Duration, f: impl Future<Output = T>) -> Option<T> {
+    #[cfg(feature = "async-std")]
+    let out = async_std::future::timeout(duration, f).await;
+    #[cfg(feature = "tokio")]
+    let out = tokio::time::timeout(duration, f).await;
+
+    out.ok()
+}
+
+/// Splits a raw reader into lines.
+#[derive(Debug)]
+struct LineReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+    eof: bool,
+}
+
+impl<R: AsyncRead + Unpin> LineReader<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: Vec::new(),
+            eof: false,
+        }
+    }
+
+    fn poll_next_line(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Option<Vec<u8>>>> {
+        loop {
+            if let Some(pos) = self.buf.iter().position(|b| *b == b'\n') {
+                let mut line: Vec<u8> = self.buf.drain(..=pos).collect();
+                line.pop();
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                return Poll::Ready(Ok(Some(line)));
+            }
+
+            if self.eof {
+                let line = std::mem::take(&mut self.buf);
+                return Poll::Ready(Ok(Some(line).filter(|line| !line.is_empty())));
+            }
+
+            let mut chunk = [0; 4096];
+            let n = ready!(poll_read(Pin::new(&mut self.reader), cx, &mut chunk))?;
+
+            if n == 0 {
+                self.eof = true;
+            } else {
+                self.buf.extend_from_slice(&chunk[..n]);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async-std")]
+fn poll_read<R: AsyncRead>(
+    reader: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    buf: &mut [u8],
+) -> Poll<io::Result<usize>> {
+    reader.poll_read(cx, buf)
+}
+
+#[cfg(feature = "tokio")]
+fn poll_read<R: AsyncRead>(
+    reader: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    buf: &mut [u8],
+) -> Poll<io::Result<usize>> {
+    let mut buf = ReadBuf::new(buf);
+    ready!(reader.poll_read(cx, &mut buf))?;
+    Poll::Ready(Ok(buf.filled().len()))
+}
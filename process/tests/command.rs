@@ -1,6 +1,8 @@
 #[cfg(feature = "async-std")]
 use async_std::test;
-use process::{Command, Error};
+use std::time::Duration;
+
+use process::{Command, Error, OutputLine, Shell};
 #[cfg(feature = "tokio")]
 use tokio::test;
 
@@ -19,3 +21,41 @@ async fn test_command() {
         err => panic!("unexpected error: {err:?}"),
     }
 }
+
+#[test_log::test(test)]
+async fn test_command_options() {
+    let cmd = Command::new("echo $GREETING; pwd")
+        .with_env("GREETING", "hello")
+        .with_current_dir("/");
+    let out = cmd.run().await.unwrap().to_string_lossy();
+    assert_eq!(out, "hello\n/\n");
+
+    let cmd = Command::new(r#"echo "hello, world!" $HOME"#).with_shell(Shell::None);
+    let out = cmd.run().await.unwrap().to_string_lossy();
+    assert_eq!(out, "hello, world! $HOME\n");
+
+    let cmd = Command::new("sleep 5").with_timeout(Duration::from_millis(100));
+    match cmd.run().await.unwrap_err() {
+        Error::TimeoutError(cmd, timeout) => {
+            assert_eq!(cmd, "sleep 5");
+            assert_eq!(timeout, Duration::from_millis(100));
+        }
+        err => panic!("unexpected error: {err:?}"),
+    }
+}
+
+#[test_log::test(test)]
+async fn test_command_streaming() {
+    let cmd = Command::new("echo hello; echo world >&2; exit 3");
+    let mut stream = cmd.run_streaming().await.unwrap();
+
+    let mut lines = Vec::new();
+    while let Some(line) = stream.next_line().await {
+        lines.push(line.unwrap());
+    }
+
+    assert!(lines.contains(&OutputLine::Stdout(b"hello".to_vec())));
+    assert!(lines.contains(&OutputLine::Stderr(b"world".to_vec())));
+    assert_eq!(stream.captured_stderr(), b"world\n");
+    assert_eq!(stream.wait().await.unwrap(), 3);
+}
@@ -0,0 +1,30 @@
+#![cfg(feature = "derive")]
+
+use std::time::Duration;
+
+use process::{Command, Pipeline};
+
+#[test]
+fn command_timeout() {
+    let cmd = Command::new("sleep 5").with_timeout(Duration::from_millis(1500));
+
+    let json = serde_json::to_string(&cmd).unwrap();
+    assert_eq!(json, r#"{"cmd":"sleep 5","timeout":"1s 500ms"}"#);
+    assert_eq!(serde_json::from_str::<Command>(&json).unwrap(), cmd);
+
+    let cmd: Command = serde_json::from_str(r#"{"cmd":"sleep 5","timeout":"1m 30s"}"#).unwrap();
+    assert_eq!(cmd.timeout(), Some(Duration::from_secs(90)));
+}
+
+#[test]
+fn pipeline() {
+    let pipeline = Pipeline::new(["echo hello", "cat"]);
+    let json = serde_json::to_string(&pipeline).unwrap();
+    assert_eq!(json, r#"["echo hello","cat"]"#);
+    assert_eq!(serde_json::from_str::<Pipeline>(&json).unwrap(), pipeline);
+
+    let pipeline: Pipeline =
+        serde_json::from_str(r#"["echo hello", {"cmd": "cat", "timeout": "1s"}]"#).unwrap();
+    assert_eq!(pipeline[0], Command::new("echo hello"));
+    assert_eq!(pipeline[1].timeout(), Some(Duration::from_secs(1)));
+}
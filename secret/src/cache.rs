@@ -0,0 +1,236 @@
+//! # Cache
+//!
+//! Module dedicated to cached secrets. A cached secret wraps another
+//! secret and keeps its value in memory for a while, so that
+//! expensive secrets (like shell commands or keyring entries) are
+//! not retrieved on every access.
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "command")]
+use process::Command;
+use tracing::debug;
+
+#[cfg(feature = "command")]
+use crate::Error;
+use crate::{Result, Secret};
+
+/// The cached secret.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct CachedSecret {
+    /// The secret the value is retrieved from.
+    pub secret: Box<Secret>,
+
+    /// The number of seconds the value is kept in memory.
+    ///
+    /// The value is kept until it is invalidated when not defined.
+    #[cfg_attr(
+        feature = "derive",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub ttl: Option<u64>,
+
+    /// The shell command used to refresh the value once it expired
+    /// or has been invalidated.
+    ///
+    /// Like command-based secrets, the value is taken from the first
+    /// line returned by the command. When not defined, the value is
+    /// retrieved again from the inner secret.
+    #[cfg(feature = "command")]
+    #[cfg_attr(
+        feature = "derive",
+        serde(
+            default,
+            alias = "refresh-cmd",
+            skip_serializing_if = "Option::is_none"
+        )
+    )]
+    pub refresh: Option<Command>,
+
+    /// The in-memory cache.
+    #[cfg_attr(feature = "derive", serde(skip))]
+    cache: SecretCache,
+}
+
+impl CachedSecret {
+    /// Creates a new cached secret from the given secret.
+    pub fn new(secret: Secret) -> Self {
+        Self {
+            secret: Box::new(secret),
+            ..Default::default()
+        }
+    }
+
+    /// Defines the time to live of the cached value, using the
+    /// builder pattern.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl.as_secs());
+        self
+    }
+
+    /// Defines the refresh command, using the builder pattern.
+    #[cfg(feature = "command")]
+    pub fn with_refresh(mut self, cmd: impl ToString) -> Self {
+        self.refresh = Some(Command::new(cmd));
+        self
+    }
+
+    /// Gets the secret value, from the cache if possible.
+    pub async fn get(&self) -> Result<String> {
+        if let Some(secret) = self.cache.get() {
+            return Ok(secret);
+        }
+
+        let secret = match self.cache.needs_refresh() {
+            #[cfg(feature = "command")]
+            true if self.refresh.is_some() => self.refresh().await?,
+            _ => Box::pin(self.secret.get()).await?,
+        };
+
+        self.cache.set(&secret, self.ttl);
+        Ok(secret)
+    }
+
+    /// Finds the secret value, from the cache if possible.
+    pub async fn find(&self) -> Result<Option<String>> {
+        if let Some(secret) = self.cache.get() {
+            return Ok(Some(secret));
+        }
+
+        let secret = match self.cache.needs_refresh() {
+            #[cfg(feature = "command")]
+            true if self.refresh.is_some() => Some(self.refresh().await?),
+            _ => Box::pin(self.secret.find()).await?,
+        };
+
+        if let Some(secret) = &secret {
+            self.cache.set(secret, self.ttl);
+        }
+
+        Ok(secret)
+    }
+
+    /// Updates the secret value of the inner secret and of the
+    /// cache.
+    pub async fn set(&mut self, secret: impl ToString) -> Result<String> {
+        let secret = Box::pin(self.secret.set(secret)).await?;
+        self.cache.set(&secret, self.ttl);
+        Ok(secret)
+    }
+
+    /// Invalidates the cached value, so that the next access
+    /// retrieves it again.
+    pub fn invalidate(&self) {
+        self.cache.invalidate()
+    }
+
+    /// Runs the refresh command and returns its first line.
+    #[cfg(feature = "command")]
+    async fn refresh(&self) -> Result<String> {
+        let Some(cmd) = &self.refresh else {
+            return Box::pin(self.secret.get()).await;
+        };
+
+        debug!("refreshing cached secret using command");
+
+        let output = cmd
+            .run()
+            .await
+            .map_err(Error::GetSecretFromCommand)?
+            .to_string_lossy();
+
+        let secret = output
+            .lines()
+            .next()
+            .ok_or(Error::GetSecretFromCommandEmptyOutputError)?
+            .to_owned();
+
+        Ok(secret)
+    }
+}
+
+impl From<Secret> for CachedSecret {
+    fn from(secret: Secret) -> Self {
+        Self::new(secret)
+    }
+}
+
+#[derive(Debug, Default)]
+struct SecretCacheInner {
+    /// The cached value and its expiration time, if any.
+    value: Option<(String, Option<Instant>)>,
+
+    /// Whether the value has been retrieved at least once.
+    fetched: bool,
+}
+
+/// The in-memory cache of a secret value.
+///
+/// Clones share the same cache.
+#[derive(Clone, Default)]
+struct SecretCache(Arc<Mutex<SecretCacheInner>>);
+
+impl SecretCache {
+    fn get(&self) -> Option<String> {
+        let mut cache = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+
+        match &cache.value {
+            Some((secret, None)) => Some(secret.clone()),
+            Some((secret, Some(expires_at))) if *expires_at > Instant::now() => {
+                Some(secret.clone())
+            }
+            Some(_) => {
+                debug!("cached secret expired");
+                cache.value = None;
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Returns `true` if the value has already been retrieved once,
+    /// which means that it needs to be refreshed.
+    fn needs_refresh(&self) -> bool {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .fetched
+    }
+
+    fn set(&self, secret: &str, ttl: Option<u64>) {
+        let mut cache = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let expires_at = ttl.map(|ttl| Instant::now() + Duration::from_secs(ttl));
+        cache.value = Some((secret.to_owned(), expires_at));
+        cache.fetched = true;
+    }
+
+    fn invalidate(&self) {
+        debug!("invalidate cached secret");
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).value = None;
+    }
+}
+
+// The cache is a runtime state rather than a configuration, so it
+// does not take part in the equality of secrets.
+impl PartialEq for SecretCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for SecretCache {}
+
+impl fmt::Debug for SecretCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretCache()")
+    }
+}
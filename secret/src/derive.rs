@@ -4,6 +4,8 @@
 use process::Command;
 use serde::{Deserialize, Serialize};
 
+use crate::{CachedSecret, PromptSecret};
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum Secret {
@@ -22,6 +24,8 @@ pub enum Secret {
     #[cfg(not(feature = "keyring"))]
     #[serde(skip_serializing, deserialize_with = "missing_keyring_feature")]
     Keyring,
+    Cached(CachedSecret),
+    Prompt(PromptSecret),
 }
 
 impl From<Secret> for crate::Secret {
@@ -38,6 +42,8 @@ fn from(secret: Secret) -> Self {
             Secret::Keyring(entry) => Self::Keyring(entry),
             #[cfg(not(feature = "keyring"))]
             Secret::Keyring => Self::Empty,
+            Secret::Cached(cached) => Self::Cached(cached),
+            Secret::Prompt(prompt) => Self::Prompt(prompt),
         }
     }
 }
@@ -13,6 +13,8 @@
 pub enum Error {
     #[error("cannot get empty secret")]
     GetEmptySecretError,
+    #[error("cannot prompt secret")]
+    PromptSecretError(#[source] std::io::Error),
     #[cfg(feature = "command")]
     #[error("cannot get secret from command")]
     GetSecretFromCommand(#[source] process::Error),
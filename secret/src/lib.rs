@@ -1,9 +1,13 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 #![doc = include_str!("../README.md")]
 
+mod cache;
 #[cfg(feature = "derive")]
 pub(crate) mod derive;
 mod error;
+mod prompt;
+
+use std::fmt;
 
 #[cfg(feature = "keyring")]
 pub use keyring;
@@ -16,7 +20,11 @@
 use tracing::debug;
 
 #[doc(inline)]
-pub use crate::error::{Error, Result};
+pub use crate::{
+    cache::CachedSecret,
+    error::{Error, Result},
+    prompt::{PromptSecret, SecretPrompt},
+};
 
 #[cfg(any(
     all(feature = "tokio", feature = "async-std"),
@@ -34,7 +42,10 @@
 ///
 /// A secret can be retrieved either from a raw string, from a shell
 /// command or from a keyring entry.
-#[derive(Clone, Debug, Default, Eq, PartialEq)]
+///
+/// The debug output redacts raw secrets, so that logging a secret
+/// (or a configuration holding one) never leaks it.
+#[derive(Clone, Default, Eq, PartialEq)]
 #[cfg_attr(
     feature = "derive",
     derive(serde::Serialize, serde::Deserialize),
@@ -67,6 +78,18 @@ pub enum Secret {
     /// See [keyring-lib](https://crates.io/crates/keyring-lib).
     #[cfg(feature = "keyring")]
     Keyring(KeyringEntry),
+
+    /// The secret is exposed by the inner secret, and cached in
+    /// memory.
+    ///
+    /// See [`CachedSecret`].
+    Cached(CachedSecret),
+
+    /// The secret is prompted to the user the first time it is
+    /// needed.
+    ///
+    /// See [`PromptSecret`].
+    Prompt(PromptSecret),
 }
 
 impl Secret {
@@ -101,6 +124,16 @@ pub fn try_new_keyring_entry(
         Ok(Self::new_keyring_entry(entry))
     }
 
+    /// Creates a new cached secret from the given secret.
+    pub fn new_cached(secret: impl Into<CachedSecret>) -> Self {
+        Self::Cached(secret.into())
+    }
+
+    /// Creates a new prompt-based secret.
+    pub fn new_prompt(prompt: PromptSecret) -> Self {
+        Self::Prompt(prompt)
+    }
+
     /// Returns `true` if the secret is empty.
     pub fn is_empty(&self) -> bool {
         *self == Self::Empty
@@ -141,6 +174,8 @@ pub async fn get(&self) -> Result<String> {
                 let secret = entry.get_secret().await?;
                 Ok(secret)
             }
+            Self::Cached(cached) => cached.get().await,
+            Self::Prompt(prompt) => prompt.get().await,
         }
     }
 
@@ -173,6 +208,8 @@ pub async fn find(&self) -> Result<Option<String>> {
                 let secret = entry.find_secret().await?;
                 Ok(secret)
             }
+            Self::Cached(cached) => cached.find().await,
+            Self::Prompt(prompt) => Ok(Some(prompt.get().await?)),
         }
     }
 
@@ -192,6 +229,12 @@ pub async fn set(&mut self, secret: impl ToString) -> Result<String> {
             }
             #[cfg(feature = "keyring")]
             Self::Keyring(entry) => entry.set_secret(secret.to_string()).await?,
+            Self::Cached(cached) => {
+                cached.set(secret.to_string()).await?;
+            }
+            Self::Prompt(prompt) => {
+                prompt.set(secret.to_string()).await?;
+            }
             Self::Empty => {
                 debug!("cannot change value of empty secret");
             }
@@ -221,6 +264,10 @@ pub async fn delete(&mut self) -> Result<()> {
             entry.delete_secret().await?;
         }
 
+        if let Self::Prompt(prompt) = self {
+            prompt.delete().await?;
+        }
+
         *self = Self::Empty;
 
         Ok(())
@@ -238,6 +285,16 @@ pub async fn delete_if_keyring(&self) -> Result<()> {
         Ok(())
     }
 
+    /// Invalidates the cached value of cached secrets, so that the
+    /// next access retrieves it again.
+    ///
+    /// This function has no effect on other variants.
+    pub fn invalidate(&self) {
+        if let Self::Cached(cached) = self {
+            cached.invalidate();
+        }
+    }
+
     /// Replaces empty secret variant with the given one.
     ///
     /// This function has no effect on other variants.
@@ -259,3 +316,18 @@ pub fn replace_with_keyring_if_empty(&mut self, entry: impl ToString) -> Result<
         Ok(())
     }
 }
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Empty"),
+            Self::Raw(_) => f.debug_tuple("Raw").field(&"<redacted>").finish(),
+            #[cfg(feature = "command")]
+            Self::Command(cmd) => f.debug_tuple("Command").field(cmd).finish(),
+            #[cfg(feature = "keyring")]
+            Self::Keyring(entry) => f.debug_tuple("Keyring").field(entry).finish(),
+            Self::Cached(cached) => f.debug_tuple("Cached").field(cached).finish(),
+            Self::Prompt(prompt) => f.debug_tuple("Prompt").field(prompt).finish(),
+        }
+    }
+}
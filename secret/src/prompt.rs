@@ -0,0 +1,292 @@
+//! # Prompt
+//!
+//! Module dedicated to prompt-based secrets. A prompt-based secret
+//! asks the user for its value the first time it is needed, which
+//! covers first-run flows where no secret is configured yet.
+
+use std::{
+    fmt,
+    future::Future,
+    io::{self, BufRead, Write},
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+#[cfg(feature = "keyring")]
+use keyring::KeyringEntry;
+use tracing::debug;
+
+use crate::{Error, Result};
+
+type PromptFuture = Pin<Box<dyn Future<Output = io::Result<String>> + Send>>;
+
+/// The callback used to prompt the user for a secret.
+///
+/// The callback receives the message to display.
+#[derive(Clone)]
+pub struct SecretPrompt(Arc<dyn Fn(String) -> PromptFuture + Send + Sync>);
+
+impl SecretPrompt {
+    /// Creates a new prompt from the given async callback.
+    pub fn new<F, Fut>(prompt: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = io::Result<String>> + Send + 'static,
+    {
+        Self(Arc::new(move |message| Box::pin(prompt(message))))
+    }
+
+    /// Creates a new prompt reading the secret from the terminal.
+    ///
+    /// The message is written to the standard error, then the secret
+    /// is read from the first line of the standard input. On unix
+    /// systems, the input is hidden using `stty`.
+    pub fn tty() -> Self {
+        Self::new(|message| async move { prompt_tty(&message) })
+    }
+
+    /// Prompts the user for a secret, displaying the given message.
+    pub async fn prompt(&self, message: impl ToString) -> io::Result<String> {
+        (self.0)(message.to_string()).await
+    }
+}
+
+impl Default for SecretPrompt {
+    fn default() -> Self {
+        Self::tty()
+    }
+}
+
+impl PartialEq for SecretPrompt {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SecretPrompt {}
+
+impl fmt::Debug for SecretPrompt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretPrompt()")
+    }
+}
+
+/// The prompt-based secret.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct PromptSecret {
+    /// The message displayed to the user.
+    ///
+    /// Defaults to [`PromptSecret::DEFAULT_MESSAGE`].
+    #[cfg_attr(
+        feature = "derive",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub message: Option<String>,
+
+    /// The keyring entry the prompted value is persisted to.
+    ///
+    /// When defined, the value is taken from this entry if it
+    /// exists, so that the user is only prompted once.
+    #[cfg(feature = "keyring")]
+    #[cfg_attr(
+        feature = "derive",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub keyring: Option<KeyringEntry>,
+
+    /// The prompt used to ask the user for the secret.
+    ///
+    /// Defaults to [`SecretPrompt::tty`].
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub prompt: Option<SecretPrompt>,
+
+    /// The prompted value, kept in memory.
+    #[cfg_attr(feature = "derive", serde(skip))]
+    value: PromptedValue,
+}
+
+impl PromptSecret {
+    /// The default message displayed to the user.
+    pub const DEFAULT_MESSAGE: &'static str = "Enter secret: ";
+
+    /// Creates a new prompt-based secret.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defines the message displayed to the user, using the builder
+    /// pattern.
+    pub fn with_message(mut self, message: impl ToString) -> Self {
+        self.message = Some(message.to_string());
+        self
+    }
+
+    /// Defines the keyring entry the prompted value is persisted to,
+    /// using the builder pattern.
+    #[cfg(feature = "keyring")]
+    pub fn with_keyring(mut self, entry: KeyringEntry) -> Self {
+        self.keyring = Some(entry);
+        self
+    }
+
+    /// Defines the prompt, using the builder pattern.
+    pub fn with_prompt(mut self, prompt: SecretPrompt) -> Self {
+        self.prompt = Some(prompt);
+        self
+    }
+
+    /// Gets the secret value, prompting the user if needed.
+    pub async fn get(&self) -> Result<String> {
+        if let Some(secret) = self.value.get() {
+            return Ok(secret);
+        }
+
+        #[cfg(feature = "keyring")]
+        if let Some(entry) = &self.keyring {
+            if let Some(secret) = entry.find_secret().await? {
+                self.value.set(&secret);
+                return Ok(secret);
+            }
+        }
+
+        let message = match &self.message {
+            Some(message) => message.as_str(),
+            None => Self::DEFAULT_MESSAGE,
+        };
+
+        debug!("prompting user for secret");
+
+        let secret = match &self.prompt {
+            Some(prompt) => prompt.prompt(message).await,
+            None => SecretPrompt::tty().prompt(message).await,
+        }
+        .map_err(Error::PromptSecretError)?;
+
+        #[cfg(feature = "keyring")]
+        if let Some(entry) = &self.keyring {
+            entry.set_secret(&secret).await?;
+        }
+
+        self.value.set(&secret);
+        Ok(secret)
+    }
+
+    /// Updates the secret value, in memory and in the keyring entry
+    /// if defined.
+    pub async fn set(&self, secret: impl ToString) -> Result<String> {
+        let secret = secret.to_string();
+
+        #[cfg(feature = "keyring")]
+        if let Some(entry) = &self.keyring {
+            entry.set_secret(&secret).await?;
+        }
+
+        self.value.set(&secret);
+        Ok(secret)
+    }
+
+    /// Deletes the secret value, from memory and from the keyring
+    /// entry if defined, so that the user is prompted again.
+    pub async fn delete(&self) -> Result<()> {
+        self.value.clear();
+
+        #[cfg(feature = "keyring")]
+        if let Some(entry) = &self.keyring {
+            if entry.find_secret().await?.is_some() {
+                entry.delete_secret().await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The prompted value, shared between clones.
+#[derive(Clone, Default)]
+struct PromptedValue(Arc<Mutex<Option<String>>>);
+
+impl PromptedValue {
+    fn get(&self) -> Option<String> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn set(&self, secret: &str) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = Some(secret.to_owned());
+    }
+
+    fn clear(&self) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+}
+
+// The prompted value is a runtime state rather than a configuration,
+// so it does not take part in the equality of secrets.
+impl PartialEq for PromptedValue {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for PromptedValue {}
+
+impl fmt::Debug for PromptedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PromptedValue()")
+    }
+}
+
+/// Prompts the user for a secret from the terminal.
+fn prompt_tty(message: &str) -> io::Result<String> {
+    let mut stderr = io::stderr();
+    stderr.write_all(message.as_bytes())?;
+    stderr.flush()?;
+
+    set_tty_echo(false);
+    let mut secret = String::new();
+    let res = io::stdin().lock().read_line(&mut secret);
+    set_tty_echo(true);
+
+    // the new line typed by the user is not echoed
+    writeln!(stderr)?;
+    res?;
+
+    let secret = secret.trim_end_matches(['\r', '\n']).to_owned();
+
+    if secret.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "prompted secret is empty",
+        ));
+    }
+
+    Ok(secret)
+}
+
+/// Enables or disables the echo of the terminal, if possible.
+#[cfg(unix)]
+fn set_tty_echo(echo: bool) {
+    use std::process::{Command, Stdio};
+
+    let arg = if echo { "echo" } else { "-echo" };
+    let res = Command::new("stty")
+        .arg(arg)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+
+    if let Err(err) = res {
+        debug!("cannot set terminal echo: {err}");
+    }
+}
+
+#[cfg(not(unix))]
+fn set_tty_echo(_echo: bool) {}
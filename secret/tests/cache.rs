@@ -0,0 +1,33 @@
+#[cfg(feature = "async-std")]
+use async_std::test;
+use secret::{CachedSecret, Secret};
+#[cfg(feature = "tokio")]
+use tokio::test;
+
+#[test_log::test(test)]
+async fn cache() {
+    let mut secret = Secret::new_cached(Secret::new_raw("secret"));
+    assert_eq!(secret.get().await.unwrap(), "secret");
+
+    // clones share the same cache
+    let clone = secret.clone();
+    secret.set("secret2").await.unwrap();
+    assert_eq!(clone.get().await.unwrap(), "secret2");
+
+    clone.invalidate();
+    assert_eq!(secret.find().await.unwrap(), Some(String::from("secret2")));
+}
+
+#[cfg(feature = "command")]
+#[test_log::test(test)]
+async fn cache_refresh() {
+    let secret =
+        CachedSecret::new(Secret::new_command("echo 'secret'")).with_refresh("echo 'refreshed'");
+    let secret = Secret::new_cached(secret);
+    assert_eq!(secret.get().await.unwrap(), "secret");
+    assert_eq!(secret.get().await.unwrap(), "secret");
+
+    // invalidated values are taken from the refresh command
+    secret.invalidate();
+    assert_eq!(secret.get().await.unwrap(), "refreshed");
+}
@@ -0,0 +1,44 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+#[cfg(feature = "async-std")]
+use async_std::test;
+use secret::{PromptSecret, Secret, SecretPrompt};
+#[cfg(feature = "tokio")]
+use tokio::test;
+
+#[test_log::test(test)]
+async fn prompt() {
+    let count = Arc::new(AtomicUsize::new(0));
+
+    let prompt = SecretPrompt::new({
+        let count = count.clone();
+        move |message| {
+            let count = count.clone();
+            async move {
+                assert_eq!(message, "Password: ");
+                count.fetch_add(1, Ordering::SeqCst);
+                Ok(String::from("secret"))
+            }
+        }
+    });
+
+    let mut secret = Secret::new_prompt(
+        PromptSecret::new()
+            .with_message("Password: ")
+            .with_prompt(prompt),
+    );
+
+    assert_eq!(secret.get().await.unwrap(), "secret");
+    // the user is prompted only once
+    assert_eq!(secret.get().await.unwrap(), "secret");
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    secret.set("secret2").await.unwrap();
+    assert_eq!(secret.get().await.unwrap(), "secret2");
+
+    secret.delete().await.unwrap();
+    assert_eq!(secret.find().await.unwrap(), None);
+}
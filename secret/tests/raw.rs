@@ -8,6 +8,7 @@
 async fn raw() {
     let mut secret = Secret::new_raw("secret");
     assert_eq!(secret.get().await.unwrap(), "secret");
+    assert_eq!(format!("{secret:?}"), r#"Raw("<redacted>")"#);
 
     secret.set("secret2").await.unwrap();
     assert_eq!(secret.get().await.unwrap(), "secret2");
@@ -7,8 +7,13 @@
 
 #[cfg(feature = "tcp-client")]
 pub mod tcp;
+#[cfg(all(unix, feature = "unix-client"))]
+pub mod unix;
 
-use std::io::{Error, ErrorKind, Result};
+use std::{
+    io::{Error, ErrorKind, Result},
+    time::Duration,
+};
 
 use async_trait::async_trait;
 use tracing::{info, trace};
@@ -50,7 +55,7 @@ async fn get(&self) -> Result<Timer> {
         match self.send(Request::Get).await {
             Ok(Response::Timer(timer)) => {
                 trace!("timer: {timer:#?}");
-                Ok(timer)
+                Ok(*timer)
             }
             Ok(res) => Err(Error::new(
                 ErrorKind::InvalidData,
@@ -61,7 +66,7 @@ async fn get(&self) -> Result<Timer> {
     }
 
     /// Send the set timer request.
-    async fn set(&self, duration: usize) -> Result<()> {
+    async fn set(&self, duration: Duration) -> Result<()> {
         info!("sending request to set timer duration");
 
         match self.send(Request::Set(duration)).await {
@@ -102,6 +107,62 @@ async fn resume(&self) -> Result<()> {
         }
     }
 
+    /// Send the lap timer request.
+    async fn lap(&self) -> Result<()> {
+        info!("sending request to record timer lap");
+
+        match self.send(Request::Lap).await {
+            Ok(Response::Ok) => Ok(()),
+            Ok(res) => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid response: {res:?}"),
+            )),
+            Err(err) => Err(Error::new(ErrorKind::Other, err)),
+        }
+    }
+
+    /// Send the skip timer cycle request.
+    async fn skip(&self) -> Result<()> {
+        info!("sending request to skip timer cycle");
+
+        match self.send(Request::Skip).await {
+            Ok(Response::Ok) => Ok(()),
+            Ok(res) => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid response: {res:?}"),
+            )),
+            Err(err) => Err(Error::new(ErrorKind::Other, err)),
+        }
+    }
+
+    /// Send the extend timer cycle request.
+    async fn extend(&self, duration: Duration) -> Result<()> {
+        info!("sending request to extend timer cycle");
+
+        match self.send(Request::Extend(duration)).await {
+            Ok(Response::Ok) => Ok(()),
+            Ok(res) => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid response: {res:?}"),
+            )),
+            Err(err) => Err(Error::new(ErrorKind::Other, err)),
+        }
+    }
+
+    /// Send the jump to timer cycle request.
+    async fn jump_to(&self, index: usize) -> Result<()> {
+        info!("sending request to jump to timer cycle");
+
+        match self.send(Request::JumpTo(index)).await {
+            Ok(Response::Ok) => Ok(()),
+            Ok(res) => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid response: {res:?}"),
+            )),
+            Err(err) => Err(Error::new(ErrorKind::Other, err)),
+        }
+    }
+
     /// Send the stop timer request.
     async fn stop(&self) -> Result<()> {
         info!("sending request to stop timer");
@@ -120,9 +181,17 @@ async fn stop(&self) -> Result<()> {
 /// The client stream trait.
 #[async_trait]
 pub trait ClientStream: RequestWriter + ResponseReader {
+    /// Write the request then read the response.
+    ///
+    /// [`Response::Error`]s sent by the server are turned into
+    /// errors.
     async fn handle(&mut self, req: Request) -> Result<Response> {
         self.write(req).await?;
-        self.read().await
+
+        match self.read().await? {
+            Response::Error(err) => Err(Error::new(ErrorKind::Other, err)),
+            res => Ok(res),
+        }
     }
 }
 
@@ -0,0 +1,44 @@
+//! # Unix socket client
+//!
+//! This module contains the implementation of the Unix socket client,
+//! based on [`tokio::net::UnixStream`].
+
+use std::{io::Result, path::PathBuf};
+
+#[cfg(feature = "async-std")]
+use async_std::os::unix::net::UnixStream;
+use async_trait::async_trait;
+#[cfg(feature = "tokio")]
+use tokio::net::UnixStream;
+use tracing::debug;
+
+use crate::{request::Request, response::Response, unix::UnixHandler};
+
+use super::{Client, ClientStream};
+
+/// The Unix socket client.
+///
+/// This [`Client`] connects to a Unix socket, to read responses and
+/// write requests using the [`protocol`](crate::protocol).
+pub struct UnixClient {
+    /// The path of the Unix socket the client should connect to.
+    pub path: PathBuf,
+}
+
+impl UnixClient {
+    /// Create a new Unix socket client using the given path.
+    pub fn new_boxed(path: impl Into<PathBuf>) -> Box<dyn Client> {
+        Box::new(Self { path: path.into() })
+    }
+}
+
+#[async_trait]
+impl Client for UnixClient {
+    /// Send the given request to the Unix socket server.
+    async fn send(&self, req: Request) -> Result<Response> {
+        debug!("connecting to Unix socket at {:?}", self.path);
+        let stream = UnixStream::connect(&self.path).await?;
+        let mut handler = UnixHandler::new(stream);
+        handler.handle(req).await
+    }
+}
@@ -0,0 +1,83 @@
+//! # Duration
+//!
+//! This module contains serde helpers to (de)serialize durations as
+//! seconds, like `1500`, with sub-second precision, like `0.25`. This
+//! keeps configurations and the wire protocol readable, compared to
+//! the default `{ secs, nanos }` representation of
+//! [`std::time::Duration`].
+
+/// Serde helper for [`Duration`] fields.
+pub(crate) mod secs {
+    use std::time::Duration;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        if duration.subsec_nanos() == 0 {
+            serializer.serialize_u64(duration.as_secs())
+        } else {
+            serializer.serialize_f64(duration.as_secs_f64())
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let secs = f64::deserialize(deserializer)?;
+        Duration::try_from_secs_f64(secs).map_err(Error::custom)
+    }
+}
+
+/// Serde helper for [`Vec<Duration>`] fields.
+pub(crate) mod secs_vec {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(transparent)]
+    struct Secs(#[serde(with = "super::secs")] Duration);
+
+    pub fn serialize<S: Serializer>(
+        durations: &[Duration],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let durations: Vec<_> = durations.iter().copied().map(Secs).collect();
+        durations.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Duration>, D::Error> {
+        let durations = Vec::<Secs>::deserialize(deserializer)?;
+        Ok(durations
+            .into_iter()
+            .map(|Secs(duration)| duration)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Durations {
+        #[serde(with = "super::secs")]
+        duration: Duration,
+        #[serde(with = "super::secs_vec")]
+        durations: Vec<Duration>,
+    }
+
+    #[test]
+    fn secs() {
+        let durations = Durations {
+            duration: Duration::from_secs(1500),
+            durations: vec![Duration::from_millis(250), Duration::from_secs(3)],
+        };
+
+        let json = serde_json::to_string(&durations).unwrap();
+        assert_eq!(json, r#"{"duration":1500,"durations":[0.25,3]}"#);
+        assert_eq!(serde_json::from_str::<Durations>(&json).unwrap(), durations);
+
+        assert!(serde_json::from_str::<Durations>(r#"{"duration":-1,"durations":[]}"#).is_err());
+    }
+}
@@ -0,0 +1,361 @@
+//! # History
+//!
+//! This module contains everything related to the timer history. When
+//! a history is configured, the timer records every cycle it
+//! completes, with timestamps. Cycles interrupted by a stop are not
+//! recorded.
+//!
+//! Recorded entries can then be queried using [`TimerStats`], for
+//! example to compute today's focus time or the current streak, and
+//! exported as CSV.
+//!
+//! Histories are pluggable via the [`TimerHistory`] trait. A JSON
+//! Lines file implementation is available with [`JsonTimerHistory`].
+
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    fs::{self, OpenOptions},
+    io::{Error, ErrorKind, Result, Write},
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use tracing::debug;
+
+/// The number of seconds in a day.
+const DAY: u64 = 24 * 60 * 60;
+
+/// The timer history entry.
+///
+/// Represents a cycle completed by the timer.
+#[derive(Clone, Debug, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TimerHistoryEntry {
+    /// The name of the completed cycle.
+    pub cycle: String,
+
+    /// The duration of the completed cycle.
+    #[serde(with = "crate::duration::secs")]
+    pub duration: Duration,
+
+    /// The wall-clock time the cycle started at, in seconds since the
+    /// Unix epoch.
+    pub started_at: u64,
+
+    /// The wall-clock time the cycle ended at, in seconds since the
+    /// Unix epoch.
+    pub ended_at: u64,
+}
+
+impl TimerHistoryEntry {
+    /// Create a new entry for the given cycle, ending now.
+    pub fn new(cycle: impl ToString, duration: Duration) -> Self {
+        let ended_at = unix_timestamp(SystemTime::now());
+
+        Self {
+            cycle: cycle.to_string(),
+            duration,
+            started_at: ended_at.saturating_sub(duration.as_secs()),
+            ended_at,
+        }
+    }
+}
+
+/// The timer history trait.
+///
+/// Timer histories must implement this trait.
+#[async_trait]
+pub trait TimerHistory: Debug + Send + Sync {
+    /// Record the given entry.
+    async fn record(&self, entry: &TimerHistoryEntry) -> Result<()>;
+
+    /// Read all the recorded entries, from the oldest to the newest.
+    async fn entries(&self) -> Result<Vec<TimerHistoryEntry>>;
+
+    /// Build statistics from all the recorded entries.
+    async fn stats(&self) -> Result<TimerStats> {
+        Ok(TimerStats::new(self.entries().await?))
+    }
+}
+
+/// The JSON Lines timer history.
+///
+/// Appends one JSON entry per line to the file at the given path.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JsonTimerHistory {
+    /// The path of the JSON Lines file.
+    pub path: PathBuf,
+}
+
+impl JsonTimerHistory {
+    /// Create a new JSON history using the given file path.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl TimerHistory for JsonTimerHistory {
+    async fn record(&self, entry: &TimerHistoryEntry) -> Result<()> {
+        let mut json = serde_json::to_vec(entry).map_err(|err| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("cannot serialize timer history entry: {err}"),
+            )
+        })?;
+        json.push(b'\n');
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&json)
+    }
+
+    async fn entries(&self) -> Result<Vec<TimerHistoryEntry>> {
+        let json = match fs::read_to_string(&self.path) {
+            Ok(json) => json,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                debug!("no timer history found at {:?}", self.path);
+                return Ok(Vec::new());
+            }
+            Err(err) => return Err(err),
+        };
+
+        json.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line).map_err(|err| {
+                    Error::new(
+                        ErrorKind::InvalidData,
+                        format!("cannot parse timer history entry: {err}"),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// The timer statistics.
+///
+/// Query API built on top of the timer history entries. Days are
+/// computed using the given UTC offset, in seconds, so that "today"
+/// matches the local day of the user.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TimerStats {
+    entries: Vec<TimerHistoryEntry>,
+}
+
+impl TimerStats {
+    /// Create new statistics from the given entries.
+    pub fn new(entries: impl IntoIterator<Item = TimerHistoryEntry>) -> Self {
+        Self {
+            entries: entries.into_iter().collect(),
+        }
+    }
+
+    /// Return the entries the statistics are built from.
+    pub fn entries(&self) -> &[TimerHistoryEntry] {
+        &self.entries
+    }
+
+    /// Compute the total time spent in each cycle.
+    pub fn totals(&self) -> BTreeMap<String, Duration> {
+        self.entries
+            .iter()
+            .fold(BTreeMap::new(), |mut totals, entry| {
+                *totals.entry(entry.cycle.clone()).or_default() += entry.duration;
+                totals
+            })
+    }
+
+    /// Compute the total time spent in the given cycle since the
+    /// given time.
+    pub fn total_since(&self, cycle: &str, since: SystemTime) -> Duration {
+        let since = unix_timestamp(since);
+
+        self.entries
+            .iter()
+            .filter(|entry| entry.cycle == cycle && entry.ended_at >= since)
+            .map(|entry| entry.duration)
+            .sum()
+    }
+
+    /// Compute the total time spent in the given cycle today.
+    ///
+    /// For example, `stats.today("Work", SystemTime::now(), 3600)`
+    /// gives today's focus time of a pomodoro user living in UTC+1.
+    pub fn today(&self, cycle: &str, now: SystemTime, utc_offset: i64) -> Duration {
+        let today = day(unix_timestamp(now), utc_offset);
+
+        self.entries
+            .iter()
+            .filter(|entry| entry.cycle == cycle && day(entry.ended_at, utc_offset) == today)
+            .map(|entry| entry.duration)
+            .sum()
+    }
+
+    /// Compute the current streak of the given cycle, in days.
+    ///
+    /// The streak is the number of consecutive days with at least one
+    /// completed cycle, ending today. The streak is not considered
+    /// broken if no cycle has been completed yet today.
+    pub fn streak(&self, cycle: &str, now: SystemTime, utc_offset: i64) -> usize {
+        let days: Vec<u64> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.cycle == cycle)
+            .map(|entry| day(entry.ended_at, utc_offset))
+            .collect();
+
+        let today = day(unix_timestamp(now), utc_offset);
+        let mut current = if days.contains(&today) {
+            today
+        } else {
+            today.saturating_sub(1)
+        };
+
+        let mut streak = 0;
+
+        while days.contains(&current) {
+            streak += 1;
+
+            if current == 0 {
+                break;
+            }
+
+            current -= 1;
+        }
+
+        streak
+    }
+
+    /// Export the entries as CSV to the given writer.
+    ///
+    /// The first line contains the column names.
+    pub fn write_csv(&self, mut writer: impl Write) -> Result<()> {
+        writeln!(writer, "cycle,duration,started-at,ended-at")?;
+
+        for entry in &self.entries {
+            writeln!(
+                writer,
+                "{},{},{},{}",
+                escape_csv(&entry.cycle),
+                entry.duration.as_secs_f64(),
+                entry.started_at,
+                entry.ended_at
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Convert the given time into seconds since the Unix epoch.
+fn unix_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+/// Compute the day of the given timestamp, shifted by the given UTC
+/// offset.
+fn day(timestamp: u64, utc_offset: i64) -> u64 {
+    timestamp.saturating_add_signed(utc_offset) / DAY
+}
+
+/// Quote the given CSV field if needed.
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[cfg(feature = "async-std")]
+    use async_std::test;
+    use tempfile::tempdir;
+    #[cfg(feature = "tokio")]
+    use tokio::test;
+
+    use super::*;
+
+    fn entry(cycle: &str, duration: u64, ended_at: u64) -> TimerHistoryEntry {
+        TimerHistoryEntry {
+            cycle: cycle.into(),
+            duration: Duration::from_secs(duration),
+            started_at: ended_at - duration,
+            ended_at,
+        }
+    }
+
+    #[test_log::test(test)]
+    async fn json_history() {
+        let dir = tempdir().unwrap();
+        let history = JsonTimerHistory::new(dir.path().join("history"));
+
+        assert_eq!(history.entries().await.unwrap(), vec![]);
+
+        let work = entry("Work", 1500, 10_000);
+        let rest = entry("Break", 300, 10_300);
+        history.record(&work).await.unwrap();
+        history.record(&rest).await.unwrap();
+
+        assert_eq!(history.entries().await.unwrap(), vec![work, rest]);
+    }
+
+    #[test_log::test(test)]
+    async fn stats() {
+        let now = UNIX_EPOCH + Duration::from_secs(3 * DAY + 10_000);
+
+        let stats = TimerStats::new([
+            entry("Work", 1500, DAY + 5_000),
+            entry("Work", 1500, 2 * DAY + 5_000),
+            entry("Break", 300, 2 * DAY + 5_300),
+            entry("Work", 1500, 3 * DAY + 5_000),
+            entry("Work", 1500, 3 * DAY + 8_000),
+        ]);
+
+        assert_eq!(stats.today("Work", now, 0), Duration::from_secs(3000));
+        assert_eq!(stats.today("Break", now, 0), Duration::ZERO);
+        assert_eq!(stats.streak("Work", now, 0), 3);
+        assert_eq!(stats.streak("Break", now, 0), 1);
+        assert_eq!(
+            stats.total_since("Work", UNIX_EPOCH + Duration::from_secs(2 * DAY)),
+            Duration::from_secs(4500)
+        );
+
+        let totals = stats.totals();
+        assert_eq!(totals.get("Work"), Some(&Duration::from_secs(6000)));
+        assert_eq!(totals.get("Break"), Some(&Duration::from_secs(300)));
+
+        // with a negative offset, the first work cycle of each day
+        // ends the day before
+        assert_eq!(stats.today("Work", now, -6000), Duration::from_secs(1500));
+        assert_eq!(stats.streak("Work", now, -6000), 4);
+    }
+
+    #[test_log::test(test)]
+    async fn csv() {
+        let stats = TimerStats::new([entry("Work, deep", 1500, 2000)]);
+
+        let mut csv = Vec::new();
+        stats.write_csv(&mut csv).unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "cycle,duration,started-at,ended-at\n\"Work, deep\",1500,500,2000\n"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! # Hook
+//!
+//! This module contains everything related to timer cycle hooks. Each
+//! [`TimerCycle`] can carry its own hooks, executed when the timer
+//! begins or ends the cycle. This saves consumers from dispatching
+//! timer events manually from the global timer handler.
+//!
+//! A hook can execute a shell command and/or send a system
+//! notification (behind cargo feature `notify`).
+
+use process::Command;
+use tracing::debug;
+
+use crate::timer::TimerCycle;
+
+/// The timer hook.
+///
+/// Each field represents an action that should be done when the hook
+/// is executed.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct TimerHook {
+    /// Execute the shell command.
+    ///
+    /// Accepted placeholders:
+    ///  - "{cycle}": the name of the cycle
+    ///  - "{duration}": the duration of the cycle, in seconds
+    pub cmd: Option<Command>,
+
+    /// Send a system notification using the given
+    /// [`notify_rust::Notification`]-like configuration.
+    pub notify: Option<TimerNotifyConfig>,
+}
+
+impl TimerHook {
+    /// Execute the hook for the given cycle.
+    ///
+    /// Errors are logged and skipped, so that a failing hook never
+    /// prevents the timer from running.
+    pub async fn exec(&self, cycle: &TimerCycle) {
+        let duration = cycle.duration.as_secs().to_string();
+
+        #[allow(unused_variables)]
+        let replace = |fmt: &str| -> String {
+            fmt.replace("{cycle}", &cycle.name)
+                .replace("{duration}", &duration)
+        };
+
+        if let Some(cmd) = self.cmd.as_ref() {
+            let res = cmd
+                .clone()
+                .replace("{cycle}", &cycle.name)
+                .replace("{duration}", &duration)
+                .run()
+                .await;
+
+            if let Err(err) = res {
+                debug!("error while executing timer command hook");
+                debug!("{err:?}");
+            }
+        }
+
+        #[cfg(feature = "notify")]
+        if let Some(notify) = self.notify.as_ref() {
+            let summary = replace(&notify.summary);
+            let body = replace(&notify.body);
+            let sound = notify.sound.clone();
+
+            let res = crate::server::spawn_blocking(move || {
+                let mut notification = notify_rust::Notification::new();
+                notification.summary(&summary).body(&body);
+
+                if let Some(sound) = sound.as_ref() {
+                    notification.sound_name(sound);
+                }
+
+                notification.show()
+            })
+            .await;
+
+            match res {
+                Ok(Ok(_)) => (),
+                Ok(Err(err)) => {
+                    debug!("error while sending system notification");
+                    debug!("{err:?}");
+                }
+                Err(err) => {
+                    debug!("cannot send system notification");
+                    debug!("{err:?}");
+                }
+            }
+        }
+    }
+}
+
+/// The timer configuration of the notify hook.
+///
+/// The structure tries to match the [`notify_rust::Notification`] API
+/// and may evolve in the future.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct TimerNotifyConfig {
+    /// The summary (or the title) of the notification.
+    ///
+    /// Accepted placeholders:
+    ///  - "{cycle}": the name of the cycle
+    ///  - "{duration}": the duration of the cycle, in seconds
+    pub summary: String,
+
+    /// The body of the notification.
+    ///
+    /// Accepted placeholders:
+    ///  - "{cycle}": the name of the cycle
+    ///  - "{duration}": the duration of the cycle, in seconds
+    pub body: String,
+
+    /// The name of the sound played along with the notification.
+    ///
+    /// On Linux, this is a name from the freedesktop sound naming
+    /// specification, like "alarm-clock-elapsed". On macOS, this is
+    /// the name of a system sound, like "Glass".
+    pub sound: Option<String>,
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{fs, time::Duration};
+
+    #[cfg(feature = "async-std")]
+    use async_std::test;
+    use process::Command;
+    use tempfile::tempdir;
+    #[cfg(feature = "tokio")]
+    use tokio::test;
+
+    use super::TimerHook;
+    use crate::timer::TimerCycle;
+
+    #[test_log::test(test)]
+    async fn exec_cmd() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("hook");
+
+        let hook = TimerHook {
+            cmd: Some(Command::new(format!(
+                "printf '{{cycle}} {{duration}}' > {}",
+                path.display()
+            ))),
+            ..Default::default()
+        };
+
+        hook.exec(&TimerCycle::new("Work", Duration::from_secs(1500)))
+            .await;
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Work 1500");
+    }
+}
@@ -0,0 +1,73 @@
+//! # macOS idle detector
+//!
+//! This module contains the idle detector for macOS. It relies on the
+//! `ioreg` command, which exposes the `HIDIdleTime` property of the
+//! HID system in nanoseconds.
+
+use std::{io::Result, time::Duration};
+
+use async_trait::async_trait;
+
+use super::{parse_error, run, IdleDetect};
+
+const PROGRAM: &str = "ioreg";
+
+const ARGS: &[&str] = &["-c", "IOHIDSystem", "-d", "4"];
+
+/// The macOS idle detector.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MacOsIdleDetector;
+
+impl MacOsIdleDetector {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn new_boxed() -> Box<dyn IdleDetect> {
+        Box::new(Self::new())
+    }
+}
+
+#[async_trait]
+impl IdleDetect for MacOsIdleDetector {
+    async fn idle_time(&self) -> Result<Duration> {
+        parse_idle_time(&run(PROGRAM, ARGS).await?)
+    }
+}
+
+/// Parse the idle time from the first `"HIDIdleTime" = <ns>` line.
+fn parse_idle_time(output: &str) -> Result<Duration> {
+    let ns = output
+        .lines()
+        .find_map(|line| {
+            let (key, val) = line.split_once('=')?;
+            let key = key.trim_matches(|c: char| c.is_whitespace() || c == '|' || c == '"');
+
+            if key == "HIDIdleTime" {
+                val.trim().parse::<u64>().ok()
+            } else {
+                None
+            }
+        })
+        .ok_or_else(|| parse_error(PROGRAM, output))?;
+    Ok(Duration::from_nanos(ns))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    #[test]
+    fn parse_idle_time() {
+        let output = concat!(
+            "+-o IOHIDSystem  <class IOHIDSystem>\n",
+            "  | {\n",
+            "  |   \"HIDIdleTime\" = 4242000000\n",
+            "  | }\n",
+        );
+        let idle_time = super::parse_idle_time(output).unwrap();
+        assert_eq!(idle_time, Duration::from_millis(4242));
+
+        assert!(super::parse_idle_time("").is_err());
+    }
+}
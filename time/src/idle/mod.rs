@@ -0,0 +1,116 @@
+//! # Idle
+//!
+//! This module contains everything related to idle detection. When
+//! an idle detector is configured, the server regularly asks it for
+//! the user idle time: the running timer is paused when the user goes
+//! idle, and resumed when the user comes back.
+//!
+//! Detectors are pluggable via the [`IdleDetect`] trait. Default
+//! implementations are available for X11, GNOME (Mutter) and macOS
+//! behind their respective cargo features.
+
+#[cfg(feature = "idle-macos")]
+pub mod macos;
+#[cfg(feature = "idle-mutter")]
+pub mod mutter;
+#[cfg(feature = "idle-x11")]
+pub mod x11;
+
+use std::{fmt::Debug, io::Result, time::Duration};
+
+use async_trait::async_trait;
+use tracing::debug;
+
+use crate::timer::{ThreadSafeTimer, TimerState};
+
+/// The maximum duration an idle command can run.
+#[cfg(any(feature = "idle-macos", feature = "idle-mutter", feature = "idle-x11"))]
+const RUN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The idle detect trait.
+///
+/// Idle detectors must implement this trait.
+#[async_trait]
+pub trait IdleDetect: Debug + Send + Sync {
+    /// Get the time elapsed since the last user activity (keyboard,
+    /// mouse etc).
+    async fn idle_time(&self) -> Result<Duration>;
+}
+
+/// The idle configuration.
+#[derive(Debug)]
+pub struct IdleConfig {
+    /// The idle detector.
+    pub detector: Box<dyn IdleDetect>,
+
+    /// The idle time after which the user is considered idle.
+    pub threshold: Duration,
+}
+
+impl IdleConfig {
+    /// Create a new idle configuration from the given detector and
+    /// threshold.
+    pub fn new(detector: Box<dyn IdleDetect>, threshold: Duration) -> Self {
+        Self {
+            detector,
+            threshold,
+        }
+    }
+
+    /// Pause or resume the given timer depending on the user idle
+    /// time.
+    pub(crate) async fn check(&self, timer: &ThreadSafeTimer) {
+        if matches!(timer.lock().await.state, TimerState::Stopped) {
+            return;
+        }
+
+        let res = match self.detector.idle_time().await {
+            Ok(idle_time) if idle_time >= self.threshold => timer.idle_pause().await,
+            Ok(_) => timer.idle_resume().await,
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = res {
+            debug!("error while detecting idle, skipping it");
+            debug!("{err:?}");
+        }
+    }
+}
+
+/// Run the given command, then return its standard output.
+///
+/// The command is executed directly, without shell, and killed if it
+/// does not exit within [`RUN_TIMEOUT`].
+#[cfg(any(feature = "idle-macos", feature = "idle-mutter", feature = "idle-x11"))]
+pub(crate) async fn run(program: &'static str, args: &'static [&'static str]) -> Result<String> {
+    use std::io::{Error, ErrorKind};
+
+    use process::{Command, Shell};
+
+    let cmd = std::iter::once(program)
+        .chain(args.iter().copied())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let output = Command::new(cmd)
+        .with_shell(Shell::None)
+        .with_timeout(RUN_TIMEOUT)
+        .run()
+        .await
+        .map_err(|err| {
+            let err = format!("cannot run idle command {program}: {err}");
+            Error::new(ErrorKind::Other, err)
+        })?;
+
+    Ok(output.to_string_lossy())
+}
+
+/// Build the error returned when an idle command output cannot be
+/// parsed.
+#[cfg(any(feature = "idle-macos", feature = "idle-mutter", feature = "idle-x11"))]
+pub(crate) fn parse_error(program: &str, output: &str) -> std::io::Error {
+    use std::io::{Error, ErrorKind};
+
+    let err = format!("cannot parse idle time from {program} output: {output}");
+    Error::new(ErrorKind::InvalidData, err)
+}
@@ -0,0 +1,80 @@
+//! # Mutter idle detector
+//!
+//! This module contains the idle detector for GNOME sessions. It
+//! relies on the Mutter idle monitor D-Bus interface, queried using
+//! the `gdbus` command, which works under both X11 and Wayland.
+//!
+//! This interface is specific to Mutter: the detector does not work
+//! under other Wayland compositors (Sway, KDE Plasma etc). Those
+//! expose the [ext-idle-notify-v1] protocol instead, which is not
+//! supported yet.
+//!
+//! [ext-idle-notify-v1]: https://wayland.app/protocols/ext-idle-notify-v1
+
+use std::{io::Result, time::Duration};
+
+use async_trait::async_trait;
+
+use super::{parse_error, run, IdleDetect};
+
+const PROGRAM: &str = "gdbus";
+
+const ARGS: &[&str] = &[
+    "call",
+    "--session",
+    "--dest",
+    "org.gnome.Mutter.IdleMonitor",
+    "--object-path",
+    "/org/gnome/Mutter/IdleMonitor/Core",
+    "--method",
+    "org.gnome.Mutter.IdleMonitor.GetIdletime",
+];
+
+/// The Mutter (GNOME) idle detector.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MutterIdleDetector;
+
+impl MutterIdleDetector {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn new_boxed() -> Box<dyn IdleDetect> {
+        Box::new(Self::new())
+    }
+}
+
+#[async_trait]
+impl IdleDetect for MutterIdleDetector {
+    async fn idle_time(&self) -> Result<Duration> {
+        parse_idle_time(&run(PROGRAM, ARGS).await?)
+    }
+}
+
+/// Parse the idle time from the D-Bus reply, which looks like
+/// `(uint64 4242,)`.
+fn parse_idle_time(output: &str) -> Result<Duration> {
+    let ms = output
+        .trim()
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .trim_end_matches(',')
+        .trim_start_matches("uint64")
+        .trim()
+        .parse()
+        .map_err(|_| parse_error(PROGRAM, output))?;
+    Ok(Duration::from_millis(ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    #[test]
+    fn parse_idle_time() {
+        let idle_time = super::parse_idle_time("(uint64 4242,)\n").unwrap();
+        assert_eq!(idle_time, Duration::from_millis(4242));
+
+        assert!(super::parse_idle_time("()").is_err());
+    }
+}
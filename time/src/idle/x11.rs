@@ -0,0 +1,57 @@
+//! # X11 idle detector
+//!
+//! This module contains the idle detector for X11 sessions. It relies
+//! on the [xprintidle] command, which prints the idle time in
+//! milliseconds as reported by the X screensaver extension.
+//!
+//! [xprintidle]: https://github.com/g0hl1n/xprintidle
+
+use std::{io::Result, time::Duration};
+
+use async_trait::async_trait;
+
+use super::{parse_error, run, IdleDetect};
+
+const PROGRAM: &str = "xprintidle";
+
+/// The X11 idle detector.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct X11IdleDetector;
+
+impl X11IdleDetector {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn new_boxed() -> Box<dyn IdleDetect> {
+        Box::new(Self::new())
+    }
+}
+
+#[async_trait]
+impl IdleDetect for X11IdleDetector {
+    async fn idle_time(&self) -> Result<Duration> {
+        parse_idle_time(&run(PROGRAM, &[]).await?)
+    }
+}
+
+fn parse_idle_time(output: &str) -> Result<Duration> {
+    let ms = output
+        .trim()
+        .parse()
+        .map_err(|_| parse_error(PROGRAM, output))?;
+    Ok(Duration::from_millis(ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    #[test]
+    fn parse_idle_time() {
+        let idle_time = super::parse_idle_time("4242\n").unwrap();
+        assert_eq!(idle_time, Duration::from_millis(4242));
+
+        assert!(super::parse_idle_time("").is_err());
+    }
+}
@@ -3,11 +3,30 @@
 
 #[cfg(feature = "client")]
 pub mod client;
+#[cfg(feature = "derive")]
+pub(crate) mod duration;
 pub(crate) mod handler;
+#[cfg(feature = "history")]
+pub mod history;
+#[cfg(feature = "hooks")]
+pub mod hook;
+#[cfg(feature = "idle")]
+pub mod idle;
+#[cfg(any(
+    feature = "tcp-binder",
+    feature = "tcp-client",
+    feature = "unix-binder",
+    feature = "unix-client"
+))]
+pub mod protocol;
 pub mod request;
 pub mod response;
 #[cfg(feature = "server")]
 pub mod server;
+#[cfg(feature = "store")]
+pub mod store;
 #[cfg(any(feature = "tcp-binder", feature = "tcp-client"))]
 pub mod tcp;
 pub mod timer;
+#[cfg(all(unix, any(feature = "unix-binder", feature = "unix-client")))]
+pub mod unix;
@@ -0,0 +1,263 @@
+//! # Protocol
+//!
+//! This module contains the wire protocol shared by the TCP and the
+//! Unix socket binders and clients. It is simple enough to be
+//! implemented by non-Rust frontends (status bars, editor plugins
+//! etc).
+//!
+//! ## Framing
+//!
+//! Each message is a frame made of a 4-byte big-endian unsigned
+//! length followed by a UTF-8 JSON payload of that length. Frames
+//! cannot exceed [`MAX_FRAME_LEN`] bytes.
+//!
+//! A client opens a connection, writes one request frame then reads
+//! one response frame. The server closes the connection afterwards.
+//!
+//! ## Requests
+//!
+//! Requests are [`Request`]s serialized in kebab-case. Requests
+//! without argument are plain strings, the other ones are objects
+//! with a single key. Durations are expressed in seconds.
+//!
+//! ```json
+//! "start"
+//! "get"
+//! {"set": 1500}
+//! "pause"
+//! "resume"
+//! "lap"
+//! "skip"
+//! {"extend": 300}
+//! {"jump-to": 2}
+//! "stop"
+//! ```
+//!
+//! ## Responses
+//!
+//! Responses are [`Response`]s serialized the same way:
+//!
+//! ```json
+//! "ok"
+//! {"timer": {"state": "running", "cycle": {"name": "Work", "duration": 1500}, ...}}
+//! {"error": "cannot extend timer: timer is a stopwatch"}
+//! ```
+
+use std::{
+    fmt,
+    io::{Error, ErrorKind, Result},
+};
+#[cfg(feature = "tokio")]
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use async_trait::async_trait;
+#[cfg(feature = "tokio")]
+use futures::ready;
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    request::{Request, RequestReader, RequestWriter},
+    response::{Response, ResponseReader, ResponseWriter},
+};
+
+/// The maximum length of a frame payload, in bytes.
+pub const MAX_FRAME_LEN: u32 = 1024 * 1024;
+
+/// Read a length-prefixed JSON frame from the given reader.
+pub async fn read_frame<T: DeserializeOwned>(
+    reader: &mut (impl AsyncRead + Send + Unpin),
+) -> Result<T> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len).await?;
+    let len = u32::from_be_bytes(len);
+
+    if len > MAX_FRAME_LEN {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("frame too large: {len} bytes (max {MAX_FRAME_LEN})"),
+        ));
+    }
+
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload).await?;
+
+    serde_json::from_slice(&payload)
+        .map_err(|err| Error::new(ErrorKind::InvalidData, format!("invalid frame: {err}")))
+}
+
+/// Write the given value as a length-prefixed JSON frame to the given
+/// writer.
+pub async fn write_frame<T: Serialize + Sync>(
+    writer: &mut (impl AsyncWrite + Send + Unpin),
+    value: &T,
+) -> Result<()> {
+    let payload = serde_json::to_vec(value).map_err(|err| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("cannot serialize frame: {err}"),
+        )
+    })?;
+
+    let len = u32::try_from(payload.len())
+        .ok()
+        .filter(|len| *len <= MAX_FRAME_LEN)
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("frame too large: {} bytes", payload.len()),
+            )
+        })?;
+
+    writer.write_all(&len.to_be_bytes()).await?;
+    writer.write_all(&payload).await?;
+    writer.flush().await
+}
+
+/// The protocol handler.
+///
+/// Reads and writes requests and responses as frames over the given
+/// stream. Used by both binders and clients.
+pub struct StreamHandler<S> {
+    stream: S,
+}
+
+impl<S> StreamHandler<S> {
+    /// Create a new handler using the given stream.
+    pub fn new(stream: impl Into<S>) -> Self {
+        Self {
+            stream: stream.into(),
+        }
+    }
+}
+
+impl<S> fmt::Debug for StreamHandler<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamHandler").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<S: AsyncRead + AsyncWrite + Send + Sync + Unpin> RequestReader for StreamHandler<S> {
+    async fn read(&mut self) -> Result<Request> {
+        read_frame(&mut self.stream).await
+    }
+}
+
+#[async_trait]
+impl<S: AsyncRead + AsyncWrite + Send + Sync + Unpin> RequestWriter for StreamHandler<S> {
+    async fn write(&mut self, req: Request) -> Result<()> {
+        write_frame(&mut self.stream, &req).await
+    }
+}
+
+#[async_trait]
+impl<S: AsyncRead + AsyncWrite + Send + Sync + Unpin> ResponseReader for StreamHandler<S> {
+    async fn read(&mut self) -> Result<Response> {
+        read_frame(&mut self.stream).await
+    }
+}
+
+#[async_trait]
+impl<S: AsyncRead + AsyncWrite + Send + Sync + Unpin> ResponseWriter for StreamHandler<S> {
+    async fn write(&mut self, res: Response) -> Result<()> {
+        write_frame(&mut self.stream, &res).await
+    }
+}
+
+/// Compatibility layer between tokio streams and the [`futures`] IO
+/// traits.
+#[cfg(feature = "tokio")]
+pub struct Compat<T>(T);
+
+#[cfg(feature = "tokio")]
+impl<T> From<T> for Compat<T> {
+    fn from(stream: T) -> Self {
+        Self(stream)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: tokio::io::AsyncRead + Unpin> AsyncRead for Compat<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        let mut buf = tokio::io::ReadBuf::new(buf);
+        ready!(tokio::io::AsyncRead::poll_read(
+            Pin::new(&mut self.0),
+            cx,
+            &mut buf
+        ))?;
+        Poll::Ready(Ok(buf.filled().len()))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: tokio::io::AsyncWrite + Unpin> AsyncWrite for Compat<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize>> {
+        tokio::io::AsyncWrite::poll_write(Pin::new(&mut self.0), cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        tokio::io::AsyncWrite::poll_flush(Pin::new(&mut self.0), cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        tokio::io::AsyncWrite::poll_shutdown(Pin::new(&mut self.0), cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    #[cfg(feature = "async-std")]
+    use async_std::test;
+    use futures::io::Cursor;
+    #[cfg(feature = "tokio")]
+    use tokio::test;
+
+    use super::*;
+
+    #[test_log::test(test)]
+    async fn frames() {
+        let mut buf = Cursor::new(Vec::new());
+        write_frame(&mut buf, &Request::Set(Duration::from_secs(1500)))
+            .await
+            .unwrap();
+        assert_eq!(buf.get_ref().as_slice(), b"\0\0\0\x0c{\"set\":1500}");
+
+        buf.set_position(0);
+        let req: Request = read_frame(&mut buf).await.unwrap();
+        assert_eq!(req, Request::Set(Duration::from_secs(1500)));
+
+        let mut handler = StreamHandler::<Cursor<Vec<u8>>>::new(Cursor::new(Vec::new()));
+        ResponseWriter::write(&mut handler, Response::Timer(Box::default()))
+            .await
+            .unwrap();
+        handler.stream.set_position(0);
+        let res = ResponseReader::read(&mut handler).await.unwrap();
+        assert_eq!(res, Response::Timer(Box::default()));
+
+        let json = serde_json::to_string(&Request::JumpTo(2)).unwrap();
+        assert_eq!(json, r#"{"jump-to":2}"#);
+        let json = serde_json::to_string(&Response::Ok).unwrap();
+        assert_eq!(json, r#""ok""#);
+    }
+
+    #[test_log::test(test)]
+    async fn frame_too_large() {
+        let mut buf = Cursor::new((MAX_FRAME_LEN + 1).to_be_bytes().to_vec());
+        let err = read_frame::<Request>(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -4,7 +4,7 @@
 //! receive back a response. This module contains the request
 //! structure as well as trait to read and write a request.
 
-use std::io::Result;
+use std::{io::Result, time::Duration};
 
 use async_trait::async_trait;
 
@@ -12,6 +12,11 @@
 ///
 /// Requests are sent by clients and received by servers.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
 pub enum Request {
     /// Request the timer to start with the first configured cycle.
     Start,
@@ -20,7 +25,7 @@ pub enum Request {
     Get,
 
     /// Request to change the current timer duration.
-    Set(usize),
+    Set(#[cfg_attr(feature = "derive", serde(with = "crate::duration::secs"))] Duration),
 
     /// Request to pause the timer.
     ///
@@ -33,6 +38,28 @@ pub enum Request {
     /// Has no effect if the timer is not paused.
     Resume,
 
+    /// Request to record a new lap.
+    ///
+    /// Only stopwatches can record laps.
+    Lap,
+
+    /// Request to end the current cycle immediately, and begin the
+    /// next one.
+    ///
+    /// Only countdown timers can skip cycles.
+    Skip,
+
+    /// Request to add the given duration to the current cycle.
+    ///
+    /// Only countdown timers can be extended.
+    Extend(#[cfg_attr(feature = "derive", serde(with = "crate::duration::secs"))] Duration),
+
+    /// Request to end the current cycle immediately, and begin the
+    /// cycle at the given index.
+    ///
+    /// Only countdown timers can jump to cycles.
+    JumpTo(usize),
+
     /// Request to stop the timer.
     ///
     /// Stopping the timer resets the state, the cycle and the value.
@@ -14,12 +14,21 @@
 ///
 /// Responses are sent by servers and received by clients.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
 pub enum Response {
     /// Default response when everything goes as expected.
     Ok,
 
     /// Response containing the current timer.
-    Timer(Timer),
+    Timer(Box<Timer>),
+
+    /// Response sent when the request could not be processed,
+    /// containing the error message.
+    Error(String),
 }
 
 /// Trait to read a server response.
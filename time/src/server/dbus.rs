@@ -0,0 +1,308 @@
+//! # D-Bus binder
+//!
+//! This module contains the implementation of the D-Bus server
+//! binder, based on [`zbus`]. It exposes the timer on the session
+//! bus, so that desktop applets and scripts can control it without
+//! implementing the length-prefixed JSON protocol.
+//!
+//! The timer is served at [`DBUS_PATH`] with the [`DBUS_INTERFACE`]
+//! interface, which contains the following members:
+//!
+//!  - methods `Start`, `Stop`, `Pause` and `Resume`
+//!  - method `Get`, returning the state, the cycle name, the cycle
+//!    duration and the elapsed time of the timer, as `(ssdd)`
+//!  - signal `StateChanged`, containing the new state
+//!  - signal `CycleChanged`, containing the new cycle name and its
+//!    duration
+//!
+//! Durations are expressed in seconds. For example, using `busctl`:
+//!
+//! ```text
+//! busctl --user call org.pimalaya.Time /org/pimalaya/Time org.pimalaya.Time1 Get
+//! ```
+
+use std::io::{self, Error, ErrorKind};
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use tracing::debug;
+use zbus::{connection, fdo, interface, SignalContext};
+
+use crate::timer::{ThreadSafeTimer, TimerEvent, TimerState};
+
+use super::ServerBind;
+
+/// The default well-known name requested on the session bus.
+pub const DBUS_NAME: &str = "org.pimalaya.Time";
+
+/// The object path the timer is served at.
+pub const DBUS_PATH: &str = "/org/pimalaya/Time";
+
+/// The name of the timer interface.
+pub const DBUS_INTERFACE: &str = "org.pimalaya.Time1";
+
+/// The D-Bus server binder.
+///
+/// This [`ServerBind`]er requests the given well-known name on the
+/// session bus, then serves the timer at [`DBUS_PATH`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DbusBind {
+    /// The well-known name requested on the session bus.
+    pub name: String,
+}
+
+impl DbusBind {
+    /// Create a new D-Bus binder using the given well-known name.
+    pub fn new(name: impl ToString) -> Box<dyn ServerBind> {
+        Box::new(Self {
+            name: name.to_string(),
+        })
+    }
+}
+
+impl Default for DbusBind {
+    fn default() -> Self {
+        Self {
+            name: DBUS_NAME.to_owned(),
+        }
+    }
+}
+
+#[async_trait]
+impl ServerBind for DbusBind {
+    async fn bind(&self, timer: ThreadSafeTimer) -> io::Result<()> {
+        let builder = connection::Builder::session()
+            .and_then(|builder| builder.name(self.name.as_str()))
+            .map_err(dbus_error)?;
+
+        debug!("serving D-Bus interface as {}", self.name);
+        serve(builder, timer).await
+    }
+}
+
+/// Serve the timer at [`DBUS_PATH`] on the connection built from the
+/// given builder, then emit signals until the timer events stream
+/// ends.
+///
+/// Signals are emitted from timer events rather than from timer
+/// changes, so that beginning a cycle with the same name as the
+/// previous one is signaled as well.
+async fn serve(builder: connection::Builder<'_>, timer: ThreadSafeTimer) -> io::Result<()> {
+    // subscribe first, so that no event is missed while building
+    // the connection
+    let mut events = timer.subscribe().await;
+
+    let conn = builder
+        .serve_at(DBUS_PATH, TimerInterface { timer })
+        .map_err(dbus_error)?
+        .build()
+        .await
+        .map_err(dbus_error)?;
+
+    debug!("D-Bus interface served at {DBUS_PATH}");
+
+    let iface = conn
+        .object_server()
+        .interface::<_, TimerInterface>(DBUS_PATH)
+        .await
+        .map_err(dbus_error)?;
+
+    while let Some(event) = events.next().await {
+        emit_signals(iface.signal_context(), &event).await;
+    }
+
+    Ok(())
+}
+
+/// Emit the D-Bus signals matching the given timer event.
+async fn emit_signals(ctxt: &SignalContext<'_>, event: &TimerEvent) {
+    let state = match event {
+        TimerEvent::Started | TimerEvent::Resumed(_) | TimerEvent::IdleResumed(_) => {
+            Some(TimerState::Running)
+        }
+        TimerEvent::Paused(_) | TimerEvent::IdlePaused(_) => Some(TimerState::Paused),
+        TimerEvent::Stopped => Some(TimerState::Stopped),
+        _ => None,
+    };
+
+    if let Some(state) = state {
+        if let Err(err) = TimerInterface::state_changed(ctxt, state_name(&state)).await {
+            debug!("cannot emit D-Bus state changed signal");
+            debug!("{err:?}");
+        }
+    }
+
+    if let TimerEvent::Began(cycle) = event {
+        let res =
+            TimerInterface::cycle_changed(ctxt, &cycle.name, cycle.duration.as_secs_f64()).await;
+
+        if let Err(err) = res {
+            debug!("cannot emit D-Bus cycle changed signal");
+            debug!("{err:?}");
+        }
+    }
+}
+
+/// The D-Bus timer interface.
+struct TimerInterface {
+    timer: ThreadSafeTimer,
+}
+
+#[interface(name = "org.pimalaya.Time1")]
+impl TimerInterface {
+    /// Start the timer with the first configured cycle.
+    async fn start(&self) -> fdo::Result<()> {
+        debug!("starting timer via D-Bus");
+        self.timer.start().await.map_err(fdo_error)
+    }
+
+    /// Stop the timer.
+    async fn stop(&self) -> fdo::Result<()> {
+        debug!("stopping timer via D-Bus");
+        self.timer.stop().await.map_err(fdo_error)
+    }
+
+    /// Pause the timer.
+    async fn pause(&self) -> fdo::Result<()> {
+        debug!("pausing timer via D-Bus");
+        self.timer.pause().await.map_err(fdo_error)
+    }
+
+    /// Resume the paused timer.
+    async fn resume(&self) -> fdo::Result<()> {
+        debug!("resuming timer via D-Bus");
+        self.timer.resume().await.map_err(fdo_error)
+    }
+
+    /// Get the state, the cycle name, the cycle duration and the
+    /// elapsed time of the timer.
+    ///
+    /// The cycle duration is the remaining time of countdown timers,
+    /// and the time elapsed since the last lap of stopwatches.
+    async fn get(&self) -> (String, String, f64, f64) {
+        let timer = self.timer.get().await;
+
+        (
+            state_name(&timer.state).to_owned(),
+            timer.cycle.name.clone(),
+            timer.cycle.duration.as_secs_f64(),
+            timer.elapsed().as_secs_f64(),
+        )
+    }
+
+    /// Signal emitted when the timer state changes.
+    #[zbus(signal)]
+    async fn state_changed(ctxt: &SignalContext<'_>, state: &str) -> zbus::Result<()>;
+
+    /// Signal emitted when the timer begins a cycle, even if it has
+    /// the same name as the previous one.
+    #[zbus(signal)]
+    async fn cycle_changed(ctxt: &SignalContext<'_>, name: &str, duration: f64)
+        -> zbus::Result<()>;
+}
+
+/// Get the D-Bus name of the given timer state.
+fn state_name(state: &TimerState) -> &'static str {
+    match state {
+        TimerState::Running => "running",
+        TimerState::Paused => "paused",
+        TimerState::Stopped => "stopped",
+    }
+}
+
+fn fdo_error(err: Error) -> fdo::Error {
+    fdo::Error::Failed(err.to_string())
+}
+
+fn dbus_error(err: zbus::Error) -> Error {
+    Error::new(ErrorKind::Other, format!("D-Bus error: {err}"))
+}
+
+#[cfg(all(test, unix, feature = "tokio"))]
+mod tests {
+    use std::time::Duration;
+
+    use futures::StreamExt;
+    use tokio::{net::UnixStream, time::timeout};
+    use zbus::{connection, message::Type, Guid, MessageStream};
+
+    use super::serve;
+    use crate::timer::{ThreadSafeTimer, TimerConfig, TimerCycle, TimerCycles};
+
+    /// Wait for the next signal, and return its member name along
+    /// with its serialized body.
+    async fn next_signal(stream: &mut MessageStream) -> (String, String) {
+        let next = async {
+            while let Some(msg) = stream.next().await {
+                let msg = msg.unwrap();
+                let header = msg.header();
+
+                if header.message_type() != Type::Signal {
+                    continue;
+                }
+
+                let member = header.member().unwrap().to_string();
+                let body = match member.as_str() {
+                    "StateChanged" => msg.body().deserialize::<String>().unwrap(),
+                    "CycleChanged" => {
+                        let (name, duration) = msg.body().deserialize::<(String, f64)>().unwrap();
+                        format!("{name} {duration}")
+                    }
+                    _ => continue,
+                };
+
+                return (member, body);
+            }
+
+            panic!("D-Bus connection closed");
+        };
+
+        timeout(Duration::from_secs(5), next).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn emit_signals() {
+        let timer = ThreadSafeTimer::new(TimerConfig {
+            cycles: TimerCycles::from([TimerCycle::new("Work", Duration::from_secs(60))]),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let (server, client) = UnixStream::pair().unwrap();
+        let server = connection::Builder::unix_stream(server)
+            .server(Guid::generate())
+            .unwrap()
+            .p2p();
+        tokio::spawn(serve(server, timer.clone()));
+
+        let client = connection::Builder::unix_stream(client)
+            .p2p()
+            .build()
+            .await
+            .unwrap();
+        let mut signals = MessageStream::from(&client);
+
+        timer.start().await.unwrap();
+        assert_eq!(
+            next_signal(&mut signals).await,
+            ("StateChanged".into(), "running".into())
+        );
+        assert_eq!(
+            next_signal(&mut signals).await,
+            ("CycleChanged".into(), "Work 60".into())
+        );
+
+        // the only cycle begins again, with the same name
+        timer.skip().await.unwrap();
+        assert_eq!(
+            next_signal(&mut signals).await,
+            ("CycleChanged".into(), "Work 60".into())
+        );
+
+        timer.pause().await.unwrap();
+        assert_eq!(
+            next_signal(&mut signals).await,
+            ("StateChanged".into(), "paused".into())
+        );
+    }
+}
@@ -7,13 +7,17 @@
 //!
 //!
 
+#[cfg(feature = "dbus")]
+pub mod dbus;
 #[cfg(feature = "tcp-binder")]
 pub mod tcp;
+#[cfg(all(unix, feature = "unix-binder"))]
+pub mod unix;
 
 use std::{
     fmt::Debug,
     future::Future,
-    io::Result,
+    io::{ErrorKind, Result},
     ops::{Deref, DerefMut},
     sync::Arc,
     time::Duration,
@@ -22,16 +26,29 @@
 #[cfg(feature = "async-std")]
 use async_std::task::sleep;
 use async_trait::async_trait;
-use futures::{lock::Mutex, select, stream::FuturesUnordered, FutureExt, StreamExt};
+#[cfg(feature = "idle")]
+use futures::future;
+use futures::{
+    future::FusedFuture, lock::Mutex, pin_mut, select, stream::FuturesUnordered, FutureExt,
+    StreamExt,
+};
 #[cfg(feature = "tokio")]
-use tokio::time::sleep;
+use tokio::{sync::watch, time::sleep};
 use tracing::{debug, trace};
 
+#[cfg(feature = "history")]
+use crate::history::TimerHistory;
+#[cfg(feature = "idle")]
+use crate::idle::{IdleConfig, IdleDetect};
+#[cfg(feature = "store")]
+use crate::store::TimerStore;
 use crate::{
     handler::{self, Handler},
     request::{Request, RequestReader},
     response::{Response, ResponseWriter},
-    timer::{ThreadSafeTimer, TimerConfig, TimerCycle, TimerEvent, TimerLoop},
+    timer::{
+        ThreadSafeTimer, TimerConfig, TimerCycle, TimerDeadline, TimerEvent, TimerLoop, TimerMode,
+    },
 };
 
 /// The server state enum.
@@ -57,6 +74,24 @@ pub struct ServerConfig {
 
     /// The binders list the server should use when starting up.
     binders: Vec<Box<dyn ServerBind>>,
+
+    /// The interval between two timer updates.
+    ///
+    /// Each update emits a [`TimerEvent::Running`] event, so a short
+    /// tick allows smooth progress UIs. Defaults to 1 second.
+    tick: Duration,
+
+    /// The idle configuration, used to pause the timer when the user
+    /// goes idle.
+    #[cfg(feature = "idle")]
+    idle: Option<IdleConfig>,
+
+    /// The interval between two idle checks.
+    ///
+    /// Idle detectors usually spawn a process, so they are queried
+    /// less often than the timer is updated. Defaults to 5 seconds.
+    #[cfg(feature = "idle")]
+    idle_interval: Duration,
 }
 
 impl Default for ServerConfig {
@@ -64,6 +99,11 @@ fn default() -> Self {
         Self {
             handler: handler::default(),
             binders: Vec::new(),
+            tick: Duration::from_secs(1),
+            #[cfg(feature = "idle")]
+            idle: None,
+            #[cfg(feature = "idle")]
+            idle_interval: Duration::from_secs(5),
         }
     }
 }
@@ -82,8 +122,25 @@ pub enum ServerEvent {
 }
 
 /// Thread safe version of the server state.
-#[derive(Clone, Debug, Default)]
-pub struct ThreadSafeState(Arc<Mutex<ServerState>>);
+///
+/// State changes are broadcast to subscribers, see
+/// [`ThreadSafeState::subscribe`].
+#[derive(Clone, Debug)]
+pub struct ThreadSafeState {
+    state: Arc<Mutex<ServerState>>,
+    #[cfg(feature = "tokio")]
+    sender: Arc<watch::Sender<ServerState>>,
+}
+
+impl Default for ThreadSafeState {
+    fn default() -> Self {
+        Self {
+            state: Default::default(),
+            #[cfg(feature = "tokio")]
+            sender: Arc::new(watch::channel(ServerState::default()).0),
+        }
+    }
+}
 
 impl ThreadSafeState {
     /// Create a new server thread safe state using defaults.
@@ -91,9 +148,23 @@ pub fn new() -> Self {
         Self::default()
     }
 
+    /// Subscribe to the server state changes.
+    #[cfg(feature = "tokio")]
+    pub fn subscribe(&self) -> watch::Receiver<ServerState> {
+        self.sender.subscribe()
+    }
+
     /// Change the inner server state with the given one.
     async fn set(&self, next_state: ServerState) {
         let mut state = self.lock().await;
+
+        #[cfg(feature = "tokio")]
+        self.sender.send_if_modified(|state| {
+            let modified = *state != next_state;
+            *state = next_state.clone();
+            modified
+        });
+
         *state = next_state;
     }
 
@@ -117,13 +188,44 @@ impl Deref for ThreadSafeState {
     type Target = Arc<Mutex<ServerState>>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.state
     }
 }
 
 impl DerefMut for ThreadSafeState {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.state
+    }
+}
+
+/// The server handle.
+///
+/// Cloneable handle used to control a running server from other
+/// tasks, for example when the server is embedded into an async
+/// application.
+#[derive(Clone, Debug)]
+pub struct ServerHandle {
+    state: ThreadSafeState,
+}
+
+impl ServerHandle {
+    /// Get the current server state.
+    pub async fn state(&self) -> ServerState {
+        self.state.lock().await.clone()
+    }
+
+    /// Subscribe to the server state changes.
+    #[cfg(feature = "tokio")]
+    pub fn subscribe(&self) -> watch::Receiver<ServerState> {
+        self.state.subscribe()
+    }
+
+    /// Request the server to shut down gracefully.
+    ///
+    /// The server stops updating the timer, saves it if a store is
+    /// configured, then [`Server::bind_with`] returns.
+    pub async fn shutdown(&self) {
+        self.state.set_stopping().await
     }
 }
 
@@ -143,48 +245,92 @@ pub trait ServerBind: Debug + Send + Sync {
 #[async_trait]
 pub trait ServerStream: RequestReader + ResponseWriter {
     /// Read the request, process it then write the response.
+    ///
+    /// Errors occurring while processing the request are sent back to
+    /// the client as [`Response::Error`].
     async fn handle(&mut self, timer: ThreadSafeTimer) -> Result<()> {
-        let req = self.read().await?;
-        let res = match req {
-            Request::Start => {
-                debug!("starting timer");
-                timer.start().await?;
-                Response::Ok
-            }
-            Request::Get => {
-                debug!("getting timer");
-                let timer = timer.get().await;
-                trace!("{timer:#?}");
-                Response::Timer(timer)
-            }
-            Request::Set(duration) => {
-                debug!("setting timer");
-                timer.set(duration).await?;
-                Response::Ok
-            }
-            Request::Pause => {
-                debug!("pausing timer");
-                timer.pause().await?;
-                Response::Ok
+        let req = match self.read().await {
+            Ok(req) => req,
+            Err(err) if err.kind() == ErrorKind::InvalidData => {
+                self.write(Response::Error(err.to_string())).await?;
+                return Err(err);
             }
-            Request::Resume => {
-                debug!("resuming timer");
-                timer.resume().await?;
-                Response::Ok
-            }
-            Request::Stop => {
-                debug!("stopping timer");
-                timer.stop().await?;
-                Response::Ok
+            Err(err) => return Err(err),
+        };
+
+        let res = match process(timer, req).await {
+            Ok(res) => res,
+            Err(err) => {
+                debug!("cannot process request: {err}");
+                Response::Error(err.to_string())
             }
         };
-        self.write(res).await?;
-        Ok(())
+
+        self.write(res).await
     }
 }
 
 impl<T: RequestReader + ResponseWriter> ServerStream for T {}
 
+/// Process the given request using the given timer.
+async fn process(timer: ThreadSafeTimer, req: Request) -> Result<Response> {
+    let res = match req {
+        Request::Start => {
+            debug!("starting timer");
+            timer.start().await?;
+            Response::Ok
+        }
+        Request::Get => {
+            debug!("getting timer");
+            let timer = timer.get().await;
+            trace!("{timer:#?}");
+            Response::Timer(Box::new(timer))
+        }
+        Request::Set(duration) => {
+            debug!("setting timer");
+            timer.set(duration).await?;
+            Response::Ok
+        }
+        Request::Pause => {
+            debug!("pausing timer");
+            timer.pause().await?;
+            Response::Ok
+        }
+        Request::Resume => {
+            debug!("resuming timer");
+            timer.resume().await?;
+            Response::Ok
+        }
+        Request::Lap => {
+            debug!("recording timer lap");
+            timer.lap().await?;
+            Response::Ok
+        }
+        Request::Skip => {
+            debug!("skipping timer cycle");
+            timer.skip().await?;
+            Response::Ok
+        }
+        Request::Extend(duration) => {
+            debug!("extending timer cycle");
+            timer.extend(duration).await?;
+            Response::Ok
+        }
+        Request::JumpTo(index) => {
+            debug!("jumping to timer cycle {index}");
+            timer.jump_to(index).await?;
+            Response::Ok
+        }
+        Request::Stop => {
+            debug!("stopping timer");
+            timer.stop().await?;
+            Response::Ok
+        }
+    };
+
+    Ok(res)
+}
+
 /// The server struct.
 #[derive(Default)]
 pub struct Server {
@@ -199,10 +345,19 @@ pub struct Server {
 }
 
 impl Server {
-    /// Start the server by running the timer in a dedicated thread as
-    /// well as all the binders in dedicated threads.
+    /// Get a handle to control the server from other tasks.
+    pub fn handle(&self) -> ServerHandle {
+        ServerHandle {
+            state: self.state.clone(),
+        }
+    }
+
+    /// Start the server by running the timer in a dedicated task as
+    /// well as all the binders.
     ///
-    /// The main thread is then blocked by the given `wait` closure.
+    /// The server runs until the given `wait` closure returns or
+    /// until a shutdown is requested via [`ServerHandle::shutdown`],
+    /// then shuts down gracefully.
     pub async fn bind_with<F: Future<Output = Result<()>> + Send + 'static>(
         self,
         wait: impl FnOnce() -> F + Send + Sync + 'static,
@@ -219,60 +374,82 @@ pub async fn bind_with<F: Future<Output = Result<()>> + Send + 'static>(
             }
         };
 
+        // restore the timer saved before the last shutdown, if any
+        #[cfg(feature = "store")]
+        if let Err(err) = self.timer.restore().await {
+            debug!("cannot restore timer, skipping it");
+            debug!("{err:?}");
+        }
+
         self.state.set_running().await;
         fire_event(ServerEvent::Started).await;
 
-        // the tick represents the timer running in a separated thread
+        // the tick represents the timer running in a separated task
         let state = self.state.clone();
         let timer = self.timer.clone();
+        let interval = self.config.tick;
+        #[cfg(feature = "idle")]
+        let idle = self.config.idle;
+        #[cfg(feature = "idle")]
+        let idle_interval = self.config.idle_interval;
         let tick = spawn(async move {
-            loop {
-                let mut state = state.lock().await;
-                match *state {
-                    ServerState::Stopping => {
-                        *state = ServerState::Stopped;
-                        break;
-                    }
-                    ServerState::Stopped => {
-                        break;
-                    }
-                    ServerState::Running => {
-                        timer.update().await;
+            let timer_loop = async {
+                loop {
+                    let state = state.lock().await;
+                    match *state {
+                        ServerState::Stopping | ServerState::Stopped => {
+                            break;
+                        }
+                        ServerState::Running => {
+                            timer.update().await;
+                        }
+                    };
+                    drop(state);
+
+                    sleep(interval).await;
+                }
+            };
+
+            // the idle detection runs next to the timer loop, on its
+            // own interval and outside of the server state lock, so
+            // that a slow detector never delays timer updates
+            #[cfg(feature = "idle")]
+            if let Some(idle) = &idle {
+                let idle_loop = async {
+                    loop {
+                        idle.check(&timer).await;
+                        sleep(idle_interval).await;
                     }
                 };
-                drop(state);
 
-                sleep(Duration::from_secs(1)).await;
+                pin_mut!(timer_loop, idle_loop);
+                future::select(timer_loop, idle_loop).await;
+                return;
             }
-        });
 
-        // start all binders in dedicated threads in order not to
-        // block the main thread
+            timer_loop.await;
+        })
+        .fuse();
+        pin_mut!(tick);
+
+        // run all binders concurrently, so that they are dropped
+        // when the server shuts down
 
         let binders = FuturesUnordered::from_iter(self.config.binders.into_iter().map(|binder| {
             let timer = self.timer.clone();
-            spawn(async move {
+            async move {
                 debug!("binding {binder:?}");
                 if let Err(err) = binder.bind(timer).await {
                     debug!("error while binding, skipping it");
                     debug!("{err:?}");
                 }
-            })
-        }))
-        .filter_map(|res| async {
-            match res {
-                Ok(res) => Some(res),
-                Err(err) => {
-                    debug!(?err, "skipping failed task");
-                    None
-                }
             }
-        })
+        }))
         .collect::<()>();
 
         debug!("main loop started");
         select! {
-            _ = tick.fuse() => (),
+            _ = tick => (),
             _ = binders.fuse() => (),
             _ = wait().fuse() => (),
         };
@@ -281,9 +458,19 @@ pub async fn bind_with<F: Future<Output = Result<()>> + Send + 'static>(
         self.state.set_stopping().await;
         fire_event(ServerEvent::Stopping).await;
 
-        // wait for the timer thread to stop before exiting
-        // tick.await
-        //     .map_err(|_| Error::new(ErrorKind::Other, "cannot wait for timer thread"))?;
+        // wait for the tick to stop, so that the timer is not
+        // updated anymore while shutting down
+        if !tick.is_terminated() {
+            if let Err(err) = tick.await {
+                debug!("cannot wait for timer task, skipping it");
+                debug!("{err:?}");
+            }
+        }
+
+        // save the timer, so that it can be restored on next start
+        self.timer.lock().await.save().await;
+
+        self.state.set_stopped().await;
         fire_event(ServerEvent::Stopped).await;
 
         Ok(())
@@ -337,9 +524,9 @@ pub fn with_timer_config(mut self, config: TimerConfig) -> Self {
     ///
     /// See <https://en.wikipedia.org/wiki/Pomodoro_Technique>.
     pub fn with_pomodoro_config(mut self) -> Self {
-        let work = TimerCycle::new("Work", 25 * 60);
-        let short_break = TimerCycle::new("Short break", 5 * 60);
-        let long_break = TimerCycle::new("Long break", 15 * 60);
+        let work = TimerCycle::new("Work", Duration::from_secs(25 * 60));
+        let short_break = TimerCycle::new("Short break", Duration::from_secs(5 * 60));
+        let long_break = TimerCycle::new("Long break", Duration::from_secs(15 * 60));
 
         *self.timer_config.cycles = vec![
             work.clone(),
@@ -360,13 +547,33 @@ pub fn with_pomodoro_config(mut self) -> Self {
     ///
     /// See <https://en.wikipedia.org/wiki/52/17_rule>.
     pub fn with_52_17_config(mut self) -> Self {
-        let work = TimerCycle::new("Work", 52 * 60);
-        let rest = TimerCycle::new("Rest", 17 * 60);
+        let work = TimerCycle::new("Work", Duration::from_secs(52 * 60));
+        let rest = TimerCycle::new("Rest", Duration::from_secs(17 * 60));
 
         *self.timer_config.cycles = vec![work, rest];
         self
     }
 
+    /// Configure the timer as a stopwatch, which counts up from zero
+    /// without cycles.
+    pub fn with_stopwatch_config(mut self) -> Self {
+        self.timer_config.mode = TimerMode::Stopwatch;
+        self
+    }
+
+    /// Configure the timer to count down once to the given
+    /// wall-clock deadline, without cycles.
+    pub fn with_deadline_config(mut self, deadline: TimerDeadline) -> Self {
+        self.timer_config.mode = TimerMode::Deadline(deadline);
+        self
+    }
+
+    /// Set the timer mode.
+    pub fn with_mode(mut self, mode: TimerMode) -> Self {
+        self.timer_config.mode = mode;
+        self
+    }
+
     /// Set the server handler.
     pub fn with_server_handler<F: Future<Output = Result<()>> + Send + 'static>(
         mut self,
@@ -376,6 +583,56 @@ pub fn with_server_handler<F: Future<Output = Result<()>> + Send + 'static>(
         self
     }
 
+    /// Set the idle detector.
+    ///
+    /// The running timer is paused when the user is idle for longer
+    /// than the given threshold, and resumed on activity.
+    #[cfg(feature = "idle")]
+    pub fn with_idle_detector(
+        mut self,
+        detector: Box<dyn IdleDetect>,
+        threshold: Duration,
+    ) -> Self {
+        self.server_config.idle = Some(IdleConfig::new(detector, threshold));
+        self
+    }
+
+    /// Set the interval between two idle checks.
+    ///
+    /// Defaults to 5 seconds.
+    #[cfg(feature = "idle")]
+    pub fn with_idle_interval(mut self, interval: Duration) -> Self {
+        self.server_config.idle_interval = interval;
+        self
+    }
+
+    /// Set the timer store.
+    ///
+    /// The timer is saved to the store every time it changes, and
+    /// restored from it when the server starts.
+    #[cfg(feature = "store")]
+    pub fn with_store(mut self, store: impl TimerStore + 'static) -> Self {
+        self.timer_config.store = Some(Arc::new(store));
+        self
+    }
+
+    /// Set the timer history.
+    ///
+    /// Every cycle completed by the timer is recorded in the history.
+    #[cfg(feature = "history")]
+    pub fn with_history(mut self, history: impl TimerHistory + 'static) -> Self {
+        self.timer_config.history = Some(Arc::new(history));
+        self
+    }
+
+    /// Set the interval between two timer updates.
+    ///
+    /// Defaults to 1 second.
+    pub fn with_tick(mut self, tick: Duration) -> Self {
+        self.server_config.tick = tick;
+        self
+    }
+
     /// Push the given server binder.
     pub fn with_binder(mut self, binder: Box<dyn ServerBind>) -> Self {
         self.server_config.binders.push(binder);
@@ -445,3 +702,107 @@ pub(crate) async fn spawn<F>(f: F) -> Result<F::Output>
 {
     Ok(tokio::task::spawn(f).await?)
 }
+
+#[cfg(all(feature = "notify", feature = "async-std"))]
+pub(crate) async fn spawn_blocking<F, T>(f: F) -> Result<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    Ok(async_std::task::spawn_blocking(f).await)
+}
+
+#[cfg(all(feature = "notify", feature = "tokio"))]
+pub(crate) async fn spawn_blocking<F, T>(f: F) -> Result<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    Ok(tokio::task::spawn_blocking(f).await?)
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use std::{
+        io::Result,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use futures::future::pending;
+    use tokio::test;
+
+    use super::{ServerBind, ServerBuilder, ServerEvent, ServerState};
+    use crate::timer::ThreadSafeTimer;
+
+    /// Binder that never accepts connections, keeping the server
+    /// running until it is shut down.
+    #[derive(Debug)]
+    struct PendingBinder;
+
+    #[async_trait]
+    impl ServerBind for PendingBinder {
+        async fn bind(&self, _timer: ThreadSafeTimer) -> Result<()> {
+            pending().await
+        }
+    }
+
+    #[test_log::test(test)]
+    async fn graceful_shutdown() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let server = ServerBuilder::new()
+            .with_pomodoro_config()
+            .with_tick(Duration::from_millis(10))
+            .with_binder(Box::new(PendingBinder))
+            .with_server_handler({
+                let events = events.clone();
+                move |event| {
+                    events.lock().unwrap().push(event);
+                    async { Ok(()) }
+                }
+            })
+            .build()
+            .unwrap();
+
+        let handle = server.handle();
+        let mut receiver = handle.subscribe();
+
+        // shut the server down as soon as it runs
+        let states = tokio::spawn(async move {
+            let mut states = Vec::new();
+
+            while receiver.changed().await.is_ok() {
+                let state = receiver.borrow().clone();
+
+                if state == ServerState::Running {
+                    handle.shutdown().await;
+                }
+
+                states.push(state.clone());
+
+                if state == ServerState::Stopped {
+                    break;
+                }
+            }
+
+            states
+        });
+
+        server.bind_with(pending::<Result<()>>).await.unwrap();
+
+        let states = states.await.unwrap();
+        assert_eq!(states.first(), Some(&ServerState::Running));
+        assert_eq!(states.last(), Some(&ServerState::Stopped));
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                ServerEvent::Started,
+                ServerEvent::Stopping,
+                ServerEvent::Stopped,
+            ],
+        );
+    }
+}
@@ -8,24 +8,19 @@
 #[cfg(feature = "async-std")]
 use async_std::net::TcpListener;
 use async_trait::async_trait;
-use futures::{AsyncBufReadExt, AsyncWriteExt};
 #[cfg(feature = "tokio")]
 use tokio::net::TcpListener;
 use tracing::debug;
 
-use crate::{
-    request::{Request, RequestReader},
-    response::{Response, ResponseWriter},
-    tcp::TcpHandler,
-    timer::ThreadSafeTimer,
-};
+use crate::{tcp::TcpHandler, timer::ThreadSafeTimer};
 
 use super::{ServerBind, ServerStream};
 
 /// The TCP server binder.
 ///
 /// This [`ServerBind`]er uses the TCP protocol to bind a listener, to
-/// read requests and write responses.
+/// read requests and write responses using the
+/// [`protocol`](crate::protocol).
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct TcpBind {
     /// The TCP host of the listener.
@@ -69,55 +64,3 @@ async fn bind(&self, timer: ThreadSafeTimer) -> io::Result<()> {
         }
     }
 }
-
-#[async_trait]
-impl RequestReader for TcpHandler {
-    async fn read(&mut self) -> io::Result<Request> {
-        let mut req = String::new();
-        self.reader.read_line(&mut req).await?;
-
-        let mut tokens = req.split_whitespace();
-        match tokens.next() {
-            Some("start") => Ok(Request::Start),
-            Some("get") => Ok(Request::Get),
-            Some("set") => match tokens.next().map(|duration| duration.parse::<usize>()) {
-                Some(Ok(duration)) => Ok(Request::Set(duration)),
-                Some(Err(err)) => Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    format!("invalid duration: {err}"),
-                )),
-                None => Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "missing duration".to_owned(),
-                )),
-            },
-            Some("pause") => Ok(Request::Pause),
-            Some("resume") => Ok(Request::Resume),
-            Some("stop") => Ok(Request::Stop),
-            Some(req) => Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("invalid request: {req}"),
-            )),
-            None => Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "missing request".to_owned(),
-            )),
-        }
-    }
-}
-
-#[async_trait]
-impl ResponseWriter for TcpHandler {
-    async fn write(&mut self, res: Response) -> io::Result<()> {
-        let res = match res {
-            Response::Ok => "ok\n".to_string(),
-            Response::Timer(timer) => {
-                format!("timer {}\n", serde_json::to_string(&timer).unwrap())
-            }
-        };
-
-        self.writer.write_all(res.as_bytes()).await?;
-
-        Ok(())
-    }
-}
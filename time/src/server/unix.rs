@@ -0,0 +1,73 @@
+//! # Unix socket binder
+//!
+//! This module contains the implementation of the Unix socket server
+//! binder, based on [`tokio::net::UnixStream`].
+
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::PathBuf,
+};
+
+#[cfg(feature = "async-std")]
+use async_std::os::unix::net::UnixListener;
+use async_trait::async_trait;
+#[cfg(feature = "tokio")]
+use tokio::net::UnixListener;
+use tracing::debug;
+
+use crate::{timer::ThreadSafeTimer, unix::UnixHandler};
+
+use super::{ServerBind, ServerStream};
+
+/// The Unix socket server binder.
+///
+/// This [`ServerBind`]er listens on a Unix socket, to read requests
+/// and write responses using the [`protocol`](crate::protocol).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UnixBind {
+    /// The path of the Unix socket.
+    pub path: PathBuf,
+}
+
+impl UnixBind {
+    /// Create a new Unix socket binder using the given path.
+    pub fn new(path: impl Into<PathBuf>) -> Box<dyn ServerBind> {
+        Box::new(Self { path: path.into() })
+    }
+}
+
+#[async_trait]
+impl ServerBind for UnixBind {
+    async fn bind(&self, timer: ThreadSafeTimer) -> io::Result<()> {
+        // a socket left behind by a previous server prevents binding
+        match fs::remove_file(&self.path) {
+            Ok(()) => debug!("removed stale Unix socket at {:?}", self.path),
+            Err(err) if err.kind() == ErrorKind::NotFound => (),
+            Err(err) => return Err(err),
+        }
+
+        #[cfg(feature = "async-std")]
+        let listener = UnixListener::bind(&self.path).await?;
+        #[cfg(feature = "tokio")]
+        let listener = UnixListener::bind(&self.path)?;
+
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    debug!("Unix socket connection accepted");
+
+                    let mut handler = UnixHandler::new(stream);
+                    if let Err(err) = handler.handle(timer.clone()).await {
+                        debug!("cannot handle request");
+                        debug!("{err:?}");
+                    }
+                }
+                Err(err) => {
+                    debug!("cannot get stream from client");
+                    debug!("{err:?}");
+                }
+            }
+        }
+    }
+}
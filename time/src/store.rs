@@ -0,0 +1,270 @@
+//! # Store
+//!
+//! This module contains everything related to timer persistence. When
+//! a store is configured, the timer saves a snapshot of itself every
+//! time it changes (start, pause, cycle change etc), and the server
+//! restores it when starting up. This way, a running timer survives
+//! restarts.
+//!
+//! Snapshots contain the wall-clock time they were saved at, so that
+//! the time elapsed while the server was down is taken into account
+//! when restoring a running timer.
+//!
+//! Stores are pluggable via the [`TimerStore`] trait. A JSON file
+//! implementation is available with [`JsonTimerStore`].
+
+use std::{
+    fmt::Debug,
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use async_trait::async_trait;
+use tracing::debug;
+
+use crate::timer::{TimerCycle, TimerLoop, TimerMode, TimerState};
+
+/// The timer snapshot.
+///
+/// Contains everything needed to restore a timer.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TimerSnapshot {
+    /// The timer mode.
+    pub mode: TimerMode,
+
+    /// The timer state.
+    pub state: TimerState,
+
+    /// The timer cycle.
+    pub cycle: TimerCycle,
+
+    /// The timer cycles counter.
+    pub cycles_count: TimerLoop,
+
+    /// The laps recorded by the stopwatch.
+    #[serde(with = "crate::duration::secs_vec")]
+    pub laps: Vec<Duration>,
+
+    /// The time elapsed since the timer started, when the snapshot
+    /// was saved.
+    #[serde(with = "crate::duration::secs")]
+    pub elapsed: Duration,
+
+    /// The total time added to the timer by
+    /// [`Timer::extend`](crate::timer::Timer::extend).
+    #[serde(default, with = "crate::duration::secs")]
+    pub extension: Duration,
+
+    /// The elapsed time at which the extended cycle ends.
+    #[serde(default, with = "crate::duration::secs")]
+    pub extended_until: Duration,
+
+    /// The wall-clock time the deadline timer ends at.
+    #[serde(default)]
+    pub deadline: Option<SystemTime>,
+
+    /// The wall-clock time the snapshot was saved at.
+    pub saved_at: SystemTime,
+}
+
+impl Default for TimerSnapshot {
+    fn default() -> Self {
+        Self {
+            mode: Default::default(),
+            state: Default::default(),
+            cycle: Default::default(),
+            cycles_count: Default::default(),
+            laps: Default::default(),
+            elapsed: Default::default(),
+            extension: Default::default(),
+            extended_until: Default::default(),
+            deadline: Default::default(),
+            saved_at: SystemTime::UNIX_EPOCH,
+        }
+    }
+}
+
+impl TimerSnapshot {
+    /// Compute the time elapsed since the timer started at the given
+    /// wall-clock time.
+    ///
+    /// Only running timers are affected by the time passing between
+    /// the save and the given time.
+    pub fn elapsed_at(&self, now: SystemTime) -> Duration {
+        match self.state {
+            TimerState::Running => {
+                let downtime = now.duration_since(self.saved_at).unwrap_or_default();
+                self.elapsed + downtime
+            }
+            TimerState::Paused | TimerState::Stopped => self.elapsed,
+        }
+    }
+}
+
+/// The timer store trait.
+///
+/// Timer stores must implement this trait.
+#[async_trait]
+pub trait TimerStore: Debug + Send + Sync {
+    /// Load the last saved snapshot, if any.
+    async fn load(&self) -> Result<Option<TimerSnapshot>>;
+
+    /// Save the given snapshot, replacing the previous one.
+    async fn save(&self, snapshot: &TimerSnapshot) -> Result<()>;
+}
+
+/// The JSON timer store.
+///
+/// Saves the snapshot as a JSON file at the given path.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JsonTimerStore {
+    /// The path of the JSON file.
+    pub path: PathBuf,
+}
+
+impl JsonTimerStore {
+    /// Create a new JSON store using the given file path.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl TimerStore for JsonTimerStore {
+    async fn load(&self) -> Result<Option<TimerSnapshot>> {
+        let json = match fs::read(&self.path) {
+            Ok(json) => json,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                debug!("no timer snapshot found at {:?}", self.path);
+                return Ok(None);
+            }
+            Err(err) => return Err(err),
+        };
+
+        let snapshot = serde_json::from_slice(&json).map_err(|err| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("cannot parse timer snapshot: {err}"),
+            )
+        })?;
+
+        Ok(Some(snapshot))
+    }
+
+    async fn save(&self, snapshot: &TimerSnapshot) -> Result<()> {
+        let json = serde_json::to_vec(snapshot).map_err(|err| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("cannot serialize timer snapshot: {err}"),
+            )
+        })?;
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // write then rename, so that a crash while saving does not
+        // corrupt the previous snapshot
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, json)?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    #[cfg(feature = "async-std")]
+    use async_std::test;
+    use tempfile::{tempdir, TempDir};
+    #[cfg(feature = "tokio")]
+    use tokio::test;
+
+    use super::*;
+    use crate::timer::{Timer, TimerConfig};
+
+    fn testing_store(dir: &TempDir) -> JsonTimerStore {
+        JsonTimerStore::new(dir.path().join("timer.json"))
+    }
+
+    #[test_log::test(test)]
+    async fn elapsed_at() {
+        let snapshot = TimerSnapshot {
+            state: TimerState::Running,
+            elapsed: Duration::from_secs(5),
+            saved_at: UNIX_EPOCH + Duration::from_secs(100),
+            ..Default::default()
+        };
+
+        let now = UNIX_EPOCH + Duration::from_millis(160_500);
+        assert_eq!(snapshot.elapsed_at(now), Duration::from_millis(65_500));
+
+        let snapshot = TimerSnapshot {
+            state: TimerState::Paused,
+            ..snapshot
+        };
+        assert_eq!(snapshot.elapsed_at(now), Duration::from_secs(5));
+    }
+
+    #[test_log::test(test)]
+    async fn json_store() {
+        let dir = tempdir().unwrap();
+        let store = testing_store(&dir);
+
+        assert_eq!(store.load().await.unwrap(), None);
+
+        let snapshot = TimerSnapshot {
+            state: TimerState::Running,
+            cycle: TimerCycle::new("Work", Duration::from_secs(42)),
+            elapsed: Duration::from_secs(3),
+            saved_at: UNIX_EPOCH + Duration::from_secs(100),
+            ..Default::default()
+        };
+
+        store.save(&snapshot).await.unwrap();
+        assert_eq!(store.load().await.unwrap(), Some(snapshot));
+    }
+
+    #[test_log::test(test)]
+    async fn restore_timer() {
+        let dir = tempdir().unwrap();
+        let store = testing_store(&dir);
+
+        store
+            .save(&TimerSnapshot {
+                state: TimerState::Paused,
+                cycle: TimerCycle::new("Work", Duration::from_secs(40)),
+                elapsed: Duration::from_secs(20),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let mut timer = Timer {
+            config: TimerConfig {
+                store: Some(Arc::new(store)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        timer.restore().await.unwrap();
+
+        assert_eq!(timer.state, TimerState::Paused);
+        assert_eq!(
+            timer.cycle,
+            TimerCycle::new("Work", Duration::from_secs(40))
+        );
+        assert_eq!(timer.elapsed(), Duration::from_secs(20));
+        assert!(timer.started_at.is_none());
+    }
+}
@@ -6,23 +6,30 @@
 //! timer, timer events are triggered.
 
 #[cfg(feature = "server")]
-use std::io::{Error, ErrorKind};
-
-#[cfg(feature = "server")]
-use futures::lock::Mutex;
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver},
+    lock::Mutex,
+};
 #[cfg(all(feature = "server", test))]
 use mock_instant::Instant;
 #[cfg(all(feature = "server", not(test)))]
 use std::time::Instant;
 use std::{
     fmt,
-    io::Result,
+    io::{Error, ErrorKind, Result},
     ops::{Deref, DerefMut},
     sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tracing::debug;
 
 use crate::handler::{self, Handler};
+#[cfg(feature = "history")]
+use crate::history::{TimerHistory, TimerHistoryEntry};
+#[cfg(feature = "hooks")]
+use crate::hook::TimerHook;
+#[cfg(feature = "store")]
+use crate::store::{TimerSnapshot, TimerStore};
 
 /// The timer loop.
 ///
@@ -57,6 +64,122 @@ fn from(count: usize) -> Self {
     }
 }
 
+/// The timer mode.
+///
+/// A timer either counts down through its cycles, or counts up like
+/// a stopwatch.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum TimerMode {
+    /// The timer counts down the duration of each configured cycle.
+    #[default]
+    Countdown,
+
+    /// The timer counts up from zero, without cycles.
+    ///
+    /// Laps can be recorded while the stopwatch is running.
+    Stopwatch,
+
+    /// The timer counts down once to the given wall-clock deadline,
+    /// without cycles, then stops by itself.
+    ///
+    /// The remaining time is derived from the system clock at every
+    /// tick, so that the deadline is still met after a suspend.
+    Deadline(TimerDeadline),
+}
+
+/// The name of the unique cycle of a stopwatch.
+pub const STOPWATCH_CYCLE_NAME: &str = "Stopwatch";
+
+/// The name of the unique cycle of a deadline timer.
+pub const DEADLINE_CYCLE_NAME: &str = "Deadline";
+
+/// The number of seconds in a day.
+const DAY: i64 = 24 * 60 * 60;
+
+/// The timer deadline.
+///
+/// Defines the wall-clock time targeted by a
+/// [`TimerMode::Deadline`] timer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum TimerDeadline {
+    /// The timer targets the given wall-clock time.
+    At(SystemTime),
+
+    /// The timer targets the next occurrence of the given time of the
+    /// day, shifted by the given UTC offset in seconds.
+    #[cfg_attr(feature = "derive", serde(rename_all = "kebab-case"))]
+    TimeOfDay {
+        /// The time elapsed since midnight.
+        #[cfg_attr(feature = "derive", serde(with = "crate::duration::secs"))]
+        time: Duration,
+
+        /// The UTC offset, in seconds.
+        utc_offset: i64,
+    },
+}
+
+impl TimerDeadline {
+    /// Parse the given time of the day, like `14:30` or `14:30:15`.
+    ///
+    /// For example, `TimerDeadline::parse_time_of_day("14:30", 3600)`
+    /// targets the next 14:30 of a user living in UTC+1.
+    pub fn parse_time_of_day(time: &str, utc_offset: i64) -> Result<Self> {
+        let invalid = || {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid time of the day {time:?}, expected HH:MM or HH:MM:SS"),
+            )
+        };
+
+        let mut units = time.trim().split(':').map(|unit| unit.parse::<u64>());
+
+        let hours = units.next().ok_or_else(invalid)?.map_err(|_| invalid())?;
+        let minutes = units.next().ok_or_else(invalid)?.map_err(|_| invalid())?;
+        let seconds = units.next().unwrap_or(Ok(0)).map_err(|_| invalid())?;
+
+        if units.next().is_some() || hours > 23 || minutes > 59 || seconds > 59 {
+            return Err(invalid());
+        }
+
+        Ok(Self::TimeOfDay {
+            time: Duration::from_secs(hours * 3600 + minutes * 60 + seconds),
+            utc_offset,
+        })
+    }
+
+    /// Compute the wall-clock time targeted by the deadline, from the
+    /// given current time.
+    pub fn resolve(&self, now: SystemTime) -> SystemTime {
+        match self {
+            Self::At(deadline) => *deadline,
+            Self::TimeOfDay { time, utc_offset } => {
+                let now = now
+                    .duration_since(UNIX_EPOCH)
+                    .map(|now| now.as_secs() as i64)
+                    .unwrap_or_default()
+                    + utc_offset;
+
+                let mut deadline = now - now.rem_euclid(DAY) + time.as_secs() as i64;
+                if deadline <= now {
+                    deadline += DAY;
+                }
+
+                UNIX_EPOCH + Duration::from_secs((deadline - utc_offset).max(0) as u64)
+            }
+        }
+    }
+}
+
 /// The timer cycle.
 ///
 /// A cycle is a step in the timer lifetime, represented by a name and
@@ -78,24 +201,76 @@ pub struct TimerCycle {
     /// the total duration of the cycle. *From the timer point of
     /// view*, the duration represents the amount of time remaining
     /// before the cycle ends.
-    pub duration: usize,
+    #[cfg_attr(feature = "derive", serde(with = "crate::duration::secs"))]
+    pub duration: Duration,
+
+    /// The hook executed when the timer begins the cycle.
+    #[cfg(feature = "hooks")]
+    #[cfg_attr(
+        feature = "derive",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub on_begin: Option<TimerHook>,
+
+    /// The hook executed when the timer ends the cycle.
+    #[cfg(feature = "hooks")]
+    #[cfg_attr(
+        feature = "derive",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub on_end: Option<TimerHook>,
 }
 
 impl TimerCycle {
-    pub fn new(name: impl ToString, duration: usize) -> Self {
+    pub fn new(name: impl ToString, duration: Duration) -> Self {
         Self {
             name: name.to_string(),
             duration,
+            ..Default::default()
         }
     }
+
+    /// Set the hook executed when the timer begins the cycle.
+    #[cfg(feature = "hooks")]
+    pub fn with_on_begin(mut self, hook: TimerHook) -> Self {
+        self.on_begin = Some(hook);
+        self
+    }
+
+    /// Set the hook executed when the timer ends the cycle.
+    #[cfg(feature = "hooks")]
+    pub fn with_on_end(mut self, hook: TimerHook) -> Self {
+        self.on_end = Some(hook);
+        self
+    }
 }
 
-impl<T: ToString> From<(T, usize)> for TimerCycle {
-    fn from((name, duration): (T, usize)) -> Self {
+impl<T: ToString> From<(T, Duration)> for TimerCycle {
+    fn from((name, duration): (T, Duration)) -> Self {
         Self::new(name, duration)
     }
 }
 
+/// The stopwatch lap.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct TimerLap {
+    /// The number of the lap, starting from 1.
+    pub number: usize,
+
+    /// The duration of the lap, since the previous one.
+    #[cfg_attr(feature = "derive", serde(with = "crate::duration::secs"))]
+    pub duration: Duration,
+
+    /// The total elapsed time when the lap was recorded.
+    #[cfg_attr(feature = "derive", serde(with = "crate::duration::secs"))]
+    pub elapsed: Duration,
+}
+
 /// The timer cycles list.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(
@@ -176,9 +351,20 @@ pub enum TimerEvent {
     /// The timer has been resumed at the given cycle.
     Resumed(TimerCycle),
 
+    /// The timer has been paused at the given cycle because the user
+    /// went idle.
+    IdlePaused(TimerCycle),
+
+    /// The timer has been resumed at the given cycle because the user
+    /// came back from idle.
+    IdleResumed(TimerCycle),
+
     /// The timer ended with the given cycle.
     Ended(TimerCycle),
 
+    /// The stopwatch recorded the given lap.
+    Lap(TimerLap),
+
     /// The timer stopped.
     Stopped,
 }
@@ -186,6 +372,9 @@ pub enum TimerEvent {
 /// The timer configuration.
 #[derive(Clone)]
 pub struct TimerConfig {
+    /// The timer mode.
+    pub mode: TimerMode,
+
     /// The list of custom timer cycles.
     pub cycles: TimerCycles,
 
@@ -194,23 +383,44 @@ pub struct TimerConfig {
 
     /// The timer event handler.
     pub handler: Arc<Handler<TimerEvent>>,
+
+    /// The timer store, used to persist the timer across restarts.
+    #[cfg(feature = "store")]
+    pub store: Option<Arc<dyn TimerStore>>,
+
+    /// The timer history, used to record completed cycles.
+    #[cfg(feature = "history")]
+    pub history: Option<Arc<dyn TimerHistory>>,
 }
 
 impl fmt::Debug for TimerConfig {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct("TimerConfig")
+        let mut f = f.debug_struct("TimerConfig");
+        f.field("mode", &self.mode)
             .field("cycles", &self.cycles)
-            .field("cycles_count", &self.cycles_count)
-            .finish()
+            .field("cycles_count", &self.cycles_count);
+
+        #[cfg(feature = "store")]
+        f.field("store", &self.store);
+
+        #[cfg(feature = "history")]
+        f.field("history", &self.history);
+
+        f.finish()
     }
 }
 
 impl Default for TimerConfig {
     fn default() -> Self {
         Self {
+            mode: Default::default(),
             cycles: Default::default(),
             cycles_count: Default::default(),
             handler: handler::default(),
+            #[cfg(feature = "store")]
+            store: None,
+            #[cfg(feature = "history")]
+            history: None,
         }
     }
 }
@@ -218,6 +428,16 @@ fn default() -> Self {
 #[cfg(feature = "server")]
 impl TimerConfig {
     fn clone_first_cycle(&self) -> Result<TimerCycle> {
+        match self.mode {
+            TimerMode::Stopwatch => {
+                return Ok(TimerCycle::new(STOPWATCH_CYCLE_NAME, Duration::ZERO));
+            }
+            TimerMode::Deadline(_) => {
+                return Ok(TimerCycle::new(DEADLINE_CYCLE_NAME, Duration::ZERO));
+            }
+            TimerMode::Countdown => (),
+        }
+
         self.cycles.first().cloned().ok_or_else(|| {
             Error::new(
                 ErrorKind::NotFound,
@@ -239,6 +459,9 @@ pub struct Timer {
     #[cfg_attr(feature = "derive", serde(skip))]
     pub config: TimerConfig,
 
+    /// The current timer mode.
+    pub mode: TimerMode,
+
     /// The current timer state.
     pub state: TimerState,
 
@@ -248,12 +471,48 @@ pub struct Timer {
     /// The current cycles counter.
     pub cycles_count: TimerLoop,
 
+    /// The laps recorded by the stopwatch, as total elapsed times.
+    #[cfg_attr(feature = "derive", serde(with = "crate::duration::secs_vec"))]
+    pub laps: Vec<Duration>,
+
     #[cfg(feature = "server")]
     #[cfg_attr(feature = "derive", serde(skip))]
     pub started_at: Option<Instant>,
 
     #[cfg(feature = "server")]
-    pub elapsed: usize,
+    #[cfg_attr(feature = "derive", serde(with = "crate::duration::secs"))]
+    pub elapsed: Duration,
+
+    /// Whether the timer has been paused because the user went idle.
+    #[cfg(feature = "server")]
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub idle: bool,
+
+    /// The total time added to the timer by [`Timer::extend`].
+    ///
+    /// Once the extended cycle ends, the position of the timer in its
+    /// cycles is the elapsed time minus this extension.
+    #[cfg(feature = "server")]
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub extension: Duration,
+
+    /// The elapsed time at which the extended cycle ends.
+    ///
+    /// The current cycle is kept until the elapsed time reaches this
+    /// value, which is zero when the cycle has not been extended.
+    #[cfg(feature = "server")]
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub extended_until: Duration,
+
+    /// The wall-clock time the deadline timer ends at.
+    ///
+    /// Only set while a [`TimerMode::Deadline`] timer is running.
+    #[cfg(feature = "server")]
+    #[cfg_attr(
+        feature = "derive",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub deadline: Option<SystemTime>,
 }
 
 impl Eq for Timer {}
@@ -274,20 +533,41 @@ fn eq(&self, other: &Self) -> bool {
 
 #[cfg(feature = "server")]
 impl Timer {
-    pub fn elapsed(&self) -> usize {
-        self.started_at
-            .map(|i| i.elapsed().as_secs() as usize)
-            .unwrap_or_default()
-            + self.elapsed
+    pub fn elapsed(&self) -> Duration {
+        self.started_at.map(|i| i.elapsed()).unwrap_or_default() + self.elapsed
     }
 
     pub async fn update(&mut self) {
-        let mut elapsed = self.elapsed();
+        let mut elapsed = self.position();
 
         match self.state {
+            TimerState::Running if matches!(self.mode, TimerMode::Deadline(_)) => {
+                let remaining = self.remaining(SystemTime::now());
+                self.cycle.duration = remaining;
+                self.fire_event(TimerEvent::Running(self.cycle.clone()))
+                    .await;
+
+                if remaining.is_zero() {
+                    self.state = TimerState::Stopped;
+                    self.fire_events([TimerEvent::Ended(self.cycle.clone()), TimerEvent::Stopped])
+                        .await;
+                    self.reset();
+                    self.save().await;
+                }
+            }
+            TimerState::Running if self.mode == TimerMode::Stopwatch => {
+                self.cycle.duration = elapsed;
+                self.fire_event(TimerEvent::Running(self.cycle.clone()))
+                    .await;
+            }
+            TimerState::Running if self.elapsed() < self.extended_until => {
+                self.cycle.duration = self.extended_until - self.elapsed();
+                self.fire_event(TimerEvent::Running(self.cycle.clone()))
+                    .await;
+            }
             TimerState::Running => {
                 let (cycles, total_duration) = self.config.cycles.iter().cloned().fold(
-                    (Vec::new(), 0),
+                    (Vec::new(), Duration::ZERO),
                     |(mut cycles, mut sum), mut cycle| {
                         cycle.duration += sum;
                         sum = cycle.duration;
@@ -297,13 +577,17 @@ pub async fn update(&mut self) {
                 );
 
                 if let TimerLoop::Fixed(cycles_count) = self.cycles_count {
-                    if elapsed >= (total_duration * cycles_count) {
+                    if elapsed >= (total_duration * cycles_count as u32) {
                         self.state = TimerState::Stopped;
+                        self.record(&self.cycle.name).await;
+                        self.save().await;
                         return;
                     }
                 }
 
-                elapsed %= total_duration;
+                elapsed = Duration::from_nanos(
+                    (elapsed.as_nanos() % total_duration.as_nanos().max(1)) as u64,
+                );
 
                 let last_cycle = cycles[cycles.len() - 1].clone();
                 let next_cycle = cycles
@@ -322,15 +606,19 @@ pub async fn update(&mut self) {
 
                 if self.cycle.name != next_cycle.name {
                     let mut prev_cycle = self.cycle.clone();
-                    prev_cycle.duration = 0;
+                    prev_cycle.duration = Duration::ZERO;
                     self.fire_events([
                         TimerEvent::Ended(prev_cycle),
                         TimerEvent::Began(next_cycle.clone()),
                     ])
                     .await;
-                }
 
-                self.cycle = next_cycle;
+                    self.record(&self.cycle.name).await;
+                    self.cycle = next_cycle;
+                    self.save().await;
+                } else {
+                    self.cycle = next_cycle;
+                }
             }
             TimerState::Paused => {
                 // nothing to do
@@ -348,6 +636,23 @@ pub async fn fire_event(&self, event: TimerEvent) {
             debug!("cannot fire timer event, skipping it");
             debug!("{err:?}");
         }
+
+        #[cfg(feature = "hooks")]
+        match &event {
+            TimerEvent::Began(cycle) => {
+                if let Some(hook) = cycle.on_begin.as_ref() {
+                    debug!("executing begin hook of cycle {}", cycle.name);
+                    hook.exec(cycle).await;
+                }
+            }
+            TimerEvent::Ended(cycle) => {
+                if let Some(hook) = cycle.on_end.as_ref() {
+                    debug!("executing end hook of cycle {}", cycle.name);
+                    hook.exec(cycle).await;
+                }
+            }
+            _ => (),
+        }
     }
 
     pub async fn fire_events(&self, events: impl IntoIterator<Item = TimerEvent>) {
@@ -359,19 +664,46 @@ pub async fn fire_events(&self, events: impl IntoIterator<Item = TimerEvent>) {
     pub async fn start(&mut self) -> Result<()> {
         if matches!(self.state, TimerState::Stopped) {
             self.state = TimerState::Running;
+            self.mode = self.config.mode.clone();
             self.cycle = self.config.clone_first_cycle()?;
             self.cycles_count = self.config.cycles_count.clone();
+            self.laps.clear();
             self.started_at = Some(Instant::now());
-            self.elapsed = 0;
+            self.elapsed = Duration::ZERO;
+            self.extension = Duration::ZERO;
+            self.extended_until = Duration::ZERO;
+            self.idle = false;
+
+            if let TimerMode::Deadline(deadline) = &self.mode {
+                let now = SystemTime::now();
+                self.deadline = Some(deadline.resolve(now));
+                self.cycle.duration = self.remaining(now);
+            }
+
             self.fire_events([TimerEvent::Started, TimerEvent::Began(self.cycle.clone())])
                 .await;
+            self.save().await;
         }
         Ok(())
     }
 
-    pub async fn set(&mut self, duration: usize) -> Result<()> {
+    pub async fn set(&mut self, duration: Duration) -> Result<()> {
+        // the stopwatch cycle duration is the elapsed time, so it
+        // needs to be adjusted as well
+        if let TimerMode::Stopwatch = self.mode {
+            self.elapsed = duration;
+            self.started_at = self.started_at.map(|_| Instant::now());
+        }
+
+        // the deadline is moved so that the remaining time matches
+        // the given duration
+        if self.deadline.is_some() {
+            self.deadline = Some(SystemTime::now() + duration);
+        }
+
         self.cycle.duration = duration;
         self.fire_event(TimerEvent::Set(self.cycle.clone())).await;
+        self.save().await;
         Ok(())
     }
 
@@ -380,8 +712,10 @@ pub async fn pause(&mut self) -> Result<()> {
             self.state = TimerState::Paused;
             self.elapsed = self.elapsed();
             self.started_at = None;
+            self.freeze_deadline();
             self.fire_event(TimerEvent::Paused(self.cycle.clone()))
                 .await;
+            self.save().await;
         }
         Ok(())
     }
@@ -390,8 +724,75 @@ pub async fn resume(&mut self) -> Result<()> {
         if matches!(self.state, TimerState::Paused) {
             self.state = TimerState::Running;
             self.started_at = Some(Instant::now());
+            self.idle = false;
+            self.thaw_deadline();
             self.fire_event(TimerEvent::Resumed(self.cycle.clone()))
                 .await;
+            self.save().await;
+        }
+        Ok(())
+    }
+
+    /// Record a new stopwatch lap.
+    ///
+    /// Only stopwatches can record laps, either running or paused.
+    pub async fn lap(&mut self) -> Result<()> {
+        if self.mode != TimerMode::Stopwatch {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "cannot record lap: timer is not a stopwatch",
+            ));
+        }
+
+        if matches!(self.state, TimerState::Running | TimerState::Paused) {
+            let elapsed = self.elapsed();
+            let prev_elapsed = self.laps.last().copied().unwrap_or_default();
+            self.laps.push(elapsed);
+
+            let lap = TimerLap {
+                number: self.laps.len(),
+                duration: elapsed.saturating_sub(prev_elapsed),
+                elapsed,
+            };
+
+            self.fire_event(TimerEvent::Lap(lap)).await;
+            self.save().await;
+        }
+
+        Ok(())
+    }
+
+    /// Pause the timer because the user went idle.
+    ///
+    /// Only running timers are paused, so that they can be resumed
+    /// automatically by [`Timer::idle_resume`].
+    pub async fn idle_pause(&mut self) -> Result<()> {
+        if matches!(self.state, TimerState::Running) {
+            self.state = TimerState::Paused;
+            self.elapsed = self.elapsed();
+            self.started_at = None;
+            self.idle = true;
+            self.freeze_deadline();
+            self.fire_event(TimerEvent::IdlePaused(self.cycle.clone()))
+                .await;
+            self.save().await;
+        }
+        Ok(())
+    }
+
+    /// Resume the timer because the user came back from idle.
+    ///
+    /// Only timers paused by [`Timer::idle_pause`] are resumed:
+    /// timers paused on purpose stay paused.
+    pub async fn idle_resume(&mut self) -> Result<()> {
+        if matches!(self.state, TimerState::Paused) && self.idle {
+            self.state = TimerState::Running;
+            self.started_at = Some(Instant::now());
+            self.idle = false;
+            self.thaw_deadline();
+            self.fire_event(TimerEvent::IdleResumed(self.cycle.clone()))
+                .await;
+            self.save().await;
         }
         Ok(())
     }
@@ -402,10 +803,305 @@ pub async fn stop(&mut self) -> Result<()> {
             self.fire_events([TimerEvent::Ended(self.cycle.clone()), TimerEvent::Stopped])
                 .await;
             self.cycle = self.config.clone_first_cycle()?;
-            self.cycles_count = self.config.cycles_count.clone();
-            self.started_at = None;
-            self.elapsed = 0;
+            self.reset();
+            self.save().await;
+        }
+        Ok(())
+    }
+
+    /// End the current cycle immediately, and begin the next one.
+    ///
+    /// Only countdown timers can skip cycles, either running or
+    /// paused. Skipped cycles are not recorded in the history.
+    pub async fn skip(&mut self) -> Result<()> {
+        if matches!(self.state, TimerState::Running | TimerState::Paused) {
+            let (index, loop_start) = self.locate()?;
+
+            if index + 1 < self.config.cycles.len() {
+                self.jump(loop_start, index + 1).await;
+            } else {
+                let loop_duration = self.config.cycles.iter().map(|c| c.duration).sum();
+                self.jump(loop_start + loop_duration, 0).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add the given duration to the current cycle.
+    ///
+    /// Only countdown timers can be extended, either running or
+    /// paused.
+    pub async fn extend(&mut self, duration: Duration) -> Result<()> {
+        if self.mode == TimerMode::Stopwatch {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "cannot extend timer: timer is a stopwatch",
+            ));
+        }
+
+        if matches!(self.state, TimerState::Running | TimerState::Paused)
+            && matches!(self.mode, TimerMode::Deadline(_))
+        {
+            self.deadline = self.deadline.map(|deadline| deadline + duration);
+            self.cycle.duration += duration;
+            self.fire_event(TimerEvent::Set(self.cycle.clone())).await;
+            self.save().await;
+        } else if matches!(self.state, TimerState::Running | TimerState::Paused) {
+            let elapsed = self.elapsed();
+
+            let cycle_end = if elapsed < self.extended_until {
+                self.extended_until
+            } else {
+                let (index, loop_start) = self.locate()?;
+                let offset: Duration = self.config.cycles[..=index]
+                    .iter()
+                    .map(|c| c.duration)
+                    .sum();
+                loop_start + offset + self.extension
+            };
+
+            self.extension += duration;
+            self.extended_until = cycle_end + duration;
+            self.cycle.duration = self.extended_until.saturating_sub(elapsed);
+            self.fire_event(TimerEvent::Set(self.cycle.clone())).await;
+            self.save().await;
+        }
+
+        Ok(())
+    }
+
+    /// End the current cycle immediately, and begin the cycle at the
+    /// given index of the current loop.
+    ///
+    /// Only countdown timers can jump to cycles, either running or
+    /// paused.
+    pub async fn jump_to(&mut self, index: usize) -> Result<()> {
+        if index >= self.config.cycles.len() {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("cannot jump to cycle {index}: cycle not found"),
+            ));
+        }
+
+        if matches!(self.state, TimerState::Running | TimerState::Paused) {
+            let (_, loop_start) = self.locate()?;
+            self.jump(loop_start, index).await;
+        }
+
+        Ok(())
+    }
+
+    /// Compute the position of the timer in its cycles.
+    ///
+    /// The position is the elapsed time minus the time added by
+    /// [`Timer::extend`]. While the extended cycle runs, the position
+    /// stays at the end of this cycle.
+    fn position(&self) -> Duration {
+        let elapsed = self.elapsed();
+
+        if elapsed < self.extended_until {
+            (self.extended_until - self.extension).saturating_sub(Duration::from_nanos(1))
+        } else {
+            elapsed.saturating_sub(self.extension)
+        }
+    }
+
+    /// Find the index of the current cycle, as well as the position
+    /// of the timer at the beginning of the current loop.
+    fn locate(&self) -> Result<(usize, Duration)> {
+        match self.mode {
+            TimerMode::Countdown => (),
+            TimerMode::Stopwatch => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "cannot change cycle: timer is a stopwatch",
+                ));
+            }
+            TimerMode::Deadline(_) => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "cannot change cycle: timer is a deadline",
+                ));
+            }
         }
+
+        if self.config.cycles.is_empty() {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                "cannot change cycle: no cycle configured",
+            ));
+        }
+
+        let position = self.position();
+        let loop_duration: Duration = self.config.cycles.iter().map(|c| c.duration).sum();
+        let loops = position.as_nanos() / loop_duration.as_nanos().max(1);
+        let loop_start = Duration::from_nanos((loop_duration.as_nanos() * loops) as u64);
+
+        let mut cycle_end = loop_start;
+        let index = self
+            .config
+            .cycles
+            .iter()
+            .position(|cycle| {
+                cycle_end += cycle.duration;
+                position < cycle_end
+            })
+            .unwrap_or_default();
+
+        Ok((index, loop_start))
+    }
+
+    /// Move the timer to the beginning of the cycle at the given
+    /// index of the loop starting at the given position.
+    async fn jump(&mut self, loop_start: Duration, index: usize) {
+        let offset: Duration = self.config.cycles[..index].iter().map(|c| c.duration).sum();
+
+        self.elapsed = loop_start + offset;
+        self.extension = Duration::ZERO;
+        self.extended_until = Duration::ZERO;
+        self.started_at = self.started_at.map(|_| Instant::now());
+
+        let mut prev_cycle = self.cycle.clone();
+        prev_cycle.duration = Duration::ZERO;
+        self.cycle = self.config.cycles[index].clone();
+
+        self.fire_events([
+            TimerEvent::Ended(prev_cycle),
+            TimerEvent::Began(self.cycle.clone()),
+        ])
+        .await;
+        self.save().await;
+    }
+
+    /// Compute the time remaining before the deadline, from the given
+    /// current time.
+    fn remaining(&self, now: SystemTime) -> Duration {
+        self.deadline
+            .and_then(|deadline| deadline.duration_since(now).ok())
+            .unwrap_or_default()
+    }
+
+    /// Freeze the remaining time of a deadline timer, so that the
+    /// time spent paused does not count.
+    fn freeze_deadline(&mut self) {
+        if self.deadline.is_some() {
+            self.cycle.duration = self.remaining(SystemTime::now());
+            self.deadline = None;
+        }
+    }
+
+    /// Move the deadline of a paused deadline timer, so that the
+    /// remaining time is the one frozen by
+    /// [`Timer::freeze_deadline`].
+    fn thaw_deadline(&mut self) {
+        if let TimerMode::Deadline(_) = self.mode {
+            self.deadline = Some(SystemTime::now() + self.cycle.duration);
+        }
+    }
+
+    /// Reset the progress of the timer, once stopped.
+    fn reset(&mut self) {
+        self.cycles_count = self.config.cycles_count.clone();
+        self.started_at = None;
+        self.elapsed = Duration::ZERO;
+        self.extension = Duration::ZERO;
+        self.extended_until = Duration::ZERO;
+        self.deadline = None;
+    }
+
+    /// Build a snapshot of the timer, used to persist it.
+    #[cfg(feature = "store")]
+    pub fn snapshot(&self) -> TimerSnapshot {
+        TimerSnapshot {
+            mode: self.mode.clone(),
+            state: self.state.clone(),
+            cycle: self.cycle.clone(),
+            cycles_count: self.cycles_count.clone(),
+            laps: self.laps.clone(),
+            elapsed: self.elapsed(),
+            extension: self.extension,
+            extended_until: self.extended_until,
+            deadline: self.deadline,
+            saved_at: SystemTime::now(),
+        }
+    }
+
+    /// Save the timer using the configured store, if any.
+    ///
+    /// Errors are logged and skipped, so that a failing store never
+    /// prevents the timer from running.
+    pub async fn save(&self) {
+        #[cfg(feature = "store")]
+        if let Some(store) = &self.config.store {
+            debug!("saving timer snapshot");
+            if let Err(err) = store.save(&self.snapshot()).await {
+                debug!("cannot save timer snapshot, skipping it");
+                debug!("{err:?}");
+            }
+        }
+    }
+
+    /// Record the completed cycle matching the given name in the
+    /// configured history, if any.
+    ///
+    /// Errors are logged and skipped, so that a failing history never
+    /// prevents the timer from running.
+    #[cfg_attr(not(feature = "history"), allow(unused_variables))]
+    pub async fn record(&self, cycle: &str) {
+        #[cfg(feature = "history")]
+        if let Some(history) = &self.config.history {
+            let duration = self
+                .config
+                .cycles
+                .iter()
+                .find(|c| c.name == cycle)
+                .map(|c| c.duration)
+                .unwrap_or_default();
+
+            debug!("recording completed cycle {cycle} in history");
+            let entry = TimerHistoryEntry::new(cycle, duration);
+            if let Err(err) = history.record(&entry).await {
+                debug!("cannot record cycle in history, skipping it");
+                debug!("{err:?}");
+            }
+        }
+    }
+
+    /// Restore the timer from the configured store, if any.
+    ///
+    /// The time elapsed while the timer was not running (for example
+    /// while the server was down) is taken into account for running
+    /// timers.
+    #[cfg(feature = "store")]
+    pub async fn restore(&mut self) -> Result<()> {
+        let Some(store) = self.config.store.clone() else {
+            return Ok(());
+        };
+
+        let Some(snapshot) = store.load().await? else {
+            return Ok(());
+        };
+
+        debug!("restoring timer from snapshot {snapshot:?}");
+
+        self.elapsed = snapshot.elapsed_at(SystemTime::now());
+        self.started_at = match snapshot.state {
+            TimerState::Running => Some(Instant::now()),
+            TimerState::Paused | TimerState::Stopped => None,
+        };
+        self.mode = snapshot.mode;
+        self.state = snapshot.state;
+        self.cycle = snapshot.cycle;
+        self.cycles_count = snapshot.cycles_count;
+        self.laps = snapshot.laps;
+        self.extension = snapshot.extension;
+        self.extended_until = snapshot.extended_until;
+        self.deadline = snapshot.deadline;
+        self.idle = false;
+
+        self.update().await;
+
         Ok(())
     }
 }
@@ -425,6 +1121,7 @@ pub fn new(config: TimerConfig) -> Result<Self> {
         let mut timer = Timer::default();
 
         timer.config = config;
+        timer.mode = timer.config.mode.clone();
         timer.cycle = timer.config.clone_first_cycle()?;
         timer.cycles_count = timer.config.cycles_count.clone();
 
@@ -443,7 +1140,28 @@ pub async fn get(&self) -> Timer {
         self.0.lock().await.clone()
     }
 
-    pub async fn set(&self, duration: usize) -> Result<()> {
+    /// Subscribe to the timer events.
+    ///
+    /// The timer event handler is wrapped so that every event fired
+    /// by the timer is also sent to the returned receiver. Events are
+    /// not sent anymore once the receiver is dropped.
+    pub async fn subscribe(&self) -> UnboundedReceiver<TimerEvent> {
+        let (sender, receiver) = mpsc::unbounded();
+
+        let mut timer = self.0.lock().await;
+        let handler = timer.config.handler.clone();
+
+        timer.config.handler = Arc::new(move |event: TimerEvent| {
+            // the receiver may have been dropped, in which case the
+            // event is just not forwarded
+            let _ = sender.unbounded_send(event.clone());
+            handler(event)
+        });
+
+        receiver
+    }
+
+    pub async fn set(&self, duration: Duration) -> Result<()> {
         self.0.lock().await.set(duration).await
     }
 
@@ -455,9 +1173,38 @@ pub async fn resume(&self) -> Result<()> {
         self.0.lock().await.resume().await
     }
 
+    pub async fn lap(&self) -> Result<()> {
+        self.0.lock().await.lap().await
+    }
+
+    pub async fn idle_pause(&self) -> Result<()> {
+        self.0.lock().await.idle_pause().await
+    }
+
+    pub async fn idle_resume(&self) -> Result<()> {
+        self.0.lock().await.idle_resume().await
+    }
+
     pub async fn stop(&self) -> Result<()> {
         self.0.lock().await.stop().await
     }
+
+    pub async fn skip(&self) -> Result<()> {
+        self.0.lock().await.skip().await
+    }
+
+    pub async fn extend(&self, duration: Duration) -> Result<()> {
+        self.0.lock().await.extend(duration).await
+    }
+
+    pub async fn jump_to(&self, index: usize) -> Result<()> {
+        self.0.lock().await.jump_to(index).await
+    }
+
+    #[cfg(feature = "store")]
+    pub async fn restore(&self) -> Result<()> {
+        self.0.lock().await.restore().await
+    }
 }
 
 #[cfg(feature = "server")]
@@ -493,14 +1240,14 @@ fn testing_timer() -> Timer {
         Timer {
             config: TimerConfig {
                 cycles: TimerCycles::from([
-                    TimerCycle::new("a", 3),
-                    TimerCycle::new("b", 2),
-                    TimerCycle::new("c", 1),
+                    TimerCycle::new("a", Duration::from_secs(3)),
+                    TimerCycle::new("b", Duration::from_secs(2)),
+                    TimerCycle::new("c", Duration::from_secs(1)),
                 ]),
                 ..Default::default()
             },
             state: TimerState::Running,
-            cycle: TimerCycle::new("a", 3),
+            cycle: TimerCycle::new("a", Duration::from_secs(3)),
             started_at: Some(Instant::now()),
             ..Default::default()
         }
@@ -511,7 +1258,7 @@ async fn running_infinite_timer() {
         let mut timer = testing_timer();
 
         assert_eq!(timer.state, TimerState::Running);
-        assert_eq!(timer.cycle, TimerCycle::new("a", 3));
+        assert_eq!(timer.cycle, TimerCycle::new("a", Duration::from_secs(3)));
 
         // next ticks: state should still be running, cycle name
         // should be the same and cycle duration should be decremented
@@ -521,7 +1268,7 @@ async fn running_infinite_timer() {
         timer.update().await;
 
         assert_eq!(timer.state, TimerState::Running);
-        assert_eq!(timer.cycle, TimerCycle::new("a", 1));
+        assert_eq!(timer.cycle, TimerCycle::new("a", Duration::from_secs(1)));
 
         // next tick: state should still be running, cycle should
         // switch to the next one
@@ -530,7 +1277,7 @@ async fn running_infinite_timer() {
         timer.update().await;
 
         assert_eq!(timer.state, TimerState::Running);
-        assert_eq!(timer.cycle, TimerCycle::new("b", 2));
+        assert_eq!(timer.cycle, TimerCycle::new("b", Duration::from_secs(2)));
 
         // next ticks: state should still be running, cycle should
         // switch to the next one
@@ -539,7 +1286,7 @@ async fn running_infinite_timer() {
         timer.update().await;
 
         assert_eq!(timer.state, TimerState::Running);
-        assert_eq!(timer.cycle, TimerCycle::new("c", 1));
+        assert_eq!(timer.cycle, TimerCycle::new("c", Duration::from_secs(1)));
 
         // next tick: state should still be running, cycle should
         // switch back to the first one
@@ -548,7 +1295,7 @@ async fn running_infinite_timer() {
         timer.update().await;
 
         assert_eq!(timer.state, TimerState::Running);
-        assert_eq!(timer.cycle, TimerCycle::new("a", 3));
+        assert_eq!(timer.cycle, TimerCycle::new("a", Duration::from_secs(3)));
     }
 
     #[test_log::test(test)]
@@ -577,12 +1324,12 @@ async fn running_timer_events() {
         assert_eq!(
             *EVENTS.lock().await,
             vec![
-                TimerEvent::Running(TimerCycle::new("a", 3)),
-                TimerEvent::Running(TimerCycle::new("a", 2)),
-                TimerEvent::Running(TimerCycle::new("a", 1)),
-                TimerEvent::Ended(TimerCycle::new("a", 0)),
-                TimerEvent::Began(TimerCycle::new("b", 2)),
-                TimerEvent::Running(TimerCycle::new("b", 2)),
+                TimerEvent::Running(TimerCycle::new("a", Duration::from_secs(3))),
+                TimerEvent::Running(TimerCycle::new("a", Duration::from_secs(2))),
+                TimerEvent::Running(TimerCycle::new("a", Duration::from_secs(1))),
+                TimerEvent::Ended(TimerCycle::new("a", Duration::from_secs(0))),
+                TimerEvent::Began(TimerCycle::new("b", Duration::from_secs(2))),
+                TimerEvent::Running(TimerCycle::new("b", Duration::from_secs(2))),
             ]
         );
     }
@@ -623,19 +1370,19 @@ async fn thread_safe_timer() {
             timer.get().await,
             Timer {
                 state: TimerState::Stopped,
-                cycle: TimerCycle::new("a", 3),
+                cycle: TimerCycle::new("a", Duration::from_secs(3)),
                 ..Default::default()
             }
         );
 
         timer.start().await.unwrap();
-        timer.set(21).await.unwrap();
+        timer.set(Duration::from_secs(21)).await.unwrap();
 
         assert_eq!(
             timer.get().await,
             Timer {
                 state: TimerState::Running,
-                cycle: TimerCycle::new("a", 21),
+                cycle: TimerCycle::new("a", Duration::from_secs(21)),
                 ..Default::default()
             }
         );
@@ -644,7 +1391,7 @@ async fn thread_safe_timer() {
             timer.get().await,
             Timer {
                 state: TimerState::Running,
-                cycle: TimerCycle::new("a", 21),
+                cycle: TimerCycle::new("a", Duration::from_secs(21)),
                 ..Default::default()
             }
         );
@@ -655,7 +1402,7 @@ async fn thread_safe_timer() {
             timer.get().await,
             Timer {
                 state: TimerState::Paused,
-                cycle: TimerCycle::new("a", 21),
+                cycle: TimerCycle::new("a", Duration::from_secs(21)),
                 ..Default::default()
             }
         );
@@ -666,7 +1413,7 @@ async fn thread_safe_timer() {
             timer.get().await,
             Timer {
                 state: TimerState::Running,
-                cycle: TimerCycle::new("a", 21),
+                cycle: TimerCycle::new("a", Duration::from_secs(21)),
                 ..Default::default()
             }
         );
@@ -677,7 +1424,7 @@ async fn thread_safe_timer() {
             timer.get().await,
             Timer {
                 state: TimerState::Stopped,
-                cycle: TimerCycle::new("a", 3),
+                cycle: TimerCycle::new("a", Duration::from_secs(3)),
                 ..Default::default()
             }
         );
@@ -686,11 +1433,254 @@ async fn thread_safe_timer() {
             *EVENTS.lock().await,
             vec![
                 TimerEvent::Started,
-                TimerEvent::Began(TimerCycle::new("a", 3)),
-                TimerEvent::Set(TimerCycle::new("a", 21)),
-                TimerEvent::Paused(TimerCycle::new("a", 21)),
-                TimerEvent::Resumed(TimerCycle::new("a", 21)),
-                TimerEvent::Ended(TimerCycle::new("a", 21)),
+                TimerEvent::Began(TimerCycle::new("a", Duration::from_secs(3))),
+                TimerEvent::Set(TimerCycle::new("a", Duration::from_secs(21))),
+                TimerEvent::Paused(TimerCycle::new("a", Duration::from_secs(21))),
+                TimerEvent::Resumed(TimerCycle::new("a", Duration::from_secs(21))),
+                TimerEvent::Ended(TimerCycle::new("a", Duration::from_secs(21))),
+                TimerEvent::Stopped,
+            ]
+        );
+    }
+
+    #[cfg(feature = "server")]
+    #[test_log::test(test)]
+    async fn idle_timer() {
+        static EVENTS: Lazy<Mutex<Vec<TimerEvent>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+        let mut timer = testing_timer();
+
+        timer.config.handler = Arc::new(|evt| {
+            Box::pin(async {
+                EVENTS.lock().await.push(evt);
+                Ok(())
+            })
+        });
+
+        // paused on purpose: should not be resumed on activity
+
+        timer.pause().await.unwrap();
+        timer.idle_resume().await.unwrap();
+        assert_eq!(timer.state, TimerState::Paused);
+
+        // paused because of idle: should be resumed on activity
+
+        timer.resume().await.unwrap();
+        timer.idle_pause().await.unwrap();
+        assert_eq!(timer.state, TimerState::Paused);
+        assert!(timer.idle);
+
+        timer.idle_resume().await.unwrap();
+        assert_eq!(timer.state, TimerState::Running);
+        assert!(!timer.idle);
+
+        assert_eq!(
+            *EVENTS.lock().await,
+            vec![
+                TimerEvent::Paused(TimerCycle::new("a", Duration::from_secs(3))),
+                TimerEvent::Resumed(TimerCycle::new("a", Duration::from_secs(3))),
+                TimerEvent::IdlePaused(TimerCycle::new("a", Duration::from_secs(3))),
+                TimerEvent::IdleResumed(TimerCycle::new("a", Duration::from_secs(3))),
+            ]
+        );
+    }
+
+    #[cfg(feature = "server")]
+    #[test_log::test(test)]
+    async fn skip_extend_jump_timer() {
+        let mut timer = testing_timer();
+
+        timer.skip().await.unwrap();
+        assert_eq!(timer.cycle, TimerCycle::new("b", Duration::from_secs(2)));
+
+        MockClock::advance(Duration::from_secs(1));
+        timer.update().await;
+        assert_eq!(timer.cycle, TimerCycle::new("b", Duration::from_secs(1)));
+
+        // the extended cycle lasts longer than the time already spent
+        // in it
+
+        timer.extend(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(timer.cycle, TimerCycle::new("b", Duration::from_secs(6)));
+
+        MockClock::advance(Duration::from_secs(5));
+        timer.update().await;
+        assert_eq!(timer.cycle, TimerCycle::new("b", Duration::from_secs(1)));
+
+        MockClock::advance(Duration::from_secs(1));
+        timer.update().await;
+        assert_eq!(timer.cycle, TimerCycle::new("c", Duration::from_secs(1)));
+
+        // skipping the last cycle begins the next loop
+
+        timer.skip().await.unwrap();
+        assert_eq!(timer.cycle, TimerCycle::new("a", Duration::from_secs(3)));
+
+        MockClock::advance(Duration::from_secs(1));
+        timer.update().await;
+        assert_eq!(timer.cycle, TimerCycle::new("a", Duration::from_secs(2)));
+
+        timer.jump_to(2).await.unwrap();
+        assert_eq!(timer.cycle, TimerCycle::new("c", Duration::from_secs(1)));
+
+        MockClock::advance(Duration::from_secs(1));
+        timer.update().await;
+        assert_eq!(timer.cycle, TimerCycle::new("a", Duration::from_secs(3)));
+
+        assert!(timer.jump_to(3).await.is_err());
+    }
+
+    #[cfg(feature = "server")]
+    #[test_log::test(test)]
+    async fn stopwatch_timer() {
+        static EVENTS: Lazy<Mutex<Vec<TimerEvent>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+        let mut config = TimerConfig {
+            mode: TimerMode::Stopwatch,
+            ..Default::default()
+        };
+
+        config.handler = Arc::new(|evt| {
+            Box::pin(async {
+                EVENTS.lock().await.push(evt);
+                Ok(())
+            })
+        });
+
+        let timer = ThreadSafeTimer::new(config).unwrap();
+        timer.start().await.unwrap();
+
+        MockClock::advance(Duration::from_secs(3));
+        timer.update().await;
+        timer.lap().await.unwrap();
+
+        MockClock::advance(Duration::from_secs(2));
+        timer.update().await;
+        timer.lap().await.unwrap();
+
+        let stopwatch = timer.get().await;
+        assert_eq!(stopwatch.mode, TimerMode::Stopwatch);
+        assert_eq!(
+            stopwatch.cycle,
+            TimerCycle::new(STOPWATCH_CYCLE_NAME, Duration::from_secs(5))
+        );
+        assert_eq!(
+            stopwatch.laps,
+            vec![Duration::from_secs(3), Duration::from_secs(5)]
+        );
+
+        assert_eq!(
+            *EVENTS.lock().await,
+            vec![
+                TimerEvent::Started,
+                TimerEvent::Began(TimerCycle::new(
+                    STOPWATCH_CYCLE_NAME,
+                    Duration::from_secs(0)
+                )),
+                TimerEvent::Running(TimerCycle::new(
+                    STOPWATCH_CYCLE_NAME,
+                    Duration::from_secs(3)
+                )),
+                TimerEvent::Lap(TimerLap {
+                    number: 1,
+                    duration: Duration::from_secs(3),
+                    elapsed: Duration::from_secs(3),
+                }),
+                TimerEvent::Running(TimerCycle::new(
+                    STOPWATCH_CYCLE_NAME,
+                    Duration::from_secs(5)
+                )),
+                TimerEvent::Lap(TimerLap {
+                    number: 2,
+                    duration: Duration::from_secs(2),
+                    elapsed: Duration::from_secs(5),
+                }),
+            ]
+        );
+
+        // countdown timers cannot record laps
+        assert!(testing_timer().lap().await.is_err());
+    }
+
+    #[test_log::test(test)]
+    async fn deadline_resolve() {
+        let now = UNIX_EPOCH + Duration::from_secs(3 * 86400 + 10 * 3600);
+
+        let deadline = TimerDeadline::parse_time_of_day("14:30", 0).unwrap();
+        assert_eq!(
+            deadline.resolve(now),
+            UNIX_EPOCH + Duration::from_secs(3 * 86400 + 14 * 3600 + 1800)
+        );
+
+        // 08:15 already passed today, so the deadline is tomorrow
+        let deadline = TimerDeadline::parse_time_of_day("08:15:30", 0).unwrap();
+        assert_eq!(
+            deadline.resolve(now),
+            UNIX_EPOCH + Duration::from_secs(4 * 86400 + 8 * 3600 + 900 + 30)
+        );
+
+        // 11:00 in UTC+2 is 09:00 in UTC, which already passed today
+        let deadline = TimerDeadline::parse_time_of_day("11:00", 7200).unwrap();
+        assert_eq!(
+            deadline.resolve(now),
+            UNIX_EPOCH + Duration::from_secs(4 * 86400 + 9 * 3600)
+        );
+
+        assert!(TimerDeadline::parse_time_of_day("24:00", 0).is_err());
+        assert!(TimerDeadline::parse_time_of_day("14", 0).is_err());
+        assert!(TimerDeadline::parse_time_of_day("14:30:00:00", 0).is_err());
+    }
+
+    #[cfg(feature = "server")]
+    #[test_log::test(test)]
+    async fn deadline_timer() {
+        static EVENTS: Lazy<Mutex<Vec<TimerEvent>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+        let deadline = SystemTime::now() + Duration::from_secs(3600);
+        let mut config = TimerConfig {
+            mode: TimerMode::Deadline(TimerDeadline::At(deadline)),
+            ..Default::default()
+        };
+
+        config.handler = Arc::new(|evt| {
+            Box::pin(async {
+                EVENTS.lock().await.push(evt);
+                Ok(())
+            })
+        });
+
+        let timer = ThreadSafeTimer::new(config).unwrap();
+        timer.start().await.unwrap();
+
+        let started = timer.get().await;
+        assert_eq!(started.deadline, Some(deadline));
+        assert_eq!(started.cycle.name, DEADLINE_CYCLE_NAME);
+        assert!(started.cycle.duration <= Duration::from_secs(3600));
+        assert!(started.cycle.duration > Duration::from_secs(3590));
+
+        // changing cycles makes no sense without cycles
+        assert!(timer.skip().await.is_err());
+
+        // pausing freezes the remaining time
+        timer.pause().await.unwrap();
+        assert_eq!(timer.get().await.deadline, None);
+        timer.resume().await.unwrap();
+        assert!(timer.get().await.deadline.is_some());
+
+        // setting the timer moves the deadline
+        timer.set(Duration::ZERO).await.unwrap();
+        timer.update().await;
+
+        let ended = timer.get().await;
+        assert_eq!(ended.state, TimerState::Stopped);
+        assert_eq!(ended.deadline, None);
+
+        let events = EVENTS.lock().await;
+        assert_eq!(events[0], TimerEvent::Started);
+        assert_eq!(
+            events[events.len() - 2..],
+            [
+                TimerEvent::Ended(TimerCycle::new(DEADLINE_CYCLE_NAME, Duration::ZERO)),
                 TimerEvent::Stopped,
             ]
         );
@@ -0,0 +1,33 @@
+//! # Unix socket
+//!
+//! This module contains shared Unix socket code for both server and
+//! client. Requests and responses are exchanged using the
+//! [`protocol`](crate::protocol).
+
+use std::path::PathBuf;
+
+#[cfg(feature = "async-std")]
+pub use async_std::os::unix::net::UnixStream;
+
+#[cfg(feature = "tokio")]
+use crate::protocol::Compat;
+use crate::protocol::StreamHandler;
+
+/// The Unix socket shared configuration between clients and servers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct UnixConfig {
+    /// The path of the Unix socket.
+    pub path: PathBuf,
+}
+
+/// The Unix stream, compatible with the [`futures`] IO traits.
+#[cfg(feature = "tokio")]
+pub type UnixStream = Compat<tokio::net::UnixStream>;
+
+/// The Unix socket protocol handler.
+pub type UnixHandler = StreamHandler<UnixStream>;
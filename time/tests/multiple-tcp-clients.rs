@@ -25,8 +25,8 @@ async fn multiple_tcp_clients() {
             Ok(())
         })
         .with_binder(TcpBind::new(HOST, PORT))
-        .with_cycle(("Work", 3))
-        .with_cycle(("Break", 5))
+        .with_cycle(("Work", Duration::from_secs(3)))
+        .with_cycle(("Break", Duration::from_secs(5)))
         .build()
         .unwrap();
 
@@ -44,7 +44,7 @@ async fn multiple_tcp_clients() {
                 client1.get().await.unwrap(),
                 Timer {
                     state: TimerState::Running,
-                    cycle: TimerCycle::new("Work", 1),
+                    cycle: TimerCycle::new("Work", Duration::from_secs(1)),
                     ..Timer::default()
                 }
             );
@@ -56,8 +56,8 @@ async fn multiple_tcp_clients() {
                 client2.get().await.unwrap(),
                 Timer {
                     state: TimerState::Paused,
-                    cycle: TimerCycle::new("Work", 1),
-                    elapsed: 2,
+                    cycle: TimerCycle::new("Work", Duration::from_secs(1)),
+                    elapsed: Duration::from_secs(2),
                     ..Timer::default()
                 }
             );
@@ -69,8 +69,8 @@ async fn multiple_tcp_clients() {
                 client1.get().await.unwrap(),
                 Timer {
                     state: TimerState::Running,
-                    cycle: TimerCycle::new("Break", 5),
-                    elapsed: 2,
+                    cycle: TimerCycle::new("Break", Duration::from_secs(5)),
+                    elapsed: Duration::from_secs(2),
                     ..Timer::default()
                 }
             );
@@ -81,8 +81,8 @@ async fn multiple_tcp_clients() {
                 client1.get().await.unwrap(),
                 Timer {
                     state: TimerState::Running,
-                    cycle: TimerCycle::new("Break", 3),
-                    elapsed: 2,
+                    cycle: TimerCycle::new("Break", Duration::from_secs(3)),
+                    elapsed: Duration::from_secs(2),
                     ..Timer::default()
                 }
             );
@@ -93,7 +93,7 @@ async fn multiple_tcp_clients() {
                 client2.get().await.unwrap(),
                 Timer {
                     state: TimerState::Stopped,
-                    cycle: TimerCycle::new("Work", 3),
+                    cycle: TimerCycle::new("Work", Duration::from_secs(3)),
                     ..Timer::default()
                 }
             );